@@ -0,0 +1,103 @@
+// `tv add module.new_vpc --source <src> --version <v> --set key=value`:
+// append a new module block, since `tv set` only edits blocks that exist.
+//
+// The block goes at the end of the file, after a blank line, laid out the
+// way `terraform fmt` would (source, version, then the --set arguments in
+// order, `=` aligned). A --set value is written as HCL when it is a number,
+// bool, null, list, object, quoted string, function call or a reference
+// (var., local., module., data., ...), and as a string otherwise, so
+// `name=main` and `cidr=10.0.0.0/16` need no quoting.
+
+use anyhow::{Context, Result, anyhow};
+use hcl_edit::Ident;
+use hcl_edit::expr::Expression;
+use hcl_edit::structure::Body;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::query::{block_matches, quote_segment};
+use crate::{find_tf_file, hcl_quote, parse_scan_query, write_file};
+
+const REFERENCE_ROOTS: &[&str] = &["var", "local", "module", "data", "path", "each", "count", "terraform", "self"];
+
+#[derive(Debug, Clone, Default)]
+pub struct AddOptions {
+    pub source: String,
+    pub version: Option<String>,
+    pub arguments: Vec<(String, String)>,  // --set key=value, in order
+}
+
+pub fn argument_value(value: &str) -> String {
+    // The HCL to write for a --set value
+    let is_hcl = match value.trim().parse::<Expression>() {
+        Ok(Expression::Traversal(traversal)) => {
+            matches!(traversal.expr, Expression::Variable(ref root) if REFERENCE_ROOTS.contains(&root.as_str()))
+        }
+        Ok(
+            Expression::Number(_)
+            | Expression::Bool(_)
+            | Expression::Null(_)
+            | Expression::Array(_)
+            | Expression::Object(_)
+            | Expression::String(_)
+            | Expression::StringTemplate(_)
+            | Expression::FuncCall(_),
+        ) => true,
+        _ => false,
+    };
+    if is_hcl { value.trim().to_string() } else { hcl_quote(value) }
+}
+
+pub fn module_block(label: &str, options: &AddOptions) -> String {
+    let mut lines = vec![("source".to_string(), hcl_quote(&options.source))];
+    if let Some(ref version) = options.version {
+        lines.push(("version".to_string(), hcl_quote(version)));
+    }
+    lines.extend(options.arguments.iter().map(|(key, value)| (key.clone(), argument_value(value))));
+
+    let width = lines.iter().map(|(key, _)| key.len()).max().unwrap_or(0);
+    let mut block = format!("module {} {{\n", hcl_quote(label));
+    for (key, value) in lines {
+        block.push_str(&format!("  {:<width$} = {}\n", key, value, width = width));
+    }
+    block + "}\n"
+}
+
+pub fn add_module(query: &str, file: Option<&Path>, options: &AddOptions) -> Result<PathBuf> {
+    let parsed_query = parse_scan_query(query)?;
+    let label = match parsed_query.block_label {
+        Some(ref label) if parsed_query.block_type == "module" && parsed_query.attribute.is_none() && label != "*" => {
+            label.clone()
+        }
+        _ => return Err(anyhow!("Query must name a new module, e.g. module.new_vpc: {}", query)),
+    };
+    for (key, _) in &options.arguments {
+        if key == "source" || key == "version" || Ident::try_new(key.as_str()).is_err() {
+            return Err(anyhow!("Invalid argument name '{}'", key));
+        }
+    }
+
+    // A file that does not exist yet is created
+    let file_path = match file {
+        Some(path) if !path.exists() => path.to_path_buf(),
+        _ => find_tf_file(file)?,
+    };
+    let content = fs::read_to_string(&file_path).unwrap_or_default();
+    let body: Body = content
+        .parse()
+        .with_context(|| format!("Failed to parse HCL: {:?}", file_path))?;
+    if body.iter().filter_map(|s| s.as_block()).any(|b| block_matches(b, &parsed_query)) {
+        return Err(anyhow!("module.{} already exists in {}", quote_segment(&label), crate::paths::show(&file_path)));
+    }
+
+    let block = module_block(&label, options);
+    let updated = match content.trim_end() {
+        "" => block,
+        existing => format!("{}\n\n{}", existing, block),
+    };
+    updated
+        .parse::<Body>()
+        .with_context(|| format!("Not valid HCL once added:\n{}", updated))?;
+    write_file(&file_path, &updated)?;
+    Ok(file_path)
+}
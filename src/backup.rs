@@ -0,0 +1,52 @@
+// `tv set --backup[=SUFFIX]`: keep each file as it was before the
+// invocation first rewrote it, next to it with the suffix added (main.tf.bak
+// by default). lib::write_file saves the copy before every write; a file
+// written twice keeps its original content, and a file that did not exist
+// gets no backup. An existing backup is replaced.
+
+use anyhow::{Context, Result, anyhow};
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_SUFFIX: &str = ".bak";
+
+thread_local! {
+    static SUFFIX: RefCell<Option<String>> = const { RefCell::new(None) };
+    static SAVED: RefCell<HashSet<PathBuf>> = RefCell::new(HashSet::new());
+}
+
+pub fn set_suffix(suffix: Option<String>) {
+    SUFFIX.with(|s| *s.borrow_mut() = suffix);
+    SAVED.with(|s| s.borrow_mut().clear());
+}
+
+pub fn parse_suffix(suffix: &str) -> Result<String> {
+    // --backup=SUFFIX; empty, the backup would be the file itself, and with
+    // a separator or .. it would land in another directory
+    if suffix.is_empty() {
+        return Err(anyhow!("the suffix must not be empty"));
+    }
+    if suffix.contains(['/', '\\']) || suffix.contains("..") {
+        return Err(anyhow!("the suffix {:?} must not contain / \\ or ..", suffix));
+    }
+    Ok(suffix.to_string())
+}
+
+pub fn backup_path(file: &Path, suffix: &str) -> PathBuf {
+    let name = file.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+    file.with_file_name(format!("{}{}", name, suffix))
+}
+
+pub fn save(file: &Path, before: Option<&str>) -> Result<Option<PathBuf>> {
+    // The backup written, if backups are on and this is the file's first write
+    let (Some(suffix), Some(before)) = (SUFFIX.with(|s| s.borrow().clone()), before) else {
+        return Ok(None);
+    };
+    if !SAVED.with(|s| s.borrow_mut().insert(file.to_path_buf())) {
+        return Ok(None);
+    }
+    let backup = backup_path(file, &suffix);
+    crate::replace_file(&backup, before).with_context(|| format!("Failed to write backup: {:?}", backup))?;
+    Ok(Some(backup))
+}
@@ -0,0 +1,103 @@
+// `tv bench --files 10000 --modules-per-file 5`: throughput of the file
+// walker, scan, get and set over a generated corpus, so parser and walker
+// regressions show up as numbers from one release to the next.
+//
+// The corpus is written to a temporary directory (removed afterwards) or to
+// --dir (kept). Files are spread over directories of 100, and every module
+// has a git or registry source, a version and a few arguments. Edits made
+// by the benchmark are not recorded in the undo journal.
+
+use anyhow::{Result, anyhow};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::{find_all_tf_files, get_value, journal, scan_files, set_value};
+
+const FILES_PER_DIR: usize = 100;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchResult {
+    pub name: String,
+    pub operations: usize,  // Files walked, blocks matched, values read or written
+    pub elapsed: Duration,
+}
+
+impl BenchResult {
+    pub fn per_second(&self) -> f64 {
+        self.operations as f64 / self.elapsed.as_secs_f64().max(1e-9)
+    }
+}
+
+fn module_block(file: usize, module: usize) -> String {
+    if (file + module).is_multiple_of(2) {
+        format!(
+            "module \"m{module}\" {{\n  source = \"git::https://github.com/org/module-{module}.git?ref=v1.{file}.0\"\n  name   = \"file-{file}-module-{module}\"\n  tags   = {{ Team = \"platform\", Index = \"{module}\" }}\n}}\n"
+        )
+    } else {
+        format!(
+            "module \"m{module}\" {{\n  source  = \"terraform-aws-modules/vpc/aws\"\n  version = \"5.{module}.0\"\n  name    = \"file-{file}-module-{module}\"\n  cidr    = \"10.{}.0.0/16\"\n  azs     = [\"eu-west-1a\", \"eu-west-1b\"]\n}}\n",
+            file % 256
+        )
+    }
+}
+
+pub fn generate_corpus(dir: &Path, files: usize, modules_per_file: usize) -> Result<Vec<PathBuf>> {
+    let mut written = Vec::with_capacity(files);
+    for file in 0..files {
+        let subdir = dir.join(format!("d{:04}", file / FILES_PER_DIR));
+        fs::create_dir_all(&subdir)?;
+        let path = subdir.join(format!("f{:06}.tf", file));
+        let content: Vec<String> = (0..modules_per_file).map(|module| module_block(file, module)).collect();
+        fs::write(&path, content.join("\n"))?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+fn measure(name: &str, run: impl FnOnce() -> Result<usize>) -> Result<BenchResult> {
+    let started = Instant::now();
+    let operations = run()?;
+    Ok(BenchResult { name: name.to_string(), operations, elapsed: started.elapsed() })
+}
+
+fn run_all(dir: &Path, files: &[PathBuf]) -> Result<Vec<BenchResult>> {
+    Ok(vec![
+        measure("walk", || Ok(find_all_tf_files(dir)?.len()))?,
+        measure("scan", || Ok(scan_files("module.*", dir)?.len()))?,
+        measure("get", || {
+            for file in files {
+                get_value("module.m0.source", Some(file))?
+                    .ok_or_else(|| anyhow!("module.m0.source missing in {:?}", file))?;
+            }
+            Ok(files.len())
+        })?,
+        measure("set", || {
+            for file in files {
+                set_value("module.m0.name", "benchmarked", Some(file))?;
+            }
+            Ok(files.len())
+        })?,
+    ])
+}
+
+pub fn bench(files: usize, modules_per_file: usize, dir: Option<&Path>) -> Result<Vec<BenchResult>> {
+    if files == 0 || modules_per_file == 0 {
+        return Err(anyhow!("--files and --modules-per-file must be at least 1"));
+    }
+    let (dir, temporary) = match dir {
+        Some(dir) => (dir.to_path_buf(), false),
+        None => (std::env::temp_dir().join(format!("tv-bench-{}", std::process::id())), true),
+    };
+    let corpus = generate_corpus(&dir, files, modules_per_file);
+
+    let txn = journal::deactivate();
+    let results = corpus.and_then(|corpus| run_all(&dir, &corpus));
+    if let Some(txn) = txn {
+        journal::activate(txn);
+    }
+    if temporary {
+        let _ = fs::remove_dir_all(&dir);
+    }
+    results
+}
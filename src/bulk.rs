@@ -0,0 +1,71 @@
+// `tv set QUERY VALUE --dir PATH`: apply one set to every match of a scan
+// query under a directory, e.g. `tv set 'module.*.source["ref"]' v2.0.0
+// --dir .` bumps the ref of every module that has one.
+//
+// The query is a scan query, so `*` labels match any block, but it has to
+// address an attribute (or a key of one) and an index, not a filter:
+// blocks that do not have it are left alone, as scan would not report
+// them. Each match is set through its concrete query, so indirection and
+// the --type and --heredoc options apply as they do for one file; files
+// whose content does not change are not reported. With --if-current, the
+// matches whose value is not the expected one are skipped.
+
+use anyhow::{Result, anyhow};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::matches::attribute_path;
+use crate::policy::Expectation;
+use crate::query::block_prefix;
+use crate::{SetOptions, get_typed_value_resolved, parse_scan_query, scan_files, set_value_with_options};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Changed {
+    pub file: PathBuf,
+    pub queries: Vec<String>,  // The concrete queries set, in file order
+}
+
+pub fn set_all(
+    query: &str,
+    value: &str,
+    dir: &Path,
+    options: &SetOptions,
+    if_current: Option<&Expectation>,
+) -> Result<Vec<Changed>> {
+    let parsed = parse_scan_query(query)?;
+    if parsed.filter.is_some() || parsed.negated {
+        return Err(anyhow!("--dir sets what the query addresses; use an index such as [\"ref\"], not a filter"));
+    }
+    let path = attribute_path(&parsed).ok_or_else(|| anyhow!("--dir needs a query that addresses an attribute"))?;
+
+    let mut changed: Vec<Changed> = Vec::new();
+    for (file, name) in scan_files(query, dir)? {
+        let target = format!("{}.{}", block_prefix(&parsed.block_type, &name), path);
+        // Only a value other than the expected one skips a match; failing to
+        // read it is an error, as it is for one file
+        if let Some(expected) = if_current {
+            let current = get_typed_value_resolved(&target, Some(&file))?;
+            if expected.check(current.as_ref().map(|c| c.value.as_str())).is_err() {
+                continue;
+            }
+        }
+        let before = fs::read_to_string(&file).ok();
+        set_value_with_options(&target, value, Some(&file), options)?;
+        if fs::read_to_string(&file).ok() == before {
+            continue;
+        }
+        match changed.last_mut() {
+            Some(last) if last.file == file => last.queries.push(target),
+            _ => changed.push(Changed { file, queries: vec![target] }),
+        }
+    }
+    Ok(changed)
+}
+
+pub fn render(changed: &[Changed], show: impl Fn(&Path) -> String) -> String {
+    changed
+        .iter()
+        .flat_map(|c| c.queries.iter().map(move |query| (&c.file, query)))
+        .map(|(file, query)| format!("{}: {}\n", show(file), query))
+        .collect()
+}
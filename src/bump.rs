@@ -0,0 +1,66 @@
+// `tv bump`: increment one component of a semver value in place, e.g.
+// `tv bump module.vpc.version minor` or `tv bump 'module.app.source["ref"]'
+// patch` for a git ref.
+//
+// The value must be MAJOR.MINOR.PATCH with an optional `v` prefix, which is
+// kept. Bumping resets the components after the bumped one and drops any
+// pre-release or build suffix: 1.4.2-rc1 bumps to 1.4.3, 2.0.0 and 1.5.0.
+// Constraints (`~> 5.0`) are not versions and are refused.
+
+use anyhow::{Result, anyhow};
+use std::path::Path;
+
+use crate::{SetOptions, ValueKind, get_typed_value_resolved, set_value_with_options};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Part {
+    Major,
+    Minor,
+    Patch,
+}
+
+impl std::str::FromStr for Part {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "major" => Ok(Part::Major),
+            "minor" => Ok(Part::Minor),
+            "patch" => Ok(Part::Patch),
+            _ => Err(anyhow!("Unknown part '{}' (expected major, minor or patch)", s)),
+        }
+    }
+}
+
+pub fn bump_version(version: &str, part: Part) -> Result<String> {
+    let (prefix, rest) = match version.strip_prefix('v') {
+        Some(rest) => ("v", rest),
+        None => ("", version),
+    };
+    let core = rest.split(['-', '+']).next().unwrap_or_default();
+    let numbers: Vec<u64> = core
+        .split('.')
+        .map(|s| s.parse::<u64>())
+        .collect::<std::result::Result<_, _>>()
+        .ok()
+        .filter(|n: &Vec<u64>| n.len() == 3)
+        .ok_or_else(|| anyhow!("{} is not a semver version (MAJOR.MINOR.PATCH)", version))?;
+    let (major, minor, patch) = (numbers[0], numbers[1], numbers[2]);
+    let (major, minor, patch) = match part {
+        Part::Major => (major + 1, 0, 0),
+        Part::Minor => (major, minor + 1, 0),
+        Part::Patch => (major, minor, patch + 1),
+    };
+    Ok(format!("{}{}.{}.{}", prefix, major, minor, patch))
+}
+
+pub fn bump(query: &str, part: Part, file: Option<&Path>) -> Result<(String, String)> {
+    // (old, new); a value held in a local or variable is bumped there
+    let current = get_typed_value_resolved(query, file)?.ok_or_else(|| anyhow!("{} not found", query))?;
+    if current.kind != ValueKind::String {
+        return Err(anyhow!("{} is not a string (found {})", query, current.kind.as_str()));
+    }
+    let bumped = bump_version(&current.value, part)?;
+    set_value_with_options(query, &bumped, file, &SetOptions::default())?;
+    Ok((current.value, bumped))
+}
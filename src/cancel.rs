@@ -0,0 +1,162 @@
+// Cancellation of long runs: Ctrl-C and `--timeout`.
+//
+// Both set one process-wide flag that the work checks between steps (before
+// reading a .tf file, before a network request, before writing a file), so
+// a cancelled run stops with an error rather than partway through an edit.
+// Files are replaced by rename (see write_file), so none is ever left
+// half-written either way. A second Ctrl-C, or a run still blocked (e.g. on
+// a hung network filesystem) a few seconds past its deadline, exits at once.
+//
+// Network requests give up after --request-timeout seconds, or sooner when
+// the deadline is closer. So do git commands, which are ended once their time
+// is up (see output).
+
+use anyhow::{Result, anyhow};
+use std::io::{self, Read};
+use std::process::{Command, Output, Stdio};
+use std::sync::Mutex;
+use std::thread::JoinHandle;
+use std::sync::atomic::{AtomicU8, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+pub const EXIT_TIMED_OUT: i32 = 124;  // Like timeout(1)
+pub const EXIT_INTERRUPTED: i32 = 130;  // 128 + SIGINT
+const GRACE: Duration = Duration::from_secs(3);
+
+const RUNNING: u8 = 0;
+const INTERRUPTED: u8 = 1;
+const TIMED_OUT: u8 = 2;
+
+static STATE: AtomicU8 = AtomicU8::new(RUNNING);
+static DEADLINE: Mutex<Option<(Instant, Duration)>> = Mutex::new(None);
+static REQUEST_TIMEOUT_MS: AtomicU64 = AtomicU64::new(30_000);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Reason {
+    Interrupted,
+    TimedOut,
+}
+
+impl Reason {
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Reason::Interrupted => EXIT_INTERRUPTED,
+            Reason::TimedOut => EXIT_TIMED_OUT,
+        }
+    }
+}
+
+pub fn cancel(reason: Reason) {
+    let state = match reason {
+        Reason::Interrupted => INTERRUPTED,
+        Reason::TimedOut => TIMED_OUT,
+    };
+    // The first reason sticks
+    let _ = STATE.compare_exchange(RUNNING, state, Ordering::SeqCst, Ordering::SeqCst);
+}
+
+fn deadline() -> Option<(Instant, Duration)> {
+    *DEADLINE.lock().unwrap()
+}
+
+pub fn cancelled() -> Option<Reason> {
+    if let Some((at, _)) = deadline()
+        && Instant::now() >= at
+    {
+        cancel(Reason::TimedOut);
+    }
+    match STATE.load(Ordering::SeqCst) {
+        INTERRUPTED => Some(Reason::Interrupted),
+        TIMED_OUT => Some(Reason::TimedOut),
+        _ => None,
+    }
+}
+
+pub fn check() -> Result<()> {
+    match cancelled() {
+        None => Ok(()),
+        Some(Reason::Interrupted) => Err(anyhow!("Interrupted; stopped before the next step")),
+        Some(Reason::TimedOut) => {
+            let timeout = deadline().map(|(_, t)| t.as_secs()).unwrap_or_default();
+            Err(anyhow!("Timed out after {}s (--timeout); stopped before the next step", timeout))
+        }
+    }
+}
+
+pub fn set_timeout(timeout: Duration) {
+    *DEADLINE.lock().unwrap() = Some((Instant::now() + timeout, timeout));
+}
+
+pub fn set_request_timeout(timeout: Duration) {
+    REQUEST_TIMEOUT_MS.store(timeout.as_millis() as u64, Ordering::SeqCst);
+}
+
+pub fn request_timeout() -> Duration {
+    let configured = Duration::from_millis(REQUEST_TIMEOUT_MS.load(Ordering::SeqCst));
+    match deadline() {
+        Some((at, _)) => configured.min(at.saturating_duration_since(Instant::now())),
+        None => configured,
+    }
+}
+
+pub fn output(command: &mut Command) -> io::Result<Output> {
+    // Command::output, killing the child after request_timeout()
+    let timeout = request_timeout();
+    let mut child = command.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+    // Drained meanwhile, so a chatty child never blocks on a full pipe
+    let stdout = child.stdout.take().map(drain);
+    let stderr = child.stderr.take().map(drain);
+
+    let started = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if started.elapsed() >= timeout {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!("gave up after {}s (--request-timeout)", timeout.as_secs()),
+            ));
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    };
+    let collect = |reader: Option<JoinHandle<io::Result<Vec<u8>>>>| match reader {
+        Some(reader) => reader.join().unwrap_or_else(|_| Err(io::Error::other("pipe reader panicked"))),
+        None => Ok(Vec::new()),
+    };
+    Ok(Output { status, stdout: collect(stdout)?, stderr: collect(stderr)? })
+}
+
+fn drain(mut pipe: impl Read + Send + 'static) -> JoinHandle<io::Result<Vec<u8>>> {
+    std::thread::spawn(move || {
+        let mut buffer = Vec::new();
+        pipe.read_to_end(&mut buffer)?;
+        Ok(buffer)
+    })
+}
+
+pub fn install_handler() -> Result<()> {
+    ctrlc::set_handler(|| {
+        if cancelled().is_some() {
+            eprintln!("tv: interrupted");
+            std::process::exit(EXIT_INTERRUPTED);
+        }
+        cancel(Reason::Interrupted);
+        eprintln!("tv: stopping after the current step (Ctrl-C again to quit now)");
+    })
+    .map_err(|e| anyhow!("Failed to install the Ctrl-C handler: {}", e))
+}
+
+pub fn spawn_watchdog() {
+    // Ends a run that does not reach a check in time, e.g. blocked on I/O
+    let Some((at, timeout)) = deadline() else {
+        return;
+    };
+    std::thread::spawn(move || {
+        std::thread::sleep(at.saturating_duration_since(Instant::now()) + GRACE);
+        eprintln!("tv: timed out after {}s", timeout.as_secs());
+        std::process::exit(EXIT_TIMED_OUT);
+    });
+}
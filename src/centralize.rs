@@ -0,0 +1,351 @@
+// Central version table for module pins.
+//
+// `tv centralize` moves literal module versions (a git source's `ref` or a
+// registry module's `version`) of one directory into a single map and
+// rewrites each module to read its entry:
+//
+//   locals {
+//     module_versions = {
+//       vpc = "v5.0.0"
+//     }
+//   }
+//   module "vpc" {
+//     source = "git::https://github.com/org/vpc.git?ref=${local.module_versions["vpc"]}"
+//   }
+//
+// With a .tfvars output the map is a variable instead (declared in
+// variables.tf) and modules read `var.module_versions["vpc"]`. `tv inline`
+// is the reverse: references are replaced by the literal values and the
+// table is removed once nothing refers to it.
+//
+// Terraform itself only accepts literal module sources and versions; the
+// rewritten form needs a tool that evaluates them early (OpenTofu >= 1.8).
+
+use anyhow::{Context, Result, anyhow};
+use hcl_edit::expr::Expression;
+use hcl_edit::structure::Body;
+use hcl_edit::{Decorate, Ident};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::map::{key_name, set_entry};
+use crate::source::ModuleSource;
+use crate::{find_all_tf_files, read_tf_body, write_tf_body};
+
+pub const DEFAULT_TABLE_NAME: &str = "module_versions";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Moved {
+    pub module: String,
+    pub file: PathBuf,
+    pub version: String,
+}
+
+fn is_tfvars(path: &Path) -> bool {
+    path.extension().and_then(|e| e.to_str()) == Some("tfvars")
+}
+
+fn reference(name: &str, key: &str, variable: bool) -> String {
+    let scope = if variable { "var" } else { "local" };
+    format!("{}.{}[\"{}\"]", scope, name, key)
+}
+
+fn object_key(key: &str) -> String {
+    // Keys that are valid identifiers stay unquoted, like hand-written HCL
+    match Ident::try_new(key) {
+        Ok(_) => key.to_string(),
+        Err(_) => format!("\"{}\"", key),
+    }
+}
+
+fn table_text(name: &str, table: &BTreeMap<String, String>, indent: &str) -> String {
+    let mut text = format!("{}{} = {{\n", indent, name);
+    for (key, version) in table {
+        text.push_str(&format!("{}  {} = \"{}\"\n", indent, object_key(key), version));
+    }
+    text.push_str(&format!("{}}}\n", indent));
+    text
+}
+
+fn replace_value(slot: &mut Expression, text: &str) -> Result<()> {
+    let mut value: Expression = text
+        .parse()
+        .with_context(|| format!("Failed to parse expression: {}", text))?;
+    *value.decor_mut() = slot.decor().clone();
+    *slot = value;
+    Ok(())
+}
+
+fn local_tf_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    // Locals and variables are scoped to one directory (one module)
+    Ok(find_all_tf_files(dir)?
+        .into_iter()
+        .filter(|p| p.parent() == Some(dir))
+        .collect())
+}
+
+pub fn centralize(dir: &Path, out: &Path, name: &str) -> Result<Vec<Moved>> {
+    let variable = is_tfvars(out);
+    let mut moved = Vec::new();
+    let mut table = BTreeMap::new();
+
+    for file in local_tf_files(dir)? {
+        let mut body = read_tf_body(&file)?;
+        let mut changed = false;
+
+        for block in body.get_blocks_mut("module") {
+            let Some(label) = block.labels.first().map(|l| l.as_str().to_string()) else {
+                continue;
+            };
+            let target = reference(name, &label, variable);
+
+            // A literal git ref in the source
+            if let Some(mut source) = block.body.get_attribute_mut("source")
+                && let Expression::String(ref literal) = *source.value_mut()
+            {
+                let mut parsed = ModuleSource::parse(literal.value());
+                if let Some(git_ref) = parsed.git_ref().map(str::to_string) {
+                    parsed.set_param("ref", &format!("${{{}}}", target));
+                    // Quotes inside the ${...} interpolation are not escaped
+                    let text = format!("\"{}\"", parsed);
+                    replace_value(source.value_mut(), &text)?;
+                    table.insert(label.clone(), git_ref.clone());
+                    moved.push(Moved { module: label, file: file.clone(), version: git_ref });
+                    changed = true;
+                    continue;
+                }
+            }
+
+            // A literal registry version
+            if let Some(mut version) = block.body.get_attribute_mut("version")
+                && let Expression::String(ref literal) = *version.value_mut()
+            {
+                let literal = literal.value().to_string();
+                replace_value(version.value_mut(), &target)?;
+                table.insert(label.clone(), literal.clone());
+                moved.push(Moved { module: label, file: file.clone(), version: literal });
+                changed = true;
+            }
+        }
+
+        if changed {
+            write_tf_body(&file, &body)?;
+        }
+    }
+
+    if table.is_empty() {
+        return Ok(moved);
+    }
+    write_table(out, name, &table, variable)?;
+    if variable {
+        declare_variable(dir, name)?;
+    }
+    Ok(moved)
+}
+
+fn write_table(out: &Path, name: &str, table: &BTreeMap<String, String>, variable: bool) -> Result<()> {
+    let mut body = if out.is_file() { read_tf_body(out)? } else { Body::new() };
+
+    // Merge into an existing table, wherever it is declared in the file
+    let existing = if variable {
+        body.get_attribute_mut(name)
+    } else {
+        body.get_blocks_mut("locals")
+            .find_map(|locals| locals.body.get_attribute_mut(name))
+    };
+    if let Some(mut attr) = existing {
+        let Expression::Object(ref mut object) = *attr.value_mut() else {
+            return Err(anyhow!("'{}' in {:?} is not a map", name, out));
+        };
+        for (key, version) in table {
+            set_entry(object, key, Expression::from(version.as_str()));
+        }
+        return write_tf_body(out, &body);
+    }
+
+    let mut content = body.to_string();
+    if !content.is_empty() && !content.ends_with("\n\n") {
+        content.push_str(if content.ends_with('\n') { "\n" } else { "\n\n" });
+    }
+    if variable {
+        content.push_str(&table_text(name, table, ""));
+    } else {
+        content.push_str(&format!("locals {{\n{}}}\n", table_text(name, table, "  ")));
+    }
+    let body: Body = content.parse().context("Failed to build version table")?;
+    write_tf_body(out, &body)
+}
+
+fn declare_variable(dir: &Path, name: &str) -> Result<()> {
+    for file in local_tf_files(dir)? {
+        let declared = read_tf_body(&file)?
+            .get_blocks("variable")
+            .any(|b| b.labels.first().map(|l| l.as_str()) == Some(name));
+        if declared {
+            return Ok(());
+        }
+    }
+
+    let path = dir.join("variables.tf");
+    let mut content = if path.is_file() { fs::read_to_string(&path)? } else { String::new() };
+    if !content.is_empty() {
+        content.push('\n');
+    }
+    content.push_str(&format!(
+        "variable \"{}\" {{\n  description = \"Module versions, by module name\"\n  type        = map(string)\n}}\n",
+        name
+    ));
+    let body: Body = content.parse().context("Failed to build variable declaration")?;
+    write_tf_body(&path, &body)
+}
+
+fn read_table(dir: &Path, name: &str, var_file: Option<&Path>) -> Result<(BTreeMap<String, String>, bool)> {
+    // The table as locals in the directory, else as variable values
+    let table_from = |value: &Expression| -> BTreeMap<String, String> {
+        let Expression::Object(object) = value else {
+            return BTreeMap::new();
+        };
+        object
+            .iter()
+            .filter_map(|(key, entry)| match entry.expr() {
+                Expression::String(s) => Some((key_name(key)?, s.value().to_string())),
+                _ => None,
+            })
+            .collect()
+    };
+
+    for file in local_tf_files(dir)? {
+        let body = read_tf_body(&file)?;
+        for locals in body.get_blocks("locals") {
+            if let Some(attr) = locals.body.get_attribute(name) {
+                return Ok((table_from(&attr.value), false));
+            }
+        }
+    }
+
+    let var_file = match var_file {
+        Some(path) => path.to_path_buf(),
+        None => dir.join("terraform.tfvars"),
+    };
+    if var_file.is_file() {
+        let body = read_tf_body(&var_file)?;
+        if let Some(attr) = body.get_attribute(name) {
+            return Ok((table_from(&attr.value), true));
+        }
+    }
+    Err(anyhow!("No '{}' table found in locals or {:?}", name, var_file))
+}
+
+fn referenced_key(text: &str, name: &str) -> Option<(String, String)> {
+    // Finds `local.<name>["key"]` or `var.<name>["key"]`; returns the whole
+    // reference and the key
+    for scope in ["local", "var"] {
+        let prefix = format!("{}.{}[\"", scope, name);
+        if let Some(start) = text.find(&prefix) {
+            let key_start = start + prefix.len();
+            let key_end = key_start + text[key_start..].find("\"]")?;
+            let reference = &text[start..key_end + 2];
+            return Some((reference.to_string(), text[key_start..key_end].to_string()));
+        }
+    }
+    None
+}
+
+pub fn inline(dir: &Path, name: &str, var_file: Option<&Path>) -> Result<Vec<Moved>> {
+    let (table, variable) = read_table(dir, name, var_file)?;
+    let mut moved = Vec::new();
+    let mut unresolved = 0;
+
+    for file in local_tf_files(dir)? {
+        let mut body = read_tf_body(&file)?;
+        let mut changed = false;
+
+        for block in body.get_blocks_mut("module") {
+            let label = block.labels.first().map(|l| l.as_str().to_string()).unwrap_or_default();
+            for attribute in ["source", "version"] {
+                let Some(mut attr) = block.body.get_attribute_mut(attribute) else {
+                    continue;
+                };
+                let text = attr.value_mut().to_string();
+                let Some((reference, key)) = referenced_key(&text, name) else {
+                    continue;
+                };
+                let Some(version) = table.get(&key) else {
+                    unresolved += 1;
+                    continue;
+                };
+                let literal = if attribute == "source" {
+                    text.trim().replace(&format!("${{{}}}", reference), version)
+                } else {
+                    format!("\"{}\"", version)
+                };
+                replace_value(attr.value_mut(), &literal)?;
+                moved.push(Moved { module: label.clone(), file: file.clone(), version: version.clone() });
+                changed = true;
+            }
+        }
+
+        if changed {
+            write_tf_body(&file, &body)?;
+        }
+    }
+
+    if unresolved == 0 {
+        remove_table(dir, name, variable, var_file)?;
+    }
+    Ok(moved)
+}
+
+fn remove_table(dir: &Path, name: &str, variable: bool, var_file: Option<&Path>) -> Result<()> {
+    // Only once nothing in the directory refers to the table any more
+    let references = [format!("local.{}", name), format!("var.{}", name)];
+    for file in local_tf_files(dir)? {
+        let content = fs::read_to_string(&file)?;
+        if references.iter().any(|r| content.contains(r.as_str())) {
+            return Ok(());
+        }
+    }
+
+    for file in local_tf_files(dir)? {
+        let mut body = read_tf_body(&file)?;
+        let before = body.to_string();
+        if variable {
+            loop {
+                let position = body.iter().position(|s| {
+                    s.as_block().is_some_and(|b| {
+                        b.ident.as_str() == "variable" && b.labels.first().map(|l| l.as_str()) == Some(name)
+                    })
+                });
+                let Some(i) = position else { break };
+                body.remove(i);
+            }
+        } else {
+            for locals in body.get_blocks_mut("locals") {
+                locals.body.remove_attribute(name);
+            }
+            // Drop locals blocks the table was the only entry of
+            loop {
+                let position = body.iter().position(|s| {
+                    s.as_block().is_some_and(|b| b.ident.as_str() == "locals" && b.body.is_empty())
+                });
+                let Some(i) = position else { break };
+                body.remove(i);
+            }
+        }
+        if body.to_string() != before {
+            write_tf_body(&file, &body)?;
+        }
+    }
+
+    if variable {
+        let var_file = var_file.map(Path::to_path_buf).unwrap_or_else(|| dir.join("terraform.tfvars"));
+        if var_file.is_file() {
+            let mut body = read_tf_body(&var_file)?;
+            if body.remove_attribute(name).is_some() {
+                write_tf_body(&var_file, &body)?;
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,92 @@
+// Shell completions and man pages, generated from the command line
+// definition so they never drift from it, for packaging (Homebrew, apt)
+// and for users installing a release binary by hand.
+//
+// `tv completions install [shell]` writes the script where the shell looks
+// for it, per user; without a shell, $SHELL decides:
+//
+//   bash  $XDG_DATA_HOME/bash-completion/completions/tv (bash-completion)
+//   zsh   $ZDOTDIR/.zfunc/_tv (the directory must be in fpath)
+//   fish  $XDG_CONFIG_HOME/fish/completions/tv.fish
+//
+// Elvish and PowerShell have no such directory; `tv completions print
+// <shell>` is sourced from their profile instead. `tv manpage` renders
+// tv(1), or with --dir one page per subcommand (tv-scan.1, ...).
+
+use anyhow::{Context, Result, anyhow};
+use clap::Command;
+use clap_complete::Shell;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const BIN_NAME: &str = "tv";
+
+pub fn detect_shell() -> Result<Shell> {
+    Shell::from_env().ok_or_else(|| anyhow!("Cannot tell the shell from $SHELL; name it, e.g. tv completions install zsh"))
+}
+
+pub fn script(shell: Shell, command: &mut Command) -> String {
+    let mut out = Vec::new();
+    clap_complete::generate(shell, command, BIN_NAME, &mut out);
+    String::from_utf8_lossy(&out).to_string()
+}
+
+fn env_dir(var: &str, home_fallback: &[&str]) -> Result<PathBuf> {
+    if let Some(dir) = std::env::var_os(var).filter(|d| !d.is_empty()) {
+        return Ok(PathBuf::from(dir));
+    }
+    let home = std::env::var_os("HOME").ok_or_else(|| anyhow!("Neither ${} nor $HOME is set", var))?;
+    Ok(home_fallback.iter().fold(PathBuf::from(home), |path, part| path.join(part)))
+}
+
+pub fn install_path(shell: Shell) -> Result<PathBuf> {
+    match shell {
+        Shell::Bash => Ok(env_dir("XDG_DATA_HOME", &[".local", "share"])?.join("bash-completion").join("completions").join(BIN_NAME)),
+        Shell::Zsh => Ok(env_dir("ZDOTDIR", &[])?.join(".zfunc").join(format!("_{}", BIN_NAME))),
+        Shell::Fish => Ok(env_dir("XDG_CONFIG_HOME", &[".config"])?.join("fish").join("completions").join(format!("{}.fish", BIN_NAME))),
+        _ => Err(anyhow!(
+            "{} has no completions directory; add `tv completions print {}` to its profile",
+            shell,
+            shell
+        )),
+    }
+}
+
+pub fn install_hint(shell: Shell) -> Option<&'static str> {
+    // What the shell needs before it picks the script up
+    match shell {
+        Shell::Bash => Some("needs the bash-completion package; new shells load it"),
+        Shell::Zsh => Some("add `fpath+=~/.zfunc` before `compinit` in ~/.zshrc if it is not there, then start a new shell"),
+        _ => None,
+    }
+}
+
+pub fn install(shell: Shell, command: &mut Command, path: Option<&Path>) -> Result<PathBuf> {
+    let path = match path {
+        Some(path) => path.to_path_buf(),
+        None => install_path(shell)?,
+    };
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).with_context(|| format!("Failed to create directory: {:?}", parent))?;
+    }
+    fs::write(&path, script(shell, command)).with_context(|| format!("Failed to write completions: {:?}", path))?;
+    Ok(path)
+}
+
+pub fn manpage(command: Command) -> Result<String> {
+    let mut out = Vec::new();
+    clap_mangen::Man::new(command).render(&mut out)?;
+    Ok(String::from_utf8_lossy(&out).to_string())
+}
+
+pub fn write_manpages(command: Command, dir: &Path) -> Result<Vec<PathBuf>> {
+    // tv.1 and tv-<subcommand>.1 for every subcommand, nested ones included
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create directory: {:?}", dir))?;
+    clap_mangen::generate_to(command, dir).with_context(|| format!("Failed to write man pages to {:?}", dir))?;
+    let mut pages: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|e| e == "1"))
+        .collect();
+    pages.sort();
+    Ok(pages)
+}
@@ -0,0 +1,228 @@
+// Repository configuration: tv.toml in the scanned directory or any parent.
+//
+// [[track]]
+// match   = "*github.com/org/*"         # module label, source url or provider address
+// channel = "stable"                    # stable, any (default) or a pre-release name
+// tags    = "v1.*-lts"                  # only versions whose tag matches
+//
+// The first rule matching a module (or provider) decides which versions
+// check-updates and upgrade may propose for it. A pre-release channel such
+// as "beta" also accepts stable releases, but no other pre-releases.
+//
+// minimum_release_age = "7d"   # top level, or per [[track]] rule
+//
+// Versions tagged less than this long ago are not proposed yet, since fresh
+// releases are the ones most likely to be withdrawn. Ages are a number
+// followed by s, m, h, d or w.
+//
+// [[group]]
+// name  = "aws-modules"
+// match = ["*github.com/terraform-aws-modules/*"]
+//
+// Modules in a group are upgraded together (`tv upgrade --group
+// aws-modules`, one journal transaction); a module belongs to the first
+// group with a matching pattern, other modules are upgraded on their own.
+//
+// [labels]
+// "envs/prod" = "team=payments, tier=prod"
+//
+// Directory labels for `--selector` (see labels.rs).
+//
+// on_override = "edit"   # warn (default), edit or fail
+//
+// What `tv set` does with a value an override file replaces (see effective.rs).
+//
+// [upgrade_guides]
+// "hashicorp/aws" = "https://example.com/aws-v{major}"
+//
+// Upgrade guide URLs for major versions (see guides.rs).
+//
+// tv_version = "1.4.x"
+//
+// The tv versions the repository expects (see self_update.rs).
+//
+// protected_paths = ["envs/prod/**"]
+//
+// Files edits may only change with --allow-protected or a confirmation
+// (see protect.rs).
+
+use anyhow::{Context, Result, anyhow};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::constraint::{Version, parse_version_spec};
+use crate::effective::OnOverride;
+use crate::wildcard_match;
+
+pub const CONFIG_FILE: &str = "tv.toml";
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub minimum_release_age: Option<String>,
+    #[serde(default)]
+    pub track: Vec<TrackRule>,
+    #[serde(default)]
+    pub group: Vec<Group>,
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,  // Directory pattern -> "key=value, ..."
+    #[serde(default)]
+    pub upgrade_guides: BTreeMap<String, String>,  // Provider or module pattern -> URL with {major}
+    pub on_override: Option<OnOverride>,
+    pub tv_version: Option<String>,
+    #[serde(default)]
+    pub protected_paths: Vec<String>,  // Relative to the directory of tv.toml
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum Channel {
+    #[default]
+    Any,
+    Stable,
+    Prerelease(String),  // e.g. "beta": stable releases and -beta* pre-releases
+}
+
+impl std::str::FromStr for Channel {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Channel> {
+        match s {
+            "any" => Ok(Channel::Any),
+            "stable" => Ok(Channel::Stable),
+            "" => Err(anyhow!("Channel cannot be empty")),
+            name => Ok(Channel::Prerelease(name.to_string())),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Channel {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Channel, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        text.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TrackRule {
+    #[serde(rename = "match")]
+    pub pattern: String,
+    #[serde(default)]
+    pub channel: Channel,
+    pub tags: Option<String>,
+    pub minimum_release_age: Option<String>,
+}
+
+impl TrackRule {
+    pub fn allows(&self, tag: &str) -> bool {
+        // Tags that are not versions are never proposed
+        let Ok(version) = Version::parse(tag) else {
+            return false;
+        };
+        if let Some(ref pattern) = self.tags
+            && !wildcard_match(pattern, tag)
+        {
+            return false;
+        }
+        match (&self.channel, &version.prerelease) {
+            (Channel::Any, _) | (_, None) => true,
+            (Channel::Stable, Some(_)) => false,
+            (Channel::Prerelease(name), Some(pre)) => pre.starts_with(name.as_str()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Group {
+    pub name: String,
+    #[serde(rename = "match")]
+    pub patterns: Vec<String>,
+}
+
+impl Config {
+    pub fn rule_for(&self, names: &[&str]) -> Option<&TrackRule> {
+        // `names` are what a module or provider can be matched by
+        self.track
+            .iter()
+            .find(|rule| names.iter().any(|name| wildcard_match(&rule.pattern, name)))
+    }
+
+    pub fn group_for(&self, names: &[&str]) -> Option<&Group> {
+        self.group.iter().find(|group| {
+            group
+                .patterns
+                .iter()
+                .any(|pattern| names.iter().any(|name| wildcard_match(pattern, name)))
+        })
+    }
+
+    pub fn minimum_release_age(&self, rule: Option<&TrackRule>) -> Result<Option<Duration>> {
+        // A rule's own minimum age overrides the top-level one
+        let age = rule
+            .and_then(|r| r.minimum_release_age.as_deref())
+            .or(self.minimum_release_age.as_deref());
+        age.map(parse_age).transpose()
+    }
+
+    pub fn has_group(&self, name: &str) -> bool {
+        self.group.iter().any(|g| g.name == name)
+    }
+}
+
+pub fn parse_age(text: &str) -> Result<Duration> {
+    let text = text.trim();
+    let split = text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| anyhow!("Invalid age '{}'; expected e.g. 7d or 12h", text))?;
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(anyhow!("Invalid age '{}'; units are s, m, h, d and w", text)),
+    };
+    let seconds = number
+        .checked_mul(seconds)
+        .ok_or_else(|| anyhow!("Invalid age '{}'; age too large", text))?;
+    Ok(Duration::from_secs(seconds))
+}
+
+pub fn find_config(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|d| d.join(CONFIG_FILE))
+        .find(|path| path.is_file())
+}
+
+pub fn parse_config(content: &str) -> Result<Config> {
+    let config: Config = toml::from_str(content).context("Invalid configuration")?;
+    for (i, group) in config.group.iter().enumerate() {
+        if config.group[..i].iter().any(|g| g.name == group.name) {
+            return Err(anyhow!("Duplicate group name: {}", group.name));
+        }
+    }
+    let ages = config.track.iter().map(|r| &r.minimum_release_age);
+    for age in ages.chain([&config.minimum_release_age]).flatten() {
+        parse_age(age)?;
+    }
+    if let Some(ref pin) = config.tv_version {
+        parse_version_spec(pin).with_context(|| format!("Invalid tv_version: {}", pin))?;
+    }
+    Ok(config)
+}
+
+pub fn load_config(dir: &Path) -> Result<Config> {
+    // A missing tv.toml is an empty configuration
+    let Some(path) = find_config(dir) else {
+        return Ok(Config::default());
+    };
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read config file: {:?}", path))?;
+    parse_config(&content).with_context(|| format!("Invalid config file: {:?}", path))
+}
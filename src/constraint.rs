@@ -0,0 +1,387 @@
+// Terraform version constraints, e.g. `>= 4.0, < 6.0` or `~> 5.1`.
+//
+// Versions follow terraform's (go-version) rules: any number of numeric
+// segments, missing segments count as zero, and a pre-release suffix
+// (`1.0.0-beta1`) sorts before the release.
+
+use anyhow::{Result, anyhow};
+use std::cmp::Ordering;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Version {
+    pub segments: Vec<u64>,
+    pub prerelease: Option<String>,
+}
+
+impl Version {
+    pub fn parse(version: &str) -> Result<Version> {
+        let version = version.trim().trim_start_matches('v');
+        let (numbers, prerelease) = match version.split_once('-') {
+            Some((numbers, pre)) => (numbers, Some(pre.to_string())),
+            None => (version, None),
+        };
+        // Build metadata does not take part in comparisons
+        let numbers = numbers.split('+').next().unwrap_or_default();
+
+        let segments = numbers
+            .split('.')
+            .map(|s| s.parse::<u64>())
+            .collect::<std::result::Result<Vec<u64>, _>>()
+            .map_err(|_| anyhow!("Invalid version: {}", version))?;
+        Ok(Version { segments, prerelease })
+    }
+
+    pub fn segment(&self, index: usize) -> u64 {
+        self.segments.get(index).copied().unwrap_or(0)
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let len = self.segments.len().max(other.segments.len());
+        for i in 0..len {
+            match self.segment(i).cmp(&other.segment(i)) {
+                Ordering::Equal => continue,
+                ordering => return ordering,
+            }
+        }
+        match (&self.prerelease, &other.prerelease) {
+            (None, None) => Ordering::Equal,
+            (None, Some(_)) => Ordering::Greater,
+            (Some(_), None) => Ordering::Less,
+            (Some(a), Some(b)) => a.cmp(b),
+        }
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl std::fmt::Display for Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let segments: Vec<String> = self.segments.iter().map(|s| s.to_string()).collect();
+        write!(f, "{}", segments.join("."))?;
+        if let Some(ref pre) = self.prerelease {
+            write!(f, "-{}", pre)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operator {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    Pessimistic,
+}
+
+impl Operator {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Operator::Eq => "=",
+            Operator::Ne => "!=",
+            Operator::Gt => ">",
+            Operator::Ge => ">=",
+            Operator::Lt => "<",
+            Operator::Le => "<=",
+            Operator::Pessimistic => "~>",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Constraint {
+    pub op: Operator,
+    pub version: Version,
+}
+
+impl std::fmt::Display for Constraint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.op.as_str(), self.version)
+    }
+}
+
+pub fn parse_constraints(constraints: &str) -> Result<Vec<Constraint>> {
+    constraints
+        .split(',')
+        .map(str::trim)
+        .filter(|c| !c.is_empty())
+        .map(|c| {
+            // Longest operators first so `>=` is not read as `>`
+            let (op, rest) = [
+                ("~>", Operator::Pessimistic),
+                (">=", Operator::Ge),
+                ("<=", Operator::Le),
+                ("!=", Operator::Ne),
+                (">", Operator::Gt),
+                ("<", Operator::Lt),
+                ("=", Operator::Eq),
+            ]
+            .iter()
+            .find_map(|(prefix, op)| c.strip_prefix(prefix).map(|rest| (*op, rest)))
+            .unwrap_or((Operator::Eq, c));
+            Ok(Constraint {
+                op,
+                version: Version::parse(rest)?,
+            })
+        })
+        .collect()
+}
+
+pub fn parse_version_spec(spec: &str) -> Result<Vec<Constraint>> {
+    // `5.x` or `5.2.*` is every version with that prefix; anything else is a constraint
+    let spec = spec.trim();
+    let Some(prefix) = spec.strip_suffix(".x").or_else(|| spec.strip_suffix(".*")) else {
+        return parse_constraints(spec);
+    };
+    let lower = Version::parse(prefix)?;
+    let mut upper = lower.segments.clone();
+    *upper.last_mut().ok_or_else(|| anyhow!("Invalid version: {}", spec))? += 1;
+    let upper = Version { segments: upper, prerelease: None };
+    parse_constraints(&format!(">= {}, < {}", lower, upper))
+}
+
+pub fn exact_version(constraints: &str) -> Option<String> {
+    // "5.0.0" or "= 5.0.0"; ranges are not pins
+    match parse_constraints(constraints).ok()?.as_slice() {
+        [only] if only.op == Operator::Eq => Some(only.version.to_string()),
+        _ => None,
+    }
+}
+
+// Lower and upper bounds of the versions a constraint set allows;
+// the flag says whether the bound itself is allowed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Range {
+    pub lower: Option<(Version, bool)>,
+    pub upper: Option<(Version, bool)>,
+    pub excluded: Vec<Version>,
+}
+
+fn pessimistic_upper(version: &Version) -> Option<Version> {
+    // ~> 1.2.3 allows < 1.3.0; ~> 1.2 allows < 2.0; ~> 1 has no upper bound
+    let len = version.segments.len();
+    if len < 2 {
+        return None;
+    }
+    let mut segments = version.segments[..len - 1].to_vec();
+    *segments.last_mut().unwrap() += 1;
+    segments.resize(len, 0);
+    Some(Version { segments, prerelease: None })
+}
+
+fn tighten_lower(range: &mut Range, version: Version, inclusive: bool) {
+    let tighter = match range.lower {
+        None => true,
+        Some((ref current, current_inclusive)) => {
+            version > *current || (version == *current && current_inclusive && !inclusive)
+        }
+    };
+    if tighter {
+        range.lower = Some((version, inclusive));
+    }
+}
+
+fn tighten_upper(range: &mut Range, version: Version, inclusive: bool) {
+    let tighter = match range.upper {
+        None => true,
+        Some((ref current, current_inclusive)) => {
+            version < *current || (version == *current && current_inclusive && !inclusive)
+        }
+    };
+    if tighter {
+        range.upper = Some((version, inclusive));
+    }
+}
+
+pub fn range_of(constraints: &[Constraint]) -> Range {
+    let mut range = Range {
+        lower: None,
+        upper: None,
+        excluded: Vec::new(),
+    };
+    for c in constraints {
+        let v = c.version.clone();
+        match c.op {
+            Operator::Eq => {
+                tighten_lower(&mut range, v.clone(), true);
+                tighten_upper(&mut range, v, true);
+            }
+            Operator::Ne => range.excluded.push(v),
+            Operator::Gt => tighten_lower(&mut range, v, false),
+            Operator::Ge => tighten_lower(&mut range, v, true),
+            Operator::Lt => tighten_upper(&mut range, v, false),
+            Operator::Le => tighten_upper(&mut range, v, true),
+            Operator::Pessimistic => {
+                if let Some(upper) = pessimistic_upper(&v) {
+                    tighten_upper(&mut range, upper, false);
+                }
+                tighten_lower(&mut range, v, true);
+            }
+        }
+    }
+    range
+}
+
+pub fn pessimistic_constraint(constraints: &str) -> Option<String> {
+    // `>= 5.0` -> `~> 5.0`: the declared lower bound, capped below the next
+    // major version. A lower bound with a patch level keeps it, since
+    // `~> 5.0.1` would also rule out 5.1: `>= 5.0.1` -> `~> 5.0, >= 5.0.1`.
+    // None when there already is an upper bound or no `>=` to convert.
+    let parsed = parse_constraints(constraints).ok()?;
+    let convertible = |c: &Constraint| c.op == Operator::Ge && c.version.prerelease.is_none();
+    if range_of(&parsed).upper.is_some() || !parsed.iter().any(convertible) {
+        return None;
+    }
+    let converted: Vec<String> = parsed
+        .iter()
+        .map(|c| {
+            if !convertible(c) {
+                return c.to_string();
+            }
+            let major_minor = format!("{}.{}", c.version.segment(0), c.version.segment(1));
+            if c.version.segments.iter().skip(2).all(|s| *s == 0) {
+                format!("~> {}", major_minor)
+            } else {
+                format!("~> {}, {}", major_minor, c)
+            }
+        })
+        .collect();
+    Some(converted.join(", "))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConstraintStyle {
+    Pessimistic,  // ~> 5.0
+    Exact,        // 5.0.0
+    Range,        // >= 5.0, < 6.0
+}
+
+impl std::str::FromStr for ConstraintStyle {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<ConstraintStyle> {
+        match s {
+            "pessimistic" => Ok(ConstraintStyle::Pessimistic),
+            "exact" => Ok(ConstraintStyle::Exact),
+            "range" => Ok(ConstraintStyle::Range),
+            _ => Err(anyhow!("Expected 'pessimistic', 'exact' or 'range', got '{}'", s)),
+        }
+    }
+}
+
+fn pessimistic_form(lower: &Version, upper: &Version) -> Option<String> {
+    // `~> lower`, or `~> major.minor, >= lower` when the lower bound has a
+    // patch level but the upper bound is the next major version
+    if pessimistic_upper(lower).is_some_and(|u| u.cmp(upper) == Ordering::Equal) {
+        return Some(format!("~> {}", lower));
+    }
+    let major_minor = Version { segments: vec![lower.segment(0), lower.segment(1)], prerelease: None };
+    if pessimistic_upper(&major_minor)?.cmp(upper) != Ordering::Equal {
+        return None;
+    }
+    Some(match lower.cmp(&major_minor) {
+        Ordering::Equal => format!("~> {}", major_minor),
+        _ => format!("~> {}, >= {}", major_minor, lower),
+    })
+}
+
+pub fn normalize_constraint(constraints: &str, style: ConstraintStyle) -> std::result::Result<String, String> {
+    // The same set of versions written in another style; multi-clause
+    // constraints collapse to their tightest bounds. Err says why there is
+    // no equivalent in that style.
+    let parsed = parse_constraints(constraints).map_err(|_| "not a version constraint".to_string())?;
+    let range = range_of(&parsed);
+    if !range.is_satisfiable() {
+        return Err("no version satisfies it".to_string());
+    }
+    if [&range.lower, &range.upper].iter().any(|b| b.as_ref().is_some_and(|(v, _)| v.prerelease.is_some())) {
+        return Err("pre-release bounds are left as written".to_string());
+    }
+    let pinned = match (&range.lower, &range.upper) {
+        (Some((lower, true)), Some((upper, true))) if lower.cmp(upper) == Ordering::Equal => Some(lower),
+        _ => None,
+    };
+
+    let mut excluded = range.excluded.clone();
+    excluded.sort();
+    excluded.dedup();
+    let mut clauses = match style {
+        ConstraintStyle::Exact => match pinned {
+            Some(version) => return Ok(version.to_string()),
+            None => return Err("allows more than one version".to_string()),
+        },
+        ConstraintStyle::Range => {
+            if let Some(version) = pinned {
+                return Ok(format!("= {}", version));
+            }
+            let mut clauses = Vec::new();
+            if let Some((ref lower, inclusive)) = range.lower {
+                clauses.push(format!("{} {}", if inclusive { ">=" } else { ">" }, lower));
+            }
+            if let Some((ref upper, inclusive)) = range.upper {
+                clauses.push(format!("{} {}", if inclusive { "<=" } else { "<" }, upper));
+            }
+            if clauses.is_empty() {
+                return Err("allows any version".to_string());
+            }
+            clauses
+        }
+        ConstraintStyle::Pessimistic => match (&range.lower, &range.upper) {
+            _ if pinned.is_some() => return Err("pins a single version".to_string()),
+            (Some((lower, true)), Some((upper, false))) => match pessimistic_form(lower, upper) {
+                Some(form) => vec![form],
+                None => return Err("no ~> form allows the same versions".to_string()),
+            },
+            (_, None) => return Err("has no upper bound".to_string()),
+            _ => return Err("no ~> form allows the same versions".to_string()),
+        },
+    };
+    clauses.extend(excluded.iter().map(|v| format!("!= {}", v)));
+    Ok(clauses.join(", "))
+}
+
+impl Range {
+    pub fn is_satisfiable(&self) -> bool {
+        match (&self.lower, &self.upper) {
+            (Some((lower, lower_inclusive)), Some((upper, upper_inclusive))) => {
+                match lower.cmp(upper) {
+                    Ordering::Less => true,
+                    Ordering::Equal => {
+                        *lower_inclusive && *upper_inclusive && !self.excluded.contains(lower)
+                    }
+                    Ordering::Greater => false,
+                }
+            }
+            _ => true,
+        }
+    }
+}
+
+pub fn satisfies(version: &Version, constraints: &[Constraint]) -> bool {
+    constraints.iter().all(|c| match c.op {
+        Operator::Eq => version == &c.version,
+        Operator::Ne => version != &c.version,
+        Operator::Gt => version > &c.version,
+        Operator::Ge => version >= &c.version,
+        Operator::Lt => version < &c.version,
+        Operator::Le => version <= &c.version,
+        Operator::Pessimistic => {
+            version >= &c.version
+                && pessimistic_upper(&c.version).is_none_or(|upper| version < &upper)
+        }
+    })
+}
+
+pub fn compatible(a: &[Constraint], b: &[Constraint]) -> bool {
+    // Some version satisfies both constraint sets
+    let combined: Vec<Constraint> = a.iter().chain(b.iter()).cloned().collect();
+    range_of(&combined).is_satisfiable()
+}
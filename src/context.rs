@@ -0,0 +1,36 @@
+// `--context N` on `tv scan` and `tv grep`: the lines around each result,
+// like grep -C, so results can be reviewed in the terminal without opening
+// every file.
+//
+//   "./main.tf": "module.vpc"
+//     4 | module "vpc" {
+//   > 5 |   source  = "terraform-aws-modules/vpc/aws"
+//     6 |   version = "5.1.0"
+//   --
+//   "./dr/main.tf": "module.vpc"
+//   ...
+//
+// The matched line is marked with `>` and highlighted. For scan it is the
+// line of the value addressed, or the block's first line for whole-block
+// queries.
+
+use crate::style;
+
+pub fn excerpt(content: &str, line: usize, context: usize, mark: impl Fn(&str) -> String) -> String {
+    // Lines line-context..=line+context, numbered from 1
+    let lines: Vec<&str> = content.lines().collect();
+    if line == 0 || line > lines.len() {
+        return String::new();
+    }
+    let first = line.saturating_sub(context).max(1);
+    let last = (line + context).min(lines.len());
+    let width = last.to_string().len();
+    (first..=last)
+        .map(|n| {
+            let text = lines[n - 1];
+            let (marker, text) = if n == line { (">", mark(text)) } else { (" ", text.to_string()) };
+            let number = style::dim(&format!("{:>width$}", n, width = width));
+            format!("{} {} | {}", marker, number, text).trim_end().to_string() + "\n"
+        })
+        .collect()
+}
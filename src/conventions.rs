@@ -0,0 +1,194 @@
+// Naming conventions for module blocks, declared in the policy file and
+// enforced by `tv validate`:
+//
+// convention "label-is-last-segment" {
+//   match   = "*github.com/org/*"    # optional: module label or source url
+//   name    = "^{segment}$"
+//   message = "Name modules after what they fetch"
+// }
+//
+// convention "internal-over-ssh" {
+//   match = "*github.com/org/*"
+//   url   = "^(git::ssh://|git@)"
+// }
+//
+// `name`, `url` and `path` are regexes the module label, the source url
+// and the source subdirectory (`//modules/vpc`, empty without one) must
+// match. `{name}` and `{segment}` in them stand for the label and the last
+// segment of what the source fetches: the subdirectory, else the registry
+// module name or the repository name.
+//
+// Where the fix is mechanical a violation suggests it: the label the
+// convention wants (`{segment}`, or with `-` as `_`), or the source over
+// ssh or https. Fixes are only suggested; renaming a module also means
+// moving its state.
+
+use anyhow::{Context, Result, anyhow};
+use hcl_edit::expr::Expression;
+use hcl_edit::structure::Block;
+use regex::Regex;
+use std::path::Path;
+
+use crate::policy::{CheckReport, Violation};
+use crate::query::quote_segment;
+use crate::source::{ModuleSource, SourceKind};
+use crate::{find_all_tf_files, read_tf_body, wildcard_match};
+
+#[derive(Debug, Clone)]
+pub struct Convention {
+    pub name: String,
+    pub pattern: Option<String>,  // `match`; None for every module
+    pub label: Option<String>,  // Regexes, before {name} and {segment} are filled in
+    pub url: Option<String>,
+    pub path: Option<String>,
+    pub message: Option<String>,
+}
+
+fn string_attr(block: &Block, name: &str) -> Result<Option<String>> {
+    match block.body.get_attribute(name).map(|attr| &attr.value) {
+        None => Ok(None),
+        Some(Expression::String(s)) => Ok(Some(s.value().to_string())),
+        Some(_) => Err(anyhow!("'{}' must be a string", name)),
+    }
+}
+
+fn fill(pattern: &str, label: &str, segment: &str) -> String {
+    pattern.replace("{name}", &regex::escape(label)).replace("{segment}", &regex::escape(segment))
+}
+
+pub fn parse_convention(block: &Block) -> Result<Convention> {
+    let name = block
+        .labels
+        .first()
+        .map(|l| l.as_str().to_string())
+        .ok_or_else(|| anyhow!("convention blocks need a name: convention \"<name>\" {{ ... }}"))?;
+    let context = || format!("Invalid convention \"{}\"", name);
+    let convention = Convention {
+        pattern: string_attr(block, "match").with_context(context)?,
+        label: string_attr(block, "name").with_context(context)?,
+        url: string_attr(block, "url").with_context(context)?,
+        path: string_attr(block, "path").with_context(context)?,
+        message: string_attr(block, "message").with_context(context)?,
+        name: name.clone(),
+    };
+    if convention.label.is_none() && convention.url.is_none() && convention.path.is_none() {
+        return Err(anyhow!("convention \"{}\" needs name, url or path", convention.name));
+    }
+    // Checked once here, with placeholders filled by something plain
+    for pattern in [&convention.label, &convention.url, &convention.path].into_iter().flatten() {
+        Regex::new(&fill(pattern, "x", "x")).with_context(|| format!("Invalid regex: {}", pattern)).with_context(context)?;
+    }
+    Ok(convention)
+}
+
+pub fn source_segment(source: &ModuleSource) -> String {
+    // The last segment of what a source fetches, e.g. vpc for
+    // git::https://github.com/org/vpc.git or terraform-aws-modules/vpc/aws
+    if let Some(ref path) = source.path {
+        return path.trim_end_matches('/').rsplit('/').next().unwrap_or_default().to_string();
+    }
+    if source.kind == SourceKind::Registry {
+        let parts: Vec<&str> = source.url.split('/').collect();
+        return parts[parts.len().saturating_sub(2)].to_string();
+    }
+    let last = source.url.trim_end_matches('/').rsplit(['/', ':']).next().unwrap_or_default();
+    last.trim_end_matches(".git").to_string()
+}
+
+fn ssh_url(url: &str) -> Option<String> {
+    // git::https://host/org/repo.git -> git::ssh://git@host/org/repo.git
+    let rest = url.strip_prefix("git::").unwrap_or(url);
+    let rest = rest.strip_prefix("https://").or_else(|| rest.strip_prefix("http://")).unwrap_or(rest);
+    if rest.contains("://") || rest.starts_with("git@") || !rest.contains('/') {
+        return None;
+    }
+    Some(format!("git::ssh://git@{}", rest))
+}
+
+fn https_url(url: &str) -> Option<String> {
+    // git@host:org/repo.git or git::ssh://git@host/org/repo.git -> git::https://host/org/repo.git
+    let rest = url.strip_prefix("git::").unwrap_or(url);
+    if let Some(rest) = rest.strip_prefix("ssh://") {
+        return Some(format!("git::https://{}", rest.split_once('@').map_or(rest, |(_, host)| host)));
+    }
+    let (host, path) = rest.strip_prefix("git@")?.split_once(':')?;
+    Some(format!("git::https://{}/{}", host, path))
+}
+
+fn check_module(convention: &Convention, label: &str, source: &ModuleSource) -> Result<Option<(String, Option<String>)>> {
+    // The reason a module breaks the convention, and a fix if one is mechanical
+    let segment = source_segment(source);
+    let matches = |pattern: &str, text: &str| -> Result<bool> { Ok(Regex::new(&fill(pattern, label, &segment))?.is_match(text)) };
+
+    if let Some(ref pattern) = convention.label
+        && !matches(pattern, label)?
+    {
+        let mut fix = None;
+        for candidate in [segment.clone(), segment.replace('-', "_")] {
+            if !candidate.is_empty() && matches(pattern, &candidate)? {
+                fix = Some(format!("rename module.{} to module.{} (and move its state)", label, quote_segment(&candidate)));
+                break;
+            }
+        }
+        return Ok(Some((format!("name \"{}\" does not match {}", label, fill(pattern, label, &segment)), fix)));
+    }
+    if let Some(ref pattern) = convention.url
+        && !matches(pattern, &source.url)?
+    {
+        let mut fix = None;
+        for candidate in [ssh_url(&source.url), https_url(&source.url)].into_iter().flatten() {
+            if matches(pattern, &candidate)? {
+                let fixed = ModuleSource { url: candidate, ..source.clone() };
+                fix = Some(format!("source = \"{}\"", fixed));
+                break;
+            }
+        }
+        return Ok(Some((format!("url \"{}\" does not match {}", source.url, fill(pattern, label, &segment)), fix)));
+    }
+    if let Some(ref pattern) = convention.path {
+        let path = source.path.as_deref().unwrap_or_default();
+        if !matches(pattern, path)? {
+            return Ok(Some((format!("path \"{}\" does not match {}", path, fill(pattern, label, &segment)), None)));
+        }
+    }
+    Ok(None)
+}
+
+pub fn convention_report(convention: &Convention, dir: &Path) -> Result<CheckReport> {
+    let mut report = CheckReport {
+        name: convention.name.clone(),
+        message: convention.message.clone(),
+        checked: 0,
+        violations: Vec::new(),
+    };
+    for file in find_all_tf_files(dir)? {
+        // Files that do not parse are reported by the duplicate-attributes
+        // check or by the other checks
+        let Ok(body) = read_tf_body(&file) else {
+            continue;
+        };
+        for block in body.get_blocks("module") {
+            let Some(Expression::String(source)) = block.body.get_attribute("source").map(|a| &a.value) else {
+                continue;
+            };
+            let label = block.labels.first().map(|l| l.as_str()).unwrap_or_default();
+            let source = ModuleSource::parse(source.value());
+            if let Some(ref pattern) = convention.pattern
+                && !wildcard_match(pattern, label)
+                && !wildcard_match(pattern, &source.url)
+            {
+                continue;
+            }
+            report.checked += 1;
+            if let Some((reason, fix)) = check_module(convention, label, &source)? {
+                report.violations.push(Violation {
+                    file: file.clone(),
+                    block: format!("module.{}", quote_segment(label)),
+                    reason,
+                    fix,
+                });
+            }
+        }
+    }
+    Ok(report)
+}
@@ -0,0 +1,230 @@
+// `tv stats-dir`: one summary line per root module directory, for the
+// people who want the dashboard rather than the details.
+//
+// A root module directory is a directory with .tf files that no other
+// directory uses as a local module (`source = "./modules/vpc"`). For each:
+//
+// - modules: module blocks, local ones included;
+// - pinned: the share of non-local modules that are pinned (see pin_age.rs);
+// - providers: distinct names in required_providers and provider blocks;
+// - oldest pin (--lookup only): the pinned module whose tag or registry
+//   version was released longest ago.
+//
+// Output is a table, JSON or markdown (for pasting into issues and wikis).
+// With --stable the oldest pin shows its release date rather than its age
+// in days, and JSON leaves `days` null.
+
+use anyhow::{Result, anyhow};
+use hcl_edit::expr::Expression;
+use serde_json::json;
+use std::collections::{BTreeSet, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::doctor::provider_requirements;
+use crate::pin_age::{PinAge, age_in_days, module_pin, released};
+use crate::source::SourceKind;
+use crate::stable;
+use crate::updates::{ReleaseDates, UpdateOptions};
+use crate::{find_all_tf_files, read_tf_body};
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ReportFormat {
+    #[default]
+    Table,
+    Json,
+    Markdown,
+}
+
+impl std::str::FromStr for ReportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "table" => Ok(ReportFormat::Table),
+            "json" => Ok(ReportFormat::Json),
+            "markdown" | "md" => Ok(ReportFormat::Markdown),
+            _ => Err(anyhow!("Unknown format '{}' (expected table, json or markdown)", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DirStats {
+    pub dir: PathBuf,
+    pub modules: usize,
+    pub pinnable: usize,  // Modules that are not local
+    pub pinned: usize,
+    pub providers: Vec<String>,  // Sorted
+    pub oldest_pin: Option<PinAge>,
+}
+
+impl DirStats {
+    pub fn pinned_percent(&self) -> Option<u64> {
+        // None when there is nothing to pin
+        (self.pinnable > 0).then(|| (self.pinned * 100 / self.pinnable) as u64)
+    }
+}
+
+fn local_module_dir(dir: &Path, source: &str) -> Option<PathBuf> {
+    (source.starts_with("./") || source.starts_with("../")).then(|| dir.join(source).canonicalize().ok())?
+}
+
+pub fn root_dirs(dir: &Path) -> Result<Vec<PathBuf>> {
+    // Directories with .tf files, minus those used as local modules
+    let mut dirs: BTreeSet<PathBuf> = BTreeSet::new();
+    let mut used: HashSet<PathBuf> = HashSet::new();
+    for file in find_all_tf_files(dir)? {
+        let parent = crate::directory_of(&file).to_path_buf();
+        for block in read_tf_body(&file)?.get_blocks("module") {
+            if let Some(Expression::String(source)) = block.body.get_attribute("source").map(|a| &a.value)
+                && let Some(module_dir) = local_module_dir(&parent, source.value())
+            {
+                used.insert(module_dir);
+            }
+        }
+        dirs.insert(parent);
+    }
+    Ok(dirs
+        .into_iter()
+        .filter(|d| d.canonicalize().map_or(true, |c| !used.contains(&c)))
+        .collect())
+}
+
+pub fn dir_stats(dir: &Path, lookups: Option<&UpdateOptions>) -> Result<Vec<DirStats>> {
+    let mut dates = lookups.map(|o| ReleaseDates::new(&o.cache_dir, &o.registry));
+    let mut report = Vec::new();
+
+    for root in root_dirs(dir)? {
+        let mut stats = DirStats {
+            dir: root.clone(),
+            modules: 0,
+            pinnable: 0,
+            pinned: 0,
+            providers: Vec::new(),
+            oldest_pin: None,
+        };
+        let mut providers = BTreeSet::new();
+        let mut oldest: Option<(u64, PinAge)> = None;
+
+        let files = find_all_tf_files(&root)?.into_iter().filter(|f| crate::directory_of(f) == root);
+        for file in files {
+            let body = read_tf_body(&file)?;
+            providers.extend(provider_requirements(&body, &file).into_iter().map(|r| r.name));
+            providers.extend(
+                body.get_blocks("provider")
+                    .filter_map(|b| b.labels.first().map(|l| l.as_str().to_string())),
+            );
+
+            for block in body.get_blocks("module") {
+                stats.modules += 1;
+                let Some(pin) = module_pin(&root, block)? else {
+                    continue;
+                };
+                if pin.source.kind == SourceKind::Local {
+                    continue;
+                }
+                stats.pinnable += 1;
+                if pin.pin.is_none() {
+                    continue;
+                }
+                stats.pinned += 1;
+                if let Some(ref mut dates) = dates
+                    && let Some(date) = released(dates, &pin)?
+                    && oldest.as_ref().is_none_or(|(d, _)| date < *d)
+                {
+                    let age = PinAge {
+                        file: file.clone(),
+                        module: pin.module,
+                        pin: pin.pin.unwrap_or_default(),
+                        days: Some(age_in_days(date)),
+                        released: Some(date),
+                    };
+                    oldest = Some((date, age));
+                }
+            }
+        }
+
+        stats.providers = providers.into_iter().collect();
+        stats.oldest_pin = oldest.map(|(_, age)| age);
+        report.push(stats);
+    }
+    Ok(report)
+}
+
+fn columns(stats: &DirStats, show: &impl Fn(&Path) -> String) -> [String; 5] {
+    [
+        show(&stats.dir),
+        stats.modules.to_string(),
+        stats.pinned_percent().map_or("-".to_string(), |p| format!("{}%", p)),
+        if stats.providers.is_empty() { "-".to_string() } else { stats.providers.join(", ") },
+        stats
+            .oldest_pin
+            .as_ref()
+            .map_or("-".to_string(), |o| match o.released {
+                Some(released) if stable::enabled() => format!("{} {} ({})", o.module, o.pin, stable::date(released)),
+                _ => format!("{} {} ({}d)", o.module, o.pin, o.days.unwrap_or_default()),
+            }),
+    ]
+}
+
+const HEADERS: [&str; 5] = ["directory", "modules", "pinned", "providers", "oldest pin"];
+
+pub fn render(report: &[DirStats], format: ReportFormat, show: impl Fn(&Path) -> String) -> String {
+    let rows: Vec<[String; 5]> = report.iter().map(|s| columns(s, &show)).collect();
+    match format {
+        ReportFormat::Json => {
+            let dirs: Vec<_> = report
+                .iter()
+                .map(|s| {
+                    json!({
+                        "dir": show(&s.dir),
+                        "modules": s.modules,
+                        "pinnable": s.pinnable,
+                        "pinned": s.pinned,
+                        "pinned_percent": s.pinned_percent(),
+                        "providers": s.providers,
+                        "oldest_pin": s.oldest_pin.as_ref().map(|o| json!({
+                            "module": o.module,
+                            "pin": o.pin,
+                            "days": if stable::enabled() { None } else { o.days },
+                            "released": o.released.map(stable::date),
+                        })),
+                    })
+                })
+                .collect();
+            serde_json::to_string_pretty(&dirs).unwrap_or_default() + "\n"
+        }
+        ReportFormat::Markdown => {
+            let mut out = format!("| {} |\n|{}\n", HEADERS.join(" | "), "---|".repeat(HEADERS.len()));
+            for row in &rows {
+                out.push_str(&format!("| {} |\n", row.join(" | ").replace('\n', " ")));
+            }
+            out
+        }
+        ReportFormat::Table => {
+            let mut widths = HEADERS.map(str::len);
+            for row in &rows {
+                for (width, cell) in widths.iter_mut().zip(row) {
+                    *width = (*width).max(cell.chars().count());
+                }
+            }
+            let line = |cells: Vec<&str>| {
+                let padded: Vec<String> = cells
+                    .iter()
+                    .enumerate()
+                    .map(|(i, cell)| match i {
+                        // Counts are right-aligned
+                        1 | 2 => format!("{:>width$}", cell, width = widths[i]),
+                        _ => format!("{:<width$}", cell, width = widths[i]),
+                    })
+                    .collect();
+                padded.join("  ").trim_end().to_string() + "\n"
+            };
+            let mut out = line(HEADERS.to_vec());
+            for row in &rows {
+                out.push_str(&line(row.iter().map(String::as_str).collect()));
+            }
+            out
+        }
+    }
+}
@@ -0,0 +1,697 @@
+// `tv doctor`: repository and environment health checks.
+//
+// Each check reports findings with an actionable fix. Repository checks
+// group .tf files by directory, since every directory is one root module
+// as far as terraform is concerned.
+//
+// The dependency-confusion check is the only one that goes to the network:
+// a registry source without a host (`acme/vpc/aws`) always means the public
+// registry, so when a private registry is configured and publishes the same
+// module, the short form installs whatever the public registry has under
+// that name (possibly nothing yet, until someone claims the namespace).
+//
+// Sources that nearly match another one across the whole repository are a
+// warning (see similar.rs).
+
+use anyhow::Result;
+use hcl_edit::Decorate;
+use hcl_edit::expr::Expression;
+use hcl_edit::structure::Body;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::constraint::{Version, compatible, parse_constraints, pessimistic_constraint, range_of};
+use crate::duplicates::find_duplicates;
+use crate::registry::{self, ModuleAddress};
+use crate::similar::similar_sources;
+use crate::source::ModuleSource;
+use crate::submodules::{submodules, vendored_modules};
+use crate::terraformrc;
+use crate::{conflict_markers, find_all_tf_files, read_tf_body, write_tf_body};
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub severity: Severity,
+    pub check: &'static str,
+    pub file: Option<PathBuf>,
+    pub message: String,
+    pub fix: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct ProviderRequirement {
+    pub file: PathBuf,
+    pub name: String,
+    pub source: Option<String>,
+    pub version: Option<String>,
+}
+
+pub fn provider_requirements(body: &Body, file: &Path) -> Vec<ProviderRequirement> {
+    // terraform { required_providers { aws = { source = "...", version = "..." } } }
+    let mut requirements = Vec::new();
+    let terraform_blocks = body
+        .iter()
+        .filter_map(|s| s.as_block())
+        .filter(|b| b.ident.as_str() == "terraform");
+
+    for terraform in terraform_blocks {
+        let required = terraform
+            .body
+            .iter()
+            .filter_map(|s| s.as_block())
+            .filter(|b| b.ident.as_str() == "required_providers");
+        for block in required {
+            for attr in block.body.iter().filter_map(|s| s.as_attribute()) {
+                let (source, version) = match attr.value {
+                    Expression::Object(ref object) => {
+                        let field = |name: &str| match crate::map::get_entry(object, name) {
+                            Some(Expression::String(s)) => Some(s.value().to_string()),
+                            _ => None,
+                        };
+                        (field("source"), field("version"))
+                    }
+                    // Legacy shorthand: aws = "~> 5.0"
+                    Expression::String(ref s) => (None, Some(s.value().to_string())),
+                    _ => (None, None),
+                };
+                requirements.push(ProviderRequirement {
+                    file: file.to_path_buf(),
+                    name: attr.key.as_str().to_string(),
+                    source,
+                    version,
+                });
+            }
+        }
+    }
+
+    requirements
+}
+
+fn check_provider_pin(requirement: &ProviderRequirement, findings: &mut Vec<Finding>) {
+    // Without an upper bound the next major version (and its breaking
+    // changes) is installed by the next `terraform init -upgrade`
+    let name = &requirement.name;
+    let (message, fix) = match requirement.version.as_deref() {
+        None => (
+            format!("provider \"{}\" has no version constraint", name),
+            "add a pessimistic constraint, e.g. version = \"~> 5.0\"".to_string(),
+        ),
+        Some(version) => {
+            let Ok(constraints) = parse_constraints(version) else {
+                return;
+            };
+            if range_of(&constraints).upper.is_some() {
+                return;
+            }
+            let fix = match pessimistic_constraint(version) {
+                Some(pinned) => format!("run `tv doctor --fix` to use \"{}\"", pinned),
+                None => "add an upper bound, e.g. a pessimistic (~>) constraint".to_string(),
+            };
+            (format!("provider \"{}\" has no upper version bound: \"{}\"", name, version), fix)
+        }
+    };
+    findings.push(Finding {
+        severity: Severity::Warning,
+        check: "provider-pin",
+        file: Some(requirement.file.clone()),
+        message,
+        fix,
+    });
+}
+
+fn check_root(files: &[PathBuf], findings: &mut Vec<Finding>) {
+    let mut module_labels: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+    let mut providers: BTreeMap<String, Vec<ProviderRequirement>> = BTreeMap::new();
+
+    for file in files {
+        let content = fs::read_to_string(file).map_err(anyhow::Error::from);
+        let markers = content.as_deref().map(conflict_markers).unwrap_or_default();
+        if !markers.is_empty() {
+            let lines: Vec<String> = markers.iter().map(|l| l.to_string()).collect();
+            findings.push(Finding {
+                severity: Severity::Error,
+                check: "merge-conflict",
+                file: Some(file.clone()),
+                message: format!("unresolved merge conflict (markers at lines {})", lines.join(", ")),
+                fix: "resolve the conflict and remove the markers; tv skips this file".to_string(),
+            });
+            continue;
+        }
+        // Such files do not parse; the duplicates are the finding
+        let duplicates = content.as_deref().map(find_duplicates).unwrap_or_default();
+        if !duplicates.is_empty() {
+            for duplicate in duplicates {
+                let lines: Vec<String> = duplicate.lines.iter().map(|(first, _)| first.to_string()).collect();
+                let scope = if duplicate.block.is_empty() { "top level".to_string() } else { duplicate.block };
+                findings.push(Finding {
+                    severity: Severity::Error,
+                    check: "duplicate-attribute",
+                    file: Some(file.clone()),
+                    message: format!("{} sets \"{}\" {} times (lines {})", scope, duplicate.key, lines.len(), lines.join(", ")),
+                    fix: "keep one definition, or run `tv doctor --fix keep-first|keep-last`".to_string(),
+                });
+            }
+            continue;
+        }
+        let body: Body = match content.and_then(|content| content.parse().map_err(anyhow::Error::from)) {
+            Ok(body) => body,
+            Err(e) => {
+                findings.push(Finding {
+                    severity: Severity::Error,
+                    check: "parse",
+                    file: Some(file.clone()),
+                    message: format!("file cannot be parsed: {}", e),
+                    fix: "fix the syntax error (`terraform fmt` points at it); tv skips this file"
+                        .to_string(),
+                });
+                continue;
+            }
+        };
+
+        for block in body.iter().filter_map(|s| s.as_block()) {
+            if block.ident.as_str() != "module" {
+                continue;
+            }
+            let Some(label) = block.labels.first().map(|l| l.as_str().to_string()) else {
+                continue;
+            };
+            if block.body.get_attribute("source").is_none() {
+                findings.push(Finding {
+                    severity: Severity::Error,
+                    check: "module-source",
+                    file: Some(file.clone()),
+                    message: format!("module \"{}\" has no source", label),
+                    fix: format!("add a source, e.g. tv set 'module.{}.source' <source>", label),
+                });
+            }
+            module_labels.entry(label).or_default().push(file.clone());
+        }
+
+        for requirement in provider_requirements(&body, file) {
+            providers.entry(requirement.name.clone()).or_default().push(requirement);
+        }
+    }
+
+    for (label, declared_in) in module_labels {
+        if declared_in.len() > 1 {
+            let locations: Vec<String> =
+                declared_in.iter().map(|f| f.display().to_string()).collect();
+            findings.push(Finding {
+                severity: Severity::Error,
+                check: "duplicate-module",
+                file: declared_in.first().cloned(),
+                message: format!(
+                    "module \"{}\" is declared {} times ({})",
+                    label,
+                    declared_in.len(),
+                    locations.join(", ")
+                ),
+                fix: "rename or remove all but one of the declarations".to_string(),
+            });
+        }
+    }
+
+    for (name, requirements) in providers {
+        for requirement in &requirements {
+            check_provider_pin(requirement, findings);
+        }
+
+        let mut sources: Vec<&str> =
+            requirements.iter().filter_map(|r| r.source.as_deref()).collect();
+        sources.sort();
+        sources.dedup();
+        if sources.len() > 1 {
+            findings.push(Finding {
+                severity: Severity::Error,
+                check: "provider-conflict",
+                file: requirements.first().map(|r| r.file.clone()),
+                message: format!(
+                    "provider \"{}\" has different sources: {}",
+                    name,
+                    sources.join(", ")
+                ),
+                fix: "declare one source for the provider in a single required_providers block".to_string(),
+            });
+        }
+
+        let mut versions: Vec<&str> =
+            requirements.iter().filter_map(|r| r.version.as_deref()).collect();
+        versions.sort();
+        versions.dedup();
+        if versions.len() > 1 {
+            // Terraform combines every constraint; only a disjoint set fails init
+            let parsed: Vec<_> = versions.iter().filter_map(|v| parse_constraints(v).ok()).collect();
+            let incompatible = parsed
+                .iter()
+                .enumerate()
+                .any(|(i, a)| parsed[i + 1..].iter().any(|b| !compatible(a, b)));
+            let (severity, problem) = if incompatible {
+                (Severity::Error, "incompatible")
+            } else {
+                (Severity::Warning, "different")
+            };
+            findings.push(Finding {
+                severity,
+                check: "provider-conflict",
+                file: requirements.first().map(|r| r.file.clone()),
+                message: format!(
+                    "provider \"{}\" has {} version constraints: {}",
+                    name,
+                    problem,
+                    versions.join(", ")
+                ),
+                fix: "run `tv doctor --fix --prefer highest` to keep a single declaration".to_string(),
+            });
+        }
+    }
+}
+
+fn root_modules(dir: &Path) -> Result<BTreeMap<PathBuf, Vec<PathBuf>>> {
+    let mut roots: BTreeMap<PathBuf, Vec<PathBuf>> = BTreeMap::new();
+    for file in find_all_tf_files(dir)? {
+        let root = file.parent().map(|p| p.to_path_buf()).unwrap_or_default();
+        roots.entry(root).or_default().push(file);
+    }
+    for files in roots.values_mut() {
+        files.sort();
+    }
+    Ok(roots)
+}
+
+pub fn diagnose_dir(dir: &Path) -> Result<Vec<Finding>> {
+    let mut findings = Vec::new();
+    let roots = root_modules(dir)?;
+    for files in roots.values() {
+        check_root(files, &mut findings);
+    }
+
+    let files: Vec<PathBuf> = roots.into_values().flatten().collect();
+    for vendored in vendored_modules(&files, &submodules(dir)?) {
+        let submodule = &vendored.submodule;
+        findings.push(Finding {
+            severity: Severity::Info,
+            check: "submodule-source",
+            file: Some(vendored.file),
+            message: format!(
+                "module.{} source \"{}\" is inside git submodule {}{}",
+                vendored.module,
+                vendored.source,
+                submodule.name,
+                submodule.url.as_ref().map(|u| format!(" ({})", u)).unwrap_or_default()
+            ),
+            fix: "its version is the submodule commit; update it with `git submodule update --remote`".to_string(),
+        });
+    }
+
+    for similar in similar_sources(dir)? {
+        let used: Vec<&str> = similar.uses.iter().map(|u| u.block.as_str()).collect();
+        findings.push(Finding {
+            severity: Severity::Warning,
+            check: "similar-sources",
+            file: similar.uses.first().map(|u| u.file.clone()),
+            message: format!(
+                "{} source \"{}\" ({}) is {:.2} similar to \"{}\" (used {} times); a typo or a fork?",
+                similar.kind,
+                similar.suspect,
+                used.join(", "),
+                similar.score,
+                similar.likely,
+                similar.likely_uses
+            ),
+            fix: format!("use \"{}\" if it is meant to be the same {}", similar.likely, similar.kind),
+        });
+    }
+    Ok(findings)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Prefer {
+    Highest,
+    Lowest,
+}
+
+impl std::str::FromStr for Prefer {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Prefer> {
+        match s {
+            "highest" => Ok(Prefer::Highest),
+            "lowest" => Ok(Prefer::Lowest),
+            _ => Err(anyhow::anyhow!("Expected 'highest' or 'lowest', got '{}'", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Consolidation {
+    pub provider: String,
+    pub kept: PathBuf,
+    pub removed_from: Vec<PathBuf>,
+}
+
+fn minimum_version(requirement: &ProviderRequirement) -> Option<Version> {
+    let constraints = parse_constraints(requirement.version.as_deref()?).ok()?;
+    range_of(&constraints).lower.map(|(version, _)| version)
+}
+
+fn remove_provider_requirement(file: &Path, provider: &str) -> Result<()> {
+    let mut body = read_tf_body(file)?;
+    for terraform in body.get_blocks_mut("terraform") {
+        for required in terraform.body.get_blocks_mut("required_providers") {
+            required.body.remove_attribute(provider);
+        }
+        // Drop required_providers blocks left empty
+        loop {
+            let empty = terraform.body.iter().position(|s| {
+                s.as_block()
+                    .is_some_and(|b| b.ident.as_str() == "required_providers" && b.body.is_empty())
+            });
+            let Some(index) = empty else {
+                break;
+            };
+            terraform.body.remove(index);
+        }
+    }
+    write_tf_body(file, &body)
+}
+
+fn set_provider_source(file: &Path, provider: &str, source: &str) -> Result<()> {
+    let mut body = read_tf_body(file)?;
+    for terraform in body.get_blocks_mut("terraform") {
+        for required in terraform.body.get_blocks_mut("required_providers") {
+            if let Some(mut attr) = required.body.get_attribute_mut(provider)
+                && let Expression::Object(ref mut object) = *attr.value_mut()
+            {
+                crate::map::set_entry(object, "source", Expression::from(source));
+            }
+        }
+    }
+    write_tf_body(file, &body)
+}
+
+#[derive(Debug, Clone)]
+pub struct PinFix {
+    pub file: PathBuf,
+    pub provider: String,
+    pub from: String,
+    pub to: String,
+}
+
+pub fn pin_providers(dir: &Path) -> Result<Vec<PinFix>> {
+    // Rewrite unbounded `>=` provider constraints as pessimistic ones
+    let mut fixes = Vec::new();
+    for file in find_all_tf_files(dir)? {
+        let Ok(mut body) = read_tf_body(&file) else {
+            continue;
+        };
+        let mut changed = Vec::new();
+        for requirement in provider_requirements(&body, &file) {
+            if let Some(from) = requirement.version
+                && let Some(to) = pessimistic_constraint(&from)
+            {
+                changed.push(PinFix { file: file.clone(), provider: requirement.name, from, to });
+            }
+        }
+        if changed.is_empty() {
+            continue;
+        }
+        for terraform in body.get_blocks_mut("terraform") {
+            for required in terraform.body.get_blocks_mut("required_providers") {
+                for fix in &changed {
+                    let Some(mut attr) = required.body.get_attribute_mut(&fix.provider) else {
+                        continue;
+                    };
+                    match *attr.value_mut() {
+                        Expression::Object(ref mut object) => {
+                            crate::map::set_entry(object, "version", Expression::from(fix.to.as_str()))
+                        }
+                        // Legacy shorthand: aws = ">= 5.0"
+                        ref mut value @ Expression::String(_) => {
+                            let mut pinned = Expression::from(fix.to.as_str());
+                            *pinned.decor_mut() = value.decor().clone();
+                            *value = pinned;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+        write_tf_body(&file, &body)?;
+        fixes.extend(changed);
+    }
+    Ok(fixes)
+}
+
+pub fn consolidate_providers(dir: &Path, prefer: Prefer) -> Result<Vec<Consolidation>> {
+    // Keep one required_providers entry per provider and root, chosen by its
+    // minimum allowed version, and remove the entries in the other files
+    let mut consolidations = Vec::new();
+
+    for files in root_modules(dir)?.values() {
+        let mut providers: BTreeMap<String, Vec<ProviderRequirement>> = BTreeMap::new();
+        for file in files {
+            let Ok(body) = read_tf_body(file) else {
+                continue;
+            };
+            for requirement in provider_requirements(&body, file) {
+                providers.entry(requirement.name.clone()).or_default().push(requirement);
+            }
+        }
+
+        for (name, requirements) in providers {
+            let mut declared_in: Vec<&PathBuf> = requirements.iter().map(|r| &r.file).collect();
+            declared_in.dedup();
+            if declared_in.len() < 2 {
+                continue;
+            }
+
+            // Different sources are different providers; that needs a human
+            let mut sources: Vec<&str> =
+                requirements.iter().filter_map(|r| r.source.as_deref()).collect();
+            sources.sort();
+            sources.dedup();
+            if sources.len() > 1 {
+                continue;
+            }
+
+            let mut kept = &requirements[0];
+            for candidate in &requirements[1..] {
+                let better = match (minimum_version(candidate), minimum_version(kept)) {
+                    (Some(_), None) => true,
+                    (Some(a), Some(b)) => match prefer {
+                        Prefer::Highest => a > b,
+                        Prefer::Lowest => a < b,
+                    },
+                    _ => false,
+                };
+                if better {
+                    kept = candidate;
+                }
+            }
+
+            // The kept declaration inherits the source the others agree on
+            if kept.source.is_none()
+                && let Some(source) = sources.first()
+            {
+                set_provider_source(&kept.file, &name, source)?;
+            }
+
+            let mut removed_from = Vec::new();
+            for file in declared_in {
+                if *file != kept.file {
+                    remove_provider_requirement(file, &name)?;
+                    removed_from.push(file.clone());
+                }
+            }
+            consolidations.push(Consolidation {
+                provider: name,
+                kept: kept.file.clone(),
+                removed_from,
+            });
+        }
+    }
+
+    Ok(consolidations)
+}
+
+fn env_credential_hosts() -> Vec<String> {
+    std::env::vars()
+        .filter_map(|(key, _)| key.strip_prefix("TF_TOKEN_").map(|host| host.replace('_', ".")))
+        .collect()
+}
+
+pub fn private_registry_hosts() -> Vec<String> {
+    // Hosts with credentials, other than the public registry; an invalid CLI
+    // configuration is reported by diagnose_environment
+    let mut hosts = env_credential_hosts();
+    if let Some(path) = terraformrc::default_config_path()
+        && let Ok(content) = fs::read_to_string(&path)
+        && let Ok(configured) = terraformrc::credential_hosts(&content)
+    {
+        hosts.extend(configured);
+    }
+    let mut hosts: Vec<String> = hosts
+        .into_iter()
+        .map(|h| h.to_lowercase())
+        .filter(|h| h != registry::DEFAULT_HOST)
+        .collect();
+    hosts.sort();
+    hosts.dedup();
+    hosts
+}
+
+pub fn diagnose_dependency_confusion(
+    dir: &Path,
+    private_hosts: &[String],
+    client: &registry::Client,
+) -> Result<Vec<Finding>> {
+    let mut findings = Vec::new();
+    if private_hosts.is_empty() {
+        return Ok(findings);
+    }
+
+    for file in find_all_tf_files(dir)? {
+        let Ok(body) = read_tf_body(&file) else {
+            continue;
+        };
+        for block in body.get_blocks("module") {
+            let Some(Expression::String(source)) = block.body.get_attribute("source").map(|a| &a.value) else {
+                continue;
+            };
+            // Only sources without a host are ambiguous
+            let Some(public) = ModuleAddress::parse(source.value()) else {
+                continue;
+            };
+            if ModuleSource::parse(source.value()).url.split('/').count() != 3 {
+                continue;
+            }
+            let label = block.labels.first().map(|l| l.as_str()).unwrap_or_default();
+
+            for host in private_hosts {
+                let private = ModuleAddress { host: host.clone(), ..public.clone() };
+                if !client.module_exists(&private)? {
+                    continue;
+                }
+                let short = format!("{}/{}/{}", public.namespace, public.name, public.provider);
+                let (severity, message) = if client.module_exists(&public)? {
+                    (
+                        Severity::Error,
+                        format!(
+                            "module \"{}\": {} installs the public registry's module, not the one {} publishes",
+                            label, short, host
+                        ),
+                    )
+                } else if client.namespace_exists(&public.host, &public.namespace)? {
+                    (
+                        Severity::Warning,
+                        format!(
+                            "module \"{}\": {} is published by {}, and namespace '{}' is taken on the public registry",
+                            label, short, host, public.namespace
+                        ),
+                    )
+                } else {
+                    (
+                        Severity::Warning,
+                        format!(
+                            "module \"{}\": {} is published by {}, but the short form resolves to the public registry, where namespace '{}' is unclaimed",
+                            label, short, host, public.namespace
+                        ),
+                    )
+                };
+                findings.push(Finding {
+                    severity,
+                    check: "dependency-confusion",
+                    file: Some(file.clone()),
+                    message,
+                    fix: format!("use the full address: {}", private),
+                });
+            }
+        }
+    }
+    Ok(findings)
+}
+
+pub fn diagnose_environment() -> Vec<Finding> {
+    let mut findings = Vec::new();
+
+    if Command::new("git").arg("--version").output().is_err() {
+        findings.push(Finding {
+            severity: Severity::Warning,
+            check: "git",
+            file: None,
+            message: "git is not installed or not on PATH".to_string(),
+            fix: "install git; fleet mode clones repositories with it".to_string(),
+        });
+    }
+
+    let mut credentials = env_credential_hosts();
+
+    if let Some(path) = terraformrc::default_config_path()
+        && path.is_file()
+    {
+        let parsed = fs::read_to_string(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|content| {
+                terraformrc::parse_provider_installation(&content)?;
+                terraformrc::credential_hosts(&content)
+            });
+        match parsed {
+            Ok(hosts) => credentials.extend(hosts),
+            Err(e) => findings.push(Finding {
+                severity: Severity::Error,
+                check: "cli-config",
+                file: Some(path),
+                message: format!("Terraform CLI configuration is invalid: {:#}", e),
+                fix: "fix the file; terraform init will reject it too".to_string(),
+            }),
+        }
+    }
+
+    credentials.sort();
+    credentials.dedup();
+    findings.push(Finding {
+        severity: Severity::Info,
+        check: "credentials",
+        file: None,
+        message: if credentials.is_empty() {
+            "no registry credentials configured".to_string()
+        } else {
+            format!("registry credentials for: {}", credentials.join(", "))
+        },
+        fix: "private registries need TF_TOKEN_<host> or a credentials block in ~/.terraformrc".to_string(),
+    });
+
+    let git_token = ["GITHUB_TOKEN", "GH_TOKEN"]
+        .iter()
+        .any(|var| std::env::var_os(var).is_some());
+    if !git_token {
+        findings.push(Finding {
+            severity: Severity::Info,
+            check: "credentials",
+            file: None,
+            message: "no GITHUB_TOKEN or GH_TOKEN set".to_string(),
+            fix: "set a token to avoid GitHub rate limits and reach private repositories".to_string(),
+        });
+    }
+
+    findings
+}
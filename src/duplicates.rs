@@ -0,0 +1,238 @@
+// Attributes defined twice in one block, e.g. `version` on both sides of a
+// resolved merge conflict. Terraform (and hcl-edit) refuse such files, so
+// they are found in the text: a small scanner follows blocks, brackets,
+// strings, templates, heredocs and comments, and records the lines each
+// attribute spans.
+//
+// `tv doctor` reports them, and `tv doctor --fix keep-first|keep-last`
+// deletes the lines of every other definition. A fixed file must parse, or
+// it is left as it was.
+
+use anyhow::{Result, anyhow};
+use regex::Regex;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use crate::{conflict_markers, find_all_tf_files, write_file};
+
+static ATTRIBUTE_START: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\s*([A-Za-z_][A-Za-z0-9_-]*)\s*=([^=]|$)").unwrap());
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Duplicate {
+    pub block: String,  // e.g. module.vpc, or "" at the top level
+    pub key: String,
+    pub lines: Vec<(usize, usize)>,  // 1-based first and last line of each definition
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Keep {
+    First,
+    #[default]
+    Last,
+}
+
+impl std::str::FromStr for Keep {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Keep> {
+        match s {
+            "keep-first" => Ok(Keep::First),
+            "keep-last" => Ok(Keep::Last),
+            _ => Err(anyhow!("Expected 'keep-first' or 'keep-last', got '{}'", s)),
+        }
+    }
+}
+
+enum Frame {
+    Block(usize),  // Index into the block addresses
+    Bracket,  // ( [ { inside an expression
+    Str,
+    Template,  // ${ ... } or %{ ... } inside a string
+}
+
+fn block_address(header: &str) -> String {
+    // `module "vpc"` -> module.vpc
+    header
+        .split_whitespace()
+        .map(|part| part.trim_matches('"'))
+        .collect::<Vec<&str>>()
+        .join(".")
+}
+
+fn heredoc_marker(rest: &str) -> Option<String> {
+    // `<<EOF` or `<<-EOF`, at the position of the `<<`
+    let rest = rest.strip_prefix("<<")?;
+    let rest = rest.strip_prefix('-').unwrap_or(rest);
+    let marker: String = rest.chars().take_while(|c| c.is_ascii_alphanumeric() || *c == '_').collect();
+    (!marker.is_empty()).then_some(marker)
+}
+
+pub fn find_duplicates(content: &str) -> Vec<Duplicate> {
+    // Both sides of an unresolved conflict define everything twice
+    if !conflict_markers(content).is_empty() {
+        return Vec::new();
+    }
+    let mut addresses = vec![String::new()];
+    let mut frames = vec![Frame::Block(0)];
+    let mut open: Option<(usize, String, usize)> = None;  // Block, key, first line
+    let mut attributes: Vec<(usize, String, usize, usize)> = Vec::new();
+    let mut heredoc: Option<String> = None;
+    let mut in_comment = false;
+
+    for (number, line) in content.split('\n').enumerate() {
+        let line = line.trim_end_matches('\r');
+        if let Some(ref marker) = heredoc {
+            if line.trim() == marker {
+                heredoc = None;
+            }
+        } else {
+            let at_block = matches!(frames.last(), Some(Frame::Block(_)));
+            if at_block
+                && open.is_none()
+                && !in_comment
+                && let Some(caps) = ATTRIBUTE_START.captures(line)
+                && let Some(Frame::Block(block)) = frames.last()
+            {
+                open = Some((*block, caps[1].to_string(), number));
+            }
+
+            let chars: Vec<char> = line.chars().collect();
+            let mut i = 0;
+            while i < chars.len() {
+                let c = chars[i];
+                let next = chars.get(i + 1).copied();
+                if in_comment {
+                    if c == '*' && next == Some('/') {
+                        in_comment = false;
+                        i += 1;
+                    }
+                    i += 1;
+                    continue;
+                }
+                match frames.last() {
+                    Some(Frame::Str) => match (c, next) {
+                        ('\\', _) => i += 1,
+                        ('"', _) => {
+                            frames.pop();
+                        }
+                        ('$' | '%', Some('{')) => {
+                            frames.push(Frame::Template);
+                            i += 1;
+                        }
+                        _ => {}
+                    },
+                    _ => match (c, next) {
+                        ('"', _) => frames.push(Frame::Str),
+                        ('#', _) | ('/', Some('/')) => break,
+                        ('/', Some('*')) => {
+                            in_comment = true;
+                            i += 1;
+                        }
+                        ('<', Some('<')) => {
+                            let rest: String = chars[i..].iter().collect();
+                            if let Some(marker) = heredoc_marker(&rest) {
+                                heredoc = Some(marker);
+                                break;
+                            }
+                            i += 1;
+                        }
+                        ('{', _) if matches!(frames.last(), Some(Frame::Block(_))) && open.is_none() => {
+                            let header: String = chars[..i].iter().collect();
+                            let parent = match frames.last() {
+                                Some(Frame::Block(parent)) => addresses[*parent].clone(),
+                                _ => String::new(),
+                            };
+                            let own = block_address(&header);
+                            addresses.push(if parent.is_empty() { own } else { format!("{}.{}", parent, own) });
+                            frames.push(Frame::Block(addresses.len() - 1));
+                        }
+                        ('{' | '(' | '[', _) => frames.push(Frame::Bracket),
+                        ('}' | ')' | ']', _) if frames.len() > 1 => {
+                            frames.pop();
+                        }
+                        _ => {}
+                    },
+                }
+                i += 1;
+            }
+        }
+
+        // An attribute ends with the line that returns to its block
+        if heredoc.is_none()
+            && let Some((block, _, _)) = open
+            && matches!(frames.last(), Some(Frame::Block(b)) if *b == block)
+        {
+            let (block, key, first) = open.take().unwrap();
+            attributes.push((block, key, first, number));
+        }
+    }
+
+    let mut duplicates: Vec<(usize, Duplicate)> = Vec::new();
+    for (block, key, first, last) in attributes {
+        match duplicates.iter_mut().find(|(b, d)| *b == block && d.key == key) {
+            Some((_, duplicate)) => duplicate.lines.push((first + 1, last + 1)),
+            None => duplicates.push((
+                block,
+                Duplicate { block: addresses[block].clone(), key, lines: vec![(first + 1, last + 1)] },
+            )),
+        }
+    }
+    duplicates.into_iter().map(|(_, d)| d).filter(|d| d.lines.len() > 1).collect()
+}
+
+pub fn remove_duplicates(content: &str, keep: Keep) -> Result<String> {
+    let fixed = strip_duplicates(content, keep);
+    fixed
+        .parse::<hcl_edit::structure::Body>()
+        .map_err(|e| anyhow!("Still invalid after removing duplicate attributes: {}", e))?;
+    Ok(fixed)
+}
+
+pub(crate) fn strip_duplicates(content: &str, keep: Keep) -> String {
+    // The content without the other definitions, whether it parses or not
+    let duplicates = find_duplicates(content);
+    let mut removed = Vec::new();
+    for duplicate in &duplicates {
+        let kept = match keep {
+            Keep::First => 0,
+            Keep::Last => duplicate.lines.len() - 1,
+        };
+        for (i, (first, last)) in duplicate.lines.iter().enumerate() {
+            if i != kept {
+                removed.extend(*first..=*last);
+            }
+        }
+    }
+    content
+        .split_inclusive('\n')
+        .enumerate()
+        .filter(|(i, _)| !removed.contains(&(i + 1)))
+        .map(|(_, line)| line)
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DuplicateFix {
+    pub file: PathBuf,
+    pub duplicates: Vec<Duplicate>,
+}
+
+pub fn fix_duplicates(dir: &Path, keep: Keep) -> Result<Vec<DuplicateFix>> {
+    let mut fixed = Vec::new();
+    for file in find_all_tf_files(dir)? {
+        let Ok(content) = fs::read_to_string(&file) else {
+            continue;
+        };
+        let duplicates = find_duplicates(&content);
+        if duplicates.is_empty() {
+            continue;
+        }
+        let Ok(repaired) = remove_duplicates(&content, keep) else {
+            continue;
+        };
+        write_file(&file, &repaired)?;
+        fixed.push(DuplicateFix { file, duplicates });
+    }
+    Ok(fixed)
+}
@@ -0,0 +1,210 @@
+// `tv effective module.vpc --dir envs/prod`: a block as Terraform sees it
+// once the directory's override files are merged in.
+//
+// Terraform reads override.tf and *_override.tf after the other files of a
+// directory, each in lexical order, and merges every top-level block of an
+// override file into the block with the same type and label: an attribute
+// replaces the one of the same name, and nested blocks replace all nested
+// blocks of their type. Two kinds merge finer: locals per name, and
+// `terraform { required_providers { ... } }` per provider.
+//
+// `tv set` checks this before writing, since editing a value an override
+// replaces changes nothing Terraform will use. `--on-override` (or
+// `on_override` in tv.toml) decides what happens then: `warn` (the default)
+// edits the file anyway with a warning, `edit` edits the override file
+// instead, and `fail` refuses.
+
+use anyhow::{Result, anyhow};
+use hcl_edit::structure::{Block, Body};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::load_config;
+use crate::paths::resolve;
+use crate::query::{Query, block_matches, parse_scan_query};
+use crate::{directory_of, find_tf_file, parse_query, read_tf_body};
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum OnOverride {
+    #[default]
+    Warn,
+    Edit,
+    Fail,
+}
+
+impl std::str::FromStr for OnOverride {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<OnOverride> {
+        match s {
+            "warn" => Ok(OnOverride::Warn),
+            "edit" => Ok(OnOverride::Edit),
+            "fail" => Ok(OnOverride::Fail),
+            _ => Err(anyhow!("Expected 'warn', 'edit' or 'fail', got '{}'", s)),
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for OnOverride {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<OnOverride, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        text.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Definition {
+    pub name: String,  // Attribute, nested block type, or required_providers.<name>
+    pub value: String,  // As written
+    pub nested: bool,  // A nested block; value holds all of them
+    pub file: PathBuf,
+    pub overridden: Vec<(PathBuf, String)>,  // Earlier definitions it replaced
+}
+
+pub fn is_override_file(path: &Path) -> bool {
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+    name == "override.tf" || name.ends_with("_override.tf")
+}
+
+fn directory_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    // One directory is one module: primary files first, then overrides
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file() && path.extension().and_then(|e| e.to_str()) == Some("tf"))
+        .collect();
+    files.sort_by_key(|path| (is_override_file(path), path.clone()));
+    Ok(files)
+}
+
+fn definitions(block: &Block) -> Vec<(String, String, bool)> {
+    // Name, value, whether it is a nested block
+    let mut found = Vec::new();
+    for structure in block.body.iter() {
+        if let Some(attr) = structure.as_attribute() {
+            found.push((attr.key.as_str().to_string(), attr.value.to_string().trim().to_string(), false));
+        } else if let Some(nested) = structure.as_block() {
+            let ident = nested.ident.as_str();
+            if block.ident.as_str() == "terraform" && ident == "required_providers" {
+                for entry in nested.body.attributes() {
+                    let name = format!("required_providers.{}", entry.key.as_str());
+                    found.push((name, entry.value.to_string().trim().to_string(), false));
+                }
+            } else {
+                let text = Body::builder().block(nested.clone()).build().to_string();
+                found.push((ident.to_string(), text.trim().to_string(), true));
+            }
+        }
+    }
+    found
+}
+
+pub fn effective(dir: &Path, query: &Query) -> Result<Vec<Definition>> {
+    let mut merged: Vec<Definition> = Vec::new();
+    for file in directory_files(dir)? {
+        let Ok(body) = read_tf_body(&file) else {
+            continue;
+        };
+        let overriding = is_override_file(&file);
+        for block in body.blocks().filter(|b| block_matches(b, query)) {
+            let mut replaced_blocks: Vec<String> = Vec::new();
+            for (name, value, nested) in definitions(block) {
+                // Several nested blocks of a type add up, until an override replaces them all
+                let accumulate = nested && (!overriding || replaced_blocks.contains(&name));
+                match merged.iter().position(|d| d.name == name) {
+                    Some(pos) if accumulate => {
+                        let definition = &mut merged[pos];
+                        definition.value = format!("{}\n{}", definition.value, value);
+                    }
+                    Some(pos) => {
+                        let definition = &mut merged[pos];
+                        let previous = std::mem::replace(&mut definition.value, value);
+                        let previous_file = std::mem::replace(&mut definition.file, file.clone());
+                        definition.overridden.push((previous_file, previous));
+                        if nested {
+                            replaced_blocks.push(name);
+                        }
+                    }
+                    None => {
+                        if nested {
+                            replaced_blocks.push(name.clone());
+                        }
+                        merged.push(Definition { name, value, nested, file: file.clone(), overridden: Vec::new() });
+                    }
+                }
+            }
+        }
+    }
+    if let Some(ref attribute) = query.attribute {
+        // terraform.required_providers means every provider entry
+        let key = definition_key(query).unwrap_or_else(|| attribute.clone());
+        let prefix = format!("{}.", key);
+        merged.retain(|d| d.name == key || d.name.starts_with(&prefix));
+    }
+    Ok(merged)
+}
+
+pub fn effective_block(query: &str, dir: &Path) -> Result<Vec<Definition>> {
+    let parsed = parse_scan_query(query)?;
+    if query.contains('*') {
+        return Err(anyhow!("effective needs one block, not a wildcard: {}", query));
+    }
+    effective(dir, &parsed)
+}
+
+fn definition_key(query: &Query) -> Option<String> {
+    // What an override replaces when the query's value changes
+    match query.nested_blocks.as_slice() {
+        [first, provider, ..] if query.block_type == "terraform" && first == "required_providers" => {
+            Some(format!("required_providers.{}", provider))
+        }
+        [first] if query.block_type == "terraform" && first == "required_providers" => {
+            Some(format!("required_providers.{}", query.attribute_name()))
+        }
+        [first, ..] => Some(first.clone()),
+        [] => query.attribute.clone(),
+    }
+}
+
+pub fn overridden_by(query: &str, file: Option<&Path>) -> Result<Option<PathBuf>> {
+    // The override file that replaces the value `tv set` would write, if any
+    let parsed = parse_query(query)?;
+    let file = find_tf_file(file)?;
+    if is_override_file(&file) {
+        return Ok(None);
+    }
+    let Some(key) = definition_key(&parsed) else {
+        return Ok(None);
+    };
+    let dir = directory_of(&file);
+    let mut block_query = parsed.clone();
+    block_query.nested_blocks.clear();
+    block_query.attribute = None;
+    block_query.index = None;
+    Ok(effective(dir, &block_query)?
+        .into_iter()
+        .find(|d| d.name == key && is_override_file(&d.file))
+        .map(|d| d.file))
+}
+
+pub fn set_target(query: &str, file: Option<&Path>, on_override: Option<OnOverride>) -> Result<(PathBuf, Option<PathBuf>)> {
+    // The file `tv set` should edit, and the override that keeps the value
+    // from taking effect when it edits that file anyway
+    let file = find_tf_file(file)?;
+    let Some(by) = overridden_by(query, Some(&file))? else {
+        return Ok((file, None));
+    };
+    let on_override = match on_override {
+        Some(on_override) => on_override,
+        None => load_config(&resolve(directory_of(&file)))?.on_override.unwrap_or_default(),
+    };
+    match on_override {
+        OnOverride::Warn => Ok((file, Some(by))),
+        OnOverride::Edit => Ok((by, None)),
+        OnOverride::Fail => Err(anyhow!(
+            "{} is overridden in {:?}, so changing it in {:?} has no effect; use --on-override edit to change the override",
+            query,
+            by,
+            file
+        )),
+    }
+}
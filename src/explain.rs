@@ -0,0 +1,137 @@
+// `tv explain`: show how a scan query was parsed and why blocks did or
+// did not match it, so zero-match queries can be debugged.
+
+use anyhow::Result;
+use hcl_edit::structure::Body;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::query::{block_takes_label, block_takes_name};
+use crate::{BlockMatch, ScanOptions, ScanQuery, find_all_tf_files, match_block, parse_scan_query};
+
+#[derive(Debug)]
+pub struct NonMatch {
+    pub file: PathBuf,
+    pub block: String,
+    pub reason: BlockMatch,
+}
+
+#[derive(Debug)]
+pub struct ExplainReport {
+    pub query: ScanQuery,
+    pub files_scanned: usize,
+    pub files_matched: usize,
+    pub unparsable: Vec<(PathBuf, String)>,
+    pub blocks_considered: usize,  // Blocks with the query's block type
+    pub blocks_matched: usize,
+    pub non_matches: Vec<NonMatch>,
+}
+
+pub fn explain_scan(query: &str, dir: &Path, options: &ScanOptions) -> Result<ExplainReport> {
+    let scan_query = parse_scan_query(query)?;
+    let tf_files = find_all_tf_files(dir)?;
+
+    let mut report = ExplainReport {
+        query: scan_query,
+        files_scanned: tf_files.len(),
+        files_matched: 0,
+        unparsable: Vec::new(),
+        blocks_considered: 0,
+        blocks_matched: 0,
+        non_matches: Vec::new(),
+    };
+
+    for file_path in tf_files {
+        // Unlike scan, a broken file is part of the explanation rather than an error
+        let body: Body = match fs::read_to_string(&file_path)
+            .map_err(anyhow::Error::from)
+            .and_then(|content| content.parse().map_err(anyhow::Error::from))
+        {
+            Ok(body) => body,
+            Err(e) => {
+                report.unparsable.push((file_path, e.to_string()));
+                continue;
+            }
+        };
+
+        let mut file_matched = false;
+        for block in body.iter().filter_map(|s| s.as_block()) {
+            let outcome = match_block(block, &report.query, options)?;
+            if outcome == BlockMatch::WrongBlockType {
+                continue;
+            }
+
+            report.blocks_considered += 1;
+            if let BlockMatch::Matched(_) = outcome {
+                report.blocks_matched += 1;
+                file_matched = true;
+            } else {
+                let mut address = block.ident.as_str().to_string();
+                for label in block.labels.iter() {
+                    address.push('.');
+                    address.push_str(label.as_str());
+                }
+                report.non_matches.push(NonMatch {
+                    file: file_path.clone(),
+                    block: address,
+                    reason: outcome,
+                });
+            }
+        }
+
+        if file_matched {
+            report.files_matched += 1;
+        }
+    }
+
+    Ok(report)
+}
+
+pub fn describe_scan_query(query: &ScanQuery) -> String {
+    let nested = if query.nested_blocks.is_empty() {
+        "(none)".to_string()
+    } else {
+        query.nested_blocks.join(" > ")
+    };
+    let filter = match (&query.filter, &query.index, query.negated) {
+        (Some(f), _, false) if f.attribute.is_empty() => format!("value == \"{}\"", f.value),
+        (Some(f), _, true) if f.attribute.is_empty() => format!("value != \"{}\"", f.value),
+        (Some(f), _, false) => format!("{} == \"{}\"", f.attribute, f.value),
+        (Some(f), _, true) => format!("{} != \"{}\"", f.attribute, f.value),
+        (None, Some(index), false) => format!("[\"{}\"] exists", index),
+        (None, Some(index), true) => format!("[\"{}\"] is absent", index),
+        (None, None, _) => "(none)".to_string(),
+    };
+
+    let mut out = String::new();
+    out.push_str(&format!("  block type:  {}\n", query.block_type));
+    let label = match query.block_label {
+        Some(ref label) => label.as_str(),
+        None if block_takes_label(&query.block_type) => "* (any)",
+        None => "(none)",
+    };
+    out.push_str(&format!("  label:       {}\n", label));
+    if block_takes_name(&query.block_type) {
+        out.push_str(&format!("  name:        {}\n", query.block_name.as_deref().unwrap_or("* (any)")));
+    }
+    out.push_str(&format!("  nested path: {}\n", nested));
+    out.push_str(&format!(
+        "  attribute:   {}\n",
+        query.attribute.as_deref().unwrap_or("(none)")
+    ));
+    out.push_str(&format!("  filter:      {}\n", filter));
+    out
+}
+
+pub fn describe_miss(reason: &BlockMatch) -> String {
+    match reason {
+        BlockMatch::Matched(_) => "matched".to_string(),
+        BlockMatch::WrongBlockType => "different block type".to_string(),
+        BlockMatch::WrongLabel(Some(label)) => format!("label is '{}'", label),
+        BlockMatch::WrongLabel(None) => "block has no label".to_string(),
+        BlockMatch::MissingNestedBlock(name) => format!("missing nested block '{}'", name),
+        BlockMatch::MissingAttribute(name) => format!("missing attribute '{}'", name),
+        BlockMatch::FilterMismatch(Some(value)) => format!("filter mismatch (value is \"{}\")", value),
+        BlockMatch::FilterMismatch(None) => "filter mismatch (value not present)".to_string(),
+    }
+}
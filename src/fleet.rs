@@ -0,0 +1,298 @@
+// Fleet mode: run tv queries across a list of git repositories.
+//
+// Repositories are declared in a YAML file:
+//
+// repos:
+//   - url: https://github.com/org/infra.git
+//     name: infra        # optional, derived from the url
+//     ref: main          # optional, defaults to the remote HEAD
+//
+// Each repository is cloned (or fetched, if already present) into a
+// workspace cache directory and the query runs against the checkout.
+// Checkouts are shallow and sparse by default so that only .tf files (and
+// tv.toml, for check-updates' track rules) are materialized, which keeps
+// multi-hundred-repo caches small.
+
+use anyhow::{Context, Result, anyhow};
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::config::{CONFIG_FILE, Config, load_config};
+use crate::updates::{Update, UpdateOptions, check_updates};
+use crate::{ScanQuery, scan_files};
+
+#[derive(Debug, Deserialize)]
+pub struct FleetFile {
+    pub repos: Vec<FleetRepo>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FleetRepo {
+    pub url: String,
+    pub name: Option<String>,
+    #[serde(rename = "ref")]
+    pub git_ref: Option<String>,
+}
+
+impl FleetRepo {
+    pub fn display_name(&self) -> String {
+        if let Some(ref name) = self.name {
+            return name.clone();
+        }
+        repo_name_from_url(&self.url)
+    }
+}
+
+// Files materialized in sparse checkouts (gitignore-style patterns)
+pub const SPARSE_PATTERNS: &[&str] = &["*.tf", CONFIG_FILE];
+
+#[derive(Debug, Clone)]
+pub struct SyncOptions {
+    pub depth: Option<u32>,  // None means full history
+    pub sparse: bool,
+}
+
+impl Default for SyncOptions {
+    fn default() -> Self {
+        SyncOptions {
+            depth: Some(1),
+            sparse: true,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct FleetScanResult {
+    pub repo: String,
+    pub matches: Result<Vec<(PathBuf, String)>>,
+}
+
+#[derive(Debug)]
+pub struct FleetUpdatesResult {
+    pub repo: String,
+    pub updates: Result<Vec<Update>>,
+}
+
+pub fn load_repos(path: &Path) -> Result<Vec<FleetRepo>> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read repos file: {:?}", path))?;
+    let fleet: FleetFile = serde_yaml::from_str(&content)
+        .with_context(|| format!("Failed to parse repos file: {:?}", path))?;
+
+    // Two repos resolving to the same name would share a checkout
+    let mut seen = std::collections::HashSet::new();
+    for repo in &fleet.repos {
+        let name = repo.display_name();
+        check_name(&name).with_context(|| format!("In {:?}", path))?;
+        if !seen.insert(name.clone()) {
+            return Err(anyhow!(
+                "Duplicate repository name '{}' in {:?}; set an explicit name",
+                name,
+                path
+            ));
+        }
+    }
+
+    Ok(fleet.repos)
+}
+
+pub fn check_name(name: &str) -> Result<()> {
+    // The name is the checkout's directory in the cache; `../infra` would
+    // have git force-checkout and sparsify a repository outside it
+    if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\']) || Path::new(name).is_absolute() {
+        return Err(anyhow!("Invalid repository name {:?}; set a plain directory name with name:", name));
+    }
+    Ok(())
+}
+
+pub fn repo_name_from_url(url: &str) -> String {
+    // https://github.com/org/infra.git -> infra
+    // git@github.com:org/infra.git -> infra
+    let trimmed = url.trim_end_matches('/');
+    let last = trimmed
+        .rsplit(['/', ':'])
+        .next()
+        .unwrap_or(trimmed);
+    last.trim_end_matches(".git").to_string()
+}
+
+pub fn default_cache_dir() -> PathBuf {
+    if let Some(xdg) = std::env::var_os("XDG_CACHE_HOME") {
+        return PathBuf::from(xdg).join("tv").join("fleet");
+    }
+    if let Some(home) = std::env::var_os("HOME") {
+        return PathBuf::from(home).join(".cache").join("tv").join("fleet");
+    }
+    std::env::temp_dir().join("tv").join("fleet")
+}
+
+fn run_git(args: &[&str], cwd: Option<&Path>) -> Result<()> {
+    let mut cmd = Command::new("git");
+    if let Some(dir) = cwd {
+        cmd.current_dir(dir);
+    }
+    let output = crate::cancel::output(cmd.args(args))
+        .with_context(|| "Failed to run git; is it installed?")?;
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+pub fn sync_repo(repo: &FleetRepo, cache_dir: &Path, options: &SyncOptions) -> Result<PathBuf> {
+    let name = repo.display_name();
+    check_name(&name)?;
+    let checkout = cache_dir.join(&name);
+    if checkout.parent() != Some(cache_dir) {
+        return Err(anyhow!("Refusing {}: its checkout {:?} is not in {:?}", name, checkout, cache_dir));
+    }
+    if repo.git_ref.as_deref().is_some_and(|r| r.starts_with('-')) {
+        // No branch or tag starts with '-'; this is an option in disguise
+        return Err(anyhow!("Refusing {}: its ref starts with '-'", name));
+    }
+    let depth_arg = options.depth.map(|d| format!("--depth={}", d));
+
+    if !checkout.join(".git").is_dir() {
+        fs::create_dir_all(cache_dir)
+            .with_context(|| format!("Failed to create cache directory: {:?}", cache_dir))?;
+        let checkout_str = checkout.to_string_lossy().to_string();
+
+        let mut clone_args = vec!["clone", "--quiet", "--no-checkout"];
+        if let Some(ref depth) = depth_arg {
+            clone_args.push(depth);
+        }
+        if options.sparse {
+            // Skip downloading blobs we are never going to check out
+            clone_args.push("--filter=blob:none");
+        }
+        clone_args.extend_from_slice(&["--", &repo.url, &checkout_str]);
+        run_git(&clone_args, None)?;
+    }
+
+    if options.sparse {
+        let mut sparse_args = vec!["sparse-checkout", "set", "--no-cone"];
+        sparse_args.extend_from_slice(SPARSE_PATTERNS);
+        run_git(&sparse_args, Some(&checkout))?;
+    } else {
+        run_git(&["sparse-checkout", "disable"], Some(&checkout))?;
+    }
+
+    // Fetch and move to the requested ref, for fresh and existing checkouts alike
+    let git_ref = repo.git_ref.as_deref().unwrap_or("HEAD");
+    let mut fetch_args = vec!["fetch", "--quiet"];
+    if let Some(ref depth) = depth_arg {
+        fetch_args.push(depth);
+    }
+    fetch_args.extend_from_slice(&["--", "origin", git_ref]);
+    run_git(&fetch_args, Some(&checkout))?;
+    run_git(&["checkout", "--quiet", "--force", "FETCH_HEAD"], Some(&checkout))?;
+
+    Ok(checkout)
+}
+
+pub fn evict_cache(repos: &[FleetRepo], cache_dir: &Path, all: bool) -> Result<Vec<PathBuf>> {
+    // Remove checkouts that are no longer listed in the repos file,
+    // or every checkout when `all` is set
+    let mut removed = Vec::new();
+    if !cache_dir.is_dir() {
+        return Ok(removed);
+    }
+
+    let listed: std::collections::HashSet<String> =
+        repos.iter().map(|r| r.display_name()).collect();
+
+    for entry in fs::read_dir(cache_dir)? {
+        let path = entry?.path();
+        if !path.join(".git").is_dir() {
+            // Not something we created; leave it alone
+            continue;
+        }
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        if all || !listed.contains(&name) {
+            fs::remove_dir_all(&path)
+                .with_context(|| format!("Failed to remove checkout: {:?}", path))?;
+            removed.push(path);
+        }
+    }
+
+    removed.sort();
+    Ok(removed)
+}
+
+pub fn fleet_scan(
+    repos: &[FleetRepo],
+    cache_dir: &Path,
+    options: &SyncOptions,
+    query: &str,
+) -> Vec<FleetScanResult> {
+    let mut results = Vec::new();
+
+    for repo in repos {
+        let matches = sync_repo(repo, cache_dir, options).and_then(|checkout| {
+            let found = scan_files(query, &checkout)?;
+            // Report paths relative to the repository root
+            Ok(found
+                .into_iter()
+                .map(|(path, name)| {
+                    let relative = path
+                        .strip_prefix(&checkout)
+                        .map(|p| p.to_path_buf())
+                        .unwrap_or(path);
+                    (relative, name)
+                })
+                .collect())
+        });
+
+        results.push(FleetScanResult {
+            repo: repo.display_name(),
+            matches,
+        });
+    }
+
+    results
+}
+
+pub fn fleet_check_updates(
+    repos: &[FleetRepo],
+    cache_dir: &Path,
+    options: &SyncOptions,
+    query: &ScanQuery,
+    update_options: &UpdateOptions,
+) -> Vec<FleetUpdatesResult> {
+    let mut results = Vec::new();
+
+    for repo in repos {
+        let updates = sync_repo(repo, cache_dir, options).and_then(|checkout| {
+            // The repository's own tv.toml, not one above the cache directory
+            let config = if checkout.join(CONFIG_FILE).is_file() {
+                load_config(&checkout)?
+            } else {
+                Config::default()
+            };
+            let mut updates = check_updates(&checkout, query, &config, update_options)?;
+            for update in &mut updates {
+                if let Ok(relative) = update.file.strip_prefix(&checkout) {
+                    update.file = relative.to_path_buf();
+                }
+            }
+            Ok(updates)
+        });
+
+        results.push(FleetUpdatesResult {
+            repo: repo.display_name(),
+            updates,
+        });
+    }
+
+    results
+}
@@ -0,0 +1,167 @@
+// `tv graph`: how the directories of a repository depend on each other,
+// for plain Terraform and for terragrunt live repositories alike.
+//
+// Edges come from
+//
+// - module blocks with a local source (`source = "../modules/vpc"`);
+// - terragrunt.hcl files: `terraform { source = "../modules//vpc" }` with a
+//   local source, `dependency "vpc" { config_path = "../vpc" }` and
+//   `dependencies { paths = ["../vpc", "../rds"] }`.
+//
+// Only literal paths are followed; `${get_terragrunt_dir()}/../vpc` and
+// other templates are left out. Directories are shown relative to the
+// directory graphed: `.` for itself, `../modules/vpc` outside it. Terragrunt copies its configuration
+// into .terragrunt-cache, and Terraform modules into .terraform, so files
+// there are skipped.
+
+use anyhow::{Result, anyhow};
+use hcl_edit::expr::Expression;
+use hcl_edit::structure::Body;
+use std::path::{Component, Path, PathBuf};
+
+use crate::paths::{relative_to, resolve};
+use crate::query::quote_segment;
+use crate::{find_files, read_tf_body};
+
+pub const TERRAGRUNT_FILE: &str = "terragrunt.hcl";
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum GraphFormat {
+    #[default]
+    Text,
+    Dot,
+}
+
+impl std::str::FromStr for GraphFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "text" => Ok(GraphFormat::Text),
+            "dot" => Ok(GraphFormat::Dot),
+            _ => Err(anyhow!("Unknown format '{}' (expected text or dot)", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EdgeKind {
+    Module,  // A local module source, in .tf or terragrunt.hcl
+    Dependency,  // A terragrunt dependency or dependencies block
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Edge {
+    pub from: String,
+    pub to: String,
+    pub kind: EdgeKind,
+    pub via: String,  // e.g. module.vpc, dependency.vpc, dependencies
+    pub file: PathBuf,
+}
+
+fn skipped(path: &Path) -> bool {
+    path.components()
+        .any(|c| matches!(c, Component::Normal(name) if name == ".terragrunt-cache" || name == ".terraform"))
+}
+
+fn local_path(source: &str) -> Option<String> {
+    // Terragrunt separates the subdirectory with // too
+    (source.starts_with("./") || source.starts_with("../")).then(|| source.replace("//", "/"))
+}
+
+fn string(expr: Option<&Expression>) -> Option<&str> {
+    match expr {
+        Some(Expression::String(s)) => Some(s.value()),
+        _ => None,
+    }
+}
+
+fn terragrunt_targets(body: &Body) -> Vec<(EdgeKind, String, String)> {
+    // (kind, via, path as written)
+    let mut targets = Vec::new();
+    for block in body.get_blocks("terraform") {
+        if let Some(source) = string(block.body.get_attribute("source").map(|a| &a.value)).and_then(local_path) {
+            targets.push((EdgeKind::Module, "terraform.source".to_string(), source));
+        }
+    }
+    for block in body.get_blocks("dependency") {
+        let name = block.labels.first().map(|l| l.as_str()).unwrap_or_default();
+        if let Some(path) = string(block.body.get_attribute("config_path").map(|a| &a.value)) {
+            targets.push((EdgeKind::Dependency, format!("dependency.{}", quote_segment(name)), path.to_string()));
+        }
+    }
+    for block in body.get_blocks("dependencies") {
+        if let Some(Expression::Array(paths)) = block.body.get_attribute("paths").map(|a| &a.value) {
+            for path in paths.iter().filter_map(|p| string(Some(p))) {
+                targets.push((EdgeKind::Dependency, "dependencies".to_string(), path.to_string()));
+            }
+        }
+    }
+    targets
+}
+
+fn module_targets(body: &Body) -> Vec<(EdgeKind, String, String)> {
+    body.get_blocks("module")
+        .filter_map(|block| {
+            let source = string(block.body.get_attribute("source").map(|a| &a.value)).and_then(local_path)?;
+            let label = block.labels.first().map(|l| l.as_str()).unwrap_or_default();
+            Some((EdgeKind::Module, format!("module.{}", quote_segment(label)), source))
+        })
+        .collect()
+}
+
+fn node(root: &Path, dir: &Path) -> String {
+    // Relative to the directory graphed: ., envs/prod or ../modules/vpc
+    let relative = relative_to(dir, root);
+    let relative = relative.strip_prefix(".").unwrap_or(&relative);
+    if relative.as_os_str().is_empty() { ".".to_string() } else { relative.display().to_string() }
+}
+
+pub fn dependency_graph(dir: &Path) -> Result<Vec<Edge>> {
+    let root = resolve(dir);
+    let files = find_files(dir, &|path| {
+        path.extension().and_then(|s| s.to_str()) == Some("tf")
+            || path.file_name().and_then(|s| s.to_str()) == Some(TERRAGRUNT_FILE)
+    })?;
+
+    let mut edges = Vec::new();
+    for file in files.into_iter().filter(|f| !skipped(f.strip_prefix(dir).unwrap_or(f))) {
+        let body = read_tf_body(&file)?;
+        let targets = if file.file_name().and_then(|s| s.to_str()) == Some(TERRAGRUNT_FILE) {
+            terragrunt_targets(&body)
+        } else {
+            module_targets(&body)
+        };
+        let from = resolve(crate::directory_of(&file));
+        for (kind, via, path) in targets {
+            edges.push(Edge {
+                from: node(&root, &from),
+                to: node(&root, &resolve(&from.join(path.trim_end_matches('/')))),
+                kind,
+                via,
+                file: file.clone(),
+            });
+        }
+    }
+    edges.sort_by(|a, b| (&a.from, &a.to, &a.via).cmp(&(&b.from, &b.to, &b.via)));
+    edges.dedup_by(|a, b| (&a.from, &a.to, &a.via) == (&b.from, &b.to, &b.via));
+    Ok(edges)
+}
+
+pub fn render(edges: &[Edge], format: GraphFormat) -> String {
+    match format {
+        GraphFormat::Text => edges.iter().map(|e| format!("{} -> {} ({})\n", e.from, e.to, e.via)).collect(),
+        GraphFormat::Dot => {
+            // Module edges dashed, so stack dependencies stand out
+            let mut out = "digraph tv {\n".to_string();
+            for edge in edges {
+                let style = match edge.kind {
+                    EdgeKind::Module => ", style=dashed",
+                    EdgeKind::Dependency => "",
+                };
+                out.push_str(&format!("  {:?} -> {:?} [label={:?}{}];\n", edge.from, edge.to, edge.via, style));
+            }
+            out + "}\n"
+        }
+    }
+}
@@ -0,0 +1,128 @@
+// Free-text search over attribute values, reported by structural address.
+//
+// `tv grep terraform-aws-vpc` finds every attribute value containing the
+// text and reports where it lives the way a query would address it
+// (`module.vpc.source`, `terraform.required_providers.aws.source`) along
+// with file and line, instead of the raw matching line. Object values are
+// searched key by key so the address points at the innermost value.
+
+use anyhow::{Context, Result};
+use hcl_edit::Span;
+use hcl_edit::expr::Expression;
+use hcl_edit::structure::{Block, Body};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::find_all_tf_files;
+use crate::index::line_of;
+use crate::map::key_name;
+use crate::query::{block_takes_label, quote_segment};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GrepMatch {
+    pub file: PathBuf,
+    pub line: usize,
+    pub address: String,  // e.g. module.vpc.source
+    pub value: String,  // Raw HCL of the matching value
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct GrepOptions {
+    pub ignore_case: bool,
+}
+
+fn contains(haystack: &str, needle: &str, options: &GrepOptions) -> bool {
+    if options.ignore_case {
+        haystack.to_lowercase().contains(&needle.to_lowercase())
+    } else {
+        haystack.contains(needle)
+    }
+}
+
+fn search_value(
+    value: &Expression,
+    address: &str,
+    pattern: &str,
+    content: &str,
+    options: &GrepOptions,
+    found: &mut Vec<(usize, String, String)>,
+) {
+    if let Expression::Object(object) = value {
+        for (key, entry) in object.iter() {
+            let Some(name) = key_name(key) else {
+                continue;
+            };
+            let address = format!("{}.{}", address, quote_segment(&name));
+            search_value(entry.expr(), &address, pattern, content, options, found);
+        }
+        return;
+    }
+
+    let raw = value.to_string().trim().to_string();
+    if contains(&raw, pattern, options) {
+        let line = value.span().map(|s| line_of(content, s.start)).unwrap_or(0);
+        found.push((line, address.to_string(), raw));
+    }
+}
+
+fn search_block(
+    block: &Block,
+    address: &str,
+    pattern: &str,
+    content: &str,
+    options: &GrepOptions,
+    found: &mut Vec<(usize, String, String)>,
+) {
+    for structure in block.body.iter() {
+        if let Some(attr) = structure.as_attribute() {
+            let address = format!("{}.{}", address, quote_segment(attr.key.as_str()));
+            search_value(&attr.value, &address, pattern, content, options, found);
+        } else if let Some(nested) = structure.as_block() {
+            // Nested blocks are addressed by type, or by label (dynamic "tag")
+            let name = nested.labels.first().map(|l| l.as_str()).unwrap_or(nested.ident.as_str());
+            let address = format!("{}.{}", address, quote_segment(name));
+            search_block(nested, &address, pattern, content, options, found);
+        }
+    }
+}
+
+pub fn grep_file(path: &Path, pattern: &str, options: &GrepOptions) -> Result<Vec<GrepMatch>> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read file: {:?}", path))?;
+    if !contains(&content, pattern, options) {
+        return Ok(Vec::new());
+    }
+    let body: Body = content
+        .parse()
+        .with_context(|| format!("Failed to parse HCL: {:?}", path))?;
+
+    let mut found = Vec::new();
+    for block in body.iter().filter_map(|s| s.as_block()) {
+        let block_type = block.ident.as_str();
+        let address = match block.labels.first() {
+            Some(label) if block_takes_label(block_type) => {
+                format!("{}.{}", block_type, quote_segment(label.as_str()))
+            }
+            _ => block_type.to_string(),
+        };
+        search_block(block, &address, pattern, &content, options, &mut found);
+    }
+
+    Ok(found
+        .into_iter()
+        .map(|(line, address, value)| GrepMatch {
+            file: path.to_path_buf(),
+            line,
+            address,
+            value,
+        })
+        .collect())
+}
+
+pub fn grep_dir(pattern: &str, dir: &Path, options: &GrepOptions) -> Result<Vec<GrepMatch>> {
+    let mut matches = Vec::new();
+    for path in find_all_tf_files(dir)? {
+        matches.extend(grep_file(&path, pattern, options)?);
+    }
+    Ok(matches)
+}
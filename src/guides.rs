@@ -0,0 +1,36 @@
+// Upgrade guides: where a provider (or module) documents what breaks in a
+// major version, linked from check-updates and `tv outdated --providers`
+// when the newest release is a major version ahead.
+//
+// Guides of well-known providers are built in; `[upgrade_guides]` in
+// tv.toml adds others or replaces them, keyed like track rules by a
+// pattern for the provider address, module label or source url (see
+// config.rs). `{major}` in a URL is the major version moved to:
+//
+// [upgrade_guides]
+// "terraform-aws-modules/eks/aws" = "https://github.com/terraform-aws-modules/terraform-aws-eks/blob/master/docs/UPGRADE-{major}.0.md"
+
+use crate::config::Config;
+use crate::wildcard_match;
+
+const BUILTIN: &[(&str, &str)] = &[
+    ("hashicorp/aws", "https://registry.terraform.io/providers/hashicorp/aws/latest/docs/guides/version-{major}-upgrade"),
+    ("hashicorp/azurerm", "https://registry.terraform.io/providers/hashicorp/azurerm/latest/docs/guides/{major}.0-upgrade-guide"),
+    ("hashicorp/google", "https://registry.terraform.io/providers/hashicorp/google/latest/docs/guides/version_{major}_upgrade"),
+    ("hashicorp/google-beta", "https://registry.terraform.io/providers/hashicorp/google-beta/latest/docs/guides/version_{major}_upgrade"),
+    ("hashicorp/kubernetes", "https://registry.terraform.io/providers/hashicorp/kubernetes/latest/docs/guides/v{major}-upgrade-guide"),
+    ("hashicorp/helm", "https://registry.terraform.io/providers/hashicorp/helm/latest/docs/guides/v{major}-upgrade-guide"),
+];
+
+pub fn upgrade_guide(config: &Config, names: &[&str], major: u64) -> Option<String> {
+    // `names` are what the provider or module can be matched by; tv.toml first
+    let configured = config
+        .upgrade_guides
+        .iter()
+        .find(|(pattern, _)| names.iter().any(|name| wildcard_match(pattern, name)))
+        .map(|(_, url)| url.as_str());
+    let url = configured.or_else(|| {
+        BUILTIN.iter().find(|(provider, _)| names.contains(provider)).map(|(_, url)| *url)
+    })?;
+    Some(url.replace("{major}", &major.to_string()))
+}
@@ -0,0 +1,230 @@
+// Module pin history from the undo journal, and `tv flapping`: warn when a
+// module's version or git ref goes back and forth (5.1.0 -> 5.2.0 -> 5.1.0)
+// within a time window, which usually means two automation systems (a
+// dependency bot and a pipeline running `tv set`, say) are fighting.
+//
+// Every journaled transaction keeps each file's content before and after
+// (see journal.rs). The pins of both are cached in <journal_dir>/history.json
+// by transaction ID, so only transactions new since the last run are parsed.
+// A file whose content at a transaction differs from what the previous
+// transaction left was changed outside tv (or by `tv undo`); that change is
+// dated by the later transaction, the first time it could be seen.
+//
+// Pins are addressed like queries: module.vpc.version and
+// module.vpc.source["ref"].
+
+use anyhow::{Context, Result};
+use hcl_edit::expr::Expression;
+use hcl_edit::structure::Body;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::journal::{self, TransactionMeta};
+use crate::query::quote_segment;
+use crate::source::ModuleSource;
+use crate::typed_from_raw;
+
+const CACHE_FILE: &str = "history.json";
+
+type Pins = BTreeMap<String, String>;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Snapshot {
+    path: PathBuf,
+    before: Pins,  // Empty if the file was created
+    after: Pins,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct HistoryCache {
+    transactions: BTreeMap<String, Vec<Snapshot>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub time: u64,
+    pub txn: String,
+    pub command: Option<String>,  // None when changed outside tv
+    pub path: PathBuf,
+    pub address: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Flap {
+    pub path: PathBuf,
+    pub address: String,
+    pub reverts: usize,  // Times a value came back
+    pub changes: Vec<Change>,
+}
+
+pub fn module_pins(content: &str) -> Pins {
+    // Unparseable content has no pins
+    let mut pins = Pins::new();
+    let Ok(body) = content.parse::<Body>() else {
+        return pins;
+    };
+    for block in body.iter().filter_map(|s| s.as_block()) {
+        let Some(label) = block.labels.first().filter(|_| block.ident.as_str() == "module") else {
+            continue;
+        };
+        let module = format!("module.{}", quote_segment(label.as_str()));
+        if let Some(version) = block.body.get_attribute("version") {
+            pins.insert(format!("{}.version", module), typed_from_raw(&version.value.to_string()).value);
+        }
+        if let Some(Expression::String(source)) = block.body.get_attribute("source").map(|a| &a.value)
+            && let Some(reference) = ModuleSource::parse(source.value()).git_ref()
+        {
+            pins.insert(format!("{}.source[\"ref\"]", module), reference.to_string());
+        }
+    }
+    pins
+}
+
+fn snapshots(journal_dir: &Path, txn: &TransactionMeta) -> Vec<Snapshot> {
+    // A snapshot the journal lost reads as empty
+    let read = |name: &str| fs::read_to_string(journal_dir.join(&txn.id).join(name)).unwrap_or_default();
+    txn.entries
+        .iter()
+        .map(|entry| Snapshot {
+            path: entry.path.clone(),
+            before: entry.before.as_deref().map(|name| module_pins(&read(name))).unwrap_or_default(),
+            after: module_pins(&read(&entry.after)),
+        })
+        .collect()
+}
+
+fn load_cache(journal_dir: &Path) -> HistoryCache {
+    // A missing or corrupt cache is rebuilt
+    fs::read_to_string(journal_dir.join(CACHE_FILE))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn diff(old: &Pins, new: &Pins, mut change: impl FnMut(&str, Option<&String>, Option<&String>)) {
+    for address in old.keys().chain(new.keys().filter(|k| !old.contains_key(*k))) {
+        let (before, after) = (old.get(address), new.get(address));
+        if before != after {
+            change(address, before, after);
+        }
+    }
+}
+
+pub fn history(journal_dir: &Path) -> Result<Vec<Change>> {
+    // Every pin change, oldest first
+    let transactions = journal::list_transactions(journal_dir)?;
+    let mut cache = load_cache(journal_dir);
+    let mut stale = false;
+    for txn in &transactions {
+        if !cache.transactions.contains_key(&txn.id) {
+            cache.transactions.insert(txn.id.clone(), snapshots(journal_dir, txn));
+            stale = true;
+        }
+    }
+    if stale {
+        let json = serde_json::to_string(&cache)?;
+        fs::write(journal_dir.join(CACHE_FILE), json)
+            .with_context(|| format!("Failed to write history cache: {:?}", journal_dir))?;
+    }
+
+    let mut changes = Vec::new();
+    let mut last: HashMap<PathBuf, Pins> = HashMap::new();
+    for txn in &transactions {
+        for snapshot in &cache.transactions[&txn.id] {
+            let mut record = |command: Option<&String>, address: &str, old: Option<&String>, new: Option<&String>| {
+                changes.push(Change {
+                    time: txn.created,
+                    txn: txn.id.clone(),
+                    command: command.cloned(),
+                    path: snapshot.path.clone(),
+                    address: address.to_string(),
+                    old: old.cloned(),
+                    new: new.cloned(),
+                })
+            };
+            if let Some(previous) = last.get(&snapshot.path) {
+                diff(previous, &snapshot.before, |address, old, new| record(None, address, old, new));
+            }
+            diff(&snapshot.before, &snapshot.after, |address, old, new| record(Some(&txn.command), address, old, new));
+            last.insert(snapshot.path.clone(), snapshot.after.clone());
+        }
+    }
+    Ok(changes)
+}
+
+pub fn flapping(changes: &[Change], window: Duration) -> Vec<Flap> {
+    // A pin flaps when a value it left comes back within the window
+    let mut timelines: BTreeMap<(&Path, &str), Vec<&Change>> = BTreeMap::new();
+    for change in changes {
+        timelines.entry((&change.path, &change.address)).or_default().push(change);
+    }
+
+    let mut flaps = Vec::new();
+    for ((path, address), timeline) in timelines {
+        let mut involved = vec![false; timeline.len()];
+        let mut reverts = 0;
+        for (i, change) in timeline.iter().enumerate() {
+            let since = change.time.saturating_sub(window.as_secs());
+            let left = (0..i).rev().take_while(|&j| timeline[j].time >= since).find(|&j| timeline[j].old == change.new);
+            if let Some(j) = left {
+                reverts += 1;
+                involved[j..=i].iter_mut().for_each(|flag| *flag = true);
+            }
+        }
+        if reverts > 0 {
+            flaps.push(Flap {
+                path: path.to_path_buf(),
+                address: address.to_string(),
+                reverts,
+                changes: timeline.iter().zip(&involved).filter(|(_, flag)| **flag).map(|(c, _)| (*c).clone()).collect(),
+            });
+        }
+    }
+    flaps
+}
+
+fn timestamp(time: u64) -> String {
+    // YYYY-MM-DD HH:MM (UTC)
+    format!("{} {:02}:{:02}", crate::stable::date(time), time % 86400 / 3600, time % 3600 / 60)
+}
+
+fn shown(value: Option<&String>) -> String {
+    value.map(|v| format!("\"{}\"", v)).unwrap_or_else(|| "(unset)".to_string())
+}
+
+pub fn render(flaps: &[Flap], show: impl Fn(&Path) -> String) -> String {
+    let mut out = String::new();
+    for flap in flaps {
+        let first = flap.changes.first().map(|c| c.time).unwrap_or_default();
+        let last = flap.changes.last().map(|c| c.time).unwrap_or_default();
+        let mut values = vec![shown(flap.changes.first().and_then(|c| c.old.as_ref()))];
+        values.extend(flap.changes.iter().map(|c| shown(c.new.as_ref())));
+        out.push_str(&format!(
+            "{}: {}: {} changed {} times in {}: {}\n",
+            crate::style::yellow("warning"),
+            show(&flap.path),
+            flap.address,
+            flap.changes.len(),
+            duration(last - first),
+            values.join(" -> ")
+        ));
+        for change in &flap.changes {
+            let by = change.command.clone().unwrap_or_else(|| crate::style::dim("changed outside tv"));
+            out.push_str(&format!("  {}  {} -> {}  {}\n", timestamp(change.time), shown(change.old.as_ref()), shown(change.new.as_ref()), by));
+        }
+    }
+    out
+}
+
+fn duration(seconds: u64) -> String {
+    match seconds {
+        s if s < 3600 => format!("{}m", s / 60),
+        s if s < 86400 => format!("{}h", s / 3600),
+        s => format!("{}d", s / 86400),
+    }
+}
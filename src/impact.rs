@@ -0,0 +1,182 @@
+// `tv upgrade-impact module.<name> --to <version>`: how a module's interface
+// changes between the pinned version and another one.
+//
+// Inputs are the module's `variable` blocks (required when they have no
+// default), outputs its `output` blocks: read from the module's .tf files at
+// both refs for git sources (see info.rs), from the registry's `inputs` and
+// `outputs` for registry sources. The call site is then checked against the
+// target version: arguments it passes for inputs that no longer exist, and
+// required inputs it does not pass.
+//
+// `--annotate` marks the arguments passed for removed inputs with a
+// `# tv: removed in <version>` comment on their line, so the follow-up shows
+// in the upgrade's diff. Arguments already marked are left alone.
+
+use anyhow::{Context, Result, anyhow};
+use hcl_edit::Decorate;
+use hcl_edit::structure::Body;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use crate::constraint::exact_version;
+use crate::info::{GitTree, ModuleInfo};
+use crate::registry::{Client, ModuleAddress};
+use crate::source::SourceKind;
+use crate::{read_tf_body, write_tf_body};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Interface {
+    pub inputs: BTreeMap<String, bool>,  // Name -> required
+    pub outputs: BTreeSet<String>,
+}
+
+pub fn parse_interface(sources: &[String]) -> Result<Interface> {
+    // The contents of a module's .tf files
+    let mut interface = Interface::default();
+    for content in sources {
+        let body: Body = content.parse().context("Failed to parse module HCL")?;
+        for block in body.get_blocks("variable") {
+            if let Some(name) = block.labels.first() {
+                let required = block.body.get_attribute("default").is_none();
+                interface.inputs.insert(name.as_str().to_string(), required);
+            }
+        }
+        for block in body.get_blocks("output") {
+            if let Some(name) = block.labels.first() {
+                interface.outputs.insert(name.as_str().to_string());
+            }
+        }
+    }
+    Ok(interface)
+}
+
+fn registry_interface(details: &Value) -> Interface {
+    // {"inputs": [{"name": "cidr", "required": false}, ...], "outputs": [{"name": "vpc_id"}, ...]}
+    let entries = |key: &str| details.get(key).and_then(Value::as_array).cloned().unwrap_or_default();
+    Interface {
+        inputs: entries("inputs")
+            .iter()
+            .filter_map(|input| {
+                let name = input.get("name")?.as_str()?.to_string();
+                Some((name, input.get("required").and_then(Value::as_bool).unwrap_or(false)))
+            })
+            .collect(),
+        outputs: entries("outputs")
+            .iter()
+            .filter_map(|output| Some(output.get("name")?.as_str()?.to_string()))
+            .collect(),
+    }
+}
+
+pub fn module_interface(info: &ModuleInfo, version: &str, registry: &Client, cache_dir: &Path) -> Result<Interface> {
+    match info.source.kind {
+        SourceKind::Git => {
+            let tree = GitTree::fetch(&info.source, Some(version), cache_dir)?;
+            let sources = tree
+                .files()?
+                .iter()
+                .filter(|name| name.ends_with(".tf"))
+                .map(|name| tree.read(name))
+                .collect::<Result<Vec<String>>>()?;
+            parse_interface(&sources).with_context(|| format!("In {} at {}", info.source.url, version))
+        }
+        SourceKind::Registry => {
+            let address = ModuleAddress::parse(&info.source.to_string())
+                .ok_or_else(|| anyhow!("Invalid registry source: {}", info.source))?;
+            let details = registry
+                .module_details(&address, Some(version), info.source.path.as_deref())?
+                .ok_or_else(|| anyhow!("{} {} has no module at {:?}", address, version, info.source.path))?;
+            Ok(registry_interface(&details))
+        }
+        kind => Err(anyhow!("Cannot read the inputs of {} sources: {}", kind.as_str(), info.source)),
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Impact {
+    pub module: String,
+    pub from: String,
+    pub to: String,
+    pub removed_inputs: Vec<String>,
+    pub added_inputs: Vec<(String, bool)>,  // Name, required
+    pub removed_outputs: Vec<String>,
+    pub added_outputs: Vec<String>,
+    pub passed_removed: Vec<String>,  // Arguments the call site passes for removed inputs
+    pub missing_required: Vec<String>,  // Required inputs the call site does not pass
+}
+
+impl Impact {
+    pub fn breaking(&self) -> bool {
+        !self.passed_removed.is_empty() || !self.missing_required.is_empty()
+    }
+}
+
+pub fn compare(info: &ModuleInfo, from: &Interface, to: &Interface) -> Impact {
+    let passes = |name: &String| info.arguments.contains(name);
+    let removed_inputs: Vec<String> = from.inputs.keys().filter(|n| !to.inputs.contains_key(*n)).cloned().collect();
+    Impact {
+        module: info.module.clone(),
+        passed_removed: removed_inputs.iter().filter(|n| passes(n)).cloned().collect(),
+        missing_required: to
+            .inputs
+            .iter()
+            .filter(|(name, required)| **required && !passes(name))
+            .map(|(name, _)| name.clone())
+            .collect(),
+        removed_inputs,
+        added_inputs: to
+            .inputs
+            .iter()
+            .filter(|(name, _)| !from.inputs.contains_key(*name))
+            .map(|(name, required)| (name.clone(), *required))
+            .collect(),
+        removed_outputs: from.outputs.difference(&to.outputs).cloned().collect(),
+        added_outputs: to.outputs.difference(&from.outputs).cloned().collect(),
+        ..Default::default()
+    }
+}
+
+pub fn upgrade_impact(info: &ModuleInfo, to: &str, registry: &Client, cache_dir: &Path) -> Result<Impact> {
+    // Registry modules must be pinned to one version to compare against
+    let from = match info.source.kind {
+        SourceKind::Registry => info.version.as_deref().and_then(exact_version),
+        _ => info.pinned().map(str::to_string),
+    }
+    .ok_or_else(|| anyhow!("module.{} is not pinned to a version", info.module))?;
+
+    let before = module_interface(info, &from, registry, cache_dir)?;
+    let after = module_interface(info, to, registry, cache_dir)?;
+    Ok(Impact {
+        from,
+        to: to.to_string(),
+        ..compare(info, &before, &after)
+    })
+}
+
+pub fn annotate_removed(info: &ModuleInfo, impact: &Impact) -> Result<usize> {
+    // Returns how many arguments were marked
+    let mut body = read_tf_body(&info.file)?;
+    let block = body
+        .get_blocks_mut("module")
+        .find(|b| b.labels.first().is_some_and(|l| l.as_str() == info.module))
+        .ok_or_else(|| anyhow!("No module '{}' in {:?}", info.module, info.file))?;
+
+    let mut marked = 0;
+    for name in &impact.passed_removed {
+        let Some(mut attr) = block.body.get_attribute_mut(name) else {
+            continue;
+        };
+        let suffix = attr.decor().suffix().map(|s| s.to_string()).unwrap_or_default();
+        if suffix.contains("# tv: removed in") {
+            continue;
+        }
+        let suffix = format!("{} # tv: removed in {}", suffix.trim_end(), impact.to);
+        attr.decor_mut().set_suffix(suffix);
+        marked += 1;
+    }
+    if marked > 0 {
+        write_tf_body(&info.file, &body)?;
+    }
+    Ok(marked)
+}
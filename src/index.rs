@@ -0,0 +1,310 @@
+// Persistent inventory of the blocks in a directory tree.
+//
+// `tv index build` records every top-level block of every .tf file, with
+// its attributes (including those of nested blocks, e.g.
+// required_providers.aws), their raw values and line spans, and the block's
+// own text. Scan and get can then look blocks up by type and label instead
+// of parsing every file; only the candidate blocks' text is parsed.
+//
+// Layout: <dir>/.tv/index.json. A file's entry is used only while the
+// file's size and modification time still match; stale or new files are
+// parsed directly, so results never depend on the index being current.
+//
+// `tv index update` re-parses only what changed: files whose stamp differs
+// or that git reports as modified are hashed, and re-parsed if the hash
+// differs (git status catches edits that kept the old mtime).
+
+use anyhow::{Context, Result, anyhow};
+use hcl_edit::Span;
+use hcl_edit::structure::{Block, Body};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::UNIX_EPOCH;
+
+use crate::{ScanQuery, block_matches, find_all_tf_files};
+
+pub const INDEX_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Index {
+    pub version: u32,
+    pub files: Vec<IndexedFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexedFile {
+    pub path: PathBuf,  // Relative to the indexed directory
+    pub size: u64,
+    pub modified: u64,  // Nanoseconds since the epoch
+    pub hash: String,
+    pub error: Option<String>,  // Set if the file did not parse
+    pub blocks: Vec<IndexedBlock>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexedBlock {
+    pub block_type: String,
+    pub label: Option<String>,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub attributes: Vec<IndexedAttribute>,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexedAttribute {
+    pub name: String,  // Dotted path through nested blocks, e.g. required_providers.aws
+    pub value: String,  // Raw HCL
+    pub line: usize,
+}
+
+pub fn default_index_path(dir: &Path) -> PathBuf {
+    dir.join(".tv").join("index.json")
+}
+
+pub fn content_hash(content: &[u8]) -> String {
+    // FNV-1a: stable across builds, unlike std's hasher
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in content {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{:016x}", hash)
+}
+
+fn file_stamp(path: &Path) -> Result<(u64, u64)> {
+    let metadata = fs::metadata(path).with_context(|| format!("Failed to stat {:?}", path))?;
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0);
+    Ok((metadata.len(), modified))
+}
+
+pub(crate) fn line_of(content: &str, offset: usize) -> usize {
+    content[..offset.min(content.len())].matches('\n').count() + 1
+}
+
+fn collect_attributes(block: &Block, prefix: &str, content: &str, out: &mut Vec<IndexedAttribute>) {
+    for structure in block.body.iter() {
+        if let Some(attr) = structure.as_attribute() {
+            out.push(IndexedAttribute {
+                name: format!("{}{}", prefix, attr.key.as_str()),
+                value: attr.value.to_string().trim().to_string(),
+                line: attr.span().map(|s| line_of(content, s.start)).unwrap_or(0),
+            });
+        } else if let Some(nested) = structure.as_block() {
+            let name = nested.labels.first().map(|l| l.as_str()).unwrap_or(nested.ident.as_str());
+            collect_attributes(nested, &format!("{}{}.", prefix, name), content, out);
+        }
+    }
+}
+
+pub fn index_file(root: &Path, path: &Path) -> Result<IndexedFile> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read file: {:?}", path))?;
+    let (size, modified) = file_stamp(path)?;
+    let relative = path.strip_prefix(root).unwrap_or(path).to_path_buf();
+
+    let mut indexed = IndexedFile {
+        path: relative,
+        size,
+        modified,
+        hash: content_hash(content.as_bytes()),
+        error: None,
+        blocks: Vec::new(),
+    };
+
+    let body: Body = match content.parse() {
+        Ok(body) => body,
+        Err(err) => {
+            // Recorded so the file is parsed (and its error reported) on lookup
+            indexed.error = Some(err.to_string());
+            return Ok(indexed);
+        }
+    };
+
+    for block in body.iter().filter_map(|s| s.as_block()) {
+        let Some(span) = block.span() else {
+            continue;
+        };
+        let mut attributes = Vec::new();
+        collect_attributes(block, "", &content, &mut attributes);
+        indexed.blocks.push(IndexedBlock {
+            block_type: block.ident.as_str().to_string(),
+            label: block.labels.first().map(|l| l.as_str().to_string()),
+            start_line: line_of(&content, span.start),
+            end_line: line_of(&content, span.end.saturating_sub(1)),
+            attributes,
+            text: content[span].to_string(),
+        });
+    }
+    Ok(indexed)
+}
+
+pub fn build(dir: &Path) -> Result<Index> {
+    let mut files = Vec::new();
+    for path in find_all_tf_files(dir)? {
+        files.push(index_file(dir, &path)?);
+    }
+    Ok(Index {
+        version: INDEX_VERSION,
+        files,
+    })
+}
+
+pub fn save(index: &Index, path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create index directory: {:?}", parent))?;
+    }
+    let json = serde_json::to_string(index).context("Failed to serialize index")?;
+    fs::write(path, json).with_context(|| format!("Failed to write index: {:?}", path))
+}
+
+pub fn load(path: &Path) -> Result<Index> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read index: {:?}", path))?;
+    let index: Index = serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse index: {:?}", path))?;
+    if index.version != INDEX_VERSION {
+        return Err(anyhow!(
+            "Index {:?} has version {}, expected {}; rebuild it with `tv index build`",
+            path,
+            index.version,
+            INDEX_VERSION
+        ));
+    }
+    Ok(index)
+}
+
+impl Index {
+    pub fn file(&self, root: &Path, path: &Path) -> Option<&IndexedFile> {
+        let relative = path.strip_prefix(root).unwrap_or(path);
+        self.files.iter().find(|f| f.path == relative)
+    }
+
+    pub fn fresh_file(&self, root: &Path, path: &Path) -> Option<&IndexedFile> {
+        // Only entries whose file is unchanged (and parsed) can stand in for it
+        let indexed = self.file(root, path)?;
+        let fresh = indexed.error.is_none()
+            && file_stamp(path).is_ok_and(|stamp| stamp == (indexed.size, indexed.modified));
+        fresh.then_some(indexed)
+    }
+}
+
+impl IndexedFile {
+    pub fn candidates<'a>(&'a self, query: &'a ScanQuery) -> impl Iterator<Item = Result<Block>> + 'a {
+        // Blocks of the query's type and label, parsed from their text;
+        // unlabeled blocks (import "addresses") are checked once parsed
+        self.blocks
+            .iter()
+            .filter(|b| {
+                b.block_type == query.block_type
+                    && (b.label.is_none() || query.block_label.as_ref().is_none_or(|l| b.label.as_ref() == Some(l)))
+            })
+            .filter_map(|b| {
+                let parsed = b.text.parse::<Body>().context("Failed to parse indexed block").and_then(|body| {
+                    body.into_iter()
+                        .find_map(|s| s.into_block().ok())
+                        .ok_or_else(|| anyhow!("Indexed block text is not a block"))
+                });
+                match parsed {
+                    Ok(block) if !block_matches(&block, query) => None,
+                    parsed => Some(parsed),
+                }
+            })
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct UpdateReport {
+    pub reparsed: Vec<PathBuf>,
+    pub removed: Vec<PathBuf>,
+    pub unchanged: usize,
+}
+
+pub fn git_changed_files(dir: &Path) -> HashSet<PathBuf> {
+    // Modified and untracked files under `dir`, relative to it; empty
+    // outside a git repository
+    let run = |args: &[&str]| {
+        Command::new("git")
+            .current_dir(dir)
+            .args(args)
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).to_string())
+    };
+    let Some(top) = run(&["rev-parse", "--show-toplevel"]) else {
+        return HashSet::new();
+    };
+    let Some(status) = run(&["status", "--porcelain", "-z", "--untracked-files=all", "--", "."]) else {
+        return HashSet::new();
+    };
+    let Ok(dir) = dir.canonicalize() else {
+        return HashSet::new();
+    };
+    let top = PathBuf::from(top.trim());
+
+    // Entries are "XY path"; a rename is followed by its old path
+    let mut changed = HashSet::new();
+    let mut entries = status.split('\0').filter(|e| !e.is_empty());
+    while let Some(entry) = entries.next() {
+        let (code, path) = entry.split_at(3.min(entry.len()));
+        if code.starts_with('R') || code.starts_with('C') {
+            entries.next();
+        }
+        if let Ok(relative) = top.join(path).strip_prefix(&dir) {
+            changed.insert(relative.to_path_buf());
+        }
+    }
+    changed
+}
+
+pub fn update(dir: &Path, index: &mut Index) -> Result<UpdateReport> {
+    let mut report = UpdateReport::default();
+    let changed = git_changed_files(dir);
+    let tf_files = find_all_tf_files(dir)?;
+
+    let mut files = Vec::new();
+    for path in tf_files {
+        let relative = path.strip_prefix(dir).unwrap_or(&path).to_path_buf();
+        let Some(mut entry) = index.files.iter().find(|f| f.path == relative).cloned() else {
+            files.push(index_file(dir, &path)?);
+            report.reparsed.push(relative);
+            continue;
+        };
+
+        let stamp = file_stamp(&path)?;
+        if stamp == (entry.size, entry.modified) && !changed.contains(&relative) {
+            files.push(entry);
+            report.unchanged += 1;
+            continue;
+        }
+
+        // Touched but identical content only needs a new stamp
+        let content = fs::read(&path).with_context(|| format!("Failed to read file: {:?}", path))?;
+        if content_hash(&content) == entry.hash {
+            (entry.size, entry.modified) = stamp;
+            files.push(entry);
+            report.unchanged += 1;
+        } else {
+            files.push(index_file(dir, &path)?);
+            report.reparsed.push(relative);
+        }
+    }
+
+    for old in &index.files {
+        if !files.iter().any(|f| f.path == old.path) {
+            report.removed.push(old.path.clone());
+        }
+    }
+    index.files = files;
+    Ok(report)
+}
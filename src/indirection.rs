@@ -0,0 +1,251 @@
+// Values that point somewhere else: `local.module_versions["vpc"]` or
+// `var.module_versions["vpc"]` (optionally inside a template, e.g.
+// `?ref=${local.module_versions["vpc"]}`), as written by `tv centralize`.
+//
+// Locals are looked up in the .tf files of the same directory; variables in
+// terraform.tfvars and *.auto.tfvars, then in the variable's default.
+// `get --resolve` reports the literal behind the reference and `set` writes
+// to the entry the reference points at instead of replacing it.
+
+use anyhow::{Result, anyhow};
+use hcl_edit::Decorate;
+use hcl_edit::expr::Expression;
+use hcl_edit::structure::Body;
+use std::path::{Path, PathBuf};
+
+use crate::map::{get_entry, set_entry};
+use crate::{find_all_tf_files, read_tf_body, write_tf_body};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scope {
+    Local,
+    Var,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reference {
+    pub scope: Scope,
+    pub name: String,
+    pub key: Option<String>,  // Map entry, for `local.name["key"]`
+    pub text: String,  // The reference as written
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '-'
+}
+
+pub fn find_reference(text: &str) -> Option<Reference> {
+    for (scope, prefix) in [(Scope::Local, "local."), (Scope::Var, "var.")] {
+        let mut search = 0;
+        while let Some(pos) = text[search..].find(prefix) {
+            let start = search + pos;
+            search = start + prefix.len();
+            // `local.` must not be the tail of another name (e.g. `nonlocal.`)
+            if text[..start].chars().next_back().is_some_and(is_name_char) {
+                continue;
+            }
+            let name_start = start + prefix.len();
+            let name_len = text[name_start..]
+                .find(|c: char| !is_name_char(c))
+                .unwrap_or(text.len() - name_start);
+            if name_len == 0 {
+                continue;
+            }
+            let name_end = name_start + name_len;
+            let mut end = name_end;
+            let mut key = None;
+            if let Some(rest) = text[name_end..].strip_prefix("[\"")
+                && let Some(key_len) = rest.find("\"]")
+            {
+                key = Some(rest[..key_len].to_string());
+                end = name_end + 2 + key_len + 2;
+            }
+            return Some(Reference {
+                scope,
+                name: text[name_start..name_end].to_string(),
+                key,
+                text: text[start..end].to_string(),
+            });
+        }
+    }
+    None
+}
+
+pub fn as_reference(value: &str) -> Option<Reference> {
+    // The value is nothing but a reference: a bare expression or a lone
+    // interpolation (`${local.x}`)
+    let value = value.trim();
+    let inner = value
+        .strip_prefix("${")
+        .and_then(|v| v.strip_suffix('}'))
+        .unwrap_or(value);
+    find_reference(inner).filter(|r| r.text == inner)
+}
+
+fn directory_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    Ok(find_all_tf_files(dir)?
+        .into_iter()
+        .filter(|p| p.parent() == Some(dir))
+        .collect())
+}
+
+fn tfvars_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    // terraform.tfvars first, then *.auto.tfvars in name order, like terraform
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.to_string_lossy().ends_with(".auto.tfvars"))
+        .collect();
+    files.sort();
+    let default = dir.join("terraform.tfvars");
+    if default.is_file() {
+        files.insert(0, default);
+    }
+    Ok(files)
+}
+
+fn select<'a>(value: &'a Expression, key: Option<&str>) -> Option<&'a Expression> {
+    match (key, value) {
+        (None, value) => Some(value),
+        (Some(key), Expression::Object(object)) => get_entry(object, key),
+        _ => None,
+    }
+}
+
+fn literal(value: &Expression) -> String {
+    match value {
+        Expression::String(s) => s.value().to_string(),
+        other => other.to_string().trim().to_string(),
+    }
+}
+
+// Where a reference's value is defined: the file and a way to find the
+// attribute in it
+enum Definition {
+    Local(PathBuf),
+    VarValue(PathBuf),
+    VarDefault(PathBuf),
+}
+
+fn find_definition(dir: &Path, reference: &Reference) -> Result<Option<Definition>> {
+    match reference.scope {
+        Scope::Local => {
+            for file in directory_files(dir)? {
+                let body = read_tf_body(&file)?;
+                if body.get_blocks("locals").any(|l| l.body.get_attribute(&reference.name).is_some()) {
+                    return Ok(Some(Definition::Local(file)));
+                }
+            }
+        }
+        Scope::Var => {
+            // The last tfvars file that sets it wins, as in terraform
+            let mut found = None;
+            for file in tfvars_files(dir)? {
+                if read_tf_body(&file)?.get_attribute(&reference.name).is_some() {
+                    found = Some(Definition::VarValue(file));
+                }
+            }
+            if found.is_some() {
+                return Ok(found);
+            }
+            for file in directory_files(dir)? {
+                let body = read_tf_body(&file)?;
+                let has_default = body
+                    .get_blocks("variable")
+                    .filter(|b| b.labels.first().map(|l| l.as_str()) == Some(reference.name.as_str()))
+                    .any(|b| b.body.get_attribute("default").is_some());
+                if has_default {
+                    return Ok(Some(Definition::VarDefault(file)));
+                }
+            }
+        }
+    }
+    Ok(None)
+}
+
+fn defined_value<'a>(body: &'a Body, definition: &Definition, reference: &Reference) -> Option<&'a Expression> {
+    let value = match definition {
+        Definition::Local(_) => body
+            .get_blocks("locals")
+            .find_map(|l| l.body.get_attribute(&reference.name))
+            .map(|a| &a.value),
+        Definition::VarValue(_) => body.get_attribute(&reference.name).map(|a| &a.value),
+        Definition::VarDefault(_) => body
+            .get_blocks("variable")
+            .filter(|b| b.labels.first().map(|l| l.as_str()) == Some(reference.name.as_str()))
+            .find_map(|b| b.body.get_attribute("default"))
+            .map(|a| &a.value),
+    }?;
+    select(value, reference.key.as_deref())
+}
+
+fn definition_path(definition: &Definition) -> &Path {
+    match definition {
+        Definition::Local(path) | Definition::VarValue(path) | Definition::VarDefault(path) => path,
+    }
+}
+
+pub fn resolve_reference(dir: &Path, reference: &Reference) -> Result<Option<Expression>> {
+    let Some(definition) = find_definition(dir, reference)? else {
+        return Ok(None);
+    };
+    let body = read_tf_body(definition_path(&definition))?;
+    Ok(defined_value(&body, &definition, reference).cloned())
+}
+
+pub fn resolve(dir: &Path, value: &str) -> Result<String> {
+    // Replace every resolvable reference; a value that is only a reference
+    // becomes the referenced literal
+    if let Some(reference) = as_reference(value) {
+        let resolved = resolve_reference(dir, &reference)?;
+        return Ok(resolved.map(|e| literal(&e)).unwrap_or_else(|| value.to_string()));
+    }
+    let mut resolved = value.to_string();
+    let mut rest = 0;
+    while let Some(reference) = find_reference(&resolved[rest..]) {
+        let interpolation = format!("${{{}}}", reference.text);
+        let Some(pos) = resolved[rest..].find(&interpolation) else {
+            break;
+        };
+        let start = rest + pos;
+        match resolve_reference(dir, &reference)?.map(|e| literal(&e)) {
+            Some(literal) => {
+                resolved.replace_range(start..start + interpolation.len(), &literal);
+                rest = start + literal.len();
+            }
+            None => rest = start + interpolation.len(),
+        }
+    }
+    Ok(resolved)
+}
+
+pub fn update_reference(dir: &Path, reference: &Reference, new_value: Expression) -> Result<PathBuf> {
+    // Writes `value` (a string) where the reference is defined
+    let definition = find_definition(dir, reference)?
+        .ok_or_else(|| anyhow!("{} is not defined in {:?}", reference.text, dir))?;
+    let path = definition_path(&definition).to_path_buf();
+    let mut body = read_tf_body(&path)?;
+
+    let mut attr = match definition {
+        Definition::Local(_) => body
+            .get_blocks_mut("locals")
+            .find_map(|l| l.body.get_attribute_mut(&reference.name)),
+        Definition::VarValue(_) => body.get_attribute_mut(&reference.name),
+        Definition::VarDefault(_) => body
+            .get_blocks_mut("variable")
+            .filter(|b| b.labels.first().map(|l| l.as_str()) == Some(reference.name.as_str()))
+            .find_map(|b| b.body.get_attribute_mut("default")),
+    }
+    .ok_or_else(|| anyhow!("{} is not defined in {:?}", reference.text, path))?;
+
+    match (&reference.key, attr.value_mut()) {
+        (Some(key), Expression::Object(object)) => set_entry(object, key, new_value),
+        (Some(_), _) => return Err(anyhow!("{} does not refer to a map", reference.text)),
+        (None, slot) => {
+            let mut new_value = new_value;
+            *new_value.decor_mut() = slot.decor().clone();
+            *slot = new_value;
+        }
+    }
+    write_tf_body(&path, &body)?;
+    Ok(path)
+}
@@ -0,0 +1,122 @@
+// `tv info module.<name>`: what a module block pins, and its documentation.
+//
+// With `--docs` the README of the pinned version is fetched: from the
+// registry (see registry.rs) for registry sources, the exact `version` or
+// the latest one under a range; from the repository at `?ref=` (the default
+// branch without one) for git sources, fetched into the tag mirrors of
+// updates.rs. Other sources have no README tv knows how to find.
+
+use anyhow::{Result, anyhow};
+use std::path::{Path, PathBuf};
+
+use crate::constraint::exact_version;
+use crate::query::quote_segment;
+use crate::registry::{Client, ModuleAddress};
+use crate::source::{ModuleSource, SourceKind};
+use crate::updates::{mirror_dir, remote_url, run_git};
+use crate::{find_tf_file, get_typed_value_resolved, read_tf_body, split_query_path};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModuleInfo {
+    pub module: String,
+    pub file: PathBuf,
+    pub source: ModuleSource,
+    pub version: Option<String>,  // The `version` attribute, resolved
+    pub arguments: Vec<String>,  // Inputs passed to the module, meta-arguments excluded
+}
+
+const META_ARGUMENTS: [&str; 6] = ["source", "version", "providers", "count", "for_each", "depends_on"];
+
+impl ModuleInfo {
+    pub fn pinned(&self) -> Option<&str> {
+        // The git ref or registry version the module is pinned to
+        match self.source.kind {
+            SourceKind::Git => self.source.git_ref(),
+            _ => self.version.as_deref(),
+        }
+    }
+}
+
+pub fn module_info(query: &str, file: Option<&Path>) -> Result<ModuleInfo> {
+    let (parts, bracket) = split_query_path(query)?;
+    let [block_type, label] = &parts[..] else {
+        return Err(anyhow!("Expected a module query such as module.vpc, got '{}'", query));
+    };
+    if block_type != "module" || bracket.is_some() {
+        return Err(anyhow!("Expected a module query such as module.vpc, got '{}'", query));
+    }
+
+    let attribute = |name: &str| get_typed_value_resolved(&format!("module.{}.{}", quote_segment(label), name), file);
+    let source = attribute("source")?
+        .ok_or_else(|| anyhow!("No module '{}' with a source found", label))?;
+    let file_path = find_tf_file(file)?;
+    let body = read_tf_body(&file_path)?;
+    let arguments = body
+        .get_blocks("module")
+        .find(|b| b.labels.first().is_some_and(|l| l.as_str() == label))
+        .map(|block| {
+            block
+                .body
+                .attributes()
+                .map(|a| a.key.as_str().to_string())
+                .filter(|key| !META_ARGUMENTS.contains(&key.as_str()))
+                .collect()
+        })
+        .unwrap_or_default();
+    Ok(ModuleInfo {
+        module: label.clone(),
+        file: file_path,
+        source: ModuleSource::parse(&source.value),
+        version: attribute("version")?.map(|v| v.value),
+        arguments,
+    })
+}
+
+pub(crate) struct GitTree {
+    // A module's directory at one commit of a tag mirror (see updates.rs)
+    mirror: PathBuf,
+    commit: String,
+    dir: String,  // "" or "modules/vpc/"
+}
+
+impl GitTree {
+    pub(crate) fn fetch(source: &ModuleSource, git_ref: Option<&str>, cache_dir: &Path) -> Result<GitTree> {
+        // Without a ref, the remote's default branch
+        let mirror = mirror_dir(&source.url, cache_dir)?;
+        run_git(&["fetch", "--quiet", "--depth=1", "--", &remote_url(&source.url), git_ref.unwrap_or("HEAD")], &mirror)?;
+        let commit = run_git(&["rev-parse", "FETCH_HEAD"], &mirror)?.trim().to_string();
+        let dir = source.path.as_deref().map(|p| format!("{}/", p.trim_end_matches('/'))).unwrap_or_default();
+        Ok(GitTree { mirror, commit, dir })
+    }
+
+    pub(crate) fn files(&self) -> Result<Vec<String>> {
+        let names = run_git(&["ls-tree", "--name-only", &format!("{}:{}", self.commit, self.dir)], &self.mirror)?;
+        Ok(names.lines().map(str::to_string).collect())
+    }
+
+    pub(crate) fn read(&self, name: &str) -> Result<String> {
+        run_git(&["show", &format!("{}:{}{}", self.commit, self.dir, name)], &self.mirror)
+    }
+}
+
+fn git_readme(source: &ModuleSource, cache_dir: &Path) -> Result<Option<String>> {
+    // README.md, README, readme.markdown, ...: the first one in the module's directory
+    let tree = GitTree::fetch(source, source.git_ref(), cache_dir)?;
+    match tree.files()?.iter().find(|n| n.to_lowercase().starts_with("readme")) {
+        Some(name) => tree.read(name).map(Some),
+        None => Ok(None),
+    }
+}
+
+pub fn fetch_readme(info: &ModuleInfo, registry: &Client, cache_dir: &Path) -> Result<Option<String>> {
+    match info.source.kind {
+        SourceKind::Git => git_readme(&info.source, cache_dir),
+        SourceKind::Registry => {
+            let address = ModuleAddress::parse(&info.source.to_string())
+                .ok_or_else(|| anyhow!("Invalid registry source: {}", info.source))?;
+            let version = info.version.as_deref().and_then(exact_version);
+            registry.module_readme(&address, version.as_deref(), info.source.path.as_deref())
+        }
+        kind => Err(anyhow!("No docs for {} sources: {}", kind.as_str(), info.source)),
+    }
+}
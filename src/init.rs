@@ -0,0 +1,184 @@
+// `tv init`: starter files for a repository adopting tv.
+//
+//   tv.toml            configuration (see config.rs), commented
+//   tv-policy.hcl      a starter policy for `tv validate` (see policy.rs)
+//   .tvignore          directories scans skip (see find_all_tf_files)
+//   CI                 a GitHub Actions workflow or a GitLab CI job running
+//                      `tv validate` and `tv check-updates`
+//
+// Existing files are left alone unless --force is given. Without flags,
+// main asks on a terminal which of the optional files to create.
+
+use anyhow::{Result, anyhow};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::CONFIG_FILE;
+use crate::policy::DEFAULT_POLICY_FILE;
+use crate::{TVIGNORE, write_file};
+
+pub const GITHUB_WORKFLOW: &str = ".github/workflows/tv.yml";
+pub const GITLAB_JOB: &str = ".gitlab/ci/tv.yml";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Ci {
+    GitHub,
+    GitLab,
+}
+
+impl std::str::FromStr for Ci {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Ci> {
+        match s {
+            "github" => Ok(Ci::GitHub),
+            "gitlab" => Ok(Ci::GitLab),
+            _ => Err(anyhow!("Expected 'github' or 'gitlab', got '{}'", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InitOptions {
+    pub policy: bool,
+    pub ignore: bool,
+    pub ci: Option<Ci>,
+    pub force: bool,  // Overwrite existing files
+}
+
+impl Default for InitOptions {
+    fn default() -> Self {
+        InitOptions { policy: true, ignore: true, ci: None, force: false }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Created {
+    pub path: PathBuf,
+    pub existed: bool,  // Kept as it was (or overwritten with force)
+}
+
+const CONFIG_TEMPLATE: &str = r#"# tv configuration: https://github.com/manoelhc/tv
+
+# Do not propose versions tagged less than a week ago
+minimum_release_age = "7d"
+
+# Which versions check-updates and upgrade may propose, first match wins
+# [[track]]
+# match   = "*github.com/your-org/*"
+# channel = "stable"
+
+# Modules upgraded together
+# [[group]]
+# name  = "aws-modules"
+# match = ["*github.com/terraform-aws-modules/*"]
+
+# What `tv set` does with a value an override file replaces: warn, edit or fail
+# on_override = "warn"
+
+# tv versions this repository expects (`tv self pin 1.4.x`)
+# tv_version = "1.4.x"
+"#;
+
+const POLICY_TEMPLATE: &str = r#"# Checks run by `tv validate`
+
+# Modules fetched from a URL (git, GitHub, S3, ...) must pin a release tag
+check "url-modules-tagged" {
+  query   = "module.*.source[url=\"*://*\"]"
+  expect  = "matches:[?&]ref=v?\\d"
+  message = "Modules fetched from a URL must be pinned to a release tag"
+}
+
+# Fail when a pinned registry module or provider version has been yanked
+# reject_yanked = true
+
+# Fail when a module is pinned to a release older than this
+# max_pin_age = "180d"
+
+# Fail when a module or provider source nearly matches another (a typo or a fork)
+# reject_similar_sources = true
+
+# Require `--reason` (e.g. a ticket) for edits under these paths
+# require_reason "prod" {
+#   paths   = ["envs/prod/**"]
+#   pattern = "^[A-Z]+-[0-9]+$"
+# }
+"#;
+
+const IGNORE_TEMPLATE: &str = r#"# Paths tv scans skip; patterns with a / are relative to this directory
+.terraform
+.terragrunt-cache
+"#;
+
+const GITHUB_TEMPLATE: &str = r#"name: tv
+
+on:
+  pull_request:
+  schedule:
+    - cron: "0 6 * * 1"
+
+jobs:
+  tv:
+    runs-on: ubuntu-latest
+    steps:
+      - uses: actions/checkout@v4
+      - name: Install tv
+        run: cargo install --locked --git https://github.com/manoelhc/tv
+      - name: Validate
+        run: tv validate
+      - name: Check for updates
+        run: tv check-updates
+"#;
+
+const GITLAB_TEMPLATE: &str = r#"# Include from .gitlab-ci.yml:
+#
+# include:
+#   - local: .gitlab/ci/tv.yml
+
+tv:
+  image: rust:latest
+  rules:
+    - if: $CI_PIPELINE_SOURCE == "merge_request_event"
+    - if: $CI_PIPELINE_SOURCE == "schedule"
+  before_script:
+    - cargo install --locked --git https://github.com/manoelhc/tv
+  script:
+    - tv validate
+    - tv check-updates
+"#;
+
+pub fn scaffold(options: &InitOptions) -> Vec<(&'static str, &'static str)> {
+    // Path relative to the repository, content
+    let mut files = vec![(CONFIG_FILE, CONFIG_TEMPLATE)];
+    if options.policy {
+        files.push((DEFAULT_POLICY_FILE, POLICY_TEMPLATE));
+    }
+    if options.ignore {
+        files.push((TVIGNORE, IGNORE_TEMPLATE));
+    }
+    match options.ci {
+        Some(Ci::GitHub) => files.push((GITHUB_WORKFLOW, GITHUB_TEMPLATE)),
+        Some(Ci::GitLab) => files.push((GITLAB_JOB, GITLAB_TEMPLATE)),
+        None => {}
+    }
+    files
+}
+
+pub fn init(dir: &Path, options: &InitOptions) -> Result<Vec<Created>> {
+    if !dir.is_dir() {
+        return Err(anyhow!("Path is not a directory: {:?}", dir));
+    }
+    let mut created = Vec::new();
+    for (name, content) in scaffold(options) {
+        let path = dir.join(name);
+        let existed = path.exists();
+        if !existed || options.force {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            write_file(&path, content)?;
+        }
+        created.push(Created { path, existed });
+    }
+    Ok(created)
+}
@@ -0,0 +1,75 @@
+// `tv list`: every module block under a directory, with its source taken
+// apart (url, subdirectory, ref) and its version, as one table instead of
+// a scan followed by a get per module.
+//
+// Sources that are not a literal string (a reference or a template) are
+// shown as their HCL text in the url column, with path and ref left empty.
+
+use anyhow::Result;
+use hcl_edit::expr::Expression;
+use std::path::{Path, PathBuf};
+
+use crate::output::table;
+use crate::query::quote_segment;
+use crate::source::ModuleSource;
+use crate::{find_all_tf_files, read_tf_body};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModuleEntry {
+    pub file: PathBuf,
+    pub label: String,
+    pub source: Option<String>,  // HCL text of the source expression; None without one
+    pub url: Option<String>,
+    pub path: Option<String>,
+    pub git_ref: Option<String>,
+    pub version: Option<String>,
+}
+
+fn text(expr: &Expression) -> String {
+    match expr {
+        Expression::String(s) => s.value().to_string(),
+        other => other.to_string().trim().to_string(),
+    }
+}
+
+pub fn list_modules(dir: &Path) -> Result<Vec<ModuleEntry>> {
+    let mut entries = Vec::new();
+    for file in find_all_tf_files(dir)? {
+        let body = read_tf_body(&file)?;
+        for block in body.get_blocks("module") {
+            let source = block.body.get_attribute("source").map(|a| &a.value);
+            let parsed = match source {
+                Some(Expression::String(s)) => Some(ModuleSource::parse(s.value())),
+                _ => None,
+            };
+            entries.push(ModuleEntry {
+                file: file.clone(),
+                label: block.labels.first().map(|l| l.as_str().to_string()).unwrap_or_default(),
+                source: source.map(text),
+                url: parsed.as_ref().map(|p| p.url.clone()).or_else(|| source.map(text)),
+                path: parsed.as_ref().and_then(|p| p.path.clone()),
+                git_ref: parsed.as_ref().and_then(|p| p.git_ref().map(str::to_string)),
+                version: block.body.get_attribute("version").map(|a| text(&a.value)),
+            });
+        }
+    }
+    Ok(entries)
+}
+
+pub fn render(entries: &[ModuleEntry], show: impl Fn(&Path) -> String) -> String {
+    let cell = |value: &Option<String>| value.clone().unwrap_or_else(|| "-".to_string());
+    let rows: Vec<Vec<String>> = entries
+        .iter()
+        .map(|e| {
+            vec![
+                show(&e.file),
+                quote_segment(&e.label),
+                cell(&e.url),
+                cell(&e.path),
+                cell(&e.git_ref),
+                cell(&e.version),
+            ]
+        })
+        .collect();
+    table(&["file", "module", "url", "path", "ref", "version"], &rows)
+}
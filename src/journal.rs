@@ -0,0 +1,232 @@
+// Undo journal for file edits.
+//
+// Every invocation that writes files gets one transaction; all files it
+// touches are recorded under the same transaction ID so a bulk edit can be
+// reviewed (`tv show-txn <id>`) and reverted (`tv undo --txn <id>`) as a unit.
+//
+// Layout:
+//   <journal_dir>/<txn id>/journal.json   transaction metadata
+//   <journal_dir>/<txn id>/<n>.before     content before the first write
+//   <journal_dir>/<txn id>/<n>.after      content after the last write
+//
+// The active transaction is tracked per thread and consulted by every
+// write; with no active transaction writes are not journaled.
+
+use anyhow::{Context, Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const JOURNAL_FILE: &str = "journal.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub path: PathBuf,
+    pub before: Option<String>,  // Backup file name; None if the file was created
+    pub after: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionMeta {
+    pub id: String,
+    pub created: u64,
+    pub command: String,
+    pub entries: Vec<JournalEntry>,
+    #[serde(default)]
+    pub undone: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,  // --reason, e.g. a ticket
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub undo_reason: Option<String>,  // --reason given to `tv undo`
+}
+
+#[derive(Debug)]
+pub struct Transaction {
+    dir: PathBuf,
+    pub meta: TransactionMeta,
+}
+
+thread_local! {
+    static ACTIVE: RefCell<Option<Transaction>> = const { RefCell::new(None) };
+}
+
+pub fn default_journal_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os("TV_JOURNAL_DIR") {
+        return PathBuf::from(dir);
+    }
+    if let Some(state) = std::env::var_os("XDG_STATE_HOME") {
+        return PathBuf::from(state).join("tv").join("journal");
+    }
+    if let Some(home) = std::env::var_os("HOME") {
+        return PathBuf::from(home)
+            .join(".local")
+            .join("state")
+            .join("tv")
+            .join("journal");
+    }
+    std::env::temp_dir().join("tv").join("journal")
+}
+
+fn new_transaction_id() -> String {
+    // Sortable by creation time; the suffix separates concurrent invocations
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    format!(
+        "{}-{:08x}",
+        now.as_secs(),
+        now.subsec_nanos() ^ std::process::id()
+    )
+}
+
+impl Transaction {
+    pub fn begin(journal_dir: &Path, command: &str) -> Transaction {
+        let id = new_transaction_id();
+        Transaction {
+            dir: journal_dir.join(&id),
+            meta: TransactionMeta {
+                id,
+                created: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_secs())
+                    .unwrap_or_default(),
+                command: command.to_string(),
+                entries: Vec::new(),
+                undone: false,
+                reason: None,
+                undo_reason: None,
+            },
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.meta.id
+    }
+
+    pub fn record(&mut self, path: &Path, before: Option<&str>, after: &str) -> Result<()> {
+        // The directory is created lazily so read-only commands leave no trace
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create journal directory: {:?}", self.dir))?;
+        let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+
+        let existing = self.meta.entries.iter().position(|e| e.path == path);
+        let index = existing.unwrap_or(self.meta.entries.len());
+        let after_name = format!("{}.after", index);
+        fs::write(self.dir.join(&after_name), after)?;
+
+        // A file written twice keeps its original backup
+        if existing.is_none() {
+            let before_name = match before {
+                Some(content) => {
+                    let name = format!("{}.before", index);
+                    fs::write(self.dir.join(&name), content)?;
+                    Some(name)
+                }
+                None => None,
+            };
+            self.meta.entries.push(JournalEntry {
+                path,
+                before: before_name,
+                after: after_name,
+            });
+        }
+
+        self.save()
+    }
+
+    fn save(&self) -> Result<()> {
+        let json = serde_json::to_string_pretty(&self.meta)?;
+        fs::write(self.dir.join(JOURNAL_FILE), json)
+            .with_context(|| format!("Failed to write journal: {:?}", self.dir))
+    }
+}
+
+pub fn activate(txn: Transaction) {
+    ACTIVE.with(|active| *active.borrow_mut() = Some(txn));
+}
+
+pub fn deactivate() -> Option<Transaction> {
+    ACTIVE.with(|active| active.borrow_mut().take())
+}
+
+pub(crate) fn record_write(path: &Path, before: Option<&str>, after: &str) -> Result<()> {
+    ACTIVE.with(|active| match active.borrow_mut().as_mut() {
+        Some(txn) => txn.record(path, before, after),
+        None => Ok(()),
+    })
+}
+
+pub fn load_transaction(journal_dir: &Path, id: &str) -> Result<TransactionMeta> {
+    let path = journal_dir.join(id).join(JOURNAL_FILE);
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Transaction not found: {}", id))?;
+    serde_json::from_str(&content).with_context(|| format!("Corrupt journal: {:?}", path))
+}
+
+pub fn list_transactions(journal_dir: &Path) -> Result<Vec<TransactionMeta>> {
+    let mut transactions = Vec::new();
+    if !journal_dir.is_dir() {
+        return Ok(transactions);
+    }
+
+    for entry in fs::read_dir(journal_dir)? {
+        let path = entry?.path();
+        if path.join(JOURNAL_FILE).is_file() {
+            let id = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+            transactions.push(load_transaction(journal_dir, &id)?);
+        }
+    }
+
+    transactions.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(transactions)
+}
+
+pub fn undo_transaction(journal_dir: &Path, id: &str, force: bool) -> Result<Vec<PathBuf>> {
+    let mut meta = load_transaction(journal_dir, id)?;
+    if meta.undone {
+        return Err(anyhow!("Transaction {} was already undone", id));
+    }
+    let txn_dir = journal_dir.join(id);
+
+    // Refuse to clobber edits made after the transaction
+    if !force {
+        for entry in &meta.entries {
+            let expected = fs::read_to_string(txn_dir.join(&entry.after))?;
+            let current = fs::read_to_string(&entry.path).ok();
+            if current.as_deref() != Some(expected.as_str()) {
+                return Err(anyhow!(
+                    "{:?} changed since transaction {}; use --force to restore anyway",
+                    entry.path,
+                    id
+                ));
+            }
+        }
+    }
+
+    // Restores are edits too: protected_paths and require_reason apply
+    // before any is made
+    for entry in &meta.entries {
+        crate::protect::check_write(&entry.path)?;
+    }
+
+    let mut restored = Vec::new();
+    for entry in meta.entries.iter().rev() {
+        match entry.before {
+            Some(ref before) => {
+                let content = fs::read_to_string(txn_dir.join(before))?;
+                crate::replace_file(&entry.path, &content)
+                    .with_context(|| format!("Failed to restore file: {:?}", entry.path))?;
+            }
+            None => {
+                fs::remove_file(&entry.path)
+                    .with_context(|| format!("Failed to remove file: {:?}", entry.path))?;
+            }
+        }
+        restored.push(entry.path.clone());
+    }
+
+    meta.undone = true;
+    meta.undo_reason = crate::protect::reason();
+    fs::write(txn_dir.join(JOURNAL_FILE), serde_json::to_string_pretty(&meta)?)?;
+    Ok(restored)
+}
@@ -0,0 +1,181 @@
+// Directory labels and Kubernetes-style selectors: `--selector
+// 'team=payments,tier!=dev'` on scan, check-updates and upgrade targets the
+// directories of a monorepo by metadata instead of path globs.
+//
+// A directory is labelled by a `tv.labels` file in it, or by tv.toml:
+//
+// [labels]
+// "envs/prod"       = "tier=prod"       # directory relative to tv.toml
+// "teams/payments*" = "team=payments"   # * matches any characters
+//
+// Labels are inherited by subdirectories; a nearer directory overrides a
+// label set further up, and a tv.labels file overrides tv.toml for its own
+// directory. Ancestors are read up to the directory holding tv.toml (or
+// .git). Selector requirements are and-ed: `key=value` (or `==`),
+// `key!=value` (also true when the key is absent), `key` and `!key`.
+
+use anyhow::{Context, Result, anyhow};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::{CONFIG_FILE, find_config, load_config};
+use crate::wildcard_match;
+
+pub const LABELS_FILE: &str = "tv.labels";
+
+pub type Labels = BTreeMap<String, String>;
+
+fn valid_key(key: &str) -> bool {
+    !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || "._-/".contains(c))
+}
+
+fn valid_value(value: &str) -> bool {
+    value.chars().all(|c| c.is_ascii_alphanumeric() || "._-".contains(c))
+}
+
+pub fn parse_labels(text: &str) -> Result<Labels> {
+    // `team=payments, tier=prod`; commas or newlines, `#` starts a comment
+    let mut labels = Labels::new();
+    for line in text.lines() {
+        let line = line.split('#').next().unwrap_or_default();
+        for entry in line.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (key, value) = entry
+                .split_once('=')
+                .map(|(k, v)| (k.trim(), v.trim()))
+                .ok_or_else(|| anyhow!("Invalid label '{}'; expected key=value", entry))?;
+            if !valid_key(key) || !valid_value(value) {
+                return Err(anyhow!("Invalid label '{}'", entry));
+            }
+            labels.insert(key.to_string(), value.to_string());
+        }
+    }
+    Ok(labels)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Requirement {
+    Equals(String, String),
+    NotEquals(String, String),
+    Exists(String),
+    NotExists(String),
+}
+
+impl Requirement {
+    pub fn matches(&self, labels: &Labels) -> bool {
+        match self {
+            Requirement::Equals(key, value) => labels.get(key) == Some(value),
+            Requirement::NotEquals(key, value) => labels.get(key) != Some(value),
+            Requirement::Exists(key) => labels.contains_key(key),
+            Requirement::NotExists(key) => !labels.contains_key(key),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Selector {
+    pub requirements: Vec<Requirement>,
+}
+
+impl std::str::FromStr for Selector {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Selector> {
+        let mut requirements = Vec::new();
+        for part in s.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let requirement = if let Some((key, value)) = part.split_once("!=") {
+                Requirement::NotEquals(key.trim().to_string(), value.trim().to_string())
+            } else if let Some((key, value)) = part.split_once("==").or_else(|| part.split_once('=')) {
+                Requirement::Equals(key.trim().to_string(), value.trim().to_string())
+            } else if let Some(key) = part.strip_prefix('!') {
+                Requirement::NotExists(key.trim().to_string())
+            } else {
+                Requirement::Exists(part.to_string())
+            };
+            let valid = match &requirement {
+                Requirement::Equals(key, value) | Requirement::NotEquals(key, value) => valid_key(key) && valid_value(value),
+                Requirement::Exists(key) | Requirement::NotExists(key) => valid_key(key),
+            };
+            if !valid {
+                return Err(anyhow!("Invalid selector requirement '{}'", part));
+            }
+            requirements.push(requirement);
+        }
+        if requirements.is_empty() {
+            return Err(anyhow!("Selector cannot be empty"));
+        }
+        Ok(Selector { requirements })
+    }
+}
+
+impl Selector {
+    pub fn matches(&self, labels: &Labels) -> bool {
+        self.requirements.iter().all(|r| r.matches(labels))
+    }
+}
+
+fn is_top(dir: &Path) -> bool {
+    dir.join(CONFIG_FILE).is_file() || dir.join(".git").exists()
+}
+
+pub fn directory_labels(dir: &Path) -> Result<Labels> {
+    let dir = dir.canonicalize().with_context(|| format!("Failed to resolve directory: {:?}", dir))?;
+    let root = find_config(&dir).and_then(|path| path.parent().map(Path::to_path_buf));
+    let rules = match root {
+        Some(ref root) => load_config(root)?.labels,
+        None => BTreeMap::new(),
+    };
+
+    // Outermost first, so nearer directories override
+    let mut chain: Vec<&Path> = Vec::new();
+    for ancestor in dir.ancestors() {
+        chain.push(ancestor);
+        if is_top(ancestor) {
+            break;
+        }
+    }
+
+    let mut labels = Labels::new();
+    for ancestor in chain.into_iter().rev() {
+        if let Some(ref root) = root
+            && let Ok(relative) = ancestor.strip_prefix(root)
+        {
+            let relative = relative.to_string_lossy().replace('\\', "/");
+            let relative = if relative.is_empty() { "." } else { relative.as_str() };
+            for (pattern, text) in &rules {
+                if wildcard_match(pattern.trim_end_matches('/'), relative) {
+                    let set = parse_labels(text).with_context(|| format!("In [labels] \"{}\" of {}", pattern, CONFIG_FILE))?;
+                    labels.extend(set);
+                }
+            }
+        }
+        let marker = ancestor.join(LABELS_FILE);
+        if marker.is_file() {
+            let text = fs::read_to_string(&marker).with_context(|| format!("Failed to read file: {:?}", marker))?;
+            labels.extend(parse_labels(&text).with_context(|| format!("In {:?}", marker))?);
+        }
+    }
+    Ok(labels)
+}
+
+pub fn select_files(files: Vec<PathBuf>, selector: &Selector) -> Result<Vec<PathBuf>> {
+    // Keeps the files whose directory matches; each directory is read once
+    let mut cache: HashMap<PathBuf, bool> = HashMap::new();
+    let mut selected = Vec::new();
+    for file in files {
+        let dir = file.parent().map(Path::to_path_buf).unwrap_or_default();
+        let dir = if dir.as_os_str().is_empty() { PathBuf::from(".") } else { dir };
+        let matches = match cache.get(&dir) {
+            Some(matches) => *matches,
+            None => {
+                let matches = selector.matches(&directory_labels(&dir)?);
+                cache.insert(dir, matches);
+                matches
+            }
+        };
+        if matches {
+            selected.push(file);
+        }
+    }
+    Ok(selected)
+}
@@ -1,106 +1,82 @@
 // Re-export the main functions and types for testing
 use anyhow::{Context, Result, anyhow};
-use hcl_edit::Ident;
+use hcl_edit::Decorate;
 use hcl_edit::expr::Expression;
 use hcl_edit::structure::{Attribute, Body};
 use std::fs;
 use std::path::PathBuf;
 
-#[derive(Debug)]
-pub struct Query {
-    pub block_type: String,
-    pub block_label: Option<String>,
-    pub nested_blocks: Vec<String>,
-    pub attribute: String,
-    pub index: Option<String>,
-}
-
-#[derive(Debug)]
-pub struct ScanQuery {
-    pub block_type: String,
-    pub block_label: Option<String>,  // None means wildcard
-    pub nested_blocks: Vec<String>,
-    pub attribute: Option<String>,  // None if we're just matching the block
-    pub filter: Option<AttributeFilter>,
-}
-
-#[derive(Debug)]
-pub struct AttributeFilter {
-    pub attribute: String,
-    pub value: String,
-}
-
-pub fn parse_query(query: &str) -> Result<Query> {
-    // Expected formats:
-    // - module.name.attribute (simple: block with label)
-    // - module.name.source["ref"] (simple with index)
-    // - terraform.required_providers.aws.source (nested: terraform block -> required_providers block -> aws object attr -> source field)
-
-    let parts: Vec<&str> = query.split('.').collect();
-    if parts.len() < 2 {
-        return Err(anyhow!(
-            "Query must have at least 2 parts: block_type.attribute or block_type.label.attribute"
-        ));
-    }
-
-    let block_type = parts[0].to_string();
-    
-    // Parse the rest - could be label.attribute or nested.blocks.attribute
-    // We need to figure out the last part with optional index as the attribute
-    let rest = parts[1..].join(".");
-    let (rest_without_index, index) = if let Some(bracket_start) = rest.find('[') {
-        let bracket_end = rest
-            .find(']')
-            .ok_or_else(|| anyhow!("Unclosed bracket in query"))?;
-        let rest_part = rest[..bracket_start].to_string();
-        let idx = rest[bracket_start + 1..bracket_end]
-            .trim_matches('"')
-            .to_string();
-        (rest_part, Some(idx))
-    } else {
-        (rest, None)
-    };
-
-    // Split the rest into parts
-    let remaining_parts: Vec<&str> = rest_without_index.split('.').collect();
-    
-    if remaining_parts.is_empty() {
-        return Err(anyhow!("Query must include an attribute"));
-    }
-    
-    // The last part is always the attribute
-    let attribute = remaining_parts.last().unwrap().to_string();
-    
-    // Everything in between is either a label or nested blocks
-    let middle_parts: Vec<String> = remaining_parts[..remaining_parts.len() - 1]
-        .iter()
-        .map(|s| s.to_string())
-        .collect();
-    
-    // Determine if we have a simple block_type.label.attribute pattern
-    // or a nested block pattern
-    let (block_label, nested_blocks) = if middle_parts.len() == 1 {
-        // Simple pattern: module.vpc.source -> label is "vpc"
-        (Some(middle_parts[0].clone()), vec![])
-    } else if middle_parts.is_empty() {
-        // Pattern: terraform.attribute -> no label
-        (None, vec![])
-    } else {
-        // Nested pattern: terraform.required_providers.aws.source
-        // Need to determine which parts are blocks vs attributes
-        // For now, we'll assume all middle parts could be either blocks or attributes
-        // and handle them dynamically
-        (None, middle_parts.clone())
-    };
-
-    Ok(Query {
-        block_type,
-        block_label,
-        nested_blocks,
-        attribute,
-        index,
-    })
-}
+pub mod add;
+pub mod backup;
+pub mod bench;
+pub mod bulk;
+pub mod bump;
+pub mod cancel;
+pub mod centralize;
+pub mod completions;
+pub mod config;
+pub mod constraint;
+pub mod context;
+pub mod conventions;
+pub mod dirstats;
+pub mod doctor;
+pub mod duplicates;
+pub mod effective;
+pub mod explain;
+pub mod fleet;
+pub mod graph;
+pub mod grep;
+pub mod guides;
+pub mod history;
+pub mod impact;
+pub mod index;
+pub mod info;
+pub mod indirection;
+pub mod init;
+pub mod inventory;
+pub mod journal;
+pub mod labels;
+pub mod list;
+pub mod map;
+pub mod matches;
+pub mod normalize;
+pub mod outdated;
+pub mod output;
+pub mod parallel;
+pub mod paths;
+pub mod pin_age;
+pub mod plan;
+pub mod policy;
+pub mod promote;
+pub mod protect;
+pub mod query;
+pub mod registry;
+pub mod remote;
+pub mod rename;
+pub mod resolved;
+pub mod self_update;
+pub mod similar;
+pub mod source;
+pub mod stable;
+pub mod stats;
+pub mod style;
+pub mod submodules;
+pub mod tacos;
+pub mod template;
+pub mod terraformrc;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod tolerant;
+pub mod tfc;
+pub mod unset;
+pub mod updates;
+pub mod usage;
+pub mod workspaces;
+
+pub use query::{
+    AttributeFilter, Query, ScanQuery, block_matches, parse_attribute_filter, parse_query,
+    parse_scan_query, split_query_path,
+};
 
 pub fn find_tf_file(path: Option<&std::path::Path>) -> Result<PathBuf> {
     if let Some(p) = path {
@@ -127,177 +103,188 @@ pub fn find_tf_file(path: Option<&std::path::Path>) -> Result<PathBuf> {
     find_tf_file(Some(&current_dir))
 }
 
-pub fn get_value(query: &str, file: Option<&std::path::Path>) -> Result<Option<String>> {
-    let parsed_query = parse_query(query)?;
-    let file_path = find_tf_file(file)?;
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ValueKind {
+    String,
+    Number,
+    Bool,
+    Null,
+    List,
+    Object,
+    Expression,  // References, function calls, interpolated templates, ...
+}
 
-    let content = fs::read_to_string(&file_path)
-        .with_context(|| format!("Failed to read file: {:?}", file_path))?;
+impl ValueKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ValueKind::String => "string",
+            ValueKind::Number => "number",
+            ValueKind::Bool => "bool",
+            ValueKind::Null => "null",
+            ValueKind::List => "list",
+            ValueKind::Object => "object",
+            ValueKind::Expression => "expression",
+        }
+    }
+}
 
-    let body: Body = content
-        .parse()
-        .with_context(|| format!("Failed to parse HCL: {:?}", file_path))?;
+impl std::str::FromStr for ValueKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "string" => Ok(ValueKind::String),
+            "number" => Ok(ValueKind::Number),
+            "bool" => Ok(ValueKind::Bool),
+            "null" => Ok(ValueKind::Null),
+            "list" => Ok(ValueKind::List),
+            "object" => Ok(ValueKind::Object),
+            "expression" => Ok(ValueKind::Expression),
+            _ => Err(anyhow!("Unknown type '{}' (expected string, number, bool, null, list, object or expression)", s)),
+        }
+    }
+}
 
-    // Find the block
-    for structure in body.iter() {
-        if let Some(block) = structure.as_block()
-            && block.ident.as_str() == parsed_query.block_type
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypedValue {
+    pub value: String,
+    pub kind: ValueKind,
+}
+
+pub fn expression_kind(expr: &Expression) -> ValueKind {
+    match expr {
+        Expression::String(_) => ValueKind::String,
+        Expression::Number(_) => ValueKind::Number,
+        Expression::Bool(_) => ValueKind::Bool,
+        Expression::Null(_) => ValueKind::Null,
+        Expression::Array(_) => ValueKind::List,
+        Expression::Object(_) => ValueKind::Object,
+        // A heredoc without interpolations is still a plain string
+        Expression::HeredocTemplate(heredoc)
+            if heredoc
+                .template
+                .iter()
+                .all(|e| matches!(e, hcl_edit::template::Element::Literal(_))) =>
         {
-            // Check labels if we expect one
-            if let Some(ref expected_label) = parsed_query.block_label {
-                let labels: Vec<String> = block
-                    .labels
-                    .iter()
-                    .map(|l| l.as_str())
-                    .map(|s| s.to_string())
-                    .collect();
-
-                if labels.first().map(|s| s.as_str()) != Some(expected_label.as_str()) {
-                    continue;
-                }
-            }
-            
-            // Navigate through nested blocks if any
-            let mut current_body = &block.body;
-            let mut attr_path = vec![];
-            
-            for (idx, nested_name) in parsed_query.nested_blocks.iter().enumerate() {
-                let mut found_as_block = false;
-                
-                // Try to find as a nested block first
-                for item in current_body.iter() {
-                    if let Some(nested_block) = item.as_block() {
-                        let nested_ident = nested_block.ident.as_str();
-                        let nested_labels: Vec<String> = nested_block
-                            .labels
-                            .iter()
-                            .map(|l| l.as_str())
-                            .map(|s| s.to_string())
-                            .collect();
-                        
-                        if nested_ident == nested_name 
-                            || nested_labels.first().map(|s| s.as_str()) == Some(nested_name) {
-                            current_body = &nested_block.body;
-                            found_as_block = true;
-                            break;
-                        }
-                    }
-                }
-                
-                // If not found as a block, treat remaining parts as attribute path
-                if !found_as_block {
-                    attr_path = parsed_query.nested_blocks[idx..].to_vec();
-                    attr_path.push(parsed_query.attribute.clone());
-                    break;
-                }
-            }
-            
-            // If we have an attribute path, navigate through object attributes
-            if !attr_path.is_empty() {
-                return navigate_object_attributes(current_body, &attr_path, parsed_query.index.as_deref());
-            }
-            
-            // Find the attribute in the final body
-            for attr_item in current_body.iter() {
-                if let Some(attr) = attr_item.as_attribute()
-                    && attr.key.as_str() == parsed_query.attribute
-                {
-                    let value_str = attr.value.to_string();
+            ValueKind::String
+        }
+        _ => ValueKind::Expression,
+    }
+}
 
-                    if let Some(ref index_key) = parsed_query.index {
-                        return extract_param_from_source(&value_str, index_key);
-                    }
+pub fn value_kind(raw: &str) -> ValueKind {
+    match raw.trim().parse::<Expression>() {
+        Ok(expr) => expression_kind(&expr),
+        Err(_) => ValueKind::Expression,
+    }
+}
 
-                    return Ok(Some(value_str.trim().trim_matches('"').to_string()));
-                }
-            }
-        }
+pub(crate) fn typed_from_raw(raw: &str) -> TypedValue {
+    // String literals are unescaped, so what set wrote reads back as given
+    let value = match raw.trim().parse::<Expression>() {
+        Ok(Expression::String(s)) => s.value().to_string(),
+        _ => raw.trim().trim_matches('"').to_string(),
+    };
+    TypedValue {
+        value,
+        kind: value_kind(raw),
     }
+}
 
-    Ok(None)
+pub(crate) fn typed_param(source: &str, param_name: &str) -> Result<Option<TypedValue>> {
+    // Source parameters (ref, url, path, ...) are always strings
+    Ok(extract_param_from_source(source, param_name)?.map(|value| TypedValue {
+        value,
+        kind: ValueKind::String,
+    }))
 }
 
-fn navigate_object_attributes(
-    body: &hcl_edit::structure::Body,
-    attr_path: &[String],
-    index: Option<&str>,
-) -> Result<Option<String>> {
-    if attr_path.is_empty() {
+pub fn get_value(query: &str, file: Option<&std::path::Path>) -> Result<Option<String>> {
+    Ok(get_typed_value(query, file)?.map(|v| v.value))
+}
+
+pub fn get_typed_value(query: &str, file: Option<&std::path::Path>) -> Result<Option<TypedValue>> {
+    let parsed_query = parse_query(query)?;
+    let file_path = find_tf_file(file)?;
+    let body = read_tf_body(&file_path)?;
+    get_from_blocks(body.iter().filter_map(|s| s.as_block()), &parsed_query)
+}
+
+pub fn get_typed_value_resolved(query: &str, file: Option<&std::path::Path>) -> Result<Option<TypedValue>> {
+    // Like get_typed_value, but follows local./var. references (see indirection.rs)
+    let Some(typed) = get_typed_value(query, file)? else {
         return Ok(None);
+    };
+    let file_path = find_tf_file(file)?;
+    let dir = directory_of(&file_path);
+
+    if let Some(reference) = indirection::as_reference(&typed.value) {
+        return Ok(Some(match indirection::resolve_reference(dir, &reference)? {
+            Some(value) => typed_from_raw(&value.to_string()),
+            None => typed,
+        }));
     }
-    
-    let first_attr = &attr_path[0];
-    
-    // Find the first attribute in the body
-    for item in body.iter() {
-        if let Some(attr) = item.as_attribute() {
-            if attr.key.as_str() == first_attr {
-                // Get the value and navigate deeper if needed
-                let value_str = attr.value.to_string();
-                
-                if attr_path.len() == 1 {
-                    // This is the final attribute
-                    if let Some(index_key) = index {
-                        return extract_param_from_source(&value_str, index_key);
-                    }
-                    return Ok(Some(value_str.trim().trim_matches('"').to_string()));
-                } else {
-                    // Need to navigate deeper into the object
-                    return extract_from_object_string(&value_str, &attr_path[1..], index);
-                }
-            }
+    Ok(Some(TypedValue {
+        value: indirection::resolve(dir, &typed.value)?,
+        ..typed
+    }))
+}
+
+pub(crate) fn directory_of(file_path: &std::path::Path) -> &std::path::Path {
+    match file_path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => std::path::Path::new("."),
+    }
+}
+
+pub fn get_typed_value_indexed(query: &str, dir: &std::path::Path) -> Result<Option<TypedValue>> {
+    Ok(locate_typed_value_indexed(query, dir)?.map(|(_, typed)| typed))
+}
+
+pub fn locate_typed_value_indexed(query: &str, dir: &std::path::Path) -> Result<Option<(PathBuf, TypedValue)>> {
+    // Looks through every .tf file directly in `dir`, using index entries
+    // for unchanged files and parsing the rest; returns the file it is in
+    let parsed_query = parse_query(query)?;
+    let index = index::load(&index::default_index_path(dir))?;
+
+    let mut tf_files: Vec<PathBuf> = find_all_tf_files(dir)?
+        .into_iter()
+        .filter(|p| p.parent() == Some(dir))
+        .collect();
+    tf_files.sort();
+
+    for file_path in tf_files {
+        let blocks: Vec<hcl_edit::structure::Block> = match index.fresh_file(dir, &file_path) {
+            Some(indexed) => indexed.candidates(&parsed_query).collect::<Result<_>>()?,
+            None => read_tf_body(&file_path)?
+                .into_iter()
+                .filter_map(|s| s.into_block().ok())
+                .collect(),
+        };
+        if let Some(value) = get_from_blocks(blocks.iter(), &parsed_query)? {
+            return Ok(Some((file_path, value)));
         }
     }
-    
     Ok(None)
 }
 
-fn extract_from_object_string(
-    object_str: &str,
-    attr_path: &[String],
-    index: Option<&str>,
-) -> Result<Option<String>> {
-    // Parse the object string to extract nested attribute value
-    // object_str looks like: {source = "hashicorp/aws", version = "6.15.0"}
-    // or multi-line:
-    // {
-    //   source = "hashicorp/aws"
-    //   version = "6.15.0"
-    // }
-    
-    if attr_path.is_empty() {
-        return Ok(None);
-    }
-    
-    let target_attr = &attr_path[0];
-    
-    // Clean up the object string - remove braces and whitespace
-    let cleaned = object_str.trim().trim_matches(|c| c == '{' || c == '}').trim();
-    
-    // Parse line by line or by looking for the pattern
-    // Look for pattern: attr_name = "value" or attr_name = value
-    let pattern = format!("{} =", target_attr);
-    if let Some(start_idx) = cleaned.find(&pattern) {
-        let after_equals = &cleaned[start_idx + pattern.len()..].trim_start();
-        
-        // Extract the value - could be quoted or unquoted
-        // Value ends at newline or comma or closing brace
-        let value_end = after_equals
-            .find(&[',', '\n', '}'][..])
-            .unwrap_or(after_equals.len());
-        let value = after_equals[..value_end].trim().trim_matches('"').to_string();
-        
-        if attr_path.len() == 1 {
-            if let Some(index_key) = index {
-                return extract_param_from_source(&format!("\"{}\"", value), index_key);
-            }
-            return Ok(Some(value));
-        } else {
-            // More nesting - recursively extract
-            return extract_from_object_string(&value, &attr_path[1..], index);
+fn get_from_blocks<'a>(
+    blocks: impl Iterator<Item = &'a hcl_edit::structure::Block>,
+    parsed_query: &Query,
+) -> Result<Option<TypedValue>> {
+    // The first block the query resolves in wins
+    for block in blocks {
+        if !block_matches(block, parsed_query) {
+            continue;
+        }
+        if let Ok(query::Target::Value(value)) = query::resolve(block, parsed_query) {
+            return match parsed_query.index {
+                Some(ref index_key) => query::select(value, index_key),
+                None => Ok(Some(typed_from_raw(&value.to_string()))),
+            };
         }
     }
-    
+
     Ok(None)
 }
 
@@ -333,41 +320,17 @@ pub fn extract_url_from_source(source: &str) -> String {
     // or: github.com/org/repo.git//path?ref=version
     // or: terraform-aws-modules/vpc/aws (registry)
     // or: ./modules/vpc (local)
-
-    // Keep the git:: prefix if present
-    let url_start = source;
-    let search_start = if source.starts_with("git::") {
-        // Skip the git:: prefix for searching but include it in result
-        5
-    } else {
-        0
+    //
+    // The URL ends at the query string or at the `//` that starts a
+    // subdirectory (not the one after the scheme), whichever comes first.
+    // The git:: prefix is kept.
+    let without_query = source.split_once('?').map_or(source, |(url, _)| url);
+    let (scheme, rest) = match without_query.split_once("://") {
+        Some((scheme, rest)) => (format!("{}://", scheme), rest),
+        None => (String::new(), without_query),
     };
-
-    let mut url_end = source.len();
-
-    // Remove path component (starts with // but not part of https://)
-    // We need to find // that's NOT part of the protocol
-    if let Some(protocol_end) = source[search_start..].find("://") {
-        // Look for // after the protocol
-        let absolute_protocol_end = search_start + protocol_end + 3;
-        let after_protocol = &source[absolute_protocol_end..];
-        if let Some(path_idx) = after_protocol.find("//") {
-            // Found path delimiter after protocol
-            url_end = absolute_protocol_end + path_idx;
-        }
-    } else {
-        // No protocol, just look for //
-        if let Some(path_idx) = source[search_start..].find("//") {
-            url_end = search_start + path_idx;
-        }
-    }
-
-    // Check if there's a query string before the path delimiter
-    if let Some(query_idx) = source[..url_end].find('?') {
-        url_end = query_idx;
-    }
-
-    url_start[..url_end].to_string()
+    let url = rest.split_once("//").map_or(rest, |(url, _)| url);
+    format!("{}{}", scheme, url)
 }
 
 pub fn extract_path_from_source(source: &str) -> Option<String> {
@@ -403,32 +366,8 @@ fn navigate_to_nested_body_mut<'a>(
     nested_blocks: &[String],
 ) -> Result<&'a mut hcl_edit::structure::Body> {
     for nested_block_name in nested_blocks {
-        let mut found = false;
-        let mut idx = 0;
-        
-        // Find the index of the nested block
-        for (i, item) in body.iter().enumerate() {
-            if let Some(nested_block) = item.as_block() {
-                let nested_ident = nested_block.ident.as_str();
-                let nested_labels: Vec<String> = nested_block
-                    .labels
-                    .iter()
-                    .map(|l| l.as_str())
-                    .map(|s| s.to_string())
-                    .collect();
-                
-                if nested_ident == nested_block_name 
-                    || nested_labels.first().map(|s| s.as_str()) == Some(nested_block_name.as_str()) {
-                    found = true;
-                    idx = i;
-                    break;
-                }
-            }
-        }
-        
-        if !found {
-            return Err(anyhow!("Nested block '{}' not found", nested_block_name));
-        }
+        let idx = query::nested_block_index(body, nested_block_name)
+            .ok_or_else(|| anyhow!("Nested block '{}' not found", nested_block_name))?;
         
         // Navigate to the nested block's body
         if let Some(item) = body.get_mut(idx) {
@@ -445,75 +384,277 @@ fn navigate_to_nested_body_mut<'a>(
     Ok(body)
 }
 
+pub fn conflict_markers(content: &str) -> Vec<usize> {
+    // 1-based lines of unresolved merge conflict markers; `=======` alone
+    // only counts once a conflict has started
+    let lines: Vec<&str> = content.lines().collect();
+    if !lines.iter().any(|line| line.starts_with("<<<<<<<")) {
+        return Vec::new();
+    }
+    lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| {
+            ["<<<<<<<", ">>>>>>>", "|||||||"].iter().any(|marker| line.starts_with(marker)) || line.trim_end() == "======="
+        })
+        .map(|(i, _)| i + 1)
+        .collect()
+}
+
+pub(crate) fn parse_tf(content: &str, file_path: &std::path::Path) -> Result<Body> {
+    // A conflicted file gets a targeted error instead of the parser's
+    let markers = conflict_markers(content);
+    if !markers.is_empty() {
+        let lines: Vec<String> = markers.iter().map(|l| l.to_string()).collect();
+        return Err(anyhow!(
+            "Unresolved merge conflict in {:?} (markers at lines {}); resolve it first",
+            file_path,
+            lines.join(", ")
+        ));
+    }
+    content
+        .parse()
+        .with_context(|| format!("Failed to parse HCL: {:?}", file_path))
+}
+
+pub(crate) fn read_tf_body(file_path: &std::path::Path) -> Result<Body> {
+    cancel::check()?;
+    let content = fs::read_to_string(file_path)
+        .with_context(|| format!("Failed to read file: {:?}", file_path))?;
+    parse_tf(&content, file_path)
+}
+
+pub(crate) fn write_tf_body(file_path: &std::path::Path, body: &Body) -> Result<()> {
+    write_file(file_path, &body.to_string())
+}
+
+pub(crate) fn write_file(file_path: &std::path::Path, content: &str) -> Result<()> {
+    // Every edit goes through the undo journal (see journal.rs), after
+    // protected_paths are checked (see protect.rs) and the --backup copy is
+    // kept (see backup.rs)
+    cancel::check()?;
+    protect::check_write(file_path)?;
+    let before = fs::read_to_string(file_path).ok();
+    backup::save(file_path, before.as_deref())?;
+    journal::record_write(file_path, before.as_deref(), content)?;
+
+    replace_file(file_path, content)
+        .with_context(|| format!("Failed to write file: {:?}", file_path))
+}
+
+pub(crate) fn replace_file(file_path: &std::path::Path, content: &str) -> std::io::Result<()> {
+    // Write next to the file and rename over it, so an interrupted run
+    // never leaves it half-written. The copy is synced before the rename and
+    // takes the file's permissions and (where allowed) owner; a symlink is
+    // followed so the link itself stays in place.
+    let is_link = fs::symlink_metadata(file_path).is_ok_and(|m| m.file_type().is_symlink());
+    let target = if is_link { fs::canonicalize(file_path)? } else { file_path.to_path_buf() };
+    let name = target.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+    let temp = target.with_file_name(format!(".{}.{}.tv-tmp", name, std::process::id()));
+    let result = write_synced(&temp, content).and_then(|_| {
+        if let Ok(metadata) = fs::metadata(&target) {
+            fs::set_permissions(&temp, metadata.permissions())?;
+            keep_owner(&temp, &metadata);
+        }
+        fs::rename(&temp, &target)
+    });
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+fn write_synced(path: &std::path::Path, content: &str) -> std::io::Result<()> {
+    use std::io::Write;
+    let mut file = fs::File::create(path)?;
+    file.write_all(content.as_bytes())?;
+    file.sync_all()
+}
+
+#[cfg(unix)]
+fn keep_owner(path: &std::path::Path, metadata: &fs::Metadata) {
+    // Only root can give a file away, so a failure keeps the writer as owner
+    use std::os::unix::fs::MetadataExt;
+    let _ = std::os::unix::fs::chown(path, Some(metadata.uid()), Some(metadata.gid()));
+}
+
+#[cfg(not(unix))]
+fn keep_owner(_path: &std::path::Path, _metadata: &fs::Metadata) {}
+
+pub(crate) fn find_attribute_mut<'a>(body: &'a mut Body, query: &Query) -> Result<&'a mut Attribute> {
+    // Locate the attribute addressed by a query whose nested parts are all blocks
+    let block_pos = body
+        .iter()
+        .position(|s| s.as_block().map(|b| block_matches(b, query)).unwrap_or(false))
+        .ok_or_else(|| anyhow!("Block not found: {}", query.block_type))?;
+
+    let block = body
+        .get_mut(block_pos)
+        .and_then(|s| s.as_block_mut())
+        .ok_or_else(|| anyhow!("Expected block at index {}", block_pos))?;
+    let nested_body = navigate_to_nested_body_mut(&mut block.body, &query.nested_blocks)?;
+
+    let attr_pos = nested_body
+        .iter()
+        .position(|s| s.as_attribute().map(|a| a.key.as_str() == query.attribute_name()).unwrap_or(false))
+        .ok_or_else(|| anyhow!("Attribute '{}' not found in block", query.attribute_name()))?;
+
+    nested_body
+        .get_mut(attr_pos)
+        .and_then(|s| s.as_attribute_mut())
+        .ok_or_else(|| anyhow!("Expected attribute at index {}", attr_pos))
+}
+
+// How set writes the value
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct SetOptions {
+    pub heredoc: bool,  // Multi-line values that end in a newline as a <<EOT heredoc
+    pub allow_interpolation: bool,  // Keep ${...} and %{...} live instead of escaping them
+    pub kind: Option<ValueKind>,  // --type: write the value as this HCL type; None is a string
+    pub create: bool,  // Append the attribute to the block when it is missing
+    pub create_block: bool,  // Also create the block, nested blocks and object keys leading to it
+}
+
+pub fn has_interpolation(value: &str) -> bool {
+    value.contains("${") || value.contains("%{")
+}
+
+pub fn hcl_quote(value: &str) -> String {
+    // `"`, `\`, `${` and `%{` escaped, so the value reads back exactly as given
+    Expression::from(value).to_string()
+}
+
+pub fn value_expression(value: &str, options: &SetOptions) -> Result<Expression> {
+    // Strings unless --type asks for another HCL type, which the value must parse as
+    let kind = match options.kind {
+        None | Some(ValueKind::String) => return string_expression(value, options),
+        Some(kind) => kind,
+    };
+    let expr: Expression = value
+        .trim()
+        .parse()
+        .map_err(|_| anyhow!("Not a valid {} value: {}", kind.as_str(), value))?;
+    if kind != ValueKind::Expression && expression_kind(&expr) != kind {
+        return Err(anyhow!("Not a {} value: {}", kind.as_str(), value));
+    }
+    Ok(expr)
+}
+
+pub fn string_expression(value: &str, options: &SetOptions) -> Result<Expression> {
+    // Values may come from automation, so ${ and %{ are only live on request
+    let live = options.allow_interpolation && has_interpolation(value);
+
+    // A heredoc always ends in a newline, so only such values can be one
+    if options.heredoc
+        && let Some(text) = value.strip_suffix('\n')
+        && text.contains('\n')
+    {
+        let mut delimiter = "EOT".to_string();
+        while text.lines().any(|line| line.trim() == delimiter) {
+            delimiter.push('_');
+        }
+        let escaped = if live {
+            text.to_string()
+        } else {
+            text.replace("${", "$${").replace("%{", "%%{")
+        };
+        let heredoc = format!("<<{}\n{}\n{}", delimiter, escaped, delimiter);
+        return heredoc
+            .parse()
+            .with_context(|| format!("Failed to parse expression: {}", heredoc));
+    }
+    if live {
+        let template = hcl_quote(value).replace("$${", "${").replace("%%{", "%{");
+        return template
+            .parse()
+            .with_context(|| format!("Invalid interpolation in value: {}", value));
+    }
+    Ok(Expression::from(value))
+}
+
+pub fn check_current(query: &str, file: Option<&std::path::Path>, expected: &policy::Expectation) -> Result<()> {
+    // `tv set --if-current`: the value set would replace, read through a
+    // local or variable as set writes through it, must be the expected one
+    let current = get_typed_value_resolved(query, file)?;
+    expected
+        .check(current.as_ref().map(|c| c.value.as_str()))
+        .map_err(|reason| anyhow!("Unexpected current value for {}: {}", query, reason))
+}
+
 pub fn set_value(query: &str, value: &str, file: Option<&std::path::Path>) -> Result<()> {
+    set_value_with_options(query, value, file, &SetOptions::default())
+}
+
+pub fn set_value_with_options(
+    query: &str,
+    value: &str,
+    file: Option<&std::path::Path>,
+    options: &SetOptions,
+) -> Result<()> {
     let parsed_query = parse_query(query)?;
-    let file_path = find_tf_file(file)?;
+    // With --create-block a file that does not exist yet is created
+    let file_path = match file {
+        Some(path) if options.create_block && !path.exists() => path.to_path_buf(),
+        _ => find_tf_file(file)?,
+    };
 
-    let content = fs::read_to_string(&file_path)
-        .with_context(|| format!("Failed to read file: {:?}", file_path))?;
+    // A value that only refers to a local or variable (e.g. a centralized
+    // version) is changed where it is defined
+    if file_path.exists()
+        && let Some(current) = get_typed_value(query, Some(&file_path))?
+        && let Some(reference) = indirection::as_reference(&current.value)
+    {
+        indirection::update_reference(directory_of(&file_path), &reference, value_expression(value, options)?)?;
+        return Ok(());
+    }
+
+    let content = match fs::read_to_string(&file_path) {
+        Err(_) if options.create_block && !file_path.exists() => String::new(),
+        content => content.with_context(|| format!("Failed to read file: {:?}", file_path))?,
+    };
 
     let mut body: Body = content
         .parse()
         .with_context(|| format!("Failed to parse HCL: {:?}", file_path))?;
 
+    if options.create_block && create_path(&mut body, &parsed_query, &value_expression(value, options)?)? {
+        return write_tf_body(&file_path, &body);
+    }
+    let options = &SetOptions { create: options.create || options.create_block, ..*options };
+
     // Find the block
     let mut found = false;
     for mut structure in body.iter_mut() {
         if let Some(block) = structure.as_block_mut()
-            && block.ident.as_str() == parsed_query.block_type
+            && block_matches(block, &parsed_query)
         {
-            // Check labels if we expect one
-            if let Some(ref expected_label) = parsed_query.block_label {
-                let labels: Vec<String> = block
-                    .labels
-                    .iter()
-                    .map(|l| l.as_str())
-                    .map(|s| s.to_string())
-                    .collect();
-
-                if labels.first().map(|s| s.as_str()) != Some(expected_label.as_str()) {
-                    continue;
-                }
-            }
+            let attribute = parsed_query.attribute_name().to_string();
             
             // Navigate through nested blocks and determine if we need to handle object attributes
             let mut current_body = &mut block.body;
             let mut attr_path = vec![];
             let mut navigated_blocks = 0;
             
-            for (idx, nested_name) in parsed_query.nested_blocks.iter().enumerate() {
-                let mut found_as_block = false;
-                
-                // Try to find as a nested block first  
-                // We need to check without borrowing mutably yet
-                for item in current_body.iter() {
-                    if let Some(nested_block) = item.as_block() {
-                        let nested_ident = nested_block.ident.as_str();
-                        let nested_labels: Vec<String> = nested_block
-                            .labels
-                            .iter()
-                            .map(|l| l.as_str())
-                            .map(|s| s.to_string())
-                            .collect();
-                        
-                        if nested_ident == nested_name 
-                            || nested_labels.first().map(|s| s.as_str()) == Some(nested_name) {
-                            found_as_block = true;
-                            break;
-                        }
+            // Leading segments that name nested blocks (at each level in turn);
+            // we need to check without borrowing mutably yet
+            let mut probe = &*current_body;
+            for nested_name in &parsed_query.nested_blocks {
+                let nested = query::nested_block_index(probe, nested_name)
+                    .and_then(|i| probe.get(i))
+                    .and_then(|s| s.as_block());
+                match nested {
+                    Some(nested) => {
+                        probe = &nested.body;
+                        navigated_blocks += 1;
                     }
-                }
-                
-                if found_as_block {
-                    // Navigate using the helper function for the blocks we found
-                    navigated_blocks = idx + 1;
-                } else {
-                    // Rest are object attributes
-                    attr_path = parsed_query.nested_blocks[idx..].to_vec();
-                    attr_path.push(parsed_query.attribute.clone());
-                    break;
+                    None => break,
                 }
             }
+            if navigated_blocks < parsed_query.nested_blocks.len() {
+                // Rest are object attributes
+                attr_path = parsed_query.nested_blocks[navigated_blocks..].to_vec();
+                attr_path.push(attribute.clone());
+            }
             
             // Navigate to the deepest block level
             if navigated_blocks > 0 {
@@ -522,7 +663,7 @@ pub fn set_value(query: &str, value: &str, file: Option<&std::path::Path>) -> Re
             
             // If we have an attribute path, we need to update within an object
             if !attr_path.is_empty() {
-                update_object_attribute(current_body, &attr_path, value, parsed_query.index.as_deref())?;
+                update_object_attribute(current_body, &attr_path, value, parsed_query.index.as_deref(), options)?;
                 found = true;
                 break;
             }
@@ -530,18 +671,44 @@ pub fn set_value(query: &str, value: &str, file: Option<&std::path::Path>) -> Re
             // Otherwise, handle as a direct attribute
             let pos = current_body.iter().position(|s| {
                 s.as_attribute()
-                    .map(|a| a.key.as_str() == parsed_query.attribute)
+                    .map(|a| a.key.as_str() == attribute)
                     .unwrap_or(false)
             });
 
             if let Some(pos) = pos {
+                // Numeric indexes replace a single list element in place
+                if let Some(ref index_key) = parsed_query.index
+                    && let Ok(element_index) = index_key.parse::<usize>()
+                    && let Some(attr) = current_body.get_mut(pos).and_then(|s| s.as_attribute_mut())
+                    && let Expression::Array(ref mut array) = attr.value
+                {
+                    let element = value_expression(value, options)?;
+                    list::replace_element(array, element_index, element)?;
+                    found = true;
+                    break;
+                }
+
+                // Indexes into maps set (or add) a single key
+                if let Some(ref index_key) = parsed_query.index
+                    && let Some(attr) = current_body.get_mut(pos).and_then(|s| s.as_attribute_mut())
+                    && let Expression::Object(ref mut object) = attr.value
+                {
+                    let entry = value_expression(value, options)?;
+                    map::set_entry(object, index_key, entry);
+                    found = true;
+                    break;
+                }
+
                 // Get current value if we need to modify a parameter
-                let new_value_str = if let Some(ref index_key) = parsed_query.index {
+                let mut new_expr = if let Some(ref index_key) = parsed_query.index {
                     // Get the current value
                     if let Some(attr_struct) = current_body.get(pos) {
                         if let Some(attr) = attr_struct.as_attribute() {
                             let current_value = attr.value.to_string();
-                            update_param_in_source(&current_value, index_key, value)?
+                            let new_value_str = update_param_in_source(&current_value, index_key, value)?;
+                            new_value_str.parse().with_context(|| {
+                                format!("Failed to parse expression: {}", new_value_str)
+                            })?
                         } else {
                             return Err(anyhow!("Expected attribute at position"));
                         }
@@ -549,29 +716,24 @@ pub fn set_value(query: &str, value: &str, file: Option<&std::path::Path>) -> Re
                         return Err(anyhow!("Attribute not found at position"));
                     }
                 } else {
-                    format!("\"{}\"", value)
+                    value_expression(value, options)?
                 };
 
-                // Create new attribute
-                let new_expr: Expression = new_value_str.parse().with_context(|| {
-                    format!("Failed to parse expression: {}", new_value_str)
-                })?;
-                let key = Ident::new(parsed_query.attribute.clone());
-                let new_attr = Attribute::new(key, new_expr);
-
-                // Remove old and insert new
-                current_body.remove(pos);
-                current_body
-                    .try_insert(pos, new_attr)
-                    .map_err(|_| anyhow!("Failed to insert attribute"))?;
+                // Replace the value only, keeping indentation and comments
+                let Some(attr) = current_body.get_mut(pos).and_then(|s| s.as_attribute_mut()) else {
+                    return Err(anyhow!("Attribute not found at position"));
+                };
+                *new_expr.decor_mut() = attr.value.decor().clone();
+                attr.value = new_expr;
 
+                found = true;
+                break;
+            } else if options.create && parsed_query.index.is_none() {
+                append_attribute(current_body, &attribute, value_expression(value, options)?);
                 found = true;
                 break;
             } else {
-                return Err(anyhow!(
-                    "Attribute '{}' not found in block",
-                    parsed_query.attribute
-                ));
+                return Err(anyhow!("Attribute '{}' not found in block", attribute));
             }
         }
     }
@@ -584,170 +746,215 @@ pub fn set_value(query: &str, value: &str, file: Option<&std::path::Path>) -> Re
     }
 
     // Write back to file
-    fs::write(&file_path, body.to_string())?;
+    write_tf_body(&file_path, &body)
+}
+
+fn create_path(body: &mut Body, query: &Query, value: &Expression) -> Result<bool> {
+    // --create-block: add what is missing on the way to the value, laid out
+    // as `terraform fmt` would. Missing segments are blocks, except that
+    // provider entries of required_providers are objects (aws = { ... }) and
+    // a segment naming an existing attribute continues into its object.
+    // Returns whether the value was written along the way; if not, only the
+    // attribute itself may be missing and set appends it as --create does.
+    let blocks = match query.nested_blocks.iter().position(|s| s == "required_providers") {
+        Some(i) if query.block_type == "terraform" => i + 1,
+        _ => query.nested_blocks.len(),
+    };
+    let (nested, keys) = query.nested_blocks.split_at(blocks);
+    let mut keys: Vec<&str> = keys.iter().map(String::as_str).collect();
+    keys.push(query.attribute_name());
+    let creatable = || match query.index {
+        Some(ref index) => Err(anyhow!("--create-block cannot create an indexed value [\"{}\"]", index)),
+        None => Ok(()),
+    };
+
+    let Some(pos) = body.iter().position(|s| s.as_block().is_some_and(|b| block_matches(b, query))) else {
+        creatable()?;
+        let header = new_block_header(query)?;
+        let text = format!("{} {{\n{}}}\n", header, render_path(nested, &keys, value, 1));
+        let blank = if body.iter().next().is_some() { "\n" } else { "" };
+        insert_parsed(body, &text, blank)?;
+        return Ok(true);
+    };
+    let mut current = &mut body.get_mut(pos).and_then(|s| s.as_block_mut()).unwrap().body;
+
+    for (depth, segment) in nested.iter().enumerate() {
+        let Some(index) = query::nested_block_index(current, segment) else {
+            if current.get_attribute(segment).is_some() {
+                // An object value, e.g. tags = { ... }; keys are created below
+                let keys: Vec<&str> = nested[depth..].iter().map(String::as_str).chain(keys).collect();
+                return create_keys(current, &keys, value, depth + 1);
+            }
+            creatable()?;
+            let (name, nth) = query::split_repeat(segment);
+            let existing = current.iter().filter(|s| s.as_block().is_some_and(|b| b.ident.as_str() == name)).count();
+            if nth != existing {
+                return Err(anyhow!("Nested block '{}' not found; only {}#{} can be created next", segment, name, existing));
+            }
+            let mut segments = nested[depth..].to_vec();
+            segments[0] = name.to_string();
+            let text = render_path(&segments, &keys, value, depth + 1);
+            insert_parsed(current, &text, "")?;
+            return Ok(true);
+        };
+        current = &mut current.get_mut(index).and_then(|s| s.as_block_mut()).unwrap().body;
+    }
+    create_keys(current, &keys, value, nested.len() + 1)
+}
+
+fn create_keys(body: &mut Body, keys: &[&str], value: &Expression, depth: usize) -> Result<bool> {
+    // Object keys below an attribute (the first key), adding the missing ones
+    let Some(mut attribute) = body.get_attribute_mut(keys[0]) else {
+        if keys.len() == 1 {
+            return Ok(false);
+        }
+        let text = render_path(&[], keys, value, depth);
+        insert_parsed(body, &text, "")?;
+        return Ok(true);
+    };
+    let mut slot = attribute.value_mut();
+    for (level, key) in keys.iter().enumerate().skip(1) {
+        let Expression::Object(object) = slot else {
+            return Ok(false);
+        };
+        if map::get_entry(object, key).is_none() {
+            let text = render_path(&[], &keys[level..], value, depth + level);
+            let (_, entry) = text.trim().split_once(" = ").unwrap_or_default();
+            let entry: Expression = entry.parse().with_context(|| format!("Failed to parse expression: {}", entry))?;
+            map::set_entry(object, key, entry);
+            return Ok(true);
+        }
+        slot = map::get_entry_mut(object, key).unwrap();
+    }
+    Ok(false)
+}
+
+fn new_block_header(query: &Query) -> Result<String> {
+    // `module "vpc"`, `resource "aws_instance" "web"`, `terraform`
+    if query::address_attribute(&query.block_type).is_some() {
+        return Err(anyhow!("--create-block cannot create {} blocks", query.block_type));
+    }
+    let mut labels = vec![];
+    if query::block_takes_label(&query.block_type) {
+        labels.extend(query.block_label.iter());
+        if query::block_takes_name(&query.block_type) {
+            labels.extend(query.block_name.iter());
+        }
+    }
+    Ok(std::iter::once(query.block_type.clone()).chain(labels.into_iter().map(|l| hcl_quote(l))).collect::<Vec<_>>().join(" "))
+}
+
+fn render_path(blocks: &[String], keys: &[&str], value: &Expression, depth: usize) -> String {
+    // Nested blocks, then attribute and object keys, each a level deeper
+    let indent = "  ".repeat(depth);
+    match (blocks, keys) {
+        ([block, rest @ ..], _) => {
+            format!("{}{} {{\n{}{}}}\n", indent, block, render_path(rest, keys, value, depth + 1), indent)
+        }
+        ([], [key]) => format!("{}{} = {}\n", indent, key, value),
+        ([], [key, rest @ ..]) => {
+            let key = if hcl_edit::Ident::try_new(*key).is_ok() { key.to_string() } else { hcl_quote(key) };
+            format!("{}{} = {{\n{}{}}}\n", indent, key, render_path(&[], rest, value, depth + 1), indent)
+        }
+        ([], []) => String::new(),
+    }
+}
+
+fn insert_parsed(body: &mut Body, text: &str, prefix: &str) -> Result<()> {
+    // Append the structure rendered as `text`, keeping its layout
+    let parsed: Body = text.parse().with_context(|| format!("Not valid HCL once created:\n{}", text))?;
+    for mut structure in parsed.into_iter() {
+        if !prefix.is_empty() {
+            let indent = structure.decor().prefix().map(|p| p.to_string()).unwrap_or_default();
+            structure.decor_mut().set_prefix(format!("{}{}", prefix, indent));
+        }
+        body.set_prefer_oneline(false);
+        body.push(structure);
+    }
     Ok(())
 }
 
+fn append_attribute(body: &mut Body, key: &str, mut value: Expression) {
+    // After the last attribute (nested blocks follow the arguments), indented
+    // like it, with `=` lined up when the new key fits its column
+    let last = body.iter().enumerate().filter(|(_, s)| s.as_attribute().is_some()).map(|(i, _)| i).last();
+    let (indent, column) = match last.and_then(|i| body.get(i)).and_then(|s| s.as_attribute()) {
+        Some(previous) => {
+            let prefix = previous.decor().prefix().map(|p| p.to_string()).unwrap_or_default();
+            let indent = prefix.rsplit('\n').next().unwrap_or_default().to_string();
+            let suffix = previous.key.decor().suffix().map(|s| s.len()).unwrap_or(1);
+            (indent, previous.key.len() + suffix)
+        }
+        None => ("  ".to_string(), 0),
+    };
+    let padding = column.saturating_sub(key.len()).max(1);
+    let mut key = hcl_edit::Decorated::new(hcl_edit::Ident::new(key));
+    key.decor_mut().set_suffix(" ".repeat(padding));
+    value.decor_mut().set_prefix(" ");
+    let mut attribute = Attribute::new(key, value);
+    attribute.decor_mut().set_prefix(indent);
+    body.set_prefer_oneline(false);
+    body.insert(last.map_or(0, |i| i + 1), attribute);
+}
+
 fn update_object_attribute(
     body: &mut hcl_edit::structure::Body,
     attr_path: &[String],
     new_value: &str,
     index: Option<&str>,
+    options: &SetOptions,
 ) -> Result<()> {
+    // Walk the parsed object (e.g. `aws = { source = ..., version = ... }`)
+    // down to the value and replace it in place, keeping its layout
     if attr_path.is_empty() {
         return Err(anyhow!("Empty attribute path"));
     }
-    
+
     let first_attr = &attr_path[0];
-    
-    // Find the first attribute in the body
-    let pos = body.iter().position(|item| {
-        item.as_attribute()
-            .map(|a| a.key.as_str() == first_attr)
-            .unwrap_or(false)
-    });
-    
-    if let Some(pos) = pos {
-        if let Some(item) = body.get(pos) {
-            if let Some(attr) = item.as_attribute() {
-                let current_value = attr.value.to_string();
-                
-                // Update the value within the object
-                let new_value_str = if attr_path.len() == 1 {
-                    // Direct attribute update
-                    if let Some(index_key) = index {
-                        update_param_in_source(&current_value, index_key, new_value)?
-                    } else {
-                        format!("\"{}\"", new_value)
-                    }
-                } else {
-                    // Need to update nested attribute within object
-                    update_in_object_string(&current_value, &attr_path[1..], new_value, index)?
-                };
-                
-                // Create new attribute with updated value
-                let new_expr: Expression = new_value_str.parse().with_context(|| {
-                    format!("Failed to parse expression: {}", new_value_str)
-                })?;
-                let key = Ident::new(first_attr.clone());
-                let new_attr = Attribute::new(key, new_expr);
-                
-                // Remove old and insert new
-                body.remove(pos);
-                body.try_insert(pos, new_attr)
-                    .map_err(|_| anyhow!("Failed to insert attribute"))?;
-                
-                return Ok(());
-            }
-        }
+    let pos = body
+        .iter()
+        .position(|s| s.as_attribute().is_some_and(|a| a.key.as_str() == first_attr))
+        .ok_or_else(|| anyhow!("Attribute '{}' not found", first_attr))?;
+    let attr = body
+        .get_mut(pos)
+        .and_then(|s| s.as_attribute_mut())
+        .ok_or_else(|| anyhow!("Expected attribute at position"))?;
+
+    let mut slot = &mut attr.value;
+    for key in &attr_path[1..] {
+        let Expression::Object(object) = slot else {
+            return Err(anyhow!("Attribute '{}' not found in object", key));
+        };
+        slot = map::get_entry_mut(object, key).ok_or_else(|| anyhow!("Attribute '{}' not found in object", key))?;
     }
-    
-    Err(anyhow!("Attribute '{}' not found", first_attr))
-}
 
-fn update_in_object_string(
-    object_str: &str,
-    attr_path: &[String],
-    new_value: &str,
-    index: Option<&str>,
-) -> Result<String> {
-    // Update a value within an object string
-    // object_str looks like: {source = "hashicorp/aws", version = "6.15.0"}
-    // or multi-line:
-    // {
-    //   source = "hashicorp/aws"
-    //   version = "6.15.0"
-    // }
-    
-    if attr_path.is_empty() {
-        return Err(anyhow!("Empty attribute path"));
-    }
-    
-    let target_attr = &attr_path[0];
-    
-    // Parse the object structure
-    let trimmed = object_str.trim();
-    let opening_brace = if let Some(pos) = trimmed.find('{') {
-        &trimmed[..=pos]
-    } else {
-        ""
-    };
-    
-    let closing_brace_pos = trimmed.rfind('}').unwrap_or(trimmed.len());
-    let closing_brace = if closing_brace_pos < trimmed.len() {
-        &trimmed[closing_brace_pos..]
-    } else {
-        ""
-    };
-    
-    // Get the content between braces
-    let content_start = if !opening_brace.is_empty() {
-        opening_brace.len()
-    } else {
-        0
-    };
-    let content = &trimmed[content_start..closing_brace_pos];
-    
-    // Find and replace the attribute value
-    let pattern = format!("{} =", target_attr);
-    if let Some(start_idx) = content.find(&pattern) {
-        let before_attr = &content[..start_idx];
-        let after_equals_start = start_idx + pattern.len();
-        let after_equals = &content[after_equals_start..];
-        
-        // Find where the old value ends (looking for newline, comma, or end)
-        let mut value_end = after_equals.len();
-        for (idx, ch) in after_equals.char_indices() {
-            if ch == '\n' || ch == ',' {
-                value_end = idx;
-                break;
-            }
+    let mut new_expr = match index {
+        Some(index_key) => {
+            let new_value_str = update_param_in_source(&slot.to_string(), index_key, new_value)?;
+            new_value_str
+                .parse()
+                .with_context(|| format!("Failed to parse expression: {}", new_value_str))?
         }
-        
-        // Extract whitespace before and after the value
-        let whitespace_before = after_equals[..after_equals.len().min(value_end)]
-            .chars()
-            .take_while(|c| c.is_whitespace() && *c != '\n')
-            .collect::<String>();
-        let value_start_in_after = whitespace_before.len();
-        let after_value = &after_equals[value_end..];
-        
-        // Format the new value
-        let formatted_new_value = if index.is_some() {
-            format!("\"{}\"", new_value)
-        } else if attr_path.len() > 1 {
-            // More nesting
-            let old_value = after_equals[value_start_in_after..value_end].trim().trim_matches('"');
-            update_in_object_string(old_value, &attr_path[1..], new_value, index)?
-        } else {
-            format!("\"{}\"", new_value)
-        };
-        
-        // Reconstruct the object with better formatting
-        let mut result = String::new();
-        result.push_str(opening_brace);
-        result.push_str(before_attr);
-        result.push_str(&pattern);
-        result.push_str(&whitespace_before);
-        result.push_str(&formatted_new_value);
-        result.push_str(after_value);
-        result.push_str(closing_brace);
-        
-        return Ok(result);
-    }
-    
-    Err(anyhow!("Attribute '{}' not found in object", target_attr))
+        None => value_expression(new_value, options)?,
+    };
+    *new_expr.decor_mut() = slot.decor().clone();
+    *slot = new_expr;
+    Ok(())
 }
 
 pub fn update_param_in_source(source: &str, param_name: &str, new_value: &str) -> Result<String> {
-    // Remove quotes from source string
-    let source = source.trim().trim_matches('"');
+    // Unescape a quoted HCL string, or remove quotes from a bare source string
+    let source = match source.trim().parse::<Expression>() {
+        Ok(Expression::String(s)) => s.value().to_string(),
+        _ => source.trim().trim_matches('"').to_string(),
+    };
+    let source = source.as_str();
 
     // Handle special cases for "url" and "path"
     if param_name == "url" {
-        return Ok(format!("\"{}\"", update_url_in_source(source, new_value)));
+        return Ok(hcl_quote(&update_url_in_source(source, new_value)));
     } else if param_name == "path" {
-        return Ok(format!("\"{}\"", update_path_in_source(source, new_value)));
+        return Ok(hcl_quote(&update_path_in_source(source, new_value)));
     }
 
     // Look for param_name=value pattern in query string
@@ -763,15 +970,12 @@ pub fn update_param_in_source(source: &str, param_name: &str, new_value: &str) -
         result.push_str(new_value);
         result.push_str(&remaining[value_end..]);
 
-        return Ok(format!("\"{}\"", result));
+        return Ok(hcl_quote(&result));
     }
 
     // If parameter doesn't exist, add it to query string
     let separator = if source.contains('?') { "&" } else { "?" };
-    Ok(format!(
-        "\"{}{}{}={}\"",
-        source, separator, param_name, new_value
-    ))
+    Ok(hcl_quote(&format!("{}{}{}={}", source, separator, param_name, new_value)))
 }
 
 pub fn update_url_in_source(source: &str, new_url: &str) -> String {
@@ -860,138 +1064,44 @@ pub fn update_path_in_source(source: &str, new_path: &str) -> String {
     }
 }
 
-pub fn parse_scan_query(query: &str) -> Result<ScanQuery> {
-    // Expected formats:
-    // - module.* (all modules)
-    // - module.vpc.source (specific module with attribute)
-    // - terraform.required_providers.* (terraform block with nested required_providers)
-    // - terraform.required_providers.aws (specific provider)
-    // - module.*.source[url=="https://..."] (with filter)
-    
-    // First check if there's a filter
-    let (query_part, filter) = if let Some(bracket_start) = query.find('[') {
-        let bracket_end = query.find(']')
-            .ok_or_else(|| anyhow!("Unclosed bracket in query"))?;
-        let filter_str = &query[bracket_start + 1..bracket_end];
-        let query_before_filter = &query[..bracket_start];
-        
-        // Parse filter: e.g., url=="https://..." or ref=="v1.0.0"
-        let filter = parse_attribute_filter(filter_str)?;
-        (query_before_filter, Some(filter))
-    } else {
-        (query, None)
-    };
-    
-    let parts: Vec<&str> = query_part.split('.').collect();
-    if parts.is_empty() {
-        return Err(anyhow!("Query cannot be empty"));
-    }
-    
-    let block_type = parts[0].to_string();
-    
-    if parts.len() == 1 {
-        // Just block type: "module" or "terraform"
-        return Ok(ScanQuery {
-            block_type,
-            block_label: None,
-            nested_blocks: vec![],
-            attribute: None,
-            filter,
-        });
-    }
-    
-    // Parse remaining parts
-    let remaining = &parts[1..];
-    
-    // Determine if block_type typically has labels (like "module") or not (like "terraform")
-    let block_has_labels = block_type == "module" || block_type == "resource" || block_type == "data";
-    
-    let (block_label, content_start) = if block_has_labels {
-        // For module/resource/data, second part is label (or wildcard)
-        if remaining[0] == "*" {
-            (None, 1)  // Wildcard label
-        } else {
-            (Some(remaining[0].to_string()), 1)
-        }
-    } else {
-        // For terraform/variable/output/etc, no label
-        (None, 0)
-    };
-    
-    // Handle rest as nested blocks and/or attribute
-    if content_start < remaining.len() {
-        let rest_parts: Vec<String> = remaining[content_start..].iter().map(|s| s.to_string()).collect();
-        
-        // Last part could be attribute or wildcard
-        if rest_parts.is_empty() {
-            // No more parts after label
-            Ok(ScanQuery {
-                block_type,
-                block_label,
-                nested_blocks: vec![],
-                attribute: None,
-                filter,
-            })
-        } else if rest_parts.last().map(|s| s.as_str()) == Some("*") {
-            // Ends with wildcard - all parts are nested blocks/paths
-            let nested = rest_parts[..rest_parts.len()-1].to_vec();
-            Ok(ScanQuery {
-                block_type,
-                block_label: None,  // Wildcard at end means any label
-                nested_blocks: nested,
-                attribute: None,
-                filter,
-            })
-        } else {
-            // Last part is specific attribute
-            let attribute = rest_parts.last().unwrap().clone();
-            let nested = if rest_parts.len() > 1 {
-                rest_parts[..rest_parts.len()-1].to_vec()
-            } else {
-                vec![]
-            };
-            
-            Ok(ScanQuery {
-                block_type,
-                block_label,
-                nested_blocks: nested,
-                attribute: Some(attribute),
-                filter,
-            })
-        }
-    } else {
-        // No rest parts - just block type and label/wildcard
-        Ok(ScanQuery {
-            block_type,
-            block_label,
-            nested_blocks: vec![],
-            attribute: None,
-            filter,
-        })
-    }
+// .tvignore in the scanned directory: one pattern per line (# comments).
+// A pattern with a `/` matches paths relative to that directory, e.g.
+// envs/sandbox or envs/*/generated.tf; any other pattern matches a file or
+// directory name anywhere below it, e.g. .terraform or *_generated.tf.
+// An ignored directory is not descended into.
+pub const TVIGNORE: &str = ".tvignore";
+
+pub fn load_ignore(dir: &std::path::Path) -> Vec<String> {
+    // .tvignore, and the ignore list of the workspace rooted at dir (see workspaces.rs)
+    let workspace = workspaces::at(dir).map(|w| w.ignore).unwrap_or_default();
+    fs::read_to_string(dir.join(TVIGNORE))
+        .unwrap_or_default()
+        .lines()
+        .chain(workspace.iter().map(String::as_str))
+        .map(|line| line.trim().trim_end_matches('/').trim_start_matches("./"))
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect()
 }
 
-pub fn parse_attribute_filter(filter_str: &str) -> Result<AttributeFilter> {
-    // Parse: url=="value" or ref=="value" or path=="value"
-    // Also support single equals for matching
-    
-    let (attribute, rest) = if let Some(pos) = filter_str.find("==") {
-        (&filter_str[..pos], &filter_str[pos+2..])
-    } else if let Some(pos) = filter_str.find('=') {
-        (&filter_str[..pos], &filter_str[pos+1..])
-    } else {
-        return Err(anyhow!("Invalid filter format: {}", filter_str));
-    };
-    
-    let value = rest.trim().trim_matches('"').to_string();
-    
-    Ok(AttributeFilter {
-        attribute: attribute.trim().to_string(),
-        value,
+pub fn is_ignored(patterns: &[String], relative: &std::path::Path) -> bool {
+    let path = relative.to_string_lossy().replace('\\', "/");
+    let name = relative.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+    patterns.iter().any(|pattern| {
+        if pattern.contains('/') {
+            wildcard_match(pattern, &path)
+        } else {
+            wildcard_match(pattern, &name)
+        }
     })
 }
 
 pub fn find_all_tf_files(dir: &std::path::Path) -> Result<Vec<PathBuf>> {
+    find_files(dir, &|path| path.extension().and_then(|s| s.to_str()) == Some("tf"))
+}
+
+pub fn find_files(dir: &std::path::Path, keep: &dyn Fn(&std::path::Path) -> bool) -> Result<Vec<PathBuf>> {
+    // Files under dir that keep accepts, skipping what .tvignore lists
     let mut tf_files = Vec::new();
     
     if !dir.exists() {
@@ -1002,162 +1112,443 @@ pub fn find_all_tf_files(dir: &std::path::Path) -> Result<Vec<PathBuf>> {
         return Err(anyhow!("Path is not a directory: {:?}", dir));
     }
     
-    fn visit_dir(dir: &std::path::Path, tf_files: &mut Vec<PathBuf>) -> Result<()> {
+    fn visit_dir(
+        root: &std::path::Path,
+        dir: &std::path::Path,
+        ignore: &[String],
+        keep: &dyn Fn(&std::path::Path) -> bool,
+        tf_files: &mut Vec<PathBuf>,
+    ) -> Result<()> {
+        cancel::check()?;
         for entry in fs::read_dir(dir)? {
             let entry = entry?;
             let path = entry.path();
+            if is_ignored(ignore, path.strip_prefix(root).unwrap_or(&path)) {
+                continue;
+            }
             
             if path.is_dir() {
-                visit_dir(&path, tf_files)?;
-            } else if path.extension().and_then(|s| s.to_str()) == Some("tf") {
+                visit_dir(root, &path, ignore, keep, tf_files)?;
+            } else if keep(&path) {
                 tf_files.push(path);
             }
         }
         Ok(())
     }
     
-    visit_dir(dir, &mut tf_files)?;
+    visit_dir(dir, dir, &load_ignore(dir), keep, &mut tf_files)?;
+    // readdir order varies by platform and filesystem
+    tf_files.sort();
     Ok(tf_files)
 }
 
-pub fn scan_files(query: &str, dir: &std::path::Path) -> Result<Vec<(PathBuf, String)>> {
+// One scan result: the file and the name of the matching block, which is
+// its label (module "vpc" -> "vpc") or, for unlabeled blocks, its type
+// ("terraform"). Results are sorted as ScanOptions::sort says.
+pub type ScanMatch = (PathBuf, String);
+
+// How many results a file with several matches produces
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Per {
+    #[default]
+    Match,  // One per matching block
+    Block,  // One per distinct block name, e.g. several terraform blocks count once
+    File,   // One per file, named after its first match
+}
+
+impl std::str::FromStr for Per {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Per> {
+        match s {
+            "match" => Ok(Per::Match),
+            "block" => Ok(Per::Block),
+            "file" => Ok(Per::File),
+            _ => Err(anyhow!("Expected 'match', 'block' or 'file', got '{}'", s)),
+        }
+    }
+}
+
+// Order of scan results; ties fall back to path, then block name
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Sort {
+    #[default]
+    Path,
+    Name,
+    Version,  // Lowest first; blocks without a version come last
+}
+
+impl std::str::FromStr for Sort {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Sort> {
+        match s {
+            "path" => Ok(Sort::Path),
+            "name" => Ok(Sort::Name),
+            "version" => Ok(Sort::Version),
+            _ => Err(anyhow!("Expected 'path', 'name' or 'version', got '{}'", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ScanOptions {
+    pub exact: bool,  // Compare url filters literally instead of canonicalized
+    pub per: Per,
+    pub sort: Sort,
+    pub use_index: bool,  // Look blocks up in <dir>/.tv/index.json (see index.rs)
+    pub selector: Option<labels::Selector>,  // Only directories whose labels match
+    pub on_parse_error: OnParseError,
+    pub submodules: bool,  // Also walk git submodules outside dir (see submodules.rs)
+    pub tolerant: bool,  // Read what parses of invalid files (see tolerant.rs)
+}
+
+// What scan does with a file it cannot parse
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum OnParseError {
+    #[default]
+    Fail,
+    Warn,  // Skip the file, and report it (see ParseFailure)
+    Skip,
+}
+
+impl std::str::FromStr for OnParseError {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<OnParseError> {
+        match s {
+            "fail" => Ok(OnParseError::Fail),
+            "warn" => Ok(OnParseError::Warn),
+            "skip" => Ok(OnParseError::Skip),
+            _ => Err(anyhow!("Expected 'fail', 'warn' or 'skip', got '{}'", s)),
+        }
+    }
+}
+
+// A file scan skipped, or, with `tolerant`, read only after repairs
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseFailure {
+    pub file: PathBuf,
+    pub conflict_markers: Vec<usize>,  // Lines; empty for other parse errors
+    pub message: String,
+    pub repairs: Vec<String>,  // Empty when the file was skipped
+}
+
+pub fn scan_files(query: &str, dir: &std::path::Path) -> Result<Vec<ScanMatch>> {
+    scan_files_with_options(query, dir, &ScanOptions::default())
+}
+
+pub fn scan_files_with_options(
+    query: &str,
+    dir: &std::path::Path,
+    options: &ScanOptions,
+) -> Result<Vec<ScanMatch>> {
+    Ok(scan_files_with_failures(query, dir, options)?.0)
+}
+
+pub fn scan_files_with_failures(
+    query: &str,
+    dir: &std::path::Path,
+    options: &ScanOptions,
+) -> Result<(Vec<ScanMatch>, Vec<ParseFailure>)> {
+    // Files skipped under OnParseError::Warn or Skip come back as failures
     let scan_query = parse_scan_query(query)?;
-    let tf_files = find_all_tf_files(dir)?;
+    let mut tf_files = find_all_tf_files(dir)?;
+    if options.submodules {
+        tf_files.extend(submodules::submodule_files(dir)?);
+    }
+    if let Some(ref selector) = options.selector {
+        tf_files = labels::select_files(tf_files, selector)?;
+    }
+    let literals = required_literals(&scan_query, options);
+    let index = if options.use_index {
+        Some(index::load(&index::default_index_path(dir))?)
+    } else {
+        None
+    };
     
     let mut results = Vec::new();
+    let mut failures = Vec::new();
     
     for file_path in tf_files {
-        let module_names = find_matching_modules(&file_path, &scan_query)?;
-        for module_name in module_names {
-            results.push((file_path.clone(), module_name));
+        let fresh = index.as_ref().and_then(|i| i.fresh_file(dir, &file_path));
+        let mut module_names = match fresh {
+            Some(indexed) => find_matching_indexed(indexed, &scan_query, options)?,
+            None => match find_matching_modules(&file_path, &scan_query, &literals, options) {
+                Ok((found, repairs)) => {
+                    if !repairs.is_empty() {
+                        failures.push(ParseFailure {
+                            file: file_path.clone(),
+                            conflict_markers: Vec::new(),
+                            message: "read tolerantly".to_string(),
+                            repairs,
+                        });
+                    }
+                    found
+                }
+                Err(e) if options.on_parse_error != OnParseError::Fail => {
+                    let content = fs::read_to_string(&file_path).unwrap_or_default();
+                    failures.push(ParseFailure {
+                        conflict_markers: conflict_markers(&content),
+                        file: file_path,
+                        message: format!("{:#}", e),
+                        repairs: Vec::new(),
+                    });
+                    continue;
+                }
+                Err(e) => return Err(e),
+            },
+        };
+        match options.per {
+            Per::Match => {}
+            Per::Block => {
+                let mut seen = std::collections::HashSet::new();
+                module_names.retain(|(name, _)| seen.insert(name.clone()));
+            }
+            Per::File => module_names.truncate(1),
+        }
+        for (module_name, version) in module_names {
+            results.push((file_path.clone(), module_name, version));
         }
     }
     
-    Ok(results)
+    match options.sort {
+        Sort::Path => results.sort_by(|a, b| (&a.0, &a.1).cmp(&(&b.0, &b.1))),
+        Sort::Name => results.sort_by(|a, b| (&a.1, &a.0).cmp(&(&b.1, &b.0))),
+        Sort::Version => results.sort_by(|a, b| {
+            let by_version = match (&a.2, &b.2) {
+                (Some(x), Some(y)) => x.cmp(y),
+                (Some(_), None) => std::cmp::Ordering::Less,
+                (None, Some(_)) => std::cmp::Ordering::Greater,
+                (None, None) => std::cmp::Ordering::Equal,
+            };
+            by_version.then_with(|| (&a.0, &a.1).cmp(&(&b.0, &b.1)))
+        }),
+    }
+    Ok((results.into_iter().map(|(path, name, _)| (path, name)).collect(), failures))
+}
+
+pub fn null_separated<'a>(paths: impl IntoIterator<Item = &'a std::path::Path>) -> Vec<u8> {
+    // Raw path bytes, each followed by a NUL, for `xargs -0`; no quoting,
+    // so names with spaces or newlines come through intact
+    let mut out = Vec::new();
+    for path in paths {
+        out.extend_from_slice(path.as_os_str().as_encoded_bytes());
+        out.push(0);
+    }
+    out
+}
+
+pub fn block_arguments(
+    file: &std::path::Path,
+    block_type: &str,
+    name: &str,
+    arguments: &[String],
+) -> Result<Vec<(String, TypedValue)>> {
+    // The values of some arguments of a scanned block, in the order asked;
+    // arguments the block does not set are left out
+    let block = query::block_prefix(block_type, name);
+    let mut values = Vec::new();
+    for argument in arguments {
+        if let Some(typed) = get_typed_value(&format!("{}.{}", block, argument), Some(file))? {
+            values.push((argument.clone(), typed));
+        }
+    }
+    Ok(values)
 }
 
-fn find_matching_modules(file_path: &std::path::Path, scan_query: &ScanQuery) -> Result<Vec<String>> {
+pub fn block_version(block: &hcl_edit::structure::Block, scan_query: &ScanQuery) -> Option<constraint::Version> {
+    // The version a match is sorted by: the matched value's own `version`
+    // (required_providers.aws) or the value itself, else the block's
+    // `version` attribute, else the `ref` of its source
+    let parse = |raw: &str| {
+        let raw = raw.trim().trim_matches('"');
+        let source = source::ModuleSource::parse(raw);
+        let text = source.git_ref().unwrap_or(raw);
+        constraint::parse_constraints(text).ok()?.first().map(|c| c.version.clone())
+    };
+
+    if scan_query.attribute.is_some()
+        && let Ok(query::Target::Value(value)) = query::resolve(block, scan_query)
+    {
+        let found = match value {
+            Expression::Object(object) => map::get_entry(object, "version").and_then(|v| parse(&v.to_string())),
+            other => parse(&other.to_string()),
+        };
+        if found.is_some() {
+            return found;
+        }
+    }
+    let version = block.body.get_attribute("version").and_then(|attr| parse(&attr.value.to_string()));
+    if version.is_some() {
+        return version;
+    }
+    parse(&block.body.get_attribute("source")?.value.to_string())
+}
+
+pub fn required_literals(scan_query: &ScanQuery, options: &ScanOptions) -> Vec<String> {
+    // Text any matching file must contain, so most files can be skipped
+    // without parsing. Selector keys are left out (url and path are parts of
+    // a source string, not text in the file), as are canonicalized urls and
+    // anything HCL would have to escape.
+    let mut literals = vec![scan_query.block_type.clone()];
+    literals.extend(scan_query.block_label.iter().cloned());
+    literals.extend(scan_query.block_name.iter().cloned());
+    literals.extend(scan_query.nested_blocks.iter().map(|n| query::split_repeat(n).0.to_string()));
+    literals.extend(scan_query.attribute.iter().cloned());
+
+    if let Some(ref filter) = scan_query.filter
+        && !scan_query.negated
+        && (filter.attribute != "url" || options.exact)
+    {
+        literals.extend(filter.value.split('*').map(str::to_string));
+    }
+
+    literals.retain(|l| !l.is_empty() && !l.contains(['"', '\\', '$', '%']));
+    literals
+}
+
+fn find_matching_indexed(
+    indexed: &index::IndexedFile,
+    scan_query: &ScanQuery,
+    options: &ScanOptions,
+) -> Result<Vec<(String, Option<constraint::Version>)>> {
+    let mut matching_modules = Vec::new();
+    for block in indexed.candidates(scan_query) {
+        let block = block?;
+        if let BlockMatch::Matched(name) = match_block(&block, scan_query, options)? {
+            matching_modules.push((name, block_version(&block, scan_query)));
+        }
+    }
+    Ok(matching_modules)
+}
+
+type BlockVersion = (String, Option<constraint::Version>);  // Name, version for --sort version
+
+fn find_matching_modules(
+    file_path: &std::path::Path,
+    scan_query: &ScanQuery,
+    literals: &[String],
+    options: &ScanOptions,
+) -> Result<(Vec<BlockVersion>, Vec<String>)> {
+    // The matches, and the repairs a tolerant parse took
     let content = fs::read_to_string(file_path)
         .with_context(|| format!("Failed to read file: {:?}", file_path))?;
     
-    let body: Body = content
-        .parse()
-        .with_context(|| format!("Failed to parse HCL: {:?}", file_path))?;
+    let may_match = literals
+        .iter()
+        .all(|literal| memchr::memmem::find(content.as_bytes(), literal.as_bytes()).is_some());
+    if !may_match {
+        return Ok((Vec::new(), Vec::new()));
+    }
+    
+    let (body, repairs) = if options.tolerant {
+        tolerant::parse_tolerant(&content, file_path)?
+    } else {
+        (parse_tf(&content, file_path)?, Vec::new())
+    };
     
     let mut matching_modules = Vec::new();
     
     // Look for blocks matching the query
     for structure in body.iter() {
-        if let Some(block) = structure.as_block() {
-            if block.ident.as_str() != scan_query.block_type {
-                continue;
-            }
-            
-            // Get the block label (module name for module blocks)
-            let labels: Vec<String> = block
-                .labels
-                .iter()
-                .map(|l| l.as_str().to_string())
-                .collect();
-            
-            let block_label = labels.first().map(|s| s.as_str());
-            
-            // Check block label if specified
-            if let Some(ref expected_label) = scan_query.block_label {
-                if block_label != Some(expected_label.as_str()) {
-                    continue;
-                }
-            }
-            
-            // If no nested blocks or attribute specified, we found a match
-            if scan_query.nested_blocks.is_empty() && scan_query.attribute.is_none() {
-                // For blocks with labels (like modules), use the label
-                // For blocks without labels (like terraform), use the block type
-                if let Some(label) = block_label {
-                    matching_modules.push(label.to_string());
-                } else {
-                    matching_modules.push(scan_query.block_type.clone());
-                }
-                continue;
-            }
-            
-            // Navigate through nested blocks
-            let mut current_body = &block.body;
-            
-            let mut nested_matched = true;
-            for nested_name in &scan_query.nested_blocks {
-                let mut found_this_level = false;
-                
-                for item in current_body.iter() {
-                    if let Some(nested_block) = item.as_block() {
-                        if nested_block.ident.as_str() == nested_name {
-                            current_body = &nested_block.body;
-                            found_this_level = true;
-                            break;
-                        }
-                    }
-                }
-                
-                if !found_this_level {
-                    nested_matched = false;
-                    break;
-                }
-            }
-            
-            if !nested_matched {
-                continue;
+        if let Some(block) = structure.as_block()
+            && let BlockMatch::Matched(name) = match_block(block, scan_query, options)?
+        {
+            matching_modules.push((name, block_version(block, scan_query)));
+        }
+    }
+    
+    Ok((matching_modules, repairs))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BlockMatch {
+    Matched(String),
+    WrongBlockType,
+    WrongLabel(Option<String>),
+    MissingNestedBlock(String),
+    MissingAttribute(String),
+    FilterMismatch(Option<String>),  // The value the filter was compared against
+}
+
+pub fn match_block(
+    block: &hcl_edit::structure::Block,
+    scan_query: &ScanQuery,
+    options: &ScanOptions,
+) -> Result<BlockMatch> {
+    if block.ident.as_str() != scan_query.block_type {
+        return Ok(BlockMatch::WrongBlockType);
+    }
+    
+    // Get the block label (module name for module blocks, type.name for resources)
+    let labels: Vec<&str> = block.labels.iter().map(|l| l.as_str()).collect();
+    let block_label = (!labels.is_empty()).then(|| labels.join(".")).or_else(|| query::block_address(block));
+    if !block_matches(block, scan_query) {
+        return Ok(BlockMatch::WrongLabel(block_label));
+    }
+    
+    // For blocks with labels (like modules), use the label
+    // For import, moved and removed blocks, the address they manage
+    // For other blocks without labels (like terraform), use the block type
+    let name = block_label.unwrap_or_else(|| scan_query.block_type.clone());
+    
+    let value = match query::resolve(block, scan_query) {
+        Ok(query::Target::Value(value)) => value,
+        // The path ends at a nested block, e.g. terraform.required_providers
+        Ok(query::Target::Block(_)) if scan_query.index.is_none() && scan_query.filter.is_none() => {
+            return Ok(BlockMatch::Matched(name));
+        }
+        Ok(query::Target::Block(_)) => {
+            return Ok(BlockMatch::MissingAttribute(scan_query.attribute_name().to_string()));
+        }
+        Err(miss) => return Ok(miss),
+    };
+    
+    // An index only requires the element, key or parameter to exist
+    // (or, negated, to be absent)
+    if let Some(ref index_key) = scan_query.index {
+        let selected = query::select(value, index_key)?;
+        match selected {
+            None if !scan_query.negated => {
+                return Ok(BlockMatch::MissingAttribute(format!(
+                    "{}[\"{}\"]",
+                    scan_query.attribute_name(),
+                    index_key
+                )));
             }
-            
-            // Check attribute if specified
-            if let Some(ref attr_name) = scan_query.attribute {
-                for item in current_body.iter() {
-                    if let Some(attr) = item.as_attribute() {
-                        if attr.key.as_str() == attr_name {
-                            // Check filter if specified
-                            if let Some(ref filter) = scan_query.filter {
-                                let value_str = attr.value.to_string();
-                                if !matches_filter(&value_str, filter)? {
-                                    continue;
-                                }
-                            }
-                            
-                            // For blocks with labels, use the label
-                            // For blocks without labels, use the block type
-                            if let Some(label) = block_label {
-                                matching_modules.push(label.to_string());
-                            } else {
-                                matching_modules.push(scan_query.block_type.clone());
-                            }
-                            break;
-                        }
-                    }
-                }
-            } else {
-                // No specific attribute required, nested blocks matched
-                if let Some(label) = block_label {
-                    matching_modules.push(label.to_string());
-                } else {
-                    matching_modules.push(scan_query.block_type.clone());
-                }
+            Some(present) if scan_query.negated => {
+                return Ok(BlockMatch::FilterMismatch(Some(present.value)));
             }
+            _ => {}
         }
     }
     
-    Ok(matching_modules)
+    if let Some(ref filter) = scan_query.filter {
+        let selected = query::select(value, &filter.attribute)?.map(|v| v.value);
+        if matches_filter(selected.as_deref(), filter, options) == scan_query.negated {
+            return Ok(BlockMatch::FilterMismatch(selected));
+        }
+    }
+    
+    Ok(BlockMatch::Matched(name))
 }
 
-fn matches_filter(value_str: &str, filter: &AttributeFilter) -> Result<bool> {
-    // Extract the value based on the filter attribute (url, ref, path, etc.)
-    let extracted = extract_param_from_source(value_str, &filter.attribute)?;
-    
-    if let Some(extracted_value) = extracted {
-        Ok(wildcard_match(&filter.value, &extracted_value))
-    } else {
-        Ok(false)
+fn matches_filter(selected: Option<&str>, filter: &AttributeFilter, options: &ScanOptions) -> bool {
+    let Some(selected) = selected else {
+        return false;
+    };
+    // Cosmetic url differences (host case, .git, git::) should not hide a match
+    if filter.attribute == "url" && !options.exact {
+        return wildcard_match(
+            &source::canonicalize_url(&filter.value),
+            &source::canonicalize_url(selected),
+        );
     }
+    wildcard_match(&filter.value, selected)
 }
 
-fn wildcard_match(pattern: &str, text: &str) -> bool {
+pub(crate) fn wildcard_match(pattern: &str, text: &str) -> bool {
     // Simple wildcard matching with * as wildcard
     // If no wildcards, do exact match
     if !pattern.contains('*') {
@@ -0,0 +1,141 @@
+// Operations on list-valued (tuple) attributes, e.g. `azs = ["a", "b"]`.
+//
+// Elements are edited in place on the parsed expression so that the
+// surrounding layout (one-line vs. one element per line, trailing commas,
+// comments) is preserved.
+
+use anyhow::{Context, Result, anyhow};
+use hcl_edit::Decorate;
+use hcl_edit::expr::{Array, Expression};
+use std::path::Path;
+
+use crate::{find_attribute_mut, find_tf_file, parse_query, read_tf_body, write_tf_body};
+
+pub fn parse_element(value: &str) -> Result<Expression> {
+    value
+        .trim()
+        .parse()
+        .with_context(|| format!("Failed to parse expression: {}", value))
+}
+
+fn as_array_mut(expr: &mut Expression) -> Result<&mut Array> {
+    match expr {
+        Expression::Array(array) => Ok(array),
+        other => Err(anyhow!(
+            "Attribute is not a list (found {})",
+            crate::expression_kind(other).as_str()
+        )),
+    }
+}
+
+fn same_element(a: &Expression, b: &Expression) -> bool {
+    // Compare ignoring surrounding whitespace and comments
+    let mut a = a.clone();
+    let mut b = b.clone();
+    a.decor_mut().clear();
+    b.decor_mut().clear();
+    a.to_string() == b.to_string()
+}
+
+pub fn push_element(array: &mut Array, mut element: Expression) {
+    element.decor_mut().clear();
+
+    if let Some(last) = array.iter().last() {
+        let last_prefix = last.decor().prefix().map(|p| p.to_string()).unwrap_or_default();
+        let prefix = if array.len() > 1 || last_prefix.contains('\n') {
+            last_prefix
+        } else {
+            " ".to_string()
+        };
+        element.decor_mut().set_prefix(prefix);
+
+        // Without a trailing comma the closing newline lives in the last
+        // element's suffix; hand it over to the new last element
+        if !array.trailing_comma() {
+            let last = array.get_mut(array.len() - 1).unwrap();
+            let suffix = last.decor().suffix().map(|s| s.to_string()).unwrap_or_default();
+            last.decor_mut().set_suffix("");
+            element.decor_mut().set_suffix(suffix);
+        }
+    }
+
+    array.push(element);
+}
+
+pub fn remove_element(array: &mut Array, index: usize) -> Result<Expression> {
+    if index >= array.len() {
+        return Err(anyhow!("Index {} out of range for list of length {}", index, array.len()));
+    }
+
+    let is_last = index == array.len() - 1;
+    let removed = array.remove(index);
+
+    // Keep the first element flush against the bracket
+    if index == 0
+        && let Some(first) = array.get_mut(0)
+    {
+        let prefix = removed.decor().prefix().map(|p| p.to_string()).unwrap_or_default();
+        first.decor_mut().set_prefix(prefix);
+    }
+
+    // Keep the closing layout when the last element carried it
+    if is_last
+        && !array.trailing_comma()
+        && !array.is_empty()
+    {
+        let suffix = removed.decor().suffix().map(|s| s.to_string()).unwrap_or_default();
+        let last = array.len() - 1;
+        array.get_mut(last).unwrap().decor_mut().set_suffix(suffix);
+    }
+
+    Ok(removed)
+}
+
+pub fn replace_element(array: &mut Array, index: usize, mut element: Expression) -> Result<()> {
+    let len = array.len();
+    let slot = array
+        .get_mut(index)
+        .ok_or_else(|| anyhow!("Index {} out of range for list of length {}", index, len))?;
+    *element.decor_mut() = slot.decor().clone();
+    *slot = element;
+    Ok(())
+}
+
+pub fn append_item(query: &str, value: &str, file: Option<&Path>) -> Result<()> {
+    let parsed_query = parse_query(query)?;
+    let file_path = find_tf_file(file)?;
+    let element = parse_element(value)?;
+
+    let mut body = read_tf_body(&file_path)?;
+    let attr = find_attribute_mut(&mut body, &parsed_query)?;
+    push_element(as_array_mut(&mut attr.value)?, element);
+
+    write_tf_body(&file_path, &body)
+}
+
+pub fn remove_item(query: &str, value: &str, file: Option<&Path>) -> Result<usize> {
+    // Removes every element equal to `value`; returns how many were removed
+    let parsed_query = parse_query(query)?;
+    let file_path = find_tf_file(file)?;
+    let element = parse_element(value)?;
+
+    let mut body = read_tf_body(&file_path)?;
+    let attr = find_attribute_mut(&mut body, &parsed_query)?;
+    let array = as_array_mut(&mut attr.value)?;
+
+    let mut removed = 0;
+    loop {
+        let Some(index) = array.iter().position(|e| same_element(e, &element)) else {
+            break;
+        };
+        remove_element(array, index)?;
+        removed += 1;
+    }
+
+    if removed == 0 {
+        return Err(anyhow!("Value {} not found in '{}'", value.trim(), parsed_query.attribute_name()));
+    }
+
+    write_tf_body(&file_path, &body)?;
+    Ok(removed)
+}
@@ -1,7 +1,12 @@
 use anyhow::Result;
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
+use std::io::{IsTerminal, Write};
 use std::path::PathBuf;
-use tv::{get_value, set_value, scan_files};
+use std::time::{Duration, Instant};
+use tv::matches::describe_match;
+use tv::source::ModuleSource;
+use tv::{add, backup, bench, bulk, bump, cancel, centralize, completions, config, context, dirstats, doctor, duplicates, effective, explain, fleet, graph, grep, guides, history, impact, index, info, init, inventory, journal, labels, list, map, normalize, outdated, output, parallel, paths, pin_age, plan, policy, promote, protect, query, registry, remote, rename, self_update, stable, stats, style, tacos, template, terraformrc, tfc, unset, updates, usage, workspaces};
+use tv::{OnParseError, Per, ScanOptions, SetOptions, Sort, TypedValue, ValueKind, block_arguments, check_current, get_typed_value, find_tf_file, get_typed_value_resolved, locate_typed_value_indexed, has_interpolation, null_separated, parse_query, parse_scan_query, set_value_with_options, scan_files_with_failures};
 
 #[derive(Parser)]
 #[command(name = "tv")]
@@ -10,6 +15,45 @@ use tv::{get_value, set_value, scan_files};
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+    /// Do not record file edits in the undo journal
+    #[arg(long, global = true)]
+    no_journal: bool,
+    /// Undo journal location (defaults to $TV_JOURNAL_DIR or ~/.local/state/tv/journal)
+    #[arg(long, global = true)]
+    journal_dir: Option<PathBuf>,
+    /// Print how the query was parsed (to stderr) before running the command
+    #[arg(long, global = true)]
+    dump_query: bool,
+    /// Color human-readable output: auto (terminal and no NO_COLOR), always or never
+    #[arg(long, global = true, default_value = "auto")]
+    color: style::ColorChoice,
+    /// Print paths relative to the current directory, absolute, or relative to the git root (repo-relative)
+    #[arg(long, global = true)]
+    path_format: Option<paths::PathFormat>,
+    /// Give up after this many seconds for the whole invocation (exit status 124)
+    #[arg(long, global = true, value_name = "SECS")]
+    timeout: Option<u64>,
+    /// Give up on a single network request or git command after this many seconds
+    #[arg(long, global = true, value_name = "SECS", default_value_t = 30)]
+    request_timeout: u64,
+    /// Allow edits to the protected_paths of tv.toml without asking
+    #[arg(long, global = true)]
+    allow_protected: bool,
+    /// Why files are edited (e.g. a ticket); recorded in the undo journal, and required by require_reason in the policy
+    #[arg(long, global = true)]
+    reason: Option<String>,
+    /// Byte-identical output for identical repositories: repo-relative paths, release dates instead of ages
+    #[arg(long, global = true)]
+    stable: bool,
+    /// Print results as text, json (get and scan) or csv (scan)
+    #[arg(long, global = true, default_value = "text")]
+    output: output::OutputFormat,
+    /// Run from the directory of this workspace of tv.workspaces.toml
+    #[arg(long, global = true, value_name = "NAME", conflicts_with = "all_workspaces")]
+    workspace: Option<String>,
+    /// Run once in every workspace of tv.workspaces.toml
+    #[arg(long, global = true)]
+    all_workspaces: bool,
 }
 
 #[derive(Subcommand)]
@@ -24,6 +68,24 @@ enum Commands {
         /// Path to .tf file (defaults to current directory)
         #[arg(short, long)]
         file: Option<PathBuf>,
+        /// Prefix the value with its HCL type (string, number, bool, null, list, object, expression)
+        #[arg(long)]
+        show_type: bool,
+        /// Look through every .tf file in the directory using its index (see `tv index build`)
+        #[arg(long)]
+        index: bool,
+        /// Follow local./var. references to the literal they point at
+        #[arg(long)]
+        resolve: bool,
+        /// Query a git repository instead of local files (fetched into a cache)
+        #[arg(long, value_name = "URL")]
+        remote: Option<String>,
+        /// Branch, tag or commit of --remote (defaults to its HEAD)
+        #[arg(long = "ref", value_name = "REF", requires = "remote")]
+        git_ref: Option<String>,
+        /// Print each result as this template, e.g. '{file}:{name} -> {value}' (see README for fields)
+        #[arg(long, value_name = "TEMPLATE")]
+        format: Option<String>,
     },
     /// Set a value in a .tf file
     Set {
@@ -34,6 +96,112 @@ enum Commands {
         /// Path to .tf file (defaults to current directory)
         #[arg(short, long)]
         file: Option<PathBuf>,
+        /// Write a multi-line value that ends in a newline as a <<EOT heredoc
+        #[arg(long)]
+        heredoc: bool,
+        /// Write ${...} and %{...} in the value as interpolation instead of escaping them
+        #[arg(long)]
+        allow_interpolation: bool,
+        /// Write the value as this HCL type: string (default), number, bool, null, list, object or expression
+        #[arg(long = "type", value_name = "TYPE")]
+        value_type: Option<ValueKind>,
+        /// When an override file replaces the value: warn, edit the override instead, or fail (default: tv.toml, else warn)
+        #[arg(long)]
+        on_override: Option<effective::OnOverride>,
+        /// Append the attribute to the block if it does not exist
+        #[arg(long)]
+        create: bool,
+        /// Also create the block, its nested blocks and the file if they do not exist
+        #[arg(long)]
+        create_block: bool,
+        /// Keep the original file as <file><SUFFIX> before rewriting it (default suffix: .bak)
+        #[arg(long, value_name = "SUFFIX", num_args = 0..=1, require_equals = true, default_missing_value = backup::DEFAULT_SUFFIX, value_parser = backup::parse_suffix)]
+        backup: Option<String>,
+        /// Set every match of the query in the .tf files under this directory; `*` labels match any block
+        #[arg(long, conflicts_with_all = ["file", "create", "create_block", "on_override"])]
+        dir: Option<PathBuf>,
+        /// Only set the value if it currently is VALUE (with --dir, skip the matches where it is not)
+        #[arg(long, value_name = "VALUE", conflicts_with = "if_current_matches")]
+        if_current: Option<String>,
+        /// Only set the value if it currently matches REGEX (with --dir, skip the matches where it does not)
+        #[arg(long, value_name = "REGEX")]
+        if_current_matches: Option<String>,
+    },
+    /// Append a new module block (e.g., tv add module.new_vpc --source terraform-aws-modules/vpc/aws --version 5.1.0)
+    Add {
+        /// The module to add (e.g., module.new_vpc)
+        query: String,
+        /// Module source
+        #[arg(long)]
+        source: String,
+        /// Module version (registry modules)
+        #[arg(long)]
+        version: Option<String>,
+        /// Another argument as key=value (repeatable)
+        #[arg(long = "set", value_name = "KEY=VALUE")]
+        arguments: Vec<String>,
+        /// Path to .tf file, created if missing (defaults to current directory)
+        #[arg(short, long)]
+        file: Option<PathBuf>,
+    },
+    /// Rename a module and the module.NAME references to it (e.g., tv rename module.vpc network)
+    Rename {
+        /// The module to rename (e.g., module.vpc)
+        query: String,
+        /// The new name
+        name: String,
+        /// Directory of the module's .tf files (defaults to current directory)
+        #[arg(short, long, default_value = ".")]
+        dir: PathBuf,
+        /// Append a moved block so Terraform keeps the module's state
+        #[arg(long)]
+        moved: bool,
+    },
+    /// Delete an attribute, or a key of an object value (e.g., terraform.required_providers.aws.version)
+    Unset {
+        /// Query path (e.g., module.vpc.version)
+        query: String,
+        /// Path to .tf file (defaults to current directory)
+        #[arg(short, long)]
+        file: Option<PathBuf>,
+    },
+    /// Increment the major, minor or patch part of a semver value (e.g., module.vpc.version)
+    Bump {
+        /// Query path (e.g., module.vpc.version, module.app.source["ref"])
+        query: String,
+        /// Part to increment: major, minor or patch
+        part: bump::Part,
+        /// Path to .tf file (defaults to current directory)
+        #[arg(short, long)]
+        file: Option<PathBuf>,
+    },
+    /// Append an element to a list attribute
+    Append {
+        /// Query path (e.g., module.vpc.azs)
+        query: String,
+        /// HCL expression to append (e.g., '"us-east-1c"')
+        value: String,
+        /// Path to .tf file (defaults to current directory)
+        #[arg(short, long)]
+        file: Option<PathBuf>,
+    },
+    /// Remove every matching element from a list attribute
+    RemoveItem {
+        /// Query path (e.g., module.vpc.azs)
+        query: String,
+        /// HCL expression to remove (e.g., '"us-east-1a"')
+        value: String,
+        /// Path to .tf file (defaults to current directory)
+        #[arg(short, long)]
+        file: Option<PathBuf>,
+    },
+    /// Remove a key from a map attribute, or a whole block
+    Rm {
+        /// Query path with the key to remove (e.g., module.vpc.tags["Owner"]), or a block (e.g., module.legacy_vpc)
+        query: String,
+        /// Path to .tf file (defaults to current directory)
+        #[arg(short, long)]
+        file: Option<PathBuf>,
     },
     /// Scan for .tf files that match a query pattern
     Scan {
@@ -42,28 +210,1975 @@ enum Commands {
         /// Directory to scan (defaults to current directory)
         #[arg(short, long, default_value = ".")]
         dir: PathBuf,
+        /// Compare url filters literally instead of canonicalizing them
+        #[arg(long)]
+        exact: bool,
+        /// Output one line per match, per distinct block or per file
+        #[arg(long, default_value = "match")]
+        per: Per,
+        /// Look blocks up in the directory's index (see `tv index build`)
+        #[arg(long)]
+        index: bool,
+        /// Order results by path, block name or version (lowest first)
+        #[arg(long, default_value = "path")]
+        sort: Sort,
+        /// Only directories whose labels match (e.g. 'team=payments,tier!=dev')
+        #[arg(short = 'l', long)]
+        selector: Option<labels::Selector>,
+        /// Also print these arguments of each block (e.g. name,cidr)
+        #[arg(long, value_delimiter = ',')]
+        with_args: Vec<String>,
+        /// Fail on files that cannot be parsed, or skip them with (warn) or without a warning
+        #[arg(long, default_value = "fail")]
+        on_parse_error: OnParseError,
+        /// Print each matching file once, NUL-terminated and unquoted (for xargs -0)
+        #[arg(short = '0', long)]
+        null: bool,
+        /// Also scan the git submodules declared in .gitmodules, even outside --dir
+        #[arg(long)]
+        submodules: bool,
+        /// Read what parses of invalid files (duplicate attributes, stray commas, broken blocks)
+        #[arg(long)]
+        tolerant: bool,
+        /// Print N lines of HCL around each match
+        #[arg(short = 'C', long, value_name = "N")]
+        context: Option<usize>,
+        /// Query a git repository instead of local files (fetched into a cache)
+        #[arg(long, value_name = "URL")]
+        remote: Option<String>,
+        /// Branch, tag or commit of --remote (defaults to its HEAD)
+        #[arg(long = "ref", value_name = "REF", requires = "remote")]
+        git_ref: Option<String>,
+        /// Print each result as this template, e.g. '{file}:{name} -> {value}' (see README for fields)
+        #[arg(long, value_name = "TEMPLATE")]
+        format: Option<String>,
+    },
+    /// List every module with its source url, path, ref and version
+    List {
+        /// Directory to list (defaults to current directory)
+        #[arg(default_value = ".")]
+        dir: PathBuf,
+        /// Print each result as this template, e.g. '{file}:{name} -> {value}' (see README for fields)
+        #[arg(long, value_name = "TEMPLATE")]
+        format: Option<String>,
+    },
+    /// Show how directories depend on each other: local modules and terragrunt dependencies
+    Graph {
+        /// Directory to graph (defaults to current directory)
+        #[arg(short, long, default_value = ".")]
+        dir: PathBuf,
+        /// Output format: text or dot (Graphviz)
+        #[arg(long, default_value = "text")]
+        format: graph::GraphFormat,
+    },
+    /// Search attribute values for text and report their structural address
+    Grep {
+        /// Text to look for (e.g., terraform-aws-vpc)
+        pattern: String,
+        /// Directory to search (defaults to current directory)
+        #[arg(short, long, default_value = ".")]
+        dir: PathBuf,
+        /// Match regardless of case
+        #[arg(short, long)]
+        ignore_case: bool,
+        /// Print N lines of HCL around each match
+        #[arg(short = 'C', long, value_name = "N")]
+        context: Option<usize>,
+    },
+    /// Explain how a scan query is parsed and why blocks do or do not match
+    Explain {
+        /// Query pattern (e.g., module.*.source[ref=="main"])
+        query: String,
+        /// Directory to scan (defaults to current directory)
+        #[arg(short, long, default_value = ".")]
+        dir: PathBuf,
+        /// Compare url filters literally instead of canonicalizing them
+        #[arg(long)]
+        exact: bool,
+    },
+    /// Check the repository and environment for common problems
+    Doctor {
+        /// Directory to check (defaults to current directory)
+        #[arg(short, long, default_value = ".")]
+        dir: PathBuf,
+        /// Skip environment checks (git, credentials, CLI configuration, private registry lookups)
+        #[arg(long)]
+        no_env: bool,
+        /// Consolidate providers declared in several files of one root into one declaration,
+        /// cap unbounded `>=` provider constraints with `~>`, and remove duplicated
+        /// attributes, keeping the last (or, with keep-first, the first) definition
+        #[arg(long, num_args = 0..=1, default_missing_value = "keep-last", value_name = "KEEP")]
+        fix: Option<duplicates::Keep>,
+        /// Declaration to keep when fixing: highest or lowest minimum version
+        #[arg(long, default_value = "highest")]
+        prefer: doctor::Prefer,
+    },
+    /// Create tv.toml, a starter policy, .tvignore and a CI job for a repository
+    Init {
+        /// Repository directory (defaults to current directory)
+        #[arg(short, long, default_value = ".")]
+        dir: PathBuf,
+        /// Also create a CI job running validate and check-updates: github or gitlab
+        #[arg(long)]
+        ci: Option<init::Ci>,
+        /// Do not create tv-policy.hcl
+        #[arg(long)]
+        no_policy: bool,
+        /// Do not create .tvignore
+        #[arg(long)]
+        no_ignore: bool,
+        /// Overwrite files that already exist
+        #[arg(long)]
+        force: bool,
+        /// Do not ask on a terminal; create what the options say
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Update tv itself, or pin the tv version a repository expects
+    #[command(name = "self")]
+    SelfCmd {
+        #[command(subcommand)]
+        command: SelfCommands,
+    },
+    /// Measure walk, scan, get and set throughput over a generated corpus
+    Bench {
+        /// Number of .tf files to generate
+        #[arg(long, default_value_t = 1000)]
+        files: usize,
+        /// Modules in each file
+        #[arg(long, default_value_t = 5)]
+        modules_per_file: usize,
+        /// Generate the corpus here and keep it (defaults to a temporary directory)
+        #[arg(short, long)]
+        dir: Option<PathBuf>,
+    },
+    /// Summarize the local usage statistics recorded with TV_STATS=1
+    Stats {
+        /// Only runs in this period, e.g. 7d or 12h
+        #[arg(long)]
+        since: Option<String>,
+        /// Number of most-run queries to list
+        #[arg(long, default_value_t = 10)]
+        top: usize,
+        /// Delete the statistics file
+        #[arg(long)]
+        clear: bool,
+    },
+    /// Summarize each root module directory: modules, pinned share, providers and oldest pin
+    StatsDir {
+        /// Directory to summarize (defaults to current directory)
+        #[arg(short, long, default_value = ".")]
+        dir: PathBuf,
+        /// Output format: table, json or markdown
+        #[arg(long, default_value = "table")]
+        format: dirstats::ReportFormat,
+        /// Look up release dates of pinned tags and versions (git and the registry) to find the oldest pin
+        #[arg(long)]
+        lookup: bool,
+    },
+    /// Run the named checks of a policy file against the repository
+    Validate {
+        /// Directory to check (defaults to current directory)
+        #[arg(short, long, default_value = ".")]
+        dir: PathBuf,
+        /// Policy file (defaults to the policy of the workspace rooted at the directory, else tv-policy.hcl in it)
+        #[arg(long)]
+        policy: Option<PathBuf>,
+    },
+    /// Show what a module is pinned to, and its README with --docs
+    Info {
+        /// Module query (e.g., module.vpc)
+        query: String,
+        /// Path to .tf file (defaults to current directory)
+        #[arg(short, long)]
+        file: Option<PathBuf>,
+        /// Fetch and render the module's README for the pinned version
+        #[arg(long)]
+        docs: bool,
+    },
+    /// Compare a module's inputs and outputs between its pinned version and another
+    UpgradeImpact {
+        /// Module query (e.g., module.vpc)
+        query: String,
+        /// Version (or git ref) to compare against
+        #[arg(long)]
+        to: String,
+        /// Path to .tf file (defaults to current directory)
+        #[arg(short, long)]
+        file: Option<PathBuf>,
+        /// Mark arguments passed for removed inputs with a `# tv: removed in <version>` comment
+        #[arg(long)]
+        annotate: bool,
+    },
+    /// Rewrite provider and module version constraints in one style
+    NormalizeConstraints {
+        /// Style to write: pessimistic (~> 5.0), exact (5.0.0) or range (>= 5.0, < 6.0)
+        #[arg(long)]
+        style: tv::constraint::ConstraintStyle,
+        /// Directory to rewrite (defaults to current directory)
+        #[arg(short, long, default_value = ".")]
+        dir: PathBuf,
+        /// Show the changes without writing them
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// List git-pinned modules with a newer version tag, per tv.toml track rules
+    CheckUpdates {
+        /// Modules to check (e.g., module.vpc; defaults to all modules)
+        #[arg(default_value = "module.*")]
+        query: String,
+        /// Directory to check (defaults to current directory)
+        #[arg(short, long, default_value = ".")]
+        dir: PathBuf,
+        /// Only directories whose labels match (e.g. 'team=payments,tier!=dev')
+        #[arg(short = 'l', long)]
+        selector: Option<labels::Selector>,
+        /// Only updates of these sizes: major, minor or patch (e.g. major,minor)
+        #[arg(long, value_delimiter = ',')]
+        only: Vec<bump::Part>,
+        /// Look up at most this many repositories and registry modules at once
+        #[arg(long, value_name = "N", default_value_t = parallel::DEFAULT_JOBS)]
+        network_jobs: usize,
+        /// Reuse versions resolved by earlier runs within this age (e.g. 1h), kept in the tag cache
+        #[arg(long, value_name = "AGE")]
+        cache_ttl: Option<String>,
+    },
+    /// List registry modules whose version is behind the latest published release
+    Outdated {
+        /// Modules to check (e.g., module.vpc; defaults to all modules)
+        #[arg(default_value = "module.*")]
+        query: String,
+        /// Directory to check (defaults to current directory)
+        #[arg(short, long, default_value = ".")]
+        dir: PathBuf,
+        /// Check the providers of required_providers instead of modules
+        #[arg(long)]
+        providers: bool,
+    },
+    /// Show how long ago the version each module is pinned to was released
+    PinAge {
+        /// Modules to check (e.g., module.vpc; defaults to all modules)
+        #[arg(default_value = "module.*")]
+        query: String,
+        /// Directory to check (defaults to current directory)
+        #[arg(short, long, default_value = ".")]
+        dir: PathBuf,
+        /// Only pins older than this, e.g. 180d or 26w
+        #[arg(long)]
+        older_than: Option<String>,
+    },
+    /// Copy module refs and versions from one environment to the matching modules of another
+    Promote {
+        /// Environment to promote from (e.g., envs/staging)
+        #[arg(long)]
+        from: PathBuf,
+        /// Environment to promote to (e.g., envs/prod)
+        #[arg(long)]
+        to: PathBuf,
+        /// Modules to promote (e.g., module.vpc; defaults to all modules)
+        #[arg(short, long, default_value = "module.*")]
+        query: String,
+        /// Show the changes without writing them
+        #[arg(long)]
+        dry_run: bool,
+        /// Do not ask on a terminal before writing
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Move git-pinned modules to their newest allowed version tag
+    Upgrade {
+        /// Modules to upgrade (e.g., module.vpc; defaults to all modules)
+        #[arg(default_value = "module.*")]
+        query: String,
+        /// Directory to upgrade (defaults to current directory)
+        #[arg(short, long, default_value = ".")]
+        dir: PathBuf,
+        /// Only upgrade the modules of this tv.toml group
+        #[arg(long)]
+        group: Option<String>,
+        /// Upgrade to this release (a tag or registry version) instead of the newest allowed
+        #[arg(long)]
+        to: Option<String>,
+        /// Only directories whose labels match (e.g. 'team=payments,tier!=dev')
+        #[arg(short = 'l', long)]
+        selector: Option<labels::Selector>,
+        /// Write the changes to this plan file instead of making them
+        #[arg(long)]
+        plan_out: Option<PathBuf>,
+        /// Sign the plan file with this minisign secret key
+        #[arg(long, requires = "plan_out")]
+        sign_key: Option<PathBuf>,
+        /// Look up at most this many repositories and registry modules at once
+        #[arg(long, value_name = "N", default_value_t = parallel::DEFAULT_JOBS)]
+        network_jobs: usize,
+        /// Reuse versions resolved by earlier runs within this age (e.g. 1h), kept in the tag cache
+        #[arg(long, value_name = "AGE")]
+        cache_ttl: Option<String>,
+    },
+    /// Show a block's attributes after merging the directory's override files
+    Effective {
+        /// Block, optionally one attribute (e.g., module.vpc, module.vpc.version)
+        query: String,
+        /// Directory of the module (defaults to current directory)
+        #[arg(short, long, default_value = ".")]
+        dir: PathBuf,
+    },
+    /// List the directories that can install a provider version
+    WhoUses {
+        /// Provider and versions (e.g., hashicorp/aws@5.x, aws@">= 5.0, < 5.40")
+        target: String,
+        /// Directory to search (defaults to current directory)
+        #[arg(short, long, default_value = ".")]
+        dir: PathBuf,
+    },
+    /// Make the changes of a plan file written by `upgrade --plan-out`
+    ApplyPlan {
+        /// Plan file
+        plan: PathBuf,
+        /// Require a valid signature by this minisign public key (file or RW... key)
+        #[arg(long)]
+        verify_key: Option<String>,
+    },
+    /// Print or install shell completions
+    Completions {
+        #[command(subcommand)]
+        command: CompletionsCommands,
+    },
+    /// Print the man page, or write one per subcommand with --dir
+    Manpage {
+        /// Directory for tv.1 and tv-<subcommand>.1 (e.g., share/man/man1)
+        #[arg(long)]
+        dir: Option<PathBuf>,
+    },
+    /// Parse or build module source strings
+    Source {
+        #[command(subcommand)]
+        command: SourceCommands,
+    },
+    /// Revert every file edited by a transaction
+    Undo {
+        /// Transaction ID (defaults to the most recent transaction)
+        #[arg(long)]
+        txn: Option<String>,
+        /// Restore even if files changed after the transaction
+        #[arg(long)]
+        force: bool,
+    },
+    /// Show the files edited by a transaction, or list all transactions
+    ShowTxn {
+        /// Transaction ID
+        id: Option<String>,
+    },
+    /// Warn about module versions and refs changing back and forth (from the undo journal)
+    Flapping {
+        /// Only files under this directory (defaults to current directory)
+        #[arg(short, long, default_value = ".")]
+        dir: PathBuf,
+        /// How soon a value must come back to count, e.g. 24h or 7d
+        #[arg(long, default_value = "24h")]
+        window: String,
+    },
+    /// Run a command across a list of git repositories
+    Fleet {
+        /// YAML file listing the repositories
+        #[arg(long)]
+        repos: PathBuf,
+        /// Workspace cache for checkouts (defaults to ~/.cache/tv/fleet)
+        #[arg(long)]
+        cache_dir: Option<PathBuf>,
+        /// Clone depth for checkouts (0 fetches full history)
+        #[arg(long, default_value_t = 1)]
+        depth: u32,
+        /// Check out every file instead of only .tf files
+        #[arg(long)]
+        no_sparse: bool,
+        #[command(subcommand)]
+        command: FleetCommands,
+    },
+    /// Move literal module versions of a directory into one versions table
+    Centralize {
+        /// Directory whose modules to rewrite (defaults to current directory)
+        #[arg(short, long, default_value = ".")]
+        dir: PathBuf,
+        /// File for the table: a .tf file gets a locals block, a .tfvars file a variable value
+        #[arg(long, default_value = "versions.tf")]
+        out: PathBuf,
+        /// Name of the table
+        #[arg(long, default_value = centralize::DEFAULT_TABLE_NAME)]
+        name: String,
+    },
+    /// Replace references to a versions table with literal versions (reverse of centralize)
+    Inline {
+        /// Directory whose modules to rewrite (defaults to current directory)
+        #[arg(short, long, default_value = ".")]
+        dir: PathBuf,
+        /// Name of the table
+        #[arg(long, default_value = centralize::DEFAULT_TABLE_NAME)]
+        name: String,
+        /// tfvars file holding the table (defaults to terraform.tfvars)
+        #[arg(long)]
+        var_file: Option<PathBuf>,
+    },
+    /// Maintain an inventory of blocks that scan and get can query instead of parsing
+    Index {
+        #[command(subcommand)]
+        command: IndexCommands,
+    },
+    /// Show or set the Terraform version in required_version and TACOS configs (atlantis.yaml, .spacelift/config.yml)
+    TerraformVersion {
+        /// Directory to look in (defaults to current directory)
+        #[arg(short, long, default_value = ".")]
+        dir: PathBuf,
+        /// Version to set everywhere, in one transaction
+        #[arg(long)]
+        set: Option<String>,
+    },
+    /// Get and set HCP Terraform / Terraform Enterprise workspace variables
+    Tfc {
+        #[command(subcommand)]
+        command: TfcCommands,
+    },
+    /// Show where providers are installed from, per the Terraform CLI configuration
+    Mirrors {
+        /// Provider address to resolve (e.g., hashicorp/aws)
+        provider: Option<String>,
+        /// CLI configuration file (defaults to $TF_CLI_CONFIG_FILE or ~/.terraformrc)
+        #[arg(long)]
+        config: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum SourceCommands {
+    /// Split a source string into kind, url, path and query parameters
+    Parse {
+        /// Source string (e.g., git::https://github.com/org/repo.git//modules/vpc?ref=v1.0.0)
+        source: String,
+    },
+    /// Assemble a source string from its parts
+    Build {
+        /// Repository or registry address, including any getter prefix (e.g., git::)
+        #[arg(long)]
+        url: String,
+        /// Subdirectory within the repository
+        #[arg(long)]
+        path: Option<String>,
+        /// Git ref (tag, branch or commit)
+        #[arg(long = "ref")]
+        git_ref: Option<String>,
+        /// Extra query parameter as key=value (repeatable)
+        #[arg(long = "param")]
+        params: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum CompletionsCommands {
+    /// Print the completion script for a shell
+    Print {
+        /// bash, zsh, fish, elvish or powershell
+        shell: clap_complete::Shell,
+    },
+    /// Write the completion script where the shell loads it from
+    Install {
+        /// bash, zsh or fish (defaults to $SHELL)
+        shell: Option<clap_complete::Shell>,
+        /// Write here instead of the shell's per-user directory
+        #[arg(long)]
+        path: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum IndexCommands {
+    /// Index every .tf file under a directory into <dir>/.tv/index.json
+    Build {
+        /// Directory to index (defaults to current directory)
+        #[arg(short, long, default_value = ".")]
+        dir: PathBuf,
+    },
+    /// Re-index only files that changed (by mtime, content hash or git status)
+    Update {
+        /// Indexed directory (defaults to current directory)
+        #[arg(short, long, default_value = ".")]
+        dir: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum TfcCommands {
+    /// Get a workspace variable's value
+    Get {
+        /// Workspace as <organization>/<workspace>
+        workspace: String,
+        /// Variable key (e.g., module_ref)
+        key: String,
+        /// Default value if the variable does not exist
+        #[arg(default_value = "")]
+        default: String,
+        /// Use environment variables instead of Terraform variables
+        #[arg(long)]
+        env: bool,
+    },
+    /// Set a workspace variable, creating it if needed
+    Set {
+        /// Workspace as <organization>/<workspace>
+        workspace: String,
+        /// Variable key (e.g., module_ref)
+        key: String,
+        /// New value
+        value: String,
+        /// Use environment variables instead of Terraform variables
+        #[arg(long)]
+        env: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum SelfCommands {
+    /// Replace this binary with the newest release (within tv_version), after verifying its checksum
+    Update {
+        /// Release to install instead of the newest
+        #[arg(long)]
+        version: Option<String>,
+        /// Show the release that would be installed without installing it
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Record the tv versions the repository expects as tv_version in tv.toml
+    Pin {
+        /// Versions, e.g. 1.4.x, 1.* or ">= 1.4, < 2"
+        spec: String,
+        /// Directory whose tv.toml to update (defaults to current directory)
+        #[arg(short, long, default_value = ".")]
+        dir: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum FleetCommands {
+    /// Scan every repository for .tf files that match a query pattern
+    Scan {
+        /// Query pattern (e.g., module.*, terraform.required_providers.aws)
+        query: String,
+    },
+    /// List git-pinned modules with a newer version tag in every repository, per its tv.toml
+    CheckUpdates {
+        /// Modules to check (e.g., module.vpc; defaults to all modules)
+        #[arg(default_value = "module.*")]
+        query: String,
+        /// Only updates of these sizes: major, minor or patch (e.g. major,minor)
+        #[arg(long, value_delimiter = ',')]
+        only: Vec<bump::Part>,
+        /// Look up at most this many repositories and registry modules at once
+        #[arg(long, value_name = "N", default_value_t = parallel::DEFAULT_JOBS)]
+        network_jobs: usize,
+        /// Reuse versions resolved by earlier runs within this age (e.g. 1h), kept in the tag cache
+        #[arg(long, value_name = "AGE")]
+        cache_ttl: Option<String>,
+    },
+    /// Remove cached checkouts that are no longer listed in the repos file
+    Evict {
+        /// Remove every cached checkout
+        #[arg(long)]
+        all: bool,
     },
 }
 
 fn main() -> Result<()> {
-    let cli = Cli::parse();
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let journal_dir = cli.journal_dir.unwrap_or_else(journal::default_journal_dir);
+    // Workspaces change the current directory
+    let journal_dir = std::path::absolute(&journal_dir).unwrap_or(journal_dir);
+    style::init(cli.color);
+    if let Some(format) = cli.path_format {
+        paths::init(format);
+    }
+    stable::init(cli.stable);
+    cancel::install_handler()?;
+    cancel::set_request_timeout(Duration::from_secs(cli.request_timeout));
+    if let Some(timeout) = cli.timeout {
+        cancel::set_timeout(Duration::from_secs(timeout));
+        cancel::spawn_watchdog();
+    }
+
+    // All files written by this invocation share one transaction
+    if !cli.no_journal {
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        let command_line = format!("tv {}", args.join(" "));
+        let mut txn = journal::Transaction::begin(&journal_dir, &command_line);
+        txn.meta.reason = cli.reason.clone();
+        journal::activate(txn);
+    }
+
+    // A broken tv.toml is reported by the commands that need it
+    if let Ok(cwd) = std::env::current_dir()
+        && let Ok(config) = config::load_config(&cwd)
+        && let Some(warning) = self_update::pin_warning(&config, self_update::VERSION)
+    {
+        eprintln!("{}: {}", style::yellow("warning"), warning);
+    }
+
+    if cli.dump_query {
+        dump_query(&cli.command)?;
+    }
+
+    protect::set_protection(if cli.allow_protected {
+        protect::Protection::Allow
+    } else if std::io::stdin().is_terminal() {
+        protect::Protection::Confirm(confirm_protected)
+    } else {
+        protect::Protection::Refuse
+    });
+    protect::set_reason(cli.reason);
+
+    let command_name = subcommand_path(&matches);
+    let query = command_query(&cli.command).map(|(query, concrete)| (query.clone(), concrete));
+    let file = command_file(&cli.command);
+    let started = Instant::now();
+    let result = if cli.all_workspaces {
+        run_all_workspaces(&matches, &journal_dir, cli.output)
+    } else {
+        enter_workspace(cli.workspace.as_deref()).and_then(|()| run(cli.command, &journal_dir, cli.output))
+    };
+    journal::deactivate();
+    if stats::enabled() && command_name != "stats" {
+        let record = stats::Record::new(&command_name, query.as_ref().map(|(q, _)| q.as_str()), started.elapsed(), result.is_ok());
+        if let Err(e) = stats::append(&stats::default_stats_file(), &record) {
+            eprintln!("{}: could not record statistics: {:#}", style::yellow("warning"), e);
+        }
+    }
+    if let Err(ref e) = result
+        && cli.output == output::OutputFormat::Json
+    {
+        let query = query.as_ref().map(|(q, concrete)| (q.as_str(), *concrete));
+        eprintln!("{}", output::error_json(e, query, file.as_deref()));
+        std::process::exit(cancel::cancelled().map_or(1, |reason| reason.exit_code()));
+    }
+    if let Err(ref e) = result
+        && let Some(reason) = cancel::cancelled()
+    {
+        eprintln!("Error: {:#}", e);
+        std::process::exit(reason.exit_code());
+    }
+    result
+}
+
+fn enter_workspace(name: Option<&str>) -> Result<()> {
+    // Run from the directory of --workspace (see workspaces.rs)
+    if let Some(name) = name {
+        let all = workspaces::load_workspaces(&std::env::current_dir()?)?;
+        workspaces::enter(workspaces::named(&all, name)?)?;
+    }
+    Ok(())
+}
+
+fn run_all_workspaces(matches: &clap::ArgMatches, journal_dir: &std::path::Path, output: output::OutputFormat) -> Result<()> {
+    // The command once per workspace, each under a heading; a failing
+    // workspace does not stop the others
+    if output != output::OutputFormat::Text {
+        return Err(anyhow::anyhow!("--all-workspaces prints one result per workspace; it cannot be combined with --output json or csv"));
+    }
+    let cwd = std::env::current_dir()?;
+    let all = workspaces::load_workspaces(&cwd)?;
+    let mut failed = 0;
+    for (i, workspace) in all.iter().enumerate() {
+        let dir = paths::format_path(&workspace.dir, paths::PathFormat::Relative, &cwd);
+        if i > 0 {
+            println!();
+        }
+        println!("{}", style::bold(&format!("# {} ({})", workspace.name, dir.display())));
+        // Commands are consumed by run; parse a fresh one for each workspace
+        let command = Cli::from_arg_matches(matches).unwrap_or_else(|e| e.exit()).command;
+        if let Err(e) = workspaces::enter(workspace).and_then(|()| run(command, journal_dir, output)) {
+            cancel::check()?;
+            failed += 1;
+            eprintln!("{}: {}: {:#}", style::red("error"), workspace.name, e);
+        }
+    }
+    std::env::set_current_dir(&cwd)?;
+    if failed > 0 {
+        return Err(anyhow::anyhow!("{} of {} workspaces failed", failed, all.len()));
+    }
+    Ok(())
+}
+
+fn subcommand_path(matches: &clap::ArgMatches) -> String {
+    // e.g. "scan", or "index build" for nested commands
+    let mut names = Vec::new();
+    let mut current = matches;
+    while let Some((name, sub)) = current.subcommand() {
+        names.push(name);
+        current = sub;
+    }
+    names.join(" ")
+}
+
+fn command_query(command: &Commands) -> Option<(&String, bool)> {
+    // The query a command takes, and whether it must be a concrete address
+    // (get/set style) rather than a scan pattern
+    match command {
+        Commands::Get { query, .. }
+        | Commands::Set { query, dir: None, .. }
+        | Commands::Unset { query, .. }
+        | Commands::Bump { query, .. }
+        | Commands::Append { query, .. }
+        | Commands::RemoveItem { query, .. }
+        | Commands::Info { query, .. }
+        | Commands::UpgradeImpact { query, .. } => Some((query, true)),
+        Commands::Scan { query, .. }
+        | Commands::Set { query, dir: Some(_), .. }
+        | Commands::Rm { query, .. }
+        | Commands::Add { query, .. }
+        | Commands::Rename { query, .. }
+        | Commands::Explain { query, .. }
+        | Commands::Effective { query, .. }
+        | Commands::CheckUpdates { query, .. }
+        | Commands::Outdated { query, .. }
+        | Commands::Upgrade { query, .. }
+        | Commands::Promote { query, .. }
+        | Commands::Fleet {
+            command: FleetCommands::Scan { query } | FleetCommands::CheckUpdates { query, .. },
+            ..
+        } => Some((query, false)),
+        _ => None,
+    }
+}
+
+fn command_file(command: &Commands) -> Option<PathBuf> {
+    // The file a command was pointed at, for errors
+    match command {
+        Commands::Get { file, .. } | Commands::Set { file, .. } => file.clone(),
+        _ => None,
+    }
+}
+
+fn dump_query(command: &Commands) -> Result<()> {
+    let (query, parsed) = match command_query(command) {
+        Some((query, true)) => (query, parse_query(query)?),
+        Some((query, false)) => (query, parse_scan_query(query)?),
+        None => return Err(anyhow::anyhow!("--dump-query needs a command that takes a query")),
+    };
 
-    match cli.command {
+    eprintln!("Query: {}", query);
+    eprintln!("  canonical:   {}", parsed);
+    eprint!("{}", explain::describe_scan_query(&parsed));
+    eprintln!();
+    Ok(())
+}
+
+fn enter_remote(url: Option<&str>, git_ref: Option<&str>) -> Result<()> {
+    // Run from the root of a cached checkout of --remote (see remote.rs)
+    if let Some(url) = url {
+        let checkout = remote::checkout(url, git_ref, &remote::default_cache_dir())?;
+        std::env::set_current_dir(&checkout)
+            .map_err(|e| anyhow::anyhow!("Failed to enter checkout {:?}: {}", checkout, e))?;
+    }
+    Ok(())
+}
+
+fn provider_client() -> Result<registry::Client> {
+    // Provider versions from where `terraform init` installs them (see terraformrc.rs)
+    let installation = match terraformrc::default_config_path() {
+        Some(path) => terraformrc::load_provider_installation(&path)?,
+        None => None,
+    };
+    Ok(registry::Client::default().with_installation(installation))
+}
+
+fn update_line(file: &str, update: &updates::Update) -> String {
+    // Riskier updates stand out: major red, minor yellow, patch green
+    let latest = match update.jump() {
+        Some(bump::Part::Major) => style::red(&update.latest),
+        Some(bump::Part::Minor) => style::yellow(&update.latest),
+        Some(bump::Part::Patch) => style::green(&update.latest),
+        None => update.latest.clone(),
+    };
+    let group = match update.group {
+        Some(ref name) => format!(" {}", style::dim(&format!("[group: {}]", name))),
+        None => String::new(),
+    };
+    format!("{}: module.{} {} -> {}{}", file, update.module, update.current, latest, group)
+}
+
+fn format_ms(ms: u64) -> String {
+    if ms < 1000 { format!("{}ms", ms) } else { format!("{:.1}s", ms as f64 / 1000.0) }
+}
+
+fn ask(question: &str, default: &str) -> Result<String> {
+    eprint!("{} [{}] ", question, default);
+    std::io::stderr().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    let answer = answer.trim().to_lowercase();
+    Ok(if answer.is_empty() { default.to_lowercase() } else { answer })
+}
+
+fn confirm_protected(file: &std::path::Path) -> bool {
+    let question = format!("{} is in protected_paths (tv.toml). Change protected files?", paths::show(file));
+    ask(&question, "y/N").is_ok_and(|answer| answer.starts_with('y'))
+}
+
+fn ask_init_options(options: &mut init::InitOptions) -> Result<()> {
+    options.policy = ask("Create a starter policy (tv-policy.hcl)?", "Y/n")?.starts_with('y');
+    options.ignore = ask("Create .tvignore?", "Y/n")?.starts_with('y');
+    options.ci = loop {
+        match ask("CI job: github, gitlab or none?", "none")?.as_str() {
+            "none" => break None,
+            answer => match answer.parse() {
+                Ok(ci) => break Some(ci),
+                Err(e) => eprintln!("{}", e),
+            },
+        }
+    };
+    Ok(())
+}
+
+fn run(command: Commands, journal_dir: &std::path::Path, output: output::OutputFormat) -> Result<()> {
+    let json = output == output::OutputFormat::Json;
+    let csv = output == output::OutputFormat::Csv;
+    if json && !matches!(command, Commands::Get { .. } | Commands::Scan { .. }) {
+        return Err(anyhow::anyhow!("--output json is only supported by get and scan"));
+    }
+    if csv && !matches!(command, Commands::Scan { .. }) {
+        return Err(anyhow::anyhow!("--output csv is only supported by scan"));
+    }
+    let templated = matches!(command, Commands::Get { format: Some(_), .. } | Commands::Scan { format: Some(_), .. });
+    if templated && (json || csv) {
+        return Err(anyhow::anyhow!("--format shapes text output; it cannot be combined with --output json or csv"));
+    }
+    match command {
         Commands::Get {
             query,
             default,
             file,
+            show_type,
+            index,
+            resolve,
+            remote,
+            git_ref,
+            format,
         } => {
-            let result = get_value(&query, file.as_deref())?;
-            println!("{}", result.unwrap_or(default));
+            let template = format.map(|f| template::Template::parse(&f, template::GET_FIELDS)).transpose()?;
+            enter_remote(remote.as_deref(), git_ref.as_deref())?;
+            let (found_in, result) = if index {
+                let dir = file.unwrap_or_else(|| PathBuf::from("."));
+                match locate_typed_value_indexed(&query, &dir)? {
+                    Some((found_in, typed)) => (found_in, Some(typed)),
+                    None => (dir, None),
+                }
+            } else {
+                // Relative, like the paths scan prints
+                let found_in = find_tf_file(Some(file.as_deref().unwrap_or(std::path::Path::new("."))))?;
+                if resolve {
+                    (found_in, get_typed_value_resolved(&query, file.as_deref())?)
+                } else {
+                    (found_in, get_typed_value(&query, file.as_deref())?)
+                }
+            };
+            if json {
+                // An explicit default stands in for a miss, as a string
+                let fallback = (!default.is_empty()).then_some(TypedValue { value: default, kind: ValueKind::String });
+                let typed = result.or(fallback);
+                println!("{}", output::get_json(&found_in, &parse_query(&query)?, typed.as_ref()));
+                return Ok(());
+            }
+            if let Some(template) = template {
+                let fallback = (!default.is_empty()).then_some(TypedValue { value: default, kind: ValueKind::String });
+                let typed = result.or(fallback);
+                println!("{}", template.render(|field| template::get_field(&found_in, &query, typed.as_ref(), field)));
+                return Ok(());
+            }
+            match result {
+                Some(typed) if show_type => println!("{}\t{}", typed.kind.as_str(), typed.value),
+                Some(typed) => println!("{}", typed.value),
+                // The default is taken literally, so report it as a string
+                None if show_type => println!("string\t{}", default),
+                None => println!("{}", default),
+            }
+        }
+        Commands::Set { query, value, file, heredoc, allow_interpolation, value_type, on_override, create, create_block, backup, dir, if_current, if_current_matches } => {
+            let string = value_type.is_none_or(|kind| kind == ValueKind::String);
+            if string && !allow_interpolation && has_interpolation(&value) {
+                eprintln!("note: ${{ and %{{ are written literally; pass --allow-interpolation to interpolate them");
+            }
+            backup::set_suffix(backup);
+            let options = SetOptions { heredoc, allow_interpolation, kind: value_type, create, create_block };
+            let expected = match (if_current, if_current_matches) {
+                (Some(value), _) => Some(policy::Expectation::Equals(value)),
+                (None, Some(pattern)) => Some(policy::Expectation::parse(&format!("matches:{}", pattern))?),
+                (None, None) => None,
+            };
+            if let Some(dir) = dir {
+                let changed = bulk::set_all(&query, &value, &dir, &options, expected.as_ref())?;
+                print!("{}", bulk::render(&changed, paths::show));
+                eprintln!("{} file(s) changed", changed.len());
+                return Ok(());
+            }
+
+            let (file, overridden) = match file {
+                Some(file) if create_block && !file.exists() => (file, None),
+                file => effective::set_target(&query, file.as_deref(), on_override)?,
+            };
+            if let Some(by) = overridden {
+                eprintln!(
+                    "{}: {} is overridden in {}; Terraform will not use this change (--on-override edit changes the override)",
+                    style::yellow("warning"),
+                    query,
+                    paths::show(&by)
+                );
+            }
+            if let Some(ref expected) = expected {
+                check_current(&query, Some(&file), expected)?;
+            }
+            set_value_with_options(&query, &value, Some(&file), &options)?;
+        }
+        Commands::Add { query, source, version, arguments, file } => {
+            let arguments = arguments
+                .iter()
+                .map(|argument| {
+                    argument
+                        .split_once('=')
+                        .map(|(key, value)| (key.trim().to_string(), value.to_string()))
+                        .ok_or_else(|| anyhow::anyhow!("Argument must be key=value: {}", argument))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let options = add::AddOptions { source, version, arguments };
+            let file = add::add_module(&query, file.as_deref(), &options)?;
+            println!("Added {} to {}", query, paths::show(&file));
+        }
+        Commands::Unset { query, file } => {
+            unset::unset(&query, file.as_deref())?;
+        }
+        Commands::Bump { query, part, file } => {
+            let (old, new) = bump::bump(&query, part, file.as_deref())?;
+            println!("{}: {} -> {}", query, old, new);
         }
-        Commands::Set { query, value, file } => {
-            set_value(&query, &value, file.as_deref())?;
+        Commands::Append { query, value, file } => {
+            list::append_item(&query, &value, file.as_deref())?;
+        }
+        Commands::RemoveItem { query, value, file } => {
+            list::remove_item(&query, &value, file.as_deref())?;
+        }
+        Commands::Rename { query, name, dir, moved } => {
+            let renamed = rename::rename_module(&query, &name, &dir, moved)?;
+            print!("{}", rename::render(&renamed, paths::show));
+        }
+        Commands::Rm { query, file } => {
+            let parsed = parse_scan_query(&query)?;
+            if parsed.attribute.is_none() && parsed.index.is_none() {
+                unset::remove_block(&query, file.as_deref())?;
+            } else {
+                map::remove_key(&query, file.as_deref())?;
+            }
+        }
+        Commands::Scan {
+            query,
+            dir,
+            exact,
+            per,
+            index,
+            sort,
+            selector,
+            with_args,
+            on_parse_error,
+            null,
+            submodules,
+            tolerant,
+            context: lines,
+            remote,
+            git_ref,
+            format,
+        } => {
+            let fields: Vec<&str> = template::SCAN_FIELDS.iter().copied().chain(with_args.iter().map(String::as_str)).collect();
+            let template = format.map(|f| template::Template::parse(&f, &fields)).transpose()?;
+            enter_remote(remote.as_deref(), git_ref.as_deref())?;
+            let block_type = parse_scan_query(&query)?.block_type;
+            let per = if null { Per::File } else { per };
+            let options = ScanOptions {
+                exact,
+                per,
+                sort,
+                use_index: index,
+                selector,
+                on_parse_error,
+                submodules,
+                tolerant,
+            };
+            let (results, mut failures) = scan_files_with_failures(&query, &dir, &options)?;
+            for failure in &failures {
+                for repair in &failure.repairs {
+                    eprintln!("{}: {}: {}", style::yellow("tolerated"), paths::show(&failure.file), repair);
+                }
+            }
+            // get parses strictly, so --with-args is left out for these
+            let tolerated: Vec<PathBuf> = failures.iter().filter(|f| !f.repairs.is_empty()).map(|f| f.file.clone()).collect();
+            failures.retain(|f| f.repairs.is_empty());
+            if on_parse_error == OnParseError::Warn {
+                for failure in &failures {
+                    let reason = if failure.conflict_markers.is_empty() {
+                        "cannot be parsed".to_string()
+                    } else {
+                        let lines: Vec<String> = failure.conflict_markers.iter().map(|l| l.to_string()).collect();
+                        format!("unresolved merge conflict (lines {})", lines.join(", "))
+                    };
+                    eprintln!("{}: {}: {}; skipped", style::yellow("warning"), paths::show(&failure.file), reason);
+                }
+            }
+            if null {
+                let shown: Vec<PathBuf> = results.iter().map(|(file, _)| paths::apply(file)).collect();
+                std::io::stdout().write_all(&null_separated(shown.iter().map(PathBuf::as_path)))?;
+                return Ok(());
+            }
+            // --per file prints paths only
+            let with_args = if per == Per::File { Vec::new() } else { with_args };
+            if json {
+                let scan_query = parse_scan_query(&query)?;
+                let mut shown = Vec::new();
+                for (file, name) in results {
+                    if per == Per::File {
+                        shown.push(serde_json::json!({ "file": paths::show(&file) }));
+                        continue;
+                    }
+                    let with_args = if tolerated.contains(&file) { &[][..] } else { &with_args[..] };
+                    let arguments = block_arguments(&file, &block_type, &name, with_args)?;
+                    shown.push(output::scan_json(&describe_match(&file, &scan_query, &name)?, &arguments));
+                }
+                println!("{}", serde_json::to_string_pretty(&shown)?);
+                return Ok(());
+            }
+            if csv {
+                let scan_query = parse_scan_query(&query)?;
+                let mut out = std::io::stdout().lock();
+                if per == Per::File {
+                    write!(out, "{}", output::csv_line(&["file"]))?;
+                } else {
+                    write!(out, "{}", output::scan_csv_header(&with_args))?;
+                }
+                for (file, name) in results {
+                    if per == Per::File {
+                        write!(out, "{}", output::csv_line(&[&paths::show(&file)]))?;
+                        continue;
+                    }
+                    let asked = if tolerated.contains(&file) { &[][..] } else { &with_args[..] };
+                    let arguments = block_arguments(&file, &block_type, &name, asked)?;
+                    let found = describe_match(&file, &scan_query, &name)?;
+                    write!(out, "{}", output::scan_csv_row(&found, &arguments, &with_args))?;
+                }
+                return Ok(());
+            }
+            let scan_query = parse_scan_query(&query)?;
+            if let Some(template) = template {
+                // --per file has files only, so other fields are empty
+                for (file, name) in results {
+                    if per == Per::File {
+                        println!("{}", template.render(|field| (field == "file").then(|| paths::show(&file))));
+                        continue;
+                    }
+                    let with_args = if tolerated.contains(&file) { &[][..] } else { &with_args[..] };
+                    let arguments = block_arguments(&file, &block_type, &name, with_args)?;
+                    let found = describe_match(&file, &scan_query, &name)?;
+                    println!("{}", template.render(|field| template::scan_field(&found, &arguments, field)));
+                }
+                return Ok(());
+            }
+            for (i, (file, name)) in results.into_iter().enumerate() {
+                let path = style::cyan(&paths::show(&file));
+                if lines.is_some() && per != Per::File && i > 0 {
+                    println!("--");
+                }
+                let mut arguments = String::new();
+                let with_args = if tolerated.contains(&file) { &[][..] } else { &with_args[..] };
+                for (argument, typed) in block_arguments(&file, &block_type, &name, with_args)? {
+                    let value = match typed.kind {
+                        ValueKind::String => format!("\"{}\"", typed.value),
+                        _ => typed.value,
+                    };
+                    arguments.push_str(&format!(" {}={}", style::dim(&argument), value));
+                }
+                if per == Per::File {
+                    println!("\"{}\"", path);
+                } else if query::block_takes_label(&block_type) {
+                    println!("\"{}\": \"{}\"{}", path, style::green(&format!("{}.{}", block_type, name)), arguments);
+                } else {
+                    println!("\"{}\": \"{}\"{}", path, style::green(&name), arguments);
+                }
+                // --per file prints paths only; unparsable files have no lines to show
+                if let Some(lines) = lines.filter(|_| per != Per::File && !tolerated.contains(&file)) {
+                    let found = describe_match(&file, &scan_query, &name)?;
+                    if let Some(line) = found.line {
+                        print!("{}", context::excerpt(&std::fs::read_to_string(&file)?, line, lines, style::bold));
+                    }
+                }
+            }
+        }
+        Commands::List { dir, format } => {
+            let template = format.map(|f| template::Template::parse(&f, template::LIST_FIELDS)).transpose()?;
+            let entries = inventory::list_modules(&dir)?;
+            match template {
+                Some(template) => {
+                    for entry in &entries {
+                        println!("{}", template.render(|field| template::list_field(entry, field)));
+                    }
+                }
+                None => print!("{}", inventory::render(&entries, paths::show)),
+            }
+        }
+        Commands::Graph { dir, format } => {
+            print!("{}", graph::render(&graph::dependency_graph(&dir)?, format));
+        }
+        Commands::Grep {
+            pattern,
+            dir,
+            ignore_case,
+            context: lines,
+        } => {
+            let matches = grep::grep_dir(&pattern, &dir, &grep::GrepOptions { ignore_case })?;
+            let highlight = |text: &str| {
+                if ignore_case { style::highlight_ignore_case(text, &pattern) } else { style::highlight(text, &pattern) }
+            };
+            for (i, found) in matches.into_iter().enumerate() {
+                let value = highlight(&found.value);
+                if lines.is_some() && i > 0 {
+                    println!("--");
+                }
+                println!(
+                    "{} @ {}:{}  {}",
+                    style::green(&found.address),
+                    style::cyan(&paths::show(&found.file)),
+                    found.line,
+                    value
+                );
+                if let Some(lines) = lines {
+                    print!("{}", context::excerpt(&std::fs::read_to_string(&found.file)?, found.line, lines, highlight));
+                }
+            }
+        }
+        Commands::Explain { query, dir, exact } => {
+            let report = explain::explain_scan(&query, &dir, &ScanOptions { exact, ..Default::default() })?;
+            println!("Query: {}", query);
+            println!("  canonical:   {}", report.query);
+            print!("{}", explain::describe_scan_query(&report.query));
+            println!();
+            println!(
+                "Files:  {} scanned, {} matched, {} unparsable",
+                report.files_scanned,
+                report.files_matched,
+                report.unparsable.len()
+            );
+            println!(
+                "Blocks: {} considered, {} matched",
+                report.blocks_considered, report.blocks_matched
+            );
+            if !report.non_matches.is_empty() {
+                println!();
+                println!("{}", style::bold("Non-matching blocks:"));
+                for miss in &report.non_matches {
+                    println!(
+                        "  {}: {} - {}",
+                        style::cyan(&paths::show(&miss.file)),
+                        miss.block,
+                        style::yellow(&explain::describe_miss(&miss.reason))
+                    );
+                }
+            }
+            if !report.unparsable.is_empty() {
+                println!();
+                println!("{}", style::bold("Unparsable files:"));
+                for (file, error) in &report.unparsable {
+                    println!("  {}: {}", style::cyan(&paths::show(file)), style::red(error));
+                }
+            }
+        }
+        Commands::Doctor {
+            dir,
+            no_env,
+            fix,
+            prefer,
+        } => {
+            if let Some(keep) = fix {
+                // Files with duplicates do not parse, so they are repaired first
+                for fixed in duplicates::fix_duplicates(&dir, keep)? {
+                    for duplicate in &fixed.duplicates {
+                        let scope = if duplicate.block.is_empty() { "top level" } else { duplicate.block.as_str() };
+                        println!(
+                            "fixed: {}: removed {} extra \"{}\" from {}",
+                            paths::show(&fixed.file),
+                            duplicate.lines.len() - 1,
+                            duplicate.key,
+                            scope
+                        );
+                    }
+                }
+                for fixed in doctor::consolidate_providers(&dir, prefer)? {
+                    for file in &fixed.removed_from {
+                        println!(
+                            "fixed: provider \"{}\" removed from {} (kept in {})",
+                            fixed.provider,
+                            paths::show(file),
+                            paths::show(&fixed.kept)
+                        );
+                    }
+                }
+                for pinned in doctor::pin_providers(&dir)? {
+                    println!(
+                        "fixed: provider \"{}\" in {}: \"{}\" -> \"{}\"",
+                        pinned.provider,
+                        paths::show(&pinned.file),
+                        pinned.from,
+                        pinned.to
+                    );
+                }
+            }
+
+            let mut findings = doctor::diagnose_dir(&dir)?;
+            if !no_env {
+                findings.extend(doctor::diagnose_environment());
+                let hosts = doctor::private_registry_hosts();
+                match doctor::diagnose_dependency_confusion(&dir, &hosts, &registry::Client::default()) {
+                    Ok(found) => findings.extend(found),
+                    Err(e) => findings.push(doctor::Finding {
+                        severity: doctor::Severity::Warning,
+                        check: "dependency-confusion",
+                        file: None,
+                        message: format!("registry lookup failed: {:#}", e),
+                        fix: "check network access to the registries, or skip with --no-env".to_string(),
+                    }),
+                }
+            }
+
+            let errors = findings
+                .iter()
+                .filter(|f| f.severity == doctor::Severity::Error)
+                .count();
+            for finding in &findings {
+                let severity = match finding.severity {
+                    doctor::Severity::Error => style::red(finding.severity.as_str()),
+                    doctor::Severity::Warning => style::yellow(finding.severity.as_str()),
+                    doctor::Severity::Info => style::dim(finding.severity.as_str()),
+                };
+                match finding.file {
+                    Some(ref file) => println!(
+                        "{}: [{}] {}: {}",
+                        severity,
+                        finding.check,
+                        paths::show(file),
+                        finding.message
+                    ),
+                    None => println!(
+                        "{}: [{}] {}",
+                        severity,
+                        finding.check,
+                        finding.message
+                    ),
+                }
+                println!("    {}", style::dim(&format!("fix: {}", finding.fix)));
+            }
+            if errors > 0 {
+                return Err(anyhow::anyhow!("doctor found {} error(s)", errors));
+            }
+        }
+        Commands::Init { dir, ci, no_policy, no_ignore, force, yes } => {
+            let mut options = init::InitOptions { policy: !no_policy, ignore: !no_ignore, ci, force };
+            if !yes && ci.is_none() && !no_policy && !no_ignore && std::io::stdin().is_terminal() {
+                ask_init_options(&mut options)?;
+            }
+            for created in init::init(&dir, &options)? {
+                let path = paths::show(&created.path);
+                match (created.existed, force) {
+                    (false, _) => println!("{} {}", style::green("created"), path),
+                    (true, true) => println!("{} {}", style::yellow("overwrote"), path),
+                    (true, false) => println!("{} {} (exists; --force overwrites it)", style::dim("kept"), path),
+                }
+            }
+            if options.ci == Some(init::Ci::GitLab) {
+                println!("Include {} from .gitlab-ci.yml to run it", init::GITLAB_JOB);
+            }
         }
-        Commands::Scan { query, dir } => {
-            let results = scan_files(&query, &dir)?;
-            for (file, module_name) in results {
-                println!("\"{}\": \"module.{}\"", file.display(), module_name);
+        Commands::SelfCmd {
+            command: SelfCommands::Update { version, dry_run },
+        } => {
+            let cwd = std::env::current_dir()?;
+            let pin = config::load_config(&cwd)?.tv_version;
+            let releases = self_update::Releases::default();
+            let all = releases.list()?;
+            let release = self_update::choose(&all, pin.as_deref(), version.as_deref())?;
+            if release.version == tv::constraint::Version::parse(self_update::VERSION)? {
+                println!("tv {} is up to date", self_update::VERSION);
+            } else if dry_run {
+                println!("Would update tv {} -> {}", self_update::VERSION, release.tag);
+            } else {
+                self_update::update(&releases, release, &std::env::current_exe()?)?;
+                println!("Updated tv {} -> {} (checksum verified)", self_update::VERSION, style::green(&release.tag));
+            }
+        }
+        Commands::SelfCmd {
+            command: SelfCommands::Pin { spec, dir },
+        } => {
+            let path = self_update::pin(&dir, &spec)?;
+            println!("Pinned tv_version = \"{}\" in {}", spec, paths::show(&path));
+        }
+        Commands::Bench { files, modules_per_file, dir } => {
+            eprintln!("Benchmarking {} files with {} modules each", files, modules_per_file);
+            println!("{}", style::bold(&format!("{:<6} {:>10} {:>10} {:>12}", "op", "count", "time", "per second")));
+            for result in bench::bench(files, modules_per_file, dir.as_deref())? {
+                println!(
+                    "{:<6} {:>10} {:>10} {:>12.0}",
+                    result.name,
+                    result.operations,
+                    format_ms(result.elapsed.as_millis() as u64),
+                    result.per_second()
+                );
+            }
+        }
+        Commands::StatsDir { dir, format, lookup } => {
+            let options = updates::UpdateOptions::default();
+            let report = dirstats::dir_stats(&dir, lookup.then_some(&options))?;
+            print!("{}", dirstats::render(&report, format, paths::show));
+        }
+        Commands::Stats { since, top, clear } => {
+            let path = stats::default_stats_file();
+            if clear {
+                if path.exists() {
+                    std::fs::remove_file(&path)?;
+                }
+                println!("Cleared {}", paths::show(&path));
+                return Ok(());
+            }
+            let records = stats::load(&path)?;
+            if records.is_empty() {
+                let hint = if stats::enabled() { "" } else { " (set TV_STATS=1 to record them)" };
+                println!("No statistics in {}{}", paths::show(&path), hint);
+                return Ok(());
+            }
+            let since = match since {
+                Some(age) => {
+                    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?.as_secs();
+                    Some(now.saturating_sub(config::parse_age(&age)?.as_secs()))
+                }
+                None => None,
+            };
+            println!("{}", style::bold(&format!("{:<20} {:>6} {:>7} {:>9} {:>8} {:>8} {:>8}", "command", "runs", "failed", "total", "median", "p95", "max")));
+            for summary in stats::summarize(&records, since) {
+                println!(
+                    "{:<20} {:>6} {:>7} {:>9} {:>8} {:>8} {:>8}",
+                    summary.command,
+                    summary.runs,
+                    summary.failures,
+                    format_ms(summary.total_ms),
+                    format_ms(summary.median_ms),
+                    format_ms(summary.p95_ms),
+                    format_ms(summary.max_ms)
+                );
+            }
+            let queries = stats::top_queries(&records, since, top);
+            if !queries.is_empty() {
+                println!();
+                println!("{}", style::bold("Most-run queries"));
+                for (command, query, runs) in queries {
+                    println!("{:>6}  {} {}", runs, command, query);
+                }
+            }
+        }
+        Commands::Validate { dir, policy } => {
+            let path = policy
+                .or_else(|| workspaces::at(&dir).and_then(|w| w.policy))
+                .unwrap_or_else(|| dir.join(policy::DEFAULT_POLICY_FILE));
+            let reports = policy::validate(&policy::load_policy(&path)?, &dir, &provider_client()?)?;
+
+            let failed = reports.iter().filter(|r| !r.passed()).count();
+            for report in &reports {
+                let status = if report.passed() { style::green("PASS") } else { style::red("FAIL") };
+                println!("{} {} ({} checked)", status, style::bold(&report.name), report.checked);
+                for violation in &report.violations {
+                    println!(
+                        "    {}: {}: {}",
+                        paths::show(&violation.file),
+                        violation.block,
+                        violation.reason
+                    );
+                    if let Some(ref fix) = violation.fix {
+                        println!("        {}", style::dim(&format!("fix: {}", fix)));
+                    }
+                }
+                if let Some(ref message) = report.message
+                    && !report.passed()
+                {
+                    println!("    {}", style::dim(message));
+                }
+            }
+            if failed > 0 {
+                return Err(anyhow::anyhow!("{} of {} check(s) failed", failed, reports.len()));
+            }
+        }
+        Commands::Source {
+            command: SourceCommands::Parse { source },
+        } => {
+            let parsed = ModuleSource::parse(&source);
+            println!("kind: {}", parsed.kind.as_str());
+            println!("url: {}", parsed.url);
+            if let Some(ref path) = parsed.path {
+                println!("path: {}", path);
+            }
+            for (key, value) in &parsed.params {
+                println!("{}: {}", key, value);
+            }
+        }
+        Commands::Source {
+            command:
+                SourceCommands::Build {
+                    url,
+                    path,
+                    git_ref,
+                    params,
+                },
+        } => {
+            let mut query_params = Vec::new();
+            if let Some(git_ref) = git_ref {
+                query_params.push(("ref".to_string(), git_ref));
+            }
+            for param in params {
+                let (key, value) = param
+                    .split_once('=')
+                    .ok_or_else(|| anyhow::anyhow!("Parameter must be key=value: {}", param))?;
+                query_params.push((key.to_string(), value.to_string()));
+            }
+            println!("{}", ModuleSource::build(&url, path.as_deref(), query_params)?);
+        }
+        Commands::Completions { command: CompletionsCommands::Print { shell } } => {
+            print!("{}", completions::script(shell, &mut Cli::command()));
+        }
+        Commands::Completions { command: CompletionsCommands::Install { shell, path } } => {
+            let shell = match shell {
+                Some(shell) => shell,
+                None => completions::detect_shell()?,
+            };
+            let written = completions::install(shell, &mut Cli::command(), path.as_deref())?;
+            println!("Installed {} completions to {}", shell, written.display());
+            if let Some(hint) = completions::install_hint(shell).filter(|_| path.is_none()) {
+                println!("{}: {}", style::dim("note"), hint);
+            }
+        }
+        Commands::Manpage { dir: None } => {
+            print!("{}", completions::manpage(Cli::command())?);
+        }
+        Commands::Manpage { dir: Some(dir) } => {
+            for page in completions::write_manpages(Cli::command(), &dir)? {
+                println!("wrote {}", page.display());
+            }
+        }
+        Commands::Undo { txn, force } => {
+            let id = match txn {
+                Some(id) => id,
+                None => journal::list_transactions(journal_dir)?
+                    .into_iter()
+                    .rev()
+                    .find(|t| !t.undone)
+                    .map(|t| t.id)
+                    .ok_or_else(|| anyhow::anyhow!("No transaction to undo"))?,
+            };
+            for path in journal::undo_transaction(journal_dir, &id, force)? {
+                println!("restored {}", paths::show(&path));
+            }
+        }
+        Commands::ShowTxn { id: Some(id) } => {
+            let txn = journal::load_transaction(journal_dir, &id)?;
+            println!("Transaction {}", txn.id);
+            println!("  command: {}", txn.command);
+            println!("  created: {}", txn.created);
+            println!("  status:  {}", if txn.undone { "undone" } else { "applied" });
+            if let Some(ref reason) = txn.reason {
+                println!("  reason:  {}", reason);
+            }
+            if let Some(ref reason) = txn.undo_reason {
+                println!("  undone because: {}", reason);
+            }
+            println!("  files:");
+            for entry in &txn.entries {
+                let action = if entry.before.is_some() { "modified" } else { "created" };
+                println!("    {} {}", action, paths::show(&entry.path));
+            }
+        }
+        Commands::Flapping { dir, window } => {
+            let window = config::parse_age(&window)?;
+            // The journal records canonical paths; they print as found under --dir
+            let root = std::fs::canonicalize(&dir).unwrap_or_else(|_| dir.clone());
+            let changes: Vec<_> = history::history(journal_dir)?.into_iter().filter(|c| c.path.starts_with(&root)).collect();
+            let flaps = history::flapping(&changes, window);
+            if flaps.is_empty() {
+                println!("No flapping versions");
+            } else {
+                let show = |path: &std::path::Path| paths::show(&dir.join(path.strip_prefix(&root).unwrap_or(path)));
+                print!("{}", history::render(&flaps, show));
+            }
+        }
+        Commands::ShowTxn { id: None } => {
+            for txn in journal::list_transactions(journal_dir)? {
+                println!(
+                    "{}  {} file(s){}  {}",
+                    txn.id,
+                    txn.entries.len(),
+                    if txn.undone { " (undone)" } else { "" },
+                    txn.command
+                );
+            }
+        }
+        Commands::Fleet {
+            repos,
+            cache_dir,
+            depth,
+            no_sparse,
+            command,
+        } => {
+            let repo_list = fleet::load_repos(&repos)?;
+            let cache_dir = cache_dir.unwrap_or_else(fleet::default_cache_dir);
+            let options = fleet::SyncOptions {
+                depth: if depth == 0 { None } else { Some(depth) },
+                sparse: !no_sparse,
+            };
+
+            match command {
+                FleetCommands::Scan { query } => {
+                    let results = fleet::fleet_scan(&repo_list, &cache_dir, &options, &query);
+                    let mut failed = 0;
+                    for result in results {
+                        match result.matches {
+                            Ok(matches) => {
+                                for (file, module_name) in matches {
+                                    println!(
+                                        "\"{}:{}\": \"module.{}\"",
+                                        result.repo,
+                                        paths::show(&file),
+                                        module_name
+                                    );
+                                }
+                            }
+                            Err(e) => {
+                                failed += 1;
+                                eprintln!("{}: {}: {:#}", style::red("error"), result.repo, e);
+                            }
+                        }
+                    }
+                    if failed > 0 {
+                        return Err(anyhow::anyhow!(
+                            "{} of {} repositories failed",
+                            failed,
+                            repo_list.len()
+                        ));
+                    }
+                }
+                FleetCommands::CheckUpdates { query, only, network_jobs, cache_ttl } => {
+                    let cache_ttl = cache_ttl.as_deref().map(config::parse_age).transpose()?;
+                    let update_options = updates::UpdateOptions { network_jobs, cache_ttl, ..Default::default() };
+                    let query = parse_scan_query(&query)?;
+                    let results = fleet::fleet_check_updates(&repo_list, &cache_dir, &options, &query, &update_options);
+                    let mut failed = 0;
+                    for result in results {
+                        match result.updates {
+                            Ok(updates) => {
+                                for update in updates {
+                                    if !only.is_empty() && !update.jump().is_some_and(|jump| only.contains(&jump)) {
+                                        continue;
+                                    }
+                                    let file = format!("{}:{}", result.repo, paths::show(&update.file));
+                                    println!("{}", update_line(&file, &update));
+                                }
+                            }
+                            Err(e) => {
+                                failed += 1;
+                                eprintln!("{}: {}: {:#}", style::red("error"), result.repo, e);
+                            }
+                        }
+                    }
+                    if failed > 0 {
+                        return Err(anyhow::anyhow!(
+                            "{} of {} repositories failed",
+                            failed,
+                            repo_list.len()
+                        ));
+                    }
+                }
+                FleetCommands::Evict { all } => {
+                    for path in fleet::evict_cache(&repo_list, &cache_dir, all)? {
+                        println!("removed {}", paths::show(&path));
+                    }
+                }
+            }
+        }
+        Commands::Info { query, file, docs } => {
+            let module = info::module_info(&query, file.as_deref())?;
+            println!("{} {}", style::bold("module:"), module.module);
+            println!("{} {}", style::bold("file:"), paths::show(&module.file));
+            println!("{} {}", style::bold("source:"), module.source);
+            println!("{} {}", style::bold("kind:"), module.source.kind.as_str());
+            if let Some(pinned) = module.pinned() {
+                println!("{} {}", style::bold("pinned:"), style::green(pinned));
+            }
+            if docs {
+                let readme = info::fetch_readme(&module, &registry::Client::default(), &updates::default_cache_dir())?
+                    .ok_or_else(|| anyhow::anyhow!("No README found for module.{}", module.module))?;
+                println!();
+                // Markdown is rendered for terminals, printed as is otherwise
+                if style::enabled() {
+                    print!("{}", termimad::MadSkin::default().term_text(&readme));
+                } else {
+                    print!("{}", readme);
+                }
+            }
+        }
+        Commands::UpgradeImpact { query, to, file, annotate } => {
+            let module = info::module_info(&query, file.as_deref())?;
+            let impact = impact::upgrade_impact(&module, &to, &registry::Client::default(), &updates::default_cache_dir())?;
+            println!("module.{} {} -> {}", impact.module, impact.from, impact.to);
+            let list = |names: &[String]| names.join(", ");
+            if !impact.removed_inputs.is_empty() {
+                println!("  inputs removed:  {}", list(&impact.removed_inputs));
+            }
+            if !impact.added_inputs.is_empty() {
+                let added: Vec<String> = impact
+                    .added_inputs
+                    .iter()
+                    .map(|(name, required)| if *required { format!("{} (required)", name) } else { name.clone() })
+                    .collect();
+                println!("  inputs added:    {}", list(&added));
+            }
+            if !impact.removed_outputs.is_empty() {
+                println!("  outputs removed: {}", list(&impact.removed_outputs));
+            }
+            if !impact.added_outputs.is_empty() {
+                println!("  outputs added:   {}", list(&impact.added_outputs));
+            }
+            for name in &impact.passed_removed {
+                println!("  {} passes {}, which {} removes", style::red("error:"), name, impact.to);
+            }
+            for name in &impact.missing_required {
+                println!("  {} does not pass {}, which {} requires", style::red("error:"), name, impact.to);
+            }
+            if !impact.breaking() {
+                println!("  {}", style::green("the call site is compatible"));
+            }
+            if annotate {
+                let marked = impact::annotate_removed(&module, &impact)?;
+                println!("Marked {} argument(s) in {}", marked, paths::show(&module.file));
+            }
+        }
+        Commands::NormalizeConstraints { style: constraint_style, dir, dry_run } => {
+            for change in normalize::normalize_constraints(&dir, constraint_style, !dry_run)? {
+                match change.to {
+                    Ok(to) => {
+                        println!("{}: {}", paths::show(&change.file), change.address);
+                        println!("{}", style::red(&format!("- \"{}\"", change.from)));
+                        println!("{}", style::green(&format!("+ \"{}\"", to)));
+                    }
+                    Err(reason) => println!(
+                        "{} {}: {} \"{}\": {}",
+                        style::yellow("skipped"),
+                        paths::show(&change.file),
+                        change.address,
+                        change.from,
+                        reason
+                    ),
+                }
+            }
+        }
+        Commands::Outdated { query, dir, providers } => {
+            let config = config::load_config(&dir)?;
+            let registry = provider_client()?;
+            let outdated = if providers {
+                outdated::outdated_providers(&dir, &registry, &config)?
+            } else {
+                outdated::outdated(&dir, &parse_scan_query(&query)?, &registry, &config)?
+            };
+            if outdated.is_empty() {
+                println!("All registry {} are up to date", if providers { "providers" } else { "modules" });
+                return Ok(());
+            }
+            print!("{}", outdated::render(&outdated, paths::show));
+        }
+        Commands::PinAge { query, dir, older_than } => {
+            let min_days = older_than.as_deref().map(config::parse_age).transpose()?.map(|age| age.as_secs() / 86400);
+            let registry = registry::Client::default();
+            let mut dates = updates::ReleaseDates::new(&updates::default_cache_dir(), &registry);
+            for age in pin_age::pin_ages(&dir, &parse_scan_query(&query)?, &mut dates)? {
+                let shown = match age.days {
+                    Some(days) if min_days.is_some_and(|min| days <= min) => continue,
+                    Some(_) => age.shown().unwrap_or_default(),
+                    None if min_days.is_some() => continue,
+                    None => style::dim("release date unknown"),
+                };
+                println!("{}: module.{} {}: {}", paths::show(&age.file), age.module, age.pin, shown);
+            }
+        }
+        Commands::Promote { from, to, query, dry_run, yes } => {
+            let promotions = promote::plan_promotion(&from, &to, &parse_scan_query(&query)?)?;
+            if promotions.is_empty() {
+                println!("Nothing to promote");
+                return Ok(());
+            }
+            for promotion in &promotions {
+                println!("{}: module.{}", paths::show(&promotion.file), promotion.module);
+                println!("{}", style::red(&format!("- \"{}\"", promotion.current)));
+                println!("{}", style::green(&format!("+ \"{}\"", promotion.promoted)));
+            }
+            if dry_run {
+                return Ok(());
+            }
+            if !yes
+                && std::io::stdin().is_terminal()
+                && !ask(&format!("Apply {} change(s)?", promotions.len()), "y/N")?.starts_with('y')
+            {
+                return Ok(());
+            }
+            promote::promote(&promotions)?;
+        }
+        Commands::CheckUpdates { query, dir, selector, only, network_jobs, cache_ttl } => {
+            let config = config::load_config(&dir)?;
+            let cache_ttl = cache_ttl.as_deref().map(config::parse_age).transpose()?;
+            let options = updates::UpdateOptions { selector, network_jobs, cache_ttl, ..Default::default() };
+            for update in updates::check_updates(&dir, &parse_scan_query(&query)?, &config, &options)? {
+                let jump = update.jump();
+                if !only.is_empty() && !jump.is_some_and(|jump| only.contains(&jump)) {
+                    continue;
+                }
+                println!("{}", update_line(&paths::show(&update.file), &update));
+                if jump == Some(bump::Part::Major)
+                    && let Some(major) = tv::constraint::Version::parse(&update.latest).ok().map(|v| v.segment(0))
+                    && let Some(guide) = guides::upgrade_guide(&config, &[&update.module, &update.url], major)
+                {
+                    println!("  upgrade guide: {}", guide);
+                }
+            }
+        }
+        Commands::Upgrade {
+            query,
+            dir,
+            group,
+            to,
+            selector,
+            plan_out,
+            sign_key,
+            network_jobs,
+            cache_ttl,
+        } => {
+            let config = config::load_config(&dir)?;
+            let cache_ttl = cache_ttl.as_deref().map(config::parse_age).transpose()?;
+            let options = updates::UpdateOptions { selector, to, network_jobs, cache_ttl, ..Default::default() };
+            if let Some(path) = plan_out {
+                let pending = updates::pending_upgrades(&dir, &parse_scan_query(&query)?, &config, group.as_deref(), &options)?;
+                for update in &pending {
+                    println!("{}: module.{} {} -> {}", paths::show(&update.file), update.module, update.current, update.latest);
+                }
+                plan::save(&plan::Plan::from_updates(&pending), &path)?;
+                println!("Wrote {} change(s) to {}", pending.len(), paths::show(&path));
+                if let Some(key) = sign_key {
+                    plan::sign(&path, &key)?;
+                    println!("Signed {}", paths::show(&plan::signature_path(&path)));
+                }
+                return Ok(());
+            }
+            let upgraded = updates::upgrade(&dir, &parse_scan_query(&query)?, &config, group.as_deref(), &options)?;
+            for update in upgraded {
+                println!(
+                    "{}: module.{} upgraded {} -> {}",
+                    paths::show(&update.file),
+                    update.module,
+                    update.current,
+                    update.latest
+                );
+            }
+        }
+        Commands::Effective { query, dir } => {
+            for definition in effective::effective_block(&query, &dir)? {
+                let file = paths::show(&definition.file);
+                let origin = match definition.overridden.last() {
+                    Some((previous_file, previous)) => style::yellow(&format!(
+                        "# {}, overrides {} in {}",
+                        file,
+                        previous,
+                        paths::show(previous_file)
+                    )),
+                    None => style::dim(&format!("# {}", file)),
+                };
+                if definition.nested {
+                    println!("{}  {}", definition.value, origin);
+                } else {
+                    println!("{} = {}  {}", style::green(&definition.name), definition.value, origin);
+                }
+            }
+        }
+        Commands::WhoUses { target, dir } => {
+            let (address, range) = usage::parse_target(&target)?;
+            for found in usage::who_uses(&dir, &address, &range)? {
+                let constraints = if found.constraints.is_empty() {
+                    "no constraint".to_string()
+                } else {
+                    found
+                        .constraints
+                        .iter()
+                        .map(|(file, c)| format!("{} ({})", c, file.file_name().unwrap_or_default().to_string_lossy()))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                };
+                let locked = match found.locked {
+                    Some(ref version) if found.locked_matches => format!("locked {}", style::green(version)),
+                    Some(ref version) => format!("locked {}", style::yellow(version)),
+                    None => style::dim("not locked"),
+                };
+                println!("{}: {}; {}", style::cyan(&paths::show(&found.dir)), constraints, locked);
+            }
+        }
+        Commands::ApplyPlan { plan: path, verify_key } => {
+            let public_key = verify_key.as_deref().map(plan::parse_public_key).transpose()?;
+            let plan = plan::load_verified(&path, public_key.as_ref())?;
+            plan::apply_plan(&plan)?;
+            for change in &plan.changes {
+                println!("{}: {} {} -> {}", paths::show(&change.file), change.address, change.old, change.new);
+            }
+        }
+        Commands::Centralize { dir, out, name } => {
+            // A relative --out is relative to the directory being rewritten
+            let out = if out.is_absolute() { out } else { dir.join(out) };
+            let moved = centralize::centralize(&dir, &out, &name)?;
+            for entry in &moved {
+                println!(
+                    "module.{} ({}): {} moved to {}",
+                    entry.module,
+                    paths::show(&entry.file),
+                    entry.version,
+                    paths::show(&out)
+                );
+            }
+            if !moved.is_empty() {
+                eprintln!("note: module sources and versions that use variables or locals need OpenTofu 1.8 or later");
+            }
+        }
+        Commands::Inline { dir, name, var_file } => {
+            for entry in centralize::inline(&dir, &name, var_file.as_deref())? {
+                println!("module.{} ({}): {}", entry.module, paths::show(&entry.file), entry.version);
+            }
+        }
+        Commands::TerraformVersion { dir, set } => {
+            let location = |pin: &tacos::VersionPin| match pin.line {
+                Some(line) => format!("{}:{}: {}", paths::show(&pin.file), line, pin.key),
+                None => format!("{}: {}", paths::show(&pin.file), pin.key),
+            };
+            match set {
+                None => {
+                    for pin in tacos::terraform_versions(&dir)? {
+                        println!("{} \"{}\"", location(&pin), pin.value);
+                    }
+                }
+                Some(version) => {
+                    for bump in tacos::set_terraform_version(&dir, &version)? {
+                        match bump.to {
+                            Ok(to) => println!("{} \"{}\" -> \"{}\"", location(&bump.pin), bump.pin.value, style::green(&to)),
+                            Err(reason) => println!(
+                                "{} {} \"{}\": {}",
+                                style::yellow("skipped"),
+                                location(&bump.pin),
+                                bump.pin.value,
+                                reason
+                            ),
+                        }
+                    }
+                }
+            }
+        }
+        Commands::Tfc { command } => {
+            let client = tfc::Client::default();
+            match command {
+                TfcCommands::Get { workspace, key, default, env } => {
+                    let category = if env { "env" } else { "terraform" };
+                    match client.get_variable(&workspace, &key, category)? {
+                        Some(variable) if variable.sensitive => {
+                            return Err(anyhow::anyhow!("{} is sensitive; its value cannot be read", key));
+                        }
+                        Some(variable) => println!("{}", variable.value.unwrap_or_default()),
+                        None => println!("{}", default),
+                    }
+                }
+                TfcCommands::Set { workspace, key, value, env } => {
+                    let category = if env { "env" } else { "terraform" };
+                    client.set_variable(&workspace, &key, &value, category)?;
+                }
+            }
+        }
+        Commands::Index { command } => match command {
+            IndexCommands::Build { dir } => {
+                let built = index::build(&dir)?;
+                let path = index::default_index_path(&dir);
+                index::save(&built, &path)?;
+                let blocks: usize = built.files.iter().map(|f| f.blocks.len()).sum();
+                println!(
+                    "Indexed {} block(s) in {} file(s) into {}",
+                    blocks,
+                    built.files.len(),
+                    paths::show(&path)
+                );
+                for file in built.files.iter().filter(|f| f.error.is_some()) {
+                    eprintln!("warning: {} did not parse and was not indexed", paths::show(&file.path));
+                }
+            }
+            IndexCommands::Update { dir } => {
+                let path = index::default_index_path(&dir);
+                if !path.is_file() {
+                    return Err(anyhow::anyhow!("No index at {}; run `tv index build` first", paths::show(&path)));
+                }
+                let mut current = index::load(&path)?;
+                let report = index::update(&dir, &mut current)?;
+                index::save(&current, &path)?;
+                for file in &report.reparsed {
+                    println!("reindexed: {}", paths::show(file));
+                }
+                for file in &report.removed {
+                    println!("removed: {}", paths::show(file));
+                }
+                println!(
+                    "{} reindexed, {} removed, {} unchanged",
+                    report.reparsed.len(),
+                    report.removed.len(),
+                    report.unchanged
+                );
+            }
+        },
+        Commands::Mirrors { provider, config } => {
+            let installation = match config.or_else(terraformrc::default_config_path) {
+                Some(path) => terraformrc::load_provider_installation(&path)?,
+                None => None,
+            };
+            // Without a provider_installation block terraform installs directly
+            let installation = installation.unwrap_or(terraformrc::ProviderInstallation {
+                rules: vec![terraformrc::InstallRule {
+                    method: terraformrc::InstallMethod::Direct,
+                    include: Vec::new(),
+                    exclude: Vec::new(),
+                }],
+            });
+
+            let rules: Vec<&terraformrc::InstallRule> = match provider {
+                Some(ref provider) => installation
+                    .rules
+                    .iter()
+                    .filter(|r| r.applies_to(&terraformrc::normalize_provider_address(provider)))
+                    .collect(),
+                None => installation.rules.iter().collect(),
+            };
+            for rule in rules {
+                let location = match (&rule.method, &provider) {
+                    (terraformrc::InstallMethod::NetworkMirror(url), Some(provider)) => {
+                        terraformrc::mirror_index_url(url, provider)?
+                    }
+                    (terraformrc::InstallMethod::NetworkMirror(url), None) => url.clone(),
+                    (terraformrc::InstallMethod::FilesystemMirror(path), _) => {
+                        paths::show(path)
+                    }
+                    (terraformrc::InstallMethod::Direct, _) => {
+                        println!("{}", rule.method.as_str());
+                        continue;
+                    }
+                };
+                println!("{}\t{}", rule.method.as_str(), location);
             }
         }
     }
@@ -0,0 +1,154 @@
+// Key operations on object-valued (map) attributes, e.g.
+// `tags = { Team = "platform" }` addressed as `module.vpc.tags["Team"]`.
+//
+// This is real HCL map indexing, as opposed to the pseudo-index used for
+// source strings (`source["ref"]`); the attribute's value decides which
+// one applies.
+
+use anyhow::{Result, anyhow};
+use hcl_edit::expr::{Expression, Object, ObjectKey, ObjectValue, ObjectValueTerminator};
+use hcl_edit::{Decorate, Decorated, Ident};
+use std::path::Path;
+
+use crate::{find_attribute_mut, find_tf_file, parse_query, read_tf_body, write_tf_body};
+
+pub fn key_name(key: &ObjectKey) -> Option<String> {
+    match key {
+        ObjectKey::Ident(ident) => Some(ident.as_str().to_string()),
+        ObjectKey::Expression(Expression::String(s)) => Some(s.as_str().to_string()),
+        _ => None,
+    }
+}
+
+fn position_of(object: &Object, name: &str) -> Option<usize> {
+    object
+        .iter()
+        .position(|(key, _)| key_name(key).as_deref() == Some(name))
+}
+
+fn new_key(name: &str) -> ObjectKey {
+    // Keys that are valid identifiers stay unquoted, like hand-written HCL
+    match Ident::try_new(name) {
+        Ok(ident) => ObjectKey::Ident(Decorated::new(ident)),
+        Err(_) => ObjectKey::Expression(Expression::from(name)),
+    }
+}
+
+pub fn get_entry<'a>(object: &'a Object, name: &str) -> Option<&'a Expression> {
+    object
+        .iter()
+        .find(|(key, _)| key_name(key).as_deref() == Some(name))
+        .map(|(_, value)| value.expr())
+}
+
+pub fn get_entry_mut<'a>(object: &'a mut Object, name: &str) -> Option<&'a mut Expression> {
+    let index = position_of(object, name)?;
+    object.iter_mut().nth(index).map(|(_, value)| value.expr_mut())
+}
+
+pub fn set_entry(object: &mut Object, name: &str, mut value: Expression) {
+    // Existing key: replace the value in place, keeping its layout
+    if let Some(index) = position_of(object, name) {
+        let (_, slot) = object.iter_mut().nth(index).unwrap();
+        *value.decor_mut() = slot.expr().decor().clone();
+        *slot.expr_mut() = value;
+        return;
+    }
+
+    let mut key = new_key(name);
+    key.decor_mut().set_suffix(" ");
+    value.decor_mut().set_prefix(" ");
+
+    let terminator = match object.iter_mut().last() {
+        None => {
+            // Empty object: `{ key = value }`
+            key.decor_mut().set_prefix(" ");
+            value.decor_mut().set_suffix(" ");
+            ObjectValueTerminator::None
+        }
+        Some((last_key, last_value)) => {
+            // Indent like the previous key, minus any newline the first key carries
+            let last_prefix = last_key
+                .decor()
+                .prefix()
+                .map(|p| p.to_string())
+                .unwrap_or_default();
+            let indent = match last_prefix.rfind('\n') {
+                Some(pos) => last_prefix[pos + 1..].to_string(),
+                None => last_prefix,
+            };
+            key.decor_mut().set_prefix(indent);
+
+            let terminator = last_value.terminator();
+            if terminator == ObjectValueTerminator::None {
+                // One-line object: the space before `}` moves to the new value
+                let suffix = last_value
+                    .expr()
+                    .decor()
+                    .suffix()
+                    .map(|s| s.to_string())
+                    .unwrap_or_default();
+                last_value.expr_mut().decor_mut().set_suffix("");
+                last_value.set_terminator(ObjectValueTerminator::Comma);
+                value.decor_mut().set_suffix(suffix);
+            }
+            terminator
+        }
+    };
+
+    let mut object_value = ObjectValue::new(value);
+    object_value.set_terminator(terminator);
+    object.insert(key, object_value);
+}
+
+pub fn remove_entry(object: &mut Object, name: &str) -> Option<ObjectValue> {
+    let index = position_of(object, name)?;
+    let key = object.iter().nth(index).map(|(k, _)| k.clone())?;
+    let (removed_key, removed_value) = object.remove_entry(&key)?;
+
+    // The first key may carry the newline after `{`
+    if index == 0
+        && let Some((mut first_key, _)) = object.iter_mut().next()
+    {
+        let prefix = removed_key.decor().prefix().map(|p| p.to_string()).unwrap_or_default();
+        first_key.decor_mut().set_prefix(prefix);
+    }
+
+    // Removing the last entry of a one-line object keeps `{ a = 1 }` intact
+    if index == object.len()
+        && removed_value.terminator() == ObjectValueTerminator::None
+        && let Some((_, last_value)) = object.iter_mut().last()
+    {
+        let suffix = removed_value
+            .expr()
+            .decor()
+            .suffix()
+            .map(|s| s.to_string())
+            .unwrap_or_default();
+        last_value.set_terminator(ObjectValueTerminator::None);
+        last_value.expr_mut().decor_mut().set_suffix(suffix);
+    }
+
+    Some(removed_value)
+}
+
+pub fn remove_key(query: &str, file: Option<&Path>) -> Result<()> {
+    let parsed_query = parse_query(query)?;
+    let key = parsed_query
+        .index
+        .clone()
+        .ok_or_else(|| anyhow!("Query must address a map key, e.g. module.vpc.tags[\"Owner\"]"))?;
+    let file_path = find_tf_file(file)?;
+
+    let mut body = read_tf_body(&file_path)?;
+    let attr = find_attribute_mut(&mut body, &parsed_query)?;
+    let Expression::Object(ref mut object) = attr.value else {
+        return Err(anyhow!("Attribute '{}' is not a map", parsed_query.attribute_name()));
+    };
+
+    if remove_entry(object, &key).is_none() {
+        return Err(anyhow!("Key '{}' not found in '{}'", key, parsed_query.attribute_name()));
+    }
+
+    write_tf_body(&file_path, &body)
+}
@@ -0,0 +1,111 @@
+// Scan results as data rather than strings.
+//
+// scan_files reports each result as (file, name), the name being the labels
+// joined with dots (`aws_s3_bucket.logs`), which is ambiguous once a label
+// has a dot, a space or a quote in it. A Match carries the block's labels
+// as written, the attribute the query addressed and its value, and the
+// span of the block in the file, and the line of the value matched.
+// `tv scan --output json|csv` print them.
+
+use anyhow::Result;
+use hcl_edit::Span as _;
+use hcl_edit::structure::Body;
+use std::path::{Path, PathBuf};
+
+use crate::index::line_of;
+use crate::query::{Target, block_prefix, quote_segment, resolve};
+use crate::{Query, ScanOptions, TypedValue, get_typed_value, parse_scan_query, scan_files_with_options};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Span {
+    pub start: usize,  // Byte offsets, end exclusive
+    pub end: usize,
+    pub start_line: usize,  // 1-based, inclusive
+    pub end_line: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Match {
+    pub file: PathBuf,
+    pub block_type: String,
+    pub labels: Vec<String>,
+    pub name: String,  // As scan_files reports it
+    pub attribute: Option<String>,  // e.g. source["ref"]; None for whole blocks
+    pub value: Option<TypedValue>,
+    pub span: Option<Span>,  // Of the block; None when it cannot be located
+    pub line: Option<usize>,  // Of the value addressed, or the block's first line for whole blocks
+}
+
+pub(crate) fn attribute_path(query: &Query) -> Option<String> {
+    // The part of the query after the block, e.g. source["ref"]
+    let attribute = query.attribute.as_ref()?;
+    let mut path: Vec<String> = query.nested_blocks.iter().map(|n| quote_segment(n)).collect();
+    path.push(quote_segment(attribute));
+    let index = query.index.as_ref().map(|i| format!("[\"{}\"]", i)).unwrap_or_default();
+    Some(path.join(".") + &index)
+}
+
+pub(crate) fn scanned_value(file: &Path, query: &Query, name: &str) -> Result<Option<TypedValue>> {
+    match attribute_path(query) {
+        Some(path) => get_typed_value(&format!("{}.{}", block_prefix(&query.block_type, name), path), Some(file)),
+        None => Ok(None),
+    }
+}
+
+struct Located {
+    labels: Vec<String>,
+    span: Option<Span>,
+    line: Option<usize>,
+}
+
+fn locate(content: &str, body: &Body, query: &Query, name: &str) -> Option<Located> {
+    // The first block of the type scan would have named `name`
+    let block_type = query.block_type.as_str();
+    body.iter().filter_map(|s| s.as_block()).filter(|b| b.ident.as_str() == block_type).find_map(|block| {
+        let labels: Vec<String> = block.labels.iter().map(|l| l.as_str().to_string()).collect();
+        let named = if labels.is_empty() { name == block_type } else { labels.join(".") == name };
+        named.then(|| {
+            let span = block.span().map(|range| Span {
+                start_line: line_of(content, range.start),
+                end_line: line_of(content, range.end.saturating_sub(1)),
+                start: range.start,
+                end: range.end,
+            });
+            let start = match resolve(block, query) {
+                Ok(Target::Value(value)) => value.span().map(|r| r.start),
+                Ok(Target::Block(nested)) => nested.span().map(|r| r.start),
+                Err(_) => None,
+            };
+            Located { labels, span, line: start.map(|start| line_of(content, start)) }
+        })
+    })
+}
+
+pub fn describe_match(file: &Path, query: &Query, name: &str) -> Result<Match> {
+    let content = std::fs::read_to_string(file).unwrap_or_default();
+    let located = content.parse::<Body>().ok().and_then(|body| locate(&content, &body, query, name));
+    // Blocks named by an attribute (import's `to`, say) have no labels
+    let Located { labels, span, line } = located.unwrap_or_else(|| Located {
+        labels: if name == query.block_type { Vec::new() } else { vec![name.to_string()] },
+        span: None,
+        line: None,
+    });
+    Ok(Match {
+        file: file.to_path_buf(),
+        block_type: query.block_type.clone(),
+        labels,
+        name: name.to_string(),
+        attribute: attribute_path(query),
+        value: scanned_value(file, query, name)?,
+        span,
+        line,
+    })
+}
+
+pub fn scan_matches(query: &str, dir: &Path, options: &ScanOptions) -> Result<Vec<Match>> {
+    let scan_query = parse_scan_query(query)?;
+    scan_files_with_options(query, dir, options)?
+        .into_iter()
+        .map(|(file, name)| describe_match(&file, &scan_query, &name))
+        .collect()
+}
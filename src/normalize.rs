@@ -0,0 +1,95 @@
+// `tv normalize-constraints --style pessimistic|exact|range`: one way of
+// writing version constraints across a repository.
+//
+// Provider constraints (required_providers, object or legacy string form)
+// and module `version` attributes are rewritten only into a form that allows
+// exactly the same versions (see constraint::normalize_constraint); those
+// without one in the chosen style are reported and left alone. Values that
+// are not literal strings are not touched.
+
+use anyhow::Result;
+use hcl_edit::Decorate;
+use hcl_edit::expr::Expression;
+use std::path::{Path, PathBuf};
+
+use crate::constraint::{ConstraintStyle, normalize_constraint};
+use crate::map::get_entry_mut;
+use crate::query::quote_segment;
+use crate::{find_all_tf_files, read_tf_body, write_tf_body};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Normalized {
+    pub file: PathBuf,
+    pub address: String,  // e.g. terraform.required_providers.aws.version
+    pub from: String,
+    pub to: std::result::Result<String, String>,  // Err: why it was left alone
+}
+
+fn normalize_value(expr: &mut Expression, style: ConstraintStyle, write: bool) -> Option<(String, std::result::Result<String, String>)> {
+    // Rewrites a literal constraint in place; None when there is nothing to report
+    let Expression::String(ref current) = *expr else {
+        return None;
+    };
+    let from = current.value().to_string();
+    let to = normalize_constraint(&from, style);
+    match to {
+        Ok(ref normalized) if *normalized == from => return None,
+        Ok(ref normalized) if write => {
+            let mut value = Expression::from(normalized.as_str());
+            *value.decor_mut() = expr.decor().clone();
+            *expr = value;
+        }
+        _ => {}
+    }
+    Some((from, to))
+}
+
+pub fn normalize_constraints(dir: &Path, style: ConstraintStyle, write: bool) -> Result<Vec<Normalized>> {
+    let mut results = Vec::new();
+    for file in find_all_tf_files(dir)? {
+        let Ok(mut body) = read_tf_body(&file) else {
+            continue;
+        };
+        let mut found = Vec::new();
+
+        for block in body.get_blocks_mut("module") {
+            let Some(label) = block.labels.first().map(|l| l.as_str().to_string()) else {
+                continue;
+            };
+            if let Some(mut attr) = block.body.get_attribute_mut("version")
+                && let Some((from, to)) = normalize_value(attr.value_mut(), style, write)
+            {
+                found.push((format!("module.{}.version", quote_segment(&label)), from, to));
+            }
+        }
+
+        for terraform in body.get_blocks_mut("terraform") {
+            for required in terraform.body.get_blocks_mut("required_providers") {
+                for mut attr in required.body.attributes_mut() {
+                    let provider = attr.key.as_str().to_string();
+                    let value = match *attr.value_mut() {
+                        Expression::Object(ref mut object) => {
+                            get_entry_mut(object, "version").and_then(|value| normalize_value(value, style, write))
+                        }
+                        // Legacy shorthand: aws = ">= 5.0"
+                        ref mut value => normalize_value(value, style, write),
+                    };
+                    if let Some((from, to)) = value {
+                        found.push((format!("terraform.required_providers.{}.version", provider), from, to));
+                    }
+                }
+            }
+        }
+
+        if write && found.iter().any(|(_, _, to)| to.is_ok()) {
+            write_tf_body(&file, &body)?;
+        }
+        results.extend(found.into_iter().map(|(address, from, to)| Normalized {
+            file: file.clone(),
+            address,
+            from,
+            to,
+        }));
+    }
+    Ok(results)
+}
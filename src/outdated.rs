@@ -0,0 +1,172 @@
+// `tv outdated`: registry modules whose `version` is behind the latest
+// release the registry publishes, like `npm outdated`.
+//
+// Unlike check-updates (see updates.rs), which proposes a pin within the
+// track rules of tv.toml, this reports every registry module whose
+// constraint (`5.1.0`, `~> 5.0`, `>= 4.0, < 5.0`) does not allow the
+// latest release (its upper bound is below it), whatever the rules say.
+// For each it shows
+//
+//   current   the version attribute as written
+//   wanted    the newest release the constraint allows, if any
+//   latest    the newest release
+//
+// Deprecated versions and pre-releases are never wanted or latest. Versions
+// that are not literal (a variable) or do not parse are left out, as are
+// git modules: their newest tags are check-updates' business.
+//
+// `--providers` reports the providers of required_providers blocks the same
+// way. When the latest release is a major version past what the constraint
+// allows, the upgrade guide for it is listed under the table (see
+// guides.rs).
+
+use anyhow::Result;
+use std::cmp::Ordering;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::constraint::{Version, parse_constraints, range_of, satisfies};
+use crate::doctor::provider_requirements;
+use crate::guides::upgrade_guide;
+use crate::output::table;
+use crate::query::quote_segment;
+use crate::registry::{Client, ProviderAddress, RegistryVersion};
+use crate::updates::module_pins;
+use crate::{ScanQuery, find_all_tf_files, read_tf_body};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Outdated {
+    pub file: PathBuf,
+    pub kind: &'static str,  // module or provider
+    pub module: String,  // The module label, or the provider's local name
+    pub source: String,  // Registry address, e.g. terraform-aws-modules/vpc/aws
+    pub current: String,
+    pub wanted: Option<String>,
+    pub latest: String,
+    pub guide: Option<String>,  // For a latest release a major version ahead
+}
+
+struct Behind {
+    wanted: Option<String>,
+    latest: String,
+    major: Option<u64>,  // The latest major, when the wanted release is of an older one
+}
+
+fn behind(versions: Vec<RegistryVersion>, current: &str) -> Option<Behind> {
+    let constraints = parse_constraints(current).ok()?;
+    let mut releases: Vec<(Version, String)> = versions
+        .into_iter()
+        .filter(|v| v.deprecation.is_none())
+        .filter_map(|v| Version::parse(&v.version).ok().map(|parsed| (parsed, v.version)))
+        .filter(|(parsed, _)| parsed.prerelease.is_none())
+        .collect();
+    releases.sort();
+    let (latest, latest_text) = releases.last()?;
+    // Behind means capped below the latest release; a pin above it (a
+    // version since removed, say) or a `!=` is not
+    let upper = range_of(&constraints).upper;
+    let behind = match upper {
+        Some((ref upper, inclusive)) => match upper.cmp(latest) {
+            Ordering::Less => true,
+            Ordering::Equal => !inclusive,
+            Ordering::Greater => false,
+        },
+        None => false,
+    };
+    if !behind {
+        return None;
+    }
+    let wanted = releases.iter().rev().find(|(v, _)| satisfies(v, &constraints));
+    let major = latest.segment(0);
+    Some(Behind {
+        major: wanted.filter(|(wanted, _)| wanted.segment(0) < major).map(|_| major),
+        wanted: wanted.map(|(_, text)| text.clone()),
+        latest: latest_text.clone(),
+    })
+}
+
+pub fn outdated(dir: &Path, query: &ScanQuery, registry: &Client, config: &Config) -> Result<Vec<Outdated>> {
+    let mut outdated = Vec::new();
+    for file in find_all_tf_files(dir)? {
+        for pin in module_pins(&file, query)? {
+            let Some(ref address) = pin.registry else {
+                continue;
+            };
+            if parse_constraints(&pin.current).is_err() {
+                continue;
+            }
+            let Some(found) = behind(registry.module_versions(address)?, &pin.current) else {
+                continue;
+            };
+            outdated.push(Outdated {
+                file: file.clone(),
+                kind: "module",
+                module: pin.module.clone(),
+                source: pin.url.clone(),
+                current: pin.current.clone(),
+                wanted: found.wanted,
+                latest: found.latest,
+                guide: found.major.and_then(|major| upgrade_guide(config, &[&pin.module, &pin.url], major)),
+            });
+        }
+    }
+    Ok(outdated)
+}
+
+pub fn outdated_providers(dir: &Path, registry: &Client, config: &Config) -> Result<Vec<Outdated>> {
+    let mut outdated = Vec::new();
+    for file in find_all_tf_files(dir)? {
+        for requirement in provider_requirements(&read_tf_body(&file)?, &file) {
+            // Without a source, the local name is a hashicorp provider
+            let source = requirement.source.clone().unwrap_or_else(|| requirement.name.clone());
+            let (Some(address), Some(current)) = (ProviderAddress::parse(&source), requirement.version) else {
+                continue;
+            };
+            if parse_constraints(&current).is_err() {
+                continue;
+            }
+            let Some(found) = behind(registry.provider_versions(&address)?, &current) else {
+                continue;
+            };
+            let short = format!("{}/{}", address.namespace, address.name);
+            outdated.push(Outdated {
+                file: file.clone(),
+                kind: "provider",
+                guide: found.major.and_then(|major| upgrade_guide(config, &[&requirement.name, &short], major)),
+                module: requirement.name,
+                source: short,
+                current,
+                wanted: found.wanted,
+                latest: found.latest,
+            });
+        }
+    }
+    Ok(outdated)
+}
+
+pub fn render(outdated: &[Outdated], show: impl Fn(&Path) -> String) -> String {
+    let rows: Vec<Vec<String>> = outdated
+        .iter()
+        .map(|o| {
+            vec![
+                show(&o.file),
+                quote_segment(&o.module),
+                o.current.clone(),
+                o.wanted.clone().unwrap_or_else(|| "-".to_string()),
+                o.latest.clone(),
+                o.source.clone(),
+            ]
+        })
+        .collect();
+    let kind = outdated.first().map_or("module", |o| o.kind);
+    let mut out = table(&["file", kind, "current", "wanted", "latest", "source"], &rows);
+    let guides: Vec<String> = outdated
+        .iter()
+        .filter_map(|o| Some(format!("  {}.{} {}: {}\n", o.kind, quote_segment(&o.module), o.latest, o.guide.as_ref()?)))
+        .collect();
+    if !guides.is_empty() {
+        out.push_str("\nUpgrade guides:\n");
+        out.extend(guides);
+    }
+    out
+}
@@ -0,0 +1,309 @@
+// `--output json`: what `tv get` and `tv scan` print, as JSON for scripts
+// and CI rather than text to be parsed.
+//
+// `get` prints one object, `scan` an array with one per result:
+//
+//   {"file": "main.tf", "block_type": "module", "label": "vpc",
+//    "attribute": "version", "value": "5.1.0", "type": "string"}
+//
+// `label` is null for unlabeled blocks, and `attribute`, `value` and `type`
+// are null when the query addresses a whole block. Scan results (see
+// matches.rs) also have `labels`, the block's labels as written, and `span`,
+// where the block is: {"start", "end"} in bytes and {"start_line",
+// "end_line"}, or null when it cannot be located. Values are converted to
+// JSON where HCL has the same thing (strings, numbers, bools, null, lists,
+// objects); anything else (references, function calls) is its HCL text.
+// `scan --with-args` adds `arguments`, and `scan --per file` prints files
+// only.
+//
+// `--output csv` (scan only) prints the same as rows, for spreadsheets:
+// file, block, label, attribute, value, start_line, end_line, then one
+// column per --with-args argument. Values are as `get` prints them; missing ones are empty.
+//
+// With `--output json`, a failing command prints its error to stderr as one
+// object instead of text, so wrappers need not parse messages:
+//
+//   {"code": "not_found", "message": "Attribute 'version' not found in block",
+//    "file": "main.tf", "query": "module.vpc.version",
+//    "hint": "check the query with `tv scan`, or pass --create to add it"}
+//
+// `message` is the full context chain; `file`, `query` and `hint` are null
+// when unknown.
+
+use anyhow::{Result, anyhow};
+use hcl_edit::expr::{Expression, ObjectKey};
+use serde_json::{Map, Value, json};
+use std::path::{Path, PathBuf};
+
+use crate::matches::{Match, Span, attribute_path};
+use crate::query::block_takes_label;
+use crate::{Query, TypedValue, ValueKind};
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+    Csv,
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            "csv" => Ok(OutputFormat::Csv),
+            _ => Err(anyhow!("Expected 'text', 'json' or 'csv', got '{}'", s)),
+        }
+    }
+}
+
+fn expression_json(expr: &Expression) -> Value {
+    match expr {
+        Expression::String(s) => Value::String(s.value().to_string()),
+        Expression::Number(n) => serde_json::from_str(&n.value().to_string()).unwrap_or(Value::Null),
+        Expression::Bool(b) => Value::Bool(*b.value()),
+        Expression::Null(_) => Value::Null,
+        Expression::Array(array) => Value::Array(array.iter().map(expression_json).collect()),
+        Expression::Object(object) => {
+            let mut map = Map::new();
+            for (key, value) in object.iter() {
+                let key = match key {
+                    ObjectKey::Ident(ident) => ident.as_str().to_string(),
+                    ObjectKey::Expression(Expression::String(s)) => s.value().to_string(),
+                    ObjectKey::Expression(expr) => expr.to_string().trim().to_string(),
+                };
+                map.insert(key, expression_json(value.expr()));
+            }
+            Value::Object(map)
+        }
+        _ => Value::String(expr.to_string().trim().to_string()),
+    }
+}
+
+pub fn value_json(typed: &TypedValue) -> Value {
+    // Strings are already unquoted; the rest is parsed again from its text
+    match typed.kind {
+        ValueKind::String | ValueKind::Expression => Value::String(typed.value.clone()),
+        _ => match typed.value.parse::<Expression>() {
+            Ok(expr) => expression_json(&expr),
+            Err(_) => Value::String(typed.value.clone()),
+        },
+    }
+}
+
+fn result_json(
+    file: &Path,
+    block_type: &str,
+    label: Option<&str>,
+    attribute: Option<&str>,
+    typed: Option<&TypedValue>,
+) -> Map<String, Value> {
+    let mut result = Map::new();
+    result.insert("file".to_string(), json!(crate::paths::show(file)));
+    result.insert("block_type".to_string(), json!(block_type));
+    result.insert("label".to_string(), json!(label));
+    result.insert("attribute".to_string(), json!(attribute));
+    result.insert("value".to_string(), typed.map_or(Value::Null, value_json));
+    result.insert("type".to_string(), json!(typed.map(|t| t.kind.as_str())));
+    result
+}
+
+pub fn get_json(file: &Path, query: &Query, typed: Option<&TypedValue>) -> Value {
+    // A miss has a null value and type; the default is left to the caller
+    let labels: Vec<&str> = query.block_label.iter().chain(&query.block_name).map(String::as_str).collect();
+    let label = (!labels.is_empty()).then(|| labels.join("."));
+    let attribute = attribute_path(query);
+    Value::Object(result_json(file, &query.block_type, label.as_deref(), attribute.as_deref(), typed))
+}
+
+fn scanned_label(found: &Match) -> Option<&str> {
+    // `name` is the block name scan reports: the type for unlabeled blocks
+    (block_takes_label(&found.block_type) || found.name != found.block_type).then_some(found.name.as_str())
+}
+
+pub fn scan_json(found: &Match, arguments: &[(String, TypedValue)]) -> Value {
+    let mut result = result_json(
+        &found.file,
+        &found.block_type,
+        scanned_label(found),
+        found.attribute.as_deref(),
+        found.value.as_ref(),
+    );
+    result.insert("labels".to_string(), json!(found.labels));
+    let span = found.span.as_ref().map(|s| {
+        json!({"start": s.start, "end": s.end, "start_line": s.start_line, "end_line": s.end_line})
+    });
+    result.insert("span".to_string(), span.unwrap_or(Value::Null));
+    if !arguments.is_empty() {
+        let arguments: Map<String, Value> = arguments.iter().map(|(name, typed)| (name.clone(), value_json(typed))).collect();
+        result.insert("arguments".to_string(), Value::Object(arguments));
+    }
+    Value::Object(result)
+}
+
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+pub fn csv_line(fields: &[&str]) -> String {
+    let fields: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+    fields.join(",") + "\n"
+}
+
+pub fn scan_csv_header(with_args: &[String]) -> String {
+    let mut fields = vec!["file", "block", "label", "attribute", "value", "start_line", "end_line"];
+    fields.extend(with_args.iter().map(String::as_str));
+    csv_line(&fields)
+}
+
+pub fn scan_csv_row(found: &Match, arguments: &[(String, TypedValue)], with_args: &[String]) -> String {
+    // One row per result, with a column per asked argument
+    let shown = crate::paths::show(&found.file);
+    let line = |pick: fn(&Span) -> usize| found.span.as_ref().map(|s| pick(s).to_string()).unwrap_or_default();
+    let (start_line, end_line) = (line(|s| s.start_line), line(|s| s.end_line));
+    let mut fields = vec![
+        shown.as_str(),
+        found.block_type.as_str(),
+        scanned_label(found).unwrap_or_default(),
+        found.attribute.as_deref().unwrap_or_default(),
+        found.value.as_ref().map_or("", |t| t.value.as_str()),
+        start_line.as_str(),
+        end_line.as_str(),
+    ];
+    for argument in with_args {
+        let value = arguments.iter().find(|(name, _)| name == argument).map(|(_, t)| t.value.as_str());
+        fields.push(value.unwrap_or_default());
+    }
+    csv_line(&fields)
+}
+
+pub fn table(headers: &[&str], rows: &[Vec<String>]) -> String {
+    // Left-aligned columns two spaces apart, for `tv list` and `tv outdated`
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.chars().count()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let line = |cells: Vec<&str>| {
+        let padded: Vec<String> =
+            cells.iter().zip(&widths).map(|(cell, width)| format!("{:<width$}", cell, width = width)).collect();
+        padded.join("  ").trim_end().to_string() + "\n"
+    };
+    let mut out = line(headers.to_vec());
+    for row in rows {
+        out.push_str(&line(row.iter().map(String::as_str).collect()));
+    }
+    out
+}
+
+// What went wrong, as `code` in the JSON error
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ErrorCode {
+    InvalidQuery,
+    FileNotFound,
+    Io,
+    ParseError,
+    MergeConflict,
+    NotFound,
+    UnexpectedValue,
+    Interrupted,
+    TimedOut,
+    Error,
+}
+
+impl ErrorCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::InvalidQuery => "invalid_query",
+            ErrorCode::FileNotFound => "file_not_found",
+            ErrorCode::Io => "io",
+            ErrorCode::ParseError => "parse_error",
+            ErrorCode::MergeConflict => "merge_conflict",
+            ErrorCode::NotFound => "not_found",
+            ErrorCode::UnexpectedValue => "unexpected_value",
+            ErrorCode::Interrupted => "interrupted",
+            ErrorCode::TimedOut => "timed_out",
+            ErrorCode::Error => "error",
+        }
+    }
+
+    fn hint(&self) -> Option<&'static str> {
+        match self {
+            ErrorCode::InvalidQuery => Some("`tv explain QUERY` shows how the query is parsed"),
+            ErrorCode::FileNotFound => Some("pass --file, or run tv in a directory with .tf files"),
+            ErrorCode::ParseError => Some("fix the HCL syntax; `terraform validate` shows the details"),
+            ErrorCode::MergeConflict => Some("resolve the conflict markers, then run tv again"),
+            ErrorCode::NotFound => Some("check the query with `tv scan`, or pass --create to add it"),
+            ErrorCode::UnexpectedValue => Some("something else changed the value; read it with `tv get` before retrying"),
+            ErrorCode::TimedOut => Some("raise --timeout"),
+            _ => None,
+        }
+    }
+}
+
+pub fn error_code(error: &anyhow::Error, query: Option<(&str, bool)>) -> ErrorCode {
+    // From the error types in the chain, falling back to tv's own messages.
+    // A query that does not parse is the cause whatever the error says.
+    match crate::cancel::cancelled() {
+        Some(crate::cancel::Reason::Interrupted) => return ErrorCode::Interrupted,
+        Some(crate::cancel::Reason::TimedOut) => return ErrorCode::TimedOut,
+        None => {}
+    }
+    if let Some((query, concrete)) = query {
+        let parsed = if concrete { crate::parse_query(query) } else { crate::parse_scan_query(query) };
+        if parsed.is_err() {
+            return ErrorCode::InvalidQuery;
+        }
+    }
+    for cause in error.chain() {
+        if let Some(io) = cause.downcast_ref::<std::io::Error>() {
+            return match io.kind() {
+                std::io::ErrorKind::NotFound => ErrorCode::FileNotFound,
+                _ => ErrorCode::Io,
+            };
+        }
+        if cause.downcast_ref::<hcl_edit::parser::Error>().is_some() {
+            return ErrorCode::ParseError;
+        }
+    }
+    let message = error.to_string();
+    if message.starts_with("Unresolved merge conflict") {
+        ErrorCode::MergeConflict
+    } else if message.starts_with("Unexpected current value") {
+        ErrorCode::UnexpectedValue
+    } else if message.starts_with("No .tf files found") || message.starts_with("Invalid path") {
+        ErrorCode::FileNotFound
+    } else if message.contains(" not found") {
+        ErrorCode::NotFound
+    } else {
+        ErrorCode::Error
+    }
+}
+
+fn error_file(error: &anyhow::Error) -> Option<PathBuf> {
+    // Errors name files as quoted paths, e.g. Failed to read file: "main.tf"
+    let quoted = regex::Regex::new(r#""([^"]+\.(?:tf|tf\.json|tfvars|hcl|toml))""#).unwrap();
+    error
+        .chain()
+        .find_map(|cause| quoted.captures(&cause.to_string()).map(|c| PathBuf::from(&c[1])))
+}
+
+pub fn error_json(error: &anyhow::Error, query: Option<(&str, bool)>, file: Option<&Path>) -> Value {
+    let code = error_code(error, query);
+    let file = file.map(Path::to_path_buf).or_else(|| error_file(error));
+    json!({
+        "code": code.as_str(),
+        "message": format!("{:#}", error),
+        "file": file.map(|f| crate::paths::show(&f)),
+        "query": query.map(|(q, _)| q),
+        "hint": code.hint(),
+    })
+}
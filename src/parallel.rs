@@ -0,0 +1,30 @@
+// Bounded concurrency for network lookups (git ls-remote, registry
+// requests): `map` runs a function over distinct keys on at most `jobs`
+// threads and returns the results in the order of the keys. Callers
+// deduplicate first, so each remote is asked once per run.
+
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+pub const DEFAULT_JOBS: usize = 8;
+
+pub fn map<K: Sync, V: Send>(keys: &[K], jobs: usize, f: impl Fn(&K) -> V + Sync) -> Vec<V> {
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<V>>> = Mutex::new(keys.iter().map(|_| None).collect());
+    let workers = jobs.clamp(1, keys.len().max(1));
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| {
+                loop {
+                    let i = next.fetch_add(1, Ordering::SeqCst);
+                    let Some(key) = keys.get(i) else {
+                        break;
+                    };
+                    let value = f(key);
+                    results.lock().unwrap()[i] = Some(value);
+                }
+            });
+        }
+    });
+    results.into_inner().unwrap().into_iter().map(|v| v.expect("every key is looked up")).collect()
+}
@@ -0,0 +1,124 @@
+// How printed paths look: `--path-format relative|absolute|repo-relative`.
+//
+// Without the option paths print as they were found under --dir.
+// `relative` prints them relative to the current directory (`../` for
+// files outside it); `absolute` resolves them against it;
+// `repo-relative` prints them relative to the root of the git repository
+// that contains them, so reports are the same wherever tv was started from
+// and diff cleanly in CI. A path outside any repository is printed absolute.
+// Paths are resolved lexically (`.` and `..` removed), without following
+// symlinks, so files that no longer exist print the same way.
+//
+// With --stable (see stable.rs) paths are repo-relative by default, and
+// relative to the current directory outside a repository.
+
+use anyhow::{Result, anyhow};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicU8, Ordering};
+
+static FORMAT: AtomicU8 = AtomicU8::new(0);  // 0: as found
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PathFormat {
+    Relative = 1,
+    Absolute,
+    RepoRelative,
+}
+
+impl std::str::FromStr for PathFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<PathFormat> {
+        match s {
+            "relative" => Ok(PathFormat::Relative),
+            "absolute" => Ok(PathFormat::Absolute),
+            "repo-relative" => Ok(PathFormat::RepoRelative),
+            _ => Err(anyhow!("Expected 'relative', 'absolute' or 'repo-relative', got '{}'", s)),
+        }
+    }
+}
+
+pub fn init(format: PathFormat) {
+    FORMAT.store(format as u8, Ordering::Relaxed);
+}
+
+fn current() -> Option<PathFormat> {
+    match FORMAT.load(Ordering::Relaxed) {
+        1 => Some(PathFormat::Relative),
+        2 => Some(PathFormat::Absolute),
+        3 => Some(PathFormat::RepoRelative),
+        _ => None,
+    }
+}
+
+fn absolute(path: &Path, cwd: &Path) -> PathBuf {
+    let mut resolved = PathBuf::new();
+    for component in cwd.join(path).components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            other => resolved.push(other),
+        }
+    }
+    resolved
+}
+
+pub(crate) fn relative_to(path: &Path, base: &Path) -> PathBuf {
+    // ./a/b.tf, or ../../a/b.tf when the path is outside base
+    let path_parts: Vec<Component> = path.components().collect();
+    let base_parts: Vec<Component> = base.components().collect();
+    let common = path_parts.iter().zip(&base_parts).take_while(|(a, b)| a == b).count();
+    let mut relative = PathBuf::new();
+    if common == base_parts.len() {
+        relative.push(".");
+    }
+    for _ in common..base_parts.len() {
+        relative.push("..");
+    }
+    relative.extend(&path_parts[common..]);
+    relative
+}
+
+pub(crate) fn resolve(path: &Path) -> PathBuf {
+    // Absolute against the current directory
+    absolute(path, &std::env::current_dir().unwrap_or_default())
+}
+
+pub fn repo_root(path: &Path) -> Option<&Path> {
+    // .git is a directory, or a file in worktrees and submodules
+    path.ancestors().find(|dir| dir.join(".git").exists())
+}
+
+pub fn format_path(path: &Path, format: PathFormat, cwd: &Path) -> PathBuf {
+    let resolved = absolute(path, cwd);
+    match format {
+        PathFormat::Relative => relative_to(&resolved, &absolute(cwd, cwd)),
+        PathFormat::Absolute => resolved,
+        PathFormat::RepoRelative => match repo_root(&resolved) {
+            Some(root) => resolved.strip_prefix(root).map(Path::to_path_buf).unwrap_or_default(),
+            None => resolved,
+        },
+    }
+}
+
+pub fn apply(path: &Path) -> PathBuf {
+    let cwd = std::env::current_dir().unwrap_or_default();
+    if crate::stable::enabled() {
+        // Repo-relative unless asked otherwise, and never absolute by default
+        let format = current().unwrap_or(PathFormat::RepoRelative);
+        if format == PathFormat::RepoRelative && repo_root(&absolute(path, &cwd)).is_none() {
+            return format_path(path, PathFormat::Relative, &cwd);
+        }
+        return format_path(path, format, &cwd);
+    }
+    let Some(format) = current() else {
+        return path.to_path_buf();
+    };
+    format_path(path, format, &cwd)
+}
+
+pub fn show(path: &Path) -> String {
+    apply(path).display().to_string()
+}
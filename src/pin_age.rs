@@ -0,0 +1,121 @@
+// How old module pins are: `tv pin-age`, `tv stats-dir --lookup` and
+// `max_pin_age` in the policy file (see policy.rs).
+//
+// A module is pinned when its git `ref` is a version or a commit, or its
+// registry `version` is one exact version; local modules are never pinned.
+// A pin's age is the time since its release: the tag's date for git (see
+// updates::list_tags_with_dates) and `published_at` for the registry.
+// Commits and tags the repository does not have are of unknown age.
+
+use anyhow::Result;
+use hcl_edit::expr::Expression;
+use hcl_edit::structure::Block;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::constraint::{Version, exact_version};
+use crate::registry::ModuleAddress;
+use crate::source::{ModuleSource, SourceKind};
+use crate::updates::ReleaseDates;
+use crate::{ScanQuery, block_matches, directory_of, find_all_tf_files, indirection, read_tf_body, typed_from_raw};
+
+const DAY: u64 = 24 * 60 * 60;
+
+pub(crate) struct ModulePin {
+    pub module: String,
+    pub source: ModuleSource,
+    pub registry: Option<ModuleAddress>,
+    pub pin: Option<String>,  // Set only when pinned
+}
+
+fn is_commit(reference: &str) -> bool {
+    (7..=40).contains(&reference.len()) && reference.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+pub(crate) fn module_pin(dir: &Path, block: &Block) -> Result<Option<ModulePin>> {
+    // None for modules without a literal source
+    let Some(Expression::String(source_text)) = block.body.get_attribute("source").map(|a| &a.value) else {
+        return Ok(None);
+    };
+    let module = block.labels.first().map(|l| l.as_str().to_string()).unwrap_or_default();
+    let source = ModuleSource::parse(source_text.value());
+    let registry = ModuleAddress::parse(source_text.value());
+    let pin = match (&registry, source.git_ref()) {
+        (Some(_), _) => match block.body.get_attribute("version") {
+            Some(version) => {
+                let resolved = indirection::resolve(dir, version.value.to_string().trim())?;
+                exact_version(&typed_from_raw(&resolved).value)
+            }
+            None => None,
+        },
+        (None, Some(reference)) => {
+            let reference = indirection::resolve(dir, reference)?;
+            (Version::parse(&reference).is_ok() || is_commit(&reference)).then_some(reference)
+        }
+        (None, None) => None,
+    };
+    Ok(Some(ModulePin { module, source, registry, pin }))
+}
+
+pub(crate) fn released(dates: &mut ReleaseDates, pin: &ModulePin) -> Result<Option<u64>> {
+    let Some(ref version) = pin.pin else {
+        return Ok(None);
+    };
+    match pin.registry {
+        Some(ref address) => dates.registry_version(address, version),
+        None if pin.source.kind == SourceKind::Git => dates.git_tag(&pin.source.url, version),
+        None => Ok(None),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PinAge {
+    pub file: PathBuf,
+    pub module: String,
+    pub pin: String,  // The ref or version
+    pub days: Option<u64>,  // None when the release date is unknown
+    pub released: Option<u64>,  // Unix time
+}
+
+impl PinAge {
+    pub fn shown(&self) -> Option<String> {
+        // "512 days", or the release date with --stable (see stable.rs)
+        match (self.days, self.released) {
+            (_, Some(released)) if crate::stable::enabled() => Some(format!("released {}", crate::stable::date(released))),
+            (Some(days), _) => Some(format!("{} days", days)),
+            _ => None,
+        }
+    }
+}
+
+pub(crate) fn age_in_days(released: u64) -> u64 {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+    now.saturating_sub(released) / DAY
+}
+
+pub fn pin_ages(dir: &Path, query: &ScanQuery, dates: &mut ReleaseDates) -> Result<Vec<PinAge>> {
+    // Every pinned module the query addresses, in file order
+    let mut ages = Vec::new();
+    for file in find_all_tf_files(dir)? {
+        for block in read_tf_body(&file)?.get_blocks("module") {
+            if !block_matches(block, query) {
+                continue;
+            }
+            let Some(pin) = module_pin(directory_of(&file), block)? else {
+                continue;
+            };
+            if pin.source.kind == SourceKind::Local || pin.pin.is_none() {
+                continue;
+            }
+            let released = released(dates, &pin)?;
+            ages.push(PinAge {
+                file: file.clone(),
+                module: pin.module,
+                pin: pin.pin.unwrap_or_default(),
+                days: released.map(age_in_days),
+                released,
+            });
+        }
+    }
+    Ok(ages)
+}
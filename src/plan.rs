@@ -0,0 +1,170 @@
+// Plan files: `tv upgrade --plan-out upgrade.plan` records the changes an
+// upgrade would make instead of making them, and `tv apply-plan
+// upgrade.plan` makes them later, e.g. after review on another machine.
+//
+// A plan is JSON: one change per value, with the file (as reported by
+// upgrade, so apply it from the same working directory), the address that
+// is written (see updates.rs) and the old and new values. Before anything
+// is written every change is checked: if any address no longer holds its
+// old value, nothing is applied. The changes are one transaction (see
+// journal.rs).
+//
+// Plans can be signed with minisign (`--sign-key`, next to the plan as
+// <plan>.minisig) and `apply-plan --verify-key` then refuses a plan whose
+// signature is missing or does not match, so what is applied is exactly
+// what was reviewed. Signing runs the minisign binary (it may prompt for
+// the key's password); verifying needs only the public key.
+
+use anyhow::{Context, Result, anyhow};
+use minisign_verify::{PublicKey, Signature};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::updates::Update;
+use crate::{get_typed_value_resolved, set_value};
+
+pub const PLAN_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Change {
+    pub file: PathBuf,
+    pub address: String,  // e.g. module.vpc.source["ref"]
+    pub old: String,
+    pub new: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Plan {
+    pub version: u32,
+    pub changes: Vec<Change>,
+}
+
+impl Plan {
+    pub fn from_updates(updates: &[Update]) -> Plan {
+        Plan {
+            version: PLAN_VERSION,
+            changes: updates
+                .iter()
+                .map(|u| Change {
+                    file: u.file.clone(),
+                    address: u.query.clone(),
+                    old: u.current.clone(),
+                    new: u.latest.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+pub fn save(plan: &Plan, path: &Path) -> Result<()> {
+    let json = serde_json::to_string_pretty(plan).context("Failed to serialize plan")?;
+    fs::write(path, json + "\n").with_context(|| format!("Failed to write plan: {:?}", path))
+}
+
+pub fn parse(content: &str) -> Result<Plan> {
+    let plan: Plan = serde_json::from_str(content).context("Invalid plan")?;
+    if plan.version != PLAN_VERSION {
+        return Err(anyhow!("Unsupported plan version {} (expected {})", plan.version, PLAN_VERSION));
+    }
+    Ok(plan)
+}
+
+pub fn signature_path(plan: &Path) -> PathBuf {
+    let mut path = plan.as_os_str().to_owned();
+    path.push(".minisig");
+    PathBuf::from(path)
+}
+
+pub fn sign(plan: &Path, secret_key: &Path) -> Result<()> {
+    // Interactive: minisign asks for the key's password on the terminal
+    let status = Command::new("minisign")
+        .arg("-S")
+        .arg("-s")
+        .arg(secret_key)
+        .arg("-m")
+        .arg(plan)
+        .arg("-x")
+        .arg(signature_path(plan))
+        .status()
+        .context("Failed to run minisign (is it installed?)")?;
+    if !status.success() {
+        return Err(anyhow!("minisign failed to sign {:?}", plan));
+    }
+    Ok(())
+}
+
+pub fn parse_public_key(key: &str) -> Result<PublicKey> {
+    // A minisign.pub file, or the key itself (RW...)
+    let path = Path::new(key);
+    if path.is_file() {
+        let content = fs::read_to_string(path).with_context(|| format!("Failed to read public key: {:?}", path))?;
+        return PublicKey::decode(content.trim()).map_err(|e| anyhow!("Invalid public key in {:?}: {}", path, e));
+    }
+    PublicKey::from_base64(key.trim()).map_err(|e| anyhow!("Invalid public key '{}': {}", key, e))
+}
+
+pub fn verify(content: &[u8], signature: &str, public_key: &PublicKey) -> Result<()> {
+    let signature = Signature::decode(signature.trim()).map_err(|e| anyhow!("Invalid signature: {}", e))?;
+    // Legacy (non-prehashed) signatures are refused
+    public_key
+        .verify(content, &signature, false)
+        .map_err(|e| anyhow!("Signature does not match: {}", e))
+}
+
+pub fn load(path: &Path) -> Result<Plan> {
+    load_verified(path, None)
+}
+
+pub fn load_verified(path: &Path, public_key: Option<&PublicKey>) -> Result<Plan> {
+    // The plan is read once, so what is verified is what is applied
+    let content = fs::read_to_string(path).with_context(|| format!("Failed to read plan: {:?}", path))?;
+    if let Some(public_key) = public_key {
+        let signature_path = signature_path(path);
+        let signature = fs::read_to_string(&signature_path)
+            .with_context(|| format!("Plan is not signed: no {:?}", signature_path))?;
+        verify(content.as_bytes(), &signature, public_key).with_context(|| format!("Refusing {:?}", path))?;
+    }
+    parse(&content).with_context(|| format!("In {:?}", path))
+}
+
+pub fn stale_changes(plan: &Plan) -> Result<Vec<(Change, Option<String>)>> {
+    // Changes whose address no longer holds the old value, with what it holds
+    let mut stale = Vec::new();
+    for change in &plan.changes {
+        let current = if change.file.is_file() {
+            get_typed_value_resolved(&change.address, Some(&change.file))?.map(|typed| typed.value)
+        } else {
+            None
+        };
+        if current.as_deref() != Some(change.old.as_str()) {
+            stale.push((change.clone(), current));
+        }
+    }
+    Ok(stale)
+}
+
+pub fn apply_plan(plan: &Plan) -> Result<()> {
+    let stale = stale_changes(plan)?;
+    if !stale.is_empty() {
+        let details: Vec<String> = stale
+            .iter()
+            .map(|(change, current)| {
+                format!(
+                    "{}: {} is {}, plan expects {}",
+                    change.file.display(),
+                    change.address,
+                    current.as_deref().unwrap_or("missing"),
+                    change.old
+                )
+            })
+            .collect();
+        return Err(anyhow!("Plan is out of date, nothing applied:\n  {}", details.join("\n  ")));
+    }
+    for change in &plan.changes {
+        set_value(&change.address, &change.new, Some(&change.file))
+            .with_context(|| format!("Failed to apply {} in {:?}", change.address, change.file))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,542 @@
+// Policy file: named checks over a repository, run by `tv validate`.
+//
+// The policy file (tv-policy.hcl by default) declares checks as a query and
+// the value every block it addresses is expected to have:
+//
+// check "eks-pinned" {
+//   query   = "module.eks.source[ref]"
+//   expect  = "matches:^v\\d+"
+//   message = "EKS must be pinned to a release tag"   # optional
+// }
+//
+// The query selects the blocks (its filter, if any, narrows them down) and
+// the value to test (attribute, object key and index). Expectations:
+//
+//   present              the value exists
+//   absent               the value does not exist
+//   equals:<text>        the value is exactly <text>
+//   matches:<regex>      the value matches <regex>
+//   satisfies:<range>    the value is a version within <range>, e.g. >= 5.0, < 6.0
+//
+// A check addressing no blocks passes.
+//
+// `reject_yanked = true` at the top level adds a "yanked-versions" check:
+// registry modules and providers pinned to one exact version (in .tf files
+// or .terraform.lock.hcl) fail when the registry has removed or deprecated
+// that version (see registry.rs).
+//
+// `max_pin_age = "180d"` adds a "stale-pins" check: pinned modules whose
+// tag or registry version was released longer ago fail (see pin_age.rs).
+// Pins of unknown age (commits, missing tags) are not checked.
+//
+// `reject_similar_sources = true` adds a "similar-sources" check: module or
+// provider sources that nearly match another one (a typo or a fork, see
+// similar.rs) fail where they are used.
+//
+// `convention` blocks check module labels and sources against naming rules,
+// with a suggested fix where one is mechanical (see conventions.rs).
+//
+// require_reason "prod" {
+//   paths   = ["envs/prod/**"]       # relative to the policy file
+//   pattern = "^[A-Z]+-[0-9]+$"      # optional
+// }
+//
+// Edits to files under `paths` need a `--reason` (a ticket, say) matching
+// `pattern`; it is recorded with the journal transaction (see protect.rs).
+// These are not checks, so `tv validate` does not report them.
+//
+// Files with an attribute defined twice in one block (see duplicates.rs)
+// fail a "duplicate-attributes" check, which is only reported when there
+// are any; the other checks skip those files.
+
+use anyhow::{Context, Result, anyhow};
+use hcl_edit::expr::Expression;
+use hcl_edit::structure::{Block, Body};
+use regex::Regex;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::config::parse_age;
+use crate::constraint::{Constraint, Version, exact_version, parse_constraints, satisfies};
+use crate::conventions::{Convention, convention_report, parse_convention};
+use crate::doctor::provider_requirements;
+use crate::duplicates::find_duplicates;
+use crate::pin_age::pin_ages;
+use crate::registry::{Client, ModuleAddress, ProviderAddress, RegistryVersion, VersionStatus, version_status};
+use crate::similar::similar_sources_report;
+use crate::stable;
+use crate::updates::{ReleaseDates, default_cache_dir};
+use crate::{BlockMatch, ScanOptions, ScanQuery, block_matches, find_all_tf_files, match_block, parse_scan_query, query};
+
+pub const DEFAULT_POLICY_FILE: &str = "tv-policy.hcl";
+
+#[derive(Debug, Clone)]
+pub enum Expectation {
+    Present,
+    Absent,
+    Equals(String),
+    Matches(Regex),
+    Satisfies(Vec<Constraint>),
+}
+
+impl Expectation {
+    pub fn parse(text: &str) -> Result<Expectation> {
+        let (kind, argument) = match text.split_once(':') {
+            Some((kind, argument)) => (kind.trim(), Some(argument)),
+            None => (text.trim(), None),
+        };
+        match (kind, argument) {
+            ("present", None) => Ok(Expectation::Present),
+            ("absent", None) => Ok(Expectation::Absent),
+            ("equals", Some(value)) => Ok(Expectation::Equals(value.to_string())),
+            ("matches", Some(pattern)) => Ok(Expectation::Matches(
+                Regex::new(pattern).with_context(|| format!("Invalid regex: {}", pattern))?,
+            )),
+            ("satisfies", Some(range)) => Ok(Expectation::Satisfies(
+                parse_constraints(range).with_context(|| format!("Invalid version range: {}", range))?,
+            )),
+            _ => Err(anyhow!(
+                "Invalid expectation '{}'; expected present, absent, equals:<text>, matches:<regex> or satisfies:<range>",
+                text
+            )),
+        }
+    }
+
+    pub fn check(&self, value: Option<&str>) -> std::result::Result<(), String> {
+        let describe = |value: Option<&str>| match value {
+            Some(value) => format!("\"{}\"", value),
+            None => "missing".to_string(),
+        };
+        let passed = match (self, value) {
+            (Expectation::Present, value) => value.is_some(),
+            (Expectation::Absent, value) => value.is_none(),
+            (_, None) => false,
+            (Expectation::Equals(expected), Some(value)) => value == expected,
+            (Expectation::Matches(regex), Some(value)) => regex.is_match(value),
+            (Expectation::Satisfies(range), Some(value)) => {
+                Version::parse(value).is_ok_and(|v| satisfies(&v, range))
+            }
+        };
+        if passed {
+            return Ok(());
+        }
+        Err(match self {
+            Expectation::Present => "value is missing".to_string(),
+            Expectation::Absent => format!("value is {}, expected none", describe(value)),
+            Expectation::Equals(expected) => format!("value is {}, expected \"{}\"", describe(value), expected),
+            Expectation::Matches(regex) => format!("value is {}, expected to match {}", describe(value), regex),
+            Expectation::Satisfies(range) => {
+                let range: Vec<String> = range.iter().map(|c| c.to_string()).collect();
+                format!("value is {}, expected a version {}", describe(value), range.join(", "))
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub name: String,
+    pub query: ScanQuery,
+    pub expect: Expectation,
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ReasonRule {
+    pub name: String,
+    pub paths: Vec<String>,
+    pub pattern: Option<Regex>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Policy {
+    pub checks: Vec<Check>,
+    pub reject_yanked: bool,
+    pub max_pin_age: Option<Duration>,
+    pub reject_similar_sources: bool,
+    pub require_reason: Vec<ReasonRule>,
+    pub conventions: Vec<Convention>,
+}
+
+fn string_attr(block: &Block, name: &str) -> Result<Option<String>> {
+    match block.body.get_attribute(name).map(|attr| &attr.value) {
+        None => Ok(None),
+        Some(Expression::String(s)) => Ok(Some(s.value().to_string())),
+        Some(_) => Err(anyhow!("'{}' must be a string", name)),
+    }
+}
+
+fn string_list_attr(block: &Block, name: &str) -> Result<Vec<String>> {
+    let Some(value) = block.body.get_attribute(name).map(|attr| &attr.value) else {
+        return Ok(Vec::new());
+    };
+    let not_strings = || anyhow!("'{}' must be a list of strings", name);
+    let Expression::Array(items) = value else {
+        return Err(not_strings());
+    };
+    items
+        .iter()
+        .map(|item| match item {
+            Expression::String(s) => Ok(s.value().to_string()),
+            _ => Err(not_strings()),
+        })
+        .collect()
+}
+
+fn parse_reason_rule(block: &Block) -> Result<ReasonRule> {
+    let name = block
+        .labels
+        .first()
+        .map(|l| l.as_str().to_string())
+        .ok_or_else(|| anyhow!("require_reason blocks need a name: require_reason \"<name>\" {{ ... }}"))?;
+    let context = || format!("Invalid require_reason \"{}\"", name);
+    let paths = string_list_attr(block, "paths").with_context(context)?;
+    if paths.is_empty() {
+        return Err(anyhow!("require_reason \"{}\" has no paths", name));
+    }
+    let pattern = match string_attr(block, "pattern").with_context(context)? {
+        Some(pattern) => Some(Regex::new(&pattern).with_context(|| format!("Invalid regex: {}", pattern)).with_context(context)?),
+        None => None,
+    };
+    Ok(ReasonRule { name, paths, pattern })
+}
+
+pub fn parse_policy(content: &str) -> Result<Policy> {
+    let body: Body = content.parse().context("Failed to parse policy")?;
+    let mut checks = Vec::new();
+
+    for block in body.get_blocks("check") {
+        let name = block
+            .labels
+            .first()
+            .map(|l| l.as_str().to_string())
+            .ok_or_else(|| anyhow!("check blocks need a name: check \"<name>\" {{ ... }}"))?;
+        let context = || format!("Invalid check \"{}\"", name);
+        let query = string_attr(block, "query")
+            .with_context(context)?
+            .ok_or_else(|| anyhow!("check \"{}\" has no query", name))?;
+        let expect = string_attr(block, "expect")
+            .with_context(context)?
+            .ok_or_else(|| anyhow!("check \"{}\" has no expect", name))?;
+
+        checks.push(Check {
+            query: parse_scan_query(&query).with_context(context)?,
+            expect: Expectation::parse(&expect).with_context(context)?,
+            message: string_attr(block, "message").with_context(context)?,
+            name,
+        });
+    }
+
+    let conventions: Vec<Convention> = body.get_blocks("convention").map(parse_convention).collect::<Result<_>>()?;
+
+    // Two checks with one name could not be told apart in reports
+    let names: Vec<&str> = checks.iter().map(|c| c.name.as_str()).chain(conventions.iter().map(|c| c.name.as_str())).collect();
+    for (i, name) in names.iter().enumerate() {
+        if names[..i].contains(name) {
+            return Err(anyhow!("Duplicate check name: {}", name));
+        }
+    }
+    let reject_yanked = match body.get_attribute("reject_yanked").map(|a| &a.value) {
+        None => false,
+        Some(Expression::Bool(b)) => *b.value(),
+        Some(_) => return Err(anyhow!("'reject_yanked' must be true or false")),
+    };
+    let max_pin_age = match body.get_attribute("max_pin_age").map(|a| &a.value) {
+        None => None,
+        Some(Expression::String(age)) => Some(parse_age(age.value()).context("Invalid max_pin_age")?),
+        Some(_) => return Err(anyhow!("'max_pin_age' must be a string such as \"180d\"")),
+    };
+    let reject_similar_sources = match body.get_attribute("reject_similar_sources").map(|a| &a.value) {
+        None => false,
+        Some(Expression::Bool(b)) => *b.value(),
+        Some(_) => return Err(anyhow!("'reject_similar_sources' must be true or false")),
+    };
+    let require_reason = body.get_blocks("require_reason").map(parse_reason_rule).collect::<Result<_>>()?;
+    Ok(Policy { checks, reject_yanked, max_pin_age, reject_similar_sources, require_reason, conventions })
+}
+
+pub fn load_policy(path: &Path) -> Result<Policy> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read policy file: {:?}", path))?;
+    parse_policy(&content).with_context(|| format!("Invalid policy file: {:?}", path))
+}
+
+#[derive(Debug, Clone)]
+pub struct Violation {
+    pub file: PathBuf,
+    pub block: String,
+    pub reason: String,
+    pub fix: Option<String>,  // A suggested change, when it is mechanical
+}
+
+#[derive(Debug, Clone)]
+pub struct CheckReport {
+    pub name: String,
+    pub message: Option<String>,
+    pub checked: usize,  // Blocks the query addressed
+    pub violations: Vec<Violation>,
+}
+
+impl CheckReport {
+    pub fn passed(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+fn value_at(block: &Block, query: &ScanQuery) -> Result<Option<String>> {
+    // The value the query addresses in a block, if it exists
+    let Ok(query::Target::Value(value)) = query::resolve(block, query) else {
+        return Ok(None);
+    };
+    match query.index {
+        Some(ref key) => Ok(query::select(value, key)?.map(|v| v.value)),
+        None => Ok(Some(crate::typed_from_raw(&value.to_string()).value)),
+    }
+}
+
+fn in_scope(block: &Block, query: &ScanQuery) -> Result<bool> {
+    // The block is addressed by the query; a filter narrows the blocks down
+    // but an index is what is being checked
+    if !block_matches(block, query) {
+        return Ok(false);
+    }
+    if query.filter.is_none() {
+        return Ok(true);
+    }
+    let scope = ScanQuery {
+        index: None,
+        ..query.clone()
+    };
+    Ok(matches!(match_block(block, &scope, &ScanOptions::default())?, BlockMatch::Matched(_)))
+}
+
+pub fn run_check(check: &Check, dir: &Path) -> Result<CheckReport> {
+    let mut report = CheckReport {
+        name: check.name.clone(),
+        message: check.message.clone(),
+        checked: 0,
+        violations: Vec::new(),
+    };
+    // The value itself is checked; the filter (if any) only picks blocks
+    let value_query = ScanQuery {
+        filter: None,
+        negated: false,
+        ..check.query.clone()
+    };
+
+    for file in find_all_tf_files(dir)? {
+        let content = fs::read_to_string(&file)
+            .with_context(|| format!("Failed to read file: {:?}", file))?;
+        let body = match crate::parse_tf(&content, &file) {
+            Ok(body) => body,
+            // Reported by the duplicate-attributes check
+            Err(_) if !find_duplicates(&content).is_empty() => continue,
+            Err(e) => return Err(e),
+        };
+
+        for block in body.iter().filter_map(|s| s.as_block()) {
+            if !in_scope(block, &check.query)? {
+                continue;
+            }
+            report.checked += 1;
+            let value = value_at(block, &value_query)?;
+            if let Err(reason) = check.expect.check(value.as_deref()) {
+                let labels: Vec<&str> = block.labels.iter().map(|l| l.as_str()).collect();
+                let address = if labels.is_empty() {
+                    block.ident.as_str().to_string()
+                } else {
+                    format!("{}.{}", block.ident.as_str(), labels.join("."))
+                };
+                report.violations.push(Violation { file: file.clone(), block: address, reason, fix: None });
+            }
+        }
+    }
+    Ok(report)
+}
+
+fn yanked_reason(versions: &[RegistryVersion], version: &str) -> Option<String> {
+    match version_status(versions, version) {
+        VersionStatus::Available => None,
+        VersionStatus::Removed => Some(format!("version {} has been removed from the registry", version)),
+        VersionStatus::Deprecated(reason) => Some(format!("version {} is deprecated: {}", version, reason)),
+    }
+}
+
+pub(crate) fn lock_file_pins(path: &Path) -> Result<Vec<(String, String)>> {
+    // provider "registry.terraform.io/hashicorp/aws" { version = "5.0.0" }
+    let body = crate::read_tf_body(path)?;
+    Ok(body
+        .get_blocks("provider")
+        .filter_map(|block| {
+            let address = block.labels.first()?.as_str().to_string();
+            let version = match block.body.get_attribute("version").map(|a| &a.value) {
+                Some(Expression::String(s)) => s.value().to_string(),
+                _ => return None,
+            };
+            Some((address, version))
+        })
+        .collect())
+}
+
+pub fn yanked_report(dir: &Path, registry: &Client) -> Result<CheckReport> {
+    let mut report = CheckReport {
+        name: "yanked-versions".to_string(),
+        message: Some("pin a version the registry still publishes".to_string()),
+        checked: 0,
+        violations: Vec::new(),
+    };
+    let mut directories = BTreeSet::new();
+
+    for file in find_all_tf_files(dir)? {
+        if let Some(parent) = file.parent() {
+            directories.insert(parent.to_path_buf());
+        }
+        let content = fs::read_to_string(&file).with_context(|| format!("Failed to read file: {:?}", file))?;
+        let body = match crate::parse_tf(&content, &file) {
+            Ok(body) => body,
+            Err(_) if !find_duplicates(&content).is_empty() => continue,
+            Err(e) => return Err(e),
+        };
+
+        for block in body.get_blocks("module") {
+            let field = |name: &str| match block.body.get_attribute(name).map(|a| &a.value) {
+                Some(Expression::String(s)) => Some(s.value().to_string()),
+                _ => None,
+            };
+            let (Some(source), Some(version)) = (field("source"), field("version")) else {
+                continue;
+            };
+            let (Some(address), Some(version)) = (ModuleAddress::parse(&source), exact_version(&version)) else {
+                continue;
+            };
+            report.checked += 1;
+            if let Some(reason) = yanked_reason(&registry.module_versions(&address)?, &version) {
+                let label = block.labels.first().map(|l| l.as_str()).unwrap_or_default();
+                report.violations.push(Violation {
+                    file: file.clone(),
+                    block: format!("module.{}", query::quote_segment(label)),
+                    reason,
+                    fix: None,
+                });
+            }
+        }
+
+        for requirement in provider_requirements(&body, &file) {
+            let source = requirement.source.as_deref().unwrap_or(&requirement.name);
+            let (Some(address), Some(version)) = (
+                ProviderAddress::parse(source),
+                requirement.version.as_deref().and_then(exact_version),
+            ) else {
+                continue;
+            };
+            report.checked += 1;
+            if let Some(reason) = yanked_reason(&registry.provider_versions(&address)?, &version) {
+                report.violations.push(Violation {
+                    file: file.clone(),
+                    block: format!("terraform.required_providers.{}", requirement.name),
+                    reason,
+                    fix: None,
+                });
+            }
+        }
+    }
+
+    // Lock files pin every provider a root installs
+    for directory in directories {
+        let lock_file = directory.join(".terraform.lock.hcl");
+        if !lock_file.is_file() {
+            continue;
+        }
+        for (address, version) in lock_file_pins(&lock_file)? {
+            let Some(provider) = ProviderAddress::parse(&address) else {
+                continue;
+            };
+            report.checked += 1;
+            if let Some(reason) = yanked_reason(&registry.provider_versions(&provider)?, &version) {
+                report.violations.push(Violation {
+                    file: lock_file.clone(),
+                    block: format!("provider \"{}\"", address),
+                    reason,
+                    fix: None,
+                });
+            }
+        }
+    }
+    Ok(report)
+}
+
+pub fn stale_pins_report(dir: &Path, max_age: Duration, dates: &mut ReleaseDates) -> Result<CheckReport> {
+    let max_days = max_age.as_secs() / (24 * 60 * 60);
+    let mut report = CheckReport {
+        name: "stale-pins".to_string(),
+        message: Some(format!("upgrade modules pinned to releases older than {} days", max_days)),
+        checked: 0,
+        violations: Vec::new(),
+    };
+    let all = parse_scan_query("module.*")?;
+    for age in pin_ages(dir, &all, dates)? {
+        let Some(days) = age.days else {
+            continue;
+        };
+        report.checked += 1;
+        if days > max_days {
+            report.violations.push(Violation {
+                file: age.file,
+                block: format!("module.{}", query::quote_segment(&age.module)),
+                reason: match age.released {
+                    Some(released) if stable::enabled() => format!("pinned to {}, released {}", age.pin, stable::date(released)),
+                    _ => format!("pinned to {}, released {} days ago", age.pin, days),
+                },
+                fix: None,
+            });
+        }
+    }
+    Ok(report)
+}
+
+pub fn duplicates_report(dir: &Path) -> Result<Option<CheckReport>> {
+    // Only when some file has duplicated attributes
+    let mut report = CheckReport {
+        name: "duplicate-attributes".to_string(),
+        message: Some("keep one definition, or run `tv doctor --fix keep-first|keep-last`".to_string()),
+        checked: 0,
+        violations: Vec::new(),
+    };
+    for file in find_all_tf_files(dir)? {
+        let content = fs::read_to_string(&file).with_context(|| format!("Failed to read file: {:?}", file))?;
+        report.checked += 1;
+        for duplicate in find_duplicates(&content) {
+            let lines: Vec<String> = duplicate.lines.iter().map(|(first, _)| first.to_string()).collect();
+            report.violations.push(Violation {
+                file: file.clone(),
+                block: if duplicate.block.is_empty() { "top level".to_string() } else { duplicate.block },
+                reason: format!("\"{}\" is set {} times (lines {})", duplicate.key, lines.len(), lines.join(", ")),
+                fix: None,
+            });
+        }
+    }
+    Ok((!report.passed()).then_some(report))
+}
+
+pub fn validate(policy: &Policy, dir: &Path, registry: &Client) -> Result<Vec<CheckReport>> {
+    // Files with duplicated attributes do not parse; the other checks skip them
+    let mut reports: Vec<CheckReport> = duplicates_report(dir)?.into_iter().collect();
+    for check in &policy.checks {
+        reports.push(run_check(check, dir)?);
+    }
+    for convention in &policy.conventions {
+        reports.push(convention_report(convention, dir)?);
+    }
+    if policy.reject_yanked {
+        reports.push(yanked_report(dir, registry)?);
+    }
+    if let Some(max_age) = policy.max_pin_age {
+        reports.push(stale_pins_report(dir, max_age, &mut ReleaseDates::new(&default_cache_dir(), registry))?);
+    }
+    if policy.reject_similar_sources {
+        reports.push(similar_sources_report(dir)?);
+    }
+    Ok(reports)
+}
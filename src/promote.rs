@@ -0,0 +1,73 @@
+// `tv promote --from envs/staging --to envs/prod`: carry module versions
+// from one environment to another, the staging -> prod step of a release.
+//
+// Modules are matched by directory (relative to --from and --to, so the two
+// trees can mirror each other) and name. A module in both, fetched from the
+// same source (canonical url, or registry address), gets its git `ref` or
+// registry `version` in the target set to the one in the source. Modules in
+// only one environment, from different sources, or already at the same
+// version are left alone. Edits go through `tv set`.
+
+use anyhow::{Result, anyhow};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::query::quote_segment;
+use crate::source::canonicalize_url;
+use crate::updates::{Pin, module_pins};
+use crate::{ScanQuery, directory_of, find_all_tf_files, set_value};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Promotion {
+    pub file: PathBuf,  // In the target environment
+    pub module: String,
+    pub query: String,  // Where the version is written, e.g. module.vpc.version
+    pub current: String,
+    pub promoted: String,
+}
+
+fn pins_by_module(dir: &Path, query: &ScanQuery) -> Result<HashMap<(PathBuf, String), (PathBuf, Pin)>> {
+    // (directory relative to `dir`, module name) -> file and pin
+    let mut pins = HashMap::new();
+    for file in find_all_tf_files(dir)? {
+        let relative = directory_of(&file).strip_prefix(dir).unwrap_or(Path::new("")).to_path_buf();
+        for pin in module_pins(&file, query)? {
+            pins.insert((relative.clone(), pin.module.clone()), (file.clone(), pin));
+        }
+    }
+    Ok(pins)
+}
+
+pub fn plan_promotion(from: &Path, to: &Path, query: &ScanQuery) -> Result<Vec<Promotion>> {
+    if from.canonicalize()? == to.canonicalize()? {
+        return Err(anyhow!("--from and --to are the same directory"));
+    }
+    let source = pins_by_module(from, query)?;
+    let mut promotions: Vec<Promotion> = pins_by_module(to, query)?
+        .into_iter()
+        .filter_map(|(key, (file, target))| {
+            let (_, promoted) = source.get(&key)?;
+            let same_source = canonicalize_url(&promoted.url) == canonicalize_url(&target.url)
+                && promoted.registry.is_some() == target.registry.is_some();
+            (same_source && promoted.current != target.current).then(|| Promotion {
+                file,
+                query: match target.registry {
+                    Some(_) => format!("module.{}.version", quote_segment(&target.module)),
+                    None => format!("module.{}.source[\"ref\"]", quote_segment(&target.module)),
+                },
+                module: target.module,
+                current: target.current,
+                promoted: promoted.current.clone(),
+            })
+        })
+        .collect();
+    promotions.sort_by(|a, b| (&a.file, &a.module).cmp(&(&b.file, &b.module)));
+    Ok(promotions)
+}
+
+pub fn promote(promotions: &[Promotion]) -> Result<()> {
+    for promotion in promotions {
+        set_value(&promotion.query, &promotion.promoted, Some(&promotion.file))?;
+    }
+    Ok(())
+}
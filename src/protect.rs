@@ -0,0 +1,163 @@
+// Guards on edits, checked by lib::write_file before every write and by
+// journal::undo_transaction before it restores anything.
+//
+// Protected paths, to limit what a wildcard edit can reach:
+//
+// protected_paths = ["envs/prod/**"]   # in tv.toml
+//
+// Patterns are relative to the directory of tv.toml and match a file or
+// any directory above it, so "envs/prod" protects everything below too.
+// A write to a protected file is refused unless the invocation allows it:
+// `--allow-protected`, or a yes to the question asked on a terminal (once;
+// the rest of the invocation is then allowed).
+//
+// Paths that need a reason, for change management: `require_reason` blocks
+// in tv-policy.hcl (see policy.rs), with patterns relative to the policy
+// file. Under a workspace with a `policy` (see workspaces.rs) that policy
+// applies instead, with patterns relative to the workspace's directory. A
+// write there is refused unless the invocation gives a `--reason` matching
+// the block's pattern; main records it in the journal, and `tv undo` with
+// the transaction it undoes.
+
+use anyhow::{Result, anyhow};
+use std::cell::{Cell, RefCell};
+use std::path::{Path, PathBuf};
+
+use crate::config::{CONFIG_FILE, load_config};
+use crate::policy::{DEFAULT_POLICY_FILE, ReasonRule, load_policy};
+use crate::{wildcard_match, workspaces};
+
+#[derive(Debug, Clone, Copy, Default)]
+pub enum Protection {
+    #[default]
+    Refuse,
+    Allow,
+    Confirm(fn(&Path) -> bool),  // Asked with the first protected file
+}
+
+thread_local! {
+    static PROTECTION: Cell<Protection> = const { Cell::new(Protection::Refuse) };
+    static REASON: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+pub fn set_protection(protection: Protection) {
+    PROTECTION.with(|p| p.set(protection));
+}
+
+pub fn set_reason(reason: Option<String>) {
+    REASON.with(|r| *r.borrow_mut() = reason);
+}
+
+pub(crate) fn reason() -> Option<String> {
+    REASON.with(|r| r.borrow().clone())
+}
+
+fn relative_to_nearest(file: &Path, name: &str) -> Option<(PathBuf, PathBuf)> {
+    // The directory of the nearest `name` at or above the file's directory,
+    // and the file's path relative to it
+    let dir = crate::directory_of(file).canonicalize().ok()?;
+    let root = dir.ancestors().find(|d| d.join(name).is_file())?.to_path_buf();
+    let relative = dir.join(file.file_name()?).strip_prefix(&root).ok()?.to_path_buf();
+    Some((root, relative))
+}
+
+fn covering<'a>(patterns: &'a [String], relative: &Path) -> Option<&'a String> {
+    // The first pattern matching the path or a directory above it
+    relative
+        .ancestors()
+        .filter(|p| !p.as_os_str().is_empty())
+        .map(|p| p.to_string_lossy().replace('\\', "/"))
+        .find_map(|path| {
+            patterns
+                .iter()
+                .find(|pattern| wildcard_match(pattern.trim_start_matches("./").trim_end_matches('/'), &path))
+        })
+}
+
+pub fn protected_by(file: &Path) -> Result<Option<String>> {
+    // The protected_paths pattern covering a file, if any
+    let Some((root, relative)) = relative_to_nearest(file, CONFIG_FILE) else {
+        return Ok(None);
+    };
+    let patterns = load_config(&root)?.protected_paths;
+    Ok(covering(&patterns, &relative).cloned())
+}
+
+fn policy_for(file: &Path) -> Option<(PathBuf, PathBuf)> {
+    // The policy governing a file, the one `validate` uses for its
+    // workspace, and the file's path relative to where its patterns start
+    let dir = crate::directory_of(file).canonicalize().ok()?;
+    if let Some(workspace) = workspaces::containing(&dir)
+        && let Some(policy) = workspace.policy
+    {
+        let relative = dir.join(file.file_name()?).strip_prefix(workspace.dir.canonicalize().ok()?).ok()?.to_path_buf();
+        return Some((policy, relative));
+    }
+    let (root, relative) = relative_to_nearest(file, DEFAULT_POLICY_FILE)?;
+    Some((root.join(DEFAULT_POLICY_FILE), relative))
+}
+
+fn covering_reason_rule(file: &Path) -> Result<Option<(ReasonRule, PathBuf)>> {
+    let Some((path, relative)) = policy_for(file) else {
+        return Ok(None);
+    };
+    let policy = load_policy(&path)?;
+    let rule = policy.require_reason.into_iter().find(|rule| covering(&rule.paths, &relative).is_some());
+    Ok(rule.map(|rule| (rule, path)))
+}
+
+pub fn reason_rule(file: &Path) -> Result<Option<ReasonRule>> {
+    // The first require_reason block covering a file, if any
+    Ok(covering_reason_rule(file)?.map(|(rule, _)| rule))
+}
+
+fn check_protected(file: &Path) -> Result<()> {
+    let Some(pattern) = protected_by(file)? else {
+        return Ok(());
+    };
+    let allowed = match PROTECTION.with(Cell::get) {
+        Protection::Allow => true,
+        Protection::Refuse => false,
+        Protection::Confirm(confirm) => {
+            let allowed = confirm(file);
+            set_protection(if allowed { Protection::Allow } else { Protection::Refuse });
+            allowed
+        }
+    };
+    if !allowed {
+        return Err(anyhow!(
+            "{} is protected (protected_paths \"{}\" in tv.toml); pass --allow-protected to change it",
+            crate::paths::show(file),
+            pattern
+        ));
+    }
+    Ok(())
+}
+
+fn check_reason(file: &Path) -> Result<()> {
+    let Some((rule, policy)) = covering_reason_rule(file)? else {
+        return Ok(());
+    };
+    let policy = policy.file_name().unwrap_or_default().to_string_lossy();
+    match (reason(), rule.pattern) {
+        (None, _) => Err(anyhow!(
+            "{} needs a reason (require_reason \"{}\" in {}); pass --reason, e.g. a ticket",
+            crate::paths::show(file),
+            rule.name,
+            policy
+        )),
+        (Some(reason), Some(pattern)) if !pattern.is_match(&reason) => Err(anyhow!(
+            "--reason \"{}\" does not match {} (require_reason \"{}\" in {})",
+            reason,
+            pattern,
+            rule.name,
+            policy
+        )),
+        _ => Ok(()),
+    }
+}
+
+pub(crate) fn check_write(file: &Path) -> Result<()> {
+    check_protected(file)?;
+    check_reason(file)
+}
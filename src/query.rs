@@ -0,0 +1,440 @@
+// Query grammar shared by get, set and scan.
+//
+//   block_type[.label[.name]][.nested_block...][.attribute[.object_key...]][selector]
+//
+// - Block types take one label unless they are unlabeled (terraform, locals, ...);
+//   `*` matches any label. resource and data blocks take two, type and name:
+//   resource.aws_instance.web.ami, data.aws_ami.*.most_recent.
+// - import, moved and removed blocks repeat without labels; the segment after
+//   the type may select them by the address they manage (`to`, or `from` for
+//   removed): import."aws_instance.web".id. Addresses always contain a dot,
+//   which tells them from attributes; `*` selects any, as leaving it out does.
+// - Segments can be quoted to include dots: module."my.module".source
+// - After the label, each segment names a nested block (by type or label)
+//   until one names an attribute; later segments are keys in its object value.
+//   Repeated nested blocks take `#N` (from 0): ebs_block_device#1.volume_size
+//   A trailing `*` matches the block itself (any content).
+// - The selector is either an index, `["ref"]` / `[0]` / `["Team"]`, or a
+//   filter, `[url=="https://..."]`. Both address the same things: a source
+//   string parameter, a list element or a map key. An index alone only
+//   requires it to exist; `!` negates: `[!ref]` (absent), `[ref!="v1"]`.
+//   A filter without a key compares the value itself: `[==true]`.
+//
+// get and set need a concrete address (see `parse_query`); scan accepts
+// any query (see `parse_scan_query`).
+
+use anyhow::{Result, anyhow};
+use hcl_edit::expr::Expression;
+use hcl_edit::structure::{Block, Body};
+
+use crate::{BlockMatch, TypedValue, map, typed_from_raw, typed_param};
+
+// Block types that never take a label
+const UNLABELED_BLOCKS: &[&str] = &["terraform", "locals", "moved", "import", "removed"];
+
+// Block types labeled with a type and a name
+const NAMED_BLOCKS: &[&str] = &["resource", "data"];
+
+// Unlabeled blocks that repeat, and the attribute holding the address they manage
+const ADDRESSED_BLOCKS: &[(&str, &str)] = &[("import", "to"), ("moved", "to"), ("removed", "from")];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    pub block_type: String,
+    pub block_label: Option<String>,  // None means unlabeled or wildcard; an address for import, moved, removed
+    pub block_name: Option<String>,  // Second label of resource and data blocks; None means any
+    pub nested_blocks: Vec<String>,
+    pub attribute: Option<String>,  // None if we're just matching the block
+    pub index: Option<String>,
+    pub filter: Option<AttributeFilter>,
+    pub negated: bool,  // The selector must not match: [!ref], [ref!="v1"]
+}
+
+// Scan queries used to have their own type; they are the same grammar now
+pub type ScanQuery = Query;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttributeFilter {
+    pub attribute: String,
+    pub value: String,
+}
+
+impl Query {
+    pub fn attribute_name(&self) -> &str {
+        self.attribute.as_deref().unwrap_or_default()
+    }
+}
+
+pub(crate) fn quote_segment(segment: &str) -> String {
+    // Identifiers stay bare; anything else is quoted so it reads back the same
+    let bare = !segment.is_empty()
+        && segment.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '#');
+    if bare {
+        return segment.to_string();
+    }
+    format!("\"{}\"", segment.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+impl std::fmt::Display for Query {
+    // Canonical form: parsing it yields the same query
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.block_type)?;
+        if block_takes_label(&self.block_type) {
+            match self.block_label {
+                Some(ref label) => write!(f, ".{}", quote_segment(label))?,
+                None => write!(f, ".*")?,
+            }
+            if block_takes_name(&self.block_type) {
+                match self.block_name {
+                    Some(ref name) => write!(f, ".{}", quote_segment(name))?,
+                    None => write!(f, ".*")?,
+                }
+            }
+        } else if let Some(ref address) = self.block_label {
+            write!(f, ".{}", quote_segment(address))?;
+        }
+        for nested in &self.nested_blocks {
+            write!(f, ".{}", quote_segment(nested))?;
+        }
+        match self.attribute {
+            Some(ref attribute) => write!(f, ".{}", quote_segment(attribute))?,
+            None if !self.nested_blocks.is_empty() => write!(f, ".*")?,
+            None => {}
+        }
+        if let Some(ref index) = self.index {
+            // Selector values are taken verbatim between quotes
+            let bang = if self.negated { "!" } else { "" };
+            write!(f, "[{}\"{}\"]", bang, index)?;
+        }
+        if let Some(ref filter) = self.filter {
+            let op = if self.negated { "!=" } else { "==" };
+            write!(f, "[{}{}\"{}\"]", filter.attribute, op, filter.value)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn block_takes_label(block_type: &str) -> bool {
+    !UNLABELED_BLOCKS.contains(&block_type)
+}
+
+pub fn block_takes_name(block_type: &str) -> bool {
+    NAMED_BLOCKS.contains(&block_type)
+}
+
+pub(crate) fn address_attribute(block_type: &str) -> Option<&'static str> {
+    ADDRESSED_BLOCKS.iter().find(|(t, _)| *t == block_type).map(|(_, attribute)| *attribute)
+}
+
+pub fn block_address(block: &Block) -> Option<String> {
+    // The address an import, moved or removed block manages, as written
+    let attribute = address_attribute(block.ident.as_str())?;
+    Some(block.body.get_attribute(attribute)?.value.to_string().trim().to_string())
+}
+
+pub(crate) fn block_prefix(block_type: &str, name: &str) -> String {
+    // The query addressing a block by the name scan reports for it
+    if block_takes_name(block_type)
+        && let Some((label, name)) = name.split_once('.')
+    {
+        return format!("{}.{}.{}", block_type, quote_segment(label), quote_segment(name));
+    }
+    if block_takes_label(block_type) || (address_attribute(block_type).is_some() && name.contains('.')) {
+        return format!("{}.{}", block_type, quote_segment(name));
+    }
+    block_type.to_string()
+}
+
+pub fn split_query_path(query: &str) -> Result<(Vec<String>, Option<String>)> {
+    // Split a query on dots, except inside double quotes, so labels such as
+    // module."my.module" can be addressed; `\"` and `\\` escape inside quotes.
+    // A trailing [...] (index or filter) is returned separately, unparsed.
+    let mut segments = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut chars = query.char_indices();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' if in_quotes => match chars.next() {
+                Some((_, escaped)) => current.push(escaped),
+                None => return Err(anyhow!("Unterminated escape in query")),
+            },
+            '"' => in_quotes = !in_quotes,
+            '.' if !in_quotes => segments.push(std::mem::take(&mut current)),
+            '[' if !in_quotes => {
+                let bracket_end = query
+                    .rfind(']')
+                    .filter(|&end| end > i)
+                    .ok_or_else(|| anyhow!("Unclosed bracket in query"))?;
+                segments.push(current);
+                return Ok((segments, Some(query[i + 1..bracket_end].to_string())));
+            }
+            _ => current.push(c),
+        }
+    }
+
+    if in_quotes {
+        return Err(anyhow!("Unclosed quote in query"));
+    }
+    segments.push(current);
+    Ok((segments, None))
+}
+
+pub fn parse_query_path(query: &str) -> Result<Query> {
+    let (parts, bracket) = split_query_path(query)?;
+    let block_type = parts[0].clone();
+    if block_type.is_empty() {
+        return Err(anyhow!("Query cannot be empty"));
+    }
+
+    // A leading `!` or a `!=` negates the selector
+    let mut negated = false;
+    let bracket = bracket.map(|content| match content.trim_start().strip_prefix('!') {
+        Some(rest) => {
+            negated = true;
+            rest.to_string()
+        }
+        None => content,
+    });
+
+    // An unquoted `=` makes the selector a filter, anything else is an index
+    let (index, filter) = match bracket {
+        Some(ref content) if !content.trim_start().starts_with('"') && content.contains('=') => {
+            if let Some((attribute, value)) = content.split_once("!=") {
+                if negated {
+                    return Err(anyhow!("Selector is negated twice: [!{}]", content));
+                }
+                negated = true;
+                (None, Some(parse_attribute_filter(&format!("{}=={}", attribute, value))?))
+            } else {
+                (None, Some(parse_attribute_filter(content)?))
+            }
+        }
+        Some(content) => (Some(content.trim().trim_matches('"').to_string()), None),
+        None => (None, None),
+    };
+
+    let mut rest = &parts[1..];
+    let mut block_label = None;
+    let mut block_name = None;
+    if block_takes_label(&block_type) && !rest.is_empty() {
+        if rest[0] != "*" {
+            block_label = Some(rest[0].clone());
+        }
+        rest = &rest[1..];
+        if block_takes_name(&block_type) && !rest.is_empty() {
+            if rest[0] != "*" {
+                block_name = Some(rest[0].clone());
+            }
+            rest = &rest[1..];
+        }
+    } else if address_attribute(&block_type).is_some()
+        && let Some(first) = rest.first()
+        && ((first == "*" && rest.len() > 1) || first.contains('.'))
+    {
+        if first != "*" {
+            block_label = Some(first.clone());
+        }
+        rest = &rest[1..];
+    }
+
+    // A trailing wildcard matches whatever the block contains
+    if rest.last().map(|s| s.as_str()) == Some("*") {
+        rest = &rest[..rest.len() - 1];
+        if index.is_some() || filter.is_some() {
+            return Err(anyhow!("A selector needs an attribute, not a trailing wildcard"));
+        }
+        return Ok(Query {
+            block_type,
+            block_label,
+            block_name,
+            nested_blocks: rest.to_vec(),
+            attribute: None,
+            index,
+            filter,
+            negated,
+        });
+    }
+
+    let (attribute, nested_blocks) = match rest.split_last() {
+        Some((last, nested)) => (Some(last.clone()), nested.to_vec()),
+        None => (None, Vec::new()),
+    };
+
+    Ok(Query {
+        block_type,
+        block_label,
+        block_name,
+        nested_blocks,
+        attribute,
+        index,
+        filter,
+        negated,
+    })
+}
+
+pub fn parse_query(query: &str) -> Result<Query> {
+    // Expected formats:
+    // - module.name.attribute (simple: block with label)
+    // - module.name.source["ref"] (simple with index)
+    // - module."name.with.dots".attribute (quoted label)
+    // - terraform.required_providers.aws.source (nested: terraform block -> required_providers block -> aws object attr -> source field)
+    let (parts, _) = split_query_path(query)?;
+    if parts.len() < 2 {
+        return Err(anyhow!(
+            "Query must have at least 2 parts: block_type.attribute or block_type.label.attribute"
+        ));
+    }
+
+    let parsed = parse_query_path(query)?;
+    if parts.iter().any(|p| p == "*") {
+        return Err(anyhow!("Wildcards are only supported by scan: {}", query));
+    }
+    if parsed.filter.is_some() {
+        return Err(anyhow!("Filters are only supported by scan; use an index like [\"ref\"]"));
+    }
+    if parsed.negated {
+        return Err(anyhow!("Negated selectors are only supported by scan: {}", query));
+    }
+    if parsed.attribute.is_none() {
+        return Err(anyhow!("Query must include an attribute: {}", query));
+    }
+    Ok(parsed)
+}
+
+pub fn parse_scan_query(query: &str) -> Result<ScanQuery> {
+    // Expected formats:
+    // - module.* (all modules)
+    // - module.vpc.source (specific module with attribute)
+    // - terraform.required_providers.* (terraform block with nested required_providers)
+    // - terraform.required_providers.aws (specific provider)
+    // - module.*.source[url=="https://..."] (with filter)
+    // - module.*.source[ref] / module.*.source[!ref] (parameter present / absent)
+    // - module."name.with.dots" (quoted label)
+    parse_query_path(query)
+}
+
+pub fn parse_attribute_filter(filter_str: &str) -> Result<AttributeFilter> {
+    // Parse: url=="value" or ref=="value" or path=="value"
+    // Also support single equals for matching
+
+    let (attribute, rest) = if let Some(pos) = filter_str.find("==") {
+        (&filter_str[..pos], &filter_str[pos+2..])
+    } else if let Some(pos) = filter_str.find('=') {
+        (&filter_str[..pos], &filter_str[pos+1..])
+    } else {
+        return Err(anyhow!("Invalid filter format: {}", filter_str));
+    };
+
+    let value = rest.trim().trim_matches('"').to_string();
+
+    Ok(AttributeFilter {
+        attribute: attribute.trim().to_string(),
+        value,
+    })
+}
+
+pub fn block_matches(block: &Block, query: &Query) -> bool {
+    block.ident.as_str() == query.block_type
+        && match query.block_label {
+            Some(ref label) if address_attribute(&query.block_type).is_some() => {
+                block_address(block).as_deref() == Some(label.as_str())
+            }
+            Some(ref label) => block.labels.first().map(|l| l.as_str()) == Some(label.as_str()),
+            None => true,
+        }
+        && match query.block_name {
+            Some(ref name) => block.labels.get(1).map(|l| l.as_str()) == Some(name.as_str()),
+            None => true,
+        }
+}
+
+pub(crate) fn split_repeat(segment: &str) -> (&str, usize) {
+    // `#N` picks one of repeated nested blocks, from 0: ebs_block_device#1
+    segment
+        .rsplit_once('#')
+        .and_then(|(name, n)| Some((name, n.parse::<usize>().ok()?)))
+        .unwrap_or((segment, 0))
+}
+
+pub(crate) fn nested_block_index(body: &Body, segment: &str) -> Option<usize> {
+    // Nested blocks are addressed by type (lifecycle) or label (dynamic "tag")
+    let (name, nth) = split_repeat(segment);
+    body.iter()
+        .enumerate()
+        .filter(|(_, s)| {
+            s.as_block().is_some_and(|b| b.ident.as_str() == name || b.labels.first().map(|l| l.as_str()) == Some(name))
+        })
+        .nth(nth)
+        .map(|(i, _)| i)
+}
+
+fn find_nested_block<'a>(body: &'a Body, name: &str) -> Option<&'a Block> {
+    nested_block_index(body, name).and_then(|i| body.get(i)).and_then(|s| s.as_block())
+}
+
+#[derive(Debug)]
+pub enum Target<'a> {
+    Value(&'a Expression),
+    Block(&'a Block),
+}
+
+pub fn resolve<'a>(block: &'a Block, query: &Query) -> std::result::Result<Target<'a>, BlockMatch> {
+    // Walk nested blocks, then the attribute, then keys of its object value
+    let path: Vec<&String> = query.nested_blocks.iter().chain(query.attribute.iter()).collect();
+    let mut current = block;
+
+    for (i, name) in path.iter().enumerate() {
+        let is_last = i == path.len() - 1;
+        let attribute = current.body.get_attribute(name);
+        let nested = find_nested_block(&current.body, name);
+
+        // Blocks win in the middle of the path, attributes at its end
+        let use_block = nested.is_some() && (!is_last || attribute.is_none());
+        if use_block {
+            current = nested.unwrap();
+            continue;
+        }
+
+        let Some(attribute) = attribute else {
+            return Err(if i < query.nested_blocks.len() {
+                BlockMatch::MissingNestedBlock(name.to_string())
+            } else {
+                BlockMatch::MissingAttribute(name.to_string())
+            });
+        };
+
+        let mut value = &attribute.value;
+        for key in &path[i + 1..] {
+            value = match value {
+                Expression::Object(object) => map::get_entry(object, key)
+                    .ok_or_else(|| BlockMatch::MissingAttribute(key.to_string()))?,
+                _ => return Err(BlockMatch::MissingAttribute(key.to_string())),
+            };
+        }
+        return Ok(Target::Value(value));
+    }
+
+    Ok(Target::Block(current))
+}
+
+pub fn select(value: &Expression, key: &str) -> Result<Option<TypedValue>> {
+    // What an index or filter addresses within a value:
+    // no key is the value itself
+    if key.is_empty() {
+        return Ok(Some(typed_from_raw(&value.to_string())));
+    }
+    // numeric indexes address list elements
+    if let Expression::Array(array) = value
+        && let Ok(element_index) = key.parse::<usize>()
+    {
+        return Ok(array.get(element_index).map(|e| typed_from_raw(&e.to_string())));
+    }
+    // indexes into maps address keys
+    if let Expression::Object(object) = value {
+        return Ok(map::get_entry(object, key).map(|e| typed_from_raw(&e.to_string())));
+    }
+    // anything else is a source string parameter (ref, url, path, ...)
+    typed_param(&value.to_string(), key)
+}
@@ -0,0 +1,392 @@
+// Terraform registry client: the versions published for modules and
+// providers, and their status.
+//
+// Registry hosts are found through service discovery
+// (https://<host>/.well-known/terraform.json, `modules.v1` and
+// `providers.v1`). TV_REGISTRY_URL sends every lookup to one registry
+// instead (e.g. a mirror), without discovery; `with_host` does the same for
+// a single host.
+//
+// Provider versions follow the `provider_installation` of the Terraform CLI
+// configuration when the client is given one (`with_installation`, see
+// terraformrc.rs): the versions are those of every method covering the
+// provider, network mirrors read from their index.json and filesystem
+// mirrors from disk, as `terraform init` would see them.
+//
+// Version status: a version the registry does not list (any more) is
+// removed; a listed version carrying a `deprecation` (HCP Terraform's private
+// registry marks them so) is deprecated. Neither is ever proposed as an
+// update, and `validate` can flag configuration pinned to them.
+
+use anyhow::{Context, Result, anyhow};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::source::{ModuleSource, SourceKind};
+use crate::terraformrc::{InstallMethod, ProviderInstallation, filesystem_mirror_versions, mirror_index_url, parse_mirror_index};
+
+pub const DEFAULT_HOST: &str = "registry.terraform.io";
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ModuleAddress {
+    pub host: String,
+    pub namespace: String,
+    pub name: String,
+    pub provider: String,
+}
+
+impl ModuleAddress {
+    pub fn parse(source: &str) -> Option<ModuleAddress> {
+        // [<host>/]<namespace>/<name>/<provider>, optionally //subdir
+        let source = ModuleSource::parse(source);
+        if source.kind != SourceKind::Registry {
+            return None;
+        }
+        let parts: Vec<&str> = source.url.split('/').collect();
+        let (host, rest) = match parts.len() {
+            3 => (DEFAULT_HOST, &parts[..]),
+            4 => (parts[0], &parts[1..]),
+            _ => return None,
+        };
+        Some(ModuleAddress {
+            host: host.to_lowercase(),
+            namespace: rest[0].to_string(),
+            name: rest[1].to_string(),
+            provider: rest[2].to_string(),
+        })
+    }
+
+    fn path(&self) -> String {
+        format!("{}/{}/{}", self.namespace, self.name, self.provider)
+    }
+}
+
+impl std::fmt::Display for ModuleAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.host, self.path())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProviderAddress {
+    pub host: String,
+    pub namespace: String,
+    pub name: String,
+}
+
+impl ProviderAddress {
+    pub fn parse(source: &str) -> Option<ProviderAddress> {
+        // [<host>/]<namespace>/<type>; a bare type is a hashicorp provider
+        let parts: Vec<&str> = source.trim().split('/').collect();
+        let (host, namespace, name) = match parts[..] {
+            [name] => (DEFAULT_HOST, "hashicorp", name),
+            [namespace, name] => (DEFAULT_HOST, namespace, name),
+            [host, namespace, name] => (host, namespace, name),
+            _ => return None,
+        };
+        if [host, namespace, name].iter().any(|p| p.is_empty()) {
+            return None;
+        }
+        Some(ProviderAddress {
+            host: host.to_lowercase(),
+            namespace: namespace.to_lowercase(),
+            name: name.to_lowercase(),
+        })
+    }
+}
+
+impl std::fmt::Display for ProviderAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}/{}", self.host, self.namespace, self.name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegistryVersion {
+    pub version: String,
+    pub deprecation: Option<String>,  // Reason, when deprecated
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum VersionStatus {
+    Available,
+    Deprecated(String),
+    Removed,
+}
+
+pub fn version_status(versions: &[RegistryVersion], version: &str) -> VersionStatus {
+    match versions.iter().find(|v| v.version == version.trim_start_matches('v')) {
+        None => VersionStatus::Removed,
+        Some(RegistryVersion { deprecation: Some(reason), .. }) => VersionStatus::Deprecated(reason.clone()),
+        Some(_) => VersionStatus::Available,
+    }
+}
+
+fn parse_versions(entries: Option<&Value>) -> Vec<RegistryVersion> {
+    let Some(Value::Array(entries)) = entries else {
+        return Vec::new();
+    };
+    entries
+        .iter()
+        .filter_map(|entry| {
+            let version = entry.get("version")?.as_str()?.to_string();
+            let deprecation = match entry.get("deprecation") {
+                None | Some(Value::Null) | Some(Value::Bool(false)) => None,
+                Some(Value::String(reason)) => Some(reason.clone()),
+                Some(other) => Some(
+                    other
+                        .get("reason")
+                        .and_then(Value::as_str)
+                        .unwrap_or("deprecated")
+                        .to_string(),
+                ),
+            };
+            Some(RegistryVersion { version, deprecation })
+        })
+        .collect()
+}
+
+pub struct Client {
+    base_url: Option<String>,
+    hosts: HashMap<String, String>,  // Host -> base url, skipping discovery
+    installation: Option<ProviderInstallation>,  // Where provider versions come from; None is the registry
+    agent: ureq::Agent,
+    services: Mutex<HashMap<(String, String), String>>,  // Shared by concurrent lookups
+    versions: Mutex<HashMap<String, Vec<RegistryVersion>>>,
+}
+
+impl Default for Client {
+    fn default() -> Self {
+        let base_url = std::env::var("TV_REGISTRY_URL").ok().filter(|u| !u.is_empty());
+        Client::with_base_url(base_url)
+    }
+}
+
+impl Client {
+    pub fn with_base_url(base_url: Option<String>) -> Client {
+        Client {
+            base_url: base_url.map(|u| u.trim_end_matches('/').to_string()),
+            hosts: HashMap::new(),
+            installation: None,
+            agent: ureq::AgentBuilder::new().build(),
+            services: Mutex::new(HashMap::new()),
+            versions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn with_host(mut self, host: &str, base_url: &str) -> Client {
+        self.hosts.insert(host.to_lowercase(), base_url.trim_end_matches('/').to_string());
+        self
+    }
+
+    pub fn with_installation(mut self, installation: Option<ProviderInstallation>) -> Client {
+        self.installation = installation;
+        self
+    }
+
+    fn get_json(&self, url: &str) -> Result<Value> {
+        self.get_json_optional(url)?
+            .ok_or_else(|| anyhow!("GET {} failed: not found", url))
+    }
+
+    fn get_json_optional(&self, url: &str) -> Result<Option<Value>> {
+        // None when the registry answers 404
+        crate::cancel::check()?;
+        let response = match self.agent.get(url).timeout(crate::cancel::request_timeout()).call() {
+            Ok(response) => response,
+            Err(ureq::Error::Status(404, _)) => return Ok(None),
+            Err(e) => return Err(anyhow!("GET {} failed: {}", url, e)),
+        };
+        response
+            .into_json()
+            .map(Some)
+            .with_context(|| format!("Invalid JSON from {}", url))
+    }
+
+    fn service_url(&self, host: &str, service: &str) -> Result<String> {
+        // Base url of a registry service, e.g. https://host/v1/modules/
+        if let Some(base) = self.hosts.get(&host.to_lowercase()).or(self.base_url.as_ref()) {
+            let path = if service == "modules.v1" { "/v1/modules/" } else { "/v1/providers/" };
+            return Ok(format!("{}{}", base, path));
+        }
+        let key = (host.to_string(), service.to_string());
+        if let Some(url) = self.services.lock().unwrap().get(&key) {
+            return Ok(url.clone());
+        }
+
+        let discovery = self.get_json(&format!("https://{}/.well-known/terraform.json", host))?;
+        let path = discovery
+            .get(service)
+            .and_then(Value::as_str)
+            .ok_or_else(|| anyhow!("{} does not offer {}", host, service))?;
+        let url = if path.starts_with("https://") || path.starts_with("http://") {
+            path.to_string()
+        } else {
+            format!("https://{}{}", host, path)
+        };
+        let url = if url.ends_with('/') { url } else { format!("{}/", url) };
+        self.services.lock().unwrap().insert(key, url.clone());
+        Ok(url)
+    }
+
+    fn cached(&self, key: &str, fetch: impl FnOnce() -> Result<Vec<RegistryVersion>>) -> Result<Vec<RegistryVersion>> {
+        // Not locked while fetching, so other lookups go on meanwhile
+        if let Some(versions) = self.versions.lock().unwrap().get(key) {
+            return Ok(versions.clone());
+        }
+        let versions = fetch()?;
+        self.versions.lock().unwrap().insert(key.to_string(), versions.clone());
+        Ok(versions)
+    }
+
+    pub fn module_versions(&self, address: &ModuleAddress) -> Result<Vec<RegistryVersion>> {
+        self.cached(&format!("module:{}", address), || {
+            let url = format!("{}{}/versions", self.service_url(&address.host, "modules.v1")?, address.path());
+            let json = self.get_json(&url)?;
+            // {"modules": [{"versions": [{"version": "1.0.0"}, ...]}]}
+            Ok(parse_versions(json.pointer("/modules/0/versions")))
+        })
+    }
+
+    pub fn module_exists(&self, address: &ModuleAddress) -> Result<bool> {
+        let url = format!("{}{}/versions", self.service_url(&address.host, "modules.v1")?, address.path());
+        Ok(self
+            .get_json_optional(&url)?
+            .is_some_and(|json| !parse_versions(json.pointer("/modules/0/versions")).is_empty()))
+    }
+
+    pub fn namespace_exists(&self, host: &str, namespace: &str) -> Result<bool> {
+        // Whether anyone publishes modules under the namespace
+        let url = format!("{}{}", self.service_url(host, "modules.v1")?, namespace);
+        Ok(self.get_json_optional(&url)?.is_some_and(|json| {
+            json.get("modules").and_then(Value::as_array).is_some_and(|m| !m.is_empty())
+        }))
+    }
+
+    pub fn module_published(&self, address: &ModuleAddress, version: &str) -> Result<Option<String>> {
+        // Publication time (RFC 3339) of one module version
+        let url = format!(
+            "{}{}/{}",
+            self.service_url(&address.host, "modules.v1")?,
+            address.path(),
+            version.trim_start_matches('v')
+        );
+        let json = self.get_json(&url)?;
+        Ok(json.get("published_at").and_then(Value::as_str).map(str::to_string))
+    }
+
+    pub fn module_details(&self, address: &ModuleAddress, version: Option<&str>, submodule: Option<&str>) -> Result<Option<Value>> {
+        // What the registry knows of one module version (the latest without
+        // one), or of one of its submodules (//modules/<name> sources):
+        // {"readme": ..., "inputs": [...], "outputs": [...]}
+        let mut url = format!("{}{}", self.service_url(&address.host, "modules.v1")?, address.path());
+        if let Some(version) = version {
+            url = format!("{}/{}", url, version.trim_start_matches('v'));
+        }
+        let json = self.get_json(&url)?;
+        let module = match submodule {
+            None => json.get("root"),
+            Some(path) => json
+                .get("submodules")
+                .and_then(Value::as_array)
+                .and_then(|s| s.iter().find(|m| m.get("path").and_then(Value::as_str) == Some(path))),
+        };
+        Ok(module.cloned())
+    }
+
+    pub fn module_readme(&self, address: &ModuleAddress, version: Option<&str>, submodule: Option<&str>) -> Result<Option<String>> {
+        Ok(self
+            .module_details(address, version, submodule)?
+            .as_ref()
+            .and_then(|m| m.get("readme"))
+            .and_then(Value::as_str)
+            .filter(|readme| !readme.trim().is_empty())
+            .map(str::to_string))
+    }
+
+    pub fn provider_versions(&self, address: &ProviderAddress) -> Result<Vec<RegistryVersion>> {
+        self.cached(&format!("provider:{}", address), || {
+            let Some(ref installation) = self.installation else {
+                return self.registry_provider_versions(address);
+            };
+            let methods = installation.methods_for(&address.to_string());
+            if methods.is_empty() {
+                return Err(anyhow!("No provider_installation method of the CLI configuration covers {}", address));
+            }
+            // Mirrors know nothing of deprecations; the registry's are kept
+            // when it is consulted too
+            let mut versions: Vec<RegistryVersion> = Vec::new();
+            for method in methods {
+                let found = match method {
+                    InstallMethod::Direct => self.registry_provider_versions(address)?,
+                    InstallMethod::NetworkMirror(url) => {
+                        match self.get_json_optional(&mirror_index_url(url, &address.to_string())?)? {
+                            Some(json) => mirror_versions(parse_mirror_index(&json.to_string())?),
+                            None => Vec::new(),
+                        }
+                    }
+                    InstallMethod::FilesystemMirror(path) => {
+                        mirror_versions(filesystem_mirror_versions(path, &address.to_string())?)
+                    }
+                };
+                for version in found {
+                    if !versions.iter().any(|v| v.version == version.version) {
+                        versions.push(version);
+                    }
+                }
+            }
+            Ok(versions)
+        })
+    }
+
+    fn registry_provider_versions(&self, address: &ProviderAddress) -> Result<Vec<RegistryVersion>> {
+        let url = format!(
+            "{}{}/{}/versions",
+            self.service_url(&address.host, "providers.v1")?,
+            address.namespace,
+            address.name
+        );
+        let json = self.get_json(&url)?;
+        // {"versions": [{"version": "5.0.0", "protocols": [...]}, ...]}
+        Ok(parse_versions(json.get("versions")))
+    }
+}
+
+fn mirror_versions(versions: Vec<String>) -> Vec<RegistryVersion> {
+    versions.into_iter().map(|version| RegistryVersion { version, deprecation: None }).collect()
+}
+
+pub fn parse_timestamp(text: &str) -> Option<u64> {
+    // RFC 3339 (2024-01-31T12:00:00Z or with an offset) to seconds since
+    // the epoch; fractional seconds are ignored
+    let (date, time) = text.trim().split_once(['T', 't', ' '])?;
+    let mut date_parts = date.splitn(3, '-').map(|p| p.parse::<i64>().ok());
+    let (year, month, day) = (date_parts.next()??, date_parts.next()??, date_parts.next()??);
+
+    let offset_at = time.find(['Z', 'z', '+', '-']).unwrap_or(time.len());
+    let (clock, zone) = time.split_at(offset_at);
+    let mut clock_parts = clock.split(':');
+    let hour: i64 = clock_parts.next()?.parse().ok()?;
+    let minute: i64 = clock_parts.next()?.parse().ok()?;
+    let second: i64 = clock_parts.next().unwrap_or("0").split('.').next()?.parse().ok()?;
+    let offset = match zone.chars().next() {
+        None | Some('Z') | Some('z') => 0,
+        Some(sign) => {
+            let (h, m) = zone[1..].split_once(':').unwrap_or((&zone[1..], "0"));
+            let minutes = h.parse::<i64>().ok()? * 60 + m.parse::<i64>().ok()?;
+            if sign == '-' { -minutes * 60 } else { minutes * 60 }
+        }
+    };
+
+    // Days since 1970-01-01 (proleptic Gregorian calendar)
+    let (y, m) = if month <= 2 { (year - 1, month + 9) } else { (year, month - 3) };
+    let era = y.div_euclid(400);
+    let year_of_era = y - era * 400;
+    let day_of_year = (153 * m + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146097 + day_of_era - 719468;
+
+    let seconds = days * 86400 + hour * 3600 + minute * 60 + second - offset;
+    u64::try_from(seconds).ok()
+}
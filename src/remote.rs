@@ -0,0 +1,35 @@
+// `--remote <url> [--ref <ref>]` on get and scan: query a git repository
+// without a local checkout, e.g. for a quick audit of a repository you do
+// not have cloned:
+//
+//   tv scan 'module.*' --remote https://github.com/org/infra.git --ref main
+//
+// The repository is fetched into a cache like fleet checkouts are (shallow,
+// and sparse so only .tf files are materialized; see fleet.rs) and the
+// command runs from its root, so --dir and --file are paths in the
+// repository and results print relative to it. Checkouts are keyed by url
+// and reused, fetching the ref again on every run.
+
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+use crate::fleet::{FleetRepo, SyncOptions, repo_name_from_url, sync_repo};
+use crate::self_update::sha256_hex;
+
+pub fn default_cache_dir() -> PathBuf {
+    crate::fleet::default_cache_dir().with_file_name("remote")
+}
+
+pub fn checkout_name(url: &str) -> String {
+    // infra-1a2b3c4d: readable, and distinct for org-a/infra and org-b/infra
+    format!("{}-{}", repo_name_from_url(url), &sha256_hex(url.as_bytes())[..8])
+}
+
+pub fn checkout(url: &str, git_ref: Option<&str>, cache_dir: &Path) -> Result<PathBuf> {
+    let repo = FleetRepo {
+        url: url.to_string(),
+        name: Some(checkout_name(url)),
+        git_ref: git_ref.map(str::to_string),
+    };
+    sync_repo(&repo, cache_dir, &SyncOptions::default())
+}
@@ -0,0 +1,147 @@
+// `tv rename module.vpc network`: change a module's label and rewrite the
+// `module.vpc` references to it (`module.vpc.vpc_id`, `depends_on =
+// [module.vpc]`, `"${module.vpc.arn}"`, ...) so the configuration stays
+// valid.
+//
+// References only reach modules of the same Terraform module, so the .tf
+// files of the module's directory are rewritten, not its subdirectories.
+// The `from` of a `moved` block is an old address and is left as is.
+// Terraform would plan to destroy and recreate the module's resources;
+// --moved appends a `moved` block to the module's file so state follows
+// the new name.
+
+use anyhow::{Result, anyhow};
+use hcl_edit::expr::{Expression, Traversal, TraversalOperator};
+use hcl_edit::structure::{Block, BlockLabel, Body};
+use hcl_edit::visit_mut::{VisitMut, visit_block_mut, visit_traversal_mut};
+use hcl_edit::{Decorate, Decorated, Ident};
+use std::path::{Path, PathBuf};
+
+use crate::query::block_matches;
+use crate::{directory_of, find_all_tf_files, parse_scan_query, read_tf_body, write_file, write_tf_body};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Renamed {
+    pub file: PathBuf,
+    pub references: usize,
+    pub label: bool,  // The module block is in this file
+}
+
+struct References<'a> {
+    from: &'a str,
+    to: &'a str,
+    count: usize,
+}
+
+impl VisitMut for References<'_> {
+    fn visit_traversal_mut(&mut self, node: &mut Traversal) {
+        if matches!(node.expr, Expression::Variable(ref root) if root.as_str() == "module")
+            && let Some(TraversalOperator::GetAttr(name)) = node.operators.first_mut().map(|op| op.value_mut())
+            && name.as_str() == self.from
+        {
+            *name.value_mut() = Ident::new(self.to);
+            self.count += 1;
+        }
+        visit_traversal_mut(self, node);
+    }
+
+    fn visit_block_mut(&mut self, node: &mut Block) {
+        if node.ident.as_str() != "moved" {
+            return visit_block_mut(self, node);
+        }
+        for mut structure in node.body.iter_mut() {
+            if let Some(mut attr) = structure.as_attribute_mut()
+                && attr.get().key.as_str() != "from"
+            {
+                self.visit_expr_mut(attr.value_mut());
+            }
+        }
+    }
+}
+
+fn is_module(block: &Block, label: &str) -> bool {
+    block.ident.as_str() == "module" && block.labels.first().is_some_and(|l| l.as_str() == label)
+}
+
+pub fn moved_block(from: &str, to: &str) -> String {
+    format!("moved {{\n  from = module.{}\n  to   = module.{}\n}}\n", from, to)
+}
+
+pub fn rename_module(query: &str, new_label: &str, dir: &Path, moved: bool) -> Result<Vec<Renamed>> {
+    let parsed_query = parse_scan_query(query)?;
+    let from = match parsed_query.block_label {
+        Some(ref label) if parsed_query.block_type == "module" && parsed_query.attribute.is_none() && label != "*" => {
+            label.clone()
+        }
+        _ => return Err(anyhow!("Query must name one module, e.g. module.vpc: {}", query)),
+    };
+    // References are traversals, so the new label must be an identifier
+    Ident::try_new(new_label).map_err(|_| anyhow!("Invalid module name '{}'", new_label))?;
+
+    let files: Vec<PathBuf> = find_all_tf_files(dir)?
+        .into_iter()
+        .filter(|f| crate::paths::resolve(directory_of(f)) == crate::paths::resolve(dir))
+        .collect();
+    let mut bodies = Vec::new();
+    for file in files {
+        let body = read_tf_body(&file)?;
+        bodies.push((file, body));
+    }
+    let exists = |matches: &dyn Fn(&Block) -> bool| {
+        bodies.iter().any(|(_, body)| body.iter().filter_map(|s| s.as_block()).any(matches))
+    };
+    if !exists(&|b| block_matches(b, &parsed_query)) {
+        return Err(anyhow!("Module not found: {}", query));
+    }
+    if exists(&|b| is_module(b, new_label)) {
+        return Err(anyhow!("module.{} already exists", new_label));
+    }
+
+    let mut renamed = Vec::new();
+    for (file, mut body) in bodies {
+        let mut label = false;
+        for mut structure in body.iter_mut() {
+            if let Some(block) = structure.as_block_mut()
+                && is_module(block, &from)
+            {
+                let decor = block.labels[0].decor().clone();
+                let mut new = Decorated::new(new_label.to_string());
+                *new.decor_mut() = decor;
+                block.labels[0] = BlockLabel::String(new);
+                label = true;
+            }
+        }
+        let mut references = References { from: &from, to: new_label, count: 0 };
+        references.visit_body_mut(&mut body);
+        if !label && references.count == 0 {
+            continue;
+        }
+
+        // A moved block from the old name may be there already
+        let old_address = format!("module.{}", from);
+        let has_moved = body.get_blocks("moved").any(|b| {
+            b.body.get_attribute("from").is_some_and(|a| a.value.to_string().trim() == old_address)
+        });
+        if label && moved && !has_moved {
+            let content = body.to_string();
+            let updated = format!("{}\n\n{}", content.trim_end(), moved_block(&from, new_label));
+            updated.parse::<Body>().map_err(|e| anyhow!("Not valid HCL once renamed: {}", e))?;
+            write_file(&file, &updated)?;
+        } else {
+            write_tf_body(&file, &body)?;
+        }
+        renamed.push(Renamed { file, references: references.count, label });
+    }
+    Ok(renamed)
+}
+
+pub fn render(renamed: &[Renamed], show: impl Fn(&Path) -> String) -> String {
+    renamed
+        .iter()
+        .map(|r| match (r.label, r.references) {
+            (true, 0) => format!("{}: renamed the module\n", show(&r.file)),
+            (true, n) => format!("{}: renamed the module, {} reference(s)\n", show(&r.file), n),
+            (false, n) => format!("{}: {} reference(s)\n", show(&r.file), n),
+        })
+        .collect()
+}
@@ -0,0 +1,87 @@
+// The release check-updates and upgrade pick for a pin, remembered by what
+// decides it: the source, the pinned version, the track rule's channel and
+// tag pattern, the minimum release age and --to. Roots sharing a module
+// resolve it once per run.
+//
+// With `--cache-ttl <age>` the answers are also kept on disk, in
+// resolved.json in the tag cache directory, and later runs reuse those
+// younger than the age instead of asking again. Failed lookups are never
+// kept, and a missing or corrupt file is started afresh.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::config::TrackRule;
+
+pub const CACHE_FILE: &str = "resolved.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Entry {
+    latest: Option<String>,  // None: nothing newer was allowed
+    at: u64,  // When it was resolved, seconds since the epoch
+}
+
+#[derive(Debug, Default)]
+pub struct Resolutions {
+    entries: HashMap<String, Entry>,
+    file: Option<PathBuf>,  // Kept across runs when set
+    now: u64,
+    changed: bool,
+}
+
+pub fn key(source: &str, current: &str, rule: &TrackRule, age: Option<Duration>, to: Option<&str>) -> String {
+    // The rule's pattern only picks the modules, so it is left out
+    format!(
+        "{}\t{}\t{:?}\t{}\t{}\t{}",
+        source,
+        current,
+        rule.channel,
+        rule.tags.as_deref().unwrap_or_default(),
+        age.map(|a| a.as_secs().to_string()).unwrap_or_default(),
+        to.unwrap_or_default()
+    )
+}
+
+impl Resolutions {
+    pub fn in_memory(now: u64) -> Resolutions {
+        Resolutions { now, ..Default::default() }
+    }
+
+    pub fn load(cache_dir: &Path, ttl: Duration, now: u64) -> Resolutions {
+        // Entries older than the ttl are dropped
+        let file = cache_dir.join(CACHE_FILE);
+        let entries: HashMap<String, Entry> = fs::read_to_string(&file)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        let entries = entries.into_iter().filter(|(_, e)| now.saturating_sub(e.at) < ttl.as_secs()).collect();
+        Resolutions { entries, file: Some(file), now, changed: false }
+    }
+
+    pub fn get(&self, key: &str) -> Option<Option<String>> {
+        self.entries.get(key).map(|e| e.latest.clone())
+    }
+
+    pub fn insert(&mut self, key: String, latest: Option<String>) {
+        self.entries.insert(key, Entry { latest, at: self.now });
+        self.changed = true;
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let Some(ref file) = self.file else {
+            return Ok(());
+        };
+        if !self.changed {
+            return Ok(());
+        }
+        if let Some(dir) = file.parent() {
+            fs::create_dir_all(dir).with_context(|| format!("Failed to create cache directory: {:?}", dir))?;
+        }
+        fs::write(file, serde_json::to_string(&self.entries)?)
+            .with_context(|| format!("Failed to write resolution cache: {:?}", file))
+    }
+}
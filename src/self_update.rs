@@ -0,0 +1,215 @@
+// `tv self update` and `tv self pin`: the tv binary itself.
+//
+// Releases are GitHub releases of manoelhc/tv with one binary per platform,
+// tv-<arch>-<os> (tv-x86_64-linux, tv-aarch64-macos, tv-x86_64-windows.exe),
+// and a SHA256SUMS file listing their checksums (.github/workflows/release.yml
+// publishes both next to the archives). `tv self update` picks the
+// newest release (or --version, or the newest the pin allows), downloads the
+// binary for this platform, checks it against SHA256SUMS and only then
+// renames it over the running executable. A release without a checksum for
+// the binary is refused.
+//
+// `tv self pin 1.4.x` records the versions a repository expects in tv.toml:
+//
+// tv_version = "1.4.x"   # 1.4.x, 1.*, or a constraint such as ">= 1.4, < 2"
+//
+// Every command then warns when the running tv is outside the pin, and
+// `tv self update` stays within it.
+
+use anyhow::{Context, Result, anyhow};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::config::{CONFIG_FILE, Config, find_config};
+use crate::constraint::{Version, parse_version_spec, satisfies};
+use crate::write_file;
+
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+pub const REPOSITORY: &str = "manoelhc/tv";
+pub const CHECKSUMS: &str = "SHA256SUMS";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Release {
+    pub tag: String,
+    pub version: Version,
+    pub assets: HashMap<String, String>,  // Name -> download url
+}
+
+pub struct Releases {
+    api_url: String,
+    agent: ureq::Agent,
+}
+
+impl Default for Releases {
+    fn default() -> Self {
+        let api_url = std::env::var("TV_RELEASES_URL").ok().filter(|u| !u.is_empty());
+        Releases::with_api_url(api_url.as_deref().unwrap_or("https://api.github.com"))
+    }
+}
+
+impl Releases {
+    pub fn with_api_url(api_url: &str) -> Releases {
+        Releases { api_url: api_url.trim_end_matches('/').to_string(), agent: ureq::AgentBuilder::new().build() }
+    }
+
+    fn get(&self, url: &str) -> Result<ureq::Response> {
+        crate::cancel::check()?;
+        self.agent
+            .get(url)
+            .timeout(crate::cancel::request_timeout())
+            .call()
+            .map_err(|e| anyhow!("GET {} failed: {}", url, e))
+    }
+
+    pub fn list(&self) -> Result<Vec<Release>> {
+        // Published, non-pre-release versions, newest first
+        let url = format!("{}/repos/{}/releases", self.api_url, REPOSITORY);
+        let json: Value = self.get(&url)?.into_json().with_context(|| format!("Invalid JSON from {}", url))?;
+        let mut releases: Vec<Release> = json
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|r| !r["draft"].as_bool().unwrap_or(false) && !r["prerelease"].as_bool().unwrap_or(false))
+            .filter_map(|r| {
+                let tag = r["tag_name"].as_str()?.to_string();
+                let version = Version::parse(&tag).ok()?;
+                let assets = r["assets"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|a| Some((a["name"].as_str()?.to_string(), a["browser_download_url"].as_str()?.to_string())))
+                    .collect();
+                Some(Release { tag, version, assets })
+            })
+            .collect();
+        releases.sort_by(|a, b| b.version.cmp(&a.version));
+        Ok(releases)
+    }
+
+    pub fn download(&self, url: &str) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        self.get(url)?
+            .into_reader()
+            .read_to_end(&mut bytes)
+            .with_context(|| format!("Failed to download {}", url))?;
+        Ok(bytes)
+    }
+}
+
+pub fn asset_name() -> String {
+    let extension = if cfg!(windows) { ".exe" } else { "" };
+    format!("tv-{}-{}{}", std::env::consts::ARCH, std::env::consts::OS, extension)
+}
+
+pub fn choose<'a>(releases: &'a [Release], pin: Option<&str>, requested: Option<&str>) -> Result<&'a Release> {
+    let allowed = pin.map(parse_version_spec).transpose()?;
+    let in_pin = |release: &&Release| allowed.as_ref().is_none_or(|c| satisfies(&release.version, c));
+    match requested {
+        Some(requested) => {
+            let wanted = Version::parse(requested)?;
+            let release = releases
+                .iter()
+                .find(|r| r.version == wanted)
+                .ok_or_else(|| anyhow!("No release {}", requested))?;
+            if !in_pin(&release) {
+                return Err(anyhow!("{} is outside the pinned tv_version {}", release.tag, pin.unwrap_or_default()));
+            }
+            Ok(release)
+        }
+        None => releases.iter().find(in_pin).ok_or_else(|| match pin {
+            Some(pin) => anyhow!("No release matches the pinned tv_version {}", pin),
+            None => anyhow!("No releases found"),
+        }),
+    }
+}
+
+pub fn parse_checksums(text: &str) -> HashMap<String, String> {
+    // sha256sum output: `<hex>  <name>`, or `<hex> *<name>` for binary mode
+    text.lines()
+        .filter_map(|line| {
+            let (hash, name) = line.trim().split_once(char::is_whitespace)?;
+            let name = name.trim_start().trim_start_matches('*');
+            Some((name.to_string(), hash.to_lowercase()))
+        })
+        .collect()
+}
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn replace_executable(exe: &Path, bytes: &[u8]) -> Result<()> {
+    // Next to the executable, so the rename stays on one filesystem
+    let name = exe.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+    let temp = exe.with_file_name(format!(".{}.tv-tmp", name));
+    let result = fs::write(&temp, bytes).and_then(|_| {
+        if let Ok(metadata) = fs::metadata(exe) {
+            fs::set_permissions(&temp, metadata.permissions())?;
+        }
+        fs::rename(&temp, exe)
+    });
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result.with_context(|| format!("Failed to replace {:?}", exe))
+}
+
+pub fn update(releases: &Releases, release: &Release, exe: &Path) -> Result<()> {
+    let asset = asset_name();
+    let binary_url = release
+        .assets
+        .get(&asset)
+        .ok_or_else(|| anyhow!("Release {} has no binary for this platform ({})", release.tag, asset))?;
+    let checksums_url = release
+        .assets
+        .get(CHECKSUMS)
+        .ok_or_else(|| anyhow!("Release {} has no {}; refusing to install an unverified binary", release.tag, CHECKSUMS))?;
+
+    let checksums = parse_checksums(&String::from_utf8_lossy(&releases.download(checksums_url)?));
+    let expected = checksums
+        .get(&asset)
+        .ok_or_else(|| anyhow!("{} of {} has no checksum for {}", CHECKSUMS, release.tag, asset))?;
+    let binary = releases.download(binary_url)?;
+    let actual = sha256_hex(&binary);
+    if actual != *expected {
+        return Err(anyhow!("Checksum mismatch for {}: expected {}, got {}", asset, expected, actual));
+    }
+    crate::cancel::check()?;
+    replace_executable(exe, &binary)
+}
+
+pub fn pin_warning(config: &Config, running: &str) -> Option<String> {
+    let pin = config.tv_version.as_deref()?;
+    let constraints = parse_version_spec(pin).ok()?;
+    let version = Version::parse(running).ok()?;
+    (!satisfies(&version, &constraints)).then(|| {
+        format!("this is tv {}, but tv.toml pins tv_version = \"{}\" (run `tv self update`)", running, pin)
+    })
+}
+
+pub fn pin(dir: &Path, spec: &str) -> Result<PathBuf> {
+    // Sets tv_version in the nearest tv.toml (or a new one in dir), keeping
+    // the rest of the file as written
+    parse_version_spec(spec)?;
+    let path = find_config(dir).unwrap_or_else(|| dir.join(CONFIG_FILE));
+    let content = fs::read_to_string(&path).unwrap_or_default();
+    let entry = format!("tv_version = \"{}\"", spec);
+
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+    // Top-level keys come before the first table
+    let top_level = lines.iter().position(|l| l.trim_start().starts_with('[')).unwrap_or(lines.len());
+    let existing = lines[..top_level].iter().position(|l| {
+        l.split_once('=').is_some_and(|(key, _)| key.trim() == "tv_version")
+    });
+    match existing {
+        Some(i) => lines[i] = entry,
+        None if top_level == lines.len() => lines.push(entry),
+        None => lines.splice(top_level..top_level, [entry, String::new()]).for_each(drop),
+    }
+    write_file(&path, &(lines.join("\n") + "\n"))?;
+    Ok(path)
+}
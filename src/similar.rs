@@ -0,0 +1,177 @@
+// Near-duplicate sources: module or provider sources that differ by a few
+// characters, e.g. terraform-aws-module/vpc/aws next to
+// terraform-aws-modules/vpc/aws, or http:// next to https://. Usually one
+// of them is a typo or an unintended fork.
+//
+// Sources are compared canonicalized (see source::canonicalize_url), one
+// segment at a time: two sources are similar when they have as many
+// segments and every segment that differs is itself at least SIMILARITY
+// alike by Levenshtein distance. Comparing whole strings would pair
+// terraform-aws-modules/vpc/aws with terraform-aws-modules/eks/aws.
+//
+// The source used less often is the suspect; the other one is suggested.
+// Reported by `tv doctor` and, with `reject_similar_sources = true`, by
+// `tv validate`.
+
+use anyhow::Result;
+use hcl_edit::expr::Expression;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::doctor::provider_requirements;
+use crate::policy::{CheckReport, Violation};
+use crate::query::quote_segment;
+use crate::source::{ModuleSource, SourceKind, canonicalize_url};
+use crate::{find_all_tf_files, read_tf_body};
+
+pub const SIMILARITY: f64 = 0.8;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceUse {
+    pub file: PathBuf,
+    pub block: String,  // e.g. module.vpc or provider.aws
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimilarSources {
+    pub kind: &'static str,  // "module" or "provider"
+    pub suspect: String,  // As written where first used
+    pub likely: String,
+    pub score: f64,  // Levenshtein similarity of the canonical sources, 0..1
+    pub uses: Vec<SourceUse>,  // Of the suspect
+    pub likely_uses: usize,
+}
+
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if ca == *cb { diagonal } else { 1 + diagonal.min(above).min(row[j]) };
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+pub fn similarity(a: &str, b: &str) -> f64 {
+    // 1 for equal strings, 0 for nothing in common
+    let longest = a.chars().count().max(b.chars().count());
+    if longest == 0 {
+        return 1.0;
+    }
+    1.0 - levenshtein(a, b) as f64 / longest as f64
+}
+
+fn segments(source: &str) -> Vec<&str> {
+    source.split(['/', ':']).collect()
+}
+
+pub fn near_duplicate(a: &str, b: &str) -> bool {
+    // Canonical sources that are different, but only slightly
+    let (a, b) = (segments(a), segments(b));
+    a != b && a.len() == b.len() && a.iter().zip(&b).all(|(x, y)| x == y || similarity(x, y) >= SIMILARITY)
+}
+
+#[derive(Default)]
+struct Sources {
+    written: String,
+    uses: Vec<SourceUse>,
+}
+
+fn collect(dir: &Path) -> Result<BTreeMap<(&'static str, String), Sources>> {
+    // (kind, canonical source) -> how it is written and where it is used
+    let mut sources: BTreeMap<(&'static str, String), Sources> = BTreeMap::new();
+    let mut add = |kind: &'static str, written: &str, canonical: String, file: &Path, block: String| {
+        let entry = sources.entry((kind, canonical)).or_default();
+        if entry.uses.is_empty() {
+            entry.written = written.to_string();
+        }
+        entry.uses.push(SourceUse { file: file.to_path_buf(), block });
+    };
+
+    for file in find_all_tf_files(dir)? {
+        // Files that do not parse are reported by the other checks
+        let Ok(body) = read_tf_body(&file) else {
+            continue;
+        };
+        for block in body.get_blocks("module") {
+            let Some(Expression::String(source)) = block.body.get_attribute("source").map(|a| &a.value) else {
+                continue;
+            };
+            let source = ModuleSource::parse(source.value());
+            if source.kind == SourceKind::Local {
+                continue;
+            }
+            let label = block.labels.first().map(|l| l.as_str()).unwrap_or_default();
+            add("module", &source.url, canonicalize_url(&source.url), &file, format!("module.{}", quote_segment(label)));
+        }
+        for requirement in provider_requirements(&body, &file) {
+            let Some(ref source) = requirement.source else {
+                continue;
+            };
+            let lowered = source.to_lowercase();
+            let canonical = lowered.strip_prefix("registry.terraform.io/").unwrap_or(&lowered).to_string();
+            add("provider", source, canonical, &file, format!("provider.{}", requirement.name));
+        }
+    }
+    Ok(sources)
+}
+
+fn find_similar(sources: &BTreeMap<(&'static str, String), Sources>) -> Vec<SimilarSources> {
+    let sources: Vec<_> = sources.iter().collect();
+    let mut similar = Vec::new();
+    for (i, &((kind, a), a_sources)) in sources.iter().enumerate() {
+        for &((other_kind, b), b_sources) in &sources[i + 1..] {
+            if kind != other_kind || !near_duplicate(a, b) {
+                continue;
+            }
+            // The one used less often is the suspect; on a tie, the later one
+            let (suspect, likely) = if a_sources.uses.len() < b_sources.uses.len() {
+                (a_sources, b_sources)
+            } else {
+                (b_sources, a_sources)
+            };
+            similar.push(SimilarSources {
+                kind,
+                suspect: suspect.written.clone(),
+                likely: likely.written.clone(),
+                score: similarity(a, b),
+                uses: suspect.uses.clone(),
+                likely_uses: likely.uses.len(),
+            });
+        }
+    }
+    similar
+}
+
+pub fn similar_sources(dir: &Path) -> Result<Vec<SimilarSources>> {
+    Ok(find_similar(&collect(dir)?))
+}
+
+pub fn similar_sources_report(dir: &Path) -> Result<CheckReport> {
+    let sources = collect(dir)?;
+    let mut report = CheckReport {
+        name: "similar-sources".to_string(),
+        message: Some("use one spelling of each source; a near match is usually a typo or a fork".to_string()),
+        checked: sources.len(),
+        violations: Vec::new(),
+    };
+    for similar in find_similar(&sources) {
+        for used in &similar.uses {
+            report.violations.push(Violation {
+                file: used.file.clone(),
+                block: used.block.clone(),
+                reason: format!(
+                    "{} source \"{}\" is {:.2} similar to \"{}\"",
+                    similar.kind, similar.suspect, similar.score, similar.likely
+                ),
+                fix: Some(format!("use \"{}\" if it is meant to be the same {}", similar.likely, similar.kind)),
+            });
+        }
+    }
+    Ok(report)
+}
@@ -0,0 +1,184 @@
+// Module source strings decomposed into their parts.
+//
+// Format (go-getter style): [getter::]url[//path][?key=value&...]
+// e.g. git::https://github.com/org/repo.git//modules/vpc?ref=v1.0.0
+
+use anyhow::{Result, anyhow};
+
+use crate::{extract_path_from_source, extract_url_from_source};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SourceKind {
+    Local,
+    Registry,
+    Git,
+    Mercurial,
+    Http,
+    S3,
+    Gcs,
+    Other,
+}
+
+impl SourceKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SourceKind::Local => "local",
+            SourceKind::Registry => "registry",
+            SourceKind::Git => "git",
+            SourceKind::Mercurial => "mercurial",
+            SourceKind::Http => "http",
+            SourceKind::S3 => "s3",
+            SourceKind::Gcs => "gcs",
+            SourceKind::Other => "other",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModuleSource {
+    pub kind: SourceKind,
+    pub url: String,
+    pub path: Option<String>,
+    pub params: Vec<(String, String)>,  // Query string, in order
+}
+
+impl ModuleSource {
+    pub fn parse(source: &str) -> ModuleSource {
+        let source = source.trim().trim_matches('"');
+        let url = extract_url_from_source(source);
+        let path = extract_path_from_source(source);
+
+        let params = match source.find('?') {
+            Some(query_start) => source[query_start + 1..]
+                .split('&')
+                .filter(|p| !p.is_empty())
+                .map(|p| match p.split_once('=') {
+                    Some((k, v)) => (k.to_string(), v.to_string()),
+                    None => (p.to_string(), String::new()),
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+
+        ModuleSource {
+            kind: detect_kind(&url),
+            url,
+            path,
+            params,
+        }
+    }
+
+    pub fn build(url: &str, path: Option<&str>, params: Vec<(String, String)>) -> Result<ModuleSource> {
+        if url.is_empty() {
+            return Err(anyhow!("Source url cannot be empty"));
+        }
+        if url.contains('?') {
+            return Err(anyhow!("Source url must not contain a query string: {}", url));
+        }
+        Ok(ModuleSource {
+            kind: detect_kind(url),
+            url: url.to_string(),
+            path: path
+                .map(|p| p.trim_start_matches('/').to_string())
+                .filter(|p| !p.is_empty()),
+            params,
+        })
+    }
+
+    pub fn param(&self, key: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn git_ref(&self) -> Option<&str> {
+        self.param("ref")
+    }
+
+    pub fn set_param(&mut self, key: &str, value: &str) {
+        match self.params.iter_mut().find(|(k, _)| k == key) {
+            Some(param) => param.1 = value.to_string(),
+            None => self.params.push((key.to_string(), value.to_string())),
+        }
+    }
+}
+
+impl std::fmt::Display for ModuleSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.url)?;
+        if let Some(ref path) = self.path {
+            write!(f, "//{}", path)?;
+        }
+        for (i, (key, value)) in self.params.iter().enumerate() {
+            let separator = if i == 0 { '?' } else { '&' };
+            write!(f, "{}{}={}", separator, key, value)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn detect_kind(url: &str) -> SourceKind {
+    if url.starts_with("./") || url.starts_with("../") {
+        return SourceKind::Local;
+    }
+    if url.starts_with("git::") || url.starts_with("git@") || url.ends_with(".git") {
+        return SourceKind::Git;
+    }
+    if url.starts_with("hg::") {
+        return SourceKind::Mercurial;
+    }
+    if url.starts_with("s3::") || url.contains(".s3.amazonaws.com/") || url.contains(".s3-") {
+        return SourceKind::S3;
+    }
+    if url.starts_with("gcs::") || url.starts_with("https://www.googleapis.com/storage/") {
+        return SourceKind::Gcs;
+    }
+    // GitHub and Bitbucket shorthands are cloned with git
+    if url.starts_with("github.com/") || url.starts_with("bitbucket.org/") {
+        return SourceKind::Git;
+    }
+    if url.starts_with("http://") || url.starts_with("https://") {
+        return SourceKind::Http;
+    }
+
+    // <namespace>/<name>/<provider> or <host>/<namespace>/<name>/<provider>
+    let parts: Vec<&str> = url.split('/').collect();
+    let valid_segment = |s: &&str| {
+        !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    };
+    match parts.len() {
+        3 if parts.iter().all(valid_segment) => SourceKind::Registry,
+        4 if parts[0].contains('.') && parts[1..].iter().all(valid_segment) => SourceKind::Registry,
+        _ => SourceKind::Other,
+    }
+}
+
+pub fn canonicalize_url(url: &str) -> String {
+    // Normalize cosmetic differences so equivalent urls compare equal:
+    // git:: prefix, scheme/host case, trailing slashes, .git suffix and
+    // the github.com/bitbucket.org shorthands
+    let mut url = url.trim().trim_start_matches("git::").to_string();
+
+    if url.starts_with("github.com/") || url.starts_with("bitbucket.org/") {
+        url = format!("https://{}", url);
+    }
+
+    // Lowercase the scheme and host, but not the path
+    if let Some(scheme_end) = url.find("://") {
+        let host_start = scheme_end + 3;
+        let host_end = url[host_start..]
+            .find('/')
+            .map(|i| host_start + i)
+            .unwrap_or(url.len());
+        url = format!("{}{}", url[..host_end].to_lowercase(), &url[host_end..]);
+    } else if let Some(rest) = url.strip_prefix("git@")
+        && let Some(colon) = rest.find(':')
+    {
+        url = format!("git@{}{}", rest[..colon].to_lowercase(), &rest[colon..]);
+    }
+
+    let trimmed = url.trim_end_matches('/');
+    let trimmed = trimmed.strip_suffix(".git").unwrap_or(trimmed);
+    trimmed.trim_end_matches('/').to_string()
+}
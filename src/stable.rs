@@ -0,0 +1,39 @@
+// `--stable`: reports that are byte-identical for identical repositories,
+// so CI can cache them or diff them as artifacts to tell "no change"
+// cheaply.
+//
+// - Paths print repo-relative (see paths.rs) unless --path-format says
+//   otherwise; outside a git repository they print relative to the current
+//   directory rather than absolute.
+// - Ages relative to now ("512 days", "(40d)") print as the release date
+//   instead, which only changes when the pin does.
+//
+// Results are already sorted (files by path, then in file order), so no
+// ordering is left to chance with or without the flag.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static STABLE: AtomicBool = AtomicBool::new(false);
+
+pub fn init(enabled: bool) {
+    STABLE.store(enabled, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    STABLE.load(Ordering::Relaxed)
+}
+
+pub fn date(timestamp: u64) -> String {
+    // YYYY-MM-DD (UTC) of a unix timestamp, the inverse of the day count in
+    // registry::parse_timestamp
+    let days = (timestamp / 86400) as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let m = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * m + 2) / 5 + 1;
+    let month = if m < 10 { m + 3 } else { m - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
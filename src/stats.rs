@@ -0,0 +1,133 @@
+// Local usage statistics: which commands and queries are run, and how long
+// they take. Off unless TV_STATS=1; nothing ever leaves the machine.
+//
+// Each invocation appends one JSON line to the stats file ($TV_STATS_FILE,
+// or stats.jsonl next to the undo journal under ~/.local/state/tv):
+//
+// {"time":1760000000,"command":"scan","query":"module.*","duration_ms":42,"ok":true}
+//
+// Only the command name and query are recorded, never values or paths.
+// `tv stats` summarizes the file per command and lists the queries run most.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Record {
+    pub time: u64,  // Unix seconds
+    pub command: String,  // e.g. "scan", "index build"
+    pub query: Option<String>,
+    pub duration_ms: u64,
+    pub ok: bool,
+}
+
+impl Record {
+    pub fn new(command: &str, query: Option<&str>, duration: Duration, ok: bool) -> Record {
+        let time = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+        Record {
+            time,
+            command: command.to_string(),
+            query: query.map(str::to_string),
+            duration_ms: duration.as_millis() as u64,
+            ok,
+        }
+    }
+}
+
+pub fn enabled() -> bool {
+    std::env::var("TV_STATS").is_ok_and(|v| matches!(v.as_str(), "1" | "true" | "on"))
+}
+
+pub fn default_stats_file() -> PathBuf {
+    if let Some(file) = std::env::var_os("TV_STATS_FILE") {
+        return PathBuf::from(file);
+    }
+    crate::journal::default_journal_dir().with_file_name("stats.jsonl")
+}
+
+pub fn append(path: &Path, record: &Record) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open {:?}", path))?;
+    writeln!(file, "{}", serde_json::to_string(record)?)?;
+    Ok(())
+}
+
+pub fn load(path: &Path) -> Result<Vec<Record>> {
+    // A missing file is no statistics; lines that do not parse are skipped
+    let Ok(content) = fs::read_to_string(path) else {
+        return Ok(Vec::new());
+    };
+    Ok(content.lines().filter_map(|line| serde_json::from_str(line).ok()).collect())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandSummary {
+    pub command: String,
+    pub runs: usize,
+    pub failures: usize,
+    pub total_ms: u64,
+    pub median_ms: u64,
+    pub p95_ms: u64,
+    pub max_ms: u64,
+}
+
+fn percentile(sorted: &[u64], percent: usize) -> u64 {
+    // Nearest rank
+    let rank = (sorted.len() * percent).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+pub fn summarize(records: &[Record], since: Option<u64>) -> Vec<CommandSummary> {
+    // Most total time first: where the time goes
+    let mut durations: BTreeMap<&str, (Vec<u64>, usize)> = BTreeMap::new();
+    for record in records.iter().filter(|r| since.is_none_or(|t| r.time >= t)) {
+        let entry = durations.entry(&record.command).or_default();
+        entry.0.push(record.duration_ms);
+        entry.1 += usize::from(!record.ok);
+    }
+    let mut summaries: Vec<CommandSummary> = durations
+        .into_iter()
+        .map(|(command, (mut ms, failures))| {
+            ms.sort_unstable();
+            CommandSummary {
+                command: command.to_string(),
+                runs: ms.len(),
+                failures,
+                total_ms: ms.iter().sum(),
+                median_ms: percentile(&ms, 50),
+                p95_ms: percentile(&ms, 95),
+                max_ms: *ms.last().unwrap_or(&0),
+            }
+        })
+        .collect();
+    summaries.sort_by(|a, b| b.total_ms.cmp(&a.total_ms).then_with(|| a.command.cmp(&b.command)));
+    summaries
+}
+
+pub fn top_queries(records: &[Record], since: Option<u64>, limit: usize) -> Vec<(String, String, usize)> {
+    // Command, query and number of runs, most run first
+    let mut counts: BTreeMap<(&str, &str), usize> = BTreeMap::new();
+    for record in records.iter().filter(|r| since.is_none_or(|t| r.time >= t)) {
+        if let Some(ref query) = record.query {
+            *counts.entry((&record.command, query)).or_default() += 1;
+        }
+    }
+    let mut top: Vec<(String, String, usize)> = counts
+        .into_iter()
+        .map(|((command, query), runs)| (command.to_string(), query.to_string(), runs))
+        .collect();
+    top.sort_by_key(|entry| std::cmp::Reverse(entry.2));
+    top.truncate(limit);
+    top
+}
@@ -0,0 +1,116 @@
+// Terminal colors for human-readable output.
+//
+// `--color auto` (the default) colors only when stdout is a terminal and
+// NO_COLOR is unset (https://no-color.org); `always` and `never` override
+// both. Values meant for scripts (get, source build) are never colored.
+
+use anyhow::{Result, anyhow};
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+impl std::str::FromStr for ColorChoice {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<ColorChoice> {
+        match s {
+            "auto" => Ok(ColorChoice::Auto),
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            _ => Err(anyhow!("Expected 'auto', 'always' or 'never', got '{}'", s)),
+        }
+    }
+}
+
+pub fn should_color(choice: ColorChoice, is_terminal: bool, no_color: Option<&str>) -> bool {
+    match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        // NO_COLOR only counts when set to something
+        ColorChoice::Auto => is_terminal && no_color.is_none_or(str::is_empty),
+    }
+}
+
+pub fn init(choice: ColorChoice) {
+    let no_color = std::env::var("NO_COLOR").ok();
+    let enabled = should_color(choice, std::io::stdout().is_terminal(), no_color.as_deref());
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+fn paint(code: &str, text: &str) -> String {
+    if enabled() {
+        format!("\x1b[{}m{}\x1b[0m", code, text)
+    } else {
+        text.to_string()
+    }
+}
+
+pub fn red(text: &str) -> String {
+    paint("31", text)
+}
+
+pub fn green(text: &str) -> String {
+    paint("32", text)
+}
+
+pub fn yellow(text: &str) -> String {
+    paint("33", text)
+}
+
+pub fn cyan(text: &str) -> String {
+    paint("36", text)
+}
+
+pub fn bold(text: &str) -> String {
+    paint("1", text)
+}
+
+pub fn dim(text: &str) -> String {
+    paint("2", text)
+}
+
+pub fn highlight(text: &str, needle: &str) -> String {
+    // Every occurrence of `needle` in bold red
+    if !enabled() || needle.is_empty() {
+        return text.to_string();
+    }
+    text.split(needle)
+        .collect::<Vec<_>>()
+        .join(&paint("1;31", needle))
+}
+
+pub fn highlight_ignore_case(text: &str, needle: &str) -> String {
+    // Like highlight, comparing ASCII letters regardless of case
+    if !enabled() || needle.is_empty() {
+        return text.to_string();
+    }
+    let lower = text.to_ascii_lowercase();
+    let needle = needle.to_ascii_lowercase();
+    let mut out = String::new();
+    let mut rest = 0;
+    while let Some(pos) = lower[rest..].find(&needle) {
+        let start = rest + pos;
+        let end = start + needle.len();
+        out.push_str(&text[rest..start]);
+        out.push_str(&paint("1;31", &text[start..end]));
+        rest = end;
+    }
+    out.push_str(&text[rest..]);
+    out
+}
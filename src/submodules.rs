@@ -0,0 +1,137 @@
+// Git submodules and the modules vendored through them.
+//
+// `tv scan --submodules` also walks every submodule the repository's
+// .gitmodules declares, including those outside --dir (e.g. a shared
+// `vendor/modules` next to `envs/`), so an inventory covers vendored code.
+// Submodules that are not checked out are skipped.
+//
+// A module whose source is a relative path (`../../vendor/modules/vpc`) that
+// lands inside a submodule is really pinned by the submodule commit, not by
+// anything in the .tf file; doctor flags those so they are not mistaken for
+// plain local modules.
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::paths::{repo_root, resolve};
+use crate::source::{ModuleSource, SourceKind};
+use crate::{find_all_tf_files, read_tf_body};
+
+pub const GITMODULES: &str = ".gitmodules";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Submodule {
+    pub name: String,
+    pub path: PathBuf,  // Absolute
+    pub url: Option<String>,
+}
+
+pub fn parse_gitmodules(text: &str) -> Vec<(String, String, Option<String>)> {
+    // [submodule "name"] sections with path = and url = keys
+    let mut submodules = Vec::new();
+    let mut current: Option<(String, Option<String>, Option<String>)> = None;
+    let mut finish = |current: Option<(String, Option<String>, Option<String>)>| {
+        if let Some((name, Some(path), url)) = current {
+            submodules.push((name, path, url));
+        }
+    };
+    for line in text.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            finish(current.take());
+            current = line
+                .strip_prefix("[submodule")
+                .and_then(|rest| rest.trim().strip_suffix(']'))
+                .map(|name| (name.trim().trim_matches('"').to_string(), None, None));
+        } else if let Some((key, value)) = line.split_once('=')
+            && let Some((_, path, url)) = current.as_mut()
+        {
+            let value = value.trim().to_string();
+            match key.trim() {
+                "path" => *path = Some(value),
+                "url" => *url = Some(value),
+                _ => {}
+            }
+        }
+    }
+    finish(current);
+    submodules
+}
+
+pub fn submodules(dir: &Path) -> Result<Vec<Submodule>> {
+    // Those of the repository holding dir; none outside a repository
+    let dir = resolve(dir);
+    let Some(root) = repo_root(&dir) else {
+        return Ok(Vec::new());
+    };
+    let gitmodules = root.join(GITMODULES);
+    if !gitmodules.is_file() {
+        return Ok(Vec::new());
+    }
+    let text = fs::read_to_string(&gitmodules).with_context(|| format!("Failed to read {:?}", gitmodules))?;
+    Ok(parse_gitmodules(&text)
+        .into_iter()
+        .map(|(name, path, url)| Submodule { name, path: root.join(path), url })
+        .collect())
+}
+
+pub fn containing<'a>(submodules: &'a [Submodule], path: &Path) -> Option<&'a Submodule> {
+    let path = resolve(path);
+    submodules.iter().find(|s| path.starts_with(&s.path))
+}
+
+pub fn submodule_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    // .tf files of checked-out submodules outside dir (those inside are
+    // walked anyway)
+    let inside = resolve(dir);
+    let mut files = Vec::new();
+    for submodule in submodules(dir)? {
+        if submodule.path.starts_with(&inside) || !submodule.path.is_dir() {
+            continue;
+        }
+        files.extend(find_all_tf_files(&submodule.path)?);
+    }
+    Ok(files)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VendoredModule {
+    pub file: PathBuf,
+    pub module: String,
+    pub source: String,
+    pub submodule: Submodule,
+}
+
+pub fn vendored_modules(files: &[PathBuf], submodules: &[Submodule]) -> Vec<VendoredModule> {
+    let mut vendored = Vec::new();
+    if submodules.is_empty() {
+        return vendored;
+    }
+    for file in files {
+        let Ok(body) = read_tf_body(file) else {
+            continue;
+        };
+        for block in body.get_blocks("module") {
+            let Some(label) = block.labels.first() else {
+                continue;
+            };
+            let Some(source) = block.body.get_attribute("source").and_then(|a| a.value.as_str()) else {
+                continue;
+            };
+            if ModuleSource::parse(source).kind != SourceKind::Local {
+                continue;
+            }
+            let target = file.parent().unwrap_or(Path::new(".")).join(source);
+            if let Some(submodule) = containing(submodules, &target) {
+                vendored.push(VendoredModule {
+                    file: file.clone(),
+                    module: label.as_str().to_string(),
+                    source: source.to_string(),
+                    submodule: submodule.clone(),
+                });
+            }
+        }
+    }
+    vendored
+}
@@ -0,0 +1,165 @@
+// Terraform version pins in the configuration of TACOS (automation and
+// collaboration tools such as Atlantis and Spacelift), next to the
+// `required_version` of terraform blocks: `tv terraform-version`.
+//
+// atlantis.yaml:          projects: [{dir: ., terraform_version: v1.5.0}]
+// .spacelift/config.yml:  stack_defaults: {terraform_version: 1.5.0}
+//
+// The YAML files are edited line by line (`terraform_version: <value>`), so
+// comments, quoting and layout survive; a `v` prefix is kept as written.
+// required_version keeps its operator (`~> 1.5.0` becomes `~> 1.6.0`);
+// constraints with several clauses are reported, not rewritten. Every file
+// is written in the same transaction (see journal.rs).
+
+use anyhow::{Context, Result};
+use hcl_edit::Decorate;
+use hcl_edit::expr::Expression;
+use regex::Regex;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use crate::constraint::{Operator, Version, parse_constraints};
+use crate::{find_all_tf_files, read_tf_body, write_file, write_tf_body};
+
+pub const CONFIG_FILES: [&str; 4] = ["atlantis.yaml", "atlantis.yml", ".spacelift/config.yml", ".spacelift/config.yaml"];
+
+static YAML_PIN: LazyLock<Regex> = LazyLock::new(|| {
+    // prefix, opening quote, value, closing quote, rest (comment)
+    Regex::new(r#"^(\s*(?:-\s+)?terraform_version\s*:\s*)(["']?)([^"'#\s]+)(["']?)(.*)$"#).unwrap()
+});
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VersionPin {
+    pub file: PathBuf,
+    pub line: Option<usize>,  // 1-based, for YAML pins
+    pub key: &'static str,  // terraform_version or required_version
+    pub value: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bump {
+    pub pin: VersionPin,
+    pub to: std::result::Result<String, String>,  // Err: why it was left alone
+}
+
+pub fn yaml_pins(content: &str) -> Vec<(usize, String)> {
+    // (line index, value) of every terraform_version
+    content
+        .lines()
+        .enumerate()
+        .filter_map(|(i, line)| Some((i, YAML_PIN.captures(line)?[3].to_string())))
+        .collect()
+}
+
+fn config_files(dir: &Path) -> Vec<PathBuf> {
+    CONFIG_FILES.iter().map(|name| dir.join(name)).filter(|path| path.is_file()).collect()
+}
+
+fn required_version(expr: &Expression) -> Option<String> {
+    match expr {
+        Expression::String(s) => Some(s.value().to_string()),
+        _ => None,
+    }
+}
+
+pub fn terraform_versions(dir: &Path) -> Result<Vec<VersionPin>> {
+    let mut pins = Vec::new();
+    for file in find_all_tf_files(dir)? {
+        let Ok(body) = read_tf_body(&file) else {
+            continue;
+        };
+        for terraform in body.get_blocks("terraform") {
+            if let Some(value) = terraform.body.get_attribute("required_version").and_then(|a| required_version(&a.value)) {
+                pins.push(VersionPin { file: file.clone(), line: None, key: "required_version", value });
+            }
+        }
+    }
+    for file in config_files(dir) {
+        let content = fs::read_to_string(&file).with_context(|| format!("Failed to read file: {:?}", file))?;
+        for (index, value) in yaml_pins(&content) {
+            pins.push(VersionPin { file: file.clone(), line: Some(index + 1), key: "terraform_version", value });
+        }
+    }
+    Ok(pins)
+}
+
+pub fn bump_constraint(current: &str, version: &str) -> std::result::Result<String, String> {
+    // The new version under the current constraint's operator
+    let version = version.trim_start_matches('v');
+    match parse_constraints(current).map_err(|_| "not a version constraint".to_string())?.as_slice() {
+        // A bare version stays bare
+        [only] if only.op == Operator::Eq && !current.trim_start().starts_with('=') => Ok(version.to_string()),
+        [only] => Ok(format!("{} {}", only.op.as_str(), version)),
+        _ => Err("has several clauses; set it by hand".to_string()),
+    }
+}
+
+pub fn bump_yaml(content: &str, version: &str) -> String {
+    let version = version.trim_start_matches('v');
+    content
+        .split('\n')
+        .map(|line| {
+            YAML_PIN
+                .replace(line, |caps: &regex::Captures| {
+                    let prefix = if caps[3].starts_with('v') { "v" } else { "" };
+                    format!("{}{}{}{}{}{}", &caps[1], &caps[2], prefix, version, &caps[4], &caps[5])
+                })
+                .into_owned()
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+pub fn set_terraform_version(dir: &Path, version: &str) -> Result<Vec<Bump>> {
+    // Every pin that was (or could not be) changed
+    Version::parse(version)?;
+    let mut changed = Vec::new();
+
+    for file in find_all_tf_files(dir)? {
+        let Ok(mut body) = read_tf_body(&file) else {
+            continue;
+        };
+        let mut edits = Vec::new();
+        for terraform in body.get_blocks_mut("terraform") {
+            let Some(mut attr) = terraform.body.get_attribute_mut("required_version") else {
+                continue;
+            };
+            let Some(current) = required_version(attr.value_mut()) else {
+                continue;
+            };
+            let to = bump_constraint(&current, version);
+            if let Ok(ref bumped) = to {
+                if *bumped == current {
+                    continue;
+                }
+                let mut value = Expression::from(bumped.as_str());
+                *value.decor_mut() = attr.value_mut().decor().clone();
+                *attr.value_mut() = value;
+            }
+            let pin = VersionPin { file: file.clone(), line: None, key: "required_version", value: current };
+            edits.push(Bump { pin, to });
+        }
+        if edits.iter().any(|e| e.to.is_ok()) {
+            write_tf_body(&file, &body)?;
+        }
+        changed.extend(edits);
+    }
+
+    for file in config_files(dir) {
+        let content = fs::read_to_string(&file).with_context(|| format!("Failed to read file: {:?}", file))?;
+        let bumped = bump_yaml(&content, version);
+        if bumped == content {
+            continue;
+        }
+        let after = yaml_pins(&bumped);
+        for ((index, current), (_, new)) in yaml_pins(&content).into_iter().zip(after) {
+            if current != new {
+                let pin = VersionPin { file: file.clone(), line: Some(index + 1), key: "terraform_version", value: current };
+                changed.push(Bump { pin, to: Ok(new) });
+            }
+        }
+        write_file(&file, &bumped)?;
+    }
+    Ok(changed)
+}
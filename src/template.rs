@@ -0,0 +1,125 @@
+// `--format '{file}:{name} -> {value}'`: one line per result, shaped by a
+// template, for scan, get and list in place of their human output.
+//
+// `{field}` is replaced by the result's field, empty when it has none; `{{`
+// and `}}` are literal braces, and `\t` and `\n` a tab and a newline. The
+// fields each command knows are listed in SCAN_FIELDS, GET_FIELDS and
+// LIST_FIELDS; scan also takes the names given to --with-args. A field
+// the command does not know is an error, so a typo is not an empty column.
+
+use anyhow::{Result, anyhow};
+use std::path::Path;
+
+use crate::TypedValue;
+use crate::inventory::ModuleEntry;
+use crate::matches::Match;
+use crate::query::quote_segment;
+
+pub const SCAN_FIELDS: &[&str] = &["file", "block", "name", "attribute", "value", "type", "line"];
+pub const GET_FIELDS: &[&str] = &["file", "query", "value", "type"];
+pub const LIST_FIELDS: &[&str] = &["file", "module", "source", "url", "path", "ref", "version"];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Text(String),
+    Field(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+impl Template {
+    pub fn parse(template: &str, fields: &[&str]) -> Result<Template> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match (c, chars.peek()) {
+                ('{', Some('{')) | ('}', Some('}')) => {
+                    chars.next();
+                    text.push(c);
+                }
+                ('\\', Some('t')) => {
+                    chars.next();
+                    text.push('\t');
+                }
+                ('\\', Some('n')) => {
+                    chars.next();
+                    text.push('\n');
+                }
+                ('{', _) => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => return Err(anyhow!("Unclosed {{ in --format; write {{{{ for a literal brace")),
+                        }
+                    }
+                    let name = name.trim();
+                    if !fields.contains(&name) {
+                        return Err(anyhow!("Unknown field {{{}}} in --format; expected one of {}", name, fields.join(", ")));
+                    }
+                    if !text.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(Part::Field(name.to_string()));
+                }
+                ('}', _) => return Err(anyhow!("Unmatched }} in --format; write }}}} for a literal brace")),
+                _ => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+        Ok(Template { parts })
+    }
+
+    pub fn render(&self, field: impl Fn(&str) -> Option<String>) -> String {
+        self.parts
+            .iter()
+            .map(|part| match part {
+                Part::Text(text) => text.clone(),
+                Part::Field(name) => field(name).unwrap_or_default(),
+            })
+            .collect()
+    }
+}
+
+pub fn scan_field(found: &Match, arguments: &[(String, TypedValue)], field: &str) -> Option<String> {
+    match field {
+        "file" => Some(crate::paths::show(&found.file)),
+        "block" => Some(found.block_type.clone()),
+        "name" => Some(found.name.clone()),
+        "attribute" => found.attribute.clone(),
+        "value" => found.value.as_ref().map(|t| t.value.clone()),
+        "type" => found.value.as_ref().map(|t| t.kind.as_str().to_string()),
+        "line" => found.line.map(|l| l.to_string()),
+        argument => arguments.iter().find(|(name, _)| name == argument).map(|(_, t)| t.value.clone()),
+    }
+}
+
+pub fn get_field(file: &Path, query: &str, typed: Option<&TypedValue>, field: &str) -> Option<String> {
+    match field {
+        "file" => Some(crate::paths::show(file)),
+        "query" => Some(query.to_string()),
+        "value" => typed.map(|t| t.value.clone()),
+        "type" => typed.map(|t| t.kind.as_str().to_string()),
+        _ => None,
+    }
+}
+
+pub fn list_field(entry: &ModuleEntry, field: &str) -> Option<String> {
+    match field {
+        "file" => Some(crate::paths::show(&entry.file)),
+        "module" => Some(quote_segment(&entry.label)),
+        "source" => entry.source.clone(),
+        "url" => entry.url.clone(),
+        "path" => entry.path.clone(),
+        "ref" => entry.git_ref.clone(),
+        "version" => entry.version.clone(),
+        _ => None,
+    }
+}
@@ -0,0 +1,272 @@
+// Terraform CLI configuration (`.terraformrc`) awareness.
+//
+// The `provider_installation` block decides where `terraform init`
+// installs providers from, e.g.:
+//
+// provider_installation {
+//   network_mirror {
+//     url     = "https://mirror.example.com/"
+//     include = ["registry.terraform.io/hashicorp/*"]
+//   }
+//   direct {
+//     exclude = ["registry.terraform.io/hashicorp/*"]
+//   }
+// }
+//
+// Version lookups follow the same rules (see registry::Client::with_installation)
+// so that tv reports what terraform would actually install rather than what
+// the public registry has.
+// `credentials` blocks are only inspected for the hosts they cover, and for
+// their token when tv talks to that host itself (see tfc.rs).
+
+use anyhow::{Context, Result, anyhow};
+use hcl_edit::expr::Expression;
+use hcl_edit::structure::{Block, Body};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_REGISTRY_HOST: &str = "registry.terraform.io";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum InstallMethod {
+    Direct,
+    NetworkMirror(String),
+    FilesystemMirror(PathBuf),
+}
+
+impl InstallMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            InstallMethod::Direct => "direct",
+            InstallMethod::NetworkMirror(_) => "network_mirror",
+            InstallMethod::FilesystemMirror(_) => "filesystem_mirror",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstallRule {
+    pub method: InstallMethod,
+    pub include: Vec<String>,  // Empty means every provider
+    pub exclude: Vec<String>,
+}
+
+impl InstallRule {
+    pub fn applies_to(&self, provider: &str) -> bool {
+        let included = self.include.is_empty()
+            || self.include.iter().any(|p| provider_pattern_matches(p, provider));
+        included && !self.exclude.iter().any(|p| provider_pattern_matches(p, provider))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProviderInstallation {
+    pub rules: Vec<InstallRule>,
+}
+
+impl ProviderInstallation {
+    pub fn methods_for(&self, provider: &str) -> Vec<&InstallMethod> {
+        // Terraform consults every matching method, earlier ones first
+        let provider = normalize_provider_address(provider);
+        self.rules
+            .iter()
+            .filter(|rule| rule.applies_to(&provider))
+            .map(|rule| &rule.method)
+            .collect()
+    }
+}
+
+pub fn default_config_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("TF_CLI_CONFIG_FILE") {
+        return Some(PathBuf::from(path));
+    }
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".terraformrc"))
+}
+
+pub fn normalize_provider_address(address: &str) -> String {
+    // hashicorp/aws -> registry.terraform.io/hashicorp/aws
+    let address = address.trim().trim_matches('"');
+    let parts: Vec<&str> = address.split('/').collect();
+    match parts.len() {
+        2 => format!("{}/{}", DEFAULT_REGISTRY_HOST, address.to_lowercase()),
+        _ => address.to_lowercase(),
+    }
+}
+
+pub fn provider_pattern_matches(pattern: &str, provider: &str) -> bool {
+    // Patterns are full addresses where namespace and type may be `*`
+    let pattern = normalize_provider_address(pattern);
+    let provider = normalize_provider_address(provider);
+    let pattern_parts: Vec<&str> = pattern.split('/').collect();
+    let provider_parts: Vec<&str> = provider.split('/').collect();
+    pattern_parts.len() == provider_parts.len()
+        && pattern_parts
+            .iter()
+            .zip(provider_parts.iter())
+            .all(|(p, v)| *p == "*" || p == v)
+}
+
+fn string_attr(block: &Block, name: &str) -> Option<String> {
+    match block.body.get_attribute(name).map(|attr| &attr.value) {
+        Some(Expression::String(s)) => Some(s.value().to_string()),
+        _ => None,
+    }
+}
+
+fn string_list_attr(block: &Block, name: &str) -> Result<Vec<String>> {
+    let Some(attr) = block.body.get_attribute(name) else {
+        return Ok(Vec::new());
+    };
+    let Expression::Array(ref array) = attr.value else {
+        return Err(anyhow!("'{}' must be a list of provider addresses", name));
+    };
+    array
+        .iter()
+        .map(|expr| match expr {
+            Expression::String(s) => Ok(s.value().to_string()),
+            _ => Err(anyhow!("'{}' must be a list of provider addresses", name)),
+        })
+        .collect()
+}
+
+pub fn parse_provider_installation(content: &str) -> Result<Option<ProviderInstallation>> {
+    let body: Body = content.parse().context("Failed to parse CLI configuration")?;
+
+    let Some(block) = body
+        .iter()
+        .filter_map(|s| s.as_block())
+        .find(|b| b.ident.as_str() == "provider_installation")
+    else {
+        return Ok(None);
+    };
+
+    let mut rules = Vec::new();
+    for method_block in block.body.iter().filter_map(|s| s.as_block()) {
+        let kind = method_block.ident.as_str();
+        let method = match kind {
+            "direct" => InstallMethod::Direct,
+            "network_mirror" => InstallMethod::NetworkMirror(
+                string_attr(method_block, "url")
+                    .ok_or_else(|| anyhow!("network_mirror requires a url"))?,
+            ),
+            "filesystem_mirror" => InstallMethod::FilesystemMirror(PathBuf::from(
+                string_attr(method_block, "path")
+                    .ok_or_else(|| anyhow!("filesystem_mirror requires a path"))?,
+            )),
+            // dev_overrides never affects which versions are available
+            _ => continue,
+        };
+        rules.push(InstallRule {
+            method,
+            include: string_list_attr(method_block, "include")
+                .with_context(|| format!("Invalid {} block", kind))?,
+            exclude: string_list_attr(method_block, "exclude")
+                .with_context(|| format!("Invalid {} block", kind))?,
+        });
+    }
+
+    Ok(Some(ProviderInstallation { rules }))
+}
+
+pub fn credential_hosts(content: &str) -> Result<Vec<String>> {
+    // Hosts with a `credentials "<host>" { token = ... }` block
+    let body: Body = content.parse().context("Failed to parse CLI configuration")?;
+    Ok(body
+        .iter()
+        .filter_map(|s| s.as_block())
+        .filter(|b| b.ident.as_str() == "credentials")
+        .filter_map(|b| b.labels.first().map(|l| l.as_str().to_string()))
+        .collect())
+}
+
+pub fn credential_token(content: &str, host: &str) -> Result<Option<String>> {
+    // The token of a `credentials "<host>"` block
+    let body: Body = content.parse().context("Failed to parse CLI configuration")?;
+    Ok(body
+        .iter()
+        .filter_map(|s| s.as_block())
+        .filter(|b| b.ident.as_str() == "credentials")
+        .find(|b| b.labels.first().is_some_and(|l| l.as_str().eq_ignore_ascii_case(host)))
+        .and_then(|b| match b.body.get_attribute("token").map(|a| &a.value) {
+            Some(Expression::String(token)) => Some(token.value().to_string()),
+            _ => None,
+        }))
+}
+
+pub fn stored_token(content: &str, host: &str) -> Option<String> {
+    // credentials.tfrc.json, written by `terraform login`:
+    // {"credentials": {"app.terraform.io": {"token": "..."}}}
+    let json: serde_json::Value = serde_json::from_str(content).ok()?;
+    let hosts = json.get("credentials")?.as_object()?;
+    let (_, entry) = hosts.iter().find(|(h, _)| h.eq_ignore_ascii_case(host))?;
+    Some(entry.get("token")?.as_str()?.to_string())
+}
+
+pub fn stored_credentials_path() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".terraform.d").join("credentials.tfrc.json"))
+}
+
+pub fn load_provider_installation(path: &Path) -> Result<Option<ProviderInstallation>> {
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read CLI configuration: {:?}", path))?;
+    parse_provider_installation(&content)
+        .with_context(|| format!("Invalid CLI configuration: {:?}", path))
+}
+
+pub fn mirror_index_url(mirror_url: &str, provider: &str) -> Result<String> {
+    // Provider network mirror protocol: <base>/<hostname>/<namespace>/<type>/index.json.
+    // Terraform only speaks https to mirrors; plain http is allowed for a
+    // mirror on this machine
+    let loopback = ["http://127.0.0.1:", "http://127.0.0.1/", "http://localhost:", "http://localhost/", "http://[::1]"];
+    if !mirror_url.starts_with("https://") && !loopback.iter().any(|prefix| mirror_url.starts_with(prefix)) {
+        return Err(anyhow!("Network mirror url must use https: {}", mirror_url));
+    }
+    let provider = normalize_provider_address(provider);
+    Ok(format!("{}/{}/index.json", mirror_url.trim_end_matches('/'), provider))
+}
+
+#[derive(Debug, Deserialize)]
+struct MirrorIndex {
+    versions: HashMap<String, serde_json::Value>,
+}
+
+pub fn parse_mirror_index(json: &str) -> Result<Vec<String>> {
+    let index: MirrorIndex = serde_json::from_str(json).context("Invalid mirror index")?;
+    let mut versions: Vec<String> = index.versions.into_keys().collect();
+    versions.sort();
+    Ok(versions)
+}
+
+pub fn filesystem_mirror_versions(mirror: &Path, provider: &str) -> Result<Vec<String>> {
+    let provider = normalize_provider_address(provider);
+    let provider_dir = mirror.join(&provider);
+    let provider_type = provider.rsplit('/').next().unwrap_or_default();
+    let mut versions = Vec::new();
+    if !provider_dir.is_dir() {
+        return Ok(versions);
+    }
+
+    for entry in fs::read_dir(&provider_dir)? {
+        let path = entry?.path();
+        let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        if path.is_dir() {
+            // Unpacked layout: <type dir>/<version>/<os>_<arch>/
+            versions.push(name);
+        } else if let Some(rest) = name.strip_prefix(&format!("terraform-provider-{}_", provider_type))
+            && let Some(rest) = rest.strip_suffix(".zip")
+            && let Some((version, _target)) = rest.split_once('_')
+        {
+            // Packed layout: terraform-provider-<type>_<version>_<os>_<arch>.zip
+            versions.push(version.to_string());
+        }
+    }
+
+    versions.sort();
+    versions.dedup();
+    Ok(versions)
+}
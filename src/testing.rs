@@ -0,0 +1,162 @@
+// Test helpers for code built on tv: `features = ["testing"]`.
+//
+// tv's own integration tests use these, and extensions can use them to test
+// their transforms against realistic fixtures: temporary files and
+// directories, git repositories, a generated corpus (see bench.rs), a local
+// HTTP server standing in for registries and APIs, and assertions that a
+// rewrite produces byte-identical output.
+//
+// Golden files: `assert_golden` compares output with a file checked in next
+// to the tests; with TV_UPDATE_GOLDEN=1 it writes the file instead, so a
+// deliberate change is recorded by re-running the tests once.
+//
+// The helpers panic on failure, like assertions.
+
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+pub fn create_test_tf_file(content: &str) -> (TempDir, std::path::PathBuf) {
+    let temp_dir = TempDir::new().unwrap();
+    let file_path = temp_dir.path().join("test.tf");
+    fs::write(&file_path, content).unwrap();
+    (temp_dir, file_path)
+}
+
+pub fn create_test_dir_with_files(files: &[(&str, &str)]) -> TempDir {
+    let temp_dir = TempDir::new().unwrap();
+    for (name, content) in files {
+        let file_path = temp_dir.path().join(name);
+        if let Some(parent) = file_path.parent() {
+            fs::create_dir_all(parent).unwrap();
+        }
+        fs::write(file_path, content).unwrap();
+    }
+    temp_dir
+}
+
+pub fn create_git_repo(files: &[(&str, &str)]) -> TempDir {
+    let temp_dir = create_test_dir_with_files(files);
+    let git = |args: &[&str]| {
+        let status = std::process::Command::new("git")
+            .args(["-c", "user.name=tv", "-c", "user.email=tv@example.com"])
+            .args(args)
+            .current_dir(temp_dir.path())
+            .output()
+            .unwrap();
+        assert!(status.status.success(), "git {:?} failed", args);
+    };
+    git(&["init", "--quiet"]);
+    git(&["add", "-A"]);
+    git(&["commit", "--quiet", "-m", "initial"]);
+    temp_dir
+}
+
+pub fn serve_http(routes: Vec<(&'static str, String)>) -> String {
+    serve_http_recording(routes).0
+}
+
+pub fn serve_http_recording(
+    routes: Vec<(&'static str, String)>,
+) -> (String, std::sync::Arc<std::sync::Mutex<Vec<String>>>) {
+    // Answers requests with the JSON body of the first matching route ("<path>"
+    // for any method, or "<METHOD> <path>"), 404 otherwise, until the test
+    // exits. Returns the base url and the requests seen, as "<METHOD> <path>
+    // <body>".
+    use std::io::{BufRead, BufReader, Read, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let requests = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let seen = requests.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else { continue };
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut content_length = 0;
+            let mut header = String::new();
+            while reader.read_line(&mut header).is_ok_and(|n| n > 2) {
+                if let Some((name, value)) = header.split_once(':')
+                    && name.eq_ignore_ascii_case("content-length")
+                {
+                    content_length = value.trim().parse().unwrap_or(0);
+                }
+                header.clear();
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+
+            let mut parts = request_line.split_whitespace();
+            let method = parts.next().unwrap_or_default().to_string();
+            let path = parts.next().unwrap_or_default().to_string();
+            seen.lock().unwrap().push(format!("{} {} {}", method, path, String::from_utf8_lossy(&body)));
+            let with_method = format!("{} {}", method, path);
+            let response = match routes.iter().find(|(route, _)| *route == path || *route == with_method) {
+                Some((_, body)) => format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                ),
+                None => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+            };
+            let _ = stream.write_all(response.as_bytes());
+        }
+    });
+    (format!("http://{}", address), requests)
+}
+
+pub fn create_corpus(files: usize, modules_per_file: usize) -> TempDir {
+    // The corpus `tv bench` measures: directories of 100 files, git and registry modules
+    let temp_dir = TempDir::new().unwrap();
+    crate::bench::generate_corpus(temp_dir.path(), files, modules_per_file).unwrap();
+    temp_dir
+}
+
+fn first_difference(actual: &str, expected: &str) -> String {
+    let line = actual
+        .split_inclusive('\n')
+        .zip(expected.split_inclusive('\n'))
+        .position(|(a, e)| a != e)
+        .unwrap_or_else(|| actual.lines().count().min(expected.lines().count()));
+    let show = |text: &str| text.split_inclusive('\n').nth(line).map(|l| format!("{:?}", l)).unwrap_or("end of file".to_string());
+    format!("first difference at line {}:\n  expected: {}\n  actual:   {}", line + 1, show(expected), show(actual))
+}
+
+pub fn assert_bytes_eq(actual: &str, expected: &str) {
+    if actual != expected {
+        panic!("output differs from the expected bytes; {}", first_difference(actual, expected));
+    }
+}
+
+pub fn assert_rewrite(input: &str, expected: &str, transform: impl FnOnce(&Path) -> anyhow::Result<()>) {
+    // Writes input to a .tf file, runs the transform on it, and compares the
+    // file with expected byte for byte (whitespace, comments and all)
+    let (_temp_dir, file) = create_test_tf_file(input);
+    transform(&file).unwrap_or_else(|e| panic!("transform failed: {:#}", e));
+    assert_bytes_eq(&fs::read_to_string(&file).unwrap(), expected);
+}
+
+pub fn assert_unchanged(input: &str, transform: impl FnOnce(&Path) -> anyhow::Result<()>) {
+    assert_rewrite(input, input, transform);
+}
+
+pub fn assert_golden(golden: &Path, actual: &str) {
+    if std::env::var("TV_UPDATE_GOLDEN").is_ok_and(|v| v == "1") {
+        if let Some(parent) = golden.parent() {
+            fs::create_dir_all(parent).unwrap();
+        }
+        fs::write(golden, actual).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(golden)
+        .unwrap_or_else(|_| panic!("golden file {:?} is missing; run with TV_UPDATE_GOLDEN=1 to create it", golden));
+    if actual != expected {
+        panic!(
+            "output differs from {:?} (TV_UPDATE_GOLDEN=1 accepts it); {}",
+            golden,
+            first_difference(actual, &expected)
+        );
+    }
+}
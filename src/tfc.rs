@@ -0,0 +1,176 @@
+// HCP Terraform (Terraform Cloud) and Terraform Enterprise workspace
+// variables: `tv tfc get <organization>/<workspace> <key>` and `tv tfc set`.
+//
+// A version kept in a workspace variable (e.g. `module_ref`) is read and
+// written through the API (/api/v2), so it can be managed with the same
+// verbs as the pins in .tf files. Terraform variables are used unless
+// `--env` asks for environment variables; a key missing from the workspace
+// is created on `set`. Remote changes are not journaled.
+//
+// The host is TFE_HOSTNAME (default app.terraform.io), the token the one
+// terraform itself would use: TF_TOKEN_<host>, a `credentials` block in the
+// CLI configuration, or `terraform login`'s credentials.tfrc.json.
+// TV_TFC_URL sends the API calls to another base url instead.
+
+use anyhow::{Context, Result, anyhow};
+use serde_json::{Value, json};
+use std::fs;
+
+use crate::terraformrc;
+
+pub const DEFAULT_HOST: &str = "app.terraform.io";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Variable {
+    pub id: String,
+    pub key: String,
+    pub value: Option<String>,  // None for sensitive variables
+    pub category: String,  // "terraform" or "env"
+    pub sensitive: bool,
+}
+
+pub fn parse_workspace(text: &str) -> Result<(&str, &str)> {
+    match text.split_once('/') {
+        Some((organization, workspace)) if !organization.is_empty() && !workspace.is_empty() && !workspace.contains('/') => {
+            Ok((organization, workspace))
+        }
+        _ => Err(anyhow!("Expected <organization>/<workspace>, got '{}'", text)),
+    }
+}
+
+pub fn find_token(host: &str) -> Option<String> {
+    // TF_TOKEN_app_terraform_io: dots become underscores
+    let env_name = format!("TF_TOKEN_{}", host.replace(['.', '-'], "_"));
+    if let Ok(token) = std::env::var(env_name)
+        && !token.is_empty()
+    {
+        return Some(token);
+    }
+    if let Some(path) = terraformrc::default_config_path()
+        && let Ok(content) = fs::read_to_string(&path)
+        && let Ok(Some(token)) = terraformrc::credential_token(&content, host)
+    {
+        return Some(token);
+    }
+    let content = fs::read_to_string(terraformrc::stored_credentials_path()?).ok()?;
+    terraformrc::stored_token(&content, host)
+}
+
+fn segment(text: &str) -> String {
+    // Percent-encoded for a url path, so a '/', '?' or '#' in a name stays in it
+    text.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+fn parse_variable(data: &Value) -> Option<Variable> {
+    let attributes = data.get("attributes")?;
+    Some(Variable {
+        id: data.get("id")?.as_str()?.to_string(),
+        key: attributes.get("key")?.as_str()?.to_string(),
+        value: attributes.get("value").and_then(Value::as_str).map(str::to_string),
+        category: attributes.get("category").and_then(Value::as_str).unwrap_or("terraform").to_string(),
+        sensitive: attributes.get("sensitive").and_then(Value::as_bool).unwrap_or(false),
+    })
+}
+
+pub struct Client {
+    base_url: String,  // e.g. https://app.terraform.io/api/v2
+    token: Option<String>,
+    agent: ureq::Agent,
+}
+
+impl Default for Client {
+    fn default() -> Self {
+        let host = std::env::var("TFE_HOSTNAME").ok().filter(|h| !h.is_empty()).unwrap_or(DEFAULT_HOST.to_string());
+        let base_url = std::env::var("TV_TFC_URL")
+            .ok()
+            .filter(|u| !u.is_empty())
+            .unwrap_or(format!("https://{}/api/v2", host));
+        Client::new(&base_url, find_token(&host))
+    }
+}
+
+impl Client {
+    pub fn new(base_url: &str, token: Option<String>) -> Client {
+        Client {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            token,
+            agent: ureq::AgentBuilder::new().build(),
+        }
+    }
+
+    fn call(&self, method: &str, path: &str, body: Option<Value>) -> Result<Value> {
+        let url = format!("{}{}", self.base_url, path);
+        crate::cancel::check()?;
+        let mut request = self
+            .agent
+            .request(method, &url)
+            .timeout(crate::cancel::request_timeout())
+            .set("Content-Type", "application/vnd.api+json");
+        if let Some(ref token) = self.token {
+            request = request.set("Authorization", &format!("Bearer {}", token));
+        }
+        let response = match body {
+            Some(body) => request.send_string(&body.to_string()),
+            None => request.call(),
+        };
+        let response = match response {
+            Ok(response) => response,
+            Err(ureq::Error::Status(401, _)) => {
+                return Err(anyhow!("{} {} failed: unauthorized (set TF_TOKEN_<host> or run `terraform login`)", method, url));
+            }
+            Err(e) => return Err(anyhow!("{} {} failed: {}", method, url, e)),
+        };
+        response.into_json().with_context(|| format!("Invalid JSON from {}", url))
+    }
+
+    pub fn workspace_id(&self, organization: &str, workspace: &str) -> Result<String> {
+        let json = self.call("GET", &format!("/organizations/{}/workspaces/{}", segment(organization), segment(workspace)), None)?;
+        json.pointer("/data/id")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .ok_or_else(|| anyhow!("No id for workspace {}/{}", organization, workspace))
+    }
+
+    pub fn variables(&self, workspace_id: &str) -> Result<Vec<Variable>> {
+        let json = self.call("GET", &format!("/workspaces/{}/vars", segment(workspace_id)), None)?;
+        Ok(json
+            .get("data")
+            .and_then(Value::as_array)
+            .map(|data| data.iter().filter_map(parse_variable).collect())
+            .unwrap_or_default())
+    }
+
+    pub fn get_variable(&self, workspace: &str, key: &str, category: &str) -> Result<Option<Variable>> {
+        let (organization, name) = parse_workspace(workspace)?;
+        let id = self.workspace_id(organization, name)?;
+        Ok(self.variables(&id)?.into_iter().find(|v| v.key == key && v.category == category))
+    }
+
+    pub fn set_variable(&self, workspace: &str, key: &str, value: &str, category: &str) -> Result<Variable> {
+        let (organization, name) = parse_workspace(workspace)?;
+        let id = self.workspace_id(organization, name)?;
+        let existing = self.variables(&id)?.into_iter().find(|v| v.key == key && v.category == category);
+        let json = match existing {
+            Some(variable) => self.call(
+                "PATCH",
+                &format!("/workspaces/{}/vars/{}", segment(&id), segment(&variable.id)),
+                Some(json!({"data": {"id": variable.id, "type": "vars", "attributes": {"value": value}}})),
+            )?,
+            None => self.call(
+                "POST",
+                &format!("/workspaces/{}/vars", segment(&id)),
+                Some(json!({"data": {"type": "vars", "attributes": {
+                    "key": key, "value": value, "category": category, "hcl": false, "sensitive": false
+                }}})),
+            )?,
+        };
+        json.get("data")
+            .and_then(parse_variable)
+            .ok_or_else(|| anyhow!("Unexpected response setting {} in {}", key, workspace))
+    }
+}
@@ -0,0 +1,125 @@
+// Tolerant parsing for `scan --tolerant`: read what can be read from files
+// that are not quite valid HCL, as hand-edited and generated files often
+// are. Commands that write (set and the like) always parse strictly.
+//
+// A file that does not parse is repaired in memory, step by step:
+//
+// - an attribute defined twice in one block keeps its last definition
+//   (see duplicates.rs);
+// - a comma after an attribute in a block body (`source = "x",`) is dropped;
+// - if it still does not parse, each top-level block is parsed on its own
+//   and the blocks that fail are left out.
+//
+// Every repair is reported, and a file with unresolved merge conflict
+// markers is never read this way: which side is right cannot be guessed.
+
+use anyhow::Result;
+use hcl_edit::structure::Body;
+use regex::Regex;
+use std::sync::LazyLock;
+
+use crate::duplicates::{Keep, find_duplicates, strip_duplicates};
+use crate::{conflict_markers, parse_tf};
+
+static ATTRIBUTE_COMMA: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(\s*[A-Za-z_][A-Za-z0-9_-]*\s*=.*?)\s*,\s*$").unwrap());
+static BLOCK_HEADER: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"^[A-Za-z_][A-Za-z0-9_-]*(\s+("[^"]*"|[A-Za-z_][A-Za-z0-9_-]*))*\s*\{"#).unwrap()
+});
+
+fn balanced(line: &str) -> bool {
+    // Brackets outside strings; `list = ["a",` continues on the next line
+    let mut depth = 0i32;
+    let mut in_string = false;
+    let mut escaped = false;
+    for c in line.chars() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            '(' | '[' | '{' if !in_string => depth += 1,
+            ')' | ']' | '}' if !in_string => depth -= 1,
+            _ => {}
+        }
+    }
+    depth == 0 && !in_string
+}
+
+fn strip_attribute_commas(content: &str, repairs: &mut Vec<String>) -> String {
+    let mut fixed = String::with_capacity(content.len());
+    for (number, line) in content.split_inclusive('\n').enumerate() {
+        let (text, ending) = match line.strip_suffix('\n') {
+            Some(text) => (text, "\n"),
+            None => (line, ""),
+        };
+        match ATTRIBUTE_COMMA.captures(text) {
+            Some(caps) if balanced(&caps[1]) => {
+                repairs.push(format!("line {}: dropped the comma after an attribute", number + 1));
+                fixed.push_str(&caps[1]);
+                fixed.push_str(ending);
+            }
+            _ => fixed.push_str(line),
+        }
+    }
+    fixed
+}
+
+fn salvage_blocks(content: &str, repairs: &mut Vec<String>) -> Option<Body> {
+    // Top-level chunks: each block header starts one
+    let lines: Vec<&str> = content.split_inclusive('\n').collect();
+    let mut starts: Vec<usize> = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| BLOCK_HEADER.is_match(line))
+        .map(|(i, _)| i)
+        .collect();
+    if starts.first() != Some(&0) {
+        starts.insert(0, 0);
+    }
+    let mut body = Body::new();
+    let mut parsed_any = false;
+    for (i, start) in starts.iter().enumerate() {
+        let end = starts.get(i + 1).copied().unwrap_or(lines.len());
+        let chunk: String = lines[*start..end].concat();
+        match chunk.parse::<Body>() {
+            Ok(parsed) => {
+                parsed_any = true;
+                for structure in parsed.into_iter() {
+                    body.push(structure);
+                }
+            }
+            Err(_) if chunk.trim().is_empty() => {}
+            Err(_) => {
+                let header = lines[*start].trim().trim_end_matches('{').trim();
+                repairs.push(format!("lines {}-{}: skipped `{}`, which does not parse", start + 1, end, header));
+            }
+        }
+    }
+    parsed_any.then_some(body)
+}
+
+pub fn parse_tolerant(content: &str, file_path: &std::path::Path) -> Result<(Body, Vec<String>)> {
+    // The body and the repairs it took; none for a valid file
+    let strict = match parse_tf(content, file_path) {
+        Ok(body) => return Ok((body, Vec::new())),
+        Err(e) => e,
+    };
+    if !conflict_markers(content).is_empty() {
+        return Err(strict);
+    }
+
+    let mut repairs = Vec::new();
+    for duplicate in find_duplicates(content) {
+        let block = if duplicate.block.is_empty() { String::new() } else { format!("{}.", duplicate.block) };
+        let lines: Vec<String> = duplicate.lines.iter().map(|(first, _)| first.to_string()).collect();
+        repairs.push(format!("{}{} is defined {} times (lines {}); kept the last", block, duplicate.key, lines.len(), lines.join(", ")));
+    }
+    let repaired = strip_attribute_commas(&strip_duplicates(content, Keep::Last), &mut repairs);
+    if let Ok(body) = repaired.parse::<Body>() {
+        return Ok((body, repairs));
+    }
+    match salvage_blocks(&repaired, &mut repairs) {
+        Some(body) => Ok((body, repairs)),
+        None => Err(strict),
+    }
+}
@@ -0,0 +1,117 @@
+// `tv unset`: delete the attribute a query addresses, e.g.
+// `tv unset module.vpc.version`, or a key of an object value, e.g.
+// `tv unset terraform.required_providers.aws.version`, which removes
+// `version` from `aws = { source = ..., version = ... }`.
+//
+// The line goes with its leading comments; the rest of the file keeps its
+// layout. An index addresses a map key (`tags["Owner"]`), as with `tv rm`.
+//
+// `tv rm module.legacy_vpc` deletes a whole block, with the comments
+// directly above it and the blank lines that set it apart.
+
+use anyhow::{Result, anyhow};
+use hcl_edit::Decorate;
+use hcl_edit::expr::Expression;
+use hcl_edit::structure::Body;
+use std::path::Path;
+
+use crate::map::{get_entry_mut, remove_entry, remove_key};
+use crate::query::{block_matches, block_takes_label, block_takes_name, nested_block_index};
+use crate::{find_tf_file, parse_query, read_tf_body, write_tf_body};
+
+fn unset_in(body: &mut Body, path: &[String]) -> Result<()> {
+    // path: leading nested blocks, then the attribute, then object keys
+    let (first, rest) = path.split_first().ok_or_else(|| anyhow!("Query must include an attribute"))?;
+    let attribute = body.iter().position(|s| s.as_attribute().is_some_and(|a| a.key.as_str() == first));
+    // Blocks win in the middle of the path, attributes at its end
+    if let Some(index) = nested_block_index(body, first).filter(|_| !rest.is_empty() || attribute.is_none()) {
+        let nested = body
+            .get_mut(index)
+            .and_then(|s| s.as_block_mut())
+            .ok_or_else(|| anyhow!("Expected block at index {}", index))?;
+        return unset_in(&mut nested.body, rest);
+    }
+    let position = attribute.ok_or_else(|| anyhow!("Attribute '{}' not found in block", first))?;
+    let Some((last, keys)) = rest.split_last() else {
+        body.remove(position);
+        return Ok(());
+    };
+
+    let attr = body
+        .get_mut(position)
+        .and_then(|s| s.as_attribute_mut())
+        .ok_or_else(|| anyhow!("Expected attribute at index {}", position))?;
+    let mut value = &mut attr.value;
+    for key in keys {
+        let Expression::Object(object) = value else {
+            return Err(anyhow!("Attribute '{}' not found in object", key));
+        };
+        value = get_entry_mut(object, key).ok_or_else(|| anyhow!("Attribute '{}' not found in object", key))?;
+    }
+    let Expression::Object(object) = value else {
+        return Err(anyhow!("Attribute '{}' not found in object", last));
+    };
+    remove_entry(object, last).ok_or_else(|| anyhow!("Attribute '{}' not found in object", last))?;
+    Ok(())
+}
+
+pub fn unset(query: &str, file: Option<&Path>) -> Result<()> {
+    let parsed_query = parse_query(query)?;
+    if parsed_query.index.is_some() {
+        return remove_key(query, file);
+    }
+    let file_path = find_tf_file(file)?;
+    let mut body = read_tf_body(&file_path)?;
+
+    let position = body
+        .iter()
+        .position(|s| s.as_block().is_some_and(|b| block_matches(b, &parsed_query)))
+        .ok_or_else(|| anyhow!("Block not found: {}", parsed_query.block_type))?;
+    let block = body
+        .get_mut(position)
+        .and_then(|s| s.as_block_mut())
+        .ok_or_else(|| anyhow!("Expected block at index {}", position))?;
+    let path: Vec<String> = parsed_query.nested_blocks.iter().chain(&parsed_query.attribute).cloned().collect();
+    unset_in(&mut block.body, &path)?;
+
+    write_tf_body(&file_path, &body)
+}
+
+pub fn remove_block(query: &str, file: Option<&Path>) -> Result<()> {
+    // The first block the query names; wildcards are for scan
+    let parsed_query = crate::parse_scan_query(query)?;
+    let block_type = parsed_query.block_type.as_str();
+    let unnamed = (block_takes_label(block_type) && parsed_query.block_label.is_none())
+        || (block_takes_name(block_type) && parsed_query.block_name.is_none());
+    if unnamed || query.contains('*') || parsed_query.filter.is_some() || parsed_query.attribute.is_some() {
+        return Err(anyhow!("Query must name one block, e.g. module.legacy_vpc: {}", query));
+    }
+    let file_path = find_tf_file(file)?;
+    let mut body = read_tf_body(&file_path)?;
+    let position = body
+        .iter()
+        .position(|s| s.as_block().is_some_and(|b| block_matches(b, &parsed_query)))
+        .ok_or_else(|| anyhow!("Block not found: {}", query))?;
+    let removed = body.remove(position);
+
+    // Comments right above the block go with it; those set off by a blank
+    // line (a file header, say) stay, for the block that now follows
+    let prefix = removed.decor().prefix().map(|p| p.to_string()).unwrap_or_default();
+    let lines: Vec<&str> = prefix.split_inclusive('\n').collect();
+    let detached = match lines.iter().rposition(|l| l.trim().is_empty()) {
+        Some(blank) => lines[..=blank].concat(),
+        None => String::new(),
+    };
+    if let Some(next) = body.get_mut(position) {
+        let next_prefix = next.decor().prefix().map(|p| p.to_string()).unwrap_or_default();
+        let new_prefix = if !detached.trim().is_empty() {
+            format!("{}\n\n{}", detached.trim_end(), next_prefix.trim_start_matches(['\n', '\r']))
+        } else if position == 0 {
+            next_prefix.trim_start_matches(['\n', '\r']).to_string()
+        } else {
+            next_prefix
+        };
+        next.decor_mut().set_prefix(new_prefix);
+    }
+    write_tf_body(&file_path, &body)
+}
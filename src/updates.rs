@@ -0,0 +1,518 @@
+// Newer versions for pinned modules: `tv check-updates` and `tv upgrade`.
+//
+// Git modules pinned with `?ref=<tag>` are compared against the tags of
+// their repository (`git ls-remote --tags`), registry modules pinned to an
+// exact `version` against the versions the registry lists (deprecated ones
+// excluded, see registry.rs). A version is proposed when it is newer than
+// the pin and the module's track rule in tv.toml allows it (see config.rs);
+// without a rule any newer version is eligible. Refs that are not versions
+// (branches, commit hashes) and version constraints are left alone.
+//
+// With a minimum release age (see config.rs) release dates are needed. Git
+// tags are fetched into a bare mirror under the cache directory, since
+// ls-remote does not report dates; a tag's date is its tagger date (or its
+// commit's date, for lightweight tags). Registry versions use their
+// `published_at`.
+//
+// `--to <version>` asks for one release by name instead: it must be
+// published for every pin addressed, and track rules and release age are
+// not consulted.
+//
+// Updates carry the group (see config.rs) their module belongs to, so a
+// group can be upgraded as one batch.
+//
+// The size of an update is the first version part that changes: major,
+// minor, or patch (which includes a change of pre-release only).
+// check-updates colors updates by it and `--only` filters on it.
+//
+// Refs behind a local or variable (see indirection.rs) are resolved for the
+// comparison and updated where they are defined.
+//
+// Lookups go out concurrently, at most `--network-jobs` at a time (see
+// parallel.rs), after the pins of every file are collected: each git
+// repository and registry module is asked once per run however many pins
+// share it. Pins resolving alike are resolved once, and `--cache-ttl`
+// keeps the answers across runs (see resolved.rs).
+
+use anyhow::{Context, Result, anyhow};
+use hcl_edit::expr::Expression;
+use hcl_edit::structure::Block;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::bump::Part;
+use crate::cancel;
+use crate::config::{Config, TrackRule};
+use crate::constraint::Version;
+use crate::index::content_hash;
+use crate::labels::{Selector, select_files};
+use crate::parallel;
+use crate::query::quote_segment;
+use crate::registry::{Client, ModuleAddress, parse_timestamp};
+use crate::resolved::{self, Resolutions};
+use crate::source::{ModuleSource, SourceKind};
+use crate::{ScanQuery, block_matches, directory_of, find_all_tf_files, indirection, set_value};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Update {
+    pub file: PathBuf,
+    pub module: String,
+    pub query: String,  // Where the new version is written, e.g. module.vpc.version
+    pub url: String,
+    pub current: String,
+    pub latest: String,
+    pub group: Option<String>,
+}
+
+impl Update {
+    pub fn jump(&self) -> Option<Part> {
+        // None when either side is not a version
+        let (current, latest) = (Version::parse(&self.current).ok()?, Version::parse(&self.latest).ok()?);
+        Some(if current.segment(0) != latest.segment(0) {
+            Part::Major
+        } else if current.segment(1) != latest.segment(1) {
+            Part::Minor
+        } else {
+            Part::Patch
+        })
+    }
+}
+
+pub fn remote_url(url: &str) -> String {
+    // What git understands: the go-getter prefix and the github.com and
+    // bitbucket.org shorthands are tv's, not git's
+    let url = url.strip_prefix("git::").unwrap_or(url);
+    if url.starts_with("github.com/") || url.starts_with("bitbucket.org/") {
+        return format!("https://{}", url);
+    }
+    url.to_string()
+}
+
+pub fn list_tags(url: &str) -> Result<Vec<String>> {
+    let output = cancel::output(
+        Command::new("git")
+            .args(["ls-remote", "--tags", "--refs", "--", &remote_url(url)])
+            .env("GIT_TERMINAL_PROMPT", "0"),
+    )
+    .context("Failed to run git")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "git ls-remote {} failed: {}",
+            remote_url(url),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    // Lines are "<sha>\trefs/tags/<tag>"
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.split_once("\trefs/tags/"))
+        .map(|(_, tag)| tag.to_string())
+        .collect())
+}
+
+pub fn default_cache_dir() -> PathBuf {
+    crate::fleet::default_cache_dir().with_file_name("tags")
+}
+
+pub(crate) fn run_git(args: &[&str], cwd: &Path) -> Result<String> {
+    let output = cancel::output(Command::new("git").current_dir(cwd).args(args).env("GIT_TERMINAL_PROMPT", "0"))
+        .context("Failed to run git")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+pub(crate) fn mirror_dir(url: &str, cache_dir: &Path) -> Result<PathBuf> {
+    // A bare repository per remote, created on first use
+    let mirror = cache_dir.join(content_hash(remote_url(url).as_bytes()));
+    if !mirror.join("HEAD").is_file() {
+        fs::create_dir_all(&mirror)
+            .with_context(|| format!("Failed to create cache directory: {:?}", mirror))?;
+        run_git(&["init", "--bare", "--quiet"], &mirror)?;
+    }
+    Ok(mirror)
+}
+
+pub fn list_tags_with_dates(url: &str, cache_dir: &Path) -> Result<HashMap<String, u64>> {
+    // Tag name -> creation time (seconds since the epoch)
+    let mirror = mirror_dir(url, cache_dir)?;
+    run_git(
+        &["fetch", "--quiet", "--prune", "--depth=1", "--force", "--", &remote_url(url), "+refs/tags/*:refs/tags/*"],
+        &mirror,
+    )?;
+    let refs = run_git(&["for-each-ref", "--format=%(refname:short)\t%(creatordate:unix)", "refs/tags"], &mirror)?;
+    Ok(refs
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .filter_map(|(tag, date)| Some((tag.to_string(), date.parse().ok()?)))
+        .collect())
+}
+
+fn released_before(dates: &HashMap<String, u64>, cutoff: u64) -> Vec<String> {
+    let mut tags: Vec<String> = dates
+        .iter()
+        .filter(|(_, date)| **date <= cutoff)
+        .map(|(tag, _)| tag.clone())
+        .collect();
+    tags.sort();
+    tags
+}
+
+pub fn newest_allowed<'a>(current: &str, tags: &'a [String], rule: &TrackRule) -> Option<&'a String> {
+    // The highest allowed tag above the current version
+    let current = Version::parse(current).ok()?;
+    tags.iter()
+        .filter(|tag| rule.allows(tag))
+        .filter_map(|tag| Some((Version::parse(tag).ok()?, tag)))
+        .filter(|(version, _)| *version > current)
+        .max_by(|a, b| a.0.cmp(&b.0))
+        .map(|(_, tag)| tag)
+}
+
+pub(crate) struct Pin {
+    pub module: String,
+    pub url: String,
+    pub current: String,
+    pub registry: Option<ModuleAddress>,  // None for git modules
+}
+
+pub(crate) fn module_pins(file: &Path, query: &ScanQuery) -> Result<Vec<Pin>> {
+    // Git modules pinned with ?ref=, registry modules with an exact version
+    let content = fs::read_to_string(file)
+        .with_context(|| format!("Failed to read file: {:?}", file))?;
+    let body = crate::parse_tf(&content, file)?;
+    let string_attr = |block: &Block, name: &str| match block.body.get_attribute(name).map(|a| &a.value) {
+        Some(Expression::String(s)) => Some(s.value().to_string()),
+        _ => None,
+    };
+
+    let mut pins = Vec::new();
+    for block in body.get_blocks("module") {
+        if !block_matches(block, query) {
+            continue;
+        }
+        let Some(label) = block.labels.first().map(|l| l.as_str().to_string()) else {
+            continue;
+        };
+        let Some(source_text) = string_attr(block, "source") else {
+            continue;
+        };
+        let source = ModuleSource::parse(&source_text);
+
+        if source.kind == SourceKind::Git
+            && let Some(git_ref) = source.git_ref()
+        {
+            // git would read it as an option (--upload-pack=... runs a command)
+            if remote_url(&source.url).starts_with('-') {
+                return Err(anyhow!("Refusing module.{} in {:?}: its source url starts with '-'", label, file));
+            }
+            pins.push(Pin {
+                module: label,
+                url: source.url.clone(),
+                current: indirection::resolve(directory_of(file), git_ref)?,
+                registry: None,
+            });
+        } else if let Some(address) = ModuleAddress::parse(&source_text)
+            && let Some(version) = string_attr(block, "version")
+        {
+            pins.push(Pin {
+                module: label,
+                url: source.url.clone(),
+                current: indirection::resolve(directory_of(file), &version)?,
+                registry: Some(address),
+            });
+        }
+    }
+    Ok(pins)
+}
+
+pub struct UpdateOptions {
+    pub cache_dir: PathBuf,  // Tag mirrors, see list_tags_with_dates
+    pub registry: Client,
+    pub selector: Option<Selector>,  // Only directories whose labels match
+    pub to: Option<String>,  // This release instead of the newest allowed
+    pub network_jobs: usize,  // Lookups in flight at once
+    pub cache_ttl: Option<Duration>,  // Reuse resolutions of earlier runs this recent
+}
+
+impl Default for UpdateOptions {
+    fn default() -> Self {
+        UpdateOptions {
+            cache_dir: default_cache_dir(),
+            registry: Client::default(),
+            selector: None,
+            to: None,
+            network_jobs: parallel::DEFAULT_JOBS,
+            cache_ttl: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Lookup {
+    Tags(String),  // Tag names of a git repository
+    TagDates(String),  // Tags with their dates, for a minimum release age
+    Versions(ModuleAddress),  // Versions a registry lists; kept by the client
+}
+
+enum Fetched {
+    Tags(Vec<String>),
+    TagDates(HashMap<String, u64>),
+    Versions,  // In the registry client's cache
+}
+
+fn lookup_for(pin: &Pin, to: Option<&str>, cutoff: Option<u64>) -> Option<Lookup> {
+    // What resolving the pin will ask the network for first
+    match (to, &pin.registry, cutoff) {
+        (Some(_), _, _) if Version::parse(&pin.current).is_err() => None,
+        (_, Some(address), _) => Some(Lookup::Versions(address.clone())),
+        (Some(_), None, _) | (None, None, None) => Some(Lookup::Tags(pin.url.clone())),
+        (None, None, Some(_)) => Some(Lookup::TagDates(pin.url.clone())),
+    }
+}
+
+fn requested_release(pin: &Pin, to: &str, registry: &Client, tags: &mut HashMap<String, Vec<String>>) -> Result<String> {
+    // The release as published (v5.2.0 or 5.2.0, whichever was asked for
+    // without the v); it must exist, deprecated or not
+    let published: Vec<String> = match pin.registry {
+        Some(ref address) => registry.module_versions(address)?.into_iter().map(|v| v.version).collect(),
+        None => {
+            if !tags.contains_key(&pin.url) {
+                tags.insert(pin.url.clone(), list_tags(&pin.url)?);
+            }
+            tags[&pin.url].clone()
+        }
+    };
+    published
+        .iter()
+        .find(|release| *release == to)
+        .or_else(|| published.iter().find(|release| release.trim_start_matches('v') == to.trim_start_matches('v')))
+        .cloned()
+        .ok_or_else(|| anyhow!("module.{}: {} is not a release of {}", quote_segment(&pin.module), to, pin.url))
+}
+
+fn newest_published(
+    pin: &Pin,
+    address: &ModuleAddress,
+    rule: &TrackRule,
+    cutoff: Option<u64>,
+    registry: &Client,
+) -> Result<Option<String>> {
+    // Deprecated versions are never proposed; removed ones are not listed
+    let mut candidates: Vec<String> = registry
+        .module_versions(address)?
+        .into_iter()
+        .filter(|v| v.deprecation.is_none())
+        .map(|v| v.version)
+        .collect();
+    let Some(cutoff) = cutoff else {
+        return Ok(newest_allowed(&pin.current, &candidates, rule).cloned());
+    };
+    // Newest first, until one was published long enough ago
+    while let Some(latest) = newest_allowed(&pin.current, &candidates, rule).cloned() {
+        let published = registry.module_published(address, &latest)?;
+        if published.as_deref().and_then(parse_timestamp).is_some_and(|t| t <= cutoff) {
+            return Ok(Some(latest));
+        }
+        candidates.retain(|c| *c != latest);
+    }
+    Ok(None)
+}
+
+pub struct ReleaseDates<'a> {
+    // When pinned versions were released, asking each repository once
+    cache_dir: PathBuf,  // Tag mirrors, see list_tags_with_dates
+    registry: &'a Client,
+    tags: HashMap<String, HashMap<String, u64>>,
+}
+
+impl<'a> ReleaseDates<'a> {
+    pub fn new(cache_dir: &Path, registry: &'a Client) -> ReleaseDates<'a> {
+        ReleaseDates { cache_dir: cache_dir.to_path_buf(), registry, tags: HashMap::new() }
+    }
+
+    pub fn git_tag(&mut self, url: &str, tag: &str) -> Result<Option<u64>> {
+        if !self.tags.contains_key(url) {
+            self.tags.insert(url.to_string(), list_tags_with_dates(url, &self.cache_dir)?);
+        }
+        Ok(self.tags[url].get(tag).copied())
+    }
+
+    pub fn registry_version(&self, address: &ModuleAddress, version: &str) -> Result<Option<u64>> {
+        Ok(self.registry.module_published(address, version)?.as_deref().and_then(parse_timestamp))
+    }
+}
+
+pub fn check_updates(dir: &Path, query: &ScanQuery, config: &Config, options: &UpdateOptions) -> Result<Vec<Update>> {
+    let default_rule = TrackRule::default();
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let mut files = find_all_tf_files(dir)?;
+    if let Some(ref selector) = options.selector {
+        files = select_files(files, selector)?;
+    }
+
+    let mut memo = match options.cache_ttl {
+        Some(ttl) => Resolutions::load(&options.cache_dir, ttl, now),
+        None => Resolutions::in_memory(now),
+    };
+    let mut pins = Vec::new();
+    for file in files {
+        for pin in module_pins(&file, query)? {
+            let rule = config.rule_for(&[&pin.module, &pin.url]);
+            let age = config.minimum_release_age(rule)?;
+            let cutoff = age.map(|age| now.saturating_sub(age.as_secs()));
+            let rule = rule.unwrap_or(&default_rule);
+            let source = pin.registry.as_ref().map_or(pin.url.clone(), |address| address.to_string());
+            let key = resolved::key(&source, &pin.current, rule, age, options.to.as_deref());
+            pins.push((file.clone(), pin, rule, cutoff, key));
+        }
+    }
+
+    // Each repository and registry module once, concurrently, unless every
+    // pin needing it is already resolved
+    let mut lookups: Vec<Lookup> = Vec::new();
+    for (_, pin, _, cutoff, key) in &pins {
+        if memo.get(key).is_none()
+            && let Some(lookup) = lookup_for(pin, options.to.as_deref(), *cutoff)
+            && !lookups.contains(&lookup)
+        {
+            lookups.push(lookup);
+        }
+    }
+    let mut tags: HashMap<String, Vec<String>> = HashMap::new();
+    let mut dates: HashMap<String, HashMap<String, u64>> = HashMap::new();
+    let mut failed: HashMap<Lookup, anyhow::Error> = HashMap::new();
+    let fetched = parallel::map(&lookups, options.network_jobs, |lookup| match lookup {
+        Lookup::Tags(url) => list_tags(url).map(Fetched::Tags),
+        Lookup::TagDates(url) => list_tags_with_dates(url, &options.cache_dir).map(Fetched::TagDates),
+        Lookup::Versions(address) => options.registry.module_versions(address).map(|_| Fetched::Versions),
+    });
+    for (lookup, result) in lookups.into_iter().zip(fetched) {
+        match (lookup, result) {
+            (Lookup::Tags(url), Ok(Fetched::Tags(found))) => {
+                tags.insert(url, found);
+            }
+            (Lookup::TagDates(url), Ok(Fetched::TagDates(found))) => {
+                dates.insert(url, found);
+            }
+            (_, Ok(_)) => {}
+            (lookup, Err(e)) => {
+                failed.insert(lookup, e);
+            }
+        }
+    }
+
+    let mut updates = Vec::new();
+    for (file, pin, rule, cutoff, key) in pins {
+        let latest = match memo.get(&key) {
+            Some(latest) => latest,
+            None => {
+                // The first pin needing a failed lookup fails the run, as asking again would
+                if let Some(lookup) = lookup_for(&pin, options.to.as_deref(), cutoff)
+                    && let Some(e) = failed.remove(&lookup)
+                {
+                    return Err(e);
+                }
+                let latest = resolve_pin(&pin, rule, cutoff, options, &mut tags, &mut dates)?;
+                memo.insert(key, latest.clone());
+                latest
+            }
+        };
+
+        if let Some(latest) = latest {
+            let group = config.group_for(&[&pin.module, &pin.url]).map(|g| g.name.clone());
+            let module = quote_segment(&pin.module);
+            updates.push(Update {
+                file,
+                query: match pin.registry {
+                    Some(_) => format!("module.{}.version", module),
+                    None => format!("module.{}.source[\"ref\"]", module),
+                },
+                module: pin.module,
+                url: pin.url,
+                current: pin.current,
+                latest,
+                group,
+            });
+        }
+    }
+    memo.save()?;
+    Ok(updates)
+}
+
+fn resolve_pin(
+    pin: &Pin,
+    rule: &TrackRule,
+    cutoff: Option<u64>,
+    options: &UpdateOptions,
+    tags: &mut HashMap<String, Vec<String>>,
+    dates: &mut HashMap<String, HashMap<String, u64>>,
+) -> Result<Option<String>> {
+    Ok(match (&options.to, &pin.registry, cutoff) {
+        // Asked for by name: track rules and release age do not apply,
+        // but branches and constraints are still left alone
+        (Some(_), _, _) if Version::parse(&pin.current).is_err() => None,
+        (Some(to), _, _) => {
+            Some(requested_release(pin, to, &options.registry, tags)?).filter(|release| *release != pin.current)
+        }
+        (None, Some(address), cutoff) => newest_published(pin, address, rule, cutoff, &options.registry)?,
+        (None, None, Some(cutoff)) => {
+            if !dates.contains_key(&pin.url) {
+                dates.insert(pin.url.clone(), list_tags_with_dates(&pin.url, &options.cache_dir)?);
+            }
+            let candidates = released_before(&dates[&pin.url], cutoff);
+            newest_allowed(&pin.current, &candidates, rule).cloned()
+        }
+        (None, None, None) => {
+            if !tags.contains_key(&pin.url) {
+                tags.insert(pin.url.clone(), list_tags(&pin.url)?);
+            }
+            newest_allowed(&pin.current, &tags[&pin.url], rule).cloned()
+        }
+    })
+}
+
+pub fn apply_update(update: &Update) -> Result<()> {
+    set_value(&update.query, &update.latest, Some(&update.file))
+}
+
+pub fn pending_upgrades(
+    dir: &Path,
+    query: &ScanQuery,
+    config: &Config,
+    group: Option<&str>,
+    options: &UpdateOptions,
+) -> Result<Vec<Update>> {
+    // With a group, only that group's modules are upgraded
+    if let Some(name) = group
+        && !config.has_group(name)
+    {
+        return Err(anyhow!("No group named '{}' in {}", name, crate::config::CONFIG_FILE));
+    }
+    Ok(check_updates(dir, query, config, options)?
+        .into_iter()
+        .filter(|u| group.is_none() || u.group.as_deref() == group)
+        .collect())
+}
+
+pub fn upgrade(
+    dir: &Path,
+    query: &ScanQuery,
+    config: &Config,
+    group: Option<&str>,
+    options: &UpdateOptions,
+) -> Result<Vec<Update>> {
+    let updates = pending_upgrades(dir, query, config, group, options)?;
+    for update in &updates {
+        apply_update(update)?;
+    }
+    Ok(updates)
+}
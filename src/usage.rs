@@ -0,0 +1,102 @@
+// `tv who-uses hashicorp/aws@5.x`: the directories that can install a given
+// provider version, for planning a provider major upgrade across roots.
+//
+// A directory uses the provider when one of its .tf files requires it (see
+// doctor::provider_requirements; a bare name is a hashicorp provider) or
+// its .terraform.lock.hcl pins it. It can install the version when its
+// constraints, from every file of the directory together, allow some
+// version in the range; the lock file's pin, if any, is reported next to it
+// since `terraform init` installs that one until the lock is upgraded.
+// `5.x` and `5.2.*` are every version with that prefix, anything else after
+// `@` is a version constraint; without `@` every version matches.
+
+use anyhow::{Result, anyhow};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::constraint::{Constraint, Version, compatible, parse_constraints, parse_version_spec, satisfies};
+use crate::doctor::provider_requirements;
+use crate::policy::lock_file_pins;
+use crate::registry::ProviderAddress;
+use crate::{directory_of, find_all_tf_files, read_tf_body};
+
+pub const LOCK_FILE: &str = ".terraform.lock.hcl";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Usage {
+    pub dir: PathBuf,
+    pub constraints: Vec<(PathBuf, String)>,  // File, constraint as written
+    pub locked: Option<String>,  // Version pinned in the lock file
+    pub locked_matches: bool,  // The locked version is in the range
+}
+
+pub fn parse_target(text: &str) -> Result<(ProviderAddress, Vec<Constraint>)> {
+    // hashicorp/aws@5.x
+    let (address, spec) = match text.rsplit_once('@') {
+        Some((address, spec)) => (address, Some(spec)),
+        None => (text, None),
+    };
+    let address = ProviderAddress::parse(address).ok_or_else(|| anyhow!("Invalid provider address: {}", address))?;
+    let range = spec.map(parse_version_spec).transpose()?.unwrap_or_default();
+    Ok((address, range))
+}
+
+pub fn who_uses(dir: &Path, address: &ProviderAddress, range: &[Constraint]) -> Result<Vec<Usage>> {
+    // Only the directories whose constraints allow a version in the range
+    let mut constraints: BTreeMap<PathBuf, Vec<(PathBuf, String)>> = BTreeMap::new();
+    let mut required: BTreeMap<PathBuf, bool> = BTreeMap::new();
+    for file in find_all_tf_files(dir)? {
+        let Ok(body) = read_tf_body(&file) else {
+            continue;
+        };
+        let directory = directory_of(&file).to_path_buf();
+        for requirement in provider_requirements(&body, &file) {
+            let source = requirement.source.as_deref().unwrap_or(&requirement.name);
+            if ProviderAddress::parse(source).as_ref() != Some(address) {
+                continue;
+            }
+            required.insert(directory.clone(), true);
+            if let Some(version) = requirement.version {
+                constraints.entry(directory.clone()).or_default().push((file.clone(), version));
+            }
+        }
+        required.entry(directory).or_insert(false);
+    }
+
+    let mut usages = Vec::new();
+    for (directory, requires) in required {
+        let lock_file = directory.join(LOCK_FILE);
+        let locked = if lock_file.is_file() {
+            lock_file_pins(&lock_file)?
+                .into_iter()
+                .find(|(locked, _)| ProviderAddress::parse(locked).as_ref() == Some(address))
+                .map(|(_, version)| version)
+        } else {
+            None
+        };
+        if !requires && locked.is_none() {
+            continue;
+        }
+        let written = constraints.remove(&directory).unwrap_or_default();
+        // Constraints that do not parse are ignored rather than failing the search
+        let combined: Vec<Constraint> = written
+            .iter()
+            .filter_map(|(_, c)| parse_constraints(c).ok())
+            .flatten()
+            .collect();
+        if !compatible(&combined, range) {
+            continue;
+        }
+        let locked_matches = locked
+            .as_deref()
+            .and_then(|v| Version::parse(v).ok())
+            .is_some_and(|v| satisfies(&v, range));
+        usages.push(Usage {
+            dir: directory,
+            constraints: written,
+            locked,
+            locked_matches,
+        });
+    }
+    Ok(usages)
+}
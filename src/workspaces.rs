@@ -0,0 +1,109 @@
+// Named scan roots for monorepos: tv.workspaces.toml in the current
+// directory or any parent.
+//
+// [[workspace]]
+// name   = "networking"
+// dir    = "infra/networking"         # relative to tv.workspaces.toml
+// ignore = ["legacy", "sandbox/**"]   # .tvignore patterns, on top of its .tvignore
+// policy = "policies/networking.hcl"  # instead of <dir>/tv-policy.hcl
+//
+// `tv --workspace networking list` runs the command from the workspace's
+// directory, as `cd infra/networking && tv list` would; `--all-workspaces`
+// runs it once per workspace, in file order, under a heading for each.
+// With or without the flags, the ignore patterns apply whenever a walk
+// starts at the workspace's directory, and so does the policy for
+// `validate`; its require_reason blocks (patterns relative to the
+// workspace's directory) cover every write under it (see protect.rs).
+
+use anyhow::{Context, Result, anyhow};
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const WORKSPACES_FILE: &str = "tv.workspaces.toml";
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WorkspacesFile {
+    #[serde(default)]
+    workspace: Vec<Workspace>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Workspace {
+    pub name: String,
+    pub dir: PathBuf,  // Absolute once loaded
+    #[serde(default)]
+    pub ignore: Vec<String>,
+    pub policy: Option<PathBuf>,  // Absolute once loaded
+}
+
+pub fn find_workspaces(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|d| d.join(WORKSPACES_FILE))
+        .find(|path| path.is_file())
+}
+
+pub fn parse_workspaces(content: &str) -> Result<Vec<Workspace>> {
+    let file: WorkspacesFile = toml::from_str(content).context("Invalid workspaces file")?;
+    for (i, workspace) in file.workspace.iter().enumerate() {
+        if workspace.name.is_empty() {
+            return Err(anyhow!("Workspace #{} has an empty name", i));
+        }
+        if file.workspace[..i].iter().any(|w| w.name == workspace.name) {
+            return Err(anyhow!("Duplicate workspace name: {}", workspace.name));
+        }
+    }
+    Ok(file.workspace)
+}
+
+pub fn load_workspaces(dir: &Path) -> Result<Vec<Workspace>> {
+    // From the nearest tv.workspaces.toml, with paths resolved against it
+    let path = find_workspaces(dir)
+        .ok_or_else(|| anyhow!("No {} in {:?} or its parents", WORKSPACES_FILE, dir))?;
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read workspaces file: {:?}", path))?;
+    let mut workspaces = parse_workspaces(&content).with_context(|| format!("Invalid workspaces file: {:?}", path))?;
+    let root = std::path::absolute(path.parent().unwrap_or(Path::new(".")))?;
+    for workspace in &mut workspaces {
+        workspace.dir = root.join(&workspace.dir);
+        workspace.policy = workspace.policy.as_ref().map(|policy| root.join(policy));
+    }
+    Ok(workspaces)
+}
+
+pub fn named<'a>(workspaces: &'a [Workspace], name: &str) -> Result<&'a Workspace> {
+    workspaces.iter().find(|w| w.name == name).ok_or_else(|| {
+        let names: Vec<&str> = workspaces.iter().map(|w| w.name.as_str()).collect();
+        anyhow!("Unknown workspace '{}' (defined: {})", name, names.join(", "))
+    })
+}
+
+pub fn enter(workspace: &Workspace) -> Result<()> {
+    std::env::set_current_dir(&workspace.dir)
+        .map_err(|e| anyhow!("Failed to enter workspace {} ({:?}): {}", workspace.name, workspace.dir, e))
+}
+
+pub fn containing(dir: &Path) -> Option<Workspace> {
+    // The innermost workspace whose directory holds dir; broken files are
+    // left to --workspace, as for `at`
+    let dir = fs::canonicalize(dir).ok()?;
+    load_workspaces(&dir)
+        .ok()?
+        .into_iter()
+        .filter_map(|w| Some((fs::canonicalize(&w.dir).ok()?, w)))
+        .filter(|(root, _)| dir.starts_with(root))
+        .max_by_key(|(root, _)| root.components().count())
+        .map(|(_, w)| w)
+}
+
+pub fn at(dir: &Path) -> Option<Workspace> {
+    // The workspace rooted at dir; a broken workspaces file is reported by
+    // --workspace and --all-workspaces, not by every walk
+    let dir = fs::canonicalize(dir).ok()?;
+    load_workspaces(&dir)
+        .ok()?
+        .into_iter()
+        .find(|w| fs::canonicalize(&w.dir).is_ok_and(|d| d == dir))
+}
@@ -0,0 +1,77 @@
+mod common;
+
+use std::fs;
+use tv::add::{AddOptions, add_module, argument_value};
+use tv::get_value;
+
+const MAIN_TF: &str = r#"module "vpc" {
+  source  = "terraform-aws-modules/vpc/aws"
+  version = "5.1.0"
+}
+"#;
+
+fn options(arguments: &[(&str, &str)]) -> AddOptions {
+    AddOptions {
+        source: "terraform-aws-modules/eks/aws".to_string(),
+        version: Some("20.0.0".to_string()),
+        arguments: arguments.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+    }
+}
+
+#[test]
+fn test_argument_value() {
+    assert_eq!(argument_value("main"), "\"main\"");
+    assert_eq!(argument_value("10.0.0.0/16"), "\"10.0.0.0/16\"");
+    assert_eq!(argument_value("us-east-1"), "\"us-east-1\"");
+    assert_eq!(argument_value("example.com"), "\"example.com\"");
+    assert_eq!(argument_value("3"), "3");
+    assert_eq!(argument_value("true"), "true");
+    assert_eq!(argument_value("[\"a\", \"b\"]"), "[\"a\", \"b\"]");
+    assert_eq!(argument_value("module.vpc.vpc_id"), "module.vpc.vpc_id");
+    assert_eq!(argument_value("var.name"), "var.name");
+    assert_eq!(argument_value("\"quoted\""), "\"quoted\"");
+}
+
+#[test]
+fn test_add_module() {
+    let (_dir, file) = common::create_test_tf_file(MAIN_TF);
+    let added = add_module(
+        "module.eks",
+        Some(&file),
+        &options(&[("cluster_name", "main"), ("vpc_id", "module.vpc.vpc_id"), ("enable_irsa", "true")]),
+    )
+    .unwrap();
+    assert_eq!(added, file);
+    assert_eq!(
+        fs::read_to_string(&file).unwrap(),
+        format!(
+            "{}\nmodule \"eks\" {{\n  source       = \"terraform-aws-modules/eks/aws\"\n  version      = \"20.0.0\"\n  cluster_name = \"main\"\n  vpc_id       = module.vpc.vpc_id\n  enable_irsa  = true\n}}\n",
+            MAIN_TF
+        )
+    );
+    assert_eq!(get_value("module.eks.version", Some(&file)).unwrap().as_deref(), Some("20.0.0"));
+
+    // A module that exists is not added twice
+    let error = add_module("module.vpc", Some(&file), &options(&[])).unwrap_err();
+    assert!(error.to_string().contains("module.vpc already exists"), "{}", error);
+}
+
+#[test]
+fn test_add_module_to_a_new_file() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let file = temp_dir.path().join("eks.tf");
+    let options = AddOptions { source: "./modules/eks".to_string(), ..Default::default() };
+    add_module("module.eks", Some(&file), &options).unwrap();
+    assert_eq!(fs::read_to_string(&file).unwrap(), "module \"eks\" {\n  source = \"./modules/eks\"\n}\n");
+}
+
+#[test]
+fn test_add_module_rejects_bad_queries_and_arguments() {
+    let (_dir, file) = common::create_test_tf_file(MAIN_TF);
+    assert!(add_module("module.*", Some(&file), &options(&[])).is_err());
+    assert!(add_module("module.eks.version", Some(&file), &options(&[])).is_err());
+    assert!(add_module("resource.aws_s3_bucket.logs", Some(&file), &options(&[])).is_err());
+    assert!(add_module("module.eks", Some(&file), &options(&[("source", "x")])).is_err());
+    assert!(add_module("module.eks", Some(&file), &options(&[("bad key", "x")])).is_err());
+    assert_eq!(fs::read_to_string(&file).unwrap(), MAIN_TF);
+}
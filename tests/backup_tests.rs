@@ -0,0 +1,40 @@
+mod common;
+
+use tv::backup::{backup_path, parse_suffix, set_suffix};
+use tv::set_value;
+
+#[test]
+fn test_backup_keeps_the_original() {
+    let (_dir, file) = common::create_test_tf_file(common::SIMPLE_MODULE_TF);
+    set_suffix(Some(".bak".to_string()));
+
+    set_value("module.vpc.name", "first", Some(file.as_path())).unwrap();
+    set_value("module.vpc.name", "second", Some(file.as_path())).unwrap();
+    // The backup is the file before the first write of the invocation
+    let backup = backup_path(&file, ".bak");
+    assert_eq!(std::fs::read_to_string(&backup).unwrap(), common::SIMPLE_MODULE_TF);
+    assert!(std::fs::read_to_string(&file).unwrap().contains("\"second\""));
+    set_suffix(None);
+}
+
+#[test]
+fn test_no_backup_by_default() {
+    let (dir, file) = common::create_test_tf_file(common::SIMPLE_MODULE_TF);
+    set_suffix(None);
+    set_value("module.vpc.name", "new", Some(file.as_path())).unwrap();
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    assert_eq!(backup_path(&file, ".orig").file_name().unwrap(), "test.tf.orig");
+}
+
+#[test]
+fn test_backup_suffix_stays_next_to_the_file() {
+    assert_eq!(parse_suffix(".orig").unwrap(), ".orig");
+    assert_eq!(parse_suffix("~").unwrap(), "~");
+    // The backup would be the file itself
+    assert_eq!(parse_suffix("").unwrap_err().to_string(), "the suffix must not be empty");
+    // Or somewhere else
+    assert!(parse_suffix("/../../etc/x").is_err());
+    assert!(parse_suffix(".d/bak").is_err());
+    assert!(parse_suffix(".d\\bak").is_err());
+    assert!(parse_suffix("..").is_err());
+}
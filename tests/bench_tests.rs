@@ -0,0 +1,20 @@
+mod common;
+
+use tv::bench::bench;
+use tv::{find_all_tf_files, get_value};
+
+#[test]
+fn test_bench_counts_operations() {
+    let temp_dir = common::create_test_dir_with_files(&[]);
+    let results = bench(120, 3, Some(temp_dir.path())).unwrap();
+    let counts: Vec<(&str, usize)> = results.iter().map(|r| (r.name.as_str(), r.operations)).collect();
+    assert_eq!(counts, vec![("walk", 120), ("scan", 360), ("get", 120), ("set", 120)]);
+
+    // The corpus is kept when a directory is given, and the edits are real
+    let files = find_all_tf_files(temp_dir.path()).unwrap();
+    assert_eq!(files.len(), 120);
+    assert!(temp_dir.path().join("d0001").is_dir());
+    assert_eq!(get_value("module.m0.name", Some(&files[7])).unwrap().as_deref(), Some("benchmarked"));
+
+    assert!(bench(0, 5, Some(temp_dir.path())).is_err());
+}
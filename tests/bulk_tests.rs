@@ -0,0 +1,98 @@
+mod common;
+
+use tv::bulk::{Changed, render, set_all};
+use tv::policy::Expectation;
+use tv::{SetOptions, get_value};
+
+#[test]
+fn test_set_all_sets_every_match() {
+    let dir = common::create_test_dir_with_files(&[
+        ("prod/main.tf", r#"module "app" {
+  source = "git::https://github.com/org/app.git?ref=v1.0.0"
+}
+
+module "db" {
+  source = "git::https://github.com/org/db.git?ref=v1.0.0"
+}
+
+module "vpc" {
+  source  = "terraform-aws-modules/vpc/aws"
+  version = "5.0.0"
+}
+"#),
+        ("staging/main.tf", r#"module "app" {
+  source = "git::https://github.com/org/app.git?ref=v2.0.0"
+}
+"#),
+        ("dev/main.tf", r#"module "vpc" {
+  source = "terraform-aws-modules/vpc/aws"
+}
+"#),
+    ]);
+    let root = dir.path();
+
+    let changed = set_all(r#"module.*.source["ref"]"#, "v2.0.0", root, &SetOptions::default(), None).unwrap();
+    // staging already had v2.0.0; dev's module has no ref and is left alone
+    assert_eq!(changed, vec![Changed {
+        file: root.join("prod/main.tf"),
+        queries: vec![r#"module.app.source["ref"]"#.to_string(), r#"module.db.source["ref"]"#.to_string()],
+    }]);
+    let prod = Some(root.join("prod/main.tf"));
+    assert_eq!(get_value(r#"module.db.source["ref"]"#, prod.as_deref()).unwrap(), Some("v2.0.0".to_string()));
+    assert_eq!(get_value("module.vpc.version", prod.as_deref()).unwrap(), Some("5.0.0".to_string()));
+    assert_eq!(
+        std::fs::read_to_string(root.join("dev/main.tf")).unwrap(),
+        "module \"vpc\" {\n  source = \"terraform-aws-modules/vpc/aws\"\n}\n"
+    );
+
+    let changed = set_all("module.app.source[\"ref\"]", "v3.0.0", root, &SetOptions::default(), None).unwrap();
+    assert_eq!(
+        render(&changed, |p| p.strip_prefix(root).unwrap().display().to_string()),
+        "prod/main.tf: module.app.source[\"ref\"]\nstaging/main.tf: module.app.source[\"ref\"]\n"
+    );
+}
+
+#[test]
+fn test_set_all_errors() {
+    let dir = common::create_test_dir_with_files(&[("main.tf", common::SIMPLE_MODULE_TF)]);
+    let options = SetOptions::default();
+    assert!(set_all("module.*", "x", dir.path(), &options, None).is_err());
+    assert!(set_all(r#"module.*.source[ref=="v1"]"#, "x", dir.path(), &options, None).is_err());
+    assert!(set_all(r#"module.*.source[!ref]"#, "x", dir.path(), &options, None).is_err());
+    assert!(set_all("module.*.name", "x", &dir.path().join("missing"), &options, None).is_err());
+    // Nothing matching is not an error
+    assert_eq!(set_all("module.nothing.name", "x", dir.path(), &options, None).unwrap(), vec![]);
+}
+
+#[test]
+fn test_set_all_if_current_skips_other_values() {
+    let dir = common::create_test_dir_with_files(&[
+        ("a.tf", "module \"app\" {\n  version = \"1.2.0\"\n}\n"),
+        ("b.tf", "module \"app\" {\n  version = \"1.3.0\"\n}\n"),
+        ("c.tf", "module \"app\" {\n  version = \"2.0.0\"\n}\n"),
+    ]);
+    let root = dir.path();
+    let options = SetOptions::default();
+
+    let from_1_2 = Expectation::Equals("1.2.0".to_string());
+    let changed = set_all("module.*.version", "2.0.0", root, &options, Some(&from_1_2)).unwrap();
+    assert_eq!(changed.iter().map(|c| c.file.clone()).collect::<Vec<_>>(), [root.join("a.tf")]);
+
+    let any_1_x = Expectation::parse("matches:^1\\.").unwrap();
+    let changed = set_all("module.*.version", "2.1.0", root, &options, Some(&any_1_x)).unwrap();
+    assert_eq!(changed.iter().map(|c| c.file.clone()).collect::<Vec<_>>(), [root.join("b.tf")]);
+    assert_eq!(get_value("module.app.version", Some(&root.join("c.tf"))).unwrap(), Some("2.0.0".to_string()));
+}
+
+#[test]
+fn test_set_all_if_current_reports_unreadable_values() {
+    // A value that cannot be resolved is an error, not a mismatch to skip
+    let dir = common::create_test_dir_with_files(&[
+        ("a.tf", "module \"app\" {\n  version = \"1.2.0\"\n}\n"),
+        ("b/main.tf", "module \"app\" {\n  version = var.app_version\n}\n"),
+        ("b/terraform.tfvars", "app_version = \n"),
+    ]);
+    let root = dir.path();
+    let from_1_2 = Expectation::Equals("1.2.0".to_string());
+    assert!(set_all("module.*.version", "2.0.0", root, &SetOptions::default(), Some(&from_1_2)).is_err());
+}
@@ -0,0 +1,65 @@
+mod common;
+
+use tv::bump::{Part, bump, bump_version};
+use tv::get_value;
+
+const MAIN_TF: &str = r#"locals {
+  rds_version = "6.3.0"
+}
+
+module "vpc" {
+  source  = "terraform-aws-modules/vpc/aws"
+  version = "5.1.2"
+}
+
+module "app" {
+  source = "git::https://github.com/org/app.git?ref=v2.4.1"
+}
+
+module "rds" {
+  source  = "terraform-aws-modules/rds/aws"
+  version = local.rds_version
+}
+
+module "eks" {
+  source  = "terraform-aws-modules/eks/aws"
+  version = "~> 20.0"
+  count   = 2
+}
+"#;
+
+#[test]
+fn test_bump_version() {
+    assert_eq!(bump_version("1.4.2", Part::Patch).unwrap(), "1.4.3");
+    assert_eq!(bump_version("1.4.2", Part::Minor).unwrap(), "1.5.0");
+    assert_eq!(bump_version("v1.4.2", Part::Major).unwrap(), "v2.0.0");
+    assert_eq!(bump_version("1.4.2-rc1+build.5", Part::Patch).unwrap(), "1.4.3");
+    assert!(bump_version("~> 5.0", Part::Patch).is_err());
+    assert!(bump_version("1.4", Part::Minor).is_err());
+    assert!(bump_version("main", Part::Minor).is_err());
+    assert_eq!("minor".parse::<Part>().unwrap(), Part::Minor);
+    assert!("build".parse::<Part>().is_err());
+}
+
+#[test]
+fn test_bump() {
+    let (_dir, file) = common::create_test_tf_file(MAIN_TF);
+
+    let bumped = bump("module.vpc.version", Part::Minor, Some(&file)).unwrap();
+    assert_eq!(bumped, ("5.1.2".to_string(), "5.2.0".to_string()));
+    assert_eq!(get_value("module.vpc.version", Some(&file)).unwrap().as_deref(), Some("5.2.0"));
+
+    bump("module.app.source[\"ref\"]", Part::Patch, Some(&file)).unwrap();
+    let source = get_value("module.app.source", Some(&file)).unwrap();
+    assert_eq!(source.as_deref(), Some("git::https://github.com/org/app.git?ref=v2.4.2"));
+
+    // The local is bumped, not replaced
+    bump("module.rds.version", Part::Major, Some(&file)).unwrap();
+    assert_eq!(get_value("locals.rds_version", Some(&file)).unwrap().as_deref(), Some("7.0.0"));
+    assert_eq!(get_value("module.rds.version", Some(&file)).unwrap().as_deref(), Some("local.rds_version"));
+
+    let error = bump("module.eks.version", Part::Patch, Some(&file)).unwrap_err();
+    assert!(error.to_string().contains("not a semver version"), "{}", error);
+    assert!(bump("module.eks.count", Part::Patch, Some(&file)).is_err());
+    assert!(bump("module.missing.version", Part::Patch, Some(&file)).is_err());
+}
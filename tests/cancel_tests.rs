@@ -0,0 +1,47 @@
+mod common;
+
+use std::fs;
+use std::net::TcpListener;
+use std::time::{Duration, Instant};
+use tv::cancel::{Reason, cancelled, check, request_timeout, set_request_timeout, set_timeout};
+use tv::updates::list_tags;
+use tv::{get_value, set_value};
+
+// One test: the cancellation state is process-wide
+#[test]
+fn test_timeout_stops_before_reading_or_writing() {
+    let (temp_dir, file) = common::create_test_tf_file(common::SIMPLE_MODULE_TF);
+
+    set_value("module.vpc.name", "9.9.9", Some(file.as_path())).unwrap();
+    let written = fs::read_to_string(&file).unwrap();
+    assert!(written.contains("9.9.9"));
+    // Written by rename, without leaving the temporary file behind
+    let names: Vec<String> = fs::read_dir(temp_dir.path())
+        .unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+        .collect();
+    assert_eq!(names.len(), 1);
+
+    assert_eq!(request_timeout(), Duration::from_secs(30));
+    // git is ended once its time is up, e.g. on a server that never answers
+    let silent = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("git::http://{}/org/app.git", silent.local_addr().unwrap());
+    set_request_timeout(Duration::from_millis(200));
+    let started = Instant::now();
+    let error = format!("{:#}", list_tags(&url).unwrap_err());
+    assert!(error.contains("gave up after"), "{}", error);
+    assert!(started.elapsed() < Duration::from_secs(5));
+    set_request_timeout(Duration::from_secs(5));
+    assert_eq!(request_timeout(), Duration::from_secs(5));
+    assert!(check().is_ok());
+
+    set_timeout(Duration::ZERO);
+    assert_eq!(cancelled(), Some(Reason::TimedOut));
+    assert!(check().unwrap_err().to_string().contains("Timed out after 0s"));
+    // Nothing is read or written any more, and requests get no time
+    assert!(get_value("module.vpc.name", Some(file.as_path())).is_err());
+    assert!(set_value("module.vpc.name", "1.0.0", Some(file.as_path())).is_err());
+    assert_eq!(fs::read_to_string(&file).unwrap(), written);
+    assert_eq!(request_timeout(), Duration::ZERO);
+    assert_eq!(Reason::TimedOut.exit_code(), 124);
+}
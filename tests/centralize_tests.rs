@@ -0,0 +1,72 @@
+mod common;
+
+use std::fs;
+use tv::centralize::{DEFAULT_TABLE_NAME, centralize, inline};
+
+const MODULES_TF: &str = r#"module "vpc" {
+  source = "git::https://github.com/org/vpc.git?ref=v1.0.0"
+}
+
+module "rds" {
+  source  = "terraform-aws-modules/rds/aws"
+  version = "6.1.0"
+}
+
+module "local" {
+  source = "./local"
+}
+"#;
+
+#[test]
+fn test_centralize_into_locals_and_inline_back() {
+    let temp_dir = common::create_test_dir_with_files(&[("main.tf", MODULES_TF)]);
+    let dir = temp_dir.path();
+    let out = dir.join("versions.tf");
+
+    let moved = centralize(dir, &out, DEFAULT_TABLE_NAME).unwrap();
+    assert_eq!(moved.len(), 2);
+
+    let main = fs::read_to_string(dir.join("main.tf")).unwrap();
+    assert!(main.contains(r#"?ref=${local.module_versions["vpc"]}""#));
+    assert!(main.contains(r#"version = local.module_versions["rds"]"#));
+    assert!(main.contains(r#"source = "./local""#));
+    let versions = fs::read_to_string(&out).unwrap();
+    assert!(versions.contains("locals {"));
+    assert!(versions.contains(r#"vpc = "v1.0.0""#));
+    assert!(versions.contains(r#"rds = "6.1.0""#));
+
+    let restored = inline(dir, DEFAULT_TABLE_NAME, None).unwrap();
+    assert_eq!(restored.len(), 2);
+    assert_eq!(fs::read_to_string(dir.join("main.tf")).unwrap(), MODULES_TF);
+    assert!(!fs::read_to_string(&out).unwrap().contains("module_versions"));
+}
+
+#[test]
+fn test_centralize_into_tfvars() {
+    let temp_dir = common::create_test_dir_with_files(&[("main.tf", MODULES_TF)]);
+    let dir = temp_dir.path();
+    let out = dir.join("terraform.tfvars");
+
+    centralize(dir, &out, DEFAULT_TABLE_NAME).unwrap();
+    let main = fs::read_to_string(dir.join("main.tf")).unwrap();
+    assert!(main.contains(r#"version = var.module_versions["rds"]"#));
+    assert!(fs::read_to_string(&out).unwrap().starts_with("module_versions = {"));
+    assert!(fs::read_to_string(dir.join("variables.tf")).unwrap().contains("variable \"module_versions\""));
+
+    inline(dir, DEFAULT_TABLE_NAME, None).unwrap();
+    assert_eq!(fs::read_to_string(dir.join("main.tf")).unwrap(), MODULES_TF);
+    assert!(!fs::read_to_string(dir.join("variables.tf")).unwrap().contains("variable"));
+}
+
+#[test]
+fn test_centralize_merges_existing_table() {
+    let existing = "locals {\n  region = \"eu-west-1\"\n  module_versions = {\n    dns = \"v2.0.0\"\n  }\n}\n";
+    let temp_dir = common::create_test_dir_with_files(&[("main.tf", MODULES_TF), ("versions.tf", existing)]);
+    let dir = temp_dir.path();
+
+    centralize(dir, &dir.join("versions.tf"), DEFAULT_TABLE_NAME).unwrap();
+    let versions = fs::read_to_string(dir.join("versions.tf")).unwrap();
+    assert!(versions.contains(r#"dns = "v2.0.0""#));
+    assert!(versions.contains(r#"vpc = "v1.0.0""#));
+    assert_eq!(versions.matches("locals").count(), 1);
+}
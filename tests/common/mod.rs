@@ -1,24 +1,6 @@
-use std::fs;
-use tempfile::TempDir;
+#![allow(dead_code)]
 
-pub fn create_test_tf_file(content: &str) -> (TempDir, std::path::PathBuf) {
-    let temp_dir = TempDir::new().unwrap();
-    let file_path = temp_dir.path().join("test.tf");
-    fs::write(&file_path, content).unwrap();
-    (temp_dir, file_path)
-}
-
-pub fn create_test_dir_with_files(files: &[(&str, &str)]) -> TempDir {
-    let temp_dir = TempDir::new().unwrap();
-    for (name, content) in files {
-        let file_path = temp_dir.path().join(name);
-        if let Some(parent) = file_path.parent() {
-            fs::create_dir_all(parent).unwrap();
-        }
-        fs::write(file_path, content).unwrap();
-    }
-    temp_dir
-}
+pub use tv::testing::*;
 
 pub const SIMPLE_MODULE_TF: &str = r#"module "vpc" {
   source = "git::https://github.com/terraform-aws-modules/terraform-aws-vpc.git?ref=v5.0.0"
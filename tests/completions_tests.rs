@@ -0,0 +1,48 @@
+mod common;
+
+use clap::{Arg, Command};
+use clap_complete::Shell;
+use std::fs;
+use tv::completions::{install, install_path, manpage, script, write_manpages};
+
+fn command() -> Command {
+    Command::new("tv")
+        .about("Terraform Version control")
+        .subcommand(Command::new("scan").about("Scan .tf files").arg(Arg::new("query")))
+        .subcommand(Command::new("get").about("Get a value"))
+}
+
+#[test]
+fn test_script_covers_subcommands() {
+    let bash = script(Shell::Bash, &mut command());
+    assert!(bash.contains("_tv()"));
+    assert!(bash.contains("scan"));
+    let fish = script(Shell::Fish, &mut command());
+    assert!(fish.contains("complete -c tv"));
+}
+
+#[test]
+fn test_install_writes_script() {
+    let temp_dir = common::create_test_dir_with_files(&[]);
+    let path = temp_dir.path().join("completions/_tv");
+    let written = install(Shell::Zsh, &mut command(), Some(&path)).unwrap();
+    assert_eq!(written, path);
+    assert!(fs::read_to_string(&path).unwrap().starts_with("#compdef tv"));
+}
+
+#[test]
+fn test_install_path_needs_a_directory_shell() {
+    assert!(install_path(Shell::PowerShell).is_err());
+    assert!(install_path(Shell::Elvish).is_err());
+}
+
+#[test]
+fn test_manpages() {
+    let page = manpage(command()).unwrap();
+    assert!(page.contains(".TH tv 1"));
+
+    let temp_dir = common::create_test_dir_with_files(&[]);
+    let pages = write_manpages(command(), &temp_dir.path().join("man1")).unwrap();
+    let names: Vec<String> = pages.iter().map(|p| p.file_name().unwrap().to_string_lossy().to_string()).collect();
+    assert_eq!(names, vec!["tv-get.1", "tv-scan.1", "tv.1"]);
+}
@@ -0,0 +1,146 @@
+mod common;
+
+use tv::{get_value, parse_query, parse_scan_query, scan_files, set_value};
+
+const CONFIG_BLOCKS_TF: &str = r#"import {
+  to = aws_instance.web
+  id = "i-123"
+}
+
+import {
+  to = aws_instance.db
+  id = "i-456"
+}
+
+moved {
+  from = aws_instance.old
+  to   = aws_instance.web
+}
+
+removed {
+  from = aws_instance.legacy
+
+  lifecycle {
+    destroy = false
+  }
+}
+
+check "health" {
+  assert {
+    condition     = true
+    error_message = "down"
+  }
+}
+"#;
+
+#[test]
+fn test_parse_import_address_selector() {
+    let query = parse_query(r#"import."aws_instance.web".id"#).unwrap();
+    assert_eq!(query.block_type, "import");
+    assert_eq!(query.block_label, Some("aws_instance.web".to_string()));
+    assert_eq!(query.attribute, Some("id".to_string()));
+    assert_eq!(query.to_string(), r#"import."aws_instance.web".id"#);
+
+    // Without a dot the segment is an attribute, as before
+    let query = parse_query("import.id").unwrap();
+    assert_eq!(query.block_label, None);
+    assert_eq!(query.attribute, Some("id".to_string()));
+
+    // `*` selects any block; removed blocks keep their nested blocks
+    let query = parse_scan_query("import.*.id").unwrap();
+    assert_eq!(query.block_label, None);
+    assert_eq!(query.attribute, Some("id".to_string()));
+    let query = parse_scan_query("removed.lifecycle.destroy").unwrap();
+    assert_eq!(query.nested_blocks, vec!["lifecycle".to_string()]);
+}
+
+#[test]
+fn test_scan_every_import_block() {
+    let temp_dir = common::create_test_dir_with_files(&[("main.tf", CONFIG_BLOCKS_TF)]);
+    let mut names: Vec<String> = scan_files("import.*.id", temp_dir.path()).unwrap().into_iter().map(|(_, name)| name).collect();
+    names.sort();
+    assert_eq!(names, vec!["aws_instance.db", "aws_instance.web"]);
+
+    let results = scan_files(r#"import."aws_instance.db".id"#, temp_dir.path()).unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(scan_files("moved.*.from", temp_dir.path()).unwrap().len(), 1);
+    assert_eq!(scan_files("removed.*.lifecycle.destroy", temp_dir.path()).unwrap().len(), 1);
+}
+
+#[test]
+fn test_get_and_set_selected_import_block() {
+    let (_temp_dir, file_path) = common::create_test_tf_file(CONFIG_BLOCKS_TF);
+    assert_eq!(get_value(r#"import."aws_instance.db".id"#, Some(&file_path)).unwrap(), Some("i-456".to_string()));
+
+    set_value(r#"import."aws_instance.db".id"#, "i-789", Some(&file_path)).unwrap();
+    assert_eq!(get_value(r#"import."aws_instance.db".id"#, Some(&file_path)).unwrap(), Some("i-789".to_string()));
+    assert_eq!(get_value(r#"import."aws_instance.web".id"#, Some(&file_path)).unwrap(), Some("i-123".to_string()));
+    assert_eq!(get_value(r#"moved."aws_instance.web".from"#, Some(&file_path)).unwrap(), Some("aws_instance.old".to_string()));
+}
+
+#[test]
+fn test_get_check_assertion() {
+    let (_temp_dir, file_path) = common::create_test_tf_file(CONFIG_BLOCKS_TF);
+    assert_eq!(get_value("check.health.assert.error_message", Some(&file_path)).unwrap(), Some("down".to_string()));
+    assert_eq!(get_value(r#"removed."aws_instance.legacy".lifecycle.destroy"#, Some(&file_path)).unwrap(), Some("false".to_string()));
+}
+
+const RESOURCES_TF: &str = r#"data "aws_ami" "ubuntu" {
+  most_recent = true
+}
+
+resource "aws_instance" "web" {
+  ami = data.aws_ami.ubuntu.id
+
+  lifecycle {
+    prevent_destroy = true
+  }
+}
+
+resource "aws_instance" "db" {
+  ami = "ami-123"
+
+  lifecycle {
+    prevent_destroy = false
+  }
+}
+"#;
+
+#[test]
+fn test_parse_resource_type_and_name() {
+    let query = parse_query("resource.aws_instance.web.ami").unwrap();
+    assert_eq!(query.block_label, Some("aws_instance".to_string()));
+    assert_eq!(query.block_name, Some("web".to_string()));
+    assert_eq!(query.attribute, Some("ami".to_string()));
+    assert!(query.nested_blocks.is_empty());
+
+    let query = parse_scan_query("resource.aws_instance.*.lifecycle.prevent_destroy[==true]").unwrap();
+    assert_eq!(query.block_name, None);
+    assert_eq!(query.nested_blocks, vec!["lifecycle".to_string()]);
+    let filter = query.filter.as_ref().unwrap();
+    assert_eq!((filter.attribute.as_str(), filter.value.as_str()), ("", "true"));
+    assert_eq!(query.to_string(), r#"resource.aws_instance.*.lifecycle.prevent_destroy[=="true"]"#);
+}
+
+#[test]
+fn test_get_and_set_resource_and_data_attributes() {
+    let (_temp_dir, file_path) = common::create_test_tf_file(RESOURCES_TF);
+    assert_eq!(get_value("data.aws_ami.ubuntu.most_recent", Some(&file_path)).unwrap(), Some("true".to_string()));
+    assert_eq!(get_value("resource.aws_instance.web.ami", Some(&file_path)).unwrap(), Some("data.aws_ami.ubuntu.id".to_string()));
+
+    set_value("resource.aws_instance.db.ami", "ami-456", Some(&file_path)).unwrap();
+    assert_eq!(get_value("resource.aws_instance.db.ami", Some(&file_path)).unwrap(), Some("ami-456".to_string()));
+    assert_eq!(get_value("resource.aws_instance.web.ami", Some(&file_path)).unwrap(), Some("data.aws_ami.ubuntu.id".to_string()));
+}
+
+#[test]
+fn test_scan_resources_by_nested_value() {
+    let temp_dir = common::create_test_dir_with_files(&[("main.tf", RESOURCES_TF)]);
+    let names = |query: &str| -> Vec<String> {
+        scan_files(query, temp_dir.path()).unwrap().into_iter().map(|(_, name)| name).collect()
+    };
+    assert_eq!(names("resource.aws_instance.*.lifecycle.prevent_destroy[==true]"), vec!["aws_instance.web"]);
+    assert_eq!(names("resource.aws_instance.*.lifecycle.prevent_destroy[!=true]"), vec!["aws_instance.db"]);
+    assert_eq!(names("resource.aws_instance.db").len(), 1);
+    assert_eq!(names("data.*.*.most_recent"), vec!["aws_ami.ubuntu"]);
+}
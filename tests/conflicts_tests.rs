@@ -0,0 +1,69 @@
+mod common;
+
+use tv::doctor::diagnose_dir;
+use tv::{OnParseError, ScanOptions, conflict_markers, get_value, scan_files_with_failures, scan_files_with_options};
+
+const CONFLICTED_TF: &str = r#"module "vpc" {
+  source  = "terraform-aws-modules/vpc/aws"
+<<<<<<< HEAD
+  version = "5.0.0"
+=======
+  version = "5.1.0"
+>>>>>>> feature
+}
+"#;
+
+const CLEAN_TF: &str = "module \"eks\" {\n  source  = \"terraform-aws-modules/eks/aws\"\n  version = \"19.0.0\"\n}\n";
+
+#[test]
+fn test_conflict_markers() {
+    assert_eq!(conflict_markers(CONFLICTED_TF), vec![3, 5, 7]);
+    // A lone ======= (e.g. inside a heredoc) is not a conflict
+    assert!(conflict_markers("x = <<EOT\n=======\nEOT\n").is_empty());
+    assert!(conflict_markers(CLEAN_TF).is_empty());
+}
+
+#[test]
+fn test_get_names_conflict_lines() {
+    let (_temp_dir, file) = common::create_test_tf_file(CONFLICTED_TF);
+    let error = format!("{:#}", get_value("module.vpc.version", Some(&file)).unwrap_err());
+    assert!(error.contains("Unresolved merge conflict"));
+    assert!(error.contains("markers at lines 3, 5, 7"));
+}
+
+#[test]
+fn test_scan_on_parse_error() {
+    let temp_dir = common::create_test_dir_with_files(&[
+        ("conflicted.tf", CONFLICTED_TF),
+        ("broken.tf", "module \"x\" {\n  version = \n"),
+        ("main.tf", CLEAN_TF),
+    ]);
+
+    assert!(scan_files_with_options("module.*.version", temp_dir.path(), &ScanOptions::default()).is_err());
+
+    let options = ScanOptions { on_parse_error: OnParseError::Warn, ..Default::default() };
+    let (matches, mut failures) = scan_files_with_failures("module.*.version", temp_dir.path(), &options).unwrap();
+    assert_eq!(matches.len(), 1);
+    failures.sort_by(|a, b| a.file.cmp(&b.file));
+    assert_eq!(failures.len(), 2);
+    assert!(failures[0].file.ends_with("broken.tf") && failures[0].conflict_markers.is_empty());
+    assert!(failures[1].file.ends_with("conflicted.tf"));
+    assert_eq!(failures[1].conflict_markers, vec![3, 5, 7]);
+
+    assert!("skip".parse::<OnParseError>().is_ok());
+    assert!("ignore".parse::<OnParseError>().is_err());
+}
+
+#[test]
+fn test_doctor_reports_merge_conflict() {
+    let temp_dir = common::create_test_dir_with_files(&[("main.tf", CONFLICTED_TF)]);
+    let findings = diagnose_dir(temp_dir.path()).unwrap();
+    let conflicts: Vec<&str> = findings
+        .iter()
+        .filter(|f| f.check == "merge-conflict")
+        .map(|f| f.message.as_str())
+        .collect();
+    assert_eq!(conflicts, vec!["unresolved merge conflict (markers at lines 3, 5, 7)"]);
+    // Not also reported as duplicates or a parse error
+    assert!(!findings.iter().any(|f| f.check == "duplicate-attribute" || f.check == "parse"));
+}
@@ -0,0 +1,101 @@
+mod common;
+
+use tv::constraint::{ConstraintStyle, Version, compatible, normalize_constraint, parse_constraints, range_of, satisfies};
+use tv::normalize::normalize_constraints;
+
+fn v(s: &str) -> Version {
+    Version::parse(s).unwrap()
+}
+
+#[test]
+fn test_version_ordering() {
+    assert!(v("1.10.0") > v("1.9.0"));
+    assert_eq!(v("1.2"), v("1.2").clone());
+    assert_eq!(v("1.2").cmp(&v("1.2.0")), std::cmp::Ordering::Equal);
+    assert!(v("1.0.0-beta1") < v("1.0.0"));
+    assert_eq!(v("v2.3.4").to_string(), "2.3.4");
+    assert!(Version::parse("latest").is_err());
+}
+
+#[test]
+fn test_parse_constraints() {
+    let constraints = parse_constraints(">= 4.0, < 6.0").unwrap();
+    assert_eq!(constraints.len(), 2);
+    assert_eq!(constraints[1].to_string(), "< 6.0");
+
+    let pinned = parse_constraints("5.1.0").unwrap();
+    assert_eq!(pinned[0].to_string(), "= 5.1.0");
+    assert!(parse_constraints("~> five").is_err());
+}
+
+#[test]
+fn test_pessimistic_constraint() {
+    let minor = parse_constraints("~> 5.1").unwrap();
+    assert!(satisfies(&v("5.9.0"), &minor));
+    assert!(!satisfies(&v("6.0.0"), &minor));
+
+    let patch = parse_constraints("~> 5.1.2").unwrap();
+    assert!(satisfies(&v("5.1.9"), &patch));
+    assert!(!satisfies(&v("5.2.0"), &patch));
+
+    let range = range_of(&parse_constraints("~> 5.1, >= 5.3").unwrap());
+    assert_eq!(range.lower, Some((v("5.3"), true)));
+    assert_eq!(range.upper, Some((v("6.0"), false)));
+}
+
+#[test]
+fn test_compatible_constraints() {
+    let c = |s: &str| parse_constraints(s).unwrap();
+    assert!(compatible(&c("~> 5.0"), &c(">= 5.10")));
+    assert!(!compatible(&c("~> 4.0"), &c(">= 5.0")));
+    assert!(!compatible(&c("= 5.0.0"), &c("!= 5.0.0")));
+    assert!(compatible(&c("<= 5.0.0"), &c(">= 5.0.0")));
+    assert!(!compatible(&c("< 5.0.0"), &c(">= 5.0.0")));
+}
+
+#[test]
+fn test_normalize_constraint_styles() {
+    let pessimistic = |c: &str| normalize_constraint(c, ConstraintStyle::Pessimistic);
+    assert_eq!(pessimistic(">= 5.0, < 6.0"), Ok("~> 5.0".to_string()));
+    assert_eq!(pessimistic(">= 5.0, >= 5.2, < 6.0"), Ok("~> 5.2".to_string()));
+    assert_eq!(pessimistic(">= 5.1.0, < 5.2.0, != 5.1.3"), Ok("~> 5.1.0, != 5.1.3".to_string()));
+    assert_eq!(pessimistic(">= 3.1.2, < 4.0"), Ok("~> 3.1, >= 3.1.2".to_string()));
+    assert!(pessimistic(">= 5.0").is_err());
+    assert!(pessimistic(">= 5.0, < 5.5").is_err());
+
+    let range = |c: &str| normalize_constraint(c, ConstraintStyle::Range);
+    assert_eq!(range("~> 5.1.0"), Ok(">= 5.1.0, < 5.2.0".to_string()));
+    assert_eq!(range("~> 5.0, >= 5.3"), Ok(">= 5.3, < 6.0".to_string()));
+    assert_eq!(range("5.0.0"), Ok("= 5.0.0".to_string()));
+
+    let exact = |c: &str| normalize_constraint(c, ConstraintStyle::Exact);
+    assert_eq!(exact("= 5.0.0"), Ok("5.0.0".to_string()));
+    assert_eq!(exact(">= 5.0.0, <= 5.0.0"), Ok("5.0.0".to_string()));
+    assert!(exact("~> 5.0").is_err());
+    assert!(exact(">= 6.0, < 5.0").is_err());
+}
+
+#[test]
+fn test_normalize_constraints_in_files() {
+    let temp_dir = common::create_test_dir_with_files(&[(
+        "main.tf",
+        "terraform {\n  required_providers {\n    aws = {\n      source  = \"hashicorp/aws\"\n      version = \">= 5.0, < 6.0\" # pinned\n    }\n    null = \">= 3.0\"\n  }\n}\n\nmodule \"vpc\" {\n  source  = \"terraform-aws-modules/vpc/aws\"\n  version = \"~> 5.1.0\"\n}\n",
+    )]);
+    let file = temp_dir.path().join("main.tf");
+    let original = std::fs::read_to_string(&file).unwrap();
+
+    // A dry run reports without writing
+    let changes = normalize_constraints(temp_dir.path(), ConstraintStyle::Pessimistic, false).unwrap();
+    assert_eq!(changes.len(), 2);
+    assert_eq!(changes[0].address, "terraform.required_providers.aws.version");
+    assert_eq!(changes[0].to, Ok("~> 5.0".to_string()));
+    assert_eq!(changes[1].address, "terraform.required_providers.null.version");
+    assert!(changes[1].to.is_err());
+    assert_eq!(std::fs::read_to_string(&file).unwrap(), original);
+
+    normalize_constraints(temp_dir.path(), ConstraintStyle::Range, true).unwrap();
+    let rewritten = std::fs::read_to_string(&file).unwrap();
+    assert!(rewritten.contains("version = \">= 5.0, < 6.0\" # pinned\n"));
+    assert!(rewritten.contains("version = \">= 5.1.0, < 5.2.0\"\n"));
+    assert!(rewritten.contains("null = \">= 3.0\"\n"));
+}
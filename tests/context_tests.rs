@@ -0,0 +1,26 @@
+use tv::context::excerpt;
+
+const MAIN_TF: &str = r#"module "vpc" {
+  source  = "terraform-aws-modules/vpc/aws"
+
+  version = "5.1.0"
+}
+"#;
+
+#[test]
+fn test_excerpt() {
+    tv::style::set_enabled(false);
+    let marked = |text: &str| text.to_uppercase();
+    assert_eq!(
+        excerpt(MAIN_TF, 2, 1, marked),
+        "  1 | module \"vpc\" {\n> 2 |   SOURCE  = \"TERRAFORM-AWS-MODULES/VPC/AWS\"\n  3 |\n"
+    );
+    // Clipped at the ends of the file
+    assert_eq!(excerpt(MAIN_TF, 5, 3, marked).lines().count(), 4);
+    assert_eq!(excerpt(MAIN_TF, 1, 0, marked), "> 1 | MODULE \"VPC\" {\n");
+    assert_eq!(excerpt(MAIN_TF, 0, 2, marked), "");
+    assert_eq!(excerpt(MAIN_TF, 9, 2, marked), "");
+
+    let long = "x\n".repeat(12);
+    assert!(excerpt(&long, 9, 1, marked).starts_with("   8 | x\n>  9 | X\n  10 | x\n"));
+}
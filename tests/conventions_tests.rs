@@ -0,0 +1,94 @@
+mod common;
+
+use tv::conventions::source_segment;
+use tv::policy::{parse_policy, validate};
+use tv::registry::Client;
+use tv::source::ModuleSource;
+
+const MAIN_TF: &str = r#"module "vpc" {
+  source = "git::https://github.com/org/vpc.git?ref=v1.0.0"
+}
+
+module "network" {
+  source = "git::ssh://git@github.com/org/vpc.git?ref=v1.0.0"
+}
+
+module "eks_cluster" {
+  source = "git@github.com:org/eks-cluster.git"
+}
+
+module "db" {
+  source  = "terraform-aws-modules/rds/aws//modules/db_instance"
+  version = "6.0.0"
+}
+
+module "app" {
+  source = "./modules/app"
+}
+"#;
+
+const POLICY: &str = r#"convention "label-is-last-segment" {
+  name    = "^{segment}$"
+  message = "Name modules after what they fetch"
+}
+
+convention "internal-over-ssh" {
+  match = "*github.com*org/*"
+  url   = "^(git::ssh://|git@)"
+}
+
+convention "registry-submodules" {
+  match = "terraform-aws-modules/*"
+  path  = "^$"
+}
+"#;
+
+#[test]
+fn test_source_segment() {
+    let segment = |source: &str| source_segment(&ModuleSource::parse(source));
+    assert_eq!(segment("git::https://github.com/org/vpc.git?ref=v1"), "vpc");
+    assert_eq!(segment("git@github.com:org/eks-cluster.git"), "eks-cluster");
+    assert_eq!(segment("terraform-aws-modules/vpc/aws"), "vpc");
+    assert_eq!(segment("terraform-aws-modules/rds/aws//modules/db_instance"), "db_instance");
+    assert_eq!(segment("./modules/app"), "app");
+}
+
+#[test]
+fn test_conventions_in_validate() {
+    let temp_dir = common::create_test_dir_with_files(&[("main.tf", MAIN_TF)]);
+    let policy = parse_policy(POLICY).unwrap();
+    assert_eq!(policy.conventions.len(), 3);
+    let reports = validate(&policy, temp_dir.path(), &Client::with_base_url(None)).unwrap();
+
+    let naming = &reports[0];
+    assert_eq!((naming.name.as_str(), naming.checked), ("label-is-last-segment", 5));
+    let broken: Vec<(&str, Option<&str>)> = naming.violations.iter().map(|v| (v.block.as_str(), v.fix.as_deref())).collect();
+    assert_eq!(
+        broken,
+        vec![
+            ("module.network", Some("rename module.network to module.vpc (and move its state)")),
+            ("module.eks_cluster", Some("rename module.eks_cluster to module.eks-cluster (and move its state)")),
+            ("module.db", Some("rename module.db to module.db_instance (and move its state)")),
+        ]
+    );
+    assert_eq!(naming.violations[0].reason, "name \"network\" does not match ^vpc$");
+
+    let ssh = &reports[1];
+    assert_eq!(ssh.checked, 3);
+    assert_eq!(ssh.violations.len(), 1);
+    assert_eq!(ssh.violations[0].block, "module.vpc");
+    assert_eq!(ssh.violations[0].fix.as_deref(), Some("source = \"git::ssh://git@github.com/org/vpc.git?ref=v1.0.0\""));
+
+    let submodules = &reports[2];
+    assert_eq!(submodules.violations.len(), 1);
+    assert!(submodules.violations[0].fix.is_none());
+}
+
+#[test]
+fn test_parse_convention_errors() {
+    assert!(parse_policy("convention \"x\" {\n  match = \"*\"\n}\n").is_err());
+    assert!(parse_policy("convention \"x\" {\n  name = \"(\"\n}\n").is_err());
+    assert!(parse_policy("convention \"x\" {\n  name = 1\n}\n").is_err());
+    let duplicate = "check \"x\" {\n  query = \"module.*\"\n  expect = \"present\"\n}\nconvention \"x\" {\n  name = \"^a\"\n}\n";
+    assert!(parse_policy(duplicate).is_err());
+}
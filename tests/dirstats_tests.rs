@@ -0,0 +1,98 @@
+mod common;
+
+use std::process::Command;
+use tv::dirstats::{ReportFormat, dir_stats, render, root_dirs};
+use tv::updates::UpdateOptions;
+
+const PROD_TF: &str = r#"terraform {
+  required_providers {
+    aws = {
+      source  = "hashicorp/aws"
+      version = "~> 5.0"
+    }
+  }
+}
+
+provider "random" {}
+
+module "vpc" {
+  source  = "terraform-aws-modules/vpc/aws"
+  version = "5.1.0"
+}
+
+module "eks" {
+  source  = "terraform-aws-modules/eks/aws"
+  version = "~> 19.0"
+}
+
+module "app" {
+  source = "../../modules/app"
+}
+"#;
+
+const APP_TF: &str = r#"module "bucket" {
+  source = "git::https://github.com/org/bucket.git?ref=main"
+}
+"#;
+
+#[test]
+fn test_root_dirs_leave_out_local_modules() {
+    let temp_dir = common::create_test_dir_with_files(&[("envs/prod/main.tf", PROD_TF), ("modules/app/main.tf", APP_TF)]);
+    let roots = root_dirs(temp_dir.path()).unwrap();
+    assert_eq!(roots, vec![temp_dir.path().join("envs/prod")]);
+}
+
+#[test]
+fn test_dir_stats_and_formats() {
+    let temp_dir = common::create_test_dir_with_files(&[("envs/prod/main.tf", PROD_TF), ("modules/app/main.tf", APP_TF)]);
+    let report = dir_stats(temp_dir.path(), None).unwrap();
+    assert_eq!(report.len(), 1);
+    let prod = &report[0];
+    assert_eq!((prod.modules, prod.pinnable, prod.pinned), (3, 2, 1));
+    assert_eq!(prod.pinned_percent(), Some(50));
+    assert_eq!(prod.providers, vec!["aws", "random"]);
+    assert_eq!(prod.oldest_pin, None);
+
+    let show = |_: &std::path::Path| "envs/prod".to_string();
+    assert_eq!(
+        render(&report, ReportFormat::Table, show),
+        "directory  modules  pinned  providers    oldest pin\nenvs/prod        3     50%  aws, random  -\n"
+    );
+    assert_eq!(
+        render(&report, ReportFormat::Markdown, show),
+        "| directory | modules | pinned | providers | oldest pin |\n|---|---|---|---|---|\n| envs/prod | 3 | 50% | aws, random | - |\n"
+    );
+    let json: serde_json::Value = serde_json::from_str(&render(&report, ReportFormat::Json, show)).unwrap();
+    assert_eq!(json[0]["pinned_percent"], 50);
+    assert_eq!(json[0]["providers"][1], "random");
+    assert!("csv".parse::<ReportFormat>().is_err());
+}
+
+#[test]
+fn test_dir_stats_oldest_pin_from_tag_dates() {
+    let remote = common::create_git_repo(&[("README.md", "modules\n")]);
+    for (tag, date) in [("v1.0.0", "2020-01-01T00:00:00Z"), ("v2.0.0", "2030-01-01T00:00:00Z")] {
+        let status = Command::new("git")
+            .args(["-c", "user.name=tv", "-c", "user.email=tv@example.com"])
+            .args(["commit", "--quiet", "--allow-empty", "-m", tag])
+            .env("GIT_COMMITTER_DATE", date)
+            .current_dir(remote.path())
+            .status()
+            .unwrap();
+        assert!(status.success());
+        let status = Command::new("git").args(["tag", tag]).current_dir(remote.path()).status().unwrap();
+        assert!(status.success());
+    }
+    let url = format!("git::{}", remote.path().display());
+    let content = format!(
+        "module \"old\" {{\n  source = \"{url}?ref=v1.0.0\"\n}}\n\nmodule \"new\" {{\n  source = \"{url}?ref=v2.0.0\"\n}}\n"
+    );
+    let work = common::create_test_dir_with_files(&[("main.tf", &content)]);
+    let cache = tempfile::TempDir::new().unwrap();
+    let options = UpdateOptions { cache_dir: cache.path().to_path_buf(), ..Default::default() };
+
+    let report = dir_stats(work.path(), Some(&options)).unwrap();
+    let oldest = report[0].oldest_pin.as_ref().unwrap();
+    assert_eq!((oldest.module.as_str(), oldest.pin.as_str()), ("old", "v1.0.0"));
+    assert!(oldest.days.unwrap() > 365 * 5);
+}
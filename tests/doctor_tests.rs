@@ -0,0 +1,178 @@
+mod common;
+
+use std::fs;
+use tv::constraint::pessimistic_constraint;
+use tv::doctor::{Prefer, Severity, consolidate_providers, diagnose_dir, pin_providers};
+
+const VERSIONS_TF: &str = r#"terraform {
+  required_providers {
+    aws = {
+      source  = "hashicorp/aws"
+      version = "~> 5.0"
+    }
+  }
+}
+"#;
+
+const CONFLICTING_VERSIONS_TF: &str = r#"terraform {
+  required_providers {
+    aws = {
+      source  = "example/aws"
+      version = ">= 4.0"
+    }
+  }
+}
+"#;
+
+#[test]
+fn test_doctor_clean_repo() {
+    let files = vec![
+        ("main.tf", common::SIMPLE_MODULE_TF),
+        ("versions.tf", VERSIONS_TF),
+    ];
+    let temp_dir = common::create_test_dir_with_files(&files);
+
+    assert!(diagnose_dir(temp_dir.path()).unwrap().is_empty());
+}
+
+#[test]
+fn test_doctor_reports_repo_problems() {
+    let files = vec![
+        ("main.tf", common::SIMPLE_MODULE_TF),
+        ("copy.tf", common::SIMPLE_MODULE_TF),
+        ("nosource.tf", "module \"orphan\" {\n  name = \"x\"\n}\n"),
+        ("broken.tf", "module \"vpc\" {\n"),
+        ("versions.tf", VERSIONS_TF),
+        ("providers.tf", CONFLICTING_VERSIONS_TF),
+    ];
+    let temp_dir = common::create_test_dir_with_files(&files);
+
+    let findings = diagnose_dir(temp_dir.path()).unwrap();
+    let checks: Vec<(&str, Severity)> = findings.iter().map(|f| (f.check, f.severity)).collect();
+
+    assert!(checks.contains(&("parse", Severity::Error)));
+    assert!(checks.contains(&("duplicate-module", Severity::Error)));
+    assert!(checks.contains(&("module-source", Severity::Error)));
+    assert!(checks.contains(&("provider-conflict", Severity::Error)));
+    assert!(checks.contains(&("provider-conflict", Severity::Warning)));
+    assert!(checks.contains(&("provider-pin", Severity::Warning)));
+    assert!(findings.iter().all(|f| !f.fix.is_empty()));
+}
+
+#[test]
+fn test_pessimistic_constraint_keeps_lower_bound() {
+    assert_eq!(pessimistic_constraint(">= 5.0").as_deref(), Some("~> 5.0"));
+    assert_eq!(pessimistic_constraint(">= 5").as_deref(), Some("~> 5.0"));
+    assert_eq!(pessimistic_constraint(">= 5.0.1").as_deref(), Some("~> 5.0, >= 5.0.1"));
+    assert_eq!(pessimistic_constraint(">= 5.0, != 5.2.0").as_deref(), Some("~> 5.0, != 5.2.0"));
+    // Already bounded, or nothing to convert
+    assert!(pessimistic_constraint(">= 5.0, < 6.0").is_none());
+    assert!(pessimistic_constraint("~> 5.0").is_none());
+    assert!(pessimistic_constraint("> 5.0").is_none());
+}
+
+#[test]
+fn test_pin_providers_rewrites_unbounded_constraints() {
+    let files = vec![
+        ("providers.tf", CONFLICTING_VERSIONS_TF),
+        ("legacy.tf", "terraform {\n  required_providers {\n    random = \">= 3.1\" # legacy\n  }\n}\n"),
+    ];
+    let temp_dir = common::create_test_dir_with_files(&files);
+
+    let fixed = pin_providers(temp_dir.path()).unwrap();
+    assert_eq!(fixed.len(), 2);
+    let providers = fs::read_to_string(temp_dir.path().join("providers.tf")).unwrap();
+    assert_eq!(providers, CONFLICTING_VERSIONS_TF.replace(">= 4.0", "~> 4.0"));
+    let legacy = fs::read_to_string(temp_dir.path().join("legacy.tf")).unwrap();
+    assert!(legacy.contains("random = \"~> 3.1\" # legacy\n"));
+
+    let findings = diagnose_dir(temp_dir.path()).unwrap();
+    assert!(findings.iter().all(|f| f.check != "provider-pin"));
+}
+
+#[test]
+fn test_doctor_checks_each_root_separately() {
+    // The same module label in two directories is two different root modules
+    let files = vec![
+        ("envs/dev/main.tf", common::SIMPLE_MODULE_TF),
+        ("envs/prod/main.tf", common::SIMPLE_MODULE_TF),
+    ];
+    let temp_dir = common::create_test_dir_with_files(&files);
+
+    assert!(diagnose_dir(temp_dir.path()).unwrap().is_empty());
+}
+
+const OLD_VERSIONS_TF: &str = r#"terraform {
+  required_version = ">= 1.5"
+
+  required_providers {
+    aws = {
+      source  = "hashicorp/aws"
+      version = "~> 6.0"
+    }
+  }
+}
+"#;
+
+#[test]
+fn test_doctor_incompatible_constraints() {
+    let files = vec![
+        ("versions.tf", VERSIONS_TF),
+        ("providers.tf", OLD_VERSIONS_TF),
+    ];
+    let temp_dir = common::create_test_dir_with_files(&files);
+
+    let findings = diagnose_dir(temp_dir.path()).unwrap();
+    assert_eq!(findings.len(), 1);
+    assert_eq!(findings[0].severity, Severity::Error);
+    assert!(findings[0].message.contains("incompatible"));
+}
+
+#[test]
+fn test_consolidate_providers_prefer_highest() {
+    let files = vec![
+        ("versions.tf", VERSIONS_TF),
+        ("providers.tf", OLD_VERSIONS_TF),
+    ];
+    let temp_dir = common::create_test_dir_with_files(&files);
+
+    let fixed = consolidate_providers(temp_dir.path(), Prefer::Highest).unwrap();
+    assert_eq!(fixed.len(), 1);
+    assert_eq!(fixed[0].provider, "aws");
+    assert!(fixed[0].kept.ends_with("providers.tf"));
+
+    // The emptied required_providers block is dropped, the rest is untouched
+    let versions = fs::read_to_string(temp_dir.path().join("versions.tf")).unwrap();
+    assert!(!versions.contains("required_providers"));
+    assert!(versions.contains("terraform {"));
+    let providers = fs::read_to_string(temp_dir.path().join("providers.tf")).unwrap();
+    assert_eq!(providers, OLD_VERSIONS_TF);
+
+    assert!(diagnose_dir(temp_dir.path()).unwrap().is_empty());
+}
+
+#[test]
+fn test_consolidate_providers_prefer_lowest() {
+    let files = vec![
+        ("versions.tf", VERSIONS_TF),
+        ("providers.tf", OLD_VERSIONS_TF),
+    ];
+    let temp_dir = common::create_test_dir_with_files(&files);
+
+    let fixed = consolidate_providers(temp_dir.path(), Prefer::Lowest).unwrap();
+    assert!(fixed[0].kept.ends_with("versions.tf"));
+    let providers = fs::read_to_string(temp_dir.path().join("providers.tf")).unwrap();
+    assert!(providers.contains("required_version"));
+    assert!(!providers.contains("hashicorp/aws"));
+}
+
+#[test]
+fn test_consolidate_skips_different_sources() {
+    let files = vec![
+        ("versions.tf", VERSIONS_TF),
+        ("providers.tf", CONFLICTING_VERSIONS_TF),
+    ];
+    let temp_dir = common::create_test_dir_with_files(&files);
+
+    assert!(consolidate_providers(temp_dir.path(), Prefer::Highest).unwrap().is_empty());
+}
@@ -0,0 +1,91 @@
+mod common;
+
+use std::fs;
+use tv::doctor::diagnose_dir;
+use tv::duplicates::{Keep, find_duplicates, fix_duplicates, remove_duplicates};
+use tv::policy::{parse_policy, validate};
+use tv::registry::Client;
+
+// What a merge conflict resolved by keeping both sides leaves behind
+const MERGED_TF: &str = r#"module "vpc" {
+  source  = "terraform-aws-modules/vpc/aws"
+  version = "5.0.0"
+  name    = "main # not a comment ${var.env}"
+  tags = {
+    Team = "a"
+  }
+  version = "5.1.0"
+  tags = {
+    Team = "b"
+  }
+
+  lifecycle {
+    ignore_changes = [tags]
+  }
+}
+
+locals {
+  policy = <<-EOT
+    version = 1
+    version = 2
+  EOT
+  # version = 3
+  region = "eu-west-1"
+}
+"#;
+
+#[test]
+fn test_find_duplicates() {
+    let duplicates = find_duplicates(MERGED_TF);
+    let found: Vec<(&str, &str)> = duplicates.iter().map(|d| (d.block.as_str(), d.key.as_str())).collect();
+    // Heredoc contents, strings and comments are not attributes
+    assert_eq!(found, vec![("module.vpc", "version"), ("module.vpc", "tags")]);
+    assert_eq!(duplicates[0].lines, vec![(3, 3), (8, 8)]);
+    assert_eq!(duplicates[1].lines, vec![(5, 7), (9, 11)]);
+
+    // The same key in different blocks is fine
+    assert!(find_duplicates("a {\n  x = 1\n}\nb {\n  x = 1\n  c {\n    x = 2\n  }\n}\n").is_empty());
+    assert_eq!(find_duplicates("x = 1\nx = 2\n")[0].block, "");
+}
+
+#[test]
+fn test_remove_duplicates() {
+    let last = remove_duplicates(MERGED_TF, Keep::Last).unwrap();
+    assert!(!last.contains("5.0.0"));
+    assert!(last.contains("version = \"5.1.0\""));
+    assert!(last.contains("Team = \"b\"") && !last.contains("Team = \"a\""));
+
+    let first = remove_duplicates(MERGED_TF, Keep::First).unwrap();
+    assert!(first.contains("version = \"5.0.0\"") && !first.contains("5.1.0"));
+    assert!(first.contains("Team = \"a\"") && !first.contains("Team = \"b\""));
+    assert!(first.contains("lifecycle {") && first.contains("version = 2"));
+    assert!("keep-middle".parse::<Keep>().is_err());
+}
+
+#[test]
+fn test_doctor_and_validate_report_duplicates() {
+    let temp_dir = common::create_test_dir_with_files(&[("main.tf", MERGED_TF)]);
+
+    let findings = diagnose_dir(temp_dir.path()).unwrap();
+    let duplicates: Vec<&str> = findings
+        .iter()
+        .filter(|f| f.check == "duplicate-attribute")
+        .map(|f| f.message.as_str())
+        .collect();
+    assert_eq!(duplicates, vec!["module.vpc sets \"version\" 2 times (lines 3, 8)", "module.vpc sets \"tags\" 2 times (lines 5, 9)"]);
+    assert!(!findings.iter().any(|f| f.check == "parse"));
+
+    let policy = parse_policy("check \"vpc\" {\n  query  = \"module.vpc.version\"\n  expect = \"present\"\n}\n").unwrap();
+    let reports = validate(&policy, temp_dir.path(), &Client::with_base_url(None)).unwrap();
+    assert_eq!(reports[0].name, "duplicate-attributes");
+    assert_eq!(reports[0].violations.len(), 2);
+    // The other checks skip the file instead of failing
+    assert_eq!(reports[1].checked, 0);
+
+    let fixed = fix_duplicates(temp_dir.path(), Keep::Last).unwrap();
+    assert_eq!(fixed.len(), 1);
+    assert!(find_duplicates(&fs::read_to_string(temp_dir.path().join("main.tf")).unwrap()).is_empty());
+    let reports = validate(&policy, temp_dir.path(), &Client::with_base_url(None)).unwrap();
+    assert_eq!(reports.len(), 1);
+    assert!(reports[0].passed());
+}
@@ -0,0 +1,133 @@
+mod common;
+
+use tv::effective::{OnOverride, effective_block, is_override_file, overridden_by, set_target};
+
+const MAIN_TF: &str = r#"terraform {
+  required_providers {
+    aws    = { source = "hashicorp/aws", version = "5.0.0" }
+    random = { source = "hashicorp/random" }
+  }
+}
+
+module "vpc" {
+  source  = "terraform-aws-modules/vpc/aws"
+  version = "5.0.0"
+  name    = "main"
+}
+
+resource "aws_s3_bucket" "logs" {
+  bucket = "logs"
+  lifecycle {
+    prevent_destroy = true
+  }
+}
+
+locals {
+  region = "eu-west-1"
+  env    = "prod"
+}
+"#;
+
+const OVERRIDE_TF: &str = r#"terraform {
+  required_providers {
+    aws = { source = "hashicorp/aws", version = "5.2.0" }
+  }
+}
+
+module "vpc" {
+  version = "5.1.0"
+}
+
+resource "aws_s3_bucket" "logs" {
+  lifecycle {
+    ignore_changes = [tags]
+  }
+}
+
+locals {
+  env = "staging"
+}
+"#;
+
+fn summary(query: &str, dir: &std::path::Path) -> Vec<(String, String, String)> {
+    effective_block(query, dir)
+        .unwrap()
+        .into_iter()
+        .map(|d| (d.name, d.value, d.file.file_name().unwrap().to_string_lossy().to_string()))
+        .collect()
+}
+
+fn owned(expected: &[(&str, &str, &str)]) -> Vec<(String, String, String)> {
+    expected.iter().map(|(a, b, c)| (a.to_string(), b.to_string(), c.to_string())).collect()
+}
+
+#[test]
+fn test_effective_merges_overrides() {
+    let temp_dir = common::create_test_dir_with_files(&[("main.tf", MAIN_TF), ("main_override.tf", OVERRIDE_TF)]);
+    let dir = temp_dir.path();
+
+    assert_eq!(
+        summary("module.vpc", dir),
+        owned(&[
+            ("source", "\"terraform-aws-modules/vpc/aws\"", "main.tf"),
+            ("version", "\"5.1.0\"", "main_override.tf"),
+            ("name", "\"main\"", "main.tf"),
+        ])
+    );
+    let version = &effective_block("module.vpc.version", dir).unwrap()[0];
+    assert_eq!(version.overridden, vec![(dir.join("main.tf"), "\"5.0.0\"".to_string())]);
+
+    // Locals and required_providers merge per entry
+    assert_eq!(
+        summary("locals", dir),
+        owned(&[("region", "\"eu-west-1\"", "main.tf"), ("env", "\"staging\"", "main_override.tf")])
+    );
+    let providers = summary("terraform.required_providers", dir);
+    assert_eq!(providers[0].2, "main_override.tf");
+    assert_eq!(providers[1], ("required_providers.random".to_string(), "{ source = \"hashicorp/random\" }".to_string(), "main.tf".to_string()));
+
+    // Nested blocks are replaced whole
+    let resource = effective_block("resource.aws_s3_bucket", dir).unwrap();
+    let lifecycle = resource.iter().find(|d| d.name == "lifecycle").unwrap();
+    assert!(lifecycle.nested && lifecycle.value.contains("ignore_changes") && !lifecycle.value.contains("prevent_destroy"));
+
+    assert!(effective_block("module.*", dir).is_err());
+}
+
+#[test]
+fn test_set_target_overridden() {
+    let temp_dir = common::create_test_dir_with_files(&[("main.tf", MAIN_TF), ("main_override.tf", OVERRIDE_TF)]);
+    let main = temp_dir.path().join("main.tf");
+
+    assert_eq!(overridden_by("module.vpc.version", Some(&main)).unwrap(), Some(temp_dir.path().join("main_override.tf")));
+    assert_eq!(overridden_by("module.vpc.name", Some(&main)).unwrap(), None);
+    assert!(overridden_by("terraform.required_providers.aws.version", Some(&main)).unwrap().is_some());
+    assert!(overridden_by("terraform.required_providers.random.source", Some(&main)).unwrap().is_none());
+    // Editing the override itself is what takes effect
+    assert_eq!(overridden_by("module.vpc.version", Some(&temp_dir.path().join("main_override.tf"))).unwrap(), None);
+
+    assert!(is_override_file(std::path::Path::new("envs/override.tf")));
+    assert!(!is_override_file(std::path::Path::new("overrides.tf")));
+}
+
+#[test]
+fn test_set_target_on_override() {
+    let temp_dir = common::create_test_dir_with_files(&[("main.tf", MAIN_TF), ("main_override.tf", OVERRIDE_TF)]);
+    let main = temp_dir.path().join("main.tf");
+    let over = temp_dir.path().join("main_override.tf");
+
+    // Default: edit the file asked for, and report the override
+    assert_eq!(set_target("module.vpc.version", Some(&main), None).unwrap(), (main.clone(), Some(over.clone())));
+    assert_eq!(set_target("module.vpc.version", Some(&main), Some(OnOverride::Edit)).unwrap(), (over.clone(), None));
+    let err = set_target("module.vpc.version", Some(&main), Some(OnOverride::Fail)).unwrap_err().to_string();
+    assert!(err.contains("main_override.tf"));
+    // Values nothing overrides are unaffected
+    assert_eq!(set_target("module.vpc.name", Some(&main), Some(OnOverride::Fail)).unwrap(), (main.clone(), None));
+
+    // tv.toml sets the default; the option wins over it
+    std::fs::write(temp_dir.path().join("tv.toml"), "on_override = \"edit\"\n").unwrap();
+    assert_eq!(set_target("module.vpc.version", Some(&main), None).unwrap(), (over.clone(), None));
+    assert_eq!(set_target("module.vpc.version", Some(&main), Some(OnOverride::Warn)).unwrap(), (main, Some(over)));
+
+    assert!("sometimes".parse::<OnOverride>().is_err());
+}
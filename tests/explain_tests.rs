@@ -0,0 +1,70 @@
+mod common;
+
+use tv::{BlockMatch, ScanOptions};
+use tv::explain::{describe_miss, describe_scan_query, explain_scan};
+
+#[test]
+fn test_explain_counts_matches() {
+    let files = vec![
+        ("main.tf", common::SIMPLE_MODULE_TF),
+        ("other.tf", common::REGISTRY_MODULE_TF),
+        ("providers.tf", common::TERRAFORM_BLOCK_TF),
+    ];
+    let temp_dir = common::create_test_dir_with_files(&files);
+
+    let report = explain_scan("module.*.version", temp_dir.path(), &ScanOptions::default()).unwrap();
+    assert_eq!(report.files_scanned, 3);
+    assert_eq!(report.files_matched, 1);
+    assert_eq!(report.blocks_considered, 2);
+    assert_eq!(report.blocks_matched, 1);
+    assert_eq!(report.non_matches.len(), 1);
+    assert_eq!(report.non_matches[0].block, "module.vpc");
+    assert_eq!(report.non_matches[0].reason, BlockMatch::MissingAttribute("version".to_string()));
+}
+
+#[test]
+fn test_explain_reports_filter_mismatch_value() {
+    let files = vec![("main.tf", common::MULTIPLE_MODULES_TF)];
+    let temp_dir = common::create_test_dir_with_files(&files);
+
+    let report = explain_scan("module.*.source[ref==\"main\"]", temp_dir.path(), &ScanOptions::default()).unwrap();
+    assert_eq!(report.blocks_matched, 0);
+    assert_eq!(report.non_matches[0].reason, BlockMatch::FilterMismatch(Some("v5.0.0".to_string())));
+    assert_eq!(describe_miss(&report.non_matches[0].reason), "filter mismatch (value is \"v5.0.0\")");
+}
+
+#[test]
+fn test_explain_reports_wrong_label() {
+    let files = vec![("main.tf", common::MULTIPLE_MODULES_TF)];
+    let temp_dir = common::create_test_dir_with_files(&files);
+
+    let report = explain_scan("module.rds", temp_dir.path(), &ScanOptions::default()).unwrap();
+    assert_eq!(report.blocks_considered, 2);
+    assert_eq!(report.non_matches[0].reason, BlockMatch::WrongLabel(Some("vpc".to_string())));
+}
+
+#[test]
+fn test_explain_collects_unparsable_files() {
+    let files = vec![
+        ("main.tf", common::SIMPLE_MODULE_TF),
+        ("broken.tf", "module \"x\" {"),
+    ];
+    let temp_dir = common::create_test_dir_with_files(&files);
+
+    let report = explain_scan("module.*", temp_dir.path(), &ScanOptions::default()).unwrap();
+    assert_eq!(report.unparsable.len(), 1);
+    assert_eq!(report.files_matched, 1);
+}
+
+#[test]
+fn test_describe_scan_query() {
+    let files = vec![("main.tf", common::SIMPLE_MODULE_TF)];
+    let temp_dir = common::create_test_dir_with_files(&files);
+
+    let report = explain_scan("module.*.source[url==\"x\"]", temp_dir.path(), &ScanOptions::default()).unwrap();
+    let description = describe_scan_query(&report.query);
+    assert!(description.contains("block type:  module"));
+    assert!(description.contains("label:       * (any)"));
+    assert!(description.contains("attribute:   source"));
+    assert!(description.contains("filter:      url == \"x\""));
+}
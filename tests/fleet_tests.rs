@@ -0,0 +1,249 @@
+mod common;
+
+use std::fs;
+use std::process::Command;
+use tv::fleet::{evict_cache, fleet_check_updates, fleet_scan, load_repos, repo_name_from_url, sync_repo, FleetRepo, SyncOptions};
+use tv::parse_scan_query;
+use tv::updates::UpdateOptions;
+
+#[test]
+fn test_repo_name_from_url() {
+    assert_eq!(repo_name_from_url("https://github.com/org/infra.git"), "infra");
+    assert_eq!(repo_name_from_url("git@github.com:org/networking.git"), "networking");
+    assert_eq!(repo_name_from_url("https://github.com/org/platform/"), "platform");
+}
+
+#[test]
+fn test_load_repos() {
+    let temp_dir = common::create_test_dir_with_files(&[(
+        "repos.yaml",
+        "repos:\n  - url: https://github.com/org/infra.git\n  - url: https://github.com/org/other.git\n    name: custom\n    ref: main\n",
+    )]);
+
+    let repos = load_repos(&temp_dir.path().join("repos.yaml")).unwrap();
+    assert_eq!(repos.len(), 2);
+    assert_eq!(repos[0].display_name(), "infra");
+    assert_eq!(repos[1].display_name(), "custom");
+    assert_eq!(repos[1].git_ref, Some("main".to_string()));
+}
+
+#[test]
+fn test_load_repos_rejects_duplicate_names() {
+    let temp_dir = common::create_test_dir_with_files(&[(
+        "repos.yaml",
+        "repos:\n  - url: https://github.com/a/infra.git\n  - url: https://github.com/b/infra.git\n",
+    )]);
+
+    assert!(load_repos(&temp_dir.path().join("repos.yaml")).is_err());
+}
+
+#[test]
+fn test_repo_names_stay_in_the_cache() {
+    for name in ["\"\"", ".", "..", "../victim", "/tmp/victim", "a\\\\b"] {
+        let temp_dir = common::create_test_dir_with_files(&[(
+            "repos.yaml",
+            &format!("repos:\n  - url: https://github.com/org/infra.git\n    name: {}\n", name),
+        )]);
+        assert!(load_repos(&temp_dir.path().join("repos.yaml")).is_err(), "{}", name);
+    }
+    let temp_dir = common::create_test_dir_with_files(&[("repos.yaml", "repos:\n  - url: https://github.com/org/..\n")]);
+    assert!(load_repos(&temp_dir.path().join("repos.yaml")).is_err());
+
+    // Nor does sync_repo touch a repository next to the cache
+    let victim = common::create_git_repo(&[("main.tf", common::SIMPLE_MODULE_TF), ("README.md", "# victim")]);
+    let cache = victim.path().join("cache");
+    let repo = FleetRepo {
+        url: victim.path().to_string_lossy().to_string(),
+        name: Some("..".to_string()),
+        git_ref: None,
+    };
+    assert!(sync_repo(&repo, &cache, &SyncOptions::default()).is_err());
+    assert!(victim.path().join("README.md").exists());
+}
+
+#[test]
+fn test_sync_repo_clones_then_updates() {
+    let origin = common::create_git_repo(&[("main.tf", common::SIMPLE_MODULE_TF)]);
+    let cache = tempfile::TempDir::new().unwrap();
+    let repo = FleetRepo {
+        url: origin.path().to_string_lossy().to_string(),
+        name: Some("infra".to_string()),
+        git_ref: None,
+    };
+
+    let checkout = sync_repo(&repo, cache.path(), &SyncOptions::default()).unwrap();
+    assert!(checkout.join("main.tf").exists());
+
+    // A second sync reuses the existing checkout
+    let again = sync_repo(&repo, cache.path(), &SyncOptions::default()).unwrap();
+    assert_eq!(checkout, again);
+}
+
+#[test]
+fn test_fleet_scan_aggregates_per_repo() {
+    let first = common::create_git_repo(&[("main.tf", common::SIMPLE_MODULE_TF)]);
+    let second = common::create_git_repo(&[("modules/eks/main.tf", common::MULTIPLE_MODULES_TF)]);
+    let cache = tempfile::TempDir::new().unwrap();
+
+    let repos = vec![
+        FleetRepo {
+            url: first.path().to_string_lossy().to_string(),
+            name: Some("first".to_string()),
+            git_ref: None,
+        },
+        FleetRepo {
+            url: second.path().to_string_lossy().to_string(),
+            name: Some("second".to_string()),
+            git_ref: None,
+        },
+    ];
+
+    let results = fleet_scan(&repos, cache.path(), &SyncOptions::default(), "module.*");
+    assert_eq!(results.len(), 2);
+
+    assert_eq!(results[0].repo, "first");
+    let first_matches = results[0].matches.as_ref().unwrap();
+    assert_eq!(first_matches.len(), 1);
+    assert_eq!(first_matches[0].0, std::path::PathBuf::from("main.tf"));
+
+    assert_eq!(results[1].repo, "second");
+    assert_eq!(results[1].matches.as_ref().unwrap().len(), 2);
+}
+
+#[test]
+fn test_fleet_scan_reports_failed_repo() {
+    let cache = tempfile::TempDir::new().unwrap();
+    let missing = cache.path().join("does-not-exist");
+    fs::create_dir_all(cache.path().join("checkouts")).unwrap();
+
+    let repos = vec![FleetRepo {
+        url: missing.to_string_lossy().to_string(),
+        name: None,
+        git_ref: None,
+    }];
+
+    let results = fleet_scan(&repos, &cache.path().join("checkouts"), &SyncOptions::default(), "module.*");
+    assert!(results[0].matches.is_err());
+}
+
+#[test]
+fn test_sync_repo_sparse_checkout_only_tf_files() {
+    let origin = common::create_git_repo(&[
+        ("main.tf", common::SIMPLE_MODULE_TF),
+        ("modules/vpc/main.tf", common::REGISTRY_MODULE_TF),
+        ("README.md", "# infra"),
+    ]);
+    let cache = tempfile::TempDir::new().unwrap();
+    let repo = FleetRepo {
+        url: format!("file://{}", origin.path().display()),
+        name: Some("infra".to_string()),
+        git_ref: None,
+    };
+
+    let checkout = sync_repo(&repo, cache.path(), &SyncOptions::default()).unwrap();
+    assert!(checkout.join("main.tf").exists());
+    assert!(checkout.join("modules/vpc/main.tf").exists());
+    assert!(!checkout.join("README.md").exists());
+}
+
+#[test]
+fn test_sync_repo_full_checkout() {
+    let origin = common::create_git_repo(&[
+        ("main.tf", common::SIMPLE_MODULE_TF),
+        ("README.md", "# infra"),
+    ]);
+    let cache = tempfile::TempDir::new().unwrap();
+    let repo = FleetRepo {
+        url: format!("file://{}", origin.path().display()),
+        name: Some("infra".to_string()),
+        git_ref: None,
+    };
+    let options = SyncOptions { depth: None, sparse: false };
+
+    let checkout = sync_repo(&repo, cache.path(), &options).unwrap();
+    assert!(checkout.join("README.md").exists());
+}
+
+#[test]
+fn test_evict_cache_removes_unlisted_checkouts() {
+    let origin = common::create_git_repo(&[("main.tf", common::SIMPLE_MODULE_TF)]);
+    let cache = tempfile::TempDir::new().unwrap();
+    let url = origin.path().to_string_lossy().to_string();
+    let keep = FleetRepo { url: url.clone(), name: Some("keep".to_string()), git_ref: None };
+    let stale = FleetRepo { url, name: Some("stale".to_string()), git_ref: None };
+
+    sync_repo(&keep, cache.path(), &SyncOptions::default()).unwrap();
+    sync_repo(&stale, cache.path(), &SyncOptions::default()).unwrap();
+
+    let removed = evict_cache(std::slice::from_ref(&keep), cache.path(), false).unwrap();
+    assert_eq!(removed, vec![cache.path().join("stale")]);
+    assert!(cache.path().join("keep").exists());
+
+    let removed = evict_cache(&[keep], cache.path(), true).unwrap();
+    assert_eq!(removed, vec![cache.path().join("keep")]);
+}
+
+#[test]
+fn test_sync_repo_never_passes_options_to_git() {
+    let marker = tempfile::TempDir::new().unwrap();
+    let pwned = marker.path().join("pwned");
+    let cache = tempfile::TempDir::new().unwrap();
+    let repo = FleetRepo {
+        url: format!("--upload-pack=touch {};", pwned.display()),
+        name: Some("infra".to_string()),
+        git_ref: None,
+    };
+    assert!(sync_repo(&repo, cache.path(), &SyncOptions::default()).is_err());
+    assert!(!pwned.exists());
+
+    let origin = common::create_git_repo(&[("main.tf", common::SIMPLE_MODULE_TF)]);
+    let repo = FleetRepo {
+        url: origin.path().to_string_lossy().to_string(),
+        name: Some("infra".to_string()),
+        git_ref: Some(format!("--upload-pack=touch {};", pwned.display())),
+    };
+    let error = sync_repo(&repo, cache.path(), &SyncOptions::default()).unwrap_err().to_string();
+    assert_eq!(error, "Refusing infra: its ref starts with '-'");
+    assert!(!pwned.exists());
+}
+
+#[test]
+fn test_fleet_check_updates_per_repo() {
+    let module = common::create_git_repo(&[("main.tf", "")]);
+    for tag in ["v1.0.0", "v1.1.0", "v2.0.0-beta.1"] {
+        let status = Command::new("git").args(["tag", tag]).current_dir(module.path()).status().unwrap();
+        assert!(status.success());
+    }
+    let main_tf = format!("module \"app\" {{\n  source = \"git::{}?ref=v1.0.0\"\n}}\n", module.path().display());
+    // The second repository tracks stable releases only, through its tv.toml
+    let first = common::create_git_repo(&[("main.tf", &main_tf)]);
+    let second = common::create_git_repo(&[
+        ("stacks/main.tf", &main_tf),
+        ("tv.toml", "[[track]]\nmatch = \"app\"\nchannel = \"stable\"\n"),
+    ]);
+    let cache = tempfile::TempDir::new().unwrap();
+    let tags = tempfile::TempDir::new().unwrap();
+    let repo = |dir: &std::path::Path, name: &str| FleetRepo {
+        url: dir.to_string_lossy().to_string(),
+        name: Some(name.to_string()),
+        git_ref: None,
+    };
+    let repos = vec![
+        repo(first.path(), "first"),
+        repo(second.path(), "second"),
+        repo(&tags.path().join("does-not-exist"), "missing"),
+    ];
+    let options = UpdateOptions { cache_dir: tags.path().to_path_buf(), ..Default::default() };
+
+    let query = parse_scan_query("module.*").unwrap();
+    let results = fleet_check_updates(&repos, cache.path(), &SyncOptions::default(), &query, &options);
+    let found: Vec<(&str, String, &str)> = results[..2]
+        .iter()
+        .flat_map(|r| r.updates.as_ref().unwrap().iter().map(move |u| (r.repo.as_str(), u.file.display().to_string(), u.latest.as_str())))
+        .collect();
+    assert_eq!(found, vec![
+        ("first", "main.tf".to_string(), "v2.0.0-beta.1"),
+        ("second", "stacks/main.tf".to_string(), "v1.1.0"),
+    ]);
+    assert!(results[2].updates.is_err());
+}
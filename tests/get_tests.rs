@@ -1,6 +1,6 @@
 mod common;
 
-use tv::{get_value, extract_param_from_source, extract_url_from_source, extract_path_from_source};
+use tv::{get_value, get_typed_value, value_kind, ValueKind, extract_param_from_source, extract_url_from_source, extract_path_from_source};
 
 #[test]
 fn test_get_simple_module_source() {
@@ -144,3 +144,81 @@ fn test_extract_path_with_query() {
     let result = extract_path_from_source(source);
     assert_eq!(result, Some("path/to/module".to_string()));
 }
+
+const TYPED_VALUES_TF: &str = r#"module "vpc" {
+  source      = "terraform-aws-modules/vpc/aws"
+  single_nat  = true
+  az_count    = 3
+  azs         = ["us-east-1a", "us-east-1b"]
+  tags        = { Team = "platform" }
+  name        = "${var.env}-vpc"
+  cidr        = var.cidr
+  description = null
+}
+"#;
+
+#[test]
+fn test_get_typed_value_kinds() {
+    let (_dir, file) = common::create_test_tf_file(TYPED_VALUES_TF);
+
+    let cases = [
+        ("module.vpc.source", ValueKind::String),
+        ("module.vpc.single_nat", ValueKind::Bool),
+        ("module.vpc.az_count", ValueKind::Number),
+        ("module.vpc.azs", ValueKind::List),
+        ("module.vpc.tags", ValueKind::Object),
+        ("module.vpc.name", ValueKind::Expression),
+        ("module.vpc.cidr", ValueKind::Expression),
+        ("module.vpc.description", ValueKind::Null),
+    ];
+    for (query, kind) in cases {
+        let typed = get_typed_value(query, Some(file.as_path())).unwrap().unwrap();
+        assert_eq!(typed.kind, kind, "{}", query);
+    }
+}
+
+#[test]
+fn test_get_typed_value_source_param_is_string() {
+    let (_dir, file) = common::create_test_tf_file(common::SIMPLE_MODULE_TF);
+
+    let typed = get_typed_value("module.vpc.source[\"ref\"]", Some(file.as_path())).unwrap().unwrap();
+    assert_eq!(typed.value, "v5.0.0");
+    assert_eq!(typed.kind, ValueKind::String);
+}
+
+#[test]
+fn test_get_typed_value_nested_object() {
+    let (_dir, file) = common::create_test_tf_file(common::TERRAFORM_BLOCK_TF);
+
+    let typed = get_typed_value("terraform.required_providers.aws.version", Some(file.as_path())).unwrap().unwrap();
+    assert_eq!(typed.value, "6.15.0");
+    assert_eq!(typed.kind, ValueKind::String);
+}
+
+#[test]
+fn test_value_kind_heredoc() {
+    assert_eq!(value_kind("<<EOT\nhello\nEOT\n"), ValueKind::String);
+    assert_eq!(value_kind("<<EOT\n${var.x}\nEOT\n"), ValueKind::Expression);
+}
+
+#[test]
+fn test_get_and_scan_dotted_label() {
+    let content = "module \"my.module\" {\n  source = \"./modules/a\"\n}\n";
+    let (dir, file) = common::create_test_tf_file(content);
+
+    let result = get_value("module.\"my.module\".source", Some(file.as_path())).unwrap();
+    assert_eq!(result, Some("./modules/a".to_string()));
+
+    let results = tv::scan_files("module.\"my.module\".source", dir.path()).unwrap();
+    assert_eq!(results.len(), 1);
+}
+
+#[test]
+fn test_get_terraform_provider_object() {
+    // Same address scan uses; the nested part is a block, the last an attribute
+    let (_dir, file) = common::create_test_tf_file(common::TERRAFORM_BLOCK_TF);
+
+    let typed = get_typed_value("terraform.required_providers.aws", Some(file.as_path())).unwrap().unwrap();
+    assert_eq!(typed.kind, ValueKind::Object);
+    assert!(typed.value.contains("hashicorp/aws"));
+}
@@ -0,0 +1,66 @@
+mod common;
+
+use tv::graph::{EdgeKind, GraphFormat, dependency_graph, render};
+
+const APP_HCL: &str = r#"include "root" {
+  path = find_in_parent_folders()
+}
+
+dependency "vpc" {
+  config_path = "../vpc"
+}
+
+dependency "dns" {
+  config_path = "${get_terragrunt_dir()}/../dns"
+}
+
+dependencies {
+  paths = ["../vpc", "../rds/"]
+}
+"#;
+
+fn repository() -> tempfile::TempDir {
+    common::create_test_dir_with_files(&[
+        ("live/prod/app/terragrunt.hcl", APP_HCL),
+        ("live/prod/vpc/terragrunt.hcl", "terraform {\n  source = \"../../../modules//vpc\"\n}\n"),
+        ("live/prod/rds/terragrunt.hcl", "terraform {\n  source = \"git::https://github.com/org/rds.git?ref=v1.0.0\"\n}\n"),
+        ("live/prod/app/.terragrunt-cache/abc/terragrunt.hcl", APP_HCL),
+        ("modules/vpc/main.tf", "resource \"aws_vpc\" \"this\" {}\n"),
+        ("stacks/network/main.tf", "module \"vpc\" {\n  source = \"../../modules/vpc\"\n}\n"),
+    ])
+}
+
+#[test]
+fn test_dependency_graph() {
+    let temp_dir = repository();
+    let edges = dependency_graph(temp_dir.path()).unwrap();
+    let shown: Vec<(&str, &str, &str, EdgeKind)> =
+        edges.iter().map(|e| (e.from.as_str(), e.to.as_str(), e.via.as_str(), e.kind)).collect();
+    assert_eq!(
+        shown,
+        vec![
+            ("live/prod/app", "live/prod/rds", "dependencies", EdgeKind::Dependency),
+            ("live/prod/app", "live/prod/vpc", "dependencies", EdgeKind::Dependency),
+            ("live/prod/app", "live/prod/vpc", "dependency.vpc", EdgeKind::Dependency),
+            ("live/prod/vpc", "modules/vpc", "terraform.source", EdgeKind::Module),
+            ("stacks/network", "modules/vpc", "module.vpc", EdgeKind::Module),
+        ]
+    );
+    assert!(edges[2].file.ends_with("live/prod/app/terragrunt.hcl"));
+}
+
+#[test]
+fn test_render_graph() {
+    let temp_dir = repository();
+    let edges = dependency_graph(&temp_dir.path().join("live")).unwrap();
+    assert_eq!(
+        render(&edges[2..], GraphFormat::Text),
+        "prod/app -> prod/vpc (dependency.vpc)\nprod/vpc -> ../modules/vpc (terraform.source)\n"
+    );
+    assert_eq!(
+        render(&edges[2..], GraphFormat::Dot),
+        "digraph tv {\n  \"prod/app\" -> \"prod/vpc\" [label=\"dependency.vpc\"];\n  \
+         \"prod/vpc\" -> \"../modules/vpc\" [label=\"terraform.source\", style=dashed];\n}\n"
+    );
+    assert!("svg".parse::<GraphFormat>().is_err());
+}
@@ -0,0 +1,39 @@
+mod common;
+
+use tv::grep::{GrepOptions, grep_dir};
+
+#[test]
+fn test_grep_reports_structural_addresses() {
+    let files = vec![
+        ("main.tf", common::SIMPLE_MODULE_TF),
+        ("versions.tf", common::TERRAFORM_BLOCK_TF),
+    ];
+    let temp_dir = common::create_test_dir_with_files(&files);
+
+    let matches = grep_dir("terraform-aws-vpc", temp_dir.path(), &GrepOptions::default()).unwrap();
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].address, "module.vpc.source");
+    assert_eq!(matches[0].line, 2);
+    assert!(matches[0].file.ends_with("main.tf"));
+
+    // Object values are searched key by key
+    let matches = grep_dir("hashicorp", temp_dir.path(), &GrepOptions::default()).unwrap();
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].address, "terraform.required_providers.aws.source");
+    assert_eq!(matches[0].value, "\"hashicorp/aws\"");
+}
+
+#[test]
+fn test_grep_ignore_case_and_quoted_labels() {
+    let content = "module \"my.module\" {\n  source = \"./Modules/VPC\"\n  name   = \"vpc\"\n}\n";
+    let temp_dir = common::create_test_dir_with_files(&[("main.tf", content)]);
+
+    let matches = grep_dir("vpc", temp_dir.path(), &GrepOptions::default()).unwrap();
+    let addresses: Vec<&str> = matches.iter().map(|m| m.address.as_str()).collect();
+    assert_eq!(addresses, vec!["module.\"my.module\".name"]);
+
+    let options = GrepOptions { ignore_case: true };
+    let matches = grep_dir("vpc", temp_dir.path(), &options).unwrap();
+    assert_eq!(matches.len(), 2);
+    assert_eq!(matches[0].address, "module.\"my.module\".source");
+}
@@ -0,0 +1,99 @@
+mod common;
+
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+use tv::history::{self, Change};
+use tv::journal::{self, Transaction};
+use tv::set_value;
+
+fn set_in_transaction(journal_dir: &std::path::Path, query: &str, value: &str, file: &std::path::Path) {
+    // Transaction IDs only sort by the second
+    std::thread::sleep(Duration::from_millis(1100));
+    journal::activate(Transaction::begin(journal_dir, &format!("tv set {} {}", query, value)));
+    set_value(query, value, Some(file)).unwrap();
+    journal::deactivate();
+}
+
+fn change(time: u64, old: &str, new: &str) -> Change {
+    Change {
+        time,
+        txn: time.to_string(),
+        command: Some("tv set".to_string()),
+        path: PathBuf::from("main.tf"),
+        address: "module.vpc.version".to_string(),
+        old: Some(old.to_string()),
+        new: Some(new.to_string()),
+    }
+}
+
+#[test]
+fn test_module_pins() {
+    let pins = history::module_pins(&format!("{}\n{}", common::SIMPLE_MODULE_TF, r#"module "eks" {
+  source  = "terraform-aws-modules/eks/aws"
+  version = "~> 20.0"
+}
+"#));
+    assert_eq!(pins.get("module.vpc.source[\"ref\"]").map(String::as_str), Some("v5.0.0"));
+    assert_eq!(pins.get("module.eks.version").map(String::as_str), Some("~> 20.0"));
+    assert_eq!(pins.len(), 2);
+}
+
+#[test]
+fn test_history_includes_changes_outside_tv() {
+    let (_dir, file) = common::create_test_tf_file(common::REGISTRY_MODULE_TF);
+    let journal_dir = tempfile::TempDir::new().unwrap();
+
+    set_in_transaction(journal_dir.path(), "module.vpc.version", "5.1.0", &file);
+    // Another tool puts it back
+    fs::write(&file, common::REGISTRY_MODULE_TF).unwrap();
+    set_in_transaction(journal_dir.path(), "module.vpc.version", "5.1.0", &file);
+
+    let changes = history::history(journal_dir.path()).unwrap();
+    let values: Vec<_> = changes.iter().map(|c| (c.old.as_deref(), c.new.as_deref(), c.command.is_some())).collect();
+    assert_eq!(
+        values,
+        vec![
+            (Some("5.0.0"), Some("5.1.0"), true),
+            (Some("5.1.0"), Some("5.0.0"), false),
+            (Some("5.0.0"), Some("5.1.0"), true),
+        ]
+    );
+    assert!(changes.iter().all(|c| c.address == "module.vpc.version"));
+
+    let flaps = history::flapping(&changes, Duration::from_secs(3600));
+    assert_eq!(flaps.len(), 1);
+    assert_eq!(flaps[0].reverts, 2);
+    assert_eq!(flaps[0].changes.len(), 3);
+    let rendered = history::render(&flaps, |p| p.display().to_string());
+    assert!(rendered.contains("module.vpc.version changed 3 times"));
+    assert!(rendered.contains("\"5.0.0\" -> \"5.1.0\" -> \"5.0.0\" -> \"5.1.0\""));
+    assert!(rendered.contains("changed outside tv"));
+}
+
+#[test]
+fn test_history_is_cached() {
+    let (_dir, file) = common::create_test_tf_file(common::REGISTRY_MODULE_TF);
+    let journal_dir = tempfile::TempDir::new().unwrap();
+    set_in_transaction(journal_dir.path(), "module.vpc.version", "5.1.0", &file);
+
+    let first = history::history(journal_dir.path()).unwrap();
+    assert!(journal_dir.path().join("history.json").is_file());
+    // Cached pins are used even once the snapshots are gone
+    let txn = journal::list_transactions(journal_dir.path()).unwrap().remove(0);
+    fs::remove_file(journal_dir.path().join(&txn.id).join("0.after")).unwrap();
+    assert_eq!(history::history(journal_dir.path()).unwrap(), first);
+}
+
+#[test]
+fn test_flapping_needs_a_revert_within_the_window() {
+    // Steady upgrades never flap
+    let upgrades = vec![change(0, "1.0.0", "1.1.0"), change(60, "1.1.0", "1.2.0")];
+    assert!(history::flapping(&upgrades, Duration::from_secs(3600)).is_empty());
+
+    let reverted = vec![change(0, "1.0.0", "1.1.0"), change(7200, "1.1.0", "1.0.0")];
+    assert!(history::flapping(&reverted, Duration::from_secs(3600)).is_empty());
+    let flaps = history::flapping(&reverted, Duration::from_secs(86400));
+    assert_eq!(flaps.len(), 1);
+    assert_eq!(flaps[0].reverts, 1);
+}
@@ -0,0 +1,134 @@
+mod common;
+
+use std::fs;
+use tv::index::{build, default_index_path, load, save, update};
+use tv::{ScanOptions, get_typed_value_indexed, scan_files, scan_files_with_options};
+
+fn indexed_options() -> ScanOptions {
+    ScanOptions { use_index: true, ..Default::default() }
+}
+
+#[test]
+fn test_index_records_blocks_attributes_and_lines() {
+    let files = vec![
+        ("main.tf", common::SIMPLE_MODULE_TF),
+        ("versions.tf", common::TERRAFORM_BLOCK_TF),
+    ];
+    let temp_dir = common::create_test_dir_with_files(&files);
+
+    let index = build(temp_dir.path()).unwrap();
+    assert_eq!(index.files.len(), 2);
+
+    let versions = index.files.iter().find(|f| f.path.ends_with("versions.tf")).unwrap();
+    let terraform = &versions.blocks[0];
+    assert_eq!(terraform.block_type, "terraform");
+    assert_eq!(terraform.label, None);
+    assert_eq!(terraform.start_line, 1);
+    assert!(terraform.attributes.iter().any(|a| a.name == "required_providers.aws"));
+
+    let main = index.files.iter().find(|f| f.path.ends_with("main.tf")).unwrap();
+    assert_eq!(main.blocks[0].label.as_deref(), Some("vpc"));
+    assert!(main.blocks[0].attributes.iter().any(|a| a.name == "source" && a.line == 2));
+}
+
+#[test]
+fn test_scan_with_index_matches_plain_scan() {
+    let files = vec![
+        ("main.tf", common::MULTIPLE_MODULES_TF),
+        ("versions.tf", common::TERRAFORM_BLOCK_TF),
+        ("nested/registry.tf", common::REGISTRY_MODULE_TF),
+    ];
+    let temp_dir = common::create_test_dir_with_files(&files);
+    save(&build(temp_dir.path()).unwrap(), &default_index_path(temp_dir.path())).unwrap();
+
+    for query in ["module.*", "module.vpc.source", "module.*.version", "terraform.required_providers.aws"] {
+        let plain = scan_files(query, temp_dir.path()).unwrap();
+        let indexed = scan_files_with_options(query, temp_dir.path(), &indexed_options()).unwrap();
+        assert_eq!(plain, indexed, "query {}", query);
+    }
+}
+
+#[test]
+fn test_scan_with_index_parses_changed_files() {
+    let files = vec![("main.tf", common::SIMPLE_MODULE_TF)];
+    let temp_dir = common::create_test_dir_with_files(&files);
+    save(&build(temp_dir.path()).unwrap(), &default_index_path(temp_dir.path())).unwrap();
+
+    // A changed and a new file are not in the index as they are now
+    fs::write(temp_dir.path().join("main.tf"), "module \"dns\" {\n  source = \"./dns\"\n}\n").unwrap();
+    fs::write(temp_dir.path().join("new.tf"), "module \"db\" {\n  source = \"./db\"\n}\n").unwrap();
+
+    let results = scan_files_with_options("module.*", temp_dir.path(), &indexed_options()).unwrap();
+    let mut names: Vec<&str> = results.iter().map(|(_, name)| name.as_str()).collect();
+    names.sort();
+    assert_eq!(names, vec!["db", "dns"]);
+}
+
+#[test]
+fn test_get_with_index_searches_directory() {
+    let files = vec![
+        ("main.tf", common::SIMPLE_MODULE_TF),
+        ("versions.tf", common::TERRAFORM_BLOCK_TF),
+    ];
+    let temp_dir = common::create_test_dir_with_files(&files);
+    let path = default_index_path(temp_dir.path());
+    save(&build(temp_dir.path()).unwrap(), &path).unwrap();
+    assert_eq!(load(&path).unwrap().files.len(), 2);
+
+    let version = get_typed_value_indexed("terraform.required_providers.aws.version", temp_dir.path()).unwrap();
+    assert!(version.is_some());
+    let missing = get_typed_value_indexed("module.missing.source", temp_dir.path()).unwrap();
+    assert!(missing.is_none());
+}
+
+#[test]
+fn test_scan_with_index_requires_index() {
+    let files = vec![("main.tf", common::SIMPLE_MODULE_TF)];
+    let temp_dir = common::create_test_dir_with_files(&files);
+    assert!(scan_files_with_options("module.*", temp_dir.path(), &indexed_options()).is_err());
+}
+
+#[test]
+fn test_update_reparses_only_changed_files() {
+    let files = vec![
+        ("main.tf", common::SIMPLE_MODULE_TF),
+        ("versions.tf", common::TERRAFORM_BLOCK_TF),
+        ("old.tf", common::REGISTRY_MODULE_TF),
+    ];
+    let temp_dir = common::create_test_dir_with_files(&files);
+    let mut index = build(temp_dir.path()).unwrap();
+
+    // Rewritten with the same content: only the stamp is refreshed
+    fs::write(temp_dir.path().join("versions.tf"), common::TERRAFORM_BLOCK_TF).unwrap();
+    fs::write(temp_dir.path().join("main.tf"), "module \"dns\" {\n  source = \"./dns\"\n}\n").unwrap();
+    fs::write(temp_dir.path().join("new.tf"), "module \"db\" {\n  source = \"./db\"\n}\n").unwrap();
+    fs::remove_file(temp_dir.path().join("old.tf")).unwrap();
+
+    let report = update(temp_dir.path(), &mut index).unwrap();
+    let mut reparsed: Vec<String> = report.reparsed.iter().map(|p| p.display().to_string()).collect();
+    reparsed.sort();
+    assert_eq!(reparsed, vec!["main.tf", "new.tf"]);
+    assert_eq!(report.removed, vec![std::path::PathBuf::from("old.tf")]);
+    assert_eq!(report.unchanged, 1);
+
+    let main = index.file(temp_dir.path(), &temp_dir.path().join("main.tf")).unwrap();
+    assert_eq!(main.blocks[0].label.as_deref(), Some("dns"));
+    assert!(index.fresh_file(temp_dir.path(), &temp_dir.path().join("versions.tf")).is_some());
+}
+
+#[test]
+fn test_update_uses_git_status_for_same_stamp_edits() {
+    let files = vec![("main.tf", "module \"aaa\" {\n  source = \"./aaa\"\n}\n")];
+    let temp_dir = common::create_git_repo(&files);
+    let mut index = build(temp_dir.path()).unwrap();
+
+    // Same size and modification time, different content
+    let path = temp_dir.path().join("main.tf");
+    let modified = fs::metadata(&path).unwrap().modified().unwrap();
+    fs::write(&path, "module \"bbb\" {\n  source = \"./bbb\"\n}\n").unwrap();
+    fs::File::options().write(true).open(&path).unwrap().set_modified(modified).unwrap();
+
+    let report = update(temp_dir.path(), &mut index).unwrap();
+    assert_eq!(report.reparsed.len(), 1);
+    assert_eq!(index.files[0].blocks[0].label.as_deref(), Some("bbb"));
+}
@@ -0,0 +1,80 @@
+mod common;
+
+use std::fs;
+use tv::indirection::{Scope, as_reference, find_reference};
+use tv::{get_typed_value, get_typed_value_resolved, set_value};
+
+const MAIN_TF: &str = r#"module "vpc" {
+  source = "git::https://github.com/org/vpc.git?ref=${local.module_versions["vpc"]}"
+}
+
+module "rds" {
+  source  = "terraform-aws-modules/rds/aws"
+  version = var.rds_version
+}
+"#;
+
+const LOCALS_TF: &str = r#"locals {
+  module_versions = {
+    vpc = "v1.0.0"
+  }
+}
+
+variable "rds_version" {
+  type    = string
+  default = "6.0.0"
+}
+"#;
+
+#[test]
+fn test_find_reference() {
+    let reference = find_reference(r#"?ref=${local.module_versions["vpc"]}"#).unwrap();
+    assert_eq!(reference.scope, Scope::Local);
+    assert_eq!(reference.name, "module_versions");
+    assert_eq!(reference.key.as_deref(), Some("vpc"));
+    assert_eq!(reference.text, r#"local.module_versions["vpc"]"#);
+
+    assert!(as_reference(r#"${local.module_versions["vpc"]}"#).is_some());
+    assert_eq!(as_reference("var.rds_version").unwrap().scope, Scope::Var);
+    assert!(as_reference(r#"git::x?ref=${local.v["a"]}"#).is_none());
+    assert!(find_reference("nonlocal.value").is_none());
+}
+
+#[test]
+fn test_get_resolves_locals_and_variables() {
+    let temp_dir = common::create_test_dir_with_files(&[("main.tf", MAIN_TF), ("locals.tf", LOCALS_TF)]);
+    let main = temp_dir.path().join("main.tf");
+
+    let raw = get_typed_value(r#"module.vpc.source["ref"]"#, Some(&main)).unwrap().unwrap();
+    assert_eq!(raw.value, r#"${local.module_versions["vpc"]}"#);
+    let resolved = get_typed_value_resolved(r#"module.vpc.source["ref"]"#, Some(&main)).unwrap().unwrap();
+    assert_eq!(resolved.value, "v1.0.0");
+    let source = get_typed_value_resolved("module.vpc.source", Some(&main)).unwrap().unwrap();
+    assert_eq!(source.value, "git::https://github.com/org/vpc.git?ref=v1.0.0");
+
+    // Variable default, then tfvars, which takes precedence
+    let version = get_typed_value_resolved("module.rds.version", Some(&main)).unwrap().unwrap();
+    assert_eq!(version.value, "6.0.0");
+    fs::write(temp_dir.path().join("terraform.tfvars"), "rds_version = \"6.5.0\"\n").unwrap();
+    let version = get_typed_value_resolved("module.rds.version", Some(&main)).unwrap().unwrap();
+    assert_eq!(version.value, "6.5.0");
+}
+
+#[test]
+fn test_set_updates_referenced_definition() {
+    let temp_dir = common::create_test_dir_with_files(&[
+        ("main.tf", MAIN_TF),
+        ("locals.tf", LOCALS_TF),
+        ("terraform.tfvars", "rds_version = \"6.5.0\"\n"),
+    ]);
+    let main = temp_dir.path().join("main.tf");
+
+    set_value(r#"module.vpc.source["ref"]"#, "v2.0.0", Some(&main)).unwrap();
+    set_value("module.rds.version", "7.0.0", Some(&main)).unwrap();
+
+    assert_eq!(fs::read_to_string(&main).unwrap(), MAIN_TF);
+    let locals = fs::read_to_string(temp_dir.path().join("locals.tf")).unwrap();
+    assert!(locals.contains(r#"vpc = "v2.0.0""#));
+    let tfvars = fs::read_to_string(temp_dir.path().join("terraform.tfvars")).unwrap();
+    assert_eq!(tfvars, "rds_version = \"7.0.0\"\n");
+}
@@ -0,0 +1,125 @@
+mod common;
+
+use std::process::Command;
+use tv::impact::{Impact, annotate_removed, parse_interface, upgrade_impact};
+use tv::info::{fetch_readme, module_info};
+use tv::registry::Client;
+
+#[test]
+fn test_info_git_module_readme_at_pinned_ref() {
+    let remote = common::create_git_repo(&[("README.md", "# Root\n"), ("modules/vpc/README.md", "# VPC v1\n")]);
+    let git = |args: &[&str]| {
+        let status = Command::new("git")
+            .args(["-c", "user.name=tv", "-c", "user.email=tv@example.com"])
+            .args(args)
+            .current_dir(remote.path())
+            .status()
+            .unwrap();
+        assert!(status.success());
+    };
+    git(&["tag", "v1.0.0"]);
+    std::fs::write(remote.path().join("modules/vpc/README.md"), "# VPC v2\n").unwrap();
+    git(&["commit", "--quiet", "-am", "v2 docs"]);
+
+    let (_dir, file) = common::create_test_tf_file(&format!(
+        "module \"vpc\" {{\n  source = \"git::file://{}//modules/vpc?ref=v1.0.0\"\n}}\n",
+        remote.path().display()
+    ));
+    let info = module_info("module.vpc", Some(&file)).unwrap();
+    assert_eq!(info.pinned(), Some("v1.0.0"));
+
+    let cache = tempfile::TempDir::new().unwrap();
+    let readme = fetch_readme(&info, &Client::with_base_url(None), cache.path()).unwrap();
+    assert_eq!(readme.as_deref(), Some("# VPC v1\n"));
+
+    assert!(module_info("module.other", Some(&file)).is_err());
+    assert!(module_info("resource.aws_vpc.main", Some(&file)).is_err());
+}
+
+#[test]
+fn test_info_registry_module_readme() {
+    let registry = Client::with_base_url(Some(common::serve_http(vec![
+        (
+            "/v1/modules/terraform-aws-modules/vpc/aws/5.1.0",
+            r##"{"root": {"readme": "# VPC 5.1.0"}, "submodules": [{"path": "modules/vpc-endpoints", "readme": "# Endpoints"}]}"##.to_string(),
+        ),
+        ("/v1/modules/terraform-aws-modules/vpc/aws", r##"{"root": {"readme": "# VPC latest"}}"##.to_string()),
+    ])));
+    let cache = tempfile::TempDir::new().unwrap();
+    let readme_of = |content: &str| {
+        let (_dir, file) = common::create_test_tf_file(content);
+        fetch_readme(&module_info("module.vpc", Some(&file)).unwrap(), &registry, cache.path()).unwrap()
+    };
+
+    let pinned = readme_of("module \"vpc\" {\n  source  = \"terraform-aws-modules/vpc/aws\"\n  version = \"5.1.0\"\n}\n");
+    assert_eq!(pinned.as_deref(), Some("# VPC 5.1.0"));
+    // A range is not a pin: the latest version's README
+    let ranged = readme_of("module \"vpc\" {\n  source  = \"terraform-aws-modules/vpc/aws\"\n  version = \"~> 5.0\"\n}\n");
+    assert_eq!(ranged.as_deref(), Some("# VPC latest"));
+    let submodule = readme_of(
+        "module \"vpc\" {\n  source  = \"terraform-aws-modules/vpc/aws//modules/vpc-endpoints\"\n  version = \"5.1.0\"\n}\n",
+    );
+    assert_eq!(submodule.as_deref(), Some("# Endpoints"));
+}
+
+#[test]
+fn test_upgrade_impact_flags_removed_and_missing_inputs() {
+    let (_dir, file) = common::create_test_tf_file(
+        "module \"vpc\" {\n  source   = \"terraform-aws-modules/vpc/aws\"\n  version  = \"5.0.0\"\n  count    = 1\n  old_flag = true\n  cidr     = \"10.0.0.0/16\"\n}\n",
+    );
+    let info = module_info("module.vpc", Some(&file)).unwrap();
+    assert_eq!(info.arguments, vec!["old_flag", "cidr"]);
+
+    let registry = Client::with_base_url(Some(common::serve_http(vec![
+        (
+            "/v1/modules/terraform-aws-modules/vpc/aws/5.0.0",
+            r#"{"root": {"inputs": [{"name": "cidr", "required": false}, {"name": "old_flag", "required": false}], "outputs": [{"name": "vpc_id"}]}}"#.to_string(),
+        ),
+        (
+            "/v1/modules/terraform-aws-modules/vpc/aws/6.0.0",
+            r#"{"root": {"inputs": [{"name": "cidr", "required": false}, {"name": "name", "required": true}], "outputs": [{"name": "vpc_id"}, {"name": "arn"}]}}"#.to_string(),
+        ),
+    ])));
+    let cache = tempfile::TempDir::new().unwrap();
+    let impact = upgrade_impact(&info, "6.0.0", &registry, cache.path()).unwrap();
+    assert_eq!(impact.from, "5.0.0");
+    assert_eq!(impact.removed_inputs, vec!["old_flag"]);
+    assert_eq!(impact.added_inputs, vec![("name".to_string(), true)]);
+    assert_eq!(impact.added_outputs, vec!["arn"]);
+    assert_eq!(impact.passed_removed, vec!["old_flag"]);
+    assert_eq!(impact.missing_required, vec!["name"]);
+    assert!(impact.breaking());
+}
+
+#[test]
+fn test_parse_interface_from_module_files() {
+    let interface = parse_interface(&[
+        "variable \"name\" {}\nvariable \"tags\" {\n  default = {}\n}\n".to_string(),
+        "output \"id\" {\n  value = 1\n}\n".to_string(),
+    ])
+    .unwrap();
+    assert_eq!(interface.inputs.get("name"), Some(&true));
+    assert_eq!(interface.inputs.get("tags"), Some(&false));
+    assert!(interface.outputs.contains("id"));
+}
+
+#[test]
+fn test_annotate_removed_inputs() {
+    let (_dir, file) = common::create_test_tf_file(
+        "module \"vpc\" {\n  source   = \"terraform-aws-modules/vpc/aws\"\n  version  = \"5.0.0\"\n  old_flag = true # legacy\n  cidr     = \"10.0.0.0/16\"\n}\n",
+    );
+    let info = module_info("module.vpc", Some(&file)).unwrap();
+    let impact = Impact {
+        module: "vpc".to_string(),
+        to: "6.0.0".to_string(),
+        passed_removed: vec!["old_flag".to_string()],
+        ..Default::default()
+    };
+    assert_eq!(annotate_removed(&info, &impact).unwrap(), 1);
+    // A second run leaves the marked line alone
+    assert_eq!(annotate_removed(&info, &impact).unwrap(), 0);
+    assert_eq!(
+        std::fs::read_to_string(&file).unwrap(),
+        "module \"vpc\" {\n  source   = \"terraform-aws-modules/vpc/aws\"\n  version  = \"5.0.0\"\n  old_flag = true # legacy # tv: removed in 6.0.0\n  cidr     = \"10.0.0.0/16\"\n}\n"
+    );
+}
@@ -0,0 +1,79 @@
+mod common;
+
+use tv::config::load_config;
+use tv::find_all_tf_files;
+use tv::init::{Ci, InitOptions, init};
+use tv::policy::{load_policy, validate};
+use tv::registry::Client;
+
+const MODULES_TF: &str = r#"module "tagged" {
+  source = "git::https://github.com/org/eks.git?ref=v19.0.0"
+}
+
+module "branch" {
+  source = "git::https://github.com/org/net.git?ref=main"
+}
+
+module "vpc" {
+  source  = "terraform-aws-modules/vpc/aws"
+  version = "5.0.0"
+}
+"#;
+
+#[test]
+fn test_init_creates_working_files() {
+    let temp_dir = common::create_test_dir_with_files(&[("main.tf", MODULES_TF)]);
+    let dir = temp_dir.path();
+    let options = InitOptions { ci: Some(Ci::GitHub), ..Default::default() };
+
+    let created = init(dir, &options).unwrap();
+    assert_eq!(created.len(), 4);
+    assert!(created.iter().all(|c| !c.existed));
+    assert!(dir.join(".github/workflows/tv.yml").is_file());
+
+    // The files are valid for the commands that read them
+    let config = load_config(dir).unwrap();
+    assert_eq!(config.minimum_release_age.as_deref(), Some("7d"));
+    let policy = load_policy(&dir.join("tv-policy.hcl")).unwrap();
+    let reports = validate(&policy, dir, &Client::with_base_url(None)).unwrap();
+    assert_eq!(reports[0].checked, 2);
+    assert_eq!(reports[0].violations.len(), 1);
+    assert_eq!(reports[0].violations[0].block, "module.branch");
+}
+
+#[test]
+fn test_init_keeps_existing_files() {
+    let temp_dir = common::create_test_dir_with_files(&[("tv.toml", "minimum_release_age = \"1d\"\n")]);
+    let dir = temp_dir.path();
+    let options = InitOptions { policy: false, ignore: false, ci: Some(Ci::GitLab), force: false };
+
+    let created = init(dir, &options).unwrap();
+    assert_eq!(created.iter().map(|c| c.existed).collect::<Vec<_>>(), vec![true, false]);
+    assert_eq!(std::fs::read_to_string(dir.join("tv.toml")).unwrap(), "minimum_release_age = \"1d\"\n");
+    assert!(dir.join(".gitlab/ci/tv.yml").is_file());
+    assert!(!dir.join("tv-policy.hcl").exists());
+
+    init(dir, &InitOptions { force: true, ..options }).unwrap();
+    assert!(std::fs::read_to_string(dir.join("tv.toml")).unwrap().contains("7d"));
+
+    assert!("jenkins".parse::<Ci>().is_err());
+}
+
+#[test]
+fn test_tvignore_skips_paths() {
+    let temp_dir = common::create_test_dir_with_files(&[
+        ("main.tf", ""),
+        (".terraform/modules/vpc/main.tf", ""),
+        ("envs/sandbox/main.tf", ""),
+        ("envs/prod/main.tf", ""),
+        ("envs/prod/backend_generated.tf", ""),
+        (".tvignore", "# generated\n.terraform/\nenvs/sandbox\n*_generated.tf\n"),
+    ]);
+    let dir = temp_dir.path();
+    let files: Vec<String> = find_all_tf_files(dir)
+        .unwrap()
+        .iter()
+        .map(|f| f.strip_prefix(dir).unwrap().to_string_lossy().to_string())
+        .collect();
+    assert_eq!(files, vec!["envs/prod/main.tf", "main.tf"]);
+}
@@ -0,0 +1,56 @@
+mod common;
+
+use std::path::Path;
+use tv::inventory::{list_modules, render};
+
+const MAIN_TF: &str = r#"module "vpc" {
+  source  = "terraform-aws-modules/vpc/aws"
+  version = "~> 5.1"
+}
+
+module "web" {
+  source = "git::https://github.com/org/app.git//modules/web?ref=v1.2.0"
+}
+
+module "shared" {
+  source = local.shared_source
+}
+"#;
+
+#[test]
+fn test_list_modules() {
+    let temp_dir = common::create_test_dir_with_files(&[
+        ("envs/prod/main.tf", MAIN_TF),
+        ("envs/dev/main.tf", "module \"db\" {\n  source = \"../../modules/db\"\n}\n"),
+    ]);
+    let entries = list_modules(temp_dir.path()).unwrap();
+    let labels: Vec<&str> = entries.iter().map(|e| e.label.as_str()).collect();
+    assert_eq!(labels, vec!["db", "vpc", "web", "shared"]);
+
+    let vpc = &entries[1];
+    assert_eq!(vpc.url.as_deref(), Some("terraform-aws-modules/vpc/aws"));
+    assert_eq!((vpc.git_ref.as_deref(), vpc.version.as_deref()), (None, Some("~> 5.1")));
+
+    let web = &entries[2];
+    assert_eq!(web.source.as_deref(), Some("git::https://github.com/org/app.git//modules/web?ref=v1.2.0"));
+    assert_eq!(web.url.as_deref(), Some("git::https://github.com/org/app.git"));
+    assert_eq!((web.path.as_deref(), web.git_ref.as_deref()), (Some("modules/web"), Some("v1.2.0")));
+
+    // A source that is not a literal is shown as written
+    let shared = &entries[3];
+    assert_eq!(shared.url.as_deref(), Some("local.shared_source"));
+    assert_eq!(shared.path, None);
+}
+
+#[test]
+fn test_render_table() {
+    let temp_dir = common::create_test_dir_with_files(&[("main.tf", MAIN_TF)]);
+    let entries = list_modules(temp_dir.path()).unwrap();
+    let table = render(&entries[..2], |_: &Path| "main.tf".to_string());
+    assert_eq!(
+        table,
+        "file     module  url                                  path         ref     version\n\
+         main.tf  vpc     terraform-aws-modules/vpc/aws        -            -       ~> 5.1\n\
+         main.tf  web     git::https://github.com/org/app.git  modules/web  v1.2.0  -\n"
+    );
+}
@@ -0,0 +1,82 @@
+mod common;
+
+use std::fs;
+use tv::journal::{self, Transaction};
+use tv::{get_value, set_value};
+
+#[test]
+fn test_writes_in_one_transaction_share_an_id() {
+    let temp_dir = common::create_test_dir_with_files(&[
+        ("a/main.tf", common::SIMPLE_MODULE_TF),
+        ("b/main.tf", common::SIMPLE_MODULE_TF),
+    ]);
+    let journal_dir = tempfile::TempDir::new().unwrap();
+
+    journal::activate(Transaction::begin(journal_dir.path(), "tv set"));
+    set_value("module.vpc.name", "a", Some(&temp_dir.path().join("a/main.tf"))).unwrap();
+    set_value("module.vpc.name", "b", Some(&temp_dir.path().join("b/main.tf"))).unwrap();
+    let txn = journal::deactivate().unwrap();
+
+    let transactions = journal::list_transactions(journal_dir.path()).unwrap();
+    assert_eq!(transactions.len(), 1);
+    assert_eq!(transactions[0].id, txn.id());
+    assert_eq!(transactions[0].entries.len(), 2);
+    assert_eq!(transactions[0].command, "tv set");
+}
+
+#[test]
+fn test_undo_restores_every_file() {
+    let temp_dir = common::create_test_dir_with_files(&[
+        ("a/main.tf", common::SIMPLE_MODULE_TF),
+        ("b/main.tf", common::SIMPLE_MODULE_TF),
+    ]);
+    let journal_dir = tempfile::TempDir::new().unwrap();
+    let a = temp_dir.path().join("a/main.tf");
+    let b = temp_dir.path().join("b/main.tf");
+
+    journal::activate(Transaction::begin(journal_dir.path(), "tv set"));
+    set_value("module.vpc.name", "a", Some(&a)).unwrap();
+    set_value("module.vpc.name", "b", Some(&b)).unwrap();
+    // A second write to the same file keeps the original backup
+    set_value("module.vpc.name", "b2", Some(&b)).unwrap();
+    let txn = journal::deactivate().unwrap();
+    assert_eq!(txn.meta.entries.len(), 2);
+
+    let restored = journal::undo_transaction(journal_dir.path(), txn.id(), false).unwrap();
+    assert_eq!(restored.len(), 2);
+    assert_eq!(fs::read_to_string(&a).unwrap(), common::SIMPLE_MODULE_TF);
+    assert_eq!(fs::read_to_string(&b).unwrap(), common::SIMPLE_MODULE_TF);
+
+    // Undoing twice is an error
+    assert!(journal::undo_transaction(journal_dir.path(), txn.id(), false).is_err());
+    assert!(journal::load_transaction(journal_dir.path(), txn.id()).unwrap().undone);
+}
+
+#[test]
+fn test_undo_refuses_when_file_changed_afterwards() {
+    let (_dir, file) = common::create_test_tf_file(common::SIMPLE_MODULE_TF);
+    let journal_dir = tempfile::TempDir::new().unwrap();
+
+    journal::activate(Transaction::begin(journal_dir.path(), "tv set"));
+    set_value("module.vpc.name", "journaled", Some(file.as_path())).unwrap();
+    let txn = journal::deactivate().unwrap();
+
+    // An edit outside the transaction
+    set_value("module.vpc.name", "later", Some(file.as_path())).unwrap();
+
+    assert!(journal::undo_transaction(journal_dir.path(), txn.id(), false).is_err());
+    journal::undo_transaction(journal_dir.path(), txn.id(), true).unwrap();
+    assert_eq!(get_value("module.vpc.name", Some(file.as_path())).unwrap(), Some("my-vpc".to_string()));
+}
+
+#[test]
+fn test_no_active_transaction_writes_nothing() {
+    let (_dir, file) = common::create_test_tf_file(common::SIMPLE_MODULE_TF);
+    let journal_dir = tempfile::TempDir::new().unwrap();
+
+    journal::activate(Transaction::begin(journal_dir.path(), "tv get"));
+    get_value("module.vpc.name", Some(file.as_path())).unwrap();
+    journal::deactivate();
+
+    assert!(journal::list_transactions(journal_dir.path()).unwrap().is_empty());
+}
@@ -0,0 +1,71 @@
+mod common;
+
+use tv::labels::{Selector, directory_labels, parse_labels};
+use tv::{ScanOptions, scan_files_with_options};
+
+const MODULE_TF: &str = "module \"vpc\" {\n  source = \"git::https://github.com/org/vpc.git?ref=v1.0.0\"\n}\n";
+
+const TV_TOML: &str = "[labels]\n\"teams/payments*\" = \"team=payments\"\n\"teams/*/prod\" = \"tier=prod\"\n";
+
+#[test]
+fn test_parse_labels_and_selectors() {
+    let labels = parse_labels("team=payments, tier=prod\n# owner\nregion = eu-west-1\n").unwrap();
+    assert_eq!(labels.len(), 3);
+    assert_eq!(labels["region"], "eu-west-1");
+    assert!(parse_labels("team").is_err());
+    assert!(parse_labels("team=pay ments").is_err());
+
+    let selector: Selector = "team=payments,tier!=dev".parse().unwrap();
+    assert!(selector.matches(&labels));
+    // != also holds when the label is absent
+    assert!(selector.matches(&parse_labels("team=payments").unwrap()));
+    assert!(!selector.matches(&parse_labels("team=payments, tier=dev").unwrap()));
+
+    let exists: Selector = "region,!legacy".parse().unwrap();
+    assert!(exists.matches(&labels));
+    assert!(!exists.matches(&parse_labels("region=us, legacy=true").unwrap()));
+    assert!("".parse::<Selector>().is_err());
+    assert!("team=a b".parse::<Selector>().is_err());
+}
+
+#[test]
+fn test_labels_are_inherited_and_overridden() {
+    let temp_dir = common::create_test_dir_with_files(&[
+        ("tv.toml", TV_TOML),
+        ("teams/payments/prod/main.tf", MODULE_TF),
+        ("teams/payments/dev/tv.labels", "tier=dev\n"),
+        ("teams/payments/dev/main.tf", MODULE_TF),
+        ("teams/search/prod/tv.labels", "team=search, tier=staging"),
+        ("teams/search/prod/main.tf", MODULE_TF),
+    ]);
+    let labels = |dir: &str| directory_labels(&temp_dir.path().join(dir)).unwrap();
+
+    assert_eq!(labels("teams/payments/prod"), parse_labels("team=payments, tier=prod").unwrap());
+    assert_eq!(labels("teams/payments/dev"), parse_labels("team=payments, tier=dev").unwrap());
+    // The marker file overrides tv.toml for its directory
+    assert_eq!(labels("teams/search/prod"), parse_labels("team=search, tier=staging").unwrap());
+    assert!(labels(".").is_empty());
+}
+
+#[test]
+fn test_scan_with_selector() {
+    let temp_dir = common::create_test_dir_with_files(&[
+        ("tv.toml", TV_TOML),
+        ("teams/payments/prod/main.tf", MODULE_TF),
+        ("teams/payments/dev/tv.labels", "tier=dev\n"),
+        ("teams/payments/dev/main.tf", MODULE_TF),
+        ("teams/search/prod/main.tf", MODULE_TF),
+    ]);
+    let scan = |selector: &str| {
+        let options = ScanOptions { selector: Some(selector.parse().unwrap()), ..Default::default() };
+        scan_files_with_options("module.*", temp_dir.path(), &options)
+            .unwrap()
+            .into_iter()
+            .map(|(file, _)| file.strip_prefix(temp_dir.path()).unwrap().display().to_string())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(scan("team=payments,tier!=dev"), vec!["teams/payments/prod/main.tf"]);
+    assert_eq!(scan("tier=prod"), vec!["teams/payments/prod/main.tf", "teams/search/prod/main.tf"]);
+    assert_eq!(scan("!team"), vec!["teams/search/prod/main.tf"]);
+}
@@ -0,0 +1,124 @@
+mod common;
+
+use std::fs;
+use tv::list::{append_item, remove_item};
+use tv::{get_value, set_value};
+
+const ONE_LINE_LIST_TF: &str = r#"module "vpc" {
+  source = "terraform-aws-modules/vpc/aws"
+  azs    = ["us-east-1a", "us-east-1b"]
+}
+"#;
+
+const MULTI_LINE_LIST_TF: &str = r#"module "vpc" {
+  source = "terraform-aws-modules/vpc/aws"
+  azs = [
+    "us-east-1a",
+    "us-east-1b",
+  ]
+}
+"#;
+
+const NO_TRAILING_COMMA_LIST_TF: &str = r#"module "vpc" {
+  azs = [
+    "us-east-1a",
+    "us-east-1b"
+  ]
+}
+"#;
+
+#[test]
+fn test_append_one_line_list() {
+    let (_dir, file) = common::create_test_tf_file(ONE_LINE_LIST_TF);
+
+    append_item("module.vpc.azs", "\"us-east-1c\"", Some(file.as_path())).unwrap();
+    let content = fs::read_to_string(&file).unwrap();
+    assert!(content.contains(r#"azs    = ["us-east-1a", "us-east-1b", "us-east-1c"]"#));
+}
+
+#[test]
+fn test_append_multi_line_list() {
+    let (_dir, file) = common::create_test_tf_file(MULTI_LINE_LIST_TF);
+
+    append_item("module.vpc.azs", "\"us-east-1c\"", Some(file.as_path())).unwrap();
+    let content = fs::read_to_string(&file).unwrap();
+    assert!(content.contains("    \"us-east-1b\",\n    \"us-east-1c\",\n  ]"));
+}
+
+#[test]
+fn test_append_without_trailing_comma() {
+    let (_dir, file) = common::create_test_tf_file(NO_TRAILING_COMMA_LIST_TF);
+
+    append_item("module.vpc.azs", "\"us-east-1c\"", Some(file.as_path())).unwrap();
+    let content = fs::read_to_string(&file).unwrap();
+    assert!(content.contains("    \"us-east-1b\",\n    \"us-east-1c\"\n  ]"));
+}
+
+#[test]
+fn test_append_to_single_element_list() {
+    let (_dir, file) = common::create_test_tf_file("module \"vpc\" {\n  azs = [\"a\"]\n}\n");
+
+    append_item("module.vpc.azs", "\"b\"", Some(file.as_path())).unwrap();
+    let content = fs::read_to_string(&file).unwrap();
+    assert!(content.contains(r#"azs = ["a", "b"]"#));
+}
+
+#[test]
+fn test_append_to_non_list_fails() {
+    let (_dir, file) = common::create_test_tf_file(ONE_LINE_LIST_TF);
+
+    let result = append_item("module.vpc.source", "\"x\"", Some(file.as_path()));
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_remove_item_first_element() {
+    let (_dir, file) = common::create_test_tf_file(ONE_LINE_LIST_TF);
+
+    let removed = remove_item("module.vpc.azs", "\"us-east-1a\"", Some(file.as_path())).unwrap();
+    assert_eq!(removed, 1);
+    let content = fs::read_to_string(&file).unwrap();
+    assert!(content.contains(r#"azs    = ["us-east-1b"]"#));
+}
+
+#[test]
+fn test_remove_item_last_element_without_trailing_comma() {
+    let (_dir, file) = common::create_test_tf_file(NO_TRAILING_COMMA_LIST_TF);
+
+    remove_item("module.vpc.azs", "\"us-east-1b\"", Some(file.as_path())).unwrap();
+    let content = fs::read_to_string(&file).unwrap();
+    assert!(content.contains("  azs = [\n    \"us-east-1a\"\n  ]"));
+}
+
+#[test]
+fn test_remove_item_missing_value_fails() {
+    let (_dir, file) = common::create_test_tf_file(ONE_LINE_LIST_TF);
+
+    assert!(remove_item("module.vpc.azs", "\"eu-west-1a\"", Some(file.as_path())).is_err());
+}
+
+#[test]
+fn test_get_list_element_by_index() {
+    let (_dir, file) = common::create_test_tf_file(MULTI_LINE_LIST_TF);
+
+    let result = get_value("module.vpc.azs[1]", Some(file.as_path())).unwrap();
+    assert_eq!(result, Some("us-east-1b".to_string()));
+    let result = get_value("module.vpc.azs[5]", Some(file.as_path())).unwrap();
+    assert_eq!(result, None);
+}
+
+#[test]
+fn test_set_list_element_by_index() {
+    let (_dir, file) = common::create_test_tf_file(MULTI_LINE_LIST_TF);
+
+    set_value("module.vpc.azs[0]", "eu-west-1a", Some(file.as_path())).unwrap();
+    let content = fs::read_to_string(&file).unwrap();
+    assert!(content.contains("    \"eu-west-1a\",\n    \"us-east-1b\",\n"));
+}
+
+#[test]
+fn test_set_list_element_out_of_range() {
+    let (_dir, file) = common::create_test_tf_file(ONE_LINE_LIST_TF);
+
+    assert!(set_value("module.vpc.azs[2]", "x", Some(file.as_path())).is_err());
+}
@@ -0,0 +1,99 @@
+mod common;
+
+use std::fs;
+use tv::map::remove_key;
+use tv::{get_value, set_value};
+
+const ONE_LINE_MAP_TF: &str = r#"module "vpc" {
+  source = "git::https://github.com/org/vpc.git?ref=v1.0.0"
+  tags   = { Team = "network", Owner = "alice" }
+}
+"#;
+
+const MULTI_LINE_MAP_TF: &str = r#"module "vpc" {
+  tags = {
+    Team  = "network"
+    Owner = "alice"
+    "cost-center" = "42"
+  }
+}
+"#;
+
+#[test]
+fn test_get_map_key() {
+    let (_dir, file) = common::create_test_tf_file(MULTI_LINE_MAP_TF);
+
+    assert_eq!(get_value("module.vpc.tags[\"Owner\"]", Some(file.as_path())).unwrap(), Some("alice".to_string()));
+    assert_eq!(get_value("module.vpc.tags[\"cost-center\"]", Some(file.as_path())).unwrap(), Some("42".to_string()));
+    assert_eq!(get_value("module.vpc.tags[\"Missing\"]", Some(file.as_path())).unwrap(), None);
+}
+
+#[test]
+fn test_set_existing_map_key() {
+    let (_dir, file) = common::create_test_tf_file(MULTI_LINE_MAP_TF);
+
+    set_value("module.vpc.tags[\"Team\"]", "platform", Some(file.as_path())).unwrap();
+    let content = fs::read_to_string(&file).unwrap();
+    assert!(content.contains("    Team  = \"platform\"\n    Owner = \"alice\"\n"));
+}
+
+#[test]
+fn test_set_new_key_multi_line_map() {
+    let (_dir, file) = common::create_test_tf_file(MULTI_LINE_MAP_TF);
+
+    set_value("module.vpc.tags[\"Env\"]", "prod", Some(file.as_path())).unwrap();
+    let content = fs::read_to_string(&file).unwrap();
+    assert!(content.contains("    \"cost-center\" = \"42\"\n    Env = \"prod\"\n  }"));
+}
+
+#[test]
+fn test_set_new_key_one_line_map() {
+    let (_dir, file) = common::create_test_tf_file(ONE_LINE_MAP_TF);
+
+    set_value("module.vpc.tags[\"Env\"]", "prod", Some(file.as_path())).unwrap();
+    let content = fs::read_to_string(&file).unwrap();
+    assert!(content.contains(r#"tags   = { Team = "network", Owner = "alice", Env = "prod" }"#));
+}
+
+#[test]
+fn test_set_new_key_needing_quotes() {
+    let (_dir, file) = common::create_test_tf_file("module \"vpc\" {\n  tags = {}\n}\n");
+
+    set_value("module.vpc.tags[\"kubernetes.io/role\"]", "elb", Some(file.as_path())).unwrap();
+    let content = fs::read_to_string(&file).unwrap();
+    assert!(content.contains(r#"tags = { "kubernetes.io/role" = "elb" }"#));
+}
+
+#[test]
+fn test_source_index_still_uses_pseudo_params() {
+    let (_dir, file) = common::create_test_tf_file(ONE_LINE_MAP_TF);
+
+    set_value("module.vpc.source[\"ref\"]", "v2.0.0", Some(file.as_path())).unwrap();
+    assert_eq!(get_value("module.vpc.source[\"ref\"]", Some(file.as_path())).unwrap(), Some("v2.0.0".to_string()));
+}
+
+#[test]
+fn test_rm_last_key_one_line_map() {
+    let (_dir, file) = common::create_test_tf_file(ONE_LINE_MAP_TF);
+
+    remove_key("module.vpc.tags[\"Owner\"]", Some(file.as_path())).unwrap();
+    let content = fs::read_to_string(&file).unwrap();
+    assert!(content.contains(r#"tags   = { Team = "network" }"#));
+}
+
+#[test]
+fn test_rm_first_key_multi_line_map() {
+    let (_dir, file) = common::create_test_tf_file(MULTI_LINE_MAP_TF);
+
+    remove_key("module.vpc.tags[\"Team\"]", Some(file.as_path())).unwrap();
+    let content = fs::read_to_string(&file).unwrap();
+    assert!(content.contains("  tags = {\n    Owner = \"alice\"\n    \"cost-center\" = \"42\"\n  }"));
+}
+
+#[test]
+fn test_rm_missing_key_fails() {
+    let (_dir, file) = common::create_test_tf_file(ONE_LINE_MAP_TF);
+
+    assert!(remove_key("module.vpc.tags[\"Nope\"]", Some(file.as_path())).is_err());
+    assert!(remove_key("module.vpc.source[\"ref\"]", Some(file.as_path())).is_err());
+}
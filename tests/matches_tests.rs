@@ -0,0 +1,54 @@
+mod common;
+
+use tv::matches::scan_matches;
+use tv::{ScanOptions, ValueKind};
+
+const MAIN_TF: &str = r#"terraform {
+  required_version = ">= 1.5"
+}
+
+resource "aws_s3_bucket" "logs.v2" {
+  bucket = "logs"
+}
+
+resource "aws_s3_bucket" "données" {
+  bucket = "data"
+}
+
+module "vpc" {
+  source = "git::https://github.com/org/vpc.git?ref=v1.2.0"
+}
+"#;
+
+#[test]
+fn test_scan_matches() {
+    let temp_dir = common::create_test_dir_with_files(&[("main.tf", MAIN_TF)]);
+    let options = ScanOptions::default();
+
+    let buckets = scan_matches("resource.aws_s3_bucket.*", temp_dir.path(), &options).unwrap();
+    let labels: Vec<Vec<&str>> = buckets.iter().map(|m| m.labels.iter().map(String::as_str).collect()).collect();
+    assert_eq!(labels, vec![vec!["aws_s3_bucket", "données"], vec!["aws_s3_bucket", "logs.v2"]]);
+    assert_eq!(buckets[0].name, "aws_s3_bucket.données");
+    assert!(buckets[0].attribute.is_none() && buckets[0].value.is_none());
+    assert_eq!(buckets[1].span.as_ref().map(|s| s.start_line), Some(5));
+
+    // Spans are byte offsets, so non-ASCII labels do not shift them
+    let span = buckets[0].span.clone().unwrap();
+    assert_eq!((span.start_line, span.end_line), (9, 11));
+    assert!(MAIN_TF[span.start..span.end].starts_with("resource \"aws_s3_bucket\" \"données\""));
+    assert!(MAIN_TF[span.start..span.end].trim_end().ends_with('}'));
+
+    let refs = scan_matches("module.*.source[\"ref\"]", temp_dir.path(), &options).unwrap();
+    assert_eq!(refs[0].labels, vec!["vpc"]);
+    assert_eq!(refs[0].attribute.as_deref(), Some("source[\"ref\"]"));
+    let value = refs[0].value.clone().unwrap();
+    assert_eq!((value.value.as_str(), value.kind), ("v1.2.0", ValueKind::String));
+    assert_eq!(refs[0].span.as_ref().map(|s| s.start_line), Some(13));
+    assert_eq!(refs[0].line, Some(14));
+    assert_eq!(buckets[0].line, Some(9));
+
+    let terraform = scan_matches("terraform.required_version", temp_dir.path(), &options).unwrap();
+    assert!(terraform[0].labels.is_empty());
+    assert_eq!(terraform[0].block_type, "terraform");
+    assert_eq!(terraform[0].span.as_ref().map(|s| (s.start_line, s.end_line)), Some((1, 3)));
+}
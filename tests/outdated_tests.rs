@@ -0,0 +1,166 @@
+mod common;
+
+use std::path::Path;
+use tv::config::{Config, parse_config};
+use tv::outdated::{outdated, outdated_providers, render};
+use tv::parse_scan_query;
+use tv::registry::Client;
+use tv::terraformrc::parse_provider_installation;
+
+const VPC_VERSIONS: &str = r#"{"modules": [{"versions": [
+  {"version": "4.0.0"},
+  {"version": "5.0.0"},
+  {"version": "5.1.0"},
+  {"version": "6.0.0"},
+  {"version": "6.1.0", "deprecation": {"reason": "broken"}},
+  {"version": "7.0.0-beta1"}
+]}]}"#;
+
+const MAIN_TF: &str = r#"module "pinned" {
+  source  = "terraform-aws-modules/vpc/aws"
+  version = "5.0.0"
+}
+
+module "pessimistic" {
+  source  = "terraform-aws-modules/vpc/aws"
+  version = "~> 5.0"
+}
+
+module "range" {
+  source  = "terraform-aws-modules/vpc/aws"
+  version = ">= 4.0"
+}
+
+module "latest" {
+  source  = "terraform-aws-modules/vpc/aws"
+  version = "6.0.0"
+}
+
+module "ahead" {
+  source  = "terraform-aws-modules/vpc/aws"
+  version = "9.0.0"
+}
+
+module "git" {
+  source = "git::https://github.com/org/vpc.git?ref=v1.0.0"
+}
+"#;
+
+const AWS_VERSIONS: &str = r#"{"versions": [{"version": "5.80.0"}, {"version": "6.2.0"}]}"#;
+
+const PROVIDERS_TF: &str = r#"terraform {
+  required_providers {
+    aws = {
+      source  = "hashicorp/aws"
+      version = "~> 5.0"
+    }
+    custom = {
+      source  = "example/custom"
+      version = ">= 1.0"
+    }
+  }
+}
+"#;
+
+fn registry() -> Client {
+    Client::with_base_url(Some(common::serve_http(vec![
+        ("/v1/modules/terraform-aws-modules/vpc/aws/versions", VPC_VERSIONS.to_string()),
+        ("/v1/providers/hashicorp/aws/versions", AWS_VERSIONS.to_string()),
+        ("/v1/providers/example/custom/versions", AWS_VERSIONS.to_string()),
+    ])))
+}
+
+#[test]
+fn test_outdated_registry_modules() {
+    let (_dir, file) = common::create_test_tf_file(MAIN_TF);
+    let dir = file.parent().unwrap();
+    let found = outdated(dir, &parse_scan_query("module.*").unwrap(), &registry(), &Config::default()).unwrap();
+    let shown: Vec<(&str, &str, Option<&str>, &str)> = found
+        .iter()
+        .map(|o| (o.module.as_str(), o.current.as_str(), o.wanted.as_deref(), o.latest.as_str()))
+        .collect();
+    assert_eq!(
+        shown,
+        vec![("pinned", "5.0.0", Some("5.0.0"), "6.0.0"), ("pessimistic", "~> 5.0", Some("5.1.0"), "6.0.0")]
+    );
+    assert_eq!(found[0].source, "terraform-aws-modules/vpc/aws");
+    assert!(found.iter().all(|o| o.kind == "module" && o.guide.is_none()));
+
+    let only = outdated(dir, &parse_scan_query("module.pessimistic").unwrap(), &registry(), &Config::default()).unwrap();
+    assert_eq!(only.len(), 1);
+}
+
+#[test]
+fn test_render_outdated() {
+    let (_dir, file) = common::create_test_tf_file(MAIN_TF);
+    let found = outdated(file.parent().unwrap(), &parse_scan_query("module.pinned").unwrap(), &registry(), &Config::default()).unwrap();
+    assert_eq!(
+        render(&found, |_: &Path| "main.tf".to_string()),
+        "file     module  current  wanted  latest  source\n\
+         main.tf  pinned  5.0.0    5.0.0   6.0.0   terraform-aws-modules/vpc/aws\n"
+    );
+}
+
+#[test]
+fn test_outdated_providers_link_upgrade_guides() {
+    let (_dir, file) = common::create_test_tf_file(PROVIDERS_TF);
+    let dir = file.parent().unwrap();
+    let found = outdated_providers(dir, &registry(), &Config::default()).unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!((found[0].kind, found[0].module.as_str(), found[0].source.as_str()), ("provider", "aws", "hashicorp/aws"));
+    assert_eq!((found[0].wanted.as_deref(), found[0].latest.as_str()), (Some("5.80.0"), "6.2.0"));
+    assert_eq!(
+        found[0].guide.as_deref(),
+        Some("https://registry.terraform.io/providers/hashicorp/aws/latest/docs/guides/version-6-upgrade")
+    );
+    let rendered = render(&found, |_: &Path| "main.tf".to_string());
+    assert!(rendered.starts_with("file     provider  current"), "{}", rendered);
+    assert!(rendered.ends_with(
+        "\nUpgrade guides:\n  provider.aws 6.2.0: https://registry.terraform.io/providers/hashicorp/aws/latest/docs/guides/version-6-upgrade\n"
+    ), "{}", rendered);
+
+    // tv.toml replaces built-in guides and adds module ones
+    let config = parse_config(
+        "[upgrade_guides]\n\"hashicorp/aws\" = \"https://wiki.example.com/aws-{major}\"\n\"terraform-aws-modules/*\" = \"https://example.com/UPGRADE-{major}.0.md\"\n",
+    )
+    .unwrap();
+    let found = outdated_providers(dir, &registry(), &config).unwrap();
+    assert_eq!(found[0].guide.as_deref(), Some("https://wiki.example.com/aws-6"));
+
+    let (_dir, file) = common::create_test_tf_file(MAIN_TF);
+    let found = outdated(file.parent().unwrap(), &parse_scan_query("module.*").unwrap(), &registry(), &config).unwrap();
+    assert_eq!(found[0].guide.as_deref(), Some("https://example.com/UPGRADE-6.0.md"));
+}
+
+#[test]
+fn test_outdated_providers_follow_provider_installation() {
+    let (_dir, file) = common::create_test_tf_file(PROVIDERS_TF);
+    let dir = file.parent().unwrap();
+    let mirror = common::serve_http(vec![(
+        "/providers/registry.terraform.io/hashicorp/aws/index.json",
+        r#"{"versions": {"5.80.0": {}, "6.1.0": {}}}"#.to_string(),
+    )]);
+    let mirrors = tempfile::TempDir::new().unwrap();
+    std::fs::create_dir_all(mirrors.path().join("registry.terraform.io/example/custom/7.0.0/linux_amd64")).unwrap();
+    let cli_config = format!(
+        "provider_installation {{\n  network_mirror {{\n    url     = \"{}/providers/\"\n    include = [\"hashicorp/*\"]\n  }}\n  filesystem_mirror {{\n    path    = \"{}\"\n    include = [\"example/*\"]\n  }}\n  direct {{\n    exclude = [\"hashicorp/*\"]\n  }}\n}}\n",
+        mirror,
+        mirrors.path().display()
+    );
+    let installation = parse_provider_installation(&cli_config).unwrap();
+
+    // aws only from the mirror: 6.2.0 is in the registry, not in it (custom,
+    // from the filesystem mirror and the registry, is within its range)
+    let found = outdated_providers(dir, &registry().with_installation(installation), &Config::default()).unwrap();
+    let shown: Vec<(&str, &str)> = found.iter().map(|o| (o.module.as_str(), o.latest.as_str())).collect();
+    assert_eq!(shown, vec![("aws", "6.1.0")]);
+
+    // A provider no method covers cannot be installed
+    let only_mirror = parse_provider_installation(&format!(
+        "provider_installation {{\n  network_mirror {{\n    url     = \"{}/providers/\"\n    include = [\"hashicorp/*\"]\n  }}\n}}\n",
+        mirror
+    ))
+    .unwrap();
+    let error = outdated_providers(dir, &registry().with_installation(only_mirror), &Config::default()).unwrap_err();
+    assert!(format!("{:#}", error).contains("covers registry.terraform.io/example/custom"), "{:#}", error);
+}
@@ -0,0 +1,150 @@
+mod common;
+
+use serde_json::json;
+use tv::matches::describe_match;
+use tv::output::{ErrorCode, OutputFormat, csv_line, error_code, error_json, get_json, scan_csv_header, scan_csv_row, scan_json, value_json};
+use tv::{block_arguments, get_typed_value, parse_query, parse_scan_query, scan_files};
+
+const MAIN_TF: &str = r#"terraform {
+  required_providers {
+    aws = {
+      source  = "hashicorp/aws"
+      version = "~> 5.0"
+    }
+  }
+}
+
+module "vpc" {
+  source = "git::https://github.com/org/vpc.git?ref=v1.2.0"
+  azs    = ["a", "b"]
+  count  = 2
+  tags   = { Owner = "platform", "cost-center" = 42 }
+  name   = var.name
+}
+
+resource "aws_s3_bucket" "logs" {
+  bucket = "logs"
+}
+"#;
+
+#[test]
+fn test_get_json() {
+    let (_dir, file) = common::create_test_tf_file(MAIN_TF);
+    let get = |query: &str| {
+        let typed = get_typed_value(query, Some(&file)).unwrap();
+        get_json(&file, &parse_query(query).unwrap(), typed.as_ref())
+    };
+
+    let found = get("module.vpc.source[\"ref\"]");
+    assert_eq!(found["block_type"], "module");
+    assert_eq!(found["label"], "vpc");
+    assert_eq!(found["attribute"], "source[\"ref\"]");
+    assert_eq!((&found["value"], &found["type"]), (&json!("v1.2.0"), &json!("string")));
+    assert!(found["file"].as_str().unwrap().ends_with(".tf"));
+
+    assert_eq!(get("module.vpc.azs")["value"], json!(["a", "b"]));
+    assert_eq!(get("module.vpc.count")["value"], json!(2));
+    assert_eq!(get("module.vpc.tags")["value"], json!({"Owner": "platform", "cost-center": 42}));
+    assert_eq!(get("module.vpc.name")["value"], "var.name");
+    assert_eq!(get("module.vpc.name")["type"], "expression");
+    assert_eq!(get("resource.aws_s3_bucket.logs.bucket")["label"], "aws_s3_bucket.logs");
+
+    let missing = get("module.vpc.missing");
+    assert!(missing["value"].is_null() && missing["type"].is_null());
+}
+
+#[test]
+fn test_scan_json() {
+    let temp_dir = common::create_test_dir_with_files(&[("main.tf", MAIN_TF)]);
+    let scan = |query: &str, with_args: &[String]| {
+        let scan_query = parse_scan_query(query).unwrap();
+        scan_files(query, temp_dir.path())
+            .unwrap()
+            .into_iter()
+            .map(|(file, name)| {
+                let arguments = block_arguments(&file, &scan_query.block_type, &name, with_args).unwrap();
+                scan_json(&describe_match(&file, &scan_query, &name).unwrap(), &arguments)
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let modules = scan("module.*.source", &["count".to_string(), "missing".to_string()]);
+    assert_eq!(modules.len(), 1);
+    assert_eq!(modules[0]["label"], "vpc");
+    assert_eq!(modules[0]["value"], "git::https://github.com/org/vpc.git?ref=v1.2.0");
+    assert_eq!(modules[0]["arguments"], json!({"count": 2}));
+
+    let providers = scan("terraform.required_providers.aws", &[]);
+    assert!(providers[0]["label"].is_null());
+    assert_eq!(providers[0]["attribute"], "required_providers.aws");
+    assert_eq!(providers[0]["value"], json!({"source": "hashicorp/aws", "version": "~> 5.0"}));
+
+    let buckets = scan("resource.aws_s3_bucket.*", &[]);
+    assert_eq!(buckets[0]["label"], "aws_s3_bucket.logs");
+    assert_eq!(buckets[0]["labels"], json!(["aws_s3_bucket", "logs"]));
+    assert!(buckets[0]["span"]["start_line"].as_u64().unwrap() > 1);
+    assert!(buckets[0]["attribute"].is_null() && buckets[0]["value"].is_null());
+    assert!(buckets[0].get("arguments").is_none());
+}
+
+#[test]
+fn test_value_json_and_format() {
+    let typed = tv::TypedValue { value: "[1, true, null]".to_string(), kind: tv::ValueKind::List };
+    assert_eq!(value_json(&typed), json!([1, true, null]));
+    assert_eq!("json".parse::<OutputFormat>().unwrap(), OutputFormat::Json);
+    assert_eq!("text".parse::<OutputFormat>().unwrap(), OutputFormat::Text);
+    assert!("yaml".parse::<OutputFormat>().is_err());
+}
+
+#[test]
+fn test_scan_csv() {
+    let temp_dir = common::create_test_dir_with_files(&[("main.tf", MAIN_TF)]);
+    let with_args = vec!["count".to_string(), "missing".to_string()];
+    assert_eq!(scan_csv_header(&with_args), "file,block,label,attribute,value,start_line,end_line,count,missing\n");
+
+    let scan_query = parse_scan_query("module.*.source[\"ref\"]").unwrap();
+    let (file, name) = scan_files("module.*.source[\"ref\"]", temp_dir.path()).unwrap().remove(0);
+    let arguments = block_arguments(&file, "module", &name, &with_args).unwrap();
+    let found = describe_match(&file, &scan_query, &name).unwrap();
+    let row = scan_csv_row(&found, &arguments, &with_args);
+    let lines = found.span.map(|s| format!("{},{}", s.start_line, s.end_line)).unwrap();
+    assert!(row.ends_with(&format!(",module,vpc,\"source[\"\"ref\"\"]\",v1.2.0,{},2,\n", lines)), "{}", row);
+
+    let scan_query = parse_scan_query("terraform").unwrap();
+    let (file, name) = scan_files("terraform", temp_dir.path()).unwrap().remove(0);
+    let row = scan_csv_row(&describe_match(&file, &scan_query, &name).unwrap(), &[], &[]);
+    assert!(row.ends_with(",terraform,,,,1,8\n"), "{}", row);
+
+    assert_eq!(csv_line(&["a,b", "say \"hi\"", "plain"]), "\"a,b\",\"say \"\"hi\"\"\",plain\n");
+    assert_eq!("csv".parse::<OutputFormat>().unwrap(), OutputFormat::Csv);
+}
+
+#[test]
+fn test_error_json() {
+    let temp_dir = common::create_test_dir_with_files(&[("main.tf", MAIN_TF), ("bad.tf", "module \"a\" {\n")]);
+    let file = temp_dir.path().join("main.tf");
+
+    let error = tv::get_value("module.vpc.source", Some(&temp_dir.path().join("missing.tf"))).unwrap_err();
+    assert_eq!(error_code(&error, Some(("module.vpc.source", true))), ErrorCode::FileNotFound);
+
+    let error = tv::get_value("module.a.source", Some(&temp_dir.path().join("bad.tf"))).unwrap_err();
+    let shown = error_json(&error, Some(("module.a.source", true)), None);
+    assert_eq!(shown["code"], json!("parse_error"));
+    assert!(shown["file"].as_str().unwrap().ends_with("bad.tf"), "{}", shown);
+    assert!(shown["hint"].is_string());
+
+    // A query that does not parse is the cause, whatever the error says
+    let error = tv::get_value("module.*.source", Some(&file)).unwrap_err();
+    let shown = error_json(&error, Some(("module.*.source", true)), Some(&file));
+    assert_eq!(shown["code"], json!("invalid_query"));
+    assert_eq!(shown["query"], json!("module.*.source"));
+    assert_eq!(shown["message"], json!("Wildcards are only supported by scan: module.*.source"));
+
+    let error = tv::set_value("module.vpc.version", "1.0.0", Some(&file)).unwrap_err();
+    assert_eq!(error_code(&error, None), ErrorCode::NotFound);
+    let expected = tv::policy::Expectation::Equals("v0".to_string());
+    let error = tv::check_current(r#"module.vpc.source["ref"]"#, Some(&file), &expected).unwrap_err();
+    assert_eq!(error_code(&error, None), ErrorCode::UnexpectedValue);
+    let shown = error_json(&anyhow::anyhow!("something else"), None, None);
+    assert_eq!(shown, json!({"code": "error", "message": "something else", "file": null, "query": null, "hint": null}));
+}
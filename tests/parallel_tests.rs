@@ -0,0 +1,24 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tv::parallel;
+
+#[test]
+fn test_map_keeps_order_and_bounds_concurrency() {
+    let running = AtomicUsize::new(0);
+    let most = AtomicUsize::new(0);
+    let keys: Vec<u64> = (0..12).collect();
+    let doubled = parallel::map(&keys, 3, |key| {
+        let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+        most.fetch_max(now, Ordering::SeqCst);
+        std::thread::sleep(Duration::from_millis(10 + (12 - key)));
+        running.fetch_sub(1, Ordering::SeqCst);
+        key * 2
+    });
+    assert_eq!(doubled, (0..12).map(|k| k * 2).collect::<Vec<_>>());
+    assert!(most.load(Ordering::SeqCst) <= 3);
+    assert!(most.load(Ordering::SeqCst) > 1);
+
+    // No keys, and a limit of 0 still runs one at a time
+    assert!(parallel::map(&[] as &[u64], 4, |k| *k).is_empty());
+    assert_eq!(parallel::map(&[1, 2], 0, |k| k + 1), vec![2, 3]);
+}
@@ -1,11 +1,11 @@
-use tv::{parse_query, parse_scan_query, parse_attribute_filter};
+use tv::{parse_query, parse_scan_query, parse_attribute_filter, split_query_path};
 
 #[test]
 fn test_parse_query_simple_module() {
     let query = parse_query("module.vpc.source").unwrap();
     assert_eq!(query.block_type, "module");
     assert_eq!(query.block_label, Some("vpc".to_string()));
-    assert_eq!(query.attribute, "source");
+    assert_eq!(query.attribute_name(), "source");
     assert_eq!(query.index, None);
 }
 
@@ -14,7 +14,7 @@ fn test_parse_query_with_index() {
     let query = parse_query("module.vpc.source[\"ref\"]").unwrap();
     assert_eq!(query.block_type, "module");
     assert_eq!(query.block_label, Some("vpc".to_string()));
-    assert_eq!(query.attribute, "source");
+    assert_eq!(query.attribute_name(), "source");
     assert_eq!(query.index, Some("ref".to_string()));
 }
 
@@ -23,7 +23,7 @@ fn test_parse_query_with_index_no_quotes() {
     let query = parse_query("module.vpc.source[ref]").unwrap();
     assert_eq!(query.block_type, "module");
     assert_eq!(query.block_label, Some("vpc".to_string()));
-    assert_eq!(query.attribute, "source");
+    assert_eq!(query.attribute_name(), "source");
     assert_eq!(query.index, Some("ref".to_string()));
 }
 
@@ -33,7 +33,7 @@ fn test_parse_query_terraform_nested() {
     assert_eq!(query.block_type, "terraform");
     assert_eq!(query.block_label, None);
     assert_eq!(query.nested_blocks, vec!["required_providers".to_string(), "aws".to_string()]);
-    assert_eq!(query.attribute, "source");
+    assert_eq!(query.attribute_name(), "source");
 }
 
 #[test]
@@ -41,7 +41,7 @@ fn test_parse_query_terraform_simple() {
     let query = parse_query("terraform.backend").unwrap();
     assert_eq!(query.block_type, "terraform");
     assert_eq!(query.block_label, None);
-    assert_eq!(query.attribute, "backend");
+    assert_eq!(query.attribute_name(), "backend");
 }
 
 #[test]
@@ -151,7 +151,7 @@ fn test_parse_query_multiple_nested() {
     let query = parse_query("terraform.required_providers.aws.version").unwrap();
     assert_eq!(query.block_type, "terraform");
     assert_eq!(query.nested_blocks, vec!["required_providers".to_string(), "aws".to_string()]);
-    assert_eq!(query.attribute, "version");
+    assert_eq!(query.attribute_name(), "version");
 }
 
 #[test]
@@ -165,3 +165,86 @@ fn test_parse_query_with_path_index() {
     let query = parse_query("module.vpc.source[\"path\"]").unwrap();
     assert_eq!(query.index, Some("path".to_string()));
 }
+
+#[test]
+fn test_parse_query_quoted_label() {
+    let query = parse_query("module.\"my.module\".source[\"ref\"]").unwrap();
+    assert_eq!(query.block_type, "module");
+    assert_eq!(query.block_label, Some("my.module".to_string()));
+    assert_eq!(query.attribute_name(), "source");
+    assert_eq!(query.index, Some("ref".to_string()));
+
+    let escaped = parse_query(r#"module."say \"hi\"".name"#).unwrap();
+    assert_eq!(escaped.block_label, Some("say \"hi\"".to_string()));
+
+    assert!(parse_query("module.\"my.module.source").is_err());
+}
+
+#[test]
+fn test_parse_scan_query_quoted_label() {
+    let query = parse_scan_query("module.\"my.module\".source[ref==\"v1.0\"]").unwrap();
+    assert_eq!(query.block_label, Some("my.module".to_string()));
+    assert_eq!(query.attribute, Some("source".to_string()));
+    assert_eq!(query.filter.unwrap().value, "v1.0");
+}
+
+#[test]
+fn test_split_query_path() {
+    let (parts, bracket) = split_query_path("module.\"a.b\".tags[\"kubernetes.io/role\"]").unwrap();
+    assert_eq!(parts, vec!["module", "a.b", "tags"]);
+    assert_eq!(bracket, Some("\"kubernetes.io/role\"".to_string()));
+}
+
+#[test]
+fn test_get_and_scan_queries_share_one_grammar() {
+    for query in [
+        "module.vpc.source[\"ref\"]",
+        "terraform.required_providers.aws",
+        "terraform.required_providers.aws.version",
+        "provider.aws.region",
+    ] {
+        assert_eq!(parse_query(query).unwrap(), parse_scan_query(query).unwrap(), "{}", query);
+    }
+
+    // Unlabeled blocks never consume a label
+    let query = parse_query("terraform.required_providers.aws").unwrap();
+    assert_eq!(query.block_label, None);
+    assert_eq!(query.nested_blocks, vec!["required_providers".to_string()]);
+    assert_eq!(query.attribute_name(), "aws");
+}
+
+#[test]
+fn test_parse_query_rejects_scan_only_syntax() {
+    assert!(parse_query("module.*.source").is_err());
+    assert!(parse_query("module.vpc.source[ref==\"v1\"]").is_err());
+    assert!(parse_query("module.vpc").is_err());
+    assert!(parse_scan_query("module.vpc").is_ok());
+}
+
+#[test]
+fn test_parse_scan_query_index_selector() {
+    let query = parse_scan_query("module.*.tags[\"Team\"]").unwrap();
+    assert_eq!(query.index, Some("Team".to_string()));
+    assert!(query.filter.is_none());
+
+    let query = parse_scan_query("module.*.tags[Team==\"platform\"]").unwrap();
+    assert!(query.index.is_none());
+    assert_eq!(query.filter.unwrap().attribute, "Team");
+}
+
+#[test]
+fn test_query_canonical_form() {
+    let cases = [
+        ("module.vpc.source[ref]", "module.vpc.source[\"ref\"]"),
+        ("module.*", "module.*"),
+        ("terraform.required_providers.*", "terraform.required_providers.*"),
+        ("module.\"my.module\".source[url == \"x\"]", "module.\"my.module\".source[url==\"x\"]"),
+        ("terraform.required_providers.aws.version", "terraform.required_providers.aws.version"),
+    ];
+    for (query, canonical) in cases {
+        let parsed = parse_scan_query(query).unwrap();
+        assert_eq!(parsed.to_string(), canonical);
+        // The canonical form reads back as the same query
+        assert_eq!(parse_scan_query(canonical).unwrap(), parsed);
+    }
+}
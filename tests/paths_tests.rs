@@ -0,0 +1,39 @@
+mod common;
+
+use std::path::{Path, PathBuf};
+use tv::paths::{PathFormat, format_path, repo_root};
+
+#[test]
+fn test_relative_and_absolute() {
+    let cwd = Path::new("/work/repo/envs");
+    let format = |path: &str, format| format_path(Path::new(path), format, cwd);
+
+    assert_eq!(format("./prod/main.tf", PathFormat::Relative), PathBuf::from("./prod/main.tf"));
+    assert_eq!(format("../modules/vpc/main.tf", PathFormat::Relative), PathBuf::from("../modules/vpc/main.tf"));
+    assert_eq!(format("/work/repo/envs/prod/main.tf", PathFormat::Relative), PathBuf::from("./prod/main.tf"));
+    assert_eq!(format("/other/main.tf", PathFormat::Relative), PathBuf::from("../../../other/main.tf"));
+
+    assert_eq!(format("./prod/../dev/main.tf", PathFormat::Absolute), PathBuf::from("/work/repo/envs/dev/main.tf"));
+    assert_eq!(format("/x/./y.tf", PathFormat::Absolute), PathBuf::from("/x/y.tf"));
+    assert!("repo".parse::<PathFormat>().is_err());
+}
+
+#[test]
+fn test_repo_relative() {
+    let repo = common::create_git_repo(&[("envs/prod/main.tf", "module \"vpc\" {}\n")]);
+    let root = repo.path();
+    let file = root.join("envs/prod/main.tf");
+    assert_eq!(repo_root(&file), Some(root));
+
+    // The same output wherever tv runs from
+    for cwd in [root.to_path_buf(), root.join("envs/prod"), std::env::temp_dir()] {
+        assert_eq!(format_path(&file, PathFormat::RepoRelative, &cwd), PathBuf::from("envs/prod/main.tf"));
+    }
+    let relative = format_path(Path::new("main.tf"), PathFormat::RepoRelative, &root.join("envs/prod"));
+    assert_eq!(relative, PathBuf::from("envs/prod/main.tf"));
+
+    // Outside any repository: absolute
+    let outside = tempfile::TempDir::new().unwrap();
+    let loose = outside.path().join("main.tf");
+    assert_eq!(format_path(&loose, PathFormat::RepoRelative, outside.path()), loose);
+}
@@ -0,0 +1,74 @@
+mod common;
+
+use std::process::Command;
+use tv::parse_scan_query;
+use tv::pin_age::pin_ages;
+use tv::policy::{parse_policy, stale_pins_report};
+use tv::registry::Client;
+use tv::updates::ReleaseDates;
+
+fn dated_remote(tags: &[(&str, &str)]) -> tempfile::TempDir {
+    let remote = common::create_git_repo(&[("README.md", "modules\n")]);
+    for (tag, date) in tags {
+        let status = Command::new("git")
+            .args(["-c", "user.name=tv", "-c", "user.email=tv@example.com"])
+            .args(["commit", "--quiet", "--allow-empty", "-m", tag])
+            .env("GIT_COMMITTER_DATE", date)
+            .current_dir(remote.path())
+            .status()
+            .unwrap();
+        assert!(status.success());
+        let status = Command::new("git").args(["tag", tag]).current_dir(remote.path()).status().unwrap();
+        assert!(status.success());
+    }
+    remote
+}
+
+fn work_dir(url: &str) -> tempfile::TempDir {
+    let content = format!(
+        "module \"old\" {{\n  source = \"{url}?ref=v1.0.0\"\n}}\n\nmodule \"new\" {{\n  source = \"{url}?ref=v2.0.0\"\n}}\n\nmodule \"branch\" {{\n  source = \"{url}?ref=main\"\n}}\n\nmodule \"commit\" {{\n  source = \"{url}?ref=0123abcd\"\n}}\n"
+    );
+    common::create_test_dir_with_files(&[("main.tf", &content)])
+}
+
+#[test]
+fn test_pin_ages_from_tag_dates() {
+    let remote = dated_remote(&[("v1.0.0", "2020-01-01T00:00:00Z"), ("v2.0.0", "2030-01-01T00:00:00Z")]);
+    let work = work_dir(&format!("git::{}", remote.path().display()));
+    let cache = tempfile::TempDir::new().unwrap();
+    let registry = Client::with_base_url(None);
+    let mut dates = ReleaseDates::new(cache.path(), &registry);
+
+    let ages = pin_ages(work.path(), &parse_scan_query("module.*").unwrap(), &mut dates).unwrap();
+    // Branches are not pins; commits are, of unknown age
+    let modules: Vec<&str> = ages.iter().map(|a| a.module.as_str()).collect();
+    assert_eq!(modules, vec!["old", "new", "commit"]);
+    assert!(ages[0].days.unwrap() > 365 * 5);
+    assert_eq!(ages[1].days, Some(0));  // Released in the future
+    assert_eq!(ages[2].days, None);
+
+    let ages = pin_ages(work.path(), &parse_scan_query("module.old").unwrap(), &mut dates).unwrap();
+    assert_eq!(ages.len(), 1);
+}
+
+#[test]
+fn test_stale_pins_check() {
+    let policy = parse_policy("max_pin_age = \"180d\"\n").unwrap();
+    let max_age = policy.max_pin_age.unwrap();
+    assert_eq!(max_age.as_secs(), 180 * 24 * 60 * 60);
+    assert!(parse_policy("max_pin_age = \"half a year\"\n").is_err());
+    assert!(parse_policy("max_pin_age = 180\n").is_err());
+    assert!(parse_policy("").unwrap().max_pin_age.is_none());
+
+    let remote = dated_remote(&[("v1.0.0", "2020-01-01T00:00:00Z"), ("v2.0.0", "2030-01-01T00:00:00Z")]);
+    let work = work_dir(&format!("git::{}", remote.path().display()));
+    let cache = tempfile::TempDir::new().unwrap();
+    let registry = Client::with_base_url(None);
+    let report = stale_pins_report(work.path(), max_age, &mut ReleaseDates::new(cache.path(), &registry)).unwrap();
+
+    assert_eq!(report.name, "stale-pins");
+    assert_eq!(report.checked, 2);
+    assert_eq!(report.violations.len(), 1);
+    assert_eq!(report.violations[0].block, "module.old");
+    assert!(report.violations[0].reason.starts_with("pinned to v1.0.0, released "));
+}
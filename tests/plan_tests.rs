@@ -0,0 +1,104 @@
+mod common;
+
+use std::fs;
+use tv::plan::{Change, PLAN_VERSION, Plan, apply_plan, load, load_verified, parse_public_key, save, signature_path, verify};
+
+const MAIN_TF: &str = r#"module "vpc" {
+  source = "git::https://github.com/org/vpc.git?ref=v1.0.0"
+}
+
+module "eks" {
+  source  = "terraform-aws-modules/eks/aws"
+  version = "19.0.0"
+}
+"#;
+
+fn plan_for(file: &std::path::Path) -> Plan {
+    Plan {
+        version: PLAN_VERSION,
+        changes: vec![
+            Change {
+                file: file.to_path_buf(),
+                address: "module.vpc.source[\"ref\"]".to_string(),
+                old: "v1.0.0".to_string(),
+                new: "v1.2.0".to_string(),
+            },
+            Change {
+                file: file.to_path_buf(),
+                address: "module.eks.version".to_string(),
+                old: "19.0.0".to_string(),
+                new: "20.1.0".to_string(),
+            },
+        ],
+    }
+}
+
+#[test]
+fn test_plan_round_trip_and_apply() {
+    let (temp_dir, file) = common::create_test_tf_file(MAIN_TF);
+    let path = temp_dir.path().join("upgrade.plan");
+    save(&plan_for(&file), &path).unwrap();
+    let plan = load(&path).unwrap();
+    assert_eq!(plan, plan_for(&file));
+
+    apply_plan(&plan).unwrap();
+    let content = fs::read_to_string(&file).unwrap();
+    assert!(content.contains("vpc.git?ref=v1.2.0"));
+    assert!(content.contains("version = \"20.1.0\""));
+}
+
+#[test]
+fn test_stale_plan_applies_nothing() {
+    let (_temp_dir, file) = common::create_test_tf_file(MAIN_TF);
+    let plan = plan_for(&file);
+    // Someone moved eks on since the plan was made
+    fs::write(&file, MAIN_TF.replace("19.0.0", "19.5.0")).unwrap();
+
+    let error = apply_plan(&plan).unwrap_err().to_string();
+    assert!(error.contains("module.eks.version is 19.5.0, plan expects 19.0.0"));
+    assert!(fs::read_to_string(&file).unwrap().contains("ref=v1.0.0"));
+}
+
+#[test]
+fn test_unsupported_plan_version() {
+    let temp_dir = common::create_test_dir_with_files(&[("old.plan", "{\"version\": 99, \"changes\": []}")]);
+    assert!(load(&temp_dir.path().join("old.plan")).is_err());
+}
+
+// minisign's test vector: a prehashed signature of the bytes "test"
+const PUBLIC_KEY: &str = "RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3";
+const SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RUQf6LRCGA9i559r3g7V1qNyJDApGip8MfqcadIgT9CuhV3EMhHoN1mGTkUidF/z7SrlQgXdy8ofjb7bNJJylDOocrCo8KLzZwo=
+trusted comment: timestamp:1556193335\tfile:test
+y/rUw2y8/hOUYjZU71eHp/Wo1KZ40fGy2VJEDl34XMJM+TX48Ss/17u3IvIfbVR1FkZZSNCisQbuQY+bHwhEBg==
+";
+
+#[test]
+fn test_verify_signature() {
+    let key = parse_public_key(PUBLIC_KEY).unwrap();
+    verify(b"test", SIGNATURE, &key).unwrap();
+    assert!(verify(b"tesT", SIGNATURE, &key).is_err());
+
+    let temp_dir = common::create_test_dir_with_files(&[("minisign.pub", &format!("untrusted comment: minisign public key E7620F1842B4E81F\n{}\n", PUBLIC_KEY))]);
+    let from_file = parse_public_key(temp_dir.path().join("minisign.pub").to_str().unwrap()).unwrap();
+    verify(b"test", SIGNATURE, &from_file).unwrap();
+    assert!(parse_public_key("not-a-key").is_err());
+}
+
+#[test]
+fn test_apply_plan_requires_valid_signature() {
+    let (temp_dir, file) = common::create_test_tf_file(MAIN_TF);
+    let path = temp_dir.path().join("upgrade.plan");
+    save(&plan_for(&file), &path).unwrap();
+    let key = parse_public_key(PUBLIC_KEY).unwrap();
+
+    let error = format!("{:#}", load_verified(&path, Some(&key)).unwrap_err());
+    assert!(error.contains("Plan is not signed"));
+
+    // A signature of other content is refused
+    fs::write(signature_path(&path), SIGNATURE).unwrap();
+    assert!(signature_path(&path).ends_with("upgrade.plan.minisig"));
+    let error = format!("{:#}", load_verified(&path, Some(&key)).unwrap_err());
+    assert!(error.contains("Refusing"));
+    assert!(load_verified(&path, None).is_ok());
+}
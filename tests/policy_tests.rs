@@ -0,0 +1,83 @@
+mod common;
+
+use tv::policy::{Expectation, parse_policy, validate};
+use tv::registry::Client;
+
+const MODULES_TF: &str = r#"module "eks" {
+  source = "git::https://github.com/org/eks.git?ref=v19.0.0"
+}
+
+module "net" {
+  source = "git::https://github.com/org/net.git?ref=main"
+}
+
+module "vpc" {
+  source  = "terraform-aws-modules/vpc/aws"
+  version = "4.0.0"
+}
+"#;
+
+#[test]
+fn test_validate_reports_per_check() {
+    let temp_dir = common::create_test_dir_with_files(&[("main.tf", MODULES_TF)]);
+    let policy = parse_policy(
+        r#"
+check "eks-pinned" {
+  query  = "module.eks.source[ref]"
+  expect = "matches:^v\\d+"
+}
+
+check "github-tagged" {
+  query   = "module.*.source[url=\"*github.com*\"]"
+  expect  = "present"
+}
+
+check "refs-tagged" {
+  query   = "module.*.source[ref]"
+  expect  = "matches:^v"
+  message = "Git modules must use release tags"
+}
+
+check "vpc-5" {
+  query  = "module.vpc.version"
+  expect = "satisfies:>= 5.0"
+}
+"#,
+    )
+    .unwrap();
+
+    let reports = validate(&policy, temp_dir.path(), &Client::with_base_url(None)).unwrap();
+    let summary: Vec<(&str, bool, usize)> = reports
+        .iter()
+        .map(|r| (r.name.as_str(), r.passed(), r.checked))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            ("eks-pinned", true, 1),
+            ("github-tagged", true, 2),
+            ("refs-tagged", false, 3),
+            ("vpc-5", false, 1),
+        ]
+    );
+
+    let blocks: Vec<&str> = reports[2].violations.iter().map(|v| v.block.as_str()).collect();
+    assert_eq!(blocks, vec!["module.net", "module.vpc"]);
+    assert_eq!(reports[2].message.as_deref(), Some("Git modules must use release tags"));
+    assert!(reports[3].violations[0].reason.contains("4.0.0"));
+}
+
+#[test]
+fn test_policy_errors() {
+    assert!(parse_policy("check \"a\" {\n  expect = \"present\"\n}\n").is_err());
+    assert!(parse_policy("check \"a\" {\n  query = \"module.a.source\"\n  expect = \"pinned\"\n}\n").is_err());
+    assert!(parse_policy("check \"a\" {\n  query = \"module.a.source\"\n  expect = \"matches:(\"\n}\n").is_err());
+
+    let duplicate = "check \"a\" {\n  query = \"module.a.source\"\n  expect = \"present\"\n}\n";
+    assert!(parse_policy(&format!("{}{}", duplicate, duplicate)).is_err());
+
+    let absent = Expectation::parse("absent").unwrap();
+    assert!(absent.check(None).is_ok());
+    assert!(absent.check(Some("v1")).is_err());
+    assert!(Expectation::parse("equals:v1").unwrap().check(Some("v1")).is_ok());
+}
@@ -0,0 +1,78 @@
+mod common;
+
+use std::fs;
+use tv::parse_scan_query;
+use tv::promote::{plan_promotion, promote};
+
+const STAGING_TF: &str = r#"module "vpc" {
+  source  = "terraform-aws-modules/vpc/aws"
+  version = "5.2.0"
+}
+
+module "app" {
+  source = "git::https://github.com/org/app.git?ref=v1.4.0"
+}
+
+module "db" {
+  source = "git::https://github.com/org/db.git?ref=v3.0.0"
+}
+
+module "canary" {
+  source = "git::https://github.com/org/canary.git?ref=v0.1.0"
+}
+"#;
+
+const PROD_TF: &str = r#"module "vpc" {
+  source  = "terraform-aws-modules/vpc/aws"
+  version = "5.1.0"
+}
+
+module "app" {
+  source = "git::https://github.com/org/app.git?ref=v1.2.0"
+}
+
+module "db" {
+  source = "git::https://github.com/other/db.git?ref=v2.0.0"
+}
+"#;
+
+#[test]
+fn test_promote_modules_in_both_environments() {
+    let temp_dir = common::create_test_dir_with_files(&[
+        ("envs/staging/network/main.tf", STAGING_TF),
+        ("envs/prod/network/main.tf", PROD_TF),
+    ]);
+    let from = temp_dir.path().join("envs/staging");
+    let to = temp_dir.path().join("envs/prod");
+
+    let promotions = plan_promotion(&from, &to, &parse_scan_query("module.*").unwrap()).unwrap();
+    // db comes from another repository, canary is not in prod
+    let changes: Vec<(&str, &str, &str)> = promotions
+        .iter()
+        .map(|p| (p.module.as_str(), p.current.as_str(), p.promoted.as_str()))
+        .collect();
+    assert_eq!(changes, vec![("app", "v1.2.0", "v1.4.0"), ("vpc", "5.1.0", "5.2.0")]);
+    assert_eq!(promotions[0].file, to.join("network/main.tf"));
+
+    let only_vpc = plan_promotion(&from, &to, &parse_scan_query("module.vpc").unwrap()).unwrap();
+    assert_eq!(only_vpc.len(), 1);
+
+    promote(&promotions).unwrap();
+    let prod = fs::read_to_string(to.join("network/main.tf")).unwrap();
+    assert!(prod.contains("app.git?ref=v1.4.0"));
+    assert!(prod.contains("version = \"5.2.0\""));
+    assert!(prod.contains("ref=v2.0.0"));
+    assert!(plan_promotion(&from, &to, &parse_scan_query("module.*").unwrap()).unwrap().is_empty());
+}
+
+#[test]
+fn test_promote_matches_by_relative_directory() {
+    let temp_dir = common::create_test_dir_with_files(&[
+        ("staging/network/main.tf", STAGING_TF),
+        ("prod/compute/main.tf", PROD_TF),
+    ]);
+    let from = temp_dir.path().join("staging");
+    let to = temp_dir.path().join("prod");
+    assert!(plan_promotion(&from, &to, &parse_scan_query("module.*").unwrap()).unwrap().is_empty());
+    assert!(plan_promotion(&from, &from, &parse_scan_query("module.*").unwrap()).is_err());
+}
@@ -0,0 +1,210 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+use tv::journal::{self, Transaction};
+use tv::policy::parse_policy;
+use tv::protect::{Protection, protected_by, reason_rule, set_protection, set_reason};
+use tv::{SetOptions, set_value, set_value_with_options};
+
+const MAIN_TF: &str = "module \"vpc\" {\n  source  = \"terraform-aws-modules/vpc/aws\"\n  version = \"5.1.0\"\n}\n";
+
+fn repository(protected: &str) -> tempfile::TempDir {
+    common::create_test_dir_with_files(&[
+        ("tv.toml", &format!("protected_paths = [{}]\n", protected)),
+        ("envs/prod/main.tf", MAIN_TF),
+        ("envs/prod/network/main.tf", MAIN_TF),
+        ("envs/dev/main.tf", MAIN_TF),
+    ])
+}
+
+#[test]
+fn test_protected_by_patterns() {
+    let temp_dir = repository("\"envs/prod/**\", \"*/shared.tf\"");
+    let root = temp_dir.path();
+    assert_eq!(protected_by(&root.join("envs/prod/main.tf")).unwrap().as_deref(), Some("envs/prod/**"));
+    assert_eq!(protected_by(&root.join("envs/prod/network/main.tf")).unwrap().as_deref(), Some("envs/prod/**"));
+    assert_eq!(protected_by(&root.join("envs/shared.tf")).unwrap().as_deref(), Some("*/shared.tf"));
+    assert_eq!(protected_by(&root.join("envs/dev/main.tf")).unwrap(), None);
+
+    // A directory protects everything below it
+    let temp_dir = repository("\"envs/prod\"");
+    assert!(protected_by(&temp_dir.path().join("envs/prod/network/main.tf")).unwrap().is_some());
+    assert_eq!(protected_by(&temp_dir.path().join("envs/production/main.tf")).unwrap(), None);
+}
+
+#[test]
+fn test_protected_writes_need_permission() {
+    let temp_dir = repository("\"envs/prod/**\"");
+    let prod = temp_dir.path().join("envs/prod/main.tf");
+    let dev = temp_dir.path().join("envs/dev/main.tf");
+
+    set_protection(Protection::Refuse);
+    set_value("module.vpc.version", "5.2.0", Some(&dev)).unwrap();
+    let error = set_value("module.vpc.version", "5.2.0", Some(&prod)).unwrap_err().to_string();
+    assert!(error.contains("is protected (protected_paths \"envs/prod/**\" in tv.toml)"), "{}", error);
+    assert!(fs::read_to_string(&prod).unwrap().contains("5.1.0"));
+
+    set_protection(Protection::Allow);
+    set_value("module.vpc.version", "5.2.0", Some(&prod)).unwrap();
+    assert!(fs::read_to_string(&prod).unwrap().contains("5.2.0"));
+    set_protection(Protection::Refuse);
+}
+
+#[test]
+fn test_protected_writes_confirmed_once() {
+    let temp_dir = repository("\"envs/prod/**\"");
+    let prod = temp_dir.path().join("envs/prod/main.tf");
+    let network = temp_dir.path().join("envs/prod/network/main.tf");
+
+    fn no(_: &Path) -> bool {
+        false
+    }
+    fn yes(_: &Path) -> bool {
+        true
+    }
+
+    set_protection(Protection::Confirm(no));
+    assert!(set_value("module.vpc.version", "5.2.0", Some(&prod)).is_err());
+    assert!(set_value("module.vpc.version", "5.2.0", Some(&network)).is_err());
+
+    // One yes allows the rest of the invocation
+    set_protection(Protection::Confirm(yes));
+    set_value("module.vpc.version", "5.2.0", Some(&prod)).unwrap();
+    set_value("module.vpc.version", "5.2.0", Some(&network)).unwrap();
+    set_protection(Protection::Refuse);
+}
+
+const POLICY: &str = r#"require_reason "prod" {
+  paths   = ["envs/prod/**"]
+  pattern = "^[A-Z]+-[0-9]+$"
+}
+
+require_reason "any" {
+  paths = ["envs/dev"]
+}
+"#;
+
+#[test]
+fn test_reason_required_by_policy() {
+    let temp_dir = common::create_test_dir_with_files(&[
+        ("tv-policy.hcl", POLICY),
+        ("envs/prod/main.tf", MAIN_TF),
+        ("envs/dev/main.tf", MAIN_TF),
+        ("envs/qa/main.tf", MAIN_TF),
+    ]);
+    let prod = temp_dir.path().join("envs/prod/main.tf");
+    let dev = temp_dir.path().join("envs/dev/main.tf");
+    let qa = temp_dir.path().join("envs/qa/main.tf");
+    assert_eq!(reason_rule(&prod).unwrap().unwrap().name, "prod");
+    assert_eq!(reason_rule(&qa).unwrap().map(|r| r.name), None);
+
+    set_reason(None);
+    set_value("module.vpc.version", "5.2.0", Some(&qa)).unwrap();
+    let error = set_value("module.vpc.version", "5.2.0", Some(&prod)).unwrap_err().to_string();
+    assert!(error.contains("needs a reason (require_reason \"prod\" in tv-policy.hcl)"), "{}", error);
+    assert!(set_value("module.vpc.version", "5.2.0", Some(&dev)).is_err());
+
+    set_reason(Some("bump vpc".to_string()));
+    let error = set_value("module.vpc.version", "5.2.0", Some(&prod)).unwrap_err().to_string();
+    assert!(error.contains("--reason \"bump vpc\" does not match"), "{}", error);
+    set_value("module.vpc.version", "5.2.0", Some(&dev)).unwrap();
+
+    // The reason is recorded with the transaction
+    let journal_dir = tempfile::TempDir::new().unwrap();
+    set_reason(Some("OPS-123".to_string()));
+    let mut txn = Transaction::begin(journal_dir.path(), "tv set");
+    txn.meta.reason = Some("OPS-123".to_string());
+    journal::activate(txn);
+    set_value("module.vpc.version", "5.2.0", Some(&prod)).unwrap();
+    let txn = journal::deactivate().unwrap();
+    assert_eq!(journal::load_transaction(journal_dir.path(), txn.id()).unwrap().reason.as_deref(), Some("OPS-123"));
+    set_reason(None);
+}
+
+#[test]
+fn test_reason_required_by_workspace_policy() {
+    // Patterns are relative to the workspace; a tv-policy.hcl further up is not used under it
+    let temp_dir = common::create_test_dir_with_files(&[
+        ("tv.workspaces.toml", "[[workspace]]\nname = \"infra\"\ndir = \"infra\"\npolicy = \"policies/infra.hcl\"\n"),
+        ("policies/infra.hcl", POLICY),
+        ("tv-policy.hcl", "require_reason \"all\" {\n  paths = [\"*\"]\n}\n"),
+        ("infra/envs/prod/main.tf", MAIN_TF),
+        ("infra/envs/qa/main.tf", MAIN_TF),
+    ]);
+    let prod = temp_dir.path().join("infra/envs/prod/main.tf");
+    let qa = temp_dir.path().join("infra/envs/qa/main.tf");
+    assert_eq!(reason_rule(&prod).unwrap().unwrap().name, "prod");
+
+    set_reason(None);
+    set_value("module.vpc.version", "5.2.0", Some(&qa)).unwrap();
+    let error = set_value("module.vpc.version", "5.2.0", Some(&prod)).unwrap_err().to_string();
+    assert!(error.contains("needs a reason (require_reason \"prod\" in infra.hcl)"), "{}", error);
+    set_reason(Some("OPS-7".to_string()));
+    set_value("module.vpc.version", "5.2.0", Some(&prod)).unwrap();
+    set_reason(None);
+}
+
+#[test]
+fn test_parse_require_reason() {
+    let policy = parse_policy(POLICY).unwrap();
+    assert_eq!(policy.require_reason.len(), 2);
+    assert_eq!(policy.require_reason[0].paths, vec!["envs/prod/**"]);
+    assert!(policy.require_reason[1].pattern.is_none());
+    assert!(parse_policy("require_reason \"x\" {}\n").is_err());
+    assert!(parse_policy("require_reason \"x\" {\n  paths = \"envs/prod\"\n}\n").is_err());
+    assert!(parse_policy("require_reason \"x\" {\n  paths = [\"a\"]\n  pattern = \"(\"\n}\n").is_err());
+}
+
+#[test]
+fn test_undo_of_protected_files_needs_permission() {
+    let temp_dir = repository("\"envs/prod/**\"");
+    let prod = temp_dir.path().join("envs/prod/main.tf");
+    let created = temp_dir.path().join("envs/prod/extra.tf");
+    let journal_dir = tempfile::TempDir::new().unwrap();
+
+    set_protection(Protection::Allow);
+    journal::activate(Transaction::begin(journal_dir.path(), "tv set"));
+    set_value("module.vpc.version", "5.2.0", Some(&prod)).unwrap();
+    let create = SetOptions { create_block: true, ..Default::default() };
+    set_value_with_options("module.vpc.version", "5.2.0", Some(&created), &create).unwrap();
+    let txn = journal::deactivate().unwrap();
+
+    // Neither restored nor removed, and still undoable
+    set_protection(Protection::Refuse);
+    let error = journal::undo_transaction(journal_dir.path(), txn.id(), false).unwrap_err().to_string();
+    assert!(error.contains("is protected (protected_paths \"envs/prod/**\" in tv.toml)"), "{}", error);
+    assert!(fs::read_to_string(&prod).unwrap().contains("5.2.0"));
+    assert!(created.exists());
+
+    set_protection(Protection::Allow);
+    journal::undo_transaction(journal_dir.path(), txn.id(), false).unwrap();
+    assert_eq!(fs::read_to_string(&prod).unwrap(), MAIN_TF);
+    assert!(!created.exists());
+    set_protection(Protection::Refuse);
+}
+
+#[test]
+fn test_undo_under_require_reason_needs_a_reason() {
+    let temp_dir = common::create_test_dir_with_files(&[("tv-policy.hcl", POLICY), ("envs/prod/main.tf", MAIN_TF)]);
+    let prod = temp_dir.path().join("envs/prod/main.tf");
+    let journal_dir = tempfile::TempDir::new().unwrap();
+
+    set_reason(Some("OPS-123".to_string()));
+    journal::activate(Transaction::begin(journal_dir.path(), "tv set"));
+    set_value("module.vpc.version", "5.2.0", Some(&prod)).unwrap();
+    let txn = journal::deactivate().unwrap();
+
+    set_reason(None);
+    let error = journal::undo_transaction(journal_dir.path(), txn.id(), false).unwrap_err().to_string();
+    assert!(error.contains("needs a reason (require_reason \"prod\" in tv-policy.hcl)"), "{}", error);
+    assert!(fs::read_to_string(&prod).unwrap().contains("5.2.0"));
+
+    // The reason for the undo is kept with the transaction undone
+    set_reason(Some("OPS-124".to_string()));
+    journal::undo_transaction(journal_dir.path(), txn.id(), false).unwrap();
+    assert_eq!(fs::read_to_string(&prod).unwrap(), MAIN_TF);
+    let undone = journal::load_transaction(journal_dir.path(), txn.id()).unwrap();
+    assert_eq!(undone.undo_reason.as_deref(), Some("OPS-124"));
+    set_reason(None);
+}
@@ -0,0 +1,150 @@
+mod common;
+
+use tv::config::parse_config;
+use tv::parse_scan_query;
+use tv::policy::{parse_policy, validate};
+use tv::registry::{
+    Client, ModuleAddress, ProviderAddress, RegistryVersion, VersionStatus, parse_timestamp, version_status,
+};
+use tv::updates::{UpdateOptions, check_updates};
+
+const MODULE_VERSIONS: &str = r#"{"modules": [{"versions": [
+  {"version": "5.0.0"},
+  {"version": "5.1.0"},
+  {"version": "5.2.0", "deprecation": {"reason": "breaks subnets"}}
+]}]}"#;
+
+const PROVIDER_VERSIONS: &str = r#"{"versions": [
+  {"version": "5.0.0"},
+  {"version": "5.0.1", "deprecation": "use 5.0.2"},
+  {"version": "5.0.2"}
+]}"#;
+
+fn registry() -> Client {
+    Client::with_base_url(Some(common::serve_http(vec![
+        ("/v1/modules/terraform-aws-modules/vpc/aws/versions", MODULE_VERSIONS.to_string()),
+        ("/v1/modules/terraform-aws-modules/vpc/aws/5.1.0", r#"{"published_at": "2020-01-01T00:00:00Z"}"#.to_string()),
+        ("/v1/providers/hashicorp/aws/versions", PROVIDER_VERSIONS.to_string()),
+    ])))
+}
+
+#[test]
+fn test_registry_addresses_and_status() {
+    let module = ModuleAddress::parse("app.terraform.io/acme/vpc/aws//modules/subnets").unwrap();
+    assert_eq!(module.to_string(), "app.terraform.io/acme/vpc/aws");
+    assert_eq!(ModuleAddress::parse("terraform-aws-modules/vpc/aws").unwrap().host, "registry.terraform.io");
+    assert!(ModuleAddress::parse("git::https://github.com/org/vpc.git").is_none());
+
+    assert_eq!(ProviderAddress::parse("aws").unwrap().to_string(), "registry.terraform.io/hashicorp/aws");
+    assert_eq!(
+        ProviderAddress::parse("registry.opentofu.org/Hashicorp/AWS").unwrap().to_string(),
+        "registry.opentofu.org/hashicorp/aws"
+    );
+
+    let versions = vec![
+        RegistryVersion { version: "1.0.0".to_string(), deprecation: None },
+        RegistryVersion { version: "1.1.0".to_string(), deprecation: Some("broken".to_string()) },
+    ];
+    assert_eq!(version_status(&versions, "v1.0.0"), VersionStatus::Available);
+    assert_eq!(version_status(&versions, "1.1.0"), VersionStatus::Deprecated("broken".to_string()));
+    assert_eq!(version_status(&versions, "0.9.0"), VersionStatus::Removed);
+
+    assert_eq!(parse_timestamp("1970-01-02T00:00:00Z"), Some(86400));
+    assert_eq!(parse_timestamp("2024-02-29T12:00:00.5+02:00"), Some(1709200800));
+    assert_eq!(parse_timestamp("yesterday"), None);
+}
+
+#[test]
+fn test_validate_rejects_yanked_pins() {
+    let files = [
+        (
+            "main.tf",
+            "module \"vpc\" {\n  source  = \"terraform-aws-modules/vpc/aws\"\n  version = \"4.0.0\"\n}\n\nmodule \"vpc2\" {\n  source  = \"terraform-aws-modules/vpc/aws\"\n  version = \"~> 4.0\"\n}\n",
+        ),
+        (
+            "versions.tf",
+            "terraform {\n  required_providers {\n    aws = {\n      source  = \"hashicorp/aws\"\n      version = \"5.0.1\"\n    }\n  }\n}\n",
+        ),
+        (
+            ".terraform.lock.hcl",
+            "provider \"registry.terraform.io/hashicorp/aws\" {\n  version     = \"5.0.2\"\n  constraints = \"5.0.1\"\n}\n",
+        ),
+    ];
+    let temp_dir = common::create_test_dir_with_files(&files);
+    let policy = parse_policy("reject_yanked = true\n").unwrap();
+
+    let reports = validate(&policy, temp_dir.path(), &registry()).unwrap();
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].name, "yanked-versions");
+    assert_eq!(reports[0].checked, 3);
+    let violations: Vec<(&str, &str)> = reports[0]
+        .violations
+        .iter()
+        .map(|v| (v.block.as_str(), v.reason.as_str()))
+        .collect();
+    assert_eq!(
+        violations,
+        vec![
+            ("module.vpc", "version 4.0.0 has been removed from the registry"),
+            ("terraform.required_providers.aws", "version 5.0.1 is deprecated: use 5.0.2"),
+        ]
+    );
+
+    assert!(parse_policy("reject_yanked = \"yes\"\n").is_err());
+}
+
+#[test]
+fn test_registry_updates_skip_deprecated_versions() {
+    let temp_dir = common::create_test_dir_with_files(&[(
+        "main.tf",
+        "module \"vpc\" {\n  source  = \"terraform-aws-modules/vpc/aws\"\n  version = \"5.0.0\"\n}\n",
+    )]);
+    let options = UpdateOptions { registry: registry(), ..Default::default() };
+    let all = parse_scan_query("module.*").unwrap();
+
+    let updates = check_updates(temp_dir.path(), &all, &Default::default(), &options).unwrap();
+    assert_eq!(updates.len(), 1);
+    assert_eq!(updates[0].latest, "5.1.0");
+    assert_eq!(updates[0].query, "module.vpc.version");
+
+    // 5.1.0 was published long enough ago
+    let config = parse_config("minimum_release_age = \"30d\"\n").unwrap();
+    let updates = check_updates(temp_dir.path(), &all, &config, &options).unwrap();
+    assert_eq!(updates[0].latest, "5.1.0");
+
+    // Asked for by name, a deprecated version is still allowed
+    let options = UpdateOptions { to: Some("v5.2.0".to_string()), ..options };
+    let updates = check_updates(temp_dir.path(), &all, &config, &options).unwrap();
+    assert_eq!(updates[0].latest, "5.2.0");
+}
+
+#[test]
+fn test_dependency_confusion_between_private_and_public_registries() {
+    let private = common::serve_http(vec![
+        ("/v1/modules/acme/vpc/aws/versions", r#"{"modules": [{"versions": [{"version": "1.0.0"}]}]}"#.to_string()),
+        ("/v1/modules/acme/eks/aws/versions", r#"{"modules": [{"versions": [{"version": "1.0.0"}]}]}"#.to_string()),
+    ]);
+    let public = common::serve_http(vec![
+        ("/v1/modules/acme/vpc/aws/versions", r#"{"modules": [{"versions": [{"version": "9.9.9"}]}]}"#.to_string()),
+        ("/v1/modules/acme", r#"{"modules": [{"name": "vpc"}]}"#.to_string()),
+    ]);
+    let client = Client::with_base_url(None)
+        .with_host("registry.terraform.io", &public)
+        .with_host("app.terraform.io", &private);
+
+    let content = "module \"vpc\" {\n  source = \"acme/vpc/aws\"\n}\n\nmodule \"eks\" {\n  source = \"acme/eks/aws\"\n}\n\nmodule \"rds\" {\n  source = \"app.terraform.io/acme/rds/aws\"\n}\n";
+    // A file that does not parse is reported elsewhere, not here
+    let temp_dir = common::create_test_dir_with_files(&[("main.tf", content), ("broken.tf", "module \"x\" {\n")]);
+    let hosts = vec!["app.terraform.io".to_string()];
+
+    let findings = tv::doctor::diagnose_dependency_confusion(temp_dir.path(), &hosts, &client).unwrap();
+    assert_eq!(findings.len(), 2);
+    assert_eq!(findings[0].severity, tv::doctor::Severity::Error);
+    assert!(findings[0].message.contains("installs the public registry's module"));
+    assert_eq!(findings[0].fix, "use the full address: app.terraform.io/acme/vpc/aws");
+    assert_eq!(findings[1].severity, tv::doctor::Severity::Warning);
+    assert!(findings[1].message.contains("namespace 'acme' is taken"));
+
+    // Without a private registry there is nothing to confuse
+    assert!(tv::doctor::diagnose_dependency_confusion(temp_dir.path(), &[], &client).unwrap().is_empty());
+}
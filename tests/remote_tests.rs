@@ -0,0 +1,34 @@
+mod common;
+
+use tv::remote::{checkout, checkout_name};
+use tv::scan_files;
+
+#[test]
+fn test_checkout_name_is_distinct_per_url() {
+    let a = checkout_name("https://github.com/org-a/infra.git");
+    let b = checkout_name("https://github.com/org-b/infra.git");
+    assert!(a.starts_with("infra-"));
+    assert_ne!(a, b);
+    assert_eq!(a, checkout_name("https://github.com/org-a/infra.git"));
+}
+
+#[test]
+fn test_checkout_materializes_only_tf_files() {
+    let origin = common::create_git_repo(&[
+        ("envs/prod/main.tf", common::REGISTRY_MODULE_TF),
+        ("README.md", "# infra\n"),
+    ]);
+    let cache = tempfile::TempDir::new().unwrap();
+    let url = origin.path().to_string_lossy().to_string();
+
+    let dir = checkout(&url, None, cache.path()).unwrap();
+    assert!(dir.starts_with(cache.path()));
+    assert!(dir.join("envs/prod/main.tf").is_file());
+    assert!(!dir.join("README.md").exists());
+    let found = scan_files("module.*", &dir).unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].1, "vpc");
+
+    // A second run reuses the checkout
+    assert_eq!(checkout(&url, None, cache.path()).unwrap(), dir);
+}
@@ -0,0 +1,81 @@
+mod common;
+
+use std::fs;
+use tv::rename::rename_module;
+
+const MAIN_TF: &str = r#"module "vpc" {
+  source  = "terraform-aws-modules/vpc/aws"
+  version = "5.1.0"
+}
+
+module "eks" {
+  source     = "terraform-aws-modules/eks/aws"
+  vpc_id     = module.vpc.vpc_id
+  subnet_ids = module.vpc.private_subnets
+  name       = "eks-${module.vpc.name}"
+  depends_on = [module.vpc]
+}
+
+moved {
+  from = module.vpc
+  to   = module.vpc_old
+}
+"#;
+
+const OUTPUTS_TF: &str = r#"output "vpc_id" {
+  value = module.vpc.vpc_id # the id
+}
+
+output "other" {
+  value = module.vpcx.id
+}
+"#;
+
+#[test]
+fn test_rename_module_updates_references() {
+    let temp_dir = common::create_test_dir_with_files(&[
+        ("main.tf", MAIN_TF),
+        ("outputs.tf", OUTPUTS_TF),
+        ("modules/app/main.tf", "output \"id\" {\n  value = module.vpc.id\n}\n"),
+    ]);
+    let renamed = rename_module("module.vpc", "network", temp_dir.path(), false).unwrap();
+    assert_eq!(renamed.len(), 2);
+
+    let main = fs::read_to_string(temp_dir.path().join("main.tf")).unwrap();
+    assert_eq!(
+        main,
+        MAIN_TF
+            .replace("module \"vpc\"", "module \"network\"")
+            .replace("module.vpc.", "module.network.")
+            .replace("[module.vpc]", "[module.network]")
+    );
+    assert!(main.contains("from = module.vpc\n"));
+    let outputs = fs::read_to_string(temp_dir.path().join("outputs.tf")).unwrap();
+    assert!(outputs.contains("value = module.network.vpc_id # the id"));
+    assert!(outputs.contains("module.vpcx.id"));
+    // Another Terraform module
+    let app = fs::read_to_string(temp_dir.path().join("modules/app/main.tf")).unwrap();
+    assert!(app.contains("module.vpc.id"));
+
+    let main_file = renamed.iter().find(|r| r.file.ends_with("main.tf") && r.label).unwrap();
+    assert_eq!(main_file.references, 4);
+}
+
+#[test]
+fn test_rename_module_moved() {
+    let temp_dir = common::create_test_dir_with_files(&[("main.tf", common::REGISTRY_MODULE_TF)]);
+    rename_module("module.vpc", "network", temp_dir.path(), true).unwrap();
+    let main = fs::read_to_string(temp_dir.path().join("main.tf")).unwrap();
+    assert!(main.starts_with("module \"network\" {"));
+    assert!(main.ends_with("}\n\nmoved {\n  from = module.vpc\n  to   = module.network\n}\n"));
+}
+
+#[test]
+fn test_rename_module_errors() {
+    let temp_dir = common::create_test_dir_with_files(&[("main.tf", MAIN_TF)]);
+    assert!(rename_module("module.missing", "x", temp_dir.path(), false).is_err());
+    assert!(rename_module("module.vpc", "eks", temp_dir.path(), false).is_err());
+    assert!(rename_module("module.vpc", "a b", temp_dir.path(), false).is_err());
+    assert!(rename_module("module.vpc.version", "x", temp_dir.path(), false).is_err());
+    assert_eq!(fs::read_to_string(temp_dir.path().join("main.tf")).unwrap(), MAIN_TF);
+}
@@ -1,6 +1,6 @@
 mod common;
 
-use tv::{scan_files, parse_scan_query, find_all_tf_files};
+use tv::{Per, null_separated, ScanOptions, Sort, ValueKind, block_arguments, scan_files, scan_files_with_options, parse_scan_query, find_all_tf_files};
 
 #[test]
 fn test_scan_all_modules() {
@@ -283,3 +283,216 @@ fn test_scan_returns_module_names() {
     assert!(module_names.contains(&"vpc".to_string()));
     assert!(module_names.contains(&"eks".to_string()));
 }
+
+#[test]
+fn test_scan_url_filter_matches_canonical_form() {
+    let files = vec![
+        ("main.tf", common::SIMPLE_MODULE_TF),
+    ];
+    let temp_dir = common::create_test_dir_with_files(&files);
+
+    // SIMPLE_MODULE_TF uses git::https://github.com/terraform-aws-modules/terraform-aws-vpc.git
+    let query = r#"module.*.source[url=="https://GitHub.com/terraform-aws-modules/terraform-aws-vpc/"]"#;
+    let results = scan_files(query, temp_dir.path()).unwrap();
+    assert_eq!(results.len(), 1);
+
+    let shorthand = r#"module.*.source[url=="github.com/terraform-aws-modules/terraform-aws-vpc"]"#;
+    let results = scan_files(shorthand, temp_dir.path()).unwrap();
+    assert_eq!(results.len(), 1);
+}
+
+#[test]
+fn test_scan_url_filter_exact() {
+    let files = vec![
+        ("main.tf", common::SIMPLE_MODULE_TF),
+    ];
+    let temp_dir = common::create_test_dir_with_files(&files);
+    let exact = ScanOptions { exact: true, ..Default::default() };
+
+    let query = r#"module.*.source[url=="https://github.com/terraform-aws-modules/terraform-aws-vpc"]"#;
+    let results = scan_files_with_options(query, temp_dir.path(), &exact).unwrap();
+    assert_eq!(results.len(), 0);
+
+    let literal = r#"module.*.source[url=="git::https://github.com/terraform-aws-modules/terraform-aws-vpc.git"]"#;
+    let results = scan_files_with_options(literal, temp_dir.path(), &exact).unwrap();
+    assert_eq!(results.len(), 1);
+}
+
+#[test]
+fn test_scan_object_keys_and_selectors() {
+    let tagged = "module \"vpc\" {\n  source = \"./vpc\"\n  tags   = { Team = \"platform\" }\n}\n";
+    let files = vec![
+        ("main.tf", tagged),
+        ("versions.tf", common::TERRAFORM_BLOCK_TF),
+        ("other.tf", common::REGISTRY_MODULE_TF),
+    ];
+    let temp_dir = common::create_test_dir_with_files(&files);
+
+    // Keys inside an object attribute resolve like they do for get
+    let results = scan_files("terraform.required_providers.aws.version", temp_dir.path()).unwrap();
+    assert_eq!(results.len(), 1);
+
+    let results = scan_files("module.*.tags[\"Team\"]", temp_dir.path()).unwrap();
+    assert_eq!(results.len(), 1);
+    let results = scan_files("module.*.tags[Team==\"platform\"]", temp_dir.path()).unwrap();
+    assert_eq!(results.len(), 1);
+    let results = scan_files("module.*.tags[Team==\"network\"]", temp_dir.path()).unwrap();
+    assert_eq!(results.len(), 0);
+}
+
+#[test]
+fn test_scan_parameter_present_and_absent() {
+    let pinned = "module \"vpc\" {\n  source = \"git::https://github.com/org/vpc.git?ref=v1.0.0\"\n}\n";
+    let unpinned = "module \"dns\" {\n  source = \"git::https://github.com/org/dns.git\"\n}\n";
+    let files = vec![
+        ("pinned.tf", pinned),
+        ("unpinned.tf", unpinned),
+    ];
+    let temp_dir = common::create_test_dir_with_files(&files);
+
+    let results = scan_files("module.*.source[ref]", temp_dir.path()).unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].1, "vpc");
+
+    let results = scan_files("module.*.source[!ref]", temp_dir.path()).unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].1, "dns");
+
+    // Negated filters also match when the parameter is missing
+    let results = scan_files("module.*.source[ref!=\"v1.0.0\"]", temp_dir.path()).unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].1, "dns");
+}
+
+#[test]
+fn test_parse_scan_query_negated_selectors() {
+    let absent = parse_scan_query("module.*.source[!ref]").unwrap();
+    assert!(absent.negated);
+    assert_eq!(absent.index, Some("ref".to_string()));
+    assert_eq!(absent.to_string(), "module.*.source[!\"ref\"]");
+
+    let not_equal = parse_scan_query("module.*.source[!ref==\"v1\"]").unwrap();
+    assert_eq!(not_equal, parse_scan_query("module.*.source[ref!=\"v1\"]").unwrap());
+    assert_eq!(not_equal.to_string(), "module.*.source[ref!=\"v1\"]");
+
+    assert!(tv::parse_query("module.vpc.source[!ref]").is_err());
+}
+
+#[test]
+fn test_scan_per_match_block_and_file() {
+    let split = "terraform {\n  required_providers {\n    aws = { source = \"hashicorp/aws\" }\n  }\n}\n\nterraform {\n  required_providers {\n    google = { source = \"hashicorp/google\" }\n  }\n}\n";
+    let files = vec![
+        ("versions.tf", split),
+        ("main.tf", common::MULTIPLE_MODULES_TF),
+    ];
+    let temp_dir = common::create_test_dir_with_files(&files);
+    let per = |per| ScanOptions { per, ..Default::default() };
+
+    let query = "terraform.required_providers";
+    assert_eq!(scan_files_with_options(query, temp_dir.path(), &per(Per::Match)).unwrap().len(), 2);
+    assert_eq!(scan_files_with_options(query, temp_dir.path(), &per(Per::Block)).unwrap().len(), 1);
+
+    let results = scan_files_with_options("module.*", temp_dir.path(), &per(Per::Block)).unwrap();
+    assert!(results.len() > 1);
+    let results = scan_files_with_options("module.*", temp_dir.path(), &per(Per::File)).unwrap();
+    assert_eq!(results.len(), 1);
+    assert!(results[0].0.ends_with("main.tf"));
+}
+
+#[test]
+fn test_scan_skips_files_without_required_literals() {
+    let files = vec![
+        ("main.tf", common::SIMPLE_MODULE_TF),
+        // Never parsed when the query names a module "vpc"
+        ("broken.tf", "module \"dns\" { not valid hcl"),
+    ];
+    let temp_dir = common::create_test_dir_with_files(&files);
+
+    let results = scan_files("module.vpc.source", temp_dir.path()).unwrap();
+    assert_eq!(results.len(), 1);
+    // Files that may match are still parsed and report their errors
+    assert!(scan_files("module.dns", temp_dir.path()).is_err());
+}
+
+#[test]
+fn test_required_literals() {
+    let options = ScanOptions::default();
+    let query = parse_scan_query("module.vpc.source[ref==\"v1.*\"]").unwrap();
+    assert_eq!(tv::required_literals(&query, &options), vec!["module", "vpc", "source", "v1."]);
+
+    // Canonicalized urls and negated filters cannot be pre-screened
+    let query = parse_scan_query("module.*.source[url==\"github.com/org/repo\"]").unwrap();
+    assert_eq!(tv::required_literals(&query, &options), vec!["module", "source"]);
+    let query = parse_scan_query("module.*.source[ref!=\"v1\"]").unwrap();
+    assert_eq!(tv::required_literals(&query, &options), vec!["module", "source"]);
+}
+
+#[test]
+fn test_scan_sort_orders() {
+    let files = vec![
+        ("b.tf", "module \"alpha\" {\n  source  = \"terraform-aws-modules/vpc/aws\"\n  version = \"5.10.0\"\n}\n"),
+        ("a.tf", "module \"gamma\" {\n  source = \"git::https://github.com/org/repo.git?ref=v5.2.0\"\n}\n\nmodule \"beta\" {\n  source = \"./local\"\n}\n"),
+        ("c/d.tf", "module \"delta\" {\n  source  = \"terraform-aws-modules/vpc/aws\"\n  version = \"~> 4.0\"\n}\n"),
+    ];
+    let temp_dir = common::create_test_dir_with_files(&files);
+    let sorted = |sort| {
+        let options = ScanOptions { sort, ..Default::default() };
+        scan_files_with_options("module.*", temp_dir.path(), &options)
+            .unwrap()
+            .into_iter()
+            .map(|(_, name)| name)
+            .collect::<Vec<String>>()
+    };
+
+    // By path, then block name within a file
+    assert_eq!(sorted(Sort::Path), vec!["beta", "gamma", "alpha", "delta"]);
+    assert_eq!(sorted(Sort::Name), vec!["alpha", "beta", "delta", "gamma"]);
+    // Versions compare numerically; blocks without one come last
+    assert_eq!(sorted(Sort::Version), vec!["delta", "gamma", "alpha", "beta"]);
+}
+
+#[test]
+fn test_scan_block_arguments() {
+    let files = vec![(
+        "main.tf",
+        "module \"vpc\" {\n  source = \"terraform-aws-modules/vpc/aws\"\n  name   = \"main\"\n  cidr   = \"10.0.0.0/16\"\n}\n\nmodule \"db\" {\n  source = \"./db\"\n  name   = local.db\n}\n",
+    )];
+    let temp_dir = common::create_test_dir_with_files(&files);
+    let args = vec!["cidr".to_string(), "name".to_string()];
+    let found: Vec<Vec<(String, String, ValueKind)>> = scan_files("module.*", temp_dir.path())
+        .unwrap()
+        .iter()
+        .map(|(file, name)| {
+            block_arguments(file, "module", name, &args)
+                .unwrap()
+                .into_iter()
+                .map(|(arg, typed)| (arg, typed.value, typed.kind))
+                .collect()
+        })
+        .collect();
+
+    // Arguments come in the order asked; db sets no cidr
+    assert_eq!(found[0], vec![("name".to_string(), "local.db".to_string(), ValueKind::Expression)]);
+    assert_eq!(
+        found[1],
+        vec![
+            ("cidr".to_string(), "10.0.0.0/16".to_string(), ValueKind::String),
+            ("name".to_string(), "main".to_string(), ValueKind::String),
+        ]
+    );
+}
+
+#[test]
+fn test_scan_null_separated_paths() {
+    let files = vec![("a b.tf", common::SIMPLE_MODULE_TF), ("new\nline.tf", common::REGISTRY_MODULE_TF)];
+    let temp_dir = common::create_test_dir_with_files(&files);
+    let options = ScanOptions { per: Per::File, ..Default::default() };
+    let results = scan_files_with_options("module.*", temp_dir.path(), &options).unwrap();
+
+    let out = null_separated(results.iter().map(|(file, _)| file.as_path()));
+    let paths: Vec<&[u8]> = out.split(|b| *b == 0).collect();
+    assert_eq!(paths.len(), 3);
+    assert!(paths[0].ends_with(b"/a b.tf"));
+    assert!(paths[1].ends_with(b"/new\nline.tf"));
+    assert!(paths[2].is_empty());
+}
@@ -0,0 +1,107 @@
+mod common;
+
+use tv::config::{load_config, parse_config};
+use tv::self_update::{Releases, asset_name, choose, parse_checksums, pin, pin_warning, sha256_hex, update};
+
+fn serve_releases(assets_url: &str, with_checksums: bool) -> Releases {
+    // One server for the assets, one for the release list that points at them
+    let body = format!(
+        r#"[
+  {{"tag_name": "v1.5.0", "draft": false, "prerelease": false, "assets": []}},
+  {{"tag_name": "v1.4.2", "draft": false, "prerelease": false, "assets": [
+    {{"name": "{asset}", "browser_download_url": "{url}/bin"}}{sums}
+  ]}},
+  {{"tag_name": "v1.4.1", "draft": false, "prerelease": false, "assets": []}},
+  {{"tag_name": "v1.6.0-rc1", "draft": false, "prerelease": true, "assets": []}}
+]"#,
+        asset = asset_name(),
+        url = assets_url,
+        sums = if with_checksums {
+            format!(r#", {{"name": "SHA256SUMS", "browser_download_url": "{}/sums"}}"#, assets_url)
+        } else {
+            String::new()
+        }
+    );
+    let api = common::serve_http(vec![("/repos/manoelhc/tv/releases", body)]);
+    Releases::with_api_url(&api)
+}
+
+#[test]
+fn test_choose_release() {
+    let releases = serve_releases("http://unused", true);
+    let all = releases.list().unwrap();
+    assert_eq!(all.iter().map(|r| r.tag.as_str()).collect::<Vec<_>>(), vec!["v1.5.0", "v1.4.2", "v1.4.1"]);
+
+    assert_eq!(choose(&all, None, None).unwrap().tag, "v1.5.0");
+    assert_eq!(choose(&all, Some("1.4.x"), None).unwrap().tag, "v1.4.2");
+    assert_eq!(choose(&all, Some("1.4.x"), Some("1.4.1")).unwrap().tag, "v1.4.1");
+    assert!(choose(&all, Some("1.4.x"), Some("1.5.0")).is_err());
+    assert!(choose(&all, Some("2.x"), None).is_err());
+}
+
+#[test]
+fn test_update_verifies_checksum() {
+    let binary = "new tv binary".to_string();
+    let assets = common::serve_http(vec![("/bin", binary.clone())]);
+    let temp_dir = common::create_test_dir_with_files(&[("tv", "old tv binary")]);
+    let exe = temp_dir.path().join("tv");
+
+    // A binary that does not match SHA256SUMS is not installed
+    let bad_sums = format!("{}  {}\n", sha256_hex(b"something else"), asset_name());
+    let assets_bad = common::serve_http(vec![("/bin", binary.clone()), ("/sums", bad_sums)]);
+    let releases = serve_releases(&assets_bad, true);
+    let all = releases.list().unwrap();
+    let err = update(&releases, &all[1], &exe).unwrap_err().to_string();
+    assert!(err.contains("Checksum mismatch"));
+    assert_eq!(std::fs::read_to_string(&exe).unwrap(), "old tv binary");
+
+    // Nor one whose release has no checksums
+    let releases = serve_releases(&assets, false);
+    let all = releases.list().unwrap();
+    assert!(update(&releases, &all[1], &exe).unwrap_err().to_string().contains("unverified"));
+
+    let good_sums = format!("{} *{}\n", sha256_hex(binary.as_bytes()), asset_name());
+    let assets_good = common::serve_http(vec![("/bin", binary.clone()), ("/sums", good_sums)]);
+    let releases = serve_releases(&assets_good, true);
+    let all = releases.list().unwrap();
+    update(&releases, &all[1], &exe).unwrap();
+    assert_eq!(std::fs::read_to_string(&exe).unwrap(), binary);
+
+    assert_eq!(parse_checksums("ABC  tv-x\n").get("tv-x").map(String::as_str), Some("abc"));
+}
+
+#[test]
+fn test_pin_in_tv_toml() {
+    let temp_dir = common::create_test_dir_with_files(&[(
+        "tv.toml",
+        "# Settings\nminimum_release_age = \"7d\"\n\n[[track]]\nmatch = \"*\"\n",
+    )]);
+    let dir = temp_dir.path();
+
+    pin(dir, "1.4.x").unwrap();
+    let content = std::fs::read_to_string(dir.join("tv.toml")).unwrap();
+    assert_eq!(content, "# Settings\nminimum_release_age = \"7d\"\n\ntv_version = \"1.4.x\"\n\n[[track]]\nmatch = \"*\"\n");
+    pin(dir, ">= 1.4, < 2").unwrap();
+    let config = load_config(dir).unwrap();
+    assert_eq!(config.tv_version.as_deref(), Some(">= 1.4, < 2"));
+    assert_eq!(config.track.len(), 1);
+
+    assert!(pin_warning(&config, "1.9.0").is_none());
+    assert!(pin_warning(&config, "2.0.0").unwrap().contains("tv_version"));
+    assert!(pin(dir, "one point four").is_err());
+    assert!(parse_config("tv_version = \"latest\"\n").is_err());
+
+    // Without a tv.toml one is created
+    let empty = common::create_test_dir_with_files(&[]);
+    pin(empty.path(), "1.*").unwrap();
+    assert_eq!(std::fs::read_to_string(empty.path().join("tv.toml")).unwrap(), "tv_version = \"1.*\"\n");
+}
+
+#[test]
+fn test_release_workflow_publishes_what_update_downloads() {
+    // The bare binary for this platform and SHA256SUMS, as update looks for them
+    let workflow = std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/.github/workflows/release.yml")).unwrap();
+    assert!(workflow.contains(&format!("asset: {}\n", asset_name())), "release.yml does not publish {}", asset_name());
+    assert!(workflow.contains("sha256sum tv-* > SHA256SUMS"));
+    assert!(workflow.contains("gh release upload \"${{ github.event.release.tag_name }}\" SHA256SUMS"));
+}
@@ -1,6 +1,7 @@
 mod common;
 
-use tv::{set_value, get_value, update_param_in_source, update_url_in_source, update_path_in_source};
+use tv::policy::Expectation;
+use tv::{SetOptions, ValueKind, check_current, has_interpolation, set_value, set_value_with_options, get_value, update_param_in_source, update_url_in_source, update_path_in_source, extract_url_from_source, extract_path_from_source, extract_param_from_source};
 
 #[test]
 fn test_set_simple_attribute() {
@@ -146,3 +147,264 @@ fn test_update_path_with_leading_slash() {
     let result = update_path_in_source(source, "/modules/vpc");
     assert_eq!(result, "git::https://github.com/org/repo.git//modules/vpc?ref=v1.0.0");
 }
+
+#[test]
+fn test_set_keeps_indentation_and_comments() {
+    let content = "module \"vpc\" {\n  source  = \"git::https://github.com/org/vpc.git?ref=v1.0.0\"\n  version = \"1.0.0\" # pinned\n}\n";
+    let (_dir, file) = common::create_test_tf_file(content);
+
+    set_value("module.vpc.version", "2.0.0", Some(file.as_path())).unwrap();
+    set_value("module.vpc.source[\"ref\"]", "v1.1.0", Some(file.as_path())).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&file).unwrap(),
+        "module \"vpc\" {\n  source  = \"git::https://github.com/org/vpc.git?ref=v1.1.0\"\n  version = \"2.0.0\" # pinned\n}\n"
+    );
+}
+
+#[test]
+fn test_set_in_object_keeps_layout() {
+    let content = "terraform {\n  required_providers {\n    aws = { source = \"hashicorp/aws\", version = \"5.0.0\" }\n    ünï = {\n      source  = \"cörp/ünï\"\n      version = \"1.0.0\" # pinned\n    }\n  }\n}\n";
+    let (_dir, file) = common::create_test_tf_file(content);
+
+    set_value("terraform.required_providers.aws.version", "6.0.0", Some(file.as_path())).unwrap();
+    // Aligned `key  =` and non-ASCII keys and values
+    set_value("terraform.required_providers.ünï.source", "ñ/ünï", Some(file.as_path())).unwrap();
+    set_value("terraform.required_providers.ünï.version", "2.0.0-ß", Some(file.as_path())).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&file).unwrap(),
+        "terraform {\n  required_providers {\n    aws = { source = \"hashicorp/aws\", version = \"6.0.0\" }\n    ünï = {\n      source  = \"ñ/ünï\"\n      version = \"2.0.0-ß\" # pinned\n    }\n  }\n}\n"
+    );
+    assert!(set_value("terraform.required_providers.aws.region", "x", Some(file.as_path())).is_err());
+}
+
+#[test]
+fn test_unicode_source_params() {
+    let source = "git::https://gïthub.com/örg/vpc.git//mödules/ñ?ref=v1.0.0-ß";
+    assert_eq!(extract_url_from_source(source), "git::https://gïthub.com/örg/vpc.git");
+    assert_eq!(extract_path_from_source(source), Some("mödules/ñ".to_string()));
+    assert_eq!(extract_param_from_source(source, "ref").unwrap(), Some("v1.0.0-ß".to_string()));
+    assert_eq!(update_url_in_source(source, "gitlab.com/ü/vpc.git"), "gitlab.com/ü/vpc.git//mödules/ñ?ref=v1.0.0-ß");
+    assert_eq!(update_path_in_source(source, "ä"), "git::https://gïthub.com/örg/vpc.git//ä?ref=v1.0.0-ß");
+    assert_eq!(update_param_in_source(source, "ref", "v2-€").unwrap(), "\"git::https://gïthub.com/örg/vpc.git//mödules/ñ?ref=v2-€\"");
+    assert_eq!(extract_url_from_source("ünï/vpc/aws"), "ünï/vpc/aws");
+    assert_eq!(extract_url_from_source("gïthub.com/örg/vpc?ref=ü"), "gïthub.com/örg/vpc");
+}
+
+#[test]
+fn test_set_escapes_special_characters() {
+    let content = "locals {\n  banner = \"x\"\n  tags   = { Team = \"a\" }\n}\n";
+    let (_dir, file) = common::create_test_tf_file(content);
+
+    let value = r#"say "hi" to ${var.name} \ %{ if } C:\dir"#;
+    set_value("locals.banner", value, Some(file.as_path())).unwrap();
+    set_value("locals.tags[\"Team\"]", "a\"b", Some(file.as_path())).unwrap();
+    let written = std::fs::read_to_string(&file).unwrap();
+    assert!(written.contains(r#"banner = "say \"hi\" to $${var.name} \\ %%{ if } C:\\dir""#));
+    assert_eq!(get_value("locals.banner", Some(file.as_path())).unwrap(), Some(value.to_string()));
+    assert_eq!(get_value("locals.tags[\"Team\"]", Some(file.as_path())).unwrap(), Some("a\"b".to_string()));
+
+    // Without --heredoc, newlines are escaped
+    set_value("locals.banner", "one\ntwo\n", Some(file.as_path())).unwrap();
+    assert!(std::fs::read_to_string(&file).unwrap().contains("banner = \"one\\ntwo\\n\""));
+}
+
+#[test]
+fn test_set_heredoc() {
+    let content = "locals {\n  policy = \"x\"\n  region = \"eu-west-1\"\n}\n";
+    let (_dir, file) = common::create_test_tf_file(content);
+    let heredoc = SetOptions { heredoc: true, ..Default::default() };
+
+    set_value_with_options("locals.policy", "line ${one}\nEOT\n", Some(file.as_path()), &heredoc).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&file).unwrap(),
+        "locals {\n  policy = <<EOT_\nline $${one}\nEOT\nEOT_\n  region = \"eu-west-1\"\n}\n"
+    );
+
+    // A heredoc cannot hold a value without a final newline
+    set_value_with_options("locals.policy", "a\nb", Some(file.as_path()), &heredoc).unwrap();
+    assert!(std::fs::read_to_string(&file).unwrap().contains("policy = \"a\\nb\""));
+}
+
+#[test]
+fn test_set_param_in_escaped_source() {
+    let result = update_param_in_source("\"git::https://github.com/org/repo.git?ref=v1\"", "ref", "v\"2").unwrap();
+    assert_eq!(result, "\"git::https://github.com/org/repo.git?ref=v\\\"2\"");
+}
+
+#[test]
+fn test_set_allow_interpolation() {
+    let content = "locals {\n  name = \"x\"\n}\n";
+    let (_dir, file) = common::create_test_tf_file(content);
+    let live = SetOptions { allow_interpolation: true, ..Default::default() };
+
+    set_value_with_options("locals.name", "app-${var.env}", Some(file.as_path()), &live).unwrap();
+    assert!(std::fs::read_to_string(&file).unwrap().contains("name = \"app-${var.env}\""));
+    assert!(set_value_with_options("locals.name", "app-${", Some(file.as_path()), &live).is_err());
+
+    // Escaped unless asked for
+    set_value("locals.name", "app-${var.env}", Some(file.as_path())).unwrap();
+    assert!(std::fs::read_to_string(&file).unwrap().contains("name = \"app-$${var.env}\""));
+    assert!(has_interpolation("%{ if x }") && !has_interpolation("$ {x}"));
+}
+
+const INSTANCE_TF: &str = r#"resource "aws_instance" "web" {
+  root_block_device {
+    volume_size = 20
+  }
+
+  ebs_block_device {
+    device_name = "/dev/sdb"
+    volume_size = 50
+  }
+
+  ebs_block_device {
+    device_name = "/dev/sdc"
+    volume_size = 60
+  }
+}
+"#;
+
+#[test]
+fn test_set_typed_value_in_nested_block() {
+    let (_dir, file) = common::create_test_tf_file(INSTANCE_TF);
+    let number = SetOptions { kind: Some(ValueKind::Number), ..Default::default() };
+
+    set_value_with_options("resource.aws_instance.web.root_block_device.volume_size", "100", Some(file.as_path()), &number).unwrap();
+    assert!(std::fs::read_to_string(&file).unwrap().contains("    volume_size = 100\n"));
+    assert!(set_value_with_options("resource.aws_instance.web.root_block_device.volume_size", "big", Some(file.as_path()), &number).is_err());
+
+    // Without --type the value is a string
+    set_value("resource.aws_instance.web.root_block_device.volume_size", "100", Some(file.as_path())).unwrap();
+    assert!(std::fs::read_to_string(&file).unwrap().contains("    volume_size = \"100\"\n"));
+}
+
+#[test]
+fn test_set_repeated_nested_block() {
+    let (_dir, file) = common::create_test_tf_file(INSTANCE_TF);
+    let number = SetOptions { kind: Some(ValueKind::Number), ..Default::default() };
+
+    set_value_with_options("resource.aws_instance.web.ebs_block_device#1.volume_size", "80", Some(file.as_path()), &number).unwrap();
+    assert_eq!(get_value("resource.aws_instance.web.ebs_block_device#1.volume_size", Some(file.as_path())).unwrap(), Some("80".to_string()));
+    // Without #N, the first
+    assert_eq!(get_value("resource.aws_instance.web.ebs_block_device.volume_size", Some(file.as_path())).unwrap(), Some("50".to_string()));
+    assert!(set_value("resource.aws_instance.web.ebs_block_device#2.volume_size", "1", Some(file.as_path())).is_err());
+}
+
+#[test]
+fn test_set_create_appends_missing_attribute() {
+    let (_dir, file) = common::create_test_tf_file(r#"module "vpc" {
+  source  = "terraform-aws-modules/vpc/aws"
+  name    = "main" # the vpc
+
+  tags {
+    Owner = "network"
+  }
+}
+"#);
+    assert!(set_value("module.vpc.version", "5.0.0", Some(file.as_path())).is_err());
+
+    let create = SetOptions { create: true, ..Default::default() };
+    set_value_with_options("module.vpc.version", "5.0.0", Some(file.as_path()), &create).unwrap();
+    set_value_with_options("module.vpc.enable_nat_gateway", "true", Some(file.as_path()), &SetOptions { kind: Some(ValueKind::Bool), ..create }).unwrap();
+    // An attribute that exists is set as usual
+    set_value_with_options("module.vpc.name", "vpc", Some(file.as_path()), &create).unwrap();
+    assert_eq!(std::fs::read_to_string(&file).unwrap(), r#"module "vpc" {
+  source  = "terraform-aws-modules/vpc/aws"
+  name    = "vpc" # the vpc
+  version = "5.0.0"
+  enable_nat_gateway = true
+
+  tags {
+    Owner = "network"
+  }
+}
+"#);
+}
+
+#[test]
+fn test_set_create_block_bootstraps_a_file() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let file = dir.path().join("versions.tf");
+    let create = SetOptions { create_block: true, ..Default::default() };
+
+    set_value_with_options("terraform.required_providers.aws.version", "~> 5.0", Some(file.as_path()), &create).unwrap();
+    set_value_with_options("terraform.required_providers.aws.source", "hashicorp/aws", Some(file.as_path()), &create).unwrap();
+    set_value_with_options("terraform.required_version", ">= 1.5", Some(file.as_path()), &create).unwrap();
+    set_value_with_options("resource.aws_instance.web.root_block_device.volume_size", "80", Some(file.as_path()), &SetOptions { kind: Some(ValueKind::Number), ..create }).unwrap();
+    assert_eq!(std::fs::read_to_string(&file).unwrap(), r#"terraform {
+  required_version = ">= 1.5"
+  required_providers {
+    aws = {
+      version = "~> 5.0"
+      source = "hashicorp/aws"
+    }
+  }
+}
+
+resource "aws_instance" "web" {
+  root_block_device {
+    volume_size = 80
+  }
+}
+"#);
+    assert_eq!(get_value("terraform.required_providers.aws.source", Some(file.as_path())).unwrap(), Some("hashicorp/aws".to_string()));
+}
+
+#[test]
+fn test_set_create_block_errors() {
+    let (_dir, file) = common::create_test_tf_file(common::SIMPLE_MODULE_TF);
+    let create = SetOptions { create_block: true, ..Default::default() };
+    // Indexed values and addressed blocks cannot be created from a query
+    assert!(set_value_with_options("module.other.source[\"ref\"]", "v1", Some(file.as_path()), &create).is_err());
+    assert!(set_value_with_options("import.id", "i-123", Some(file.as_path()), &create).is_err());
+    // Without the flag a missing block is still an error
+    assert!(set_value("module.other.source", "x", Some(file.as_path())).is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_set_replaces_file_atomically() {
+    use std::os::unix::fs::PermissionsExt;
+    let (dir, file) = common::create_test_tf_file(common::SIMPLE_MODULE_TF);
+    std::fs::set_permissions(&file, std::fs::Permissions::from_mode(0o640)).unwrap();
+    let link = dir.path().join("link.tf");
+    std::os::unix::fs::symlink(&file, &link).unwrap();
+
+    set_value("module.vpc.name", "through-link", Some(link.as_path())).unwrap();
+    // The link still points at the file, which keeps its mode; no temp file is left
+    assert!(std::fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+    assert_eq!(get_value("module.vpc.name", Some(file.as_path())).unwrap(), Some("through-link".to_string()));
+    assert_eq!(std::fs::metadata(&file).unwrap().permissions().mode() & 0o777, 0o640);
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+}
+
+#[test]
+fn test_value_kind_from_str() {
+    assert_eq!("bool".parse::<ValueKind>().unwrap(), ValueKind::Bool);
+    assert_eq!("expression".parse::<ValueKind>().unwrap(), ValueKind::Expression);
+    assert!("integer".parse::<ValueKind>().is_err());
+}
+
+#[test]
+fn test_check_current() {
+    let (_dir, file) = common::create_test_tf_file(r#"locals {
+  vpc_ref = "v5.0.0"
+}
+
+module "vpc" {
+  source = "git::https://github.com/org/vpc.git?ref=v5.0.0"
+  version = local.vpc_ref
+}
+"#);
+    let file = Some(file.as_path());
+    let equals = |value: &str| Expectation::Equals(value.to_string());
+    check_current(r#"module.vpc.source["ref"]"#, file, &equals("v5.0.0")).unwrap();
+    check_current(r#"module.vpc.source["ref"]"#, file, &Expectation::parse("matches:^v5\\.").unwrap()).unwrap();
+    // Read through the local, as set writes through it
+    check_current("module.vpc.version", file, &equals("v5.0.0")).unwrap();
+
+    let error = check_current(r#"module.vpc.source["ref"]"#, file, &equals("v4.0.0")).unwrap_err();
+    assert_eq!(error.to_string(), r#"Unexpected current value for module.vpc.source["ref"]: value is "v5.0.0", expected "v4.0.0""#);
+    let error = check_current("module.vpc.name", file, &equals("vpc")).unwrap_err();
+    assert_eq!(error.to_string(), r#"Unexpected current value for module.vpc.name: value is missing, expected "vpc""#);
+}
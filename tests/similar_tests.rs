@@ -0,0 +1,125 @@
+mod common;
+
+use tv::doctor::{Severity, diagnose_dir};
+use tv::policy::{parse_policy, validate};
+use tv::registry::Client;
+use tv::similar::{levenshtein, near_duplicate, similar_sources, similarity};
+
+const MAIN_TF: &str = r#"terraform {
+  required_providers {
+    aws = {
+      source  = "hashicorp/aws"
+      version = "~> 5.0"
+    }
+  }
+}
+
+module "vpc" {
+  source  = "terraform-aws-modules/vpc/aws"
+  version = "5.1.0"
+}
+
+module "eks" {
+  source  = "terraform-aws-modules/eks/aws"
+  version = "20.0.0"
+}
+
+module "app" {
+  source = "git::https://github.com/org/app.git?ref=v1.0.0"
+}
+
+module "local" {
+  source = "./modules/app"
+}
+"#;
+
+const TYPOS_TF: &str = r#"terraform {
+  required_providers {
+    aws = {
+      source = "hashcorp/aws"
+    }
+  }
+}
+
+module "vpc_dr" {
+  source  = "terraform-aws-module/vpc/aws"
+  version = "5.1.0"
+}
+
+module "app_legacy" {
+  source = "git::http://github.com/org/app.git?ref=v0.9.0"
+}
+
+module "local" {
+  source = "./modules/apps"
+}
+"#;
+
+#[test]
+fn test_levenshtein_similarity() {
+    assert_eq!(levenshtein("kitten", "sitting"), 3);
+    assert_eq!(levenshtein("", "abc"), 3);
+    assert_eq!(levenshtein("same", "same"), 0);
+    assert_eq!(similarity("http", "https"), 0.8);
+    assert_eq!(similarity("", ""), 1.0);
+
+    assert!(near_duplicate("terraform-aws-module/vpc/aws", "terraform-aws-modules/vpc/aws"));
+    assert!(near_duplicate("http://github.com/org/app", "https://github.com/org/app"));
+    // Different modules of one namespace are not typos of each other
+    assert!(!near_duplicate("terraform-aws-modules/vpc/aws", "terraform-aws-modules/eks/aws"));
+    assert!(!near_duplicate("terraform-aws-modules/vpc/aws", "terraform-aws-modules/vpc/aws"));
+    assert!(!near_duplicate("org/vpc/aws", "host.io/org/vpc/aws"));
+}
+
+#[test]
+fn test_similar_sources() {
+    let temp_dir = common::create_test_dir_with_files(&[
+        ("prod/main.tf", MAIN_TF),
+        ("dev/main.tf", MAIN_TF),
+        ("dr/main.tf", TYPOS_TF),
+    ]);
+    let similar = similar_sources(temp_dir.path()).unwrap();
+    let pairs: Vec<(&str, &str, &str, usize)> = similar
+        .iter()
+        .map(|s| (s.kind, s.suspect.as_str(), s.likely.as_str(), s.likely_uses))
+        .collect();
+    assert_eq!(
+        pairs,
+        vec![
+            ("module", "git::http://github.com/org/app.git", "git::https://github.com/org/app.git", 2),
+            ("module", "terraform-aws-module/vpc/aws", "terraform-aws-modules/vpc/aws", 2),
+            ("provider", "hashcorp/aws", "hashicorp/aws", 2),
+        ]
+    );
+    assert!(similar.iter().all(|s| s.score > 0.9 && s.score < 1.0));
+    assert_eq!(similar[1].uses[0].block, "module.vpc_dr");
+    assert!(similar[1].uses[0].file.ends_with("dr/main.tf"));
+
+    let findings = diagnose_dir(temp_dir.path()).unwrap();
+    let warnings: Vec<_> = findings.iter().filter(|f| f.check == "similar-sources").collect();
+    assert_eq!(warnings.len(), 3);
+    assert_eq!(warnings[0].severity, Severity::Warning);
+    assert!(warnings[1].message.contains("\"terraform-aws-module/vpc/aws\" (module.vpc_dr) is 0.97 similar"), "{}", warnings[1].message);
+}
+
+#[test]
+fn test_reject_similar_sources() {
+    let temp_dir = common::create_test_dir_with_files(&[
+        ("prod/main.tf", MAIN_TF),
+        ("dev/main.tf", MAIN_TF),
+        ("dr/main.tf", TYPOS_TF),
+    ]);
+    let policy = parse_policy("reject_similar_sources = true\n").unwrap();
+    let reports = validate(&policy, temp_dir.path(), &Client::with_base_url(None)).unwrap();
+    let report = reports.iter().find(|r| r.name == "similar-sources").unwrap();
+    assert_eq!(report.checked, 7);
+    let blocks: Vec<&str> = report.violations.iter().map(|v| v.block.as_str()).collect();
+    assert_eq!(blocks, vec!["module.app_legacy", "module.vpc_dr", "provider.aws"]);
+    assert_eq!(
+        report.violations[2].fix.as_deref(),
+        Some("use \"hashicorp/aws\" if it is meant to be the same provider")
+    );
+
+    assert!(parse_policy("reject_similar_sources = \"yes\"\n").is_err());
+    assert!(!parse_policy("").unwrap().reject_similar_sources);
+}
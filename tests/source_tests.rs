@@ -0,0 +1,86 @@
+use tv::source::{ModuleSource, SourceKind, canonicalize_url, detect_kind};
+
+#[test]
+fn test_parse_git_source_with_path_and_ref() {
+    let source = ModuleSource::parse("git::https://github.com/org/repo.git//modules/vpc?ref=v1.0.0&depth=1");
+    assert_eq!(source.kind, SourceKind::Git);
+    assert_eq!(source.url, "git::https://github.com/org/repo.git");
+    assert_eq!(source.path, Some("modules/vpc".to_string()));
+    assert_eq!(source.git_ref(), Some("v1.0.0"));
+    assert_eq!(source.param("depth"), Some("1"));
+}
+
+#[test]
+fn test_parse_registry_source() {
+    let source = ModuleSource::parse("terraform-aws-modules/vpc/aws");
+    assert_eq!(source.kind, SourceKind::Registry);
+    assert_eq!(source.path, None);
+    assert!(source.params.is_empty());
+
+    let private = ModuleSource::parse("app.terraform.io/acme/vpc/aws");
+    assert_eq!(private.kind, SourceKind::Registry);
+}
+
+#[test]
+fn test_parse_quoted_source() {
+    let source = ModuleSource::parse("\"./modules/vpc\"");
+    assert_eq!(source.kind, SourceKind::Local);
+    assert_eq!(source.url, "./modules/vpc");
+}
+
+#[test]
+fn test_detect_kind() {
+    assert_eq!(detect_kind("github.com/org/repo"), SourceKind::Git);
+    assert_eq!(detect_kind("git@github.com:org/repo.git"), SourceKind::Git);
+    assert_eq!(detect_kind("hg::http://example.com/repo"), SourceKind::Mercurial);
+    assert_eq!(detect_kind("s3::https://s3-eu-west-1.amazonaws.com/bucket/vpc.zip"), SourceKind::S3);
+    assert_eq!(detect_kind("gcs::https://www.googleapis.com/storage/v1/modules/vpc.zip"), SourceKind::Gcs);
+    assert_eq!(detect_kind("https://example.com/vpc.zip"), SourceKind::Http);
+    assert_eq!(detect_kind("../shared"), SourceKind::Local);
+}
+
+#[test]
+fn test_round_trip() {
+    for raw in [
+        "git::https://github.com/org/repo.git//modules/vpc?ref=v1.0.0",
+        "git::https://github.com/org/repo.git?ref=main&depth=1",
+        "terraform-aws-modules/vpc/aws",
+        "./modules/vpc",
+    ] {
+        assert_eq!(ModuleSource::parse(raw).to_string(), raw);
+    }
+}
+
+#[test]
+fn test_build() {
+    let source = ModuleSource::build(
+        "git::https://github.com/org/repo.git",
+        Some("/modules/vpc"),
+        vec![("ref".to_string(), "v2.0.0".to_string())],
+    )
+    .unwrap();
+    assert_eq!(source.to_string(), "git::https://github.com/org/repo.git//modules/vpc?ref=v2.0.0");
+
+    assert!(ModuleSource::build("", None, vec![]).is_err());
+    assert!(ModuleSource::build("https://x.git?ref=a", None, vec![]).is_err());
+}
+
+#[test]
+fn test_set_param() {
+    let mut source = ModuleSource::parse("git::https://github.com/org/repo.git?ref=v1.0.0");
+    source.set_param("ref", "v1.1.0");
+    source.set_param("depth", "1");
+    assert_eq!(source.to_string(), "git::https://github.com/org/repo.git?ref=v1.1.0&depth=1");
+}
+
+#[test]
+fn test_canonicalize_url() {
+    let canonical = "https://github.com/org/repo";
+    assert_eq!(canonicalize_url("git::https://github.com/org/repo.git"), canonical);
+    assert_eq!(canonicalize_url("https://GitHub.com/org/repo/"), canonical);
+    assert_eq!(canonicalize_url("github.com/org/repo"), canonical);
+    assert_eq!(canonicalize_url("git@GitHub.com:org/repo.git"), "git@github.com:org/repo");
+
+    // Path case is significant
+    assert_ne!(canonicalize_url("https://github.com/Org/Repo"), canonical);
+}
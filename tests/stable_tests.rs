@@ -0,0 +1,39 @@
+mod common;
+
+use std::path::PathBuf;
+use tv::pin_age::PinAge;
+use tv::{paths, stable};
+
+#[test]
+fn test_date() {
+    assert_eq!(stable::date(0), "1970-01-01");
+    assert_eq!(stable::date(951_782_400), "2000-02-29");
+    assert_eq!(stable::date(1_704_067_199), "2023-12-31");
+    let parsed = tv::registry::parse_timestamp("2024-03-01T12:30:00Z").unwrap();
+    assert_eq!(stable::date(parsed), "2024-03-01");
+}
+
+#[test]
+fn test_stable_paths_and_ages() {
+    let repo = common::create_git_repo(&[("envs/prod/main.tf", "module \"vpc\" {}\n")]);
+    let outside = common::create_test_dir_with_files(&[("main.tf", "module \"vpc\" {}\n")]);
+    let age = PinAge {
+        file: PathBuf::from("main.tf"),
+        module: "vpc".to_string(),
+        pin: "v1.0.0".to_string(),
+        days: Some(512),
+        released: Some(951_782_400),
+    };
+
+    stable::init(true);
+    assert_eq!(paths::show(&repo.path().join("envs/prod/main.tf")), "envs/prod/main.tf");
+    // Outside a repository: relative to the current directory, not absolute
+    let shown = paths::show(&outside.path().join("main.tf"));
+    assert!(shown.starts_with(".") && shown.ends_with("main.tf"), "{}", shown);
+    assert_eq!(age.shown().as_deref(), Some("released 2000-02-29"));
+
+    stable::init(false);
+    assert!(paths::show(&repo.path().join("envs/prod/main.tf")).starts_with('/'));
+    assert_eq!(age.shown().as_deref(), Some("512 days"));
+    assert_eq!(PinAge { days: None, released: None, ..age }.shown(), None);
+}
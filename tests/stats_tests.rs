@@ -0,0 +1,48 @@
+mod common;
+
+use std::time::Duration;
+use tv::stats::{Record, append, load, summarize, top_queries};
+
+fn record(time: u64, command: &str, query: Option<&str>, duration_ms: u64, ok: bool) -> Record {
+    Record { time, command: command.to_string(), query: query.map(str::to_string), duration_ms, ok }
+}
+
+#[test]
+fn test_append_and_load() {
+    let temp_dir = common::create_test_dir_with_files(&[]);
+    let path = temp_dir.path().join("state/stats.jsonl");
+    assert!(load(&path).unwrap().is_empty());
+
+    let first = Record::new("scan", Some("module.*"), Duration::from_millis(42), true);
+    append(&path, &first).unwrap();
+    append(&path, &Record::new("index build", None, Duration::from_millis(7), false)).unwrap();
+    // A damaged line does not lose the others
+    std::fs::write(&path, std::fs::read_to_string(&path).unwrap() + "{not json\n").unwrap();
+
+    let records = load(&path).unwrap();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0], first);
+    assert_eq!(records[1].command, "index build");
+    assert!(!records[1].ok);
+}
+
+#[test]
+fn test_summarize() {
+    let mut records: Vec<Record> = (1..=20).map(|ms| record(100, "scan", Some("module.*"), ms * 10, true)).collect();
+    records.push(record(100, "get", Some("module.vpc.version"), 5, false));
+    records.push(record(100, "get", Some("module.vpc.version"), 15, true));
+    records.push(record(10, "upgrade", Some("module.*"), 9000, true));
+
+    let summary = summarize(&records, None);
+    assert_eq!(summary.iter().map(|s| s.command.as_str()).collect::<Vec<_>>(), vec!["upgrade", "scan", "get"]);
+    let scan = &summary[1];
+    assert_eq!((scan.runs, scan.total_ms, scan.median_ms, scan.p95_ms, scan.max_ms), (20, 2100, 100, 190, 200));
+    assert_eq!((summary[2].runs, summary[2].failures), (2, 1));
+
+    // Older runs are left out with since
+    assert_eq!(summarize(&records, Some(50)).len(), 2);
+
+    let top = top_queries(&records, None, 2);
+    assert_eq!(top[0], ("scan".to_string(), "module.*".to_string(), 20));
+    assert_eq!(top[1], ("get".to_string(), "module.vpc.version".to_string(), 2));
+}
@@ -0,0 +1,25 @@
+use tv::style::{ColorChoice, highlight, red, set_enabled, should_color};
+
+#[test]
+fn test_should_color() {
+    assert!(should_color(ColorChoice::Auto, true, None));
+    assert!(!should_color(ColorChoice::Auto, false, None));
+    assert!(!should_color(ColorChoice::Auto, true, Some("1")));
+    // An empty NO_COLOR does not count
+    assert!(should_color(ColorChoice::Auto, true, Some("")));
+    assert!(should_color(ColorChoice::Always, false, Some("1")));
+    assert!(!should_color(ColorChoice::Never, true, None));
+    assert!("sometimes".parse::<ColorChoice>().is_err());
+}
+
+#[test]
+fn test_painting_follows_enabled() {
+    set_enabled(false);
+    assert_eq!(red("error"), "error");
+    assert_eq!(highlight("a-vpc-b", "vpc"), "a-vpc-b");
+
+    set_enabled(true);
+    assert_eq!(red("error"), "\x1b[31merror\x1b[0m");
+    assert_eq!(highlight("a-vpc-b", "vpc"), "a-\x1b[1;31mvpc\x1b[0m-b");
+    set_enabled(false);
+}
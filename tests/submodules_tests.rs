@@ -0,0 +1,77 @@
+mod common;
+
+use tv::doctor::diagnose_dir;
+use tv::submodules::{parse_gitmodules, submodule_files, submodules};
+use tv::{ScanOptions, scan_files_with_options};
+
+const GITMODULES: &str = r#"[submodule "vendor-modules"]
+	path = vendor/modules
+	url = https://github.com/org/terraform-modules.git
+[submodule "docs"]
+	url = https://github.com/org/docs.git
+"#;
+
+const PROD_TF: &str = r#"module "vpc" {
+  source = "../../vendor/modules/vpc"
+}
+
+module "app" {
+  source = "./app"
+}
+"#;
+
+fn vendored_repo() -> tempfile::TempDir {
+    common::create_git_repo(&[
+        (".gitmodules", GITMODULES),
+        ("envs/prod/main.tf", PROD_TF),
+        ("envs/prod/app/main.tf", "module \"queue\" {\n  source = \"terraform-aws-modules/sqs/aws\"\n}\n"),
+        ("vendor/modules/vpc/main.tf", "module \"subnets\" {\n  source = \"./subnets\"\n}\n"),
+    ])
+}
+
+#[test]
+fn test_parse_gitmodules() {
+    // A section without a path is not a usable submodule
+    assert_eq!(
+        parse_gitmodules(GITMODULES),
+        vec![(
+            "vendor-modules".to_string(),
+            "vendor/modules".to_string(),
+            Some("https://github.com/org/terraform-modules.git".to_string())
+        )]
+    );
+}
+
+#[test]
+fn test_scan_includes_submodules_outside_dir() {
+    let repo = vendored_repo();
+    let prod = repo.path().join("envs/prod");
+    assert_eq!(submodules(&prod).unwrap()[0].path, repo.path().join("vendor/modules"));
+
+    let plain = scan_files_with_options("module.*", &prod, &ScanOptions::default()).unwrap();
+    let names: Vec<&str> = plain.iter().map(|(_, name)| name.as_str()).collect();
+    assert_eq!(names, vec!["queue", "app", "vpc"]);
+
+    let options = ScanOptions { submodules: true, ..Default::default() };
+    let with_submodules = scan_files_with_options("module.*", &prod, &options).unwrap();
+    assert_eq!(with_submodules.len(), 4);
+    assert!(with_submodules.iter().any(|(file, name)| name == "subnets" && file.ends_with("vendor/modules/vpc/main.tf")));
+
+    // Submodules inside the walked directory are not walked twice
+    assert!(submodule_files(repo.path()).unwrap().is_empty());
+}
+
+#[test]
+fn test_doctor_flags_sources_inside_submodules() {
+    let repo = vendored_repo();
+    let findings = diagnose_dir(&repo.path().join("envs/prod")).unwrap();
+    let flagged: Vec<&str> = findings
+        .iter()
+        .filter(|f| f.check == "submodule-source")
+        .map(|f| f.message.as_str())
+        .collect();
+    assert_eq!(
+        flagged,
+        vec!["module.vpc source \"../../vendor/modules/vpc\" is inside git submodule vendor-modules (https://github.com/org/terraform-modules.git)"]
+    );
+}
@@ -0,0 +1,46 @@
+mod common;
+
+use std::fs;
+use tv::tacos::{bump_constraint, bump_yaml, set_terraform_version, terraform_versions};
+
+const ATLANTIS_YAML: &str = "version: 3\nprojects:\n  - dir: .\n    terraform_version: v1.5.7  # keep in sync\n  - dir: prod\n    terraform_version: \"1.5.7\"\n";
+
+#[test]
+fn test_bump_keeps_operator_and_format() {
+    assert_eq!(bump_constraint("~> 1.5.0", "1.6.2"), Ok("~> 1.6.2".to_string()));
+    assert_eq!(bump_constraint("1.5.7", "v1.6.2"), Ok("1.6.2".to_string()));
+    assert_eq!(bump_constraint("= 1.5.7", "1.6.2"), Ok("= 1.6.2".to_string()));
+    assert!(bump_constraint(">= 1.5, < 2.0", "1.6.2").is_err());
+
+    assert_eq!(
+        bump_yaml(ATLANTIS_YAML, "1.6.2"),
+        "version: 3\nprojects:\n  - dir: .\n    terraform_version: v1.6.2  # keep in sync\n  - dir: prod\n    terraform_version: \"1.6.2\"\n"
+    );
+}
+
+#[test]
+fn test_terraform_version_across_hcl_and_yaml() {
+    let temp_dir = common::create_test_dir_with_files(&[
+        ("versions.tf", "terraform {\n  required_version = \">= 1.5.0\"\n}\n"),
+        ("legacy/versions.tf", "terraform {\n  required_version = \">= 1.0, < 2.0\"\n}\n"),
+        ("atlantis.yaml", ATLANTIS_YAML),
+        (".spacelift/config.yml", "version: 1\nstack_defaults:\n  terraform_version: 1.5.7\n"),
+    ]);
+
+    let pins = terraform_versions(temp_dir.path()).unwrap();
+    assert_eq!(pins.len(), 5);
+    assert!(pins.iter().any(|p| p.key == "terraform_version" && p.line == Some(4) && p.value == "v1.5.7"));
+
+    let bumps = set_terraform_version(temp_dir.path(), "1.6.2").unwrap();
+    assert_eq!(bumps.iter().filter(|b| b.to.is_ok()).count(), 4);
+    // Several clauses are reported, not rewritten
+    let skipped: Vec<_> = bumps.iter().filter(|b| b.to.is_err()).collect();
+    assert_eq!(skipped.len(), 1);
+    assert!(skipped[0].pin.file.ends_with("legacy/versions.tf"));
+
+    let versions = fs::read_to_string(temp_dir.path().join("versions.tf")).unwrap();
+    assert_eq!(versions, "terraform {\n  required_version = \">= 1.6.2\"\n}\n");
+    let spacelift = fs::read_to_string(temp_dir.path().join(".spacelift/config.yml")).unwrap();
+    assert_eq!(spacelift, "version: 1\nstack_defaults:\n  terraform_version: 1.6.2\n");
+    assert!(set_terraform_version(temp_dir.path(), "latest").is_err());
+}
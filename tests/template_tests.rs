@@ -0,0 +1,52 @@
+mod common;
+
+use tv::matches::describe_match;
+use tv::template::{GET_FIELDS, LIST_FIELDS, SCAN_FIELDS, Template, get_field, list_field, scan_field};
+use tv::{TypedValue, ValueKind, block_arguments, inventory, parse_scan_query, scan_files};
+
+const MAIN_TF: &str = r#"module "vpc" {
+  source  = "git::https://github.com/org/vpc.git?ref=v1.2.0"
+  version = "1.2.0"
+  name    = "main"
+}
+"#;
+
+#[test]
+fn test_template_parse_and_render() {
+    let template = Template::parse("{file}:{value}\\t{{literal}}", GET_FIELDS).unwrap();
+    let rendered = template.render(|field| match field {
+        "file" => Some("main.tf".to_string()),
+        _ => None,
+    });
+    // Missing fields are empty
+    assert_eq!(rendered, "main.tf:\t{literal}");
+
+    assert!(Template::parse("{nope}", GET_FIELDS).is_err());
+    assert!(Template::parse("{file", GET_FIELDS).is_err());
+    assert!(Template::parse("file}", GET_FIELDS).is_err());
+    assert_eq!(Template::parse("", GET_FIELDS).unwrap().render(|_| None), "");
+}
+
+#[test]
+fn test_template_fields() {
+    let temp_dir = common::create_test_dir_with_files(&[("main.tf", MAIN_TF)]);
+    let with_args = vec!["name".to_string()];
+
+    let scan_query = parse_scan_query("module.*.version").unwrap();
+    let (file, name) = scan_files("module.*.version", temp_dir.path()).unwrap().remove(0);
+    let found = describe_match(&file, &scan_query, &name).unwrap();
+    let arguments = block_arguments(&file, "module", &name, &with_args).unwrap();
+    let fields: Vec<&str> = SCAN_FIELDS.iter().copied().chain(["name"]).collect();
+    let template = Template::parse("{name} {attribute}={value} ({type}, line {line}) {block}", &fields).unwrap();
+    assert_eq!(template.render(|f| scan_field(&found, &arguments, f)), "vpc version=1.2.0 (string, line 3) module");
+    assert_eq!(scan_field(&found, &arguments, "file").unwrap(), tv::paths::show(&file));
+
+    let typed = TypedValue { value: "1.2.0".to_string(), kind: ValueKind::String };
+    let template = Template::parse("{query} {value} {type}", GET_FIELDS).unwrap();
+    assert_eq!(template.render(|f| get_field(&file, "module.vpc.version", Some(&typed), f)), "module.vpc.version 1.2.0 string");
+    assert_eq!(template.render(|f| get_field(&file, "module.vpc.nope", None, f)), "module.vpc.nope  ");
+
+    let entries = inventory::list_modules(temp_dir.path()).unwrap();
+    let template = Template::parse("{module}|{url}|{path}|{ref}|{version}", LIST_FIELDS).unwrap();
+    assert_eq!(template.render(|f| list_field(&entries[0], f)), "vpc|git::https://github.com/org/vpc.git||v1.2.0|1.2.0");
+}
@@ -0,0 +1,87 @@
+mod common;
+
+use tv::terraformrc::{
+    InstallMethod, filesystem_mirror_versions, mirror_index_url, normalize_provider_address,
+    parse_mirror_index, parse_provider_installation, provider_pattern_matches,
+};
+
+const CLI_CONFIG: &str = r#"plugin_cache_dir = "$HOME/.terraform.d/plugin-cache"
+
+provider_installation {
+  network_mirror {
+    url     = "https://mirror.example.com/providers/"
+    include = ["registry.terraform.io/hashicorp/*"]
+  }
+  filesystem_mirror {
+    path    = "/usr/share/terraform/providers"
+    include = ["example.com/*/*"]
+  }
+  direct {
+    exclude = ["registry.terraform.io/hashicorp/*", "example.com/*/*"]
+  }
+}
+"#;
+
+#[test]
+fn test_parse_provider_installation() {
+    let installation = parse_provider_installation(CLI_CONFIG).unwrap().unwrap();
+    assert_eq!(installation.rules.len(), 3);
+    assert_eq!(
+        installation.rules[0].method,
+        InstallMethod::NetworkMirror("https://mirror.example.com/providers/".to_string())
+    );
+    assert_eq!(installation.rules[2].exclude.len(), 2);
+
+    assert!(parse_provider_installation("plugin_cache_dir = \"/tmp\"\n").unwrap().is_none());
+    assert!(parse_provider_installation("provider_installation {\n  network_mirror {}\n}\n").is_err());
+}
+
+#[test]
+fn test_methods_for_provider() {
+    let installation = parse_provider_installation(CLI_CONFIG).unwrap().unwrap();
+
+    let aws = installation.methods_for("hashicorp/aws");
+    assert_eq!(aws.len(), 1);
+    assert_eq!(aws[0].as_str(), "network_mirror");
+
+    let internal = installation.methods_for("example.com/infra/widgets");
+    assert_eq!(internal[0].as_str(), "filesystem_mirror");
+
+    let other = installation.methods_for("integrations/github");
+    assert_eq!(other, vec![&InstallMethod::Direct]);
+}
+
+#[test]
+fn test_provider_addresses() {
+    assert_eq!(normalize_provider_address("HashiCorp/AWS"), "registry.terraform.io/hashicorp/aws");
+    assert!(provider_pattern_matches("registry.terraform.io/hashicorp/*", "hashicorp/aws"));
+    assert!(!provider_pattern_matches("hashicorp/*", "integrations/github"));
+    assert_eq!(
+        mirror_index_url("https://mirror.example.com/providers/", "hashicorp/aws").unwrap(),
+        "https://mirror.example.com/providers/registry.terraform.io/hashicorp/aws/index.json"
+    );
+    assert!(mirror_index_url("http://mirror.example.com/", "hashicorp/aws").is_err());
+    assert!(mirror_index_url("http://localhost.example.com/", "hashicorp/aws").is_err());
+    assert!(mirror_index_url("http://127.0.0.1:8080/", "hashicorp/aws").is_ok());
+}
+
+#[test]
+fn test_parse_mirror_index() {
+    let json = r#"{"versions": {"5.1.0": {}, "5.0.0": {}}}"#;
+    assert_eq!(parse_mirror_index(json).unwrap(), vec!["5.0.0", "5.1.0"]);
+    assert!(parse_mirror_index("{}").is_err());
+}
+
+#[test]
+fn test_filesystem_mirror_versions() {
+    let files = vec![
+        ("registry.terraform.io/hashicorp/aws/terraform-provider-aws_5.0.0_linux_amd64.zip", ""),
+        ("registry.terraform.io/hashicorp/aws/terraform-provider-aws_5.0.0_darwin_arm64.zip", ""),
+        ("registry.terraform.io/hashicorp/aws/5.1.0/linux_amd64/terraform-provider-aws", ""),
+    ];
+    let mirror = common::create_test_dir_with_files(&files);
+
+    let versions = filesystem_mirror_versions(mirror.path(), "hashicorp/aws").unwrap();
+    assert_eq!(versions, vec!["5.0.0", "5.1.0"]);
+    assert!(filesystem_mirror_versions(mirror.path(), "hashicorp/google").unwrap().is_empty());
+}
@@ -0,0 +1,33 @@
+mod common;
+
+use std::path::Path;
+use tv::explain::describe_scan_query;
+use tv::{find_all_tf_files, parse_scan_query, set_value};
+
+#[test]
+fn test_assert_rewrite() {
+    common::assert_rewrite(
+        "module \"vpc\" {\n  # pinned\n  source = \"x?ref=v1\"  // keep\n}\n",
+        "module \"vpc\" {\n  # pinned\n  source = \"x?ref=v2\"  // keep\n}\n",
+        |file| set_value("module.vpc.source[\"ref\"]", "v2", Some(file)),
+    );
+    common::assert_unchanged(common::SIMPLE_MODULE_TF, |file| {
+        set_value("module.vpc.name", "my-vpc", Some(file))
+    });
+
+    let mismatch = std::panic::catch_unwind(|| common::assert_bytes_eq("a\nb\n", "a\nc\n")).unwrap_err();
+    let message = mismatch.downcast_ref::<String>().unwrap();
+    assert!(message.contains("line 2") && message.contains("\"c\\n\""));
+}
+
+#[test]
+fn test_create_corpus() {
+    let corpus = common::create_corpus(3, 2);
+    assert_eq!(find_all_tf_files(corpus.path()).unwrap().len(), 3);
+}
+
+#[test]
+fn test_assert_golden() {
+    let query = parse_scan_query("module.*.source[url=\"*github.com*\"]").unwrap();
+    common::assert_golden(Path::new("tests/golden/describe_filter.txt"), &describe_scan_query(&query));
+}
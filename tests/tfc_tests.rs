@@ -0,0 +1,71 @@
+mod common;
+
+use tv::terraformrc::{credential_token, stored_token};
+use tv::tfc::{Client, parse_workspace};
+
+const WORKSPACE: &str = r#"{"data": {"id": "ws-123", "type": "workspaces"}}"#;
+
+const VARIABLES: &str = r#"{"data": [
+  {"id": "var-1", "type": "vars", "attributes": {"key": "module_ref", "value": "v1.2.0", "category": "terraform", "sensitive": false}},
+  {"id": "var-2", "type": "vars", "attributes": {"key": "module_ref", "value": "env-value", "category": "env", "sensitive": false}},
+  {"id": "var-3", "type": "vars", "attributes": {"key": "secret", "value": null, "category": "terraform", "sensitive": true}}
+]}"#;
+
+#[test]
+fn test_tfc_get_and_set_variables() {
+    let (base, requests) = common::serve_http_recording(vec![
+        ("/api/v2/organizations/acme/workspaces/prod", WORKSPACE.to_string()),
+        ("GET /api/v2/workspaces/ws-123/vars", VARIABLES.to_string()),
+        (
+            "PATCH /api/v2/workspaces/ws-123/vars/var-1",
+            r#"{"data": {"id": "var-1", "attributes": {"key": "module_ref", "value": "v1.3.0", "category": "terraform"}}}"#.to_string(),
+        ),
+        (
+            "POST /api/v2/workspaces/ws-123/vars",
+            r#"{"data": {"id": "var-4", "attributes": {"key": "eks_ref", "value": "v2.0.0", "category": "terraform"}}}"#.to_string(),
+        ),
+    ]);
+    let client = Client::new(&format!("{}/api/v2", base), Some("secret-token".to_string()));
+
+    let variable = client.get_variable("acme/prod", "module_ref", "terraform").unwrap().unwrap();
+    assert_eq!(variable.value.as_deref(), Some("v1.2.0"));
+    let variable = client.get_variable("acme/prod", "module_ref", "env").unwrap().unwrap();
+    assert_eq!(variable.value.as_deref(), Some("env-value"));
+    assert!(client.get_variable("acme/prod", "secret", "terraform").unwrap().unwrap().sensitive);
+    assert!(client.get_variable("acme/prod", "missing", "terraform").unwrap().is_none());
+
+    // An existing variable is updated, a missing one created
+    assert_eq!(client.set_variable("acme/prod", "module_ref", "v1.3.0", "terraform").unwrap().id, "var-1");
+    assert_eq!(client.set_variable("acme/prod", "eks_ref", "v2.0.0", "terraform").unwrap().id, "var-4");
+    let requests = requests.lock().unwrap();
+    assert!(requests.iter().any(|r| r.starts_with("PATCH /api/v2/workspaces/ws-123/vars/var-1 ") && r.contains(r#""value":"v1.3.0""#)));
+    assert!(requests.iter().any(|r| r.starts_with("POST /api/v2/workspaces/ws-123/vars ") && r.contains(r#""key":"eks_ref""#)));
+
+    assert!(parse_workspace("prod").is_err());
+    assert_eq!(parse_workspace("acme/prod").unwrap(), ("acme", "prod"));
+}
+
+#[test]
+fn test_tfc_names_are_encoded_in_urls() {
+    let (base, requests) = common::serve_http_recording(vec![
+        ("/api/v2/organizations/acme%20corp/workspaces/prod%3Fx%23y", WORKSPACE.to_string()),
+        ("GET /api/v2/workspaces/ws-123/vars", VARIABLES.to_string()),
+    ]);
+    let client = Client::new(&format!("{}/api/v2", base), None);
+
+    let variable = client.get_variable("acme corp/prod?x#y", "module_ref", "terraform").unwrap().unwrap();
+    assert_eq!(variable.value.as_deref(), Some("v1.2.0"));
+    let requests = requests.lock().unwrap();
+    assert!(requests[0].starts_with("GET /api/v2/organizations/acme%20corp/workspaces/prod%3Fx%23y "), "{}", requests[0]);
+}
+
+#[test]
+fn test_tfc_token_sources() {
+    let rc = "credentials \"app.terraform.io\" {\n  token = \"from-rc\"\n}\n";
+    assert_eq!(credential_token(rc, "app.terraform.io").unwrap().as_deref(), Some("from-rc"));
+    assert!(credential_token(rc, "tfe.example.com").unwrap().is_none());
+
+    let stored = r#"{"credentials": {"app.terraform.io": {"token": "from-login"}}}"#;
+    assert_eq!(stored_token(stored, "app.terraform.io").as_deref(), Some("from-login"));
+    assert!(stored_token(stored, "tfe.example.com").is_none());
+}
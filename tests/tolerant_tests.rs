@@ -0,0 +1,51 @@
+mod common;
+
+use std::path::Path;
+use tv::tolerant::parse_tolerant;
+use tv::{ScanOptions, scan_files_with_failures, set_value};
+
+fn labels(content: &str) -> (Vec<String>, Vec<String>) {
+    let (body, repairs) = parse_tolerant(content, Path::new("test.tf")).unwrap();
+    let labels = body.blocks().map(|b| b.labels[0].as_str().to_string()).collect();
+    (labels, repairs)
+}
+
+#[test]
+fn test_parse_tolerant_repairs() {
+    // Valid files need no repairs
+    assert_eq!(labels(common::SIMPLE_MODULE_TF), (vec!["vpc".to_string()], vec![]));
+
+    let (found, repairs) = labels("module \"a\" {\n  source = \"x\",\n  azs = [\"a\",\n    \"b\"]\n  tags = { a = 1 },\n}\n");
+    assert_eq!(found, vec!["a"]);
+    assert_eq!(repairs, vec!["line 2: dropped the comma after an attribute", "line 5: dropped the comma after an attribute"]);
+
+    let (found, repairs) = labels("module \"a\" {\n  version = \"1.0\"\n  version = \"2.0\"\n}\n");
+    assert_eq!(found, vec!["a"]);
+    assert!(repairs[0].contains("module.a.version is defined 2 times (lines 2, 3)"));
+
+    // A broken block is left out, the others are kept
+    let (found, repairs) = labels("module \"a\" {\n  source = \"x\"\n}\n\nmodule \"b\" {\n  name = \"oops\n}\n\nmodule \"c\" {\n  source = \"z\"\n}\n");
+    assert_eq!(found, vec!["a", "c"]);
+    assert_eq!(repairs, vec!["lines 5-8: skipped `module \"b\"`, which does not parse"]);
+
+    // Conflicts and files with nothing readable still fail
+    assert!(parse_tolerant("<<<<<<< HEAD\nmodule \"a\" {}\n=======\n>>>>>>> x\n", Path::new("c.tf")).is_err());
+    assert!(parse_tolerant("module \"a\" {\n  name = \"oops\n}\n", Path::new("b.tf")).is_err());
+}
+
+#[test]
+fn test_scan_tolerant_is_read_only() {
+    let broken = "module \"vpc\" {\n  source = \"x\",\n  version = \"5.0.0\"\n}\n";
+    let temp_dir = common::create_test_dir_with_files(&[("broken.tf", broken), ("main.tf", common::REGISTRY_MODULE_TF)]);
+
+    assert!(scan_files_with_failures("module.*", temp_dir.path(), &ScanOptions::default()).is_err());
+    let options = ScanOptions { tolerant: true, ..Default::default() };
+    let (matches, failures) = scan_files_with_failures("module.*", temp_dir.path(), &options).unwrap();
+    assert_eq!(matches.len(), 2);
+    assert_eq!(failures.len(), 1);
+    assert!(failures[0].file.ends_with("broken.tf") && failures[0].repairs.len() == 1);
+
+    // Writes still need valid HCL, and the file is left as it was
+    assert!(set_value("module.vpc.version", "5.1.0", Some(&temp_dir.path().join("broken.tf"))).is_err());
+    assert_eq!(std::fs::read_to_string(temp_dir.path().join("broken.tf")).unwrap(), broken);
+}
@@ -0,0 +1,109 @@
+mod common;
+
+use std::fs;
+use tv::unset::{remove_block, unset};
+
+const MAIN_TF: &str = r#"terraform {
+  required_providers {
+    aws = {
+      source  = "hashicorp/aws"
+      version = "~> 5.0"
+    }
+  }
+}
+
+module "vpc" {
+  source = "terraform-aws-modules/vpc/aws"
+  # Pinned for the 2024 migration
+  version = "5.1.0"
+  tags    = { Owner = "platform", Team = "network" }
+
+  lifecycle {
+    prevent_destroy = true
+  }
+}
+"#;
+
+#[test]
+fn test_unset_attribute() {
+    let (_dir, file) = common::create_test_tf_file(MAIN_TF);
+    unset("module.vpc.version", Some(&file)).unwrap();
+    let content = fs::read_to_string(&file).unwrap();
+    assert!(content.contains(
+        "  source = \"terraform-aws-modules/vpc/aws\"\n  tags    = { Owner = \"platform\", Team = \"network\" }\n"
+    ), "{}", content);
+    assert!(!content.contains("2024 migration"));
+
+    unset("module.vpc.lifecycle.prevent_destroy", Some(&file)).unwrap();
+    assert!(fs::read_to_string(&file).unwrap().contains("  lifecycle {\n  }\n"));
+}
+
+#[test]
+fn test_unset_object_key() {
+    let (_dir, file) = common::create_test_tf_file(MAIN_TF);
+    unset("terraform.required_providers.aws.version", Some(&file)).unwrap();
+    let content = fs::read_to_string(&file).unwrap();
+    assert!(content.starts_with(
+        "terraform {\n  required_providers {\n    aws = {\n      source  = \"hashicorp/aws\"\n    }\n  }\n}\n"
+    ), "{}", content);
+
+    // An index addresses a map key, as with rm
+    unset("module.vpc.tags[\"Team\"]", Some(&file)).unwrap();
+    assert!(fs::read_to_string(&file).unwrap().contains("tags    = { Owner = \"platform\" }"));
+}
+
+#[test]
+fn test_unset_missing_fails() {
+    let (_dir, file) = common::create_test_tf_file(MAIN_TF);
+    assert!(unset("module.vpc.missing", Some(&file)).is_err());
+    assert!(unset("module.eks.version", Some(&file)).is_err());
+    assert!(unset("terraform.required_providers.aws.missing", Some(&file)).is_err());
+    assert!(unset("module.vpc.source.ref", Some(&file)).is_err());
+    assert_eq!(fs::read_to_string(&file).unwrap(), MAIN_TF);
+}
+
+const BLOCKS_TF: &str = r#"# Network stack
+
+module "legacy_vpc" {
+  source = "terraform-aws-modules/vpc/aws"
+}
+
+# Replaces legacy_vpc
+module "vpc" {
+  source = "terraform-aws-modules/vpc/aws"
+}
+
+resource "aws_s3_bucket" "logs" {
+  bucket = "logs"
+}
+"#;
+
+#[test]
+fn test_remove_block() {
+    let (_dir, file) = common::create_test_tf_file(BLOCKS_TF);
+    remove_block("module.vpc", Some(&file)).unwrap();
+    let content = fs::read_to_string(&file).unwrap();
+    assert!(content.contains("}\n\nresource \"aws_s3_bucket\" \"logs\" {\n"), "{}", content);
+    assert!(!content.contains("Replaces legacy_vpc"));
+
+    // The file header stays when the first block goes
+    remove_block("module.legacy_vpc", Some(&file)).unwrap();
+    assert_eq!(
+        fs::read_to_string(&file).unwrap(),
+        "# Network stack\n\nresource \"aws_s3_bucket\" \"logs\" {\n  bucket = \"logs\"\n}\n"
+    );
+
+    remove_block("resource.aws_s3_bucket.logs", Some(&file)).unwrap();
+    assert!(!fs::read_to_string(&file).unwrap().contains("resource"));
+}
+
+#[test]
+fn test_remove_block_needs_one_named_block() {
+    let (_dir, file) = common::create_test_tf_file(BLOCKS_TF);
+    assert!(remove_block("module.*", Some(&file)).is_err());
+    assert!(remove_block("module", Some(&file)).is_err());
+    assert!(remove_block("resource.aws_s3_bucket", Some(&file)).is_err());
+    assert!(remove_block("module.vpc.source", Some(&file)).is_err());
+    assert!(remove_block("module.eks", Some(&file)).is_err());
+    assert_eq!(fs::read_to_string(&file).unwrap(), BLOCKS_TF);
+}
@@ -0,0 +1,291 @@
+mod common;
+
+use std::process::Command;
+use tv::config::{Channel, TrackRule, parse_age, parse_config};
+use tv::parse_scan_query;
+use tv::bump::Part;
+use tv::updates::{Update, UpdateOptions, check_updates, list_tags, newest_allowed, upgrade};
+
+fn tags(names: &[&str]) -> Vec<String> {
+    names.iter().map(|t| t.to_string()).collect()
+}
+
+#[test]
+fn test_track_rules_pick_allowed_versions() {
+    let available = tags(&["v1.0.0", "v1.1.0", "v1.2.0-lts", "v2.0.0-beta.1", "v2.0.0-rc.1", "latest"]);
+
+    let any = TrackRule::default();
+    assert_eq!(newest_allowed("v1.0.0", &available, &any).unwrap(), "v2.0.0-rc.1");
+
+    let stable = TrackRule { channel: Channel::Stable, ..Default::default() };
+    assert_eq!(newest_allowed("v1.0.0", &available, &stable).unwrap(), "v1.1.0");
+
+    let beta = TrackRule { channel: "beta".parse().unwrap(), ..Default::default() };
+    assert_eq!(newest_allowed("v1.0.0", &available, &beta).unwrap(), "v2.0.0-beta.1");
+
+    let lts = TrackRule { tags: Some("v1.*-lts".to_string()), ..Default::default() };
+    assert_eq!(newest_allowed("v1.0.0", &available, &lts).unwrap(), "v1.2.0-lts");
+
+    // Nothing newer, or a ref that is not a version
+    assert!(newest_allowed("v2.0.0", &available, &stable).is_none());
+    assert!(newest_allowed("main", &available, &any).is_none());
+}
+
+#[test]
+fn test_config_first_matching_rule_wins() {
+    let config = parse_config(
+        "[[track]]\nmatch = \"vpc\"\ntags = \"v1.*\"\n\n[[track]]\nmatch = \"*github.com/org/*\"\nchannel = \"stable\"\n",
+    )
+    .unwrap();
+    let rule = config.rule_for(&["vpc", "git::https://github.com/org/vpc.git"]).unwrap();
+    assert_eq!(rule.tags.as_deref(), Some("v1.*"));
+    let rule = config.rule_for(&["eks", "git::https://github.com/org/eks.git"]).unwrap();
+    assert_eq!(rule.channel, Channel::Stable);
+    assert!(config.rule_for(&["other", "./modules/other"]).is_none());
+
+    assert!(parse_config("[[track]]\nmatch = \"x\"\nchanel = \"stable\"\n").is_err());
+}
+
+#[test]
+fn test_update_jump() {
+    let update = |current: &str, latest: &str| Update {
+        file: "main.tf".into(),
+        module: "vpc".to_string(),
+        query: "module.vpc.version".to_string(),
+        url: "terraform-aws-modules/vpc/aws".to_string(),
+        current: current.to_string(),
+        latest: latest.to_string(),
+        group: None,
+    };
+    assert_eq!(update("v1.2.3", "v2.0.0").jump(), Some(Part::Major));
+    assert_eq!(update("1.2.3", "1.4.0").jump(), Some(Part::Minor));
+    assert_eq!(update("1.2", "1.2.1").jump(), Some(Part::Patch));
+    assert_eq!(update("2.0.0-rc.1", "2.0.0").jump(), Some(Part::Patch));
+    assert_eq!(update("main", "v1.0.0").jump(), None);
+}
+
+fn tagged_remote(names: &[&str]) -> tempfile::TempDir {
+    let remote = common::create_git_repo(&[("README.md", "modules\n")]);
+    for tag in names {
+        let status = Command::new("git")
+            .args(["tag", tag])
+            .current_dir(remote.path())
+            .status()
+            .unwrap();
+        assert!(status.success());
+    }
+    remote
+}
+
+#[test]
+fn test_source_urls_are_never_git_options() {
+    let marker = tempfile::TempDir::new().unwrap();
+    let pwned = marker.path().join("pwned");
+    let content = format!("module \"a\" {{\n  source = \"git::--upload-pack=touch {};?ref=v1.0.0\"\n}}\n", pwned.display());
+    let work = common::create_test_dir_with_files(&[("main.tf", &content)]);
+    let cache = tempfile::TempDir::new().unwrap();
+    let options = UpdateOptions { cache_dir: cache.path().to_path_buf(), ..Default::default() };
+    for config in ["", "minimum_release_age = \"1d\"\n"] {
+        let config = parse_config(config).unwrap();
+        let error = check_updates(work.path(), &parse_scan_query("module.*").unwrap(), &config, &options).unwrap_err();
+        assert!(error.to_string().contains("starts with '-'"), "{:#}", error);
+    }
+    // Even when called directly, the url is a repository, not an option
+    assert!(list_tags(&format!("--upload-pack=touch {};", pwned.display())).is_err());
+    assert!(!pwned.exists());
+}
+
+#[test]
+fn test_check_updates_names_merge_conflicts() {
+    let content = "module \"a\" {\n<<<<<<< HEAD\n  source = \"git::https://github.com/org/a.git?ref=v1.0.0\"\n=======\n  source = \"git::https://github.com/org/a.git?ref=v1.1.0\"\n>>>>>>> upgrade\n}\n";
+    let work = common::create_test_dir_with_files(&[("main.tf", content)]);
+    let cache = tempfile::TempDir::new().unwrap();
+    let options = UpdateOptions { cache_dir: cache.path().to_path_buf(), ..Default::default() };
+    let error = check_updates(work.path(), &parse_scan_query("module.*").unwrap(), &parse_config("").unwrap(), &options)
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("Unresolved merge conflict"), "{}", error);
+}
+
+#[test]
+fn test_check_updates_and_upgrade_against_git_tags() {
+    let remote = tagged_remote(&["v1.0.0", "v1.1.0", "v2.0.0-beta.1"]);
+    let url = format!("git::{}", remote.path().display());
+    let content = format!(
+        "module \"a\" {{\n  source = \"{url}?ref=v1.0.0\"\n}}\n\nmodule \"b\" {{\n  source = \"{url}//sub?ref=v1.0.0\"\n}}\n\nmodule \"c\" {{\n  source = \"{url}?ref=main\"\n}}\n"
+    );
+    let work = common::create_test_dir_with_files(&[("main.tf", &content)]);
+    let cache = tempfile::TempDir::new().unwrap();
+    let options = UpdateOptions { cache_dir: cache.path().to_path_buf(), ..Default::default() };
+    let config = parse_config("[[track]]\nmatch = \"b\"\nchannel = \"stable\"\n").unwrap();
+
+    let updates = check_updates(work.path(), &parse_scan_query("module.*").unwrap(), &config, &options).unwrap();
+    let found: Vec<(&str, &str)> = updates.iter().map(|u| (u.module.as_str(), u.latest.as_str())).collect();
+    assert_eq!(found, vec![("a", "v2.0.0-beta.1"), ("b", "v1.1.0")]);
+
+    upgrade(work.path(), &parse_scan_query("module.b").unwrap(), &config, None, &options).unwrap();
+    let written = std::fs::read_to_string(work.path().join("main.tf")).unwrap();
+    assert!(written.contains(&format!("  source = \"{url}//sub?ref=v1.1.0\"")));
+    assert!(written.contains(&format!("  source = \"{url}?ref=v1.0.0\"")));
+}
+
+#[test]
+fn test_upgrade_one_group_at_a_time() {
+    let remote = tagged_remote(&["v1.0.0", "v1.1.0"]);
+    let url = format!("git::{}", remote.path().display());
+    let content = format!(
+        "module \"vpc\" {{\n  source = \"{url}//vpc?ref=v1.0.0\"\n}}\n\nmodule \"eks\" {{\n  source = \"{url}//eks?ref=v1.0.0\"\n}}\n\nmodule \"app\" {{\n  source = \"{url}//app?ref=v1.0.0\"\n}}\n"
+    );
+    let work = common::create_test_dir_with_files(&[("main.tf", &content)]);
+    let cache = tempfile::TempDir::new().unwrap();
+    let options = UpdateOptions { cache_dir: cache.path().to_path_buf(), ..Default::default() };
+    let config = parse_config("[[group]]\nname = \"aws\"\nmatch = [\"vpc\", \"eks\"]\n").unwrap();
+    let all = parse_scan_query("module.*").unwrap();
+
+    let updates = check_updates(work.path(), &all, &config, &options).unwrap();
+    let groups: Vec<Option<&str>> = updates.iter().map(|u| u.group.as_deref()).collect();
+    assert_eq!(groups, vec![Some("aws"), Some("aws"), None]);
+
+    let upgraded = upgrade(work.path(), &all, &config, Some("aws"), &options).unwrap();
+    let modules: Vec<&str> = upgraded.iter().map(|u| u.module.as_str()).collect();
+    assert_eq!(modules, vec!["vpc", "eks"]);
+    let written = std::fs::read_to_string(work.path().join("main.tf")).unwrap();
+    assert!(written.contains("//app?ref=v1.0.0"));
+
+    assert!(upgrade(work.path(), &all, &config, Some("missing"), &options).is_err());
+    assert!(parse_config("[[group]]\nname = \"a\"\nmatch = []\n\n[[group]]\nname = \"a\"\nmatch = []\n").is_err());
+}
+
+#[test]
+fn test_minimum_release_age_skips_fresh_tags() {
+    let remote = tagged_remote(&[]);
+    let git = |args: &[&str], date: &str| {
+        let status = Command::new("git")
+            .args(["-c", "user.name=tv", "-c", "user.email=tv@example.com"])
+            .args(args)
+            .env("GIT_COMMITTER_DATE", date)
+            .current_dir(remote.path())
+            .status()
+            .unwrap();
+        assert!(status.success());
+    };
+    git(&["commit", "--quiet", "--allow-empty", "-m", "old"], "2020-01-01T00:00:00Z");
+    git(&["tag", "v1.1.0"], "2020-01-01T00:00:00Z");
+    let yesterday = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+        - 24 * 60 * 60;
+    let yesterday = format!("{} +0000", yesterday);
+    git(&["commit", "--quiet", "--allow-empty", "-m", "new"], &yesterday);
+    git(&["tag", "v1.2.0"], &yesterday);
+
+    let url = format!("git::{}", remote.path().display());
+    let content = format!("module \"a\" {{\n  source = \"{url}?ref=v1.0.0\"\n}}\n");
+    let work = common::create_test_dir_with_files(&[("main.tf", &content)]);
+    let cache = tempfile::TempDir::new().unwrap();
+    let options = UpdateOptions { cache_dir: cache.path().to_path_buf(), ..Default::default() };
+    let all = parse_scan_query("module.*").unwrap();
+
+    let config = parse_config("minimum_release_age = \"7d\"\n").unwrap();
+    let updates = check_updates(work.path(), &all, &config, &options).unwrap();
+    assert_eq!(updates[0].latest, "v1.1.0");
+
+    // A rule's own age overrides the top-level one
+    let config = parse_config(
+        "minimum_release_age = \"7d\"\n\n[[track]]\nmatch = \"a\"\nminimum_release_age = \"0d\"\n",
+    )
+    .unwrap();
+    let updates = check_updates(work.path(), &all, &config, &options).unwrap();
+    assert_eq!(updates[0].latest, "v1.2.0");
+
+    assert_eq!(parse_age("2w").unwrap().as_secs(), 14 * 24 * 60 * 60);
+    assert!(parse_age("7 days").is_err());
+    assert!(parse_age("99999999999999999w").unwrap_err().to_string().contains("age too large"));
+    assert!(parse_config("minimum_release_age = \"99999999999999999w\"\n").is_err());
+    assert!(parse_config("minimum_release_age = \"soon\"\n").is_err());
+}
+
+#[test]
+fn test_upgrade_to_a_named_release() {
+    let remote = tagged_remote(&["v1.0.0", "v1.1.0", "v2.0.0"]);
+    let url = format!("git::{}", remote.path().display());
+    let content = format!(
+        "module \"a\" {{\n  source = \"{url}//sub?ref=v1.0.0&depth=1\"\n}}\n\nmodule \"c\" {{\n  source = \"{url}?ref=main\"\n}}\n"
+    );
+    let work = common::create_test_dir_with_files(&[("main.tf", &content)]);
+    let cache = tempfile::TempDir::new().unwrap();
+    let all = parse_scan_query("module.*").unwrap();
+    let to = |version: &str| UpdateOptions {
+        cache_dir: cache.path().to_path_buf(),
+        to: Some(version.to_string()),
+        ..Default::default()
+    };
+    // Track rules do not apply to a release asked for by name
+    let config = parse_config("[[track]]\nmatch = \"a\"\ntags = \"v2.*\"\n").unwrap();
+
+    let updates = check_updates(work.path(), &all, &config, &to("1.1.0")).unwrap();
+    let found: Vec<(&str, &str)> = updates.iter().map(|u| (u.module.as_str(), u.latest.as_str())).collect();
+    assert_eq!(found, vec![("a", "v1.1.0")]);
+
+    upgrade(work.path(), &all, &config, None, &to("v1.1.0")).unwrap();
+    let written = std::fs::read_to_string(work.path().join("main.tf")).unwrap();
+    assert!(written.contains(&format!("  source = \"{url}//sub?ref=v1.1.0&depth=1\"")));
+    assert!(written.contains(&format!("  source = \"{url}?ref=main\"")));
+
+    assert!(check_updates(work.path(), &all, &config, &to("v1.1.0")).unwrap().is_empty());
+    let error = check_updates(work.path(), &all, &config, &to("v9.0.0")).unwrap_err().to_string();
+    assert!(error.contains("module.a: v9.0.0 is not a release of"), "{}", error);
+}
+
+#[test]
+fn test_check_updates_asks_each_registry_module_once() {
+    let versions = |list: &[&str]| {
+        let versions: Vec<String> = list.iter().map(|v| format!("{{\"version\": \"{}\"}}", v)).collect();
+        format!("{{\"modules\": [{{\"versions\": [{}]}}]}}", versions.join(", "))
+    };
+    let (url, requests) = common::serve_http_recording(vec![
+        ("/v1/modules/terraform-aws-modules/vpc/aws/versions", versions(&["5.0.0", "5.1.0"])),
+        ("/v1/modules/org/eks/aws/versions", versions(&["1.0.0", "2.0.0"])),
+    ]);
+    let vpc = "module \"vpc\" {\n  source  = \"terraform-aws-modules/vpc/aws\"\n  version = \"5.0.0\"\n}\n";
+    let eks = "module \"eks\" {\n  source  = \"org/eks/aws\"\n  version = \"1.0.0\"\n}\n";
+    let work = common::create_test_dir_with_files(&[("a/main.tf", vpc), ("b/main.tf", vpc), ("c/main.tf", &format!("{}\n{}", vpc, eks))]);
+    let options = UpdateOptions { registry: tv::registry::Client::with_base_url(Some(url)), network_jobs: 2, ..Default::default() };
+
+    let updates = check_updates(work.path(), &parse_scan_query("module.*").unwrap(), &parse_config("").unwrap(), &options).unwrap();
+    let found: Vec<(&str, &str)> = updates.iter().map(|u| (u.module.as_str(), u.latest.as_str())).collect();
+    assert_eq!(found, vec![("vpc", "5.1.0"), ("vpc", "5.1.0"), ("vpc", "5.1.0"), ("eks", "2.0.0")]);
+    assert_eq!(requests.lock().unwrap().len(), 2);
+}
+
+#[test]
+fn test_cache_ttl_reuses_resolutions_across_runs() {
+    let (url, requests) = common::serve_http_recording(vec![(
+        "/v1/modules/terraform-aws-modules/vpc/aws/versions",
+        "{\"modules\": [{\"versions\": [{\"version\": \"5.0.0\"}, {\"version\": \"5.1.0\"}]}]}".to_string(),
+    )]);
+    let vpc = "module \"vpc\" {\n  source  = \"terraform-aws-modules/vpc/aws\"\n  version = \"5.0.0\"\n}\n";
+    let work = common::create_test_dir_with_files(&[("main.tf", vpc)]);
+    let cache = tempfile::TempDir::new().unwrap();
+    let options = |ttl: Option<u64>| UpdateOptions {
+        cache_dir: cache.path().to_path_buf(),
+        registry: tv::registry::Client::with_base_url(Some(url.clone())),
+        cache_ttl: ttl.map(std::time::Duration::from_secs),
+        ..Default::default()
+    };
+    let (query, config) = (parse_scan_query("module.*").unwrap(), parse_config("").unwrap());
+
+    for _ in 0..2 {
+        let updates = check_updates(work.path(), &query, &config, &options(Some(3600))).unwrap();
+        assert_eq!(updates[0].latest, "5.1.0");
+    }
+    // The second run answered from resolved.json
+    assert_eq!(requests.lock().unwrap().len(), 1);
+    assert!(cache.path().join(tv::resolved::CACHE_FILE).is_file());
+
+    // Without a ttl, or with an expired one, the registry is asked again
+    check_updates(work.path(), &query, &config, &options(None)).unwrap();
+    check_updates(work.path(), &query, &config, &options(Some(0))).unwrap();
+    assert_eq!(requests.lock().unwrap().len(), 3);
+}
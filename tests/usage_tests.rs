@@ -0,0 +1,60 @@
+mod common;
+
+use tv::constraint::{Version, parse_version_spec, satisfies};
+use tv::usage::{parse_target, who_uses};
+
+const LOCK: &str = r#"provider "registry.terraform.io/hashicorp/aws" {
+  version     = "5.31.0"
+  constraints = "~> 5.0"
+}
+"#;
+
+fn versions(constraint: &str) -> String {
+    format!("terraform {{\n  required_providers {{\n    aws = {{\n      source  = \"hashicorp/aws\"\n      version = \"{}\"\n    }}\n  }}\n}}\n", constraint)
+}
+
+#[test]
+fn test_version_specs() {
+    let major = parse_version_spec("5.x").unwrap();
+    assert!(satisfies(&Version::parse("5.99.1").unwrap(), &major));
+    assert!(!satisfies(&Version::parse("6.0.0").unwrap(), &major));
+    let minor = parse_version_spec("5.2.*").unwrap();
+    assert!(satisfies(&Version::parse("5.2.9").unwrap(), &minor));
+    assert!(!satisfies(&Version::parse("5.3.0").unwrap(), &minor));
+
+    let (address, range) = parse_target("aws@>= 5.0, < 5.40").unwrap();
+    assert_eq!(address.to_string(), "registry.terraform.io/hashicorp/aws");
+    assert_eq!(range.len(), 2);
+    assert!(parse_target("hashicorp/aws").unwrap().1.is_empty());
+    assert!(parse_target("a/b/c/d@5.x").is_err());
+}
+
+#[test]
+fn test_who_uses_provider_major() {
+    let temp_dir = common::create_test_dir_with_files(&[
+        ("prod/versions.tf", &versions("~> 5.0")),
+        ("prod/.terraform.lock.hcl", LOCK),
+        ("legacy/versions.tf", &versions("~> 4.0")),
+        ("open/main.tf", "terraform {\n  required_providers {\n    aws = {\n      source = \"hashicorp/aws\"\n    }\n  }\n}\n"),
+        ("split/a.tf", &versions(">= 4.0")),
+        ("split/b.tf", "terraform {\n  required_providers {\n    aws = \"< 5.0\"\n  }\n}\n"),
+        ("other/main.tf", "terraform {\n  required_providers {\n    google = \"~> 5.0\"\n  }\n}\n"),
+    ]);
+    let (address, range) = parse_target("hashicorp/aws@5.x").unwrap();
+    let usages = who_uses(temp_dir.path(), &address, &range).unwrap();
+
+    let dirs: Vec<_> = usages.iter().map(|u| u.dir.strip_prefix(temp_dir.path()).unwrap().display().to_string()).collect();
+    // legacy and split (>= 4.0 and < 5.0 together) cannot install 5.x
+    assert_eq!(dirs, vec!["open", "prod"]);
+    assert_eq!(usages[1].locked.as_deref(), Some("5.31.0"));
+    assert!(usages[1].locked_matches);
+    assert!(usages[0].constraints.is_empty());
+
+    let (_, four) = parse_target("hashicorp/aws@4.x").unwrap();
+    let dirs: Vec<_> = who_uses(temp_dir.path(), &address, &four)
+        .unwrap()
+        .iter()
+        .map(|u| u.dir.file_name().unwrap().to_string_lossy().to_string())
+        .collect();
+    assert_eq!(dirs, vec!["legacy", "open", "split"]);
+}
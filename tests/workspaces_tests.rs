@@ -0,0 +1,60 @@
+mod common;
+
+use tv::find_all_tf_files;
+use tv::workspaces::{at, load_workspaces, named, parse_workspaces};
+
+const WORKSPACES: &str = r#"
+[[workspace]]
+name = "app"
+dir  = "app"
+
+[[workspace]]
+name   = "networking"
+dir    = "infra/networking"
+ignore = ["legacy", "sandbox/*.tf"]
+policy = "policies/networking.hcl"
+"#;
+
+#[test]
+fn test_parse_workspaces() {
+    let workspaces = parse_workspaces(WORKSPACES).unwrap();
+    assert_eq!(workspaces.iter().map(|w| w.name.as_str()).collect::<Vec<_>>(), ["app", "networking"]);
+    assert_eq!(workspaces[1].ignore, ["legacy", "sandbox/*.tf"]);
+    assert!(workspaces[0].policy.is_none());
+    assert!(parse_workspaces("").unwrap().is_empty());
+
+    assert!(parse_workspaces("[[workspace]]\nname = \"a\"\ndir = \"a\"\n[[workspace]]\nname = \"a\"\ndir = \"b\"\n").is_err());
+    assert!(parse_workspaces("[[workspace]]\nname = \"\"\ndir = \"a\"\n").is_err());
+    assert!(parse_workspaces("[[workspace]]\nname = \"a\"\n").is_err());
+    assert!(parse_workspaces("[[workspace]]\nname = \"a\"\ndir = \"a\"\npolicies = []\n").is_err());
+}
+
+#[test]
+fn test_load_workspaces_resolves_paths_and_ignores() {
+    let dir = common::create_test_dir_with_files(&[
+        ("tv.workspaces.toml", WORKSPACES),
+        ("app/main.tf", common::SIMPLE_MODULE_TF),
+        ("infra/networking/main.tf", common::SIMPLE_MODULE_TF),
+        ("infra/networking/legacy/old.tf", common::SIMPLE_MODULE_TF),
+        ("infra/networking/sandbox/try.tf", common::SIMPLE_MODULE_TF),
+    ]);
+    let root = std::path::absolute(dir.path()).unwrap();
+
+    // Found from a subdirectory, paths relative to the file
+    let workspaces = load_workspaces(&root.join("app")).unwrap();
+    let networking = named(&workspaces, "networking").unwrap();
+    assert_eq!(networking.dir, root.join("infra/networking"));
+    assert_eq!(networking.policy, Some(root.join("policies/networking.hcl")));
+    let unknown = named(&workspaces, "data").unwrap_err().to_string();
+    assert_eq!(unknown, "Unknown workspace 'data' (defined: app, networking)");
+
+    assert_eq!(at(&root.join("infra/networking")).map(|w| w.name), Some("networking".to_string()));
+    assert_eq!(at(&root.join("infra")), None);
+
+    // The ignore list applies to walks from the workspace's directory
+    let files = find_all_tf_files(&root.join("infra/networking")).unwrap();
+    assert_eq!(files, vec![root.join("infra/networking/main.tf")]);
+    assert_eq!(find_all_tf_files(&root.join("infra")).unwrap().len(), 3);
+
+    assert!(load_workspaces(&std::env::temp_dir().join("tv-no-workspaces")).is_err());
+}